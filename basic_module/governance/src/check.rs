@@ -0,0 +1,53 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::check_network_id;
+use crate::error::{Error, Insufficient};
+use crate::state::Metadata;
+use crate::transactions::{SignedTransaction, UserAction};
+
+pub fn check(signed_tx: &SignedTransaction) -> Result<(), Error> {
+    if !signed_tx.verify() {
+        return Err(Error::InvalidSignature(signed_tx.signature))
+    }
+    let tx = &signed_tx.tx;
+    if !check_network_id(tx.network_id) {
+        return Err(Error::InvalidNetworkId(tx.network_id))
+    }
+    let min_fee = Metadata::load().params.min_fee;
+    if tx.fee < min_fee {
+        return Err(Error::InsufficientFee(Insufficient {
+            required: min_fee,
+            actual: tx.fee,
+        }))
+    }
+    if let UserAction::Propose {
+        changes,
+        config_changes,
+        ..
+    } = &tx.action
+    {
+        if changes.is_empty() && config_changes.is_empty() {
+            return Err(Error::EmptyProposal)
+        }
+        if let Some(change) =
+            config_changes.iter().find(|change| serde_cbor::from_slice::<serde_cbor::Value>(&change.config).is_err())
+        {
+            return Err(Error::MalformedConfig(change.module.clone()))
+        }
+    }
+    Ok(())
+}
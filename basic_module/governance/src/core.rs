@@ -0,0 +1,45 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::error::Error;
+use crate::state::Proposal;
+use crate::transactions::Transaction;
+use coordinator::types::{ExecuteTransactionError, HeaderError, TransactionOutcome};
+use coordinator::Header;
+
+pub trait Abci {
+    fn open_block(&self, header: &Header) -> Result<(), HeaderError>;
+    fn execute_transactions(
+        &self,
+        transactions: Vec<Transaction>,
+    ) -> Result<Vec<TransactionOutcome>, ExecuteTransactionError>;
+    fn check_transaction(&self, transaction: &Transaction) -> Result<(), i64>;
+}
+
+pub trait GovernanceView {
+    fn get_proposal(&self, id: u64) -> Result<Proposal, Error>;
+    /// Returns the proposals still being voted on, from the oldest.
+    fn get_open_proposals(&self) -> Vec<Proposal>;
+}
+
+pub trait ModuleConfigProvider {
+    /// Returns the configs of the enacted proposals if the executing block closes a term.
+    fn updated_module_configs(&self) -> Vec<(String, Vec<u8>)>;
+}
+
+pub trait AdditionalTxCreator {
+    fn create(&self) -> Vec<Transaction>;
+}
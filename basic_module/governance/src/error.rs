@@ -0,0 +1,101 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use fkey::{Ed25519Public as Public, Signature};
+use std::fmt::{Display, Formatter, Result as FormatResult};
+
+#[derive(Debug)]
+/// Error indicating an expected value was not found.
+pub struct Mismatch<T> {
+    /// Value expected.
+    pub expected: T,
+    /// Value found.
+    pub found: T,
+}
+
+impl<T: Display> Display for Mismatch<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
+        write!(f, "Expected {}, found {}", self.expected, self.found)
+    }
+}
+
+#[derive(Debug)]
+pub struct Insufficient<T> {
+    /// Value to have at least
+    pub required: T,
+    /// Value found
+    pub actual: T,
+}
+
+impl<T: Display> Display for Insufficient<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
+        write!(f, "Required at least {}, found {}", self.required, self.actual)
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    InvalidSignature(Signature),
+    InvalidNetworkId(crate::types::NetworkId),
+    InsufficientFee(Insufficient<u64>),
+    InsufficientBalance(Insufficient<u64>),
+    InvalidSeq(Mismatch<u64>),
+    /// Only the stakeholders can propose and vote.
+    NotStakeholder(Public),
+    EmptyProposal,
+    InvalidParamChanges(String),
+    /// The config proposed for the module is not CBOR-encoded.
+    MalformedConfig(String),
+    NoSuchProposal(u64),
+    VotingClosed(u64),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
+        match self {
+            Error::InvalidSignature(sig) => write!(f, "Signature {:?} is invalid", sig),
+            Error::InvalidNetworkId(network_id) => write!(f, "{} is an invalid network id", network_id),
+            Error::InsufficientFee(insufficient) => write!(f, "Insufficient fee: {}", insufficient),
+            Error::InsufficientBalance(insufficient) => write!(f, "Insufficient balance: {}", insufficient),
+            Error::InvalidSeq(mismatch) => write!(f, "Seq of the transaction mismatched. {}", mismatch),
+            Error::NotStakeholder(public) => write!(f, "Public {:?} does not have any stake", public),
+            Error::EmptyProposal => write!(f, "The proposal changes neither a param nor a config"),
+            Error::InvalidParamChanges(reason) => write!(f, "Invalid param changes: {}", reason),
+            Error::MalformedConfig(module) => write!(f, "The config of {} is malformed", module),
+            Error::NoSuchProposal(id) => write!(f, "There is no proposal {}", id),
+            Error::VotingClosed(id) => write!(f, "The voting on the proposal {} is closed", id),
+        }
+    }
+}
+
+impl Error {
+    /// The code reported when the mem pool rejects a transaction.
+    pub fn code(&self) -> i64 {
+        match self {
+            Error::InvalidSignature(_) => -1,
+            Error::InvalidNetworkId(_) => -2,
+            Error::InsufficientFee(_) => -3,
+            Error::InsufficientBalance(_) => -4,
+            Error::InvalidSeq(_) => -5,
+            Error::NotStakeholder(_) => -6,
+            Error::EmptyProposal => -7,
+            Error::InvalidParamChanges(_) => -8,
+            Error::NoSuchProposal(_) => -9,
+            Error::VotingClosed(_) => -10,
+            Error::MalformedConfig(_) => -11,
+        }
+    }
+}
@@ -0,0 +1,163 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::error::{Error, Insufficient, Mismatch};
+use crate::state::{Metadata, OpenProposals, PendingModuleConfigs, Proposal, Tally};
+use crate::transactions::{AutoAction, UserAction, UserTransaction};
+use crate::types::{Choice, ConfigChange, ParamChange, ProposalStatus};
+use crate::{account_manager, account_viewer, params_manager, staking_viewer};
+use coordinator::types::TransactionOutcome;
+use fkey::Ed25519Public as Public;
+use ftypes::BlockNumber;
+
+pub fn apply_internal(
+    tx: UserTransaction,
+    sender_public: &Public,
+    block_number: BlockNumber,
+) -> Result<TransactionOutcome, Error> {
+    let UserTransaction {
+        seq,
+        fee,
+        action,
+        ..
+    } = tx;
+    let account_sequence = account_viewer().get_sequence(sender_public);
+    if account_sequence != seq {
+        return Err(Error::InvalidSeq(Mismatch {
+            expected: seq,
+            found: account_sequence,
+        }))
+    }
+
+    let account_manager = account_manager();
+    account_manager.sub_balance(sender_public, fee).map_err(|_err| {
+        Error::InsufficientBalance(Insufficient {
+            required: fee,
+            actual: account_viewer().get_balance(sender_public),
+        })
+    })?;
+    account_manager.increment_sequence(sender_public);
+
+    match action {
+        UserAction::Propose {
+            description,
+            changes,
+            config_changes,
+        } => propose(sender_public, description, changes, config_changes, block_number),
+        UserAction::Vote {
+            proposal_id,
+            choice,
+        } => vote(sender_public, proposal_id, choice, block_number),
+    }
+}
+
+fn check_stakeholder(public: &Public) -> Result<(), Error> {
+    match staking_viewer().get_stakes().get(public) {
+        Some(stake) if *stake > 0 => Ok(()),
+        _ => Err(Error::NotStakeholder(*public)),
+    }
+}
+
+fn propose(
+    proposer: &Public,
+    description: String,
+    changes: Vec<ParamChange>,
+    config_changes: Vec<ConfigChange>,
+    block_number: BlockNumber,
+) -> Result<TransactionOutcome, Error> {
+    check_stakeholder(proposer)?;
+    let mut metadata = Metadata::load();
+    let proposal = Proposal {
+        id: metadata.next_proposal_id,
+        proposer: *proposer,
+        description,
+        changes,
+        config_changes,
+        voting_ends_at: block_number + metadata.params.voting_period,
+        votes: Default::default(),
+        status: ProposalStatus::Voting,
+        tally: None,
+    };
+    // The params may change before the voting ends, so the changes are checked again when they are enacted.
+    params_manager().check_param_changes(&proposal.param_changes()).map_err(Error::InvalidParamChanges)?;
+
+    let mut open_proposals = OpenProposals::load();
+    open_proposals.insert(proposal.voting_ends_at, proposal.id);
+    open_proposals.save();
+    metadata.next_proposal_id += 1;
+    metadata.save();
+    proposal.save();
+    Ok(Default::default())
+}
+
+fn vote(
+    voter: &Public,
+    proposal_id: u64,
+    choice: Choice,
+    block_number: BlockNumber,
+) -> Result<TransactionOutcome, Error> {
+    check_stakeholder(voter)?;
+    let mut proposal = Proposal::load(proposal_id).ok_or(Error::NoSuchProposal(proposal_id))?;
+    if proposal.status != ProposalStatus::Voting || proposal.voting_ends_at < block_number {
+        return Err(Error::VotingClosed(proposal_id))
+    }
+    proposal.votes.insert(*voter, choice);
+    proposal.save();
+    Ok(Default::default())
+}
+
+pub fn execute_auto_action(action: AutoAction, current_block_number: BlockNumber) -> Result<TransactionOutcome, Error> {
+    match action {
+        AutoAction::CloseVotings => {
+            close_votings(current_block_number);
+            Ok(Default::default())
+        }
+    }
+}
+
+/// Decides the proposals whose votings end in the block, and enacts the passed ones in the order they were proposed.
+/// The enacted changes start a new era from the next term, when the staking module hands the new consensus params
+/// to the chain through its `UpdateChain` service.
+fn close_votings(block_number: BlockNumber) {
+    let mut open_proposals = OpenProposals::load();
+    let closed = open_proposals.take_closed(block_number);
+    if closed.is_empty() {
+        return
+    }
+    open_proposals.save();
+
+    let params = Metadata::load().params;
+    let stakes = staking_viewer().get_stakes();
+    let mut pending_configs = PendingModuleConfigs::load();
+    for id in closed {
+        let mut proposal = Proposal::load(id).expect("An open proposal must exist");
+        let tally = Tally::count(&proposal.votes, &stakes);
+        proposal.status = if tally.passes(&params) {
+            match params_manager().enact_param_changes(&proposal.param_changes()) {
+                Ok(()) => {
+                    pending_configs.extend(&proposal.config_changes);
+                    ProposalStatus::Enacted
+                }
+                Err(reason) => ProposalStatus::Failed(reason),
+            }
+        } else {
+            ProposalStatus::Rejected
+        };
+        proposal.tally = Some(tally);
+        proposal.save();
+    }
+    pending_configs.save();
+}
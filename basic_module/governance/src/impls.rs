@@ -0,0 +1,98 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::check::check;
+use crate::core::{Abci, AdditionalTxCreator, GovernanceView, ModuleConfigProvider};
+use crate::error::Error;
+use crate::execute::{apply_internal, execute_auto_action};
+use crate::staking_viewer;
+use crate::state::{OpenProposals, PendingModuleConfigs, Proposal};
+use crate::transactions::{create_close_block_transactions, SignedTransaction, Transaction};
+use coordinator::types::{ExecuteTransactionError, HeaderError, TransactionOutcome};
+use coordinator::Header;
+use std::cell::RefCell;
+
+struct ABCIHandle {
+    executing_block_header: RefCell<Header>,
+}
+
+impl AdditionalTxCreator for ABCIHandle {
+    fn create(&self) -> Vec<Transaction> {
+        create_close_block_transactions()
+    }
+}
+
+impl Abci for ABCIHandle {
+    fn open_block(&self, header: &Header) -> Result<(), HeaderError> {
+        *self.executing_block_header.borrow_mut() = header.clone();
+        Ok(())
+    }
+
+    fn execute_transactions(
+        &self,
+        transactions: Vec<Transaction>,
+    ) -> Result<Vec<TransactionOutcome>, ExecuteTransactionError> {
+        let block_number = self.executing_block_header.borrow().number();
+        let results: Result<Vec<_>, _> = transactions
+            .into_iter()
+            .map(|tx| match tx {
+                Transaction::User(signed_tx) => check(&signed_tx).and({
+                    let SignedTransaction {
+                        tx,
+                        signer_public,
+                        ..
+                    } = signed_tx;
+                    apply_internal(tx, &signer_public, block_number)
+                }),
+                Transaction::Auto(auto_action) => execute_auto_action(auto_action, block_number),
+            })
+            .collect();
+        // TODO: handle errors
+        results.map_err(|_| ())
+    }
+
+    fn check_transaction(&self, transaction: &Transaction) -> Result<(), i64> {
+        match transaction {
+            Transaction::User(signed_tx) => check(signed_tx).map_err(|err| err.code()),
+            Transaction::Auto(_) => Ok(()),
+        }
+    }
+}
+
+impl ModuleConfigProvider for ABCIHandle {
+    fn updated_module_configs(&self) -> Vec<(String, Vec<u8>)> {
+        let block_number = self.executing_block_header.borrow().number();
+        if staking_viewer().last_term_finished_block_num() != block_number {
+            return Vec::new()
+        }
+        let mut pending_configs = PendingModuleConfigs::load();
+        let configs = pending_configs.take();
+        pending_configs.save();
+        configs
+    }
+}
+
+struct GovernanceViewer {}
+
+impl GovernanceView for GovernanceViewer {
+    fn get_proposal(&self, id: u64) -> Result<Proposal, Error> {
+        Proposal::load(id).ok_or(Error::NoSuchProposal(id))
+    }
+
+    fn get_open_proposals(&self) -> Vec<Proposal> {
+        OpenProposals::load().ids().filter_map(Proposal::load).collect()
+    }
+}
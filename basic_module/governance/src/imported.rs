@@ -0,0 +1,40 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use fkey::Ed25519Public as Public;
+use std::collections::HashMap;
+
+pub trait AccountManager {
+    fn sub_balance(&self, public: &Public, val: u64) -> Result<(), String>;
+    fn increment_sequence(&self, public: &Public);
+}
+
+pub trait AccountView {
+    fn get_balance(&self, public: &Public) -> u64;
+    fn get_sequence(&self, public: &Public) -> u64;
+}
+
+pub trait StakingView {
+    /// The stakes of the stakeholders, including what they delegated.
+    fn get_stakes(&self) -> HashMap<Public, u64>;
+    fn last_term_finished_block_num(&self) -> u64;
+}
+
+/// Implemented by the staking module, which owns the params.
+pub trait ParamsManager {
+    fn check_param_changes(&self, changes: &[(String, u64)]) -> Result<(), String>;
+    fn enact_param_changes(&self, changes: &[(String, u64)]) -> Result<(), String>;
+}
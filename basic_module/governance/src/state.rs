@@ -0,0 +1,347 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::types::{Choice, ConfigChange, ParamChange, ProposalStatus};
+use crate::{deserialize, serialize, substorage};
+use fkey::Ed25519Public as Public;
+use ftypes::BlockNumber;
+use serde::{de::DeserializeOwned, ser::Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+const PROPOSAL_PREFIX: [u8; 1] = [0x1];
+
+const METADATA_KEY: &[u8; 8] = b"Metadata";
+const OPEN_PROPOSALS_KEY: &[u8; 13] = b"OpenProposals";
+const PENDING_MODULE_CONFIGS_KEY: &[u8; 20] = b"PendingModuleConfigs";
+
+const BASIS_POINTS: u128 = 10_000;
+
+fn load_with_key<T: DeserializeOwned>(key: &[u8]) -> Option<T> {
+    substorage().get(key).map(deserialize)
+}
+
+fn write_with_key<T: Serialize>(key: &[u8], data: T) {
+    substorage().set(key, serialize(data))
+}
+
+// The initialization process should be executed after the staking module is initialized,
+// because the proposals are checked against the params of the staking module.
+#[allow(dead_code)]
+pub fn init_governance(params: GovernanceParams) -> Result<(), String> {
+    params.verify()?;
+    Metadata {
+        params,
+        next_proposal_id: 0,
+    }
+    .save();
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct GovernanceParams {
+    /// The number of blocks after the proposal in which the votes are taken.
+    pub voting_period: u64,
+    /// The share of all the stakes that must vote, including the abstentions, for a proposal to pass.
+    /// In basis points.
+    pub quorum: u16,
+    /// The share of the yes votes among the yes and no votes that a proposal must exceed to pass.
+    /// In basis points.
+    pub threshold: u16,
+    /// The fee of a proposal or a vote.
+    pub min_fee: u64,
+}
+
+impl GovernanceParams {
+    pub fn verify(&self) -> Result<(), String> {
+        if self.voting_period == 0 {
+            return Err("You should set the voting period".to_string())
+        }
+        if u128::from(self.quorum) > BASIS_POINTS {
+            return Err(format!("The quorum({}) should be at most {} basis points", self.quorum, BASIS_POINTS))
+        }
+        if u128::from(self.threshold) >= BASIS_POINTS {
+            return Err(format!("The threshold({}) should be less than {} basis points", self.threshold, BASIS_POINTS))
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Metadata {
+    pub params: GovernanceParams,
+    pub next_proposal_id: u64,
+}
+
+impl Metadata {
+    pub fn load() -> Self {
+        load_with_key(METADATA_KEY).expect("Params must be exist")
+    }
+
+    pub fn save(self) {
+        write_with_key(METADATA_KEY, self)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Proposal {
+    pub id: u64,
+    pub proposer: Public,
+    pub description: String,
+    pub changes: Vec<ParamChange>,
+    #[serde(default)]
+    pub config_changes: Vec<ConfigChange>,
+    /// The last block in which a vote is taken.
+    pub voting_ends_at: BlockNumber,
+    /// The latest choice of each voter.
+    pub votes: BTreeMap<Public, Choice>,
+    pub status: ProposalStatus,
+    /// The stakes counted when the voting ended.
+    pub tally: Option<Tally>,
+}
+
+impl Proposal {
+    fn key(id: u64) -> Vec<u8> {
+        [&PROPOSAL_PREFIX[..], &id.to_be_bytes()].concat()
+    }
+
+    pub fn load(id: u64) -> Option<Self> {
+        load_with_key(&Self::key(id))
+    }
+
+    pub fn save(self) {
+        write_with_key(&Self::key(self.id), self)
+    }
+
+    pub fn param_changes(&self) -> Vec<(String, u64)> {
+        self.changes.iter().map(|change| (change.name.clone(), change.value)).collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tally {
+    pub yes: u64,
+    pub no: u64,
+    pub abstain: u64,
+    pub total_stake: u64,
+}
+
+impl Tally {
+    /// Counts the votes by the stakes the voters have when the voting ends,
+    /// so a stake moved to another voter during the voting is counted once, for the voter holding it.
+    pub fn count(votes: &BTreeMap<Public, Choice>, stakes: &HashMap<Public, u64>) -> Self {
+        let mut tally = Tally {
+            total_stake: stakes.values().sum(),
+            ..Default::default()
+        };
+        for (voter, choice) in votes {
+            let stake = stakes.get(voter).copied().unwrap_or_default();
+            match choice {
+                Choice::Yes => tally.yes += stake,
+                Choice::No => tally.no += stake,
+                Choice::Abstain => tally.abstain += stake,
+            }
+        }
+        tally
+    }
+
+    pub fn passes(&self, params: &GovernanceParams) -> bool {
+        let (yes, no, abstain) = (u128::from(self.yes), u128::from(self.no), u128::from(self.abstain));
+        let quorum_reached =
+            (yes + no + abstain) * BASIS_POINTS >= u128::from(self.total_stake) * u128::from(params.quorum);
+        quorum_reached && yes * BASIS_POINTS > (yes + no) * u128::from(params.threshold)
+    }
+}
+
+/// The proposals being voted on, ordered by the end of their votings and then by their ids.
+#[derive(Default)]
+pub struct OpenProposals(BTreeSet<(BlockNumber, u64)>);
+
+impl OpenProposals {
+    pub fn load() -> Self {
+        OpenProposals(load_with_key(OPEN_PROPOSALS_KEY).unwrap_or_default())
+    }
+
+    pub fn save(self) {
+        write_with_key(OPEN_PROPOSALS_KEY, self.0)
+    }
+
+    pub fn insert(&mut self, voting_ends_at: BlockNumber, id: u64) {
+        self.0.insert((voting_ends_at, id));
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = u64> + '_ {
+        self.0.iter().map(|(_, id)| *id)
+    }
+
+    /// Removes the proposals whose votings end by the block, returning their ids in the order to decide them.
+    pub fn take_closed(&mut self, block_number: BlockNumber) -> Vec<u64> {
+        let open = self.0.split_off(&(block_number + 1, 0));
+        std::mem::replace(&mut self.0, open).into_iter().map(|(_, id)| id).collect()
+    }
+}
+
+/// The configs of the enacted proposals, waiting for the current term to close.
+#[derive(Default)]
+pub struct PendingModuleConfigs(BTreeMap<String, Vec<u8>>);
+
+impl PendingModuleConfigs {
+    pub fn load() -> Self {
+        PendingModuleConfigs(load_with_key(PENDING_MODULE_CONFIGS_KEY).unwrap_or_default())
+    }
+
+    pub fn save(self) {
+        write_with_key(PENDING_MODULE_CONFIGS_KEY, self.0)
+    }
+
+    /// Adds the configs of an enacted proposal. A module changed twice in a term takes the later config.
+    pub fn extend(&mut self, changes: &[ConfigChange]) {
+        self.0.extend(changes.iter().map(|change| (change.module.clone(), change.config.clone())));
+    }
+
+    /// Takes all the configs, in the order of the module names.
+    pub fn take(&mut self) -> Vec<(String, Vec<u8>)> {
+        std::mem::take(&mut self.0).into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(quorum: u16, threshold: u16) -> GovernanceParams {
+        GovernanceParams {
+            voting_period: 10,
+            quorum,
+            threshold,
+            min_fee: 0,
+        }
+    }
+
+    fn tally(yes: u64, no: u64, abstain: u64, total_stake: u64) -> Tally {
+        Tally {
+            yes,
+            no,
+            abstain,
+            total_stake,
+        }
+    }
+
+    #[test]
+    fn votes_are_counted_by_the_stakes_of_the_voters() {
+        let (yes_voter, no_voter, abstainer, unstaked_voter, silent) =
+            (Public::random(), Public::random(), Public::random(), Public::random(), Public::random());
+        let votes: BTreeMap<_, _> = vec![
+            (yes_voter, Choice::Yes),
+            (no_voter, Choice::No),
+            (abstainer, Choice::Abstain),
+            (unstaked_voter, Choice::Yes),
+        ]
+        .into_iter()
+        .collect();
+        let stakes: HashMap<_, _> =
+            vec![(yes_voter, 30), (no_voter, 20), (abstainer, 10), (silent, 40)].into_iter().collect();
+
+        assert_eq!(tally(30, 20, 10, 100), Tally::count(&votes, &stakes));
+    }
+
+    #[test]
+    fn a_proposal_passes_at_the_quorum() {
+        let params = params(5_000, 5_000);
+        assert!(tally(30, 10, 10, 100).passes(&params));
+        assert!(!tally(30, 10, 9, 100).passes(&params));
+    }
+
+    #[test]
+    fn a_proposal_must_exceed_the_threshold() {
+        let params = params(5_000, 5_000);
+        assert!(!tally(30, 30, 0, 100).passes(&params));
+        assert!(tally(31, 29, 0, 100).passes(&params));
+    }
+
+    #[test]
+    fn abstentions_count_toward_the_quorum_only() {
+        let params = params(5_000, 0);
+        assert!(!tally(0, 0, 100, 100).passes(&params));
+        assert!(tally(1, 0, 99, 100).passes(&params));
+    }
+
+    #[test]
+    fn a_proposal_without_votes_does_not_pass() {
+        assert!(!tally(0, 0, 0, 100).passes(&params(0, 0)));
+        assert!(!tally(0, 0, 0, 0).passes(&params(0, 0)));
+    }
+
+    #[test]
+    fn the_whole_stake_is_counted_without_overflow() {
+        let max = u64::max_value();
+        assert!(tally(max, 0, 0, max).passes(&params(10_000, 9_999)));
+        assert!(!tally(max / 2, max / 2, 0, max).passes(&params(10_000, 5_000)));
+    }
+
+    #[test]
+    fn closed_proposals_are_taken_in_the_order_of_their_ends_and_ids() {
+        let mut open = OpenProposals::default();
+        open.insert(10, 3);
+        open.insert(5, 7);
+        open.insert(10, 1);
+        open.insert(11, 2);
+
+        assert_eq!(Vec::<u64>::new(), open.take_closed(4));
+        assert_eq!(vec![7, 1, 3], open.take_closed(10));
+        assert_eq!(vec![2], open.ids().collect::<Vec<_>>());
+        assert_eq!(Vec::<u64>::new(), open.take_closed(10));
+        assert_eq!(vec![2], open.take_closed(11));
+        assert_eq!(0, open.ids().count());
+    }
+
+    fn config_change(module: &str, config: &[u8]) -> ConfigChange {
+        ConfigChange {
+            module: module.to_owned(),
+            config: config.to_vec(),
+        }
+    }
+
+    #[test]
+    fn the_later_config_of_a_module_is_pending() {
+        let mut pending = PendingModuleConfigs::default();
+        pending.extend(&[config_change("token", &[1]), config_change("account", &[2])]);
+        pending.extend(&[config_change("token", &[3])]);
+
+        assert_eq!(vec![("account".to_owned(), vec![2]), ("token".to_owned(), vec![3])], pending.take());
+        assert_eq!(Vec::<(String, Vec<u8>)>::new(), pending.take());
+    }
+
+    #[test]
+    fn params_require_a_voting_period() {
+        let params = GovernanceParams {
+            voting_period: 0,
+            ..params(5_000, 5_000)
+        };
+        assert!(params.verify().is_err());
+    }
+
+    #[test]
+    fn the_quorum_can_be_the_whole_stake() {
+        assert_eq!(Ok(()), params(10_000, 5_000).verify());
+        assert!(params(10_001, 5_000).verify().is_err());
+    }
+
+    #[test]
+    fn the_threshold_must_be_less_than_the_whole() {
+        assert_eq!(Ok(()), params(5_000, 9_999).verify());
+        assert!(params(5_000, 10_000).verify().is_err());
+    }
+}
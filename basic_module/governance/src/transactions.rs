@@ -0,0 +1,77 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::types::{Choice, ConfigChange, NetworkId, ParamChange};
+use ccrypto::blake256;
+use fkey::{verify, Ed25519Public as Public, Signature};
+use primitives::H256;
+
+pub enum Transaction {
+    #[allow(dead_code)]
+    User(SignedTransaction),
+    Auto(AutoAction),
+}
+
+pub struct SignedTransaction {
+    pub signature: Signature,
+    pub signer_public: Public,
+    pub tx: UserTransaction,
+}
+
+impl SignedTransaction {
+    pub fn verify(&self) -> bool {
+        let message = self.tx.hash();
+        verify(&self.signature, message.as_ref(), &self.signer_public)
+    }
+}
+
+#[derive(Serialize)]
+pub struct UserTransaction {
+    pub seq: u64,
+    pub fee: u64,
+    pub network_id: NetworkId,
+    pub action: UserAction,
+}
+
+impl UserTransaction {
+    pub fn hash(&self) -> H256 {
+        let serialized = serde_cbor::to_vec(&self).unwrap();
+        blake256(serialized)
+    }
+}
+
+#[derive(Serialize)]
+pub enum UserAction {
+    Propose {
+        description: String,
+        changes: Vec<ParamChange>,
+        config_changes: Vec<ConfigChange>,
+    },
+    /// Votes on an open proposal. A later vote of the same voter replaces the earlier one.
+    Vote {
+        proposal_id: u64,
+        choice: Choice,
+    },
+}
+
+pub enum AutoAction {
+    /// Decides the proposals whose votings end in the block.
+    CloseVotings,
+}
+
+pub fn create_close_block_transactions() -> Vec<Transaction> {
+    vec![Transaction::Auto(AutoAction::CloseVotings)]
+}
@@ -0,0 +1,68 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{fmt, str};
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
+pub struct NetworkId([u8; 2]);
+
+impl fmt::Display for NetworkId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let s = str::from_utf8(&self.0).expect("network_id a valid utf8 string");
+        write!(f, "{}", s)
+    }
+}
+
+impl Default for NetworkId {
+    fn default() -> Self {
+        NetworkId([116, 99])
+    }
+}
+
+/// A change of a param, named as the staking module names it, e.g. `min_fee` or `consensus.term_seconds`.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub struct ParamChange {
+    pub name: String,
+    pub value: u64,
+}
+
+/// A new config of a module, delivered to it through its `update-config` service when the term closes.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub struct ConfigChange {
+    pub module: String,
+    /// The CBOR-encoded config, which must conform to the config schema of the module.
+    pub config: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Choice {
+    Yes,
+    No,
+    /// Counts toward the quorum without taking a side.
+    Abstain,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub enum ProposalStatus {
+    Voting,
+    /// Passed and enacted. The changes take effect from the next term, in a new era,
+    /// and the new configs are delivered to the modules when the current term closes.
+    Enacted,
+    /// Turned down by the voters, or didn't reach the quorum.
+    Rejected,
+    /// Passed, but the changes didn't apply to the params at the end of the voting.
+    Failed(String),
+}
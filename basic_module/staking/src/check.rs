@@ -14,22 +14,96 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::check_network_id;
+use crate::error::Insufficient;
+use crate::state::{Metadata, SponsorQuotas};
 use crate::syntax_error::Error;
-use crate::transactions::{SignedTransaction, UserTransaction};
+use crate::transactions::{SignedTransaction, UserAction, UserTransaction};
+use crate::{account_viewer, check_network_id, machine_min_fee};
 
 pub fn check(signed_tx: &SignedTransaction) -> Result<(), Error> {
     if !signed_tx.verify() {
         Err(Error::InvalidSignature(signed_tx.signature))
     } else {
+        check_network_identity(signed_tx)?;
+        check_validator_key_change(signed_tx)?;
+        check_fee_payer(signed_tx)?;
         check_inner(&signed_tx.tx)
     }
 }
 
+/// Checks the fee against the minimum fee of this node, which only the mem pool requires,
+/// since the blocks are checked against the consensus-defined minimum fee alone.
+pub fn check_machine_min_fee(tx: &UserTransaction) -> Result<(), Error> {
+    let min_fee = machine_min_fee();
+    if tx.fee < min_fee {
+        return Err(Error::InsufficientFee(Insufficient {
+            required: min_fee,
+            actual: tx.fee,
+        }))
+    }
+    Ok(())
+}
+
+/// A sponsored fee must be signed for by the sponsor, and be within its quota and balance,
+/// so that the mem pool doesn't keep the transactions the sponsor won't pay for.
+fn check_fee_payer(signed_tx: &SignedTransaction) -> Result<(), Error> {
+    if !signed_tx.verify_fee_payer() {
+        return Err(Error::InvalidFeePayer)
+    }
+    if let Some(fee_payer) = &signed_tx.tx.fee_payer {
+        let current_term_id = Metadata::load().current_term_id;
+        let affordable =
+            SponsorQuotas::load().remaining(fee_payer, current_term_id).min(account_viewer().get_balance(fee_payer));
+        if affordable < signed_tx.tx.fee {
+            return Err(Error::InsufficientSponsorship(Insufficient {
+                required: signed_tx.tx.fee,
+                actual: affordable,
+            }))
+        }
+    }
+    Ok(())
+}
+
+/// A validator can register only the network identity signed with its own key.
+fn check_network_identity(signed_tx: &SignedTransaction) -> Result<(), Error> {
+    if let UserAction::RegisterNetworkIdentity {
+        identity,
+    } = &signed_tx.tx.action
+    {
+        if identity.validator != signed_tx.signer_public || !identity.verify() {
+            return Err(Error::InvalidNetworkIdentity)
+        }
+    }
+    Ok(())
+}
+
+/// Only the validator itself can give up its key, and only for a key whose holder signed the change for this chain.
+fn check_validator_key_change(signed_tx: &SignedTransaction) -> Result<(), Error> {
+    if let UserAction::ChangeValidatorKey {
+        change,
+    } = &signed_tx.tx.action
+    {
+        if change.current != signed_tx.signer_public
+            || change.current == change.next
+            || signed_tx.tx.network_id != change.network_id
+            || !change.verify()
+        {
+            return Err(Error::InvalidValidatorKeyChange)
+        }
+    }
+    Ok(())
+}
+
 fn check_inner(tx: &UserTransaction) -> Result<(), Error> {
     if !check_network_id(tx.network_id) {
-        Err(Error::InvalidNetworkId(tx.network_id))
-    } else {
-        Ok(())
+        return Err(Error::InvalidNetworkId(tx.network_id))
+    }
+    let min_fee = tx.min_fee();
+    if tx.fee < min_fee {
+        return Err(Error::InsufficientFee(Insufficient {
+            required: min_fee,
+            actual: tx.fee,
+        }))
     }
+    Ok(())
 }
@@ -14,12 +14,14 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::state::{Banned, Params};
+use crate::state::{Banned, FeeRecord, Params};
 use crate::transactions::Transaction;
 use crate::types::Validator;
+use coordinator::replay::ReplayTag;
 use coordinator::types::{ExecuteTransactionError, HeaderError, TransactionOutcome, VerifiedCrime};
 use coordinator::Header;
 use fkey::Ed25519Public as Public;
+use ftypes::{BlockNumber, ConsensusParams, NetworkIdentity};
 use std::collections::HashMap;
 
 pub trait Abci {
@@ -29,6 +31,13 @@ pub trait Abci {
         transactions: Vec<Transaction>,
     ) -> Result<Vec<TransactionOutcome>, ExecuteTransactionError>;
     fn check_transaction(&self, transaction: &Transaction) -> Result<(), i64>;
+    /// The replay tag of a user transaction with an expiring nonce, which the mem pool drops once it expires.
+    fn replay_tag(&self, transaction: &Transaction) -> Option<ReplayTag>;
+}
+
+pub trait UpdateChain {
+    /// Returns the consensus params for the next block if the executing block began a new era.
+    fn update_chain(&self) -> Option<ConsensusParams>;
 }
 
 pub trait StakingView {
@@ -40,8 +49,21 @@ pub trait StakingView {
     fn last_term_finished_block_num(&self) -> u64;
     fn era(&self) -> u64;
     fn get_banned_validators(&self) -> Banned;
+    /// Returns the network identities registered by the current validators.
+    fn get_network_identities(&self) -> Vec<NetworkIdentity>;
+    /// Returns where the fees of the block went, if it charged any.
+    fn get_fee_record(&self, block_number: BlockNumber) -> Option<FeeRecord>;
+}
+
+/// Changes the params as the governance decided. A change names a field of `Params`, see `Params::with_change`.
+pub trait ParamsManager {
+    /// Checks that the changes apply to the current params, so that a proposal that can never pass is refused.
+    fn check_param_changes(&self, changes: &[(String, u64)]) -> Result<(), String>;
+    /// Applies the changes to the params of the next term, which start a new era.
+    fn enact_param_changes(&self, changes: &[(String, u64)]) -> Result<(), String>;
 }
 
 pub trait AdditionalTxCreator {
-    fn create(&self) -> Vec<Transaction>;
+    /// `body_size` is the size of the transactions of every module in the executing block, which moves the base fee.
+    fn create(&self, body_size: usize) -> Vec<Transaction>;
 }
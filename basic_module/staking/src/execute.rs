@@ -17,25 +17,42 @@
 use crate::error::{Insufficient, Mismatch};
 use crate::runtime_error::Error;
 use crate::state::{
-    get_stakes, Banned, Candidates, CurrentValidators, Delegation, Jail, Metadata, NextValidators, Params,
-    StakeAccount, Stakeholders,
+    get_stakes, Banned, BaseFee, BlockFees, Candidates, CurrentValidators, Delegation, FeeRecord, FeeSchedule,
+    FeeSchedules, Jail, Metadata, NetworkIdentities, NextValidators, Params, SponsorQuotas, StakeAccount, Stakeholders,
+    ValidatorKeyChanges,
 };
 use crate::transactions::{AutoAction, UserAction, UserTransaction};
 use crate::types::{Approval, ReleaseResult, StakeQuantity, Tiebreaker};
 // use crate::{account_manager, account_viewer, substorage};
-use crate::{account_manager, account_viewer};
+use crate::{account_manager, account_viewer, seen_nonces, substorage};
+use coordinator::replay::ReplayTag;
 use coordinator::types::TransactionOutcome;
 use fkey::Ed25519Public as Public;
+use ftypes::{BlockNumber, NetworkIdentity, ValidatorKeyChange};
 use primitives::Bytes;
 
-fn check_before_fee_imposition(sender_public: &Public, fee: u64, seq: u64, min_fee: u64) -> Result<(), Error> {
-    let account_sequence = account_viewer().get_sequence(sender_public);
-    if account_sequence != seq {
-        Err(Error::InvalidSeq(Mismatch {
-            expected: seq,
-            found: account_sequence,
-        }))
-    } else if fee < min_fee {
+/// A transaction with a replay tag is checked against the seen nonces instead of the sequence of the sender.
+fn check_before_fee_imposition(
+    sender_public: &Public,
+    fee: u64,
+    seq: u64,
+    replay_tag: Option<&ReplayTag>,
+    block_number: BlockNumber,
+    min_fee: u64,
+) -> Result<(), Error> {
+    match replay_tag {
+        Some(tag) => seen_nonces().check(&*substorage(), tag, block_number).map_err(Error::Replayed)?,
+        None => {
+            let account_sequence = account_viewer().get_sequence(sender_public);
+            if account_sequence != seq {
+                return Err(Error::InvalidSeq(Mismatch {
+                    expected: seq,
+                    found: account_sequence,
+                }))
+            }
+        }
+    }
+    if fee < min_fee {
         Err(Error::InsufficientFee(Insufficient {
             required: min_fee,
             actual: fee,
@@ -45,11 +62,15 @@ fn check_before_fee_imposition(sender_public: &Public, fee: u64, seq: u64, min_f
     }
 }
 
+/// The fee is charged to `fee_payer`, which differs from the sender if the fee is sponsored.
 pub fn apply_internal(
     tx: UserTransaction,
     sender_public: &Public,
+    fee_payer: &Public,
     tiebreaker: Tiebreaker,
 ) -> Result<TransactionOutcome, Error> {
+    let min_fee = tx.min_fee();
+    let replay_tag = tx.replay_tag(sender_public);
     let UserTransaction {
         action,
         fee,
@@ -57,21 +78,35 @@ pub fn apply_internal(
         ..
     } = tx;
 
-    let min_fee = action.min_fee();
-    check_before_fee_imposition(sender_public, fee, seq, min_fee)?;
+    let block_number = tiebreaker.nominated_at_block_number;
+    check_before_fee_imposition(sender_public, fee, seq, replay_tag.as_ref(), block_number, min_fee)?;
 
     // Does not impose fee and increase sequence for a failed transaction
     // let mut substorage = substorage();
     // substorage.create_checkpoint();
 
+    let mut sponsor_quotas = None;
+    if fee_payer != sender_public {
+        let mut quotas = SponsorQuotas::load();
+        quotas.spend(fee_payer, fee, Metadata::load().current_term_id)?;
+        sponsor_quotas = Some(quotas);
+    }
+
     let account_manager = account_manager();
-    account_manager.sub_balance(sender_public, fee).map_err(|_err| {
+    account_manager.sub_balance(fee_payer, fee).map_err(|_err| {
         Error::InsufficientBalance(Insufficient {
             required: fee,
-            actual: account_viewer().get_balance(sender_public),
+            actual: account_viewer().get_balance(fee_payer),
         })
     })?;
-    account_manager.increment_sequence(&sender_public);
+    BlockFees::collect(fee);
+    if let Some(quotas) = sponsor_quotas {
+        quotas.save();
+    }
+    match replay_tag {
+        Some(tag) => seen_nonces().record(&mut *substorage(), &tag),
+        None => account_manager.increment_sequence(&sender_public),
+    }
 
     let result = execute_user_action(&sender_public, action, tiebreaker);
     // match result {
@@ -117,6 +152,21 @@ fn execute_user_action(
         UserAction::ReportDoubleVote {
             ..
         } => unimplemented!(),
+        UserAction::ChangeFeeSchedule {
+            metadata_seq,
+            era,
+            schedule,
+            approvals,
+        } => change_fee_schedule(metadata_seq, era, schedule, approvals),
+        UserAction::RegisterNetworkIdentity {
+            identity,
+        } => register_network_identity(sender_public, identity),
+        UserAction::ChangeValidatorKey {
+            change,
+        } => change_validator_key(sender_public, change),
+        UserAction::SetSponsorQuota {
+            per_term,
+        } => set_sponsor_quota(sender_public, per_term),
     }
 }
 
@@ -135,6 +185,7 @@ pub fn execute_auto_action(action: AutoAction, current_block_number: u64) -> Res
             close_term(next_validators, &inactive_validators)?;
             release_jailed_prisoners(&released_addresses)?;
             jail(&inactive_validators, custody_until, kick_at);
+            change_validator_keys()?;
             increase_term_id(current_block_number);
             Ok(Default::default())
         }
@@ -151,7 +202,53 @@ pub fn execute_auto_action(action: AutoAction, current_block_number: u64) -> Res
             NextValidators::from(validators).save();
             Ok(Default::default())
         }
+        AutoAction::DistributeFees {
+            proposer,
+        } => {
+            distribute_fees(proposer, current_block_number);
+            Ok(Default::default())
+        }
+        AutoAction::AdjustBaseFee {
+            body_size,
+        } => {
+            adjust_base_fee(body_size);
+            Ok(Default::default())
+        }
+    }
+}
+
+/// Credits the proposer and the treasury with their shares of the fees charged in the block, and burns the rest.
+fn distribute_fees(proposer: Public, block_number: BlockNumber) {
+    let collected = BlockFees::take();
+    if collected == 0 {
+        return
     }
+    let Params {
+        fee_split,
+        treasury,
+        ..
+    } = Metadata::load().term_params;
+    let record = FeeRecord::new(block_number, collected, proposer, treasury, &fee_split);
+    assert!(record.is_balanced(), "The fees of the block {} are not balanced: {:?}", block_number, record);
+
+    let account_manager = account_manager();
+    account_manager.add_balance(&record.proposer, record.to_proposer);
+    account_manager.add_balance(&record.treasury, record.to_treasury);
+    record.save();
+}
+
+/// Moves the base fee by the size of the block body. It is dropped while the adjustment is disabled.
+fn adjust_base_fee(body_size: u64) {
+    let Params {
+        base_fee_adjustment,
+        consensus_params,
+        ..
+    } = Metadata::load().term_params;
+    let base_fee = match base_fee_adjustment {
+        Some(adjustment) => adjustment.next_base_fee(BaseFee::load(), body_size, consensus_params.max_body_size()),
+        None => 0,
+    };
+    BaseFee::save(base_fee)
 }
 
 fn transfer_ccs(from: &Public, to: &Public, quantity: StakeQuantity) -> Result<TransactionOutcome, Error> {
@@ -275,12 +372,81 @@ pub fn self_nominate(
     Ok(Default::default())
 }
 
+/// The identity is checked to be signed by the sender before the transaction is executed.
+fn register_network_identity(sender_public: &Public, identity: NetworkIdentity) -> Result<TransactionOutcome, Error> {
+    if Candidates::load().get_candidate(sender_public).is_none() {
+        return Err(Error::NotCandidate(*sender_public))
+    }
+
+    let mut identities = NetworkIdentities::load();
+    identities.register(identity)?;
+    identities.save();
+
+    Ok(Default::default())
+}
+
+fn set_sponsor_quota(sender_public: &Public, per_term: u64) -> Result<TransactionOutcome, Error> {
+    let mut quotas = SponsorQuotas::load();
+    quotas.set(*sender_public, per_term);
+    quotas.save();
+    Ok(Default::default())
+}
+
+/// The change is checked to be requested by the sender and signed by the next key before the transaction is executed.
+fn change_validator_key(sender_public: &Public, change: ValidatorKeyChange) -> Result<TransactionOutcome, Error> {
+    let candidates = Candidates::load();
+    if candidates.get_candidate(sender_public).is_none() {
+        return Err(Error::NotCandidate(*sender_public))
+    }
+    if is_validator_key_in_use(&candidates, &change.next) {
+        return Err(Error::ValidatorKeyInUse(change.next))
+    }
+
+    let mut changes = ValidatorKeyChanges::load();
+    changes.schedule(change.current, change.next)?;
+    changes.save();
+
+    Ok(Default::default())
+}
+
+fn is_validator_key_in_use(candidates: &Candidates, public: &Public) -> bool {
+    candidates.get_candidate(public).is_some()
+        || Jail::load().get_prisoner(public).is_some()
+        || Banned::load().is_banned(public)
+}
+
 pub fn change_params(metadata_seq: u64, params: Params, approvals: Vec<Approval>) -> Result<TransactionOutcome, Error> {
     // Update state first because the signature validation is more expensive.
     let mut metadata = Metadata::load();
     metadata.update_params(metadata_seq, params)?;
+    verify_approvals(&approvals)?;
+
+    metadata.save();
+    Ok(Default::default())
+}
+
+pub fn change_fee_schedule(
+    metadata_seq: u64,
+    era: u64,
+    schedule: FeeSchedule,
+    approvals: Vec<Approval>,
+) -> Result<TransactionOutcome, Error> {
+    let mut metadata = Metadata::load();
+    metadata.increase_seq(metadata_seq)?;
+    // The era of the accepted params is checked, not the term params' one,
+    // so that a schedule cannot be registered for an era that is about to take effect.
+    let mut fee_schedules = FeeSchedules::load();
+    fee_schedules.register(era, schedule, metadata.params.era)?;
+    verify_approvals(&approvals)?;
+
+    fee_schedules.save();
+    metadata.save();
+    Ok(Default::default())
+}
+
+/// Checks that the approvals are signed by the holders of more than half of the stakes.
+fn verify_approvals(approvals: &[Approval]) -> Result<(), Error> {
     let stakes = get_stakes();
-    // Approvals are verified
     let signed_stakes = approvals.iter().try_fold(0, |sum, approval| {
         let public = approval.signer_public;
         stakes.get(&public).map(|stake| sum + stake).ok_or_else(|| Error::SignatureOfInvalidAccount(public))
@@ -292,9 +458,7 @@ pub fn change_params(metadata_seq: u64, params: Params, approvals: Vec<Approval>
             actual: signed_stakes,
         }))
     }
-
-    metadata.save();
-    Ok(Default::default())
+    Ok(())
 }
 
 fn update_validators(validators: NextValidators) -> Result<TransactionOutcome, Error> {
@@ -363,6 +527,47 @@ fn revert_delegations(reverted_delegatees: &[Public]) -> Result<(), Error> {
     Ok(())
 }
 
+/// Applies the key changes scheduled during the closing term.
+/// A change is dropped if its candidate was jailed or expired, or if the next key was taken in the meantime.
+fn change_validator_keys() -> Result<(), Error> {
+    let mut changes = ValidatorKeyChanges::load();
+    let scheduled = changes.drain();
+    changes.save();
+    if scheduled.is_empty() {
+        return Ok(())
+    }
+
+    let mut candidates = Candidates::load();
+    let mut changed = Vec::new();
+    for (current, next) in scheduled {
+        if !is_validator_key_in_use(&candidates, &next) && candidates.change_key(&current, next) {
+            changed.push((current, next));
+        }
+    }
+    candidates.save();
+
+    let stakeholders = Stakeholders::load();
+    for stakeholder in stakeholders.iter() {
+        let mut delegation = Delegation::load(stakeholder);
+        for (current, next) in &changed {
+            let quantity = delegation.get_quantity(current);
+            if quantity > 0 {
+                delegation.sub_quantity(*current, quantity)?;
+                delegation.add_quantity(*next, quantity)?;
+            }
+        }
+        delegation.save();
+    }
+
+    // The identities are signed with the old keys, so the validators have to register them again.
+    let mut identities = NetworkIdentities::load();
+    for (current, _) in &changed {
+        identities.remove(current);
+    }
+    identities.save();
+    Ok(())
+}
+
 fn release_jailed_prisoners(released: &[Public]) -> Result<(), Error> {
     if released.is_empty() {
         return Ok(())
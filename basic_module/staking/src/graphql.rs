@@ -0,0 +1,111 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::state::{BaseFee, FeeRecord};
+use coordinator::module::{HandleGraphQlRequest, SessionId};
+use ftypes::BlockNumber;
+use remote_trait_object::Service;
+
+/// The most blocks whose fee records are listed at once.
+const MAX_FEE_RECORDS: u64 = 1000;
+
+struct GraphQlRoot;
+
+#[async_graphql::Object]
+impl GraphQlRoot {
+    /// Where the fees of the block went. Null if the block charged no fee.
+    async fn fee_record(&self, block_number: BlockNumber) -> Option<GqlFeeRecord> {
+        FeeRecord::load(block_number).map(GqlFeeRecord)
+    }
+
+    /// The fee records of the blocks from `from` to `to`, inclusive, skipping the blocks that charged no fee.
+    /// At most `MAX_FEE_RECORDS` blocks are looked up, from `from`.
+    async fn fee_records(&self, from: BlockNumber, to: BlockNumber) -> Vec<GqlFeeRecord> {
+        let to = to.min(from.saturating_add(MAX_FEE_RECORDS - 1));
+        (from..=to).filter_map(FeeRecord::load).map(GqlFeeRecord).collect()
+    }
+
+    /// The base fee per byte the next block requires of user transactions. Zero while the adjustment is disabled.
+    async fn base_fee(&self) -> u64 {
+        BaseFee::load()
+    }
+}
+
+struct GqlFeeRecord(FeeRecord);
+
+#[async_graphql::Object]
+impl GqlFeeRecord {
+    async fn block_number(&self) -> BlockNumber {
+        self.0.block_number
+    }
+
+    async fn collected(&self) -> u64 {
+        self.0.collected
+    }
+
+    async fn proposer(&self) -> String {
+        hex::encode(self.0.proposer.as_ref())
+    }
+
+    async fn to_proposer(&self) -> u64 {
+        self.0.to_proposer
+    }
+
+    async fn treasury(&self) -> String {
+        hex::encode(self.0.treasury.as_ref())
+    }
+
+    async fn to_treasury(&self) -> u64 {
+        self.0.to_treasury
+    }
+
+    async fn burned(&self) -> u64 {
+        self.0.burned
+    }
+
+    async fn balanced(&self) -> bool {
+        self.0.is_balanced()
+    }
+}
+
+pub struct GraphQlRequestHandler {
+    /// A runtime to process the asynchronous result of the query
+    tokio_runtime: tokio::runtime::Runtime,
+}
+
+impl GraphQlRequestHandler {
+    pub fn new() -> Self {
+        Self {
+            tokio_runtime: tokio::runtime::Runtime::new().unwrap(),
+        }
+    }
+}
+
+impl Service for GraphQlRequestHandler {}
+
+impl HandleGraphQlRequest for GraphQlRequestHandler {
+    fn execute(&self, _session_id: SessionId, query: &str, variables: &str) -> String {
+        let variables = match async_graphql::serde_json::from_str(variables) {
+            Ok(variables) => async_graphql::Variables::parse_from_json(variables),
+            Err(_) => return "Failed to parse JSON".to_owned(),
+        };
+        let schema =
+            async_graphql::Schema::new(GraphQlRoot, async_graphql::EmptyMutation, async_graphql::EmptySubscription);
+        let query = async_graphql::QueryBuilder::new(query).variables(variables);
+        let response = self.tokio_runtime.handle().block_on(query.execute(&schema));
+        async_graphql::serde_json::to_string(&async_graphql::http::GQLResponse(response)).unwrap()
+    }
+}
@@ -14,18 +14,21 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::check::check;
-use crate::core::{Abci, AdditionalTxCreator, StakingView};
+use crate::check::{check, check_machine_min_fee};
+use crate::core::{Abci, AdditionalTxCreator, ParamsManager, StakingView, UpdateChain};
 use crate::error::Error;
 use crate::execute::{apply_internal, execute_auto_action};
-use crate::state::{get_stakes, Banned, CurrentValidators, Metadata, Params};
+use crate::state::{get_stakes, Banned, CurrentValidators, FeeRecord, Metadata, NetworkIdentities, Params};
 use crate::transactions::{
     create_close_block_transactions, create_open_block_transactions, SignedTransaction, Transaction,
 };
 use crate::types::{Tiebreaker, Validator};
+use crate::{seen_nonces, substorage};
+use coordinator::replay::ReplayTag;
 use coordinator::types::{ExecuteTransactionError, HeaderError, TransactionOutcome, VerifiedCrime};
 use coordinator::Header;
 use fkey::Ed25519Public as Public;
+use ftypes::{BlockNumber, ConsensusParams, NetworkIdentity};
 use std::cell::RefCell;
 use std::collections::HashMap;
 
@@ -34,9 +37,10 @@ struct ABCIHandle {
 }
 
 impl AdditionalTxCreator for ABCIHandle {
-    fn create(&self) -> Vec<Transaction> {
+    fn create(&self, body_size: usize) -> Vec<Transaction> {
         let mut transactions = create_open_block_transactions();
-        transactions.extend(create_close_block_transactions(&*self.executing_block_header.borrow()).into_iter());
+        transactions
+            .extend(create_close_block_transactions(&*self.executing_block_header.borrow(), body_size).into_iter());
         transactions
     }
 }
@@ -44,6 +48,7 @@ impl AdditionalTxCreator for ABCIHandle {
 impl Abci for ABCIHandle {
     fn open_block(&self, header: &Header, _verified_crime: &[VerifiedCrime]) -> Result<(), HeaderError> {
         *self.executing_block_header.borrow_mut() = header.clone();
+        seen_nonces().prune(&mut *substorage(), header.number());
         Ok(())
     }
 
@@ -57,6 +62,7 @@ impl Abci for ABCIHandle {
             .map(|tx| match tx {
                 Transaction::User(signed_tx) => check(&signed_tx).map_err(Error::Syntax).and({
                     user_tx_idx += 1;
+                    let fee_payer = *signed_tx.fee_payer();
                     let SignedTransaction {
                         tx,
                         signer_public,
@@ -66,7 +72,7 @@ impl Abci for ABCIHandle {
                         nominated_at_block_number: self.executing_block_header.borrow().number(),
                         nominated_at_transaction_index: user_tx_idx,
                     };
-                    apply_internal(tx, &signer_public, tiebreaker).map_err(Error::Runtime)
+                    apply_internal(tx, &signer_public, &fee_payer, tiebreaker).map_err(Error::Runtime)
                 }),
                 Transaction::Auto(auto_action) => {
                     execute_auto_action(auto_action, self.executing_block_header.borrow().number())
@@ -80,10 +86,32 @@ impl Abci for ABCIHandle {
 
     fn check_transaction(&self, transaction: &Transaction) -> Result<(), i64> {
         match transaction {
-            Transaction::User(signed_tx) => check(signed_tx).map_err(|err| err.code()),
+            Transaction::User(signed_tx) => {
+                check(signed_tx).and_then(|()| check_machine_min_fee(&signed_tx.tx)).map_err(|err| err.code())
+            }
             Transaction::Auto(_) => Ok(()),
         }
     }
+
+    fn replay_tag(&self, transaction: &Transaction) -> Option<ReplayTag> {
+        match transaction {
+            Transaction::User(signed_tx) => signed_tx.replay_tag(),
+            Transaction::Auto(_) => None,
+        }
+    }
+}
+
+impl UpdateChain for ABCIHandle {
+    fn update_chain(&self) -> Option<ConsensusParams> {
+        let term_params = Metadata::load().term_params;
+        let parent_hash = self.executing_block_header.borrow().parent_hash().clone();
+        let parent_metadata = Metadata::load_from(parent_hash.into()).expect("parent metadata must exist");
+        if term_params.era != parent_metadata.term_params.era {
+            Some(term_params.consensus_params)
+        } else {
+            None
+        }
+    }
 }
 
 struct StakingViewer {}
@@ -120,4 +148,33 @@ impl StakingView for StakingViewer {
     fn get_banned_validators(&self) -> Banned {
         Banned::load()
     }
+
+    fn get_network_identities(&self) -> Vec<NetworkIdentity> {
+        let identities = NetworkIdentities::load();
+        CurrentValidators::load().iter().filter_map(|validator| identities.get(validator.pubkey()).cloned()).collect()
+    }
+
+    fn get_fee_record(&self, block_number: BlockNumber) -> Option<FeeRecord> {
+        FeeRecord::load(block_number)
+    }
+}
+
+struct ParamsChanger {}
+
+impl ParamsManager for ParamsChanger {
+    fn check_param_changes(&self, changes: &[(String, u64)]) -> Result<(), String> {
+        let current = Metadata::load().params;
+        let mut params = current;
+        for (name, value) in changes {
+            params = params.with_change(name, *value)?;
+        }
+        params.verify_change(&current)
+    }
+
+    fn enact_param_changes(&self, changes: &[(String, u64)]) -> Result<(), String> {
+        let mut metadata = Metadata::load();
+        metadata.enact_params(changes).map_err(|err| err.to_string())?;
+        metadata.save();
+        Ok(())
+    }
 }
@@ -21,6 +21,7 @@ mod check;
 mod core;
 mod error;
 mod execute;
+mod graphql;
 mod impls;
 mod imported;
 mod runtime_error;
@@ -31,6 +32,9 @@ mod types;
 
 use crate::types::NetworkId;
 use coordinator::context::{ChainHistoryAccess, SubStorageAccess};
+use coordinator::module::HandleGraphQlRequest;
+use coordinator::replay::SeenNonces;
+use ftypes::BlockNumber;
 use imported::{AccountManager, AccountView};
 use lazy_static::lazy_static;
 use parking_lot::Mutex;
@@ -39,6 +43,13 @@ fn substorage() -> Box<dyn SubStorageAccess> {
     unimplemented!()
 }
 
+/// How far ahead the expiry of a transaction with an expiring nonce may be.
+const REPLAY_WINDOW: BlockNumber = 1200;
+
+fn seen_nonces() -> SeenNonces {
+    SeenNonces::new(REPLAY_WINDOW)
+}
+
 fn deserialize<T: serde::de::DeserializeOwned>(buffer: Vec<u8>) -> T {
     serde_cbor::from_slice(&buffer).unwrap()
 }
@@ -49,6 +60,17 @@ fn serialize<T: serde::ser::Serialize>(data: T) -> Vec<u8> {
 
 lazy_static! {
     static ref NETWORK_ID: Mutex<Option<NetworkId>> = Default::default();
+    static ref MACHINE_MIN_FEE: Mutex<u64> = Default::default();
+}
+
+/// Sets the minimum fee this node requires of the user transactions it keeps in its mem pool.
+/// It differs by node, so it is not checked when a block is executed.
+pub fn set_machine_min_fee(min_fee: u64) {
+    *MACHINE_MIN_FEE.lock() = min_fee;
+}
+
+fn machine_min_fee() -> u64 {
+    *MACHINE_MIN_FEE.lock()
 }
 
 fn check_network_id(network_id: NetworkId) -> bool {
@@ -60,6 +82,11 @@ fn check_network_id(network_id: NetworkId) -> bool {
     true
 }
 
+/// The service answering the GraphQL queries on the fee records and the base fee.
+pub fn graphql_request_handler() -> Box<dyn HandleGraphQlRequest> {
+    Box::new(graphql::GraphQlRequestHandler::new())
+}
+
 fn account_manager() -> Box<dyn AccountManager> {
     unimplemented!()
 }
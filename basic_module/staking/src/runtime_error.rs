@@ -16,6 +16,7 @@
 
 use crate::error::{Insufficient, Mismatch};
 use crate::types::StakeQuantity;
+use coordinator::replay::ReplayError;
 use fkey::Ed25519Public as Public;
 use std::fmt::{Display, Formatter, Result as FormatResult};
 
@@ -28,9 +29,17 @@ pub enum Error {
     AccountInCustody(Public),
     SignatureOfInvalidAccount(Public),
     InvalidMetadataSeq(Mismatch<u64>),
+    InvalidParams(String),
+    InvalidFeeSchedule(String),
     InvalidSeq(Mismatch<u64>),
     InsufficientFee(Insufficient<u64>),
     InvalidValidators,
+    NotCandidate(Public),
+    NetworkAddressInUse(Public),
+    NodeKeyInUse(Public),
+    ValidatorKeyInUse(Public),
+    SponsorQuotaExceeded(Insufficient<u64>),
+    Replayed(ReplayError),
 }
 
 impl Display for Error {
@@ -45,9 +54,19 @@ impl Display for Error {
             Error::AccountInCustody(nominee) => write!(f, "Public {:?} is still in custody", nominee),
             Error::SignatureOfInvalidAccount(signer) => write!(f, "Public {:?} does not have any stake", signer),
             Error::InvalidMetadataSeq(mismatch) => write!(f, "Metatdata sequence mismatched. {}", mismatch),
+            Error::InvalidParams(reason) => write!(f, "Invalid params: {}", reason),
+            Error::InvalidFeeSchedule(reason) => write!(f, "Invalid fee schedule: {}", reason),
             Error::InvalidSeq(mismatch) => write!(f, "Seq of the transaction mismatched. {}", mismatch),
             Error::InsufficientFee(insufficient) => write!(f, "Insufficient fee: {}", insufficient),
             Error::InvalidValidators => write!(f, "Next validators do not match with the state's"),
+            Error::NotCandidate(public) => write!(f, "Public {:?} is not a candidate", public),
+            Error::NetworkAddressInUse(owner) => {
+                write!(f, "The network address is already registered by {:?}", owner)
+            }
+            Error::NodeKeyInUse(owner) => write!(f, "The node key is already registered by {:?}", owner),
+            Error::ValidatorKeyInUse(public) => write!(f, "Public {:?} is already used by a validator", public),
+            Error::SponsorQuotaExceeded(insufficient) => write!(f, "Sponsor quota exceeded: {}", insufficient),
+            Error::Replayed(err) => write!(f, "Replay protection failed: {}", err),
         }
     }
 }
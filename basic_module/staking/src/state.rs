@@ -19,7 +19,7 @@ use crate::runtime_error::Error;
 use crate::types::{Candidate, DepositQuantity, Prisoner, ReleaseResult, StakeQuantity, Tiebreaker, Validator};
 use crate::{account_viewer, deserialize, serialize, substorage};
 use fkey::Ed25519Public as Public;
-use ftypes::BlockId;
+use ftypes::{BlockId, BlockNumber, ConsensusParams, NetworkIdentity};
 use primitives::Bytes;
 use serde::{de::DeserializeOwned, ser::Serialize};
 use std::cmp::{max, Ordering, Reverse};
@@ -33,6 +33,7 @@ type KEY = dyn AsRef<[u8]>;
 
 const STAKE_ACCOUNT_PREFIX: [u8; 1] = [0x1];
 const DELEGATION_PREFIX: [u8; 1] = [0x2];
+const FEE_RECORD_PREFIX: [u8; 1] = [0x3];
 
 const METADATA_KEY: &[u8; 8] = b"Metadata";
 const STAKEHOLDERS_KEY: &[u8; 12] = b"Stakeholders";
@@ -41,6 +42,14 @@ const NEXT_VALIDATORS_KEY: &[u8; 14] = b"NextValidators";
 const CURRENT_VALIDATORS_KEY: &[u8; 17] = b"CurrentValidators";
 const JAIL_KEY: &[u8; 4] = b"Jail";
 const BANNED_KEY: &[u8; 6] = b"Banned";
+const FEE_SCHEDULES_KEY: &[u8; 12] = b"FeeSchedules";
+const NETWORK_IDENTITIES_KEY: &[u8; 17] = b"NetworkIdentities";
+const VALIDATOR_KEY_CHANGES_KEY: &[u8; 19] = b"ValidatorKeyChanges";
+const SPONSOR_QUOTAS_KEY: &[u8; 13] = b"SponsorQuotas";
+const BLOCK_FEES_KEY: &[u8; 9] = b"BlockFees";
+const BASE_FEE_KEY: &[u8; 7] = b"BaseFee";
+
+const BASIS_POINTS: u64 = 10_000;
 
 // The initialization process should be executed after the account module is initialized
 // because candidates require the corresponding accounts' balance
@@ -146,7 +155,10 @@ pub struct Metadata {
 
 #[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct Params {
-    pub term_seconds: u64,
+    /// Handed to the consensus engine when these params become the term params.
+    pub consensus_params: ConsensusParams,
+    /// The consensus-defined minimum fee of user transactions.
+    pub min_fee: u64,
     pub nomination_expiration: u64,
     pub custody_period: u64,
     pub release_period: u64,
@@ -155,10 +167,92 @@ pub struct Params {
     pub delegation_threshold: StakeQuantity,
     pub min_deposit: DepositQuantity,
     pub max_candidate_metadata_size: usize,
+    /// How the fees of a block are divided when the block closes.
+    pub fee_split: FeeSplit,
+    /// The account credited with the treasury share of the fees.
+    pub treasury: Public,
+    /// Lets the load of the blocks steer a base fee, which user transactions pay at least. Disabled if `None`.
+    #[serde(default)]
+    pub base_fee_adjustment: Option<BaseFeeAdjustment>,
 
     pub era: u64,
 }
 
+impl Params {
+    pub fn verify(&self) -> Result<(), String> {
+        self.consensus_params.verify()?;
+        self.fee_split.verify()?;
+        if let Some(base_fee_adjustment) = &self.base_fee_adjustment {
+            base_fee_adjustment.verify()?;
+        }
+        if self.nomination_expiration == 0 {
+            return Err("You should set the nomination expiration".to_string())
+        }
+        if self.custody_period == 0 {
+            return Err("You should set the custody period".to_string())
+        }
+        if self.release_period == 0 {
+            return Err("You should set the release period".to_string())
+        }
+        if self.max_num_of_validators == 0 {
+            return Err("You should set the maximum number of validators".to_string())
+        }
+        if self.min_num_of_validators == 0 {
+            return Err("You should set the minimum number of validators".to_string())
+        }
+        if self.delegation_threshold == 0 {
+            return Err("You should set the delegation threshold".to_string())
+        }
+        if self.min_deposit == 0 {
+            return Err("You should set the minimum deposit".to_string())
+        }
+        if self.min_num_of_validators > self.max_num_of_validators {
+            return Err(format!(
+                "The minimum number of validators({}) is larger than the maximum number of validators({})",
+                self.min_num_of_validators, self.max_num_of_validators
+            ))
+        }
+        if self.custody_period >= self.release_period {
+            return Err(format!(
+                "The release period({}) should be longer than the custody period({})",
+                self.release_period, self.custody_period
+            ))
+        }
+        Ok(())
+    }
+
+    /// Returns the params with a field changed by its name. The consensus params are named with `consensus.`.
+    pub fn with_change(mut self, name: &str, value: u64) -> Result<Self, String> {
+        if let Some(name) = name.strip_prefix("consensus.") {
+            self.consensus_params = self.consensus_params.with_change(name, value)?;
+            return Ok(self)
+        }
+        let to_usize = |value: u64| value as usize;
+        match name {
+            "min_fee" => self.min_fee = value,
+            "nomination_expiration" => self.nomination_expiration = value,
+            "custody_period" => self.custody_period = value,
+            "release_period" => self.release_period = value,
+            "max_num_of_validators" => self.max_num_of_validators = to_usize(value),
+            "min_num_of_validators" => self.min_num_of_validators = to_usize(value),
+            "delegation_threshold" => self.delegation_threshold = value,
+            "min_deposit" => self.min_deposit = value,
+            "max_candidate_metadata_size" => self.max_candidate_metadata_size = to_usize(value),
+            _ => return Err(format!("The param {} can't be changed", name)),
+        }
+        Ok(self)
+    }
+
+    pub fn verify_change(&self, current_params: &Self) -> Result<(), String> {
+        self.verify()?;
+        self.consensus_params.verify_change(&current_params.consensus_params)?;
+        if self.era < current_params.era {
+            return Err(format!("The era({}) shouldn't be less than the current era({})", self.era, current_params.era))
+        }
+        Ok(())
+    }
+}
+
 impl Metadata {
     pub fn load() -> Self {
         load_with_key(METADATA_KEY).expect("Params must be exist")
@@ -172,21 +266,59 @@ impl Metadata {
         write_with_key(METADATA_KEY, self)
     }
 
+    /// Accepts the params of a new era, which take effect when the current term closes.
     pub fn update_params(&mut self, metadata_seq: u64, new_params: Params) -> Result<(), Error> {
+        self.check_seq(metadata_seq)?;
+        new_params.verify_change(&self.params).map_err(Error::InvalidParams)?;
+        if new_params.era <= self.term_params.era {
+            return Err(Error::InvalidParams(format!(
+                "The era({}) of the params should be greater than the era({}) in effect",
+                new_params.era, self.term_params.era
+            )))
+        }
+        self.params = new_params;
+        self.seq += 1;
+        Ok(())
+    }
+
+    /// Changes the params of the next term as a passed proposal decided, in a new era.
+    /// It bumps the sequence too, so the hand-made changes signed against the old params are rejected.
+    pub fn enact_params(&mut self, changes: &[(String, u64)]) -> Result<(), Error> {
+        let mut new_params = self.params;
+        for (name, value) in changes {
+            new_params = new_params.with_change(name, *value).map_err(Error::InvalidParams)?;
+        }
+        new_params.era += 1;
+        new_params.verify_change(&self.params).map_err(Error::InvalidParams)?;
+        self.params = new_params;
+        self.seq += 1;
+        Ok(())
+    }
+
+    /// Consumes the metadata sequence for a governance action that does not touch the params.
+    pub fn increase_seq(&mut self, metadata_seq: u64) -> Result<(), Error> {
+        self.check_seq(metadata_seq)?;
+        self.seq += 1;
+        Ok(())
+    }
+
+    fn check_seq(&self, metadata_seq: u64) -> Result<(), Error> {
         if self.seq != metadata_seq {
             Err(Error::InvalidMetadataSeq(Mismatch {
                 found: metadata_seq,
                 expected: self.seq,
             }))
         } else {
-            self.params = new_params;
-            self.seq += 1;
             Ok(())
         }
     }
 
+    /// Makes the latest accepted params effective if they start a new era. It is called when a term closes,
+    /// so an era always begins at a term boundary.
     pub fn update_term_params(&mut self) {
-        self.term_params = self.params;
+        if self.params.era > self.term_params.era {
+            self.term_params = self.params;
+        }
     }
 
     pub fn increase_term_id(&mut self, last_term_finished_block_num: u64) {
@@ -585,6 +717,18 @@ impl Candidates {
         }
     }
 
+    /// Moves the candidacy to the next key, keeping its deposit and priority.
+    /// Returns false if the candidate has gone in the meantime.
+    pub fn change_key(&mut self, current: &Public, next: Public) -> bool {
+        match self.0.iter_mut().find(|c| &c.pubkey == current) {
+            Some(candidate) => {
+                candidate.pubkey = next;
+                true
+            }
+            None => false,
+        }
+    }
+
     /// reprioritize candidates in the order of last updated time
     fn reprioritize(&mut self, targets: Vec<Public>) {
         let (mut old, mut renewed): (Vec<_>, Vec<_>) = self.0.drain(..).partition(|c| !targets.contains(&c.pubkey));
@@ -681,3 +825,430 @@ pub fn get_stakes() -> HashMap<Public, u64> {
         })
         .collect()
 }
+
+/// The costs charged for a user transaction while an era is effective.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeSchedule {
+    /// The base cost of each action, keyed by the action name.
+    /// An action without an entry has no base cost.
+    pub action_costs: BTreeMap<String, u64>,
+    /// The cost of each byte of the encoded transaction.
+    pub per_byte: u64,
+    /// The cost of each item the action writes to the state.
+    pub per_storage_write: u64,
+}
+
+impl FeeSchedule {
+    pub fn cost(&self, action_name: &str, tx_size: usize, storage_writes: usize) -> u64 {
+        let base = self.action_costs.get(action_name).copied().unwrap_or_default();
+        base.saturating_add(self.per_byte.saturating_mul(tx_size as u64))
+            .saturating_add(self.per_storage_write.saturating_mul(storage_writes as u64))
+    }
+}
+
+/// Fee schedules indexed by the era from which they take effect.
+///
+/// The schedule of an era stays effective until a later era with its own schedule begins,
+/// so a repricing is registered ahead of time and activated by bumping the era in the params.
+#[derive(Default)]
+pub struct FeeSchedules(BTreeMap<u64, FeeSchedule>);
+
+impl FeeSchedules {
+    pub fn load() -> Self {
+        FeeSchedules(load_with_key(FEE_SCHEDULES_KEY).unwrap_or_default())
+    }
+
+    pub fn save(self) {
+        write_with_key(FEE_SCHEDULES_KEY, self.0)
+    }
+
+    /// Returns the schedule effective in the given era, if any.
+    pub fn effective_at(&self, era: u64) -> Option<&FeeSchedule> {
+        self.0.range(..=era).next_back().map(|(_, schedule)| schedule)
+    }
+
+    /// Registers the schedule of a future era.
+    /// The schedules of the current and the past eras are fixed, because blocks were already validated with them.
+    pub fn register(&mut self, era: u64, schedule: FeeSchedule, current_era: u64) -> Result<(), Error> {
+        if era <= current_era {
+            return Err(Error::InvalidFeeSchedule(format!(
+                "The era({}) of the fee schedule should be greater than the current era({})",
+                era, current_era
+            )))
+        }
+        self.0.insert(era, schedule);
+        Ok(())
+    }
+}
+
+/// The network identities registered by the candidates, keyed by their public keys.
+///
+/// A node address and a node key can be claimed by only one candidate,
+/// so that a validator cannot make the network layer trust its node under another validator's name.
+#[derive(Default)]
+pub struct NetworkIdentities(BTreeMap<Public, NetworkIdentity>);
+
+impl NetworkIdentities {
+    pub fn load() -> Self {
+        let identities: Vec<NetworkIdentity> = load_with_key(NETWORK_IDENTITIES_KEY).unwrap_or_default();
+        NetworkIdentities(identities.into_iter().map(|identity| (identity.validator, identity)).collect())
+    }
+
+    pub fn save(self) {
+        let vectorized: Vec<NetworkIdentity> = self.0.into_iter().map(|(_, identity)| identity).collect();
+        write_with_key(NETWORK_IDENTITIES_KEY, vectorized)
+    }
+
+    /// Registers the identity, replacing the one previously registered by the same validator.
+    pub fn register(&mut self, identity: NetworkIdentity) -> Result<(), Error> {
+        let others = self.0.values().filter(|other| other.validator != identity.validator);
+        for other in others {
+            if other.node_address == identity.node_address {
+                return Err(Error::NetworkAddressInUse(other.validator))
+            }
+            if other.node_key == identity.node_key {
+                return Err(Error::NodeKeyInUse(other.validator))
+            }
+        }
+        self.0.insert(identity.validator, identity);
+        Ok(())
+    }
+
+    pub fn get(&self, public: &Public) -> Option<&NetworkIdentity> {
+        self.0.get(public)
+    }
+
+    pub fn remove(&mut self, public: &Public) -> Option<NetworkIdentity> {
+        self.0.remove(public)
+    }
+}
+
+/// The consensus key changes waiting for the end of the current term, keyed by the current keys.
+///
+/// The keys are swapped when the term closes, before the next validators are elected,
+/// so that a validator set never changes its keys in the middle of a term.
+#[derive(Default)]
+pub struct ValidatorKeyChanges(BTreeMap<Public, Public>);
+
+impl ValidatorKeyChanges {
+    pub fn load() -> Self {
+        ValidatorKeyChanges(load_with_key(VALIDATOR_KEY_CHANGES_KEY).unwrap_or_default())
+    }
+
+    pub fn save(self) {
+        write_with_key(VALIDATOR_KEY_CHANGES_KEY, self.0)
+    }
+
+    /// Schedules the change, replacing the one previously scheduled by the same validator.
+    pub fn schedule(&mut self, current: Public, next: Public) -> Result<(), Error> {
+        if self.0.iter().any(|(other, scheduled)| *other != current && *scheduled == next) {
+            return Err(Error::ValidatorKeyInUse(next))
+        }
+        self.0.insert(current, next);
+        Ok(())
+    }
+
+    pub fn drain(&mut self) -> Vec<(Public, Public)> {
+        std::mem::take(&mut self.0).into_iter().collect()
+    }
+}
+
+/// How much a sponsor pays for the fees of the transactions it sponsors.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SponsorQuota {
+    /// The most fees paid in a term
+    pub per_term: u64,
+    /// The fees paid in `term_id`
+    pub spent: u64,
+    pub term_id: u64,
+}
+
+impl SponsorQuota {
+    /// The fees that can still be paid in the current term.
+    /// The spending of a past term doesn't count, so nothing has to be reset when a term closes.
+    pub fn remaining(&self, current_term_id: u64) -> u64 {
+        if self.term_id == current_term_id {
+            self.per_term.saturating_sub(self.spent)
+        } else {
+            self.per_term
+        }
+    }
+}
+
+/// The quotas set by the sponsors, keyed by their public keys.
+/// An account without a quota sponsors nothing.
+#[derive(Default)]
+pub struct SponsorQuotas(BTreeMap<Public, SponsorQuota>);
+
+impl SponsorQuotas {
+    pub fn load() -> Self {
+        SponsorQuotas(load_with_key(SPONSOR_QUOTAS_KEY).unwrap_or_default())
+    }
+
+    pub fn save(self) {
+        write_with_key(SPONSOR_QUOTAS_KEY, self.0)
+    }
+
+    pub fn remaining(&self, sponsor: &Public, current_term_id: u64) -> u64 {
+        self.0.get(sponsor).map(|quota| quota.remaining(current_term_id)).unwrap_or_default()
+    }
+
+    /// Sets the quota of the sponsor, keeping what it has paid in the current term.
+    /// A zero quota stops the sponsorship.
+    pub fn set(&mut self, sponsor: Public, per_term: u64) {
+        if per_term == 0 {
+            self.0.remove(&sponsor);
+        } else {
+            self.0.entry(sponsor).or_default().per_term = per_term;
+        }
+    }
+
+    pub fn spend(&mut self, sponsor: &Public, fee: u64, current_term_id: u64) -> Result<(), Error> {
+        if fee == 0 {
+            return Ok(())
+        }
+        let remaining = self.remaining(sponsor, current_term_id);
+        if remaining < fee {
+            return Err(Error::SponsorQuotaExceeded(Insufficient {
+                required: fee,
+                actual: remaining,
+            }))
+        }
+        let quota = self.0.get_mut(sponsor).expect("A sponsor with a remaining quota has its quota");
+        if quota.term_id != current_term_id {
+            quota.term_id = current_term_id;
+            quota.spent = 0;
+        }
+        quota.spent += fee;
+        Ok(())
+    }
+}
+
+/// The shares of the fees of a block, in basis points.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeSplit {
+    pub proposer: u16,
+    pub treasury: u16,
+    pub burn: u16,
+}
+
+impl FeeSplit {
+    pub fn verify(&self) -> Result<(), String> {
+        let total = u64::from(self.proposer) + u64::from(self.treasury) + u64::from(self.burn);
+        if total != BASIS_POINTS {
+            return Err(format!("The fee split should add up to {} basis points, not {}", BASIS_POINTS, total))
+        }
+        Ok(())
+    }
+
+    /// Returns the shares of the proposer, the treasury and the burn.
+    /// The first two are rounded down and the burn takes the remainder, so nothing is created or lost.
+    pub fn split(&self, fees: u64) -> (u64, u64, u64) {
+        let share = |basis_points: u16| (u128::from(fees) * u128::from(basis_points) / u128::from(BASIS_POINTS)) as u64;
+        let to_proposer = share(self.proposer);
+        let to_treasury = share(self.treasury);
+        (to_proposer, to_treasury, fees - to_proposer - to_treasury)
+    }
+}
+
+/// How the base fee follows the load of the blocks, in the manner of EIP-1559.
+///
+/// A block using more than the target raises the base fee of the following blocks and a block using less lowers it,
+/// in proportion to how far it is from the target. The base fee is a price per byte, while the `min_fee` param
+/// stays the floor of the whole fee.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BaseFeeAdjustment {
+    /// The size of the body the base fee aims at, in basis points of the max body size.
+    pub target_usage: u16,
+    /// The change of the base fee after an empty block, in basis points of the base fee.
+    /// A block exceeding the target by as much as the target raises it by the same.
+    pub max_change: u16,
+}
+
+impl BaseFeeAdjustment {
+    pub fn verify(&self) -> Result<(), String> {
+        if self.target_usage == 0 || u64::from(self.target_usage) > BASIS_POINTS {
+            return Err(format!("The target usage should be in (0, {}] basis points", BASIS_POINTS))
+        }
+        if self.max_change == 0 || u64::from(self.max_change) > BASIS_POINTS {
+            return Err(format!("The max change of the base fee should be in (0, {}] basis points", BASIS_POINTS))
+        }
+        Ok(())
+    }
+
+    /// Returns the base fee following a block which used `usage` bytes of `max_body_size`.
+    /// A block over the target raises the base fee by one at least, so that it can grow from a small value.
+    pub fn next_base_fee(&self, base_fee: u64, usage: u64, max_body_size: u64) -> u64 {
+        let target = (u128::from(max_body_size) * u128::from(self.target_usage) / u128::from(BASIS_POINTS)).max(1);
+        let usage = u128::from(usage);
+        let change = |distance: u128| {
+            let change =
+                u128::from(base_fee) * u128::from(self.max_change) * distance / (u128::from(BASIS_POINTS) * target);
+            change.min(u128::from(u64::MAX)) as u64
+        };
+        match usage.cmp(&target) {
+            Ordering::Greater => base_fee.saturating_add(change(usage - target).max(1)),
+            Ordering::Less => base_fee - change(target - usage).min(base_fee),
+            Ordering::Equal => base_fee,
+        }
+    }
+}
+
+/// The fee per byte the load of the recent blocks requires of user transactions.
+pub struct BaseFee;
+
+impl BaseFee {
+    pub fn load() -> u64 {
+        load_with_key(BASE_FEE_KEY).unwrap_or_default()
+    }
+
+    pub fn save(base_fee: u64) {
+        if base_fee == 0 {
+            remove_key(BASE_FEE_KEY)
+        } else {
+            write_with_key(BASE_FEE_KEY, base_fee)
+        }
+    }
+}
+
+/// The fees charged in the executing block, which are distributed when it closes.
+pub struct BlockFees;
+
+impl BlockFees {
+    pub fn collect(fee: u64) {
+        if fee == 0 {
+            return
+        }
+        let collected: u64 = load_with_key(BLOCK_FEES_KEY).unwrap_or_default();
+        write_with_key(BLOCK_FEES_KEY, collected + fee)
+    }
+
+    /// Returns the fees collected so far and starts over.
+    pub fn take() -> u64 {
+        let collected = load_with_key(BLOCK_FEES_KEY).unwrap_or_default();
+        remove_key(BLOCK_FEES_KEY);
+        collected
+    }
+}
+
+/// Where the fees of a block went. It is kept only for the blocks that charged fees.
+///
+/// The fees debited from the payers are credited to the proposer and the treasury or burned,
+/// so a record is balanced when the three add up to `collected`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeRecord {
+    pub block_number: BlockNumber,
+    pub collected: u64,
+    pub proposer: Public,
+    pub to_proposer: u64,
+    pub treasury: Public,
+    pub to_treasury: u64,
+    pub burned: u64,
+}
+
+impl FeeRecord {
+    /// Splits the fees collected in the block between the proposer, the treasury and the burn.
+    pub fn new(
+        block_number: BlockNumber,
+        collected: u64,
+        proposer: Public,
+        treasury: Public,
+        fee_split: &FeeSplit,
+    ) -> Self {
+        let (to_proposer, to_treasury, burned) = fee_split.split(collected);
+        Self {
+            block_number,
+            collected,
+            proposer,
+            to_proposer,
+            treasury,
+            to_treasury,
+            burned,
+        }
+    }
+
+    fn key(block_number: BlockNumber) -> Vec<u8> {
+        [&FEE_RECORD_PREFIX[..], &block_number.to_be_bytes()].concat()
+    }
+
+    pub fn load(block_number: BlockNumber) -> Option<Self> {
+        load_with_key(&Self::key(block_number))
+    }
+
+    pub fn save(self) {
+        write_with_key(&Self::key(self.block_number), self)
+    }
+
+    pub fn is_balanced(&self) -> bool {
+        self.to_proposer.checked_add(self.to_treasury).and_then(|credited| credited.checked_add(self.burned))
+            == Some(self.collected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fee_split(proposer: u16, treasury: u16, burn: u16) -> FeeSplit {
+        FeeSplit {
+            proposer,
+            treasury,
+            burn,
+        }
+    }
+
+    #[test]
+    fn fees_are_split_by_the_basis_points() {
+        let split = fee_split(5_000, 3_000, 2_000);
+        assert_eq!(split.verify(), Ok(()));
+        assert_eq!(split.split(10_000), (5_000, 3_000, 2_000));
+        assert_eq!(split.split(0), (0, 0, 0));
+    }
+
+    #[test]
+    fn burn_takes_the_rounding_remainder() {
+        let split = fee_split(3_333, 3_333, 3_334);
+        // 3333 / 10000 of 10 is 3.333, which is rounded down for the proposer and the treasury
+        assert_eq!(split.split(10), (3, 3, 4));
+        assert_eq!(split.split(1), (0, 0, 1));
+
+        let split = fee_split(10_000, 0, 0);
+        assert_eq!(split.split(7), (7, 0, 0));
+    }
+
+    #[test]
+    fn split_adds_up_to_the_whole_fee() {
+        let splits = vec![fee_split(3_333, 3_333, 3_334), fee_split(1, 9_998, 1), fee_split(0, 0, 10_000)];
+        for split in splits {
+            for &fees in &[1, 3, 9_999, 10_001, 123_456_789, u64::MAX] {
+                let (to_proposer, to_treasury, burned) = split.split(fees);
+                assert_eq!(u128::from(to_proposer) + u128::from(to_treasury) + u128::from(burned), u128::from(fees));
+            }
+        }
+    }
+
+    #[test]
+    fn split_must_add_up_to_the_basis_points() {
+        assert!(fee_split(5_000, 3_000, 1_000).verify().is_err());
+        assert!(fee_split(u16::MAX, u16::MAX, 0).verify().is_err());
+    }
+
+    #[test]
+    fn fee_record_distributes_the_collected_fees() {
+        let proposer = Public::random();
+        let treasury = Public::random();
+        let record = FeeRecord::new(3, 1_001, proposer, treasury, &fee_split(6_000, 3_000, 1_000));
+        assert_eq!(record.proposer, proposer);
+        assert_eq!(record.treasury, treasury);
+        assert_eq!((record.to_proposer, record.to_treasury, record.burned), (600, 300, 101));
+        assert!(record.is_balanced());
+    }
+
+    #[test]
+    fn fee_record_credited_more_than_collected_is_not_balanced() {
+        let mut record = FeeRecord::new(3, u64::MAX, Public::random(), Public::random(), &fee_split(5_000, 5_000, 0));
+        assert!(record.is_balanced());
+        record.burned += 1;
+        assert!(!record.is_balanced());
+    }
+}
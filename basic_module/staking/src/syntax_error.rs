@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::error::Insufficient;
 use crate::types::NetworkId;
 use fkey::Signature;
 use std::fmt;
@@ -22,6 +23,11 @@ use std::fmt;
 pub enum Error {
     InvalidSignature(Signature),
     InvalidNetworkId(NetworkId),
+    InsufficientFee(Insufficient<u64>),
+    InvalidNetworkIdentity,
+    InvalidValidatorKeyChange,
+    InvalidFeePayer,
+    InsufficientSponsorship(Insufficient<u64>),
 }
 
 impl fmt::Display for Error {
@@ -29,6 +35,17 @@ impl fmt::Display for Error {
         match self {
             Error::InvalidNetworkId(network_id) => write!(f, "{} is an invalid network id", network_id),
             Error::InvalidSignature(sig) => write!(f, "Signature {:?} is invalid", sig),
+            Error::InsufficientFee(insufficient) => write!(f, "Insufficient fee: {}", insufficient),
+            Error::InvalidNetworkIdentity => {
+                write!(f, "The network identity is not signed by the signer of the transaction")
+            }
+            Error::InvalidValidatorKeyChange => {
+                write!(f, "The key change is not requested by the signer or not signed by the next key")
+            }
+            Error::InvalidFeePayer => write!(f, "The fee payer is the signer or did not sign the transaction"),
+            Error::InsufficientSponsorship(insufficient) => {
+                write!(f, "The fee payer cannot pay the fee: {}", insufficient)
+            }
         }
     }
 }
@@ -38,6 +55,11 @@ impl Error {
         match self {
             Error::InvalidSignature(_) => -1,
             Error::InvalidNetworkId(_) => -2,
+            Error::InsufficientFee(_) => -3,
+            Error::InvalidNetworkIdentity => -4,
+            Error::InvalidValidatorKeyChange => -5,
+            Error::InvalidFeePayer => -6,
+            Error::InsufficientSponsorship(_) => -7,
         }
     }
 }
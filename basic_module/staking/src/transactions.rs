@@ -15,11 +15,13 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::chain_history_manager;
-use crate::state::{Jail, Metadata, NextValidators, Params};
+use crate::state::{BaseFee, FeeSchedule, FeeSchedules, Jail, Metadata, NextValidators, Params};
 use crate::types::{Approval, DepositQuantity, NetworkId, StakeQuantity, Validator};
 use ccrypto::blake256;
+use coordinator::replay::ReplayTag;
 use coordinator::Header;
 use fkey::{verify, Ed25519Public as Public, Signature};
+use ftypes::{BlockNumber, NetworkIdentity, ValidatorKeyChange};
 use primitives::{Bytes, H256};
 use std::collections::HashSet;
 
@@ -32,6 +34,8 @@ pub enum Transaction {
 pub struct SignedTransaction {
     pub signature: Signature,
     pub signer_public: Public,
+    /// The signature of `tx.fee_payer` on the same message, if the fee is sponsored.
+    pub fee_payer_signature: Option<Signature>,
     pub tx: UserTransaction,
 }
 
@@ -40,24 +44,94 @@ impl SignedTransaction {
         let message = self.tx.hash();
         verify(&self.signature, message.as_ref(), &self.signer_public)
     }
+
+    /// A sponsored transaction is signed by its fee payer too, so that nobody is charged without consent.
+    /// The fee payer signs the same message as the signer, which names the fee payer and the fee.
+    pub fn verify_fee_payer(&self) -> bool {
+        match (&self.tx.fee_payer, &self.fee_payer_signature) {
+            (None, None) => true,
+            (Some(fee_payer), Some(signature)) => {
+                *fee_payer != self.signer_public && verify(signature, self.tx.hash().as_ref(), fee_payer)
+            }
+            _ => false,
+        }
+    }
+
+    /// The account charged for the fee, which is the signer's unless the fee is sponsored.
+    pub fn fee_payer(&self) -> &Public {
+        self.tx.fee_payer.as_ref().unwrap_or(&self.signer_public)
+    }
+
+    pub fn replay_tag(&self) -> Option<ReplayTag> {
+        self.tx.replay_tag(&self.signer_public)
+    }
 }
 
 #[derive(Serialize)]
 pub struct UserTransaction {
-    /// Seq
+    /// Seq, which is ignored if the transaction has an `expiring_nonce`.
     pub seq: u64,
     /// Quantity of CCC to be paid as a cost for distributing this transaction to the network.
     pub fee: u64,
+    /// The sponsor paying the fee instead of the signer.
+    /// It is left out of the encoding when absent, so the transactions without a sponsor keep their hashes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee_payer: Option<Public>,
+    /// Protects the transaction from replays instead of `seq`, so that an account can have many pending transactions
+    /// without ordering them. It is left out of the encoding when absent, like `fee_payer`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiring_nonce: Option<ExpiringNonce>,
     // Network id
     pub network_id: NetworkId,
     pub action: UserAction,
 }
 
+#[derive(Serialize)]
+pub struct ExpiringNonce {
+    /// The last block the transaction can be included in.
+    pub expires_at: BlockNumber,
+    /// A random value, which the signer must not use again until the transaction expires.
+    pub nonce: H256,
+}
+
 impl UserTransaction {
     pub fn hash(&self) -> H256 {
         let serialized = serde_cbor::to_vec(&self).unwrap();
         blake256(serialized)
     }
+
+    pub fn replay_tag(&self, signer_public: &Public) -> Option<ReplayTag> {
+        self.expiring_nonce.as_ref().map(|expiring_nonce| ReplayTag {
+            signer: signer_public.as_ref().to_vec(),
+            expires_at: expiring_nonce.expires_at,
+            nonce: expiring_nonce.nonce,
+        })
+    }
+
+    /// The size of the encoded transaction.
+    pub fn size(&self) -> usize {
+        serde_cbor::to_vec(&self).unwrap().len()
+    }
+
+    /// The size priced by the fee schedules and the base fee.
+    /// The encoded fee is left out, since its length grows with the fee itself,
+    /// so a higher fee would raise the minimum fee it has to meet.
+    fn size_without_fee(&self) -> usize {
+        self.size() - serde_cbor::to_vec(&self.fee).unwrap().len()
+    }
+
+    /// The consensus-defined minimum fee, priced by the fee schedule of the current era.
+    /// The base fee, which is charged per byte, raises it while the blocks are fuller than the target.
+    /// A node may require more of the transactions in its mem pool, see `set_machine_min_fee`.
+    pub fn min_fee(&self) -> u64 {
+        let term_params = Metadata::load().term_params;
+        let scheduled = FeeSchedules::load()
+            .effective_at(term_params.era)
+            .map(|schedule| schedule.cost(self.action.name(), self.size_without_fee(), self.action.storage_writes()))
+            .unwrap_or_default();
+        let size = self.size_without_fee() as u64;
+        term_params.min_fee.max(scheduled).max(BaseFee::load().saturating_mul(size))
+    }
 }
 
 #[allow(dead_code)]
@@ -93,6 +167,22 @@ pub enum UserAction {
         message1: Bytes,
         message2: Bytes,
     },
+    ChangeFeeSchedule {
+        metadata_seq: u64,
+        era: u64,
+        schedule: FeeSchedule,
+        approvals: Vec<Approval>,
+    },
+    RegisterNetworkIdentity {
+        identity: NetworkIdentity,
+    },
+    ChangeValidatorKey {
+        change: ValidatorKeyChange,
+    },
+    /// Sets how much the signer pays in a term for the fees of the transactions it sponsors.
+    SetSponsorQuota {
+        per_term: u64,
+    },
 }
 
 pub enum AutoAction {
@@ -110,17 +200,123 @@ pub enum AutoAction {
     ChangeNextValidators {
         validators: Vec<Validator>,
     },
+    /// Divides the fees charged in the block among the proposer, the treasury and the burn.
+    DistributeFees {
+        proposer: Public,
+    },
+    /// Moves the base fee toward the load of the block, which is the size of its body.
+    AdjustBaseFee {
+        body_size: u64,
+    },
 }
 
 impl UserAction {
-    pub fn min_fee(&self) -> u64 {
-        // Where can we initialize the min fee
-        // We need both consensus-defined minimum fee and machine-defined minimum fee
-        unimplemented!()
+    /// The name used to look up the action cost in a fee schedule.
+    pub fn name(&self) -> &'static str {
+        match self {
+            UserAction::TransferCCS {
+                ..
+            } => "transfer_ccs",
+            UserAction::DelegateCCS {
+                ..
+            } => "delegate_ccs",
+            UserAction::Revoke {
+                ..
+            } => "revoke",
+            UserAction::Redelegate {
+                ..
+            } => "redelegate",
+            UserAction::SelfNominate {
+                ..
+            } => "self_nominate",
+            UserAction::ChangeParams {
+                ..
+            } => "change_params",
+            UserAction::ReportDoubleVote {
+                ..
+            } => "report_double_vote",
+            UserAction::ChangeFeeSchedule {
+                ..
+            } => "change_fee_schedule",
+            UserAction::RegisterNetworkIdentity {
+                ..
+            } => "register_network_identity",
+            UserAction::ChangeValidatorKey {
+                ..
+            } => "change_validator_key",
+            UserAction::SetSponsorQuota {
+                ..
+            } => "set_sponsor_quota",
+        }
+    }
+
+    /// The number of state items the action writes, which is charged by `FeeSchedule::per_storage_write`.
+    /// It counts the items saved by the action in `execute.rs`, and must be kept in sync with it.
+    /// The writes for charging the fee are common to all transactions, so they are not counted.
+    pub fn storage_writes(&self) -> usize {
+        match self {
+            // The stakeholders and the stake accounts of the sender and the receiver.
+            UserAction::TransferCCS {
+                ..
+            } => 3,
+            // The delegations and the stake account of the delegator.
+            UserAction::DelegateCCS {
+                ..
+            }
+            | UserAction::Revoke {
+                ..
+            }
+            | UserAction::Redelegate {
+                ..
+            } => 2,
+            // The balance of the nominee, the jail and the candidates.
+            UserAction::SelfNominate {
+                ..
+            } => 3,
+            // The metadata.
+            UserAction::ChangeParams {
+                ..
+            } => 1,
+            // The fee schedules and the metadata, whose seq is increased.
+            UserAction::ChangeFeeSchedule {
+                ..
+            } => 2,
+            // The network identities.
+            UserAction::RegisterNetworkIdentity {
+                ..
+            } => 1,
+            // The scheduled validator key changes.
+            UserAction::ChangeValidatorKey {
+                ..
+            } => 1,
+            // The sponsor quotas.
+            UserAction::SetSponsorQuota {
+                ..
+            } => 1,
+            // It is not executable yet.
+            UserAction::ReportDoubleVote {
+                ..
+            } => 0,
+        }
     }
 }
 
-pub fn create_close_block_transactions(current_header: &Header) -> Vec<Transaction> {
+pub fn create_close_block_transactions(current_header: &Header, body_size: usize) -> Vec<Transaction> {
+    // The fees go first, so that they are divided and the base fee is adjusted by the params the block was executed
+    // with, even if it closes a term.
+    let mut transactions = vec![
+        Transaction::Auto(AutoAction::DistributeFees {
+            proposer: *current_header.author(),
+        }),
+        Transaction::Auto(AutoAction::AdjustBaseFee {
+            body_size: body_size as u64,
+        }),
+    ];
+    transactions.extend(create_term_transactions(current_header));
+    transactions
+}
+
+fn create_term_transactions(current_header: &Header) -> Vec<Transaction> {
     let chain_history = chain_history_manager();
     let parent_hash = current_header.parent_hash();
     let parent_header = chain_history.get_block_header(parent_hash.clone().into()).expect("parent header must exist");
@@ -128,14 +324,16 @@ pub fn create_close_block_transactions(current_header: &Header) -> Vec<Transacti
     let metadata = Metadata::load();
     let term = metadata.current_term_id;
     let term_seconds = match term {
-        0 => parent_metadata.params.term_seconds,
-        _ => parent_metadata.term_params.term_seconds,
+        0 => parent_metadata.params.consensus_params.term_seconds(),
+        _ => parent_metadata.term_params.consensus_params.term_seconds(),
     };
 
     let mut next_validators = NextValidators::load();
     next_validators.update_weight(current_header.author());
 
-    if is_term_close(current_header, &parent_header, term_seconds) {
+    // In the middle of a term only the weights of the next validators change. The term is closed, and the
+    // validators are elected, by the first block whose timestamp crosses a term boundary.
+    if !is_term_close(current_header, &parent_header, term_seconds) {
         vec![Transaction::Auto(AutoAction::ChangeNextValidators {
             validators: next_validators.into(),
         })]
@@ -175,6 +373,7 @@ pub fn create_close_block_transactions(current_header: &Header) -> Vec<Transacti
     }
 }
 
+/// Whether the block is the first one of a new term, that is, the parent and the block are in different terms.
 fn is_term_close(header: &Header, parent: &Header, term_seconds: u64) -> bool {
     // Because the genesis block has a fixed generation time, the first block should not change the term.
     if header.number() == 1 {
@@ -210,3 +409,58 @@ pub fn create_open_block_transactions() -> Vec<Transaction> {
         validators: NextValidators::load(),
     })]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TERM_SECONDS: u64 = 100;
+
+    fn header(number: BlockNumber, timestamp: u64) -> Header {
+        Header::new(Default::default(), timestamp, number, Public::random(), Vec::new(), Vec::new())
+    }
+
+    #[test]
+    fn a_block_in_the_middle_of_a_term_does_not_close_it() {
+        assert!(!is_term_close(&header(5, 150), &header(4, 110), TERM_SECONDS));
+        assert!(!is_term_close(&header(5, 199), &header(4, 100), TERM_SECONDS));
+    }
+
+    #[test]
+    fn a_block_crossing_a_term_boundary_closes_the_term() {
+        assert!(is_term_close(&header(5, 200), &header(4, 199), TERM_SECONDS));
+        assert!(is_term_close(&header(5, 450), &header(4, 150), TERM_SECONDS));
+    }
+
+    #[test]
+    fn the_first_block_does_not_close_the_term() {
+        assert!(!is_term_close(&header(1, 200), &header(0, 0), TERM_SECONDS));
+    }
+
+    #[test]
+    fn terms_do_not_close_without_term_seconds() {
+        assert!(!is_term_close(&header(5, 200), &header(4, 100), 0));
+    }
+
+    fn transfer(fee: u64) -> UserTransaction {
+        UserTransaction {
+            seq: 0,
+            fee,
+            fee_payer: None,
+            expiring_nonce: None,
+            network_id: Default::default(),
+            action: UserAction::TransferCCS {
+                receiver_public: Public::random(),
+                quantity: 100,
+            },
+        }
+    }
+
+    #[test]
+    fn the_priced_size_does_not_depend_on_the_fee() {
+        let cheap = transfer(0);
+        let expensive = transfer(u64::max_value());
+        assert!(cheap.size() < expensive.size());
+        assert_eq!(cheap.size_without_fee(), expensive.size_without_fee());
+    }
+}
@@ -38,6 +38,12 @@ impl Default for NetworkId {
     }
 }
 
+impl PartialEq<fkey::NetworkId> for NetworkId {
+    fn eq(&self, other: &fkey::NetworkId) -> bool {
+        other.to_string().as_bytes() == self.0
+    }
+}
+
 #[derive(Eq, Default, Ord, PartialEq, PartialOrd, Serialize, Deserialize, Clone, Copy, Debug)]
 pub struct Tiebreaker {
     pub nominated_at_block_number: BlockNumber,
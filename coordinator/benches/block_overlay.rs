@@ -0,0 +1,68 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+#![feature(test)]
+
+extern crate test;
+
+#[path = "../src/context/memory.rs"]
+mod memory;
+
+use coordinator::context::{BlockOverlay, StorageAccess, SubStorageAccess};
+use memory::{Memory, MemoryStorage};
+use test::Bencher;
+
+const TRANSACTIONS: usize = 1000;
+const WRITES_PER_TRANSACTION: usize = 10;
+
+/// Executes a block whose transactions each overwrite keys read by the previous ones,
+/// reverting the transactions for which `is_failed` holds.
+fn execute_block(storage: &mut MemoryStorage, is_failed: impl Fn(usize) -> bool) -> BlockOverlay {
+    let mut overlay = BlockOverlay::new(storage, 1);
+    let mut sub_storage = overlay.sub_storage(0);
+    for tx in 0..TRANSACTIONS {
+        overlay.create_checkpoint();
+        for i in 0..WRITES_PER_TRANSACTION {
+            let key = ((tx + i) % 256).to_be_bytes();
+            let previous = sub_storage.get(&key).unwrap_or_default();
+            sub_storage.set(&key, [&previous[previous.len().saturating_sub(32)..], &tx.to_be_bytes()[..]].concat());
+        }
+        if is_failed(tx) {
+            overlay.revert_to_the_checkpoint();
+        } else {
+            overlay.discard_checkpoint();
+        }
+    }
+    overlay
+}
+
+#[bench]
+fn commit_write_heavy_block(b: &mut Bencher) {
+    let mut storage = MemoryStorage(Memory::default());
+    b.iter(|| execute_block(&mut storage, |_| false).commit());
+}
+
+#[bench]
+fn commit_write_heavy_block_with_failed_transactions(b: &mut Bencher) {
+    let mut storage = MemoryStorage(Memory::default());
+    b.iter(|| execute_block(&mut storage, |tx| tx % 3 == 0).commit());
+}
+
+#[bench]
+fn revert_write_heavy_block(b: &mut Bencher) {
+    let mut storage = MemoryStorage(Memory::default());
+    b.iter(|| execute_block(&mut storage, |_| false).revert());
+}
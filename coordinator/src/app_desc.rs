@@ -31,6 +31,7 @@ use std::fmt::Debug;
 use std::ops::{Deref, DerefMut};
 use std::str::FromStr;
 
+pub(crate) mod config_schema;
 pub(self) mod params;
 pub(self) mod validator;
 
@@ -137,6 +138,10 @@ pub struct AppDesc {
     pub host: HostSetup,
     #[serde(default)]
     pub transactions: Namespaced<SimpleName>,
+    /// List of transaction types the modules issue for the chain itself, e.g. auto-actions.
+    /// They may have no fee, so they skip the `TxSorter` and are only accepted from the node itself.
+    #[serde(default)]
+    pub system_transactions: Vec<LocalName>,
     #[serde(default)]
     pub param_defaults: Namespaced<String>,
 }
@@ -167,10 +172,25 @@ pub struct ModuleSetup {
     /// It is mainly intended for modules providing `TxSorter` service.
     #[serde(default)]
     pub transactions: Vec<LocalName>,
+    /// List of event keys this module publishes as topics when executing its transactions.
+    #[serde(default)]
+    pub publishes: Vec<LocalName>,
+    /// List of topics, `<module>/<event-key>`, this module subscribes to.
+    /// The events are delivered to the `handle-event` service the module exports.
+    #[serde(default)]
+    pub subscribes: Vec<GlobalName>,
     #[serde(default)]
     pub init_config: Value,
     #[serde(default)]
     pub genesis_config: Value,
+    /// The schema of the configs delivered to the `update-config` service the module exports.
+    /// A config not conforming to it is rejected before it reaches the module.
+    #[serde(default)]
+    pub config_schema: Value,
+    /// Whether the transactions may be executed in sessions of their own, which are not notified of
+    /// the block with `block_opened`. A module opts in if it keeps the state of a block only in its sub-storage.
+    #[serde(default)]
+    pub parallel_execution: bool,
     #[serde(default)]
     pub tags: HashMap<String, Value>,
 }
@@ -390,6 +410,7 @@ impl<'a, 'de, T: DeserializeOwned> DeserializeSeed<'de> for NamespacedMapVisitor
 #[cfg(test)]
 mod tests {
     use crate::app_desc::AppDesc;
+    use crate::values::Value;
     use unindent::unindent;
 
     #[test]
@@ -419,4 +440,104 @@ mod tests {
         );
         let _: AppDesc = serde_yaml::from_str(&source).unwrap();
     }
+
+    #[test]
+    fn load_subscriptions() {
+        let source = unindent(
+            r#"
+            default-sandboxer: single-process
+            modules:
+                token:
+                    hash: 1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef
+                    publishes:
+                        - transfer
+                staking:
+                    hash: 1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef
+                    exports:
+                        handle-event:
+                            handle-event: {}
+                    subscribes:
+                        - token/transfer
+            transactions:
+                transfer: token
+        "#,
+        );
+        AppDesc::from_str(&source).unwrap();
+    }
+
+    #[test]
+    fn subscription_to_undeclared_topic_is_rejected() {
+        let source = unindent(
+            r#"
+            default-sandboxer: single-process
+            modules:
+                token:
+                    hash: 1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef
+                staking:
+                    hash: 1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef
+                    exports:
+                        handle-event:
+                            handle-event: {}
+                    subscribes:
+                        - token/transfer
+        "#,
+        );
+        assert!(AppDesc::from_str(&source).is_err());
+    }
+
+    #[test]
+    fn subscriber_without_event_handler_is_rejected() {
+        let source = unindent(
+            r#"
+            default-sandboxer: single-process
+            modules:
+                token:
+                    hash: 1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef
+                    publishes:
+                        - transfer
+                staking:
+                    hash: 1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef
+                    subscribes:
+                        - token/transfer
+        "#,
+        );
+        assert!(AppDesc::from_str(&source).is_err());
+    }
+
+    #[test]
+    fn load_config_schema() {
+        let source = unindent(
+            r#"
+            default-sandboxer: single-process
+            modules:
+                token:
+                    hash: 1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef
+                    exports:
+                        update-config:
+                            update-config: {}
+                    config-schema:
+                        fee: int
+                        issuers: [string]
+        "#,
+        );
+        let app_desc = AppDesc::from_str(&source).unwrap();
+        let schema = &app_desc.modules["token"].config_schema;
+        assert!(matches!(schema, Value::Map(fields) if fields.len() == 2));
+    }
+
+    #[test]
+    fn config_updater_without_schema_is_rejected() {
+        let source = unindent(
+            r#"
+            default-sandboxer: single-process
+            modules:
+                token:
+                    hash: 1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef
+                    exports:
+                        update-config:
+                            update-config: {}
+        "#,
+        );
+        assert!(AppDesc::from_str(&source).is_err());
+    }
 }
@@ -0,0 +1,98 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::values::Value;
+
+const SCALAR_TYPES: &[&str] = &["int", "bool", "string", "any"];
+
+/// Checks that the schema is made of the types the coordinator knows.
+///
+/// A schema of module configs is a value of the same shape as the configs:
+/// a string names the type of a scalar, one of `int`, `bool`, `string` or `any`,
+/// a list with a single schema accepts a list of the values conforming to it,
+/// and a map accepts a map with exactly the same keys, each conforming to its schema.
+pub(crate) fn check_schema(schema: &Value) -> Result<(), String> {
+    match schema {
+        Value::String(name) if SCALAR_TYPES.contains(&name.as_str()) => Ok(()),
+        Value::String(name) => Err(format!("Unknown type '{}'", name)),
+        Value::List(items) if items.len() == 1 => check_schema(&items[0]),
+        Value::List(_) => Err("A list schema must have exactly one schema of its items".to_owned()),
+        Value::Map(fields) => {
+            fields.iter().try_for_each(|(key, field)| check_schema(field).map_err(|err| format!("{}: {}", key, err)))
+        }
+        _ => Err("A schema must be a type name, a list or a map".to_owned()),
+    }
+}
+
+/// Checks that the config conforms to the schema, which must have passed `check_schema`.
+pub(crate) fn check_config(schema: &Value, config: &Value) -> Result<(), String> {
+    match (schema, config) {
+        (Value::String(name), _) if name == "any" => Ok(()),
+        (Value::String(name), Value::Int(_)) if name == "int" => Ok(()),
+        (Value::String(name), Value::Bool(_)) if name == "bool" => Ok(()),
+        (Value::String(name), Value::String(_)) if name == "string" => Ok(()),
+        (Value::String(name), _) => Err(format!("Expected {}", name)),
+        (Value::List(items), Value::List(values)) => values.iter().enumerate().try_for_each(|(index, value)| {
+            check_config(&items[0], value).map_err(|err| format!("[{}]: {}", index, err))
+        }),
+        (Value::List(_), _) => Err("Expected a list".to_owned()),
+        (Value::Map(fields), Value::Map(values)) => {
+            if let Some(key) = values.keys().find(|key| !fields.contains_key(*key)) {
+                return Err(format!("Unknown key '{}'", key))
+            }
+            fields.iter().try_for_each(|(key, field)| match values.get(key) {
+                Some(value) => check_config(field, value).map_err(|err| format!("{}: {}", key, err)),
+                None => Err(format!("Missing key '{}'", key)),
+            })
+        }
+        (Value::Map(_), _) => Err("Expected a map".to_owned()),
+        _ => Err("Invalid schema".to_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(yaml: &str) -> Value {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn schema_with_unknown_type_is_invalid() {
+        assert!(check_schema(&value("{ fee: int, receivers: [string] }")).is_ok());
+        assert!(check_schema(&value("{ fee: float }")).is_err());
+        assert!(check_schema(&value("[int, bool]")).is_err());
+        assert!(check_schema(&value("3")).is_err());
+    }
+
+    #[test]
+    fn config_conforming_to_schema() {
+        let schema = value("{ fee: int, receivers: [string], extra: any }");
+        assert_eq!(Ok(()), check_config(&schema, &value("{ fee: 3, receivers: [a, b], extra: { x: 1 } }")));
+        assert_eq!(Ok(()), check_config(&schema, &value("{ fee: 3, receivers: [], extra: null }")));
+    }
+
+    #[test]
+    fn config_not_conforming_to_schema() {
+        let schema = value("{ fee: int, receivers: [string] }");
+        assert!(check_config(&schema, &value("{ fee: three, receivers: [] }")).is_err());
+        assert!(check_config(&schema, &value("{ fee: 3, receivers: [a, 1] }")).is_err());
+        assert!(check_config(&schema, &value("{ fee: 3 }")).is_err());
+        assert!(check_config(&schema, &value("{ fee: 3, receivers: [], typo: 1 }")).is_err());
+        assert!(check_config(&schema, &value("[3]")).is_err());
+    }
+}
@@ -14,8 +14,13 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use super::config_schema::check_schema;
 use super::AppDesc;
 use crate::app_desc::{GlobalName, Namespaced};
+use crate::event_bus::HANDLE_EVENT;
+use crate::transaction::COMPOSITE_TX_TYPE;
+use crate::values::Value;
+use crate::UPDATE_CONFIG;
 use anyhow::bail;
 
 impl AppDesc {
@@ -24,6 +29,8 @@ impl AppDesc {
         self.tx_owners_are_valid()?;
         self.host_imports_are_valid()?;
         self.module_imports_are_valid()?;
+        self.subscriptions_are_valid()?;
+        self.config_schemas_are_valid()?;
 
         Ok(())
     }
@@ -53,6 +60,10 @@ impl AppDesc {
     }
 
     fn tx_owners_are_valid(&self) -> anyhow::Result<()> {
+        if self.transactions.contains_key(COMPOSITE_TX_TYPE) {
+            bail!("'{}' is reserved for the transactions bundling other transactions", COMPOSITE_TX_TYPE)
+        }
+
         let invalid_owners: Vec<(&str, &str)> = self
             .transactions
             .iter()
@@ -65,9 +76,7 @@ impl AppDesc {
             })
             .collect();
 
-        if invalid_owners.is_empty() {
-            Ok(())
-        } else {
+        if !invalid_owners.is_empty() {
             bail!(
                 "No such owners for transactions: {}",
                 invalid_owners
@@ -77,6 +86,19 @@ impl AppDesc {
                     .join(", ")
             )
         }
+
+        let ownerless_system_txs: Vec<&str> = self
+            .system_transactions
+            .iter()
+            .map(|tx_type| tx_type as &str)
+            .filter(|tx_type| !self.transactions.contains_key(*tx_type))
+            .collect();
+
+        if ownerless_system_txs.is_empty() {
+            Ok(())
+        } else {
+            bail!("No owners for system transactions: {}", ownerless_system_txs.join(", "))
+        }
     }
 
     fn host_imports_are_valid(&self) -> anyhow::Result<()> {
@@ -90,6 +112,46 @@ impl AppDesc {
         Ok(())
     }
 
+    fn subscriptions_are_valid(&self) -> anyhow::Result<()> {
+        for (module, setup) in self.modules.iter() {
+            if setup.subscribes.is_empty() {
+                continue
+            }
+            if !setup.exports.contains_key(HANDLE_EVENT) {
+                bail!("A module, '{}' subscribes to topics without exporting '{}'", module, HANDLE_EVENT)
+            }
+            for topic in setup.subscribes.iter() {
+                let publisher = topic.module();
+                match self.modules.get(publisher) {
+                    Some(publisher_setup) => {
+                        if !publisher_setup.publishes.iter().any(|key| **key == topic.name()) {
+                            bail!("A module, '{}' subscribes to non-existing topic '{}'", module, topic)
+                        }
+                    }
+                    None => bail!("A module, '{}' subscribes to topics of non-existing module: {}", module, publisher),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn config_schemas_are_valid(&self) -> anyhow::Result<()> {
+        for (module, setup) in self.modules.iter() {
+            if setup.config_schema == Value::Null {
+                if setup.exports.contains_key(UPDATE_CONFIG) {
+                    bail!("A module, '{}' exports '{}' without a config schema", module, UPDATE_CONFIG)
+                }
+                continue
+            }
+            if let Err(err) = check_schema(&setup.config_schema) {
+                bail!("A module, '{}' has an invalid config schema: {}", module, err)
+            }
+        }
+
+        Ok(())
+    }
+
     fn imports_are_valid(&self, importer: &str, imports: &Namespaced<GlobalName>) -> anyhow::Result<()> {
         for (_to, from) in imports.iter() {
             let module = from.module();
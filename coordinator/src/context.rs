@@ -14,15 +14,30 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+mod block_overlay;
 mod chain_history_access;
+mod gas_meter;
 mod mem_pool_access;
+#[cfg(test)]
+pub(crate) mod memory;
+mod randomness_access;
+mod read_only;
 mod storage_access;
 mod sub_storage_access;
+mod time_oracle;
 
+pub use block_overlay::BlockOverlay;
 pub use chain_history_access::ChainHistoryAccess;
+pub(crate) use gas_meter::GasMeters;
+pub use gas_meter::{read_gas, write_gas, GasMeter, MeteredSubStorage};
 pub use mem_pool_access::MemPoolAccess;
+pub(crate) use randomness_access::BlockRandomness;
+pub use randomness_access::RandomnessAccess;
+pub use read_only::{ReadOnlyStorage, ReadOnlySubStorage};
 pub use storage_access::StorageAccess;
 pub use sub_storage_access::SubStorageAccess;
+pub(crate) use time_oracle::BlockTime;
+pub use time_oracle::TimeOracle;
 
 /// A `Context` provides the interface against the system services such as moulde substorage access,
 /// mempool access
@@ -0,0 +1,261 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::context::{StorageAccess, SubStorageAccess};
+use ccrypto::blake256;
+use ctypes::StorageId;
+use parking_lot::Mutex;
+use primitives::H256;
+use remote_trait_object::Service;
+use std::collections::BTreeMap;
+use std::mem;
+use std::sync::Arc;
+
+/// The values written to a sub-storage, `None` for a removed key.
+type Writes = BTreeMap<Vec<u8>, Option<Vec<u8>>>;
+
+/// The writes to a sub-storage not yet applied to it.
+/// The bottom layer holds the writes of the block, and each checkpoint stacks a layer on it.
+struct Layers {
+    base: Box<dyn SubStorageAccess>,
+    layers: Vec<Writes>,
+}
+
+impl Layers {
+    fn new(base: Box<dyn SubStorageAccess>) -> Self {
+        Self {
+            base,
+            layers: vec![Writes::new()],
+        }
+    }
+
+    fn written(&self, key: &[u8]) -> Option<&Option<Vec<u8>>> {
+        self.layers.iter().rev().find_map(|layer| layer.get(key))
+    }
+
+    fn write(&mut self, key: &[u8], value: Option<Vec<u8>>) {
+        self.layers.last_mut().expect("The bottom layer always exists").insert(key.to_vec(), value);
+    }
+
+    fn merge_top(&mut self) {
+        assert!(self.layers.len() > 1, "The checkpoint must exist");
+        let top = self.layers.pop().expect("Checked above");
+        self.layers.last_mut().expect("Checked above").extend(top);
+    }
+
+    fn drop_top(&mut self) {
+        assert!(self.layers.len() > 1, "The checkpoint must exist");
+        self.layers.pop();
+    }
+
+    fn commit(&mut self) {
+        assert_eq!(self.layers.len(), 1, "All checkpoints must be closed before the commit");
+        for (key, value) in mem::take(&mut self.layers[0]) {
+            match value {
+                Some(value) => self.base.set(&key, value),
+                None => self.base.remove(&key),
+            }
+        }
+    }
+}
+
+/// A sub-storage of a `BlockOverlay`.
+struct OverlaidSubStorage(Arc<Mutex<Layers>>);
+
+impl Service for OverlaidSubStorage {}
+
+impl SubStorageAccess for OverlaidSubStorage {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let layers = self.0.lock();
+        match layers.written(key) {
+            Some(value) => value.clone(),
+            None => layers.base.get(key),
+        }
+    }
+
+    fn set(&mut self, key: &[u8], value: Vec<u8>) {
+        self.0.lock().write(key, Some(value))
+    }
+
+    fn has(&self, key: &[u8]) -> bool {
+        let layers = self.0.lock();
+        match layers.written(key) {
+            Some(value) => value.is_some(),
+            None => layers.base.has(key),
+        }
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        self.0.lock().write(key, None)
+    }
+}
+
+/// Accumulates the writes of a block session in memory, leaving the underlying storage untouched
+/// until `commit`, which applies them to it at once.
+///
+/// A checkpoint stacks a layer of writes, so reverting to it only drops the layer,
+/// and reverting the whole block only drops the overlay.
+/// The clones share the writes.
+#[derive(Clone)]
+pub struct BlockOverlay {
+    sub_storages: Vec<Arc<Mutex<Layers>>>,
+}
+
+impl BlockOverlay {
+    pub fn new(storage: &mut dyn StorageAccess, number_of_sub_storages: usize) -> Self {
+        Self {
+            sub_storages: (0..number_of_sub_storages)
+                .map(|id| Arc::new(Mutex::new(Layers::new(storage.sub_storage(id as StorageId)))))
+                .collect(),
+        }
+    }
+
+    /// Applies the writes of the block to the underlying storage.
+    pub fn commit(self) {
+        for layers in &self.sub_storages {
+            layers.lock().commit();
+        }
+    }
+
+    /// Digests the writes made since the latest checkpoint, or the writes of the block if there's none.
+    /// Equal writes give the same digest whatever order they were made in.
+    pub fn checkpoint_digest(&self) -> H256 {
+        let tops: Vec<Writes> = self
+            .sub_storages
+            .iter()
+            .map(|layers| layers.lock().layers.last().expect("The bottom layer always exists").clone())
+            .collect();
+        blake256(serde_cbor::to_vec(&tops).unwrap())
+    }
+
+    /// Discards the writes of the block.
+    pub fn revert(self) {
+        for layers in &self.sub_storages {
+            layers.lock().layers = vec![Writes::new()];
+        }
+    }
+}
+
+impl StorageAccess for BlockOverlay {
+    fn sub_storage(&mut self, storage_id: StorageId) -> Box<dyn SubStorageAccess> {
+        Box::new(OverlaidSubStorage(Arc::clone(&self.sub_storages[storage_id as usize])))
+    }
+
+    fn create_checkpoint(&mut self) {
+        for layers in &self.sub_storages {
+            layers.lock().layers.push(Writes::new());
+        }
+    }
+
+    fn revert_to_the_checkpoint(&mut self) {
+        for layers in &self.sub_storages {
+            layers.lock().drop_top();
+        }
+    }
+
+    fn discard_checkpoint(&mut self) {
+        for layers in &self.sub_storages {
+            layers.lock().merge_top();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::memory::{Memory, MemoryStorage};
+
+    fn overlay() -> (Memory, BlockOverlay) {
+        let memory = Memory::default();
+        memory.data.lock().insert(b"kept".to_vec(), b"before".to_vec());
+        memory.data.lock().insert(b"removed".to_vec(), b"before".to_vec());
+        let overlay = BlockOverlay::new(&mut MemoryStorage(memory.clone()), 1);
+        (memory, overlay)
+    }
+
+    #[test]
+    fn writes_reach_the_storage_only_on_commit() {
+        let (memory, mut overlay) = overlay();
+        let mut sub_storage = overlay.sub_storage(0);
+        sub_storage.set(b"new", b"after".to_vec());
+        sub_storage.remove(b"removed");
+
+        assert_eq!(sub_storage.get(b"new"), Some(b"after".to_vec()));
+        assert!(!sub_storage.has(b"removed"));
+        assert_eq!(sub_storage.get(b"kept"), Some(b"before".to_vec()));
+        assert_eq!(*memory.writes.lock(), 0);
+
+        overlay.commit();
+        assert_eq!(*memory.writes.lock(), 2);
+        assert_eq!(memory.get(b"new"), Some(b"after".to_vec()));
+        assert!(!memory.has(b"removed"));
+    }
+
+    #[test]
+    fn checkpoints_are_layered() {
+        let (memory, mut overlay) = overlay();
+        let mut sub_storage = overlay.sub_storage(0);
+
+        overlay.create_checkpoint();
+        sub_storage.set(b"kept", b"discarded".to_vec());
+        overlay.create_checkpoint();
+        sub_storage.set(b"kept", b"reverted".to_vec());
+        sub_storage.remove(b"removed");
+        overlay.revert_to_the_checkpoint();
+        assert_eq!(sub_storage.get(b"kept"), Some(b"discarded".to_vec()));
+        assert!(sub_storage.has(b"removed"));
+        overlay.discard_checkpoint();
+
+        overlay.commit();
+        assert_eq!(*memory.writes.lock(), 1);
+        assert_eq!(memory.get(b"kept"), Some(b"discarded".to_vec()));
+    }
+
+    #[test]
+    fn checkpoint_digest_covers_only_the_writes_since_the_checkpoint() {
+        let (_, mut other) = overlay();
+        let (_, mut overlay) = overlay();
+        let mut sub_storage = overlay.sub_storage(0);
+        let untouched = overlay.checkpoint_digest();
+
+        sub_storage.set(b"kept", b"block".to_vec());
+        let block = overlay.checkpoint_digest();
+        assert_ne!(block, untouched);
+
+        overlay.create_checkpoint();
+        assert_eq!(overlay.checkpoint_digest(), untouched);
+        sub_storage.set(b"new", b"after".to_vec());
+        sub_storage.remove(b"removed");
+        let transaction = overlay.checkpoint_digest();
+
+        let mut other_sub_storage = other.sub_storage(0);
+        other_sub_storage.remove(b"removed");
+        other_sub_storage.set(b"new", b"after".to_vec());
+        assert_eq!(other.checkpoint_digest(), transaction);
+
+        overlay.revert_to_the_checkpoint();
+        assert_eq!(overlay.checkpoint_digest(), block);
+    }
+
+    #[test]
+    fn reverted_block_leaves_the_storage_untouched() {
+        let (memory, overlay) = overlay();
+        overlay.clone().sub_storage(0).set(b"kept", b"after".to_vec());
+        overlay.revert();
+        assert_eq!(*memory.writes.lock(), 0);
+        assert_eq!(memory.get(b"kept"), Some(b"before".to_vec()));
+    }
+}
@@ -0,0 +1,168 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::context::SubStorageAccess;
+use crate::module::SessionId;
+use parking_lot::Mutex;
+use remote_trait_object::Service;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The gas charged for reading an item from a sub-storage.
+pub const STORAGE_READ_GAS: u64 = 10;
+
+/// The gas charged for writing or removing an item of a sub-storage.
+pub const STORAGE_WRITE_GAS: u64 = 50;
+
+/// The gas charged for each 32 bytes of the keys and the values passed through a sub-storage.
+pub const STORAGE_WORD_GAS: u64 = 1;
+
+fn words(len: usize) -> u64 {
+    (len as u64 + 31) / 32
+}
+
+/// The gas of reading the value of the key, not counting the value itself.
+pub fn read_gas(key: &[u8]) -> u64 {
+    STORAGE_READ_GAS + STORAGE_WORD_GAS * words(key.len())
+}
+
+/// The gas of writing the value of the key, or removing it with no value.
+pub fn write_gas(key: &[u8], value: &[u8]) -> u64 {
+    STORAGE_WRITE_GAS + STORAGE_WORD_GAS * (words(key.len()) + words(value.len()))
+}
+
+#[derive(Default)]
+struct Metering {
+    used: u64,
+    /// Whether a transaction is metered.
+    started: bool,
+}
+
+/// Counts the gas of the storage accesses made in a session while a transaction is executed.
+///
+/// The gas is counted by the host, so a module can't under-report it. It is only counted:
+/// the accesses are made whatever the gas is, as the host can't stop a module running without accessing
+/// the storage anyway.
+#[derive(Default)]
+pub struct GasMeter(Mutex<Metering>);
+
+impl GasMeter {
+    /// Starts metering a transaction from zero.
+    pub fn start(&self) {
+        *self.0.lock() = Metering {
+            used: 0,
+            started: true,
+        };
+    }
+
+    /// Stops metering, so that the accesses are not charged until the next `start`.
+    pub fn stop(&self) {
+        *self.0.lock() = Metering::default();
+    }
+
+    pub fn used(&self) -> u64 {
+        self.0.lock().used
+    }
+
+    fn charge(&self, gas: u64) {
+        let mut metering = self.0.lock();
+        if metering.started {
+            metering.used = metering.used.saturating_add(gas);
+        }
+    }
+}
+
+/// The gas meters of the open sessions.
+#[derive(Default)]
+pub(crate) struct GasMeters(Mutex<HashMap<SessionId, Arc<GasMeter>>>);
+
+impl GasMeters {
+    /// The meter of the session, which is created if the session has none yet.
+    pub fn get(&self, session_id: SessionId) -> Arc<GasMeter> {
+        Arc::clone(self.0.lock().entry(session_id).or_default())
+    }
+
+    pub fn remove(&self, session_id: SessionId) {
+        self.0.lock().remove(&session_id);
+    }
+}
+
+/// A sub-storage charging the gas of each access to the meter of its session.
+pub struct MeteredSubStorage {
+    base: Box<dyn SubStorageAccess>,
+    meter: Arc<GasMeter>,
+}
+
+impl MeteredSubStorage {
+    pub fn new(base: Box<dyn SubStorageAccess>, meter: Arc<GasMeter>) -> Self {
+        Self {
+            base,
+            meter,
+        }
+    }
+}
+
+impl Service for MeteredSubStorage {}
+
+impl SubStorageAccess for MeteredSubStorage {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.meter.charge(read_gas(key));
+        let value = self.base.get(key)?;
+        self.meter.charge(STORAGE_WORD_GAS * words(value.len()));
+        Some(value)
+    }
+
+    fn set(&mut self, key: &[u8], value: Vec<u8>) {
+        self.meter.charge(write_gas(key, &value));
+        self.base.set(key, value)
+    }
+
+    fn has(&self, key: &[u8]) -> bool {
+        self.meter.charge(read_gas(key));
+        self.base.has(key)
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        self.meter.charge(write_gas(key, &[]));
+        self.base.remove(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::memory::Memory;
+
+    #[test]
+    fn accesses_are_charged_while_metering() {
+        let meter = Arc::new(GasMeter::default());
+        let mut storage = MeteredSubStorage::new(Box::new(Memory::default()), Arc::clone(&meter));
+
+        storage.set(b"key", vec![0; 40]);
+        assert_eq!(meter.used(), 0);
+
+        meter.start();
+        storage.set(b"key", vec![0; 40]);
+        assert_eq!(storage.get(b"key"), Some(vec![0; 40]));
+        assert!(storage.has(b"key"));
+        storage.remove(b"key");
+        let expected = write_gas(b"key", &[0; 40]) + read_gas(b"key") + 2 + read_gas(b"key") + write_gas(b"key", &[]);
+        assert_eq!(meter.used(), expected);
+
+        meter.stop();
+        assert_eq!(meter.used(), 0);
+    }
+}
@@ -0,0 +1,74 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! The in-memory storage shared by the tests of the storage wrappers.
+
+use super::{StorageAccess, SubStorageAccess};
+use ctypes::StorageId;
+use parking_lot::Mutex;
+use remote_trait_object::Service;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// A sub-storage in memory, counting the writes applied to it. Its clones share the data.
+#[derive(Clone, Default)]
+pub struct Memory {
+    pub data: Arc<Mutex<BTreeMap<Vec<u8>, Vec<u8>>>>,
+    pub writes: Arc<Mutex<usize>>,
+}
+
+impl Service for Memory {}
+
+impl SubStorageAccess for Memory {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.data.lock().get(key).cloned()
+    }
+
+    fn set(&mut self, key: &[u8], value: Vec<u8>) {
+        *self.writes.lock() += 1;
+        self.data.lock().insert(key.to_vec(), value);
+    }
+
+    fn has(&self, key: &[u8]) -> bool {
+        self.data.lock().contains_key(key)
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        *self.writes.lock() += 1;
+        self.data.lock().remove(key);
+    }
+}
+
+/// A storage whose sub-storages are all the same `Memory`. It leaves the checkpoints to the overlay on top of it.
+pub struct MemoryStorage(pub Memory);
+
+impl StorageAccess for MemoryStorage {
+    fn sub_storage(&mut self, _storage_id: StorageId) -> Box<dyn SubStorageAccess> {
+        Box::new(self.0.clone())
+    }
+
+    fn create_checkpoint(&mut self) {
+        unreachable!("The overlay manages the checkpoints")
+    }
+
+    fn revert_to_the_checkpoint(&mut self) {
+        unreachable!("The overlay manages the checkpoints")
+    }
+
+    fn discard_checkpoint(&mut self) {
+        unreachable!("The overlay manages the checkpoints")
+    }
+}
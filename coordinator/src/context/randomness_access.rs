@@ -0,0 +1,74 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccrypto::blake256;
+use primitives::H256;
+use remote_trait_object::{service, Service};
+
+/// Deterministic and predictable randomness of the block being executed.
+///
+/// The randomness is derived from the seal of the parent block, so every node executing the block gets the same values.
+/// It is not secret: anyone can compute it once the parent block is committed, before the transactions of the block
+/// are chosen, so a sender can decide whether to send a transaction knowing the outcome.
+/// The proposer of the parent block can also move it by choosing which of the precommits to put into the seal.
+/// It must not decide anything a participant gains by predicting or steering, such as a lottery.
+#[service]
+pub trait RandomnessAccess: Service {
+    /// Returns the randomness for `domain`, which is the same for the same domain within a block.
+    /// Modules should put their names into the domain lest they share values with the others.
+    fn predictable_random(&self, domain: &[u8]) -> H256;
+}
+
+pub(crate) struct BlockRandomness {
+    seed: H256,
+}
+
+impl BlockRandomness {
+    pub(crate) fn new(seed: H256) -> Self {
+        Self {
+            seed,
+        }
+    }
+}
+
+impl Service for BlockRandomness {}
+
+impl RandomnessAccess for BlockRandomness {
+    fn predictable_random(&self, domain: &[u8]) -> H256 {
+        let mut input = self.seed.to_vec();
+        input.extend_from_slice(domain);
+        blake256(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_domain_gets_same_randomness() {
+        let randomness = BlockRandomness::new(H256::from_slice(&[1; 32]));
+        assert_eq!(randomness.predictable_random(b"sampling"), randomness.predictable_random(b"sampling"));
+        assert_ne!(randomness.predictable_random(b"sampling"), randomness.predictable_random(b"shuffle"));
+    }
+
+    #[test]
+    fn randomness_depends_on_the_seed() {
+        let one = BlockRandomness::new(H256::from_slice(&[1; 32]));
+        let other = BlockRandomness::new(H256::from_slice(&[2; 32]));
+        assert_ne!(one.predictable_random(b"sampling"), other.predictable_random(b"sampling"));
+    }
+}
@@ -0,0 +1,101 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::context::{StorageAccess, SubStorageAccess};
+use ctypes::StorageId;
+use remote_trait_object::Service;
+
+/// A sub-storage which can be read but not written.
+///
+/// A query session may run on the state of an old block, which must stay as it was,
+/// so the writes are dropped instead of reaching the state.
+pub struct ReadOnlySubStorage {
+    storage_id: StorageId,
+    base: Box<dyn SubStorageAccess>,
+}
+
+impl ReadOnlySubStorage {
+    pub fn new(storage_id: StorageId, base: Box<dyn SubStorageAccess>) -> Self {
+        Self {
+            storage_id,
+            base,
+        }
+    }
+}
+
+impl Service for ReadOnlySubStorage {}
+
+impl SubStorageAccess for ReadOnlySubStorage {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.base.get(key)
+    }
+
+    fn set(&mut self, _key: &[u8], _value: Vec<u8>) {
+        tracing::warn!(storage_id = self.storage_id, "Dropped a write to a read-only sub-storage");
+    }
+
+    fn has(&self, key: &[u8]) -> bool {
+        self.base.has(key)
+    }
+
+    fn remove(&mut self, _key: &[u8]) {
+        tracing::warn!(storage_id = self.storage_id, "Dropped a removal from a read-only sub-storage");
+    }
+}
+
+/// Opens read-only views of the sub-storages of the underlying storage.
+pub struct ReadOnlyStorage<'a>(&'a mut dyn StorageAccess);
+
+impl<'a> ReadOnlyStorage<'a> {
+    pub fn new(storage: &'a mut dyn StorageAccess) -> Self {
+        ReadOnlyStorage(storage)
+    }
+}
+
+impl<'a> StorageAccess for ReadOnlyStorage<'a> {
+    fn sub_storage(&mut self, storage_id: StorageId) -> Box<dyn SubStorageAccess> {
+        Box::new(ReadOnlySubStorage::new(storage_id, self.0.sub_storage(storage_id)))
+    }
+
+    // Nothing is written, so there is nothing to revert.
+    fn create_checkpoint(&mut self) {}
+
+    fn revert_to_the_checkpoint(&mut self) {}
+
+    fn discard_checkpoint(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::memory::Memory;
+
+    #[test]
+    fn writes_do_not_reach_the_base() {
+        let mut memory = Memory::default();
+        memory.set(b"key", b"old".to_vec());
+
+        let mut read_only = ReadOnlySubStorage::new(0, Box::new(memory.clone()));
+        read_only.set(b"key", b"new".to_vec());
+        read_only.set(b"other", b"value".to_vec());
+        read_only.remove(b"key");
+
+        assert_eq!(read_only.get(b"key"), Some(b"old".to_vec()));
+        assert!(!read_only.has(b"other"));
+        assert_eq!(memory.get(b"key"), Some(b"old".to_vec()));
+        assert!(!memory.has(b"other"));
+    }
+}
@@ -0,0 +1,49 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use remote_trait_object::{service, Service};
+
+/// The time of the block being executed, which no single validator decides.
+///
+/// The header timestamp is written by the proposer alone. The median time is instead the median of
+/// the times at which the validators precommitted the parent block, so it stays between the clocks
+/// of honest validators as long as more than half of the precommits in the seal are honest.
+/// It is the header timestamp for the first block, which has no precommits to take the median of.
+#[service]
+pub trait TimeOracle: Service {
+    /// Returns the median time in seconds since the UNIX epoch.
+    fn median_time(&self) -> u64;
+}
+
+pub(crate) struct BlockTime {
+    median_time: u64,
+}
+
+impl BlockTime {
+    pub(crate) fn new(median_time: u64) -> Self {
+        Self {
+            median_time,
+        }
+    }
+}
+
+impl Service for BlockTime {}
+
+impl TimeOracle for BlockTime {
+    fn median_time(&self) -> u64 {
+        self.median_time
+    }
+}
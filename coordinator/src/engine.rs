@@ -16,11 +16,15 @@
 
 use crate::context::StorageAccess;
 use crate::header::Header;
-use crate::transaction::{Transaction, TransactionWithMetadata};
+use crate::module::TxSortingMetadata;
+use crate::replay::ReplayTag;
+use crate::transaction::{Transaction, TransactionWithMetadata, TxSizes};
 use crate::types::{
-    BlockOutcome, CloseBlockError, ErrorCode, FilteredTxs, HeaderError, TransactionOutcome, VerifiedCrime,
+    BlockOutcome, CloseBlockError, ErrorCode, FilteredTxs, HeaderError, InspectedPayload, TransactionOutcome,
+    TransactionPreview, VerifiedCrime, VetoedTxs,
 };
-use ctypes::{CompactValidatorSet, ConsensusParams};
+use ctypes::{CompactValidatorSet, ConsensusParams, NetworkIdentity};
+use primitives::H256;
 use std::sync::Arc;
 
 pub trait Initializer: Send + Sync {
@@ -36,6 +40,8 @@ pub trait BlockExecutor: Send + Sync {
         &self,
         storage: &mut dyn StorageAccess,
         header: &Header,
+        random_seed: &H256,
+        median_time: u64,
         verified_crimes: &[VerifiedCrime],
     ) -> Result<ExecutionId, HeaderError>;
     fn execute_transactions(
@@ -44,17 +50,40 @@ pub trait BlockExecutor: Send + Sync {
         storage: &mut dyn StorageAccess,
         transactions: &[Transaction],
     ) -> Result<Vec<TransactionOutcome>, ()>;
+    /// Executes the transactions to include in the block being built.
+    /// When `order_seed` is given, the chosen transactions are executed in the order of `TxOrdering::seeded_key`.
     fn prepare_block<'a>(
         &self,
         execution_id: ExecutionId,
         storage: &mut dyn StorageAccess,
         transactions: &mut dyn Iterator<Item = &'a TransactionWithMetadata>,
+        max_body_size: usize,
+        order_seed: Option<H256>,
     ) -> Vec<(&'a Transaction, TransactionOutcome)>;
+    /// Checks and executes a transaction in an opened block, leaving the block as it was.
+    fn preview_transaction(
+        &self,
+        execution_id: ExecutionId,
+        storage: &mut dyn StorageAccess,
+        transaction: &Transaction,
+    ) -> TransactionPreview;
     fn close_block(&self, execution_id: ExecutionId) -> Result<BlockOutcome, CloseBlockError>;
 }
 
 pub trait TxFilter: Send + Sync {
     fn check_transaction(&self, transaction: &Transaction) -> Result<(), ErrorCode>;
+    /// Whether the transaction is of a type the modules issue for the chain itself, e.g. an auto-action.
+    /// Only the node itself may issue them.
+    fn is_system_transaction(&self, transaction: &Transaction) -> bool;
+    /// Whether the transaction is of a type the `TxSorter` last tagged as high priority.
+    /// Such transactions are listed ahead of the others.
+    fn is_priority_transaction(&self, _transaction: &Transaction) -> bool {
+        false
+    }
+    /// The expiry and the nonce protecting the transaction from replays, if its type doesn't use a sequence.
+    fn replay_tag(&self, _transaction: &Transaction) -> Option<ReplayTag> {
+        None
+    }
     fn filter_transactions<'a>(
         &self,
         storage: &mut dyn StorageAccess,
@@ -62,12 +91,50 @@ pub trait TxFilter: Send + Sync {
         memory_limit: Option<usize>,
         size_limit: Option<usize>,
     ) -> FilteredTxs<'a>;
+    /// Splits the transactions into the ones to keep and the ones their modules vetoed in the given state.
+    fn remove_transactions<'a>(
+        &self,
+        _storage: &mut dyn StorageAccess,
+        transactions: &mut dyn Iterator<Item = &'a TransactionWithMetadata>,
+    ) -> VetoedTxs<'a> {
+        VetoedTxs {
+            kept: transactions.map(|tx| &tx.tx).collect(),
+            dropped: Vec::new(),
+        }
+    }
+    /// Orders the transactions as a block would include them, e.g. by their fees, leaving out the invalid ones.
+    fn prioritize_transactions<'a>(
+        &self,
+        _storage: &mut dyn StorageAccess,
+        transactions: &mut dyn Iterator<Item = &'a TransactionWithMetadata>,
+    ) -> Vec<&'a Transaction> {
+        transactions.map(|tx| &tx.tx).collect()
+    }
+    /// Splits the bytes of the transaction into the witness and the payload.
+    fn transaction_sizes(&self, transaction: &Transaction) -> TxSizes {
+        TxSizes::new(transaction, 0)
+    }
+    /// Decodes the payload of the transaction, if a module inspects the transactions of its type.
+    fn inspect_transaction(&self, _transaction: &Transaction) -> Option<Result<InspectedPayload, String>> {
+        None
+    }
+    /// The signer, the fee and the sequence of the transaction, by which the pending transactions are queried.
+    fn sorting_metadata(&self, _transaction: &Transaction) -> TxSortingMetadata {
+        TxSortingMetadata::default()
+    }
+}
+
+pub trait NetworkIdentityProvider: Send + Sync {
+    /// Returns the network identities of the validators registered in the given state.
+    fn network_identities(&self, storage: &mut dyn StorageAccess) -> Vec<NetworkIdentity>;
 }
 
 pub trait GraphQlHandlerProvider: Send + Sync {
     /// Returns list of (module name, module graphql handler).
     fn get(&self) -> Vec<(String, Arc<dyn super::module::HandleGraphQlRequest>)>;
 
+    /// Opens a session on read-only views of `storage`, which may be the state of any retained block.
+    /// The writes of the modules in the session are dropped.
     fn new_session_for_query(&self, storage: &mut dyn StorageAccess) -> crate::module::SessionId;
     fn end_session_for_query(&self, session: crate::module::SessionId);
 }
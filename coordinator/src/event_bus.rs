@@ -0,0 +1,191 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::app_desc::{AppDesc, MODULE_DELIMITER};
+use crate::module::{HandleEvent, SessionId};
+use crate::types::Event;
+use std::collections::{BTreeSet, HashMap};
+
+pub(crate) const HANDLE_EVENT: &str = "handle-event";
+
+/// Routes the events published by the owner modules of transactions to the subscribers.
+///
+/// An event is published on the topic `<owner>/<event-key>` only if the owner module declares
+/// the event key in its `publishes`. Subscribers of a topic receive events in the module name order,
+/// so every node delivers them in the same order.
+#[derive(Default)]
+pub(crate) struct EventBus {
+    /// A map from Tx type to the module publishing the events of its transactions.
+    publishers: HashMap<String, String>,
+    /// A map from topic to the names of the subscribing modules.
+    subscribers: HashMap<String, Vec<String>>,
+}
+
+impl EventBus {
+    pub fn new(app_desc: &AppDesc) -> Self {
+        let mut subscribers: HashMap<String, BTreeSet<String>> = HashMap::new();
+        for (module, setup) in app_desc.modules.iter() {
+            for topic in setup.subscribes.iter() {
+                subscribers.entry((**topic).clone()).or_default().insert((**module).clone());
+            }
+        }
+
+        let publishers = app_desc
+            .transactions
+            .iter()
+            .filter(|(_, owner)| {
+                app_desc.modules.get(owner.as_str()).map_or(false, |setup| !setup.publishes.is_empty())
+            })
+            .map(|(tx_type, owner)| (tx_type.clone(), (**owner).clone()))
+            .collect();
+
+        EventBus {
+            publishers,
+            subscribers: subscribers
+                .into_iter()
+                .map(|(topic, modules)| (topic, modules.into_iter().collect()))
+                .collect(),
+        }
+    }
+
+    /// Delivers the events emitted by a transaction of `tx_type` in the order they were emitted.
+    pub fn publish(
+        &self,
+        handlers: &HashMap<String, Box<dyn HandleEvent>>,
+        session_id: SessionId,
+        tx_type: &str,
+        events: &[Event],
+    ) {
+        let publisher = match self.publishers.get(tx_type) {
+            Some(publisher) => publisher,
+            None => return,
+        };
+        for event in events {
+            let topic = format!("{}{}{}", publisher, MODULE_DELIMITER, event.key);
+            for subscriber in self.subscribers.get(&topic).into_iter().flatten() {
+                if let Some(handler) = handlers.get(subscriber) {
+                    handler.handle_event(session_id, &topic, event);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+    use remote_trait_object::Service;
+    use std::sync::Arc;
+    use unindent::unindent;
+
+    type Deliveries = Arc<Mutex<Vec<(String, String, String)>>>;
+
+    struct Recorder {
+        module: String,
+        deliveries: Deliveries,
+    }
+
+    impl Service for Recorder {}
+
+    impl HandleEvent for Recorder {
+        fn handle_event(&self, _session_id: SessionId, topic: &str, event: &Event) {
+            self.deliveries.lock().push((self.module.clone(), topic.to_owned(), event.key.clone()));
+        }
+    }
+
+    fn app_desc() -> AppDesc {
+        let source = unindent(
+            r#"
+            default-sandboxer: single-process
+            modules:
+                token:
+                    hash: 1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef
+                    publishes:
+                        - transfer
+                        - burn
+                staking:
+                    hash: 1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef
+                    exports:
+                        handle-event:
+                            handle-event: {}
+                    subscribes:
+                        - token/transfer
+                        - token/burn
+                auditor:
+                    hash: 1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef
+                    exports:
+                        handle-event:
+                            handle-event: {}
+                    subscribes:
+                        - token/transfer
+            transactions:
+                transfer: token
+                stamp: auditor
+        "#,
+        );
+        AppDesc::from_str(&source).unwrap()
+    }
+
+    fn handlers(deliveries: &Deliveries) -> HashMap<String, Box<dyn HandleEvent>> {
+        ["staking", "auditor"]
+            .iter()
+            .map(|module| {
+                let recorder = Recorder {
+                    module: (*module).to_owned(),
+                    deliveries: Arc::clone(deliveries),
+                };
+                ((*module).to_owned(), Box::new(recorder) as Box<dyn HandleEvent>)
+            })
+            .collect()
+    }
+
+    fn event(key: &str) -> Event {
+        Event {
+            key: key.to_owned(),
+            value: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn events_are_delivered_in_emission_and_subscriber_order() {
+        let deliveries = Deliveries::default();
+        let bus = EventBus::new(&app_desc());
+
+        bus.publish(&handlers(&deliveries), 0, "transfer", &[event("burn"), event("transfer"), event("unknown")]);
+
+        let expected: Vec<_> = vec![
+            ("staking", "token/burn", "burn"),
+            ("auditor", "token/transfer", "transfer"),
+            ("staking", "token/transfer", "transfer"),
+        ]
+        .into_iter()
+        .map(|(module, topic, key)| (module.to_owned(), topic.to_owned(), key.to_owned()))
+        .collect();
+        assert_eq!(*deliveries.lock(), expected);
+    }
+
+    #[test]
+    fn events_of_modules_not_publishing_are_not_delivered() {
+        let deliveries = Deliveries::default();
+        let bus = EventBus::new(&app_desc());
+
+        bus.publish(&handlers(&deliveries), 0, "stamp", &[event("transfer")]);
+        bus.publish(&handlers(&deliveries), 0, "unknown", &[event("transfer")]);
+
+        assert!(deliveries.lock().is_empty());
+    }
+}
@@ -17,44 +17,65 @@
 mod app_desc;
 pub mod context;
 pub mod engine;
+mod event_bus;
 mod header;
 mod linkable;
 pub mod module;
+mod parallel;
+pub mod replay;
+mod spans;
 pub mod test_coordinator;
 mod transaction;
 pub mod types;
 pub mod values;
 mod weaver;
 
+use crate::app_desc::config_schema::check_config;
 pub use crate::app_desc::AppDesc;
-use crate::context::StorageAccess;
-use crate::engine::{BlockExecutor, ExecutionId, GraphQlHandlerProvider, Initializer, TxFilter};
+use crate::context::{
+    BlockOverlay, BlockRandomness, BlockTime, GasMeter, GasMeters, MeteredSubStorage, RandomnessAccess,
+    ReadOnlyStorage, StorageAccess, SubStorageAccess, TimeOracle,
+};
+use crate::engine::{
+    BlockExecutor, ExecutionId, GraphQlHandlerProvider, Initializer, NetworkIdentityProvider, TxFilter,
+};
+use crate::event_bus::EventBus;
 pub use crate::header::Header;
 use crate::module::{
-    HandleCrimes, HandleGraphQlRequest, InitChain, InitGenesis, SessionId, SortedTxs, Stateful, TxOwner, TxSorter,
-    UpdateChain,
+    AnchorBlock, HandleCrimes, HandleEvent, HandleGraphQlRequest, InitChain, InitGenesis, ProvideModuleConfigs,
+    ProvideNetworkIdentities, ReplayGuard, SessionId, SortedTxs, Stateful, TxInspector, TxOwner, TxSorter,
+    TxSortingMetadata, TxVeto, UpdateChain, UpdateConfig, UseRandomness, UseTimeOracle,
+};
+use crate::replay::ReplayTag;
+pub use crate::spans::{pending_module_calls, PendingModuleCall};
+pub use crate::transaction::{
+    PoolTransaction, Transaction, TransactionWithMetadata, TxOrigin, TxSizes, COMPOSITE_TX_TYPE,
 };
-pub use crate::transaction::{Transaction, TransactionWithMetadata, TxOrigin};
 use crate::types::{
-    BlockOutcome, CloseBlockError, ErrorCode, ExecuteTransactionError, FilteredTxs, HeaderError, TransactionOutcome,
-    VerifiedCrime,
+    BlockOutcome, CloseBlockError, ErrorCode, Event, ExecuteTransactionError, FilteredTxs, HeaderError,
+    InspectedPayload, TransactionOutcome, TransactionPreview, TxWithGas, TxsForBlock, VerifiedCrime, VetoedTxs,
 };
+use crate::values::Value;
 use crate::weaver::Weaver;
+use ccrypto::blake256;
 use cmodule::sandbox::Sandbox;
 use ctypes::StorageId;
-use ctypes::{CompactValidatorSet, ConsensusParams};
-use once_cell::sync::OnceCell;
+use ctypes::{CompactValidatorSet, ConsensusParams, NetworkIdentity, TxOrdering};
 use parking_lot::{Mutex, RwLock};
+use primitives::H256;
 use remote_trait_object::{Service, ServiceRef};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::mem;
 use std::ops::Bound;
 use std::ops::Bound::*;
 use std::sync::Arc;
+use tracing::Span;
 
 pub(crate) const HOST_ID: &str = "$";
 
-pub(crate) const TX_SERVICES_FOR_HOST: &[&str] = &["tx-owner"];
+pub(crate) const UPDATE_CONFIG: &str = "update-config";
+
+pub(crate) const TX_SERVICES_FOR_HOST: &[&str] = &["tx-owner", "replay-guard", "tx-veto", "tx-inspector"];
 
 pub(crate) type Occurrences = (Bound<usize>, Bound<usize>);
 
@@ -62,34 +83,96 @@ pub(crate) static SERVICES_FOR_HOST: &[(Occurrences, &str)] = &[
     ((Included(0), Unbounded), "init-genesis"),
     ((Included(1), Excluded(2)), "init-chain"),
     ((Included(0), Excluded(2)), "update-chain"),
+    ((Included(0), Excluded(2)), "anchor-block"),
+    ((Included(0), Excluded(2)), "provide-module-configs"),
+    ((Included(0), Unbounded), UPDATE_CONFIG),
     ((Included(0), Unbounded), "stateful"),
+    ((Included(0), Unbounded), "use-randomness"),
+    ((Included(0), Unbounded), "use-time-oracle"),
     ((Included(0), Excluded(2)), "tx-sorter"),
     ((Included(0), Excluded(2)), "handle-crimes"),
     ((Included(0), Unbounded), "handle-graphql-request"),
+    ((Included(0), Unbounded), event_bus::HANDLE_EVENT),
 ];
 
 type SessionSlot = u128;
 
+/// The maximum number of system transactions in a block.
+/// They are not ordered by the `TxSorter`, so they are bounded lest they crowd out the others.
+pub const MAX_SYSTEM_TXS_PER_BLOCK: usize = 32;
+
+/// The maximum number of transactions in the priority lane of a block, besides the system transactions.
+/// The lane is never evicted from the mempool, so it is bounded lest a tagged type open the mempool to a flood.
+/// The tagged transactions beyond it are ordered and evicted like the ordinary ones.
+pub const MAX_PRIORITY_TXS_PER_BLOCK: usize = 64;
+
+/// The key of the block event reporting a module config that is not applied.
+/// Its value is the CBOR-encoded pair of the module name and the reason.
+pub const CONFIG_REJECTED_EVENT: &str = "config-rejected";
+
+/// The key of the block event carrying the audit trail of the block, emitted only in the execution audit mode.
+/// Its value is the CBOR-encoded list of the state commitments: the one after opening the block,
+/// followed by one after each transaction in the order they were executed.
+pub const EXECUTION_AUDIT_EVENT: &str = "execution-audit";
+
 /// The `Coordinator` encapsulates all the logic for a Foundry application.
 ///
 /// It assembles modules and feeds them various events from the underlying
 /// consensus engine.
 pub struct Coordinator {
-    /// The maximum block size.
-    max_body_size: OnceCell<usize>,
-
     /// Currently active sessions represented as bits set.
     sessions: RwLock<Vec<SessionSlot>>,
 
     /// The key services from modules for implementing a chain.
     services: Services,
 
+    /// Routes the events of transactions to the modules subscribing to them.
+    event_bus: EventBus,
+
+    /// The number of threads executing the transactions of a block.
+    /// Transactions are executed one by one in the block session if it is 1.
+    execution_threads: usize,
+
+    /// Whether every module has opted in to executing transactions in sessions of their own.
+    parallel_execution: bool,
+
+    /// Whether a state commitment is recorded after each transaction of a block.
+    execution_audit: bool,
+
+    /// The state commitments recorded so far for each open block, if audited.
+    audit_trails: Mutex<HashMap<SessionId, Vec<H256>>>,
+
+    /// The writes of each open block, applied to the storage when the block is closed.
+    block_overlays: Mutex<HashMap<SessionId, BlockOverlay>>,
+
+    /// The context of each open block, given to the sessions executing its transactions.
+    block_contexts: Mutex<HashMap<SessionId, BlockContext>>,
+
+    /// The span of each open block, entered whenever the host works on the block.
+    block_spans: Mutex<HashMap<SessionId, Span>>,
+
+    /// Charges the storage accesses of each session to the transaction being executed in it.
+    gas_meters: Arc<GasMeters>,
+
+    /// The Tx types the sorter tagged as high priority in the last ordering it was trusted with.
+    priority_tx_types: RwLock<HashSet<String>>,
+
     /// List of `Sandbox`es of the modules constituting the current application.
+    /// A module comes before the modules it depends on, so the dependents are closed first on drop.
     _sandboxes: Vec<Box<dyn Sandbox>>,
 }
 
 const SESSION_BITS_PER_SLOT: usize = mem::size_of::<SessionSlot>() * 8;
 
+/// What the consensus decided for an open block, other than its header.
+#[derive(Clone, Copy)]
+pub(crate) struct BlockContext {
+    /// Seeds the randomness given to the modules.
+    random_seed: H256,
+    /// The median of the times the validators precommitted the parent block.
+    median_time: u64,
+}
+
 impl Coordinator {
     pub fn from_app_desc(app_desc: &AppDesc) -> anyhow::Result<Coordinator> {
         cmodule::init_modules();
@@ -102,20 +185,73 @@ impl Coordinator {
             .iter()
             .map(|(name, setup)| ((**name).clone(), serde_cbor::to_vec(&setup.genesis_config).unwrap()))
             .collect();
+        services.config_schemas = app_desc
+            .modules
+            .iter()
+            .filter(|(_, setup)| setup.config_schema != Value::Null)
+            .map(|(name, setup)| ((**name).clone(), setup.config_schema.clone()))
+            .collect();
+        services.system_tx_types = app_desc.system_transactions.iter().map(|tx_type| (**tx_type).clone()).collect();
 
         Ok(Coordinator {
             services,
+            event_bus: EventBus::new(app_desc),
+            execution_threads: 1,
+            parallel_execution: app_desc.modules.values().all(|setup| setup.parallel_execution),
+            execution_audit: false,
+            audit_trails: Default::default(),
+            block_overlays: Default::default(),
+            block_contexts: Default::default(),
+            block_spans: Default::default(),
+            gas_meters: Default::default(),
+            priority_tx_types: Default::default(),
             _sandboxes: sandboxes,
-            max_body_size: Default::default(),
             sessions: RwLock::new(vec![0]),
         })
     }
 
-    pub fn max_body_size(&self) -> usize {
-        *self.max_body_size.get().expect("the max_body_size is not set yet")
+    /// Executes the transactions of each block over the given number of threads.
+    ///
+    /// Every transaction then runs in a session of its own, which is not notified of the block
+    /// with `block_opened`. So the transactions stay executed one by one unless every module
+    /// of the application has opted in with `parallel-execution`.
+    pub fn with_execution_threads(mut self, threads: usize) -> Self {
+        if self.parallel_execution {
+            self.execution_threads = threads.max(1);
+        }
+        self
+    }
+
+    /// The number of threads executing the transactions of a block.
+    pub fn execution_threads(&self) -> usize {
+        self.execution_threads
+    }
+
+    /// Records a commitment of the state after each transaction of a block, reported as the
+    /// `EXECUTION_AUDIT_EVENT` of the block, so that nodes disagreeing on its state root can find
+    /// the first transaction they executed differently by comparing their trails.
+    ///
+    /// Each commitment chains the previous one with the hash of the transaction and the digest
+    /// of its writes, which are empty if it failed. The writes of closing the block are not covered,
+    /// so the trails agreeing on diverged state roots point at `block_closed` of the modules.
+    /// The transactions are executed one by one in the block session while audited,
+    /// whatever the number of execution threads is.
+    pub fn with_execution_audit(mut self, enabled: bool) -> Self {
+        self.execution_audit = enabled;
+        self
     }
 
     fn new_session(&self, storage: &mut dyn StorageAccess) -> SessionId {
+        self.open_session(|storage_id| storage.sub_storage(storage_id), None)
+    }
+
+    /// Opens a session on the given sub-storages.
+    /// The modules using the randomness or the time oracle get them from `block` if it's given.
+    fn open_session(
+        &self,
+        mut sub_storage: impl FnMut(StorageId) -> Box<dyn SubStorageAccess>,
+        block: Option<&BlockContext>,
+    ) -> SessionId {
         let mut sessions = self.sessions.write();
         let (index, bit) = sessions
             .iter()
@@ -135,10 +271,21 @@ impl Coordinator {
         sessions[index] |= 1 << bit;
         let session_id = bit + (SESSION_BITS_PER_SLOT * index) as SessionId;
 
+        let meter = self.gas_meters.get(session_id);
         let mut statefuls = self.services.stateful.lock();
         for (storage_id, (_, stateful)) in statefuls.iter_mut().enumerate() {
-            let sub_storage = storage.sub_storage(storage_id as StorageId);
-            stateful.new_session(session_id, ServiceRef::create_export(sub_storage));
+            let storage = MeteredSubStorage::new(sub_storage(storage_id as StorageId), Arc::clone(&meter));
+            stateful.new_session(session_id, ServiceRef::create_export(Box::new(storage) as Box<dyn SubStorageAccess>));
+        }
+        if let Some(block) = block {
+            for (_, consumer) in self.services.use_randomness.lock().iter_mut() {
+                let randomness = Box::new(BlockRandomness::new(block.random_seed)) as Box<dyn RandomnessAccess>;
+                consumer.new_session(session_id, ServiceRef::create_export(randomness));
+            }
+            for (_, consumer) in self.services.use_time_oracle.lock().iter_mut() {
+                let time_oracle = Box::new(BlockTime::new(block.median_time)) as Box<dyn TimeOracle>;
+                consumer.new_session(session_id, ServiceRef::create_export(time_oracle));
+            }
         }
 
         session_id
@@ -149,22 +296,275 @@ impl Coordinator {
         for (_, ref mut stateful) in statefuls.iter_mut() {
             stateful.end_session(session_id);
         }
+        for (_, consumer) in self.services.use_randomness.lock().iter_mut() {
+            consumer.end_session(session_id);
+        }
+        for (_, consumer) in self.services.use_time_oracle.lock().iter_mut() {
+            consumer.end_session(session_id);
+        }
+        self.gas_meters.remove(session_id);
         let mut sessions = self.sessions.write();
         let session_id = session_id as usize;
         sessions[session_id / SESSION_BITS_PER_SLOT] &= !(1 << (session_id % SESSION_BITS_PER_SLOT));
     }
 
+    /// The overlay of the block opened as `execution_id`, which shares the writes with it.
+    fn block_overlay(&self, execution_id: ExecutionId) -> Option<BlockOverlay> {
+        self.block_overlays.lock().get(&(execution_id as SessionId)).cloned()
+    }
+
+    /// Appends the commitment of `tx` to the audit trail of the block, if audited.
+    /// It must be called before the checkpoint created for the transaction is closed.
+    fn audit_transaction(&self, session_id: SessionId, tx: &Transaction, succeeded: bool) {
+        let mut trails = self.audit_trails.lock();
+        let trail = match trails.get_mut(&session_id) {
+            Some(trail) => trail,
+            None => return,
+        };
+        let writes = match self.block_overlays.lock().get(&session_id) {
+            Some(overlay) if succeeded => overlay.checkpoint_digest(),
+            _ => H256::zero(),
+        };
+        let previous = *trail.last().expect("The trail starts with the commitment of the opened block");
+        trail.push(blake256([previous.as_ref(), tx.hash().as_ref(), writes.as_ref()].concat()));
+    }
+
+    fn block_span(&self, execution_id: ExecutionId) -> Span {
+        self.block_spans.lock().get(&(execution_id as SessionId)).cloned().unwrap_or_else(Span::none)
+    }
+
+    fn is_executable(&self, tx: &Transaction) -> bool {
+        tx.is_composite() || self.services.tx_owner.contains_key(tx.tx_type())
+    }
+
+    /// Executes a transaction, running the sub-transactions of a composite transaction in order.
+    /// A composite transaction fails as a whole if any of its sub-transactions fails,
+    /// so the caller must revert the changes made by it on failure.
+    ///
+    /// The events of each (sub-)transaction are delivered to the subscribers right after it is executed,
+    /// so the changes made by the subscribers are reverted together with the transaction.
+    ///
+    /// The gas used in the outcome is the gas of the storage accesses made in the session meanwhile,
+    /// including the ones of the subscribers, counted by the host whatever the owner module reports.
+    /// It doesn't limit the transaction.
+    fn execute_transaction(&self, session_id: SessionId, tx: &Transaction) -> Result<TransactionOutcome, ()> {
+        let meter = self.gas_meters.get(session_id);
+        meter.start();
+        let result = self.execute_metered_transaction(session_id, tx, &meter);
+        meter.stop();
+        result
+    }
+
+    fn execute_metered_transaction(
+        &self,
+        session_id: SessionId,
+        tx: &Transaction,
+        meter: &GasMeter,
+    ) -> Result<TransactionOutcome, ()> {
+        let tx_owner = &self.services.tx_owner;
+        let execute = |tx: &Transaction| -> Result<TransactionOutcome, ()> {
+            let owner = tx_owner.get(tx.tx_type()).ok_or(())?;
+            let gas_before = meter.used();
+            let mut outcome = spans::in_module_call("tx-owner", "execute_transaction", tx.tx_type(), || {
+                owner.execute_transaction(session_id, tx)
+            })?;
+            self.publish_events(session_id, tx, &outcome.events);
+            outcome.gas_used = meter.used() - gas_before;
+            Ok(outcome)
+        };
+
+        let sub_transactions = match tx.sub_transactions() {
+            Some(sub_transactions) => sub_transactions,
+            None if tx.is_composite() => return Err(()),
+            None => return execute(tx),
+        };
+
+        let mut outcome = TransactionOutcome::default();
+        for sub_transaction in &sub_transactions {
+            let sub_outcome = execute(sub_transaction)?;
+            outcome.events.extend(sub_outcome.events.iter().cloned());
+            outcome.gas_used += sub_outcome.gas_used;
+            outcome.sub_outcomes.push(sub_outcome);
+        }
+        Ok(outcome)
+    }
+
+    fn publish_events(&self, session_id: SessionId, tx: &Transaction, events: &[Event]) {
+        self.event_bus.publish(&self.services.handle_event, session_id, tx.tx_type(), events);
+    }
+
+    /// Estimates the gas of a transaction with its owner module.
+    /// The estimate of a composite transaction is the sum of its sub-transactions' estimates.
+    fn estimate_gas(&self, tx: &Transaction) -> u64 {
+        let tx_owner = &self.services.tx_owner;
+        let estimate = |tx: &Transaction| tx_owner.get(tx.tx_type()).map_or(0, |owner| owner.estimate_gas(tx));
+
+        match tx.sub_transactions() {
+            Some(sub_transactions) => sub_transactions.iter().map(estimate).fold(0, u64::saturating_add),
+            None => estimate(tx),
+        }
+    }
+
+    /// Orders the candidate transactions for a block with the `TxSorter` service.
+    ///
+    /// The sorter lives in a module, so its answer is not trusted: an ordering that refers to
+    /// a transaction out of the candidates or refers to a candidate more than once is discarded,
+    /// and the candidates are taken in the given order instead.
+    ///
+    /// System transactions issued by this node itself come first in the order they were inserted,
+    /// up to `MAX_SYSTEM_TXS_PER_BLOCK`, without being given to the sorter.
+    /// The rest of them wait for the next blocks.
+    /// System transactions from peers or RPC clients are invalid.
+    ///
+    /// They are followed by the transactions of the types the sorter tags as high priority, up to
+    /// `MAX_PRIORITY_TXS_PER_BLOCK`, then the others, each in the order of the sorter. The tags of an ordering
+    /// rejected for referring to the transactions wrongly are ignored. The first two groups make the priority lane.
+    fn fetch_transactions_for_block<'a>(
+        &self,
+        session_id: SessionId,
+        txs: &[&'a TransactionWithMetadata],
+    ) -> TxsForBlock<'a> {
+        let (system_txs, txs): (Vec<&TransactionWithMetadata>, Vec<&TransactionWithMetadata>) =
+            txs.iter().copied().partition(|tx| self.is_system_transaction(&tx.tx));
+        let (mut own_system_txs, external_system_txs): (Vec<_>, Vec<_>) =
+            system_txs.into_iter().partition(|tx| tx.origin == TxOrigin::System);
+        own_system_txs.sort_by_key(|tx| tx.insertion_id);
+        own_system_txs.truncate(MAX_SYSTEM_TXS_PER_BLOCK);
+
+        let owned_txs: Vec<_> = txs.iter().map(|tx| (*tx).clone()).collect();
+
+        let sorted_txs = spans::service_call("tx-sorter", "sort_txs")
+            .in_scope(|| self.services.tx_sorter.sort_txs(session_id, &owned_txs));
+        let SortedTxs {
+            sorted,
+            invalid,
+            priority_tx_types,
+        } = if is_valid_ordering(&sorted_txs, txs.len()) {
+            sorted_txs
+        } else {
+            DefaultTxSorter.sort_txs(session_id, &owned_txs)
+        };
+        let priority_tx_types: HashSet<String> = priority_tx_types.into_iter().collect();
+        let mut priority_txs = Vec::new();
+        let mut ordinary_txs = Vec::new();
+        for i in sorted {
+            if priority_txs.len() < MAX_PRIORITY_TXS_PER_BLOCK && priority_tx_types.contains(txs[i].tx.tx_type()) {
+                priority_txs.push(i);
+            } else {
+                ordinary_txs.push(i);
+            }
+        }
+        *self.priority_tx_types.write() = priority_tx_types;
+
+        TxsForBlock {
+            priority: own_system_txs.len() + priority_txs.len(),
+            invalid: external_system_txs
+                .into_iter()
+                .map(|tx| &tx.tx)
+                .chain(invalid.into_iter().map(|i| &txs[i].tx))
+                .collect(),
+            sorted: own_system_txs
+                .into_iter()
+                .map(|tx| &tx.tx)
+                .chain(priority_txs.into_iter().chain(ordinary_txs).map(|i| &txs[i].tx))
+                .map(|tx| TxWithGas {
+                    tx,
+                    gas_estimate: self.estimate_gas(tx),
+                })
+                .collect(),
+        }
+    }
+
+    /// Delivers the module configs updated by the governance to the modules.
+    ///
+    /// A config is rejected when it does not conform to the config schema of the module,
+    /// or the module fails to apply it. The module then keeps its config, and the changes
+    /// it made to the storage while applying the config are reverted.
+    /// Each rejection is reported as a `CONFIG_REJECTED_EVENT` of the block.
+    fn update_module_configs(&self, session_id: SessionId, storage: &mut dyn StorageAccess) -> Vec<Event> {
+        let mut rejections = Vec::new();
+        let updated_configs = spans::service_call("provide-module-configs", "updated_module_configs")
+            .in_scope(|| self.services.provide_module_configs.updated_module_configs(session_id));
+        for (module, config) in updated_configs {
+            let result = self.config_updater(&module, &config).and_then(|updater| {
+                storage.create_checkpoint();
+                let result = spans::in_module_call(UPDATE_CONFIG, "update_config", &module, || {
+                    updater.update_config(session_id, &config)
+                });
+                if result.is_ok() {
+                    storage.discard_checkpoint();
+                } else {
+                    storage.revert_to_the_checkpoint();
+                }
+                result
+            });
+            if let Err(reason) = result {
+                rejections.push(Event {
+                    key: CONFIG_REJECTED_EVENT.to_owned(),
+                    value: serde_cbor::to_vec(&(module, reason)).unwrap(),
+                });
+            }
+        }
+        rejections
+    }
+
+    /// Returns the `UpdateConfig` service of the module if the config conforms to its schema.
+    fn config_updater(&self, module: &str, config: &[u8]) -> Result<&dyn UpdateConfig, String> {
+        let services = &self.services;
+        let updater = services.update_config.get(module).ok_or_else(|| format!("'{}' takes no configs", module))?;
+        let schema = services.config_schemas.get(module).ok_or_else(|| format!("'{}' has no config schema", module))?;
+        let config: Value = serde_cbor::from_slice(config).map_err(|err| format!("Malformed config: {}", err))?;
+        check_config(schema, &config)?;
+        Ok(&**updater)
+    }
+
     pub fn services(&self) -> &Services {
         &self.services
     }
 }
 
+/// Takes the transactions fitting in the block in the sorted order, then puts them in the order of their seeded keys.
+///
+/// The proposer chooses the transactions by their priority, and their hashes decide where they are executed.
+/// It's not a defense against front-running, as `TxOrdering::Seeded` explains.
+/// A transaction executed before the one it depends on fails and is left for a later block.
+fn reorder_by_seed<'a>(sorted: Vec<TxWithGas<'a>>, seed: &H256, max_body_size: usize) -> Vec<TxWithGas<'a>> {
+    let mut remaining_block_space = max_body_size;
+    let mut chosen = Vec::new();
+    for tx_with_gas in sorted {
+        let size = tx_with_gas.tx.size();
+        if remaining_block_space < size {
+            break
+        }
+        remaining_block_space -= size;
+        chosen.push(tx_with_gas);
+    }
+    chosen.sort_by_cached_key(|tx_with_gas| TxOrdering::seeded_key(seed, &tx_with_gas.tx.hash()));
+    chosen
+}
+
+/// Checks that every index refers to one of the transactions, and no transaction is referred twice.
+fn is_valid_ordering(sorted_txs: &SortedTxs, number_of_txs: usize) -> bool {
+    let mut referred = vec![false; number_of_txs];
+    sorted_txs
+        .sorted
+        .iter()
+        .chain(sorted_txs.invalid.iter())
+        .all(|&index| index < number_of_txs && !mem::replace(&mut referred[index], true))
+}
+
 pub struct Services {
     /// List of module name and `Stateful` service pairs in the current app.
     /// The module name is used to keep the index of the corresponding `Stateful`
     /// same across updates, since the index is used as `StorageId`.
     pub stateful: Mutex<Vec<(String, Box<dyn Stateful>)>>,
 
+    /// List of module name and `UseRandomness` service pairs in the current app.
+    pub use_randomness: Mutex<Vec<(String, Box<dyn UseRandomness>)>>,
+
+    /// List of module name and `UseTimeOracle` service pairs in the current app.
+    pub use_time_oracle: Mutex<Vec<(String, Box<dyn UseTimeOracle>)>>,
+
     /// List of module name and its `InitGenesis` pairs.
     pub init_genesis: Vec<(String, Box<dyn InitGenesis>)>,
 
@@ -174,6 +574,18 @@ pub struct Services {
     /// A map from Tx type to its owner.
     pub tx_owner: HashMap<String, Box<dyn TxOwner>>,
 
+    /// A map from Tx type to its `ReplayGuard`, for the types protected by an expiry and a nonce.
+    pub replay_guard: HashMap<String, Box<dyn ReplayGuard>>,
+
+    /// A map from Tx type to its `TxVeto`, for the types whose transactions may become invalid for good.
+    pub tx_veto: HashMap<String, Box<dyn TxVeto>>,
+
+    /// A map from Tx type to its `TxInspector`, for the types whose payloads the RPC can show decoded.
+    pub tx_inspector: HashMap<String, Box<dyn TxInspector>>,
+
+    /// The Tx types of system transactions, which skip the `TxSorter`.
+    pub system_tx_types: HashSet<String>,
+
     /// An optional crime handler.
     pub handle_crimes: Box<dyn HandleCrimes>,
 
@@ -183,25 +595,55 @@ pub struct Services {
     /// A service responsible for updating the validators and the parameters when closing every block.
     pub update_chain: Box<dyn UpdateChain>,
 
+    /// A service providing the external anchor when closing every block.
+    pub anchor_block: Box<dyn AnchorBlock>,
+
+    /// A service providing the module configs changed by the governance when closing every block.
+    pub provide_module_configs: Box<dyn ProvideModuleConfigs>,
+
+    /// A map from module name to its `UpdateConfig` service.
+    pub update_config: HashMap<String, Box<dyn UpdateConfig>>,
+
+    /// Per-module schema of the configs given to its `UpdateConfig` service.
+    pub config_schemas: HashMap<String, Value>,
+
+    /// A service providing the network identities of the validators.
+    pub provide_network_identities: Box<dyn ProvideNetworkIdentities>,
+
     /// A service sorting Tx'es in the mempool.
     pub tx_sorter: Box<dyn TxSorter>,
 
     /// A map from module name to its GraphQL handler
     pub handle_graphqls: Vec<(String, Arc<dyn HandleGraphQlRequest>)>,
+
+    /// A map from module name to its handler of the events it subscribes to.
+    pub handle_event: HashMap<String, Box<dyn HandleEvent>>,
 }
 
 impl Default for Services {
     fn default() -> Self {
         Self {
             stateful: Mutex::new(Vec::new()),
+            use_randomness: Mutex::new(Vec::new()),
+            use_time_oracle: Mutex::new(Vec::new()),
             init_genesis: Vec::new(),
             genesis_config: Default::default(),
             tx_owner: Default::default(),
+            replay_guard: Default::default(),
+            tx_veto: Default::default(),
+            tx_inspector: Default::default(),
+            system_tx_types: Default::default(),
             handle_crimes: Box::new(NoOpHandleCrimes) as Box<dyn HandleCrimes>,
             init_chain: Box::new(PanickingInitChain) as Box<dyn InitChain>,
             update_chain: Box::new(NoOpUpdateChain) as Box<dyn UpdateChain>,
+            anchor_block: Box::new(NoAnchorBlock) as Box<dyn AnchorBlock>,
+            provide_module_configs: Box::new(NoModuleConfigs) as Box<dyn ProvideModuleConfigs>,
+            update_config: Default::default(),
+            config_schemas: Default::default(),
+            provide_network_identities: Box::new(NoNetworkIdentities) as Box<dyn ProvideNetworkIdentities>,
             tx_sorter: Box::new(DefaultTxSorter) as Box<dyn TxSorter>,
             handle_graphqls: Default::default(),
+            handle_event: Default::default(),
         }
     }
 }
@@ -234,6 +676,36 @@ impl UpdateChain for NoOpUpdateChain {
     }
 }
 
+struct NoAnchorBlock;
+
+impl Service for NoAnchorBlock {}
+
+impl AnchorBlock for NoAnchorBlock {
+    fn external_anchor(&self, _session_id: SessionId) -> Option<H256> {
+        None
+    }
+}
+
+struct NoModuleConfigs;
+
+impl Service for NoModuleConfigs {}
+
+impl ProvideModuleConfigs for NoModuleConfigs {
+    fn updated_module_configs(&self, _session_id: SessionId) -> Vec<(String, Vec<u8>)> {
+        Vec::new()
+    }
+}
+
+struct NoNetworkIdentities;
+
+impl Service for NoNetworkIdentities {}
+
+impl ProvideNetworkIdentities for NoNetworkIdentities {
+    fn network_identities(&self, _session_id: SessionId) -> Vec<NetworkIdentity> {
+        Vec::new()
+    }
+}
+
 struct DefaultTxSorter;
 
 impl Service for DefaultTxSorter {}
@@ -243,8 +715,13 @@ impl TxSorter for DefaultTxSorter {
         SortedTxs {
             invalid: Vec::new(),
             sorted: (0..txs.len()).collect(),
+            priority_tx_types: Vec::new(),
         }
     }
+
+    fn sorting_metadata(&self, _tx: &Transaction) -> TxSortingMetadata {
+        TxSortingMetadata::default()
+    }
 }
 
 impl Initializer for Coordinator {
@@ -266,7 +743,6 @@ impl Initializer for Coordinator {
 
         let (validator_set, params) = services.init_chain.init_chain(session_id);
 
-        self.max_body_size.set(params.max_body_size() as usize).expect("this must be the first assignment");
         self.end_session(session_id);
 
         (validator_set, params)
@@ -278,18 +754,34 @@ impl BlockExecutor for Coordinator {
         &self,
         storage: &mut dyn StorageAccess,
         header: &Header,
+        random_seed: &H256,
+        median_time: u64,
         verified_crimes: &[VerifiedCrime],
     ) -> Result<ExecutionId, HeaderError> {
         let services = &self.services;
+        let span = spans::block_execution(header.number());
+        let _entered = span.enter();
 
-        let session_id = self.new_session(storage);
+        let block = BlockContext {
+            random_seed: *random_seed,
+            median_time,
+        };
+        let overlay = BlockOverlay::new(storage, services.stateful.lock().len());
+        let session_id = self.open_session(|storage_id| overlay.clone().sub_storage(storage_id), Some(&block));
 
-        services.handle_crimes.handle_crimes(session_id, verified_crimes);
+        spans::service_call("handle-crimes", "handle_crimes")
+            .in_scope(|| services.handle_crimes.handle_crimes(session_id, verified_crimes));
 
-        for owner in services.tx_owner.values() {
-            owner.block_opened(session_id, header)?;
+        for (tx_type, owner) in services.tx_owner.iter() {
+            spans::in_module_call("tx-owner", "block_opened", tx_type, || owner.block_opened(session_id, header))?;
         }
 
+        if self.execution_audit {
+            self.audit_trails.lock().insert(session_id, vec![overlay.checkpoint_digest()]);
+        }
+        self.block_overlays.lock().insert(session_id, overlay);
+        self.block_contexts.lock().insert(session_id, block);
+        self.block_spans.lock().insert(session_id, span.clone());
         Ok(session_id)
     }
 
@@ -299,24 +791,38 @@ impl BlockExecutor for Coordinator {
         storage: &mut dyn StorageAccess,
         transactions: &[Transaction],
     ) -> Result<Vec<TransactionOutcome>, ExecuteTransactionError> {
-        let services = &self.services;
+        let span = self.block_span(execution_id);
+        let _entered = span.enter();
+        let mut overlay = self.block_overlay(execution_id);
+        let storage: &mut dyn StorageAccess = match overlay {
+            Some(ref mut overlay) => overlay,
+            None => storage,
+        };
+        if self.execution_threads > 1 && !self.execution_audit {
+            let block = self.block_contexts.lock().get(&(execution_id as SessionId)).copied();
+            return Ok(self.execute_transactions_in_parallel(storage, transactions, block.as_ref()))
+        }
 
         let mut outcomes = Vec::with_capacity(transactions.len());
         let session_id = execution_id as SessionId;
 
         for tx in transactions {
-            match services.tx_owner.get(tx.tx_type()) {
-                Some(owner) => {
-                    storage.create_checkpoint();
-                    match owner.execute_transaction(session_id, tx) {
-                        Ok(outcome) => {
-                            outcomes.push(outcome);
-                            storage.discard_checkpoint();
-                        }
-                        Err(_) => storage.revert_to_the_checkpoint(),
+            if self.is_executable(tx) {
+                storage.create_checkpoint();
+                match self.execute_transaction(session_id, tx) {
+                    Ok(outcome) => {
+                        outcomes.push(outcome);
+                        self.audit_transaction(session_id, tx, true);
+                        storage.discard_checkpoint();
+                    }
+                    Err(_) => {
+                        self.audit_transaction(session_id, tx, false);
+                        storage.revert_to_the_checkpoint();
                     }
                 }
-                None => outcomes.push(TransactionOutcome::default()),
+            } else {
+                self.audit_transaction(session_id, tx, false);
+                outcomes.push(TransactionOutcome::default());
             }
         }
 
@@ -328,29 +834,44 @@ impl BlockExecutor for Coordinator {
         execution_id: ExecutionId,
         storage: &mut dyn StorageAccess,
         transactions: &mut dyn Iterator<Item = &'a TransactionWithMetadata>,
+        max_body_size: usize,
+        order_seed: Option<H256>,
     ) -> Vec<(&'a Transaction, TransactionOutcome)> {
-        let services = &self.services;
-
+        let span = self.block_span(execution_id);
+        let _entered = span.enter();
         let txs: Vec<_> = transactions.collect();
-        let owned_txs: Vec<_> = txs.iter().map(|tx| (*tx).clone()).collect();
         let session_id = execution_id as SessionId;
+        let mut overlay = self.block_overlay(execution_id);
+        let storage: &mut dyn StorageAccess = match overlay {
+            Some(ref mut overlay) => overlay,
+            None => storage,
+        };
 
-        let SortedTxs {
+        let TxsForBlock {
             sorted,
             ..
-        } = services.tx_sorter.sort_txs(session_id, &owned_txs);
+        } = self.fetch_transactions_for_block(session_id, &txs);
+        let sorted = match order_seed {
+            Some(seed) => reorder_by_seed(sorted, &seed, max_body_size),
+            None => sorted,
+        };
 
         let mut tx_n_outcomes: Vec<(&'a Transaction, TransactionOutcome)> = Vec::new();
-        let mut remaining_block_space = self.max_body_size();
+        let mut remaining_block_space = max_body_size;
 
-        for index in sorted {
-            let tx = &txs[index].tx;
-            if let Some(owner) = services.tx_owner.get(tx.tx_type()) {
-                if remaining_block_space <= tx.size() {
+        for TxWithGas {
+            tx,
+            ..
+        } in sorted
+        {
+            if self.is_executable(tx) {
+                if remaining_block_space < tx.size() {
                     break
                 }
                 storage.create_checkpoint();
-                if let Ok(outcome) = owner.execute_transaction(session_id, &tx) {
+                if let Ok(outcome) = self.execute_transaction(session_id, tx) {
+                    // Only the transactions put in the block are audited, as the verifiers execute them alone.
+                    self.audit_transaction(session_id, tx, true);
                     storage.discard_checkpoint();
                     tx_n_outcomes.push((tx, outcome));
                     remaining_block_space -= tx.size();
@@ -362,21 +883,76 @@ impl BlockExecutor for Coordinator {
         tx_n_outcomes
     }
 
+    fn preview_transaction(
+        &self,
+        execution_id: ExecutionId,
+        storage: &mut dyn StorageAccess,
+        tx: &Transaction,
+    ) -> TransactionPreview {
+        if let Err(code) = self.check_transaction(tx) {
+            return TransactionPreview::Rejected(code)
+        }
+
+        let gas_estimate = self.estimate_gas(tx);
+        let mut overlay = self.block_overlay(execution_id);
+        let storage: &mut dyn StorageAccess = match overlay {
+            Some(ref mut overlay) => overlay,
+            None => storage,
+        };
+        storage.create_checkpoint();
+        let result = self.execute_transaction(execution_id as SessionId, tx);
+        storage.revert_to_the_checkpoint();
+
+        match result {
+            Ok(outcome) => TransactionPreview::Executed {
+                gas_estimate,
+                outcome,
+            },
+            Err(()) => TransactionPreview::Failed {
+                gas_estimate,
+            },
+        }
+    }
+
     fn close_block(&self, execution_id: ExecutionId) -> Result<BlockOutcome, CloseBlockError> {
         let services = &self.services;
 
         let session_id = execution_id as SessionId;
+        // The span of the block ends when it is dropped at the end of this function.
+        let span = self.block_spans.lock().remove(&session_id).unwrap_or_else(Span::none);
+        let _entered = span.enter();
+        // Dropping the overlay on an error reverts the block.
+        let mut overlay = self.block_overlays.lock().remove(&session_id);
+        self.block_contexts.lock().remove(&session_id);
+        let audit_trail = self.audit_trails.lock().remove(&session_id);
         let mut events = Vec::new();
-        for owner in services.tx_owner.values() {
-            events.extend(owner.block_closed(session_id)?.into_iter());
+        for (tx_type, owner) in services.tx_owner.iter() {
+            let closed = spans::in_module_call("tx-owner", "block_closed", tx_type, || owner.block_closed(session_id));
+            events.extend(closed?.into_iter());
+        }
+        let (updated_validator_set, updated_consensus_params) = spans::service_call("update-chain", "update_chain")
+            .in_scope(|| services.update_chain.update_chain(session_id));
+        let external_anchor = spans::service_call("anchor-block", "external_anchor")
+            .in_scope(|| services.anchor_block.external_anchor(session_id));
+        if let Some(overlay) = overlay.as_mut() {
+            events.extend(self.update_module_configs(session_id, overlay));
+        }
+        if let Some(audit_trail) = audit_trail {
+            events.push(Event {
+                key: EXECUTION_AUDIT_EVENT.to_owned(),
+                value: serde_cbor::to_vec(&audit_trail).unwrap(),
+            });
         }
-        let (updated_validator_set, updated_consensus_params) = services.update_chain.update_chain(session_id);
 
+        if let Some(overlay) = overlay {
+            overlay.commit();
+        }
         self.end_session(session_id);
 
         Ok(BlockOutcome {
             updated_validator_set,
             updated_consensus_params,
+            external_anchor,
             events,
         })
     }
@@ -386,13 +962,69 @@ impl TxFilter for Coordinator {
     fn check_transaction(&self, tx: &Transaction) -> Result<(), ErrorCode> {
         let services = &self.services;
 
+        if tx.is_composite() {
+            // FIXME: proper error code management is required
+            let sub_transactions = tx.sub_transactions().ok_or(ErrorCode::MAX)?;
+            return sub_transactions.iter().try_for_each(|sub_transaction| self.check_transaction(sub_transaction))
+        }
+
         match services.tx_owner.get(tx.tx_type()) {
-            Some(owner) => owner.check_transaction(tx),
+            Some(owner) => {
+                spans::in_module_call("tx-owner", "check_transaction", tx.tx_type(), || owner.check_transaction(tx))
+            }
             // FIXME: proper error code management is required
             None => Err(ErrorCode::MAX),
         }
     }
 
+    fn is_system_transaction(&self, tx: &Transaction) -> bool {
+        // The composite type has no owner, so it can't be a system transaction type.
+        self.services.system_tx_types.contains(tx.tx_type())
+    }
+
+    fn is_priority_transaction(&self, tx: &Transaction) -> bool {
+        self.priority_tx_types.read().contains(tx.tx_type())
+    }
+
+    fn replay_tag(&self, tx: &Transaction) -> Option<ReplayTag> {
+        let guard = self.services.replay_guard.get(tx.tx_type())?;
+        spans::in_module_call("replay-guard", "replay_tag", tx.tx_type(), || guard.replay_tag(tx))
+    }
+
+    /// Splits a transaction with its owner module. A transaction of an unknown type has no witness in its body.
+    /// The payload of a composite transaction is the sum of its sub-transactions' payloads,
+    /// and the rest, including their witnesses, is the witness.
+    fn transaction_sizes(&self, tx: &Transaction) -> TxSizes {
+        let tx_owner = &self.services.tx_owner;
+        let split =
+            |tx: &Transaction| TxSizes::new(tx, tx_owner.get(tx.tx_type()).map_or(0, |owner| owner.witness_size(tx)));
+
+        match tx.sub_transactions() {
+            Some(sub_transactions) => {
+                let payload = sub_transactions.iter().map(|tx| split(tx).payload).sum();
+                TxSizes {
+                    witness: tx.size() - payload,
+                    payload,
+                }
+            }
+            None => split(tx),
+        }
+    }
+
+    /// A composite transaction isn't inspected as a whole, since its sub-transactions belong to their own types.
+    fn inspect_transaction(&self, tx: &Transaction) -> Option<Result<InspectedPayload, String>> {
+        let inspector = self.services.tx_inspector.get(tx.tx_type())?;
+        Some(spans::in_module_call("tx-inspector", "inspect_transaction", tx.tx_type(), || {
+            inspector.inspect_transaction(tx)
+        }))
+    }
+
+    fn sorting_metadata(&self, tx: &Transaction) -> TxSortingMetadata {
+        spans::in_module_call("tx-sorter", "sorting_metadata", tx.tx_type(), || {
+            self.services.tx_sorter.sorting_metadata(tx)
+        })
+    }
+
     fn filter_transactions<'a>(
         &self,
         storage: &mut dyn StorageAccess,
@@ -400,17 +1032,17 @@ impl TxFilter for Coordinator {
         memory_limit: Option<usize>,
         size_limit: Option<usize>,
     ) -> FilteredTxs<'a> {
-        let services = &self.services;
-
         let txs: Vec<_> = transactions.collect();
-        let owned_txs: Vec<_> = txs.iter().map(|tx| (*tx).clone()).collect();
+        let span = spans::mem_pool_batch("filter_transactions", txs.len());
+        let _entered = span.enter();
 
         let session_id = self.new_session(storage);
 
-        let SortedTxs {
+        let TxsForBlock {
             sorted,
             invalid,
-        } = services.tx_sorter.sort_txs(session_id, &owned_txs);
+            priority,
+        } = self.fetch_transactions_for_block(session_id, &txs);
 
         let memory_limit = memory_limit.unwrap_or(usize::MAX);
         let mut memory_usage = 0;
@@ -418,16 +1050,17 @@ impl TxFilter for Coordinator {
 
         let low_priority = sorted
             .into_iter()
-            .map(|i| &txs[i].tx)
+            .map(|tx_with_gas| tx_with_gas.tx)
             .enumerate()
             .skip_while(|(i, tx)| {
                 memory_usage += (*tx).size();
                 *i >= size_limit || memory_limit >= memory_usage
             })
+            // The priority lane is kept whatever the pressure is.
+            .filter(|(i, _)| *i >= priority)
             .map(|(_, tx)| tx)
             .collect();
 
-        let invalid = invalid.into_iter().map(|i| &txs[i].tx).collect();
         self.end_session(session_id);
 
         FilteredTxs {
@@ -435,6 +1068,82 @@ impl TxFilter for Coordinator {
             low_priority,
         }
     }
+
+    /// Asks the `TxVeto` of each Tx type about its transactions.
+    /// A composite transaction is dropped when any of its sub-transactions is vetoed.
+    fn remove_transactions<'a>(
+        &self,
+        storage: &mut dyn StorageAccess,
+        transactions: &mut dyn Iterator<Item = &'a TransactionWithMetadata>,
+    ) -> VetoedTxs<'a> {
+        let txs: Vec<_> = transactions.map(|tx| &tx.tx).collect();
+        let span = spans::mem_pool_batch("remove_transactions", txs.len());
+        let _entered = span.enter();
+
+        // The transactions of each type, with the indices of the transactions they came from
+        let mut by_type: HashMap<String, (Vec<usize>, Vec<Transaction>)> = HashMap::new();
+        for (index, tx) in txs.iter().enumerate() {
+            for tx in tx.sub_transactions().unwrap_or_else(|| vec![(*tx).clone()]) {
+                if self.services.tx_veto.contains_key(tx.tx_type()) {
+                    let (indices, txs) = by_type.entry(tx.tx_type().to_owned()).or_default();
+                    indices.push(index);
+                    txs.push(tx);
+                }
+            }
+        }
+        if by_type.is_empty() {
+            return VetoedTxs {
+                kept: txs,
+                dropped: Vec::new(),
+            }
+        }
+
+        let session_id = self.new_session(storage);
+        let mut vetoed = HashSet::new();
+        for (tx_type, (indices, typed_txs)) in by_type {
+            let veto = &self.services.tx_veto[&tx_type];
+            let vetoed_indices = spans::in_module_call("tx-veto", "vetoed_transactions", &tx_type, || {
+                veto.vetoed_transactions(session_id, &typed_txs)
+            });
+            // The indices come from a module, so the ones out of the range are ignored.
+            vetoed.extend(vetoed_indices.into_iter().filter_map(|i| indices.get(i).copied()));
+        }
+        self.end_session(session_id);
+
+        let (dropped, kept): (Vec<_>, Vec<_>) = txs.into_iter().enumerate().partition(|(i, _)| vetoed.contains(i));
+        VetoedTxs {
+            kept: kept.into_iter().map(|(_, tx)| tx).collect(),
+            dropped: dropped.into_iter().map(|(_, tx)| tx).collect(),
+        }
+    }
+
+    fn prioritize_transactions<'a>(
+        &self,
+        storage: &mut dyn StorageAccess,
+        transactions: &mut dyn Iterator<Item = &'a TransactionWithMetadata>,
+    ) -> Vec<&'a Transaction> {
+        let txs: Vec<_> = transactions.collect();
+        let span = spans::mem_pool_batch("prioritize_transactions", txs.len());
+        let _entered = span.enter();
+
+        let session_id = self.new_session(storage);
+        let TxsForBlock {
+            sorted,
+            ..
+        } = self.fetch_transactions_for_block(session_id, &txs);
+        self.end_session(session_id);
+
+        sorted.into_iter().map(|tx_with_gas| tx_with_gas.tx).collect()
+    }
+}
+
+impl NetworkIdentityProvider for Coordinator {
+    fn network_identities(&self, storage: &mut dyn StorageAccess) -> Vec<NetworkIdentity> {
+        let session_id = self.new_session(storage);
+        let identities = self.services.provide_network_identities.network_identities(session_id);
+        self.end_session(session_id);
+        identities
+    }
 }
 
 impl GraphQlHandlerProvider for Coordinator {
@@ -443,10 +1152,618 @@ impl GraphQlHandlerProvider for Coordinator {
     }
 
     fn new_session_for_query(&self, storage: &mut dyn StorageAccess) -> crate::module::SessionId {
-        self.new_session(storage)
+        self.new_session(&mut ReadOnlyStorage::new(storage))
     }
 
     fn end_session_for_query(&self, session: crate::module::SessionId) {
         self.end_session(session)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::write_gas;
+    use crate::TxOrigin;
+
+    /// A sub-storage keeping nothing.
+    struct Discard;
+
+    impl Service for Discard {}
+
+    impl SubStorageAccess for Discard {
+        fn get(&self, _key: &[u8]) -> Option<Vec<u8>> {
+            None
+        }
+
+        fn set(&mut self, _key: &[u8], _value: Vec<u8>) {}
+
+        fn has(&self, _key: &[u8]) -> bool {
+            false
+        }
+
+        fn remove(&mut self, _key: &[u8]) {}
+    }
+
+    /// The gas of the writes `WritesByBodyLength` makes.
+    fn gas_of_writes(writes: u64) -> u64 {
+        writes * write_gas(b"k", &[])
+    }
+
+    /// Estimates the gas by the body length, and writes as many items as the body length multiplied by `factor`
+    /// to a sub-storage metered like the ones given to the stateful modules. A transaction with an empty body fails.
+    struct WritesByBodyLength {
+        factor: usize,
+        gas_meters: Arc<GasMeters>,
+    }
+
+    impl Service for WritesByBodyLength {}
+
+    impl TxOwner for WritesByBodyLength {
+        fn block_opened(&self, _session_id: SessionId, _header: &Header) -> Result<(), HeaderError> {
+            Ok(())
+        }
+
+        fn execute_transaction(&self, session_id: SessionId, tx: &Transaction) -> Result<TransactionOutcome, ()> {
+            if tx.body().is_empty() {
+                return Err(())
+            }
+            let mut storage = MeteredSubStorage::new(Box::new(Discard), self.gas_meters.get(session_id));
+            for _ in 0..tx.body().len() * self.factor {
+                storage.set(b"k", Vec::new());
+            }
+            Ok(Default::default())
+        }
+
+        fn check_transaction(&self, _tx: &Transaction) -> Result<(), ErrorCode> {
+            Ok(())
+        }
+
+        fn estimate_gas(&self, tx: &Transaction) -> u64 {
+            tx.body().len() as u64
+        }
+
+        /// Counts the first byte of the body as the witness.
+        fn witness_size(&self, tx: &Transaction) -> usize {
+            1.min(tx.body().len())
+        }
+
+        fn block_closed(&self, _session_id: SessionId) -> Result<Vec<Event>, CloseBlockError> {
+            Ok(Vec::new())
+        }
+    }
+
+    /// A sorter answering the given ordering whatever the transactions are.
+    struct FixedSorter {
+        sorted: Vec<usize>,
+        invalid: Vec<usize>,
+        priority_tx_types: Vec<String>,
+    }
+
+    impl Service for FixedSorter {}
+
+    impl TxSorter for FixedSorter {
+        fn sort_txs(&self, _session_id: SessionId, _txs: &[TransactionWithMetadata]) -> SortedTxs {
+            SortedTxs {
+                sorted: self.sorted.clone(),
+                invalid: self.invalid.clone(),
+                priority_tx_types: self.priority_tx_types.clone(),
+            }
+        }
+
+        fn sorting_metadata(&self, _tx: &Transaction) -> TxSortingMetadata {
+            TxSortingMetadata::default()
+        }
+    }
+
+    fn coordinator_with_sorter(sorted: Vec<usize>, invalid: Vec<usize>) -> Coordinator {
+        coordinator_with_priority_sorter(sorted, invalid, Vec::new())
+    }
+
+    fn coordinator_with_priority_sorter(
+        sorted: Vec<usize>,
+        invalid: Vec<usize>,
+        priority_tx_types: Vec<String>,
+    ) -> Coordinator {
+        let gas_meters: Arc<GasMeters> = Default::default();
+        let mut services = Services::default();
+        for (tx_type, factor) in vec![("test", 1), ("greedy", 2)] {
+            let owner = WritesByBodyLength {
+                factor,
+                gas_meters: Arc::clone(&gas_meters),
+            };
+            services.tx_owner.insert(tx_type.to_owned(), Box::new(owner) as Box<dyn TxOwner>);
+        }
+        services.tx_sorter = Box::new(FixedSorter {
+            sorted,
+            invalid,
+            priority_tx_types,
+        }) as Box<dyn TxSorter>;
+        Coordinator {
+            sessions: RwLock::new(vec![0]),
+            services,
+            event_bus: Default::default(),
+            execution_threads: 1,
+            parallel_execution: true,
+            execution_audit: false,
+            audit_trails: Default::default(),
+            block_overlays: Default::default(),
+            block_contexts: Default::default(),
+            block_spans: Default::default(),
+            gas_meters,
+            priority_tx_types: Default::default(),
+            _sandboxes: Vec::new(),
+        }
+    }
+
+    fn candidates() -> Vec<TransactionWithMetadata> {
+        (1..=3)
+            .map(|len| {
+                let tx = Transaction::new("test".to_owned(), vec![0; len]);
+                TransactionWithMetadata::new(tx, TxOrigin::External, 0, 0, len as u64, None)
+            })
+            .collect()
+    }
+
+    fn fetch(
+        coordinator: &Coordinator,
+        txs: &[TransactionWithMetadata],
+    ) -> (Vec<Transaction>, Vec<u64>, Vec<Transaction>) {
+        let txs: Vec<_> = txs.iter().collect();
+        let TxsForBlock {
+            sorted,
+            invalid,
+            ..
+        } = coordinator.fetch_transactions_for_block(0, &txs);
+        (
+            sorted.iter().map(|tx_with_gas| tx_with_gas.tx.clone()).collect(),
+            sorted.iter().map(|tx_with_gas| tx_with_gas.gas_estimate).collect(),
+            invalid.into_iter().cloned().collect(),
+        )
+    }
+
+    #[test]
+    fn sorter_ordering_is_followed() {
+        let txs = candidates();
+        let coordinator = coordinator_with_sorter(vec![2, 0], vec![1]);
+
+        let (sorted, gas_estimates, invalid) = fetch(&coordinator, &txs);
+        assert_eq!(sorted, vec![txs[2].tx.clone(), txs[0].tx.clone()]);
+        assert_eq!(gas_estimates, vec![3, 1]);
+        assert_eq!(invalid, vec![txs[1].tx.clone()]);
+    }
+
+    #[test]
+    fn invented_transactions_fall_back_to_the_default_ordering() {
+        let txs = candidates();
+        let default_ordering: Vec<_> = txs.iter().map(|tx| tx.tx.clone()).collect();
+
+        for (sorted, invalid) in vec![(vec![0, 3], vec![]), (vec![0, 1], vec![usize::MAX])] {
+            let coordinator = coordinator_with_sorter(sorted, invalid);
+            let (sorted, gas_estimates, invalid) = fetch(&coordinator, &txs);
+            assert_eq!(sorted, default_ordering);
+            assert_eq!(gas_estimates, vec![1, 2, 3]);
+            assert_eq!(invalid, vec![]);
+        }
+    }
+
+    #[test]
+    fn duplicated_transactions_fall_back_to_the_default_ordering() {
+        let txs = candidates();
+        let default_ordering: Vec<_> = txs.iter().map(|tx| tx.tx.clone()).collect();
+
+        for (sorted, invalid) in vec![(vec![1, 1, 0], vec![]), (vec![0, 2], vec![2])] {
+            let coordinator = coordinator_with_sorter(sorted, invalid);
+            let (sorted, _, invalid) = fetch(&coordinator, &txs);
+            assert_eq!(sorted, default_ordering);
+            assert_eq!(invalid, vec![]);
+        }
+    }
+
+    #[test]
+    fn seeded_ordering_reorders_the_transactions_fitting_in_the_block() {
+        let txs = candidates();
+        let coordinator = coordinator_with_sorter(vec![2, 1, 0], vec![]);
+        let refs: Vec<_> = txs.iter().collect();
+        let TxsForBlock {
+            sorted,
+            ..
+        } = coordinator.fetch_transactions_for_block(0, &refs);
+        let seed = H256::from_slice(&[7; 32]);
+        let max_body_size = txs[2].tx.size() + txs[1].tx.size();
+
+        let reordered: Vec<_> = reorder_by_seed(sorted, &seed, max_body_size)
+            .into_iter()
+            .map(|tx_with_gas| tx_with_gas.tx.clone())
+            .collect();
+        let mut expected = vec![txs[2].tx.clone(), txs[1].tx.clone()];
+        expected.sort_by_key(|tx| TxOrdering::seeded_key(&seed, &tx.hash()));
+        assert_eq!(reordered, expected);
+    }
+
+    fn system_transaction(origin: TxOrigin, insertion_id: u64) -> TransactionWithMetadata {
+        let tx = Transaction::new("system".to_owned(), insertion_id.to_be_bytes().to_vec());
+        TransactionWithMetadata::new(tx, origin, 0, 0, insertion_id, None)
+    }
+
+    fn coordinator_with_system_transactions(sorted: Vec<usize>, invalid: Vec<usize>) -> Coordinator {
+        let mut coordinator = coordinator_with_sorter(sorted, invalid);
+        let owner = WritesByBodyLength {
+            factor: 1,
+            gas_meters: Arc::clone(&coordinator.gas_meters),
+        };
+        coordinator.services.tx_owner.insert("system".to_owned(), Box::new(owner) as Box<dyn TxOwner>);
+        coordinator.services.system_tx_types.insert("system".to_owned());
+        coordinator
+    }
+
+    #[test]
+    fn own_system_transactions_skip_the_sorter() {
+        let mut txs = candidates();
+        txs.push(system_transaction(TxOrigin::System, 10));
+        txs.push(system_transaction(TxOrigin::External, 7));
+        txs.push(system_transaction(TxOrigin::System, 5));
+        // Submitted through RPC, so it is not the node's own.
+        txs.push(system_transaction(TxOrigin::Local, 6));
+        // The sorter only sees the transactions that are not system ones.
+        let coordinator = coordinator_with_system_transactions(vec![2, 0], vec![1]);
+
+        let (sorted, _, invalid) = fetch(&coordinator, &txs);
+        assert_eq!(sorted, vec![txs[5].tx.clone(), txs[3].tx.clone(), txs[2].tx.clone(), txs[0].tx.clone()]);
+        assert_eq!(invalid, vec![txs[4].tx.clone(), txs[6].tx.clone(), txs[1].tx.clone()]);
+    }
+
+    fn candidates_with_critical_transaction() -> Vec<TransactionWithMetadata> {
+        let mut txs = candidates();
+        let tx = Transaction::new("critical".to_owned(), vec![0; 4]);
+        txs.push(TransactionWithMetadata::new(tx, TxOrigin::External, 0, 0, 4, None));
+        txs
+    }
+
+    #[test]
+    fn priority_transactions_come_before_the_ordinary_ones() {
+        let txs = candidates_with_critical_transaction();
+        let coordinator = coordinator_with_priority_sorter(vec![2, 3, 0], vec![1], vec!["critical".to_owned()]);
+
+        let (sorted, _, invalid) = fetch(&coordinator, &txs);
+        assert_eq!(sorted, vec![txs[3].tx.clone(), txs[2].tx.clone(), txs[0].tx.clone()]);
+        assert_eq!(invalid, vec![txs[1].tx.clone()]);
+    }
+
+    #[test]
+    fn priority_transactions_are_not_evicted() {
+        let txs = candidates_with_critical_transaction();
+        let coordinator = coordinator_with_priority_sorter(vec![2, 3, 0], vec![1], vec!["critical".to_owned()]);
+        let mut storage = CheckpointCounter::default();
+
+        let FilteredTxs {
+            invalid,
+            low_priority,
+        } = coordinator.filter_transactions(&mut storage, &mut txs.iter(), Some(0), None);
+        assert_eq!(low_priority, vec![&txs[2].tx, &txs[0].tx]);
+        assert_eq!(invalid, vec![&txs[1].tx]);
+    }
+
+    #[test]
+    fn priority_tags_of_a_rejected_ordering_are_ignored() {
+        let txs = candidates_with_critical_transaction();
+        let coordinator = coordinator_with_priority_sorter(vec![3, 3, 0], vec![], vec!["critical".to_owned()]);
+        let refs: Vec<_> = txs.iter().collect();
+
+        let TxsForBlock {
+            sorted,
+            priority,
+            ..
+        } = coordinator.fetch_transactions_for_block(0, &refs);
+        let sorted: Vec<_> = sorted.into_iter().map(|tx_with_gas| tx_with_gas.tx.clone()).collect();
+        assert_eq!(sorted, txs.iter().map(|tx| tx.tx.clone()).collect::<Vec<_>>());
+        assert_eq!(priority, 0);
+        assert!(!coordinator.is_priority_transaction(&txs[3].tx));
+    }
+
+    #[test]
+    fn priority_lane_is_bounded() {
+        let txs: Vec<_> = (0..MAX_PRIORITY_TXS_PER_BLOCK + 2)
+            .map(|i| {
+                let tx = Transaction::new("critical".to_owned(), (i as u64).to_be_bytes().to_vec());
+                TransactionWithMetadata::new(tx, TxOrigin::External, 0, 0, i as u64, None)
+            })
+            .collect();
+        let coordinator =
+            coordinator_with_priority_sorter((0..txs.len()).rev().collect(), vec![], vec!["critical".to_owned()]);
+        let refs: Vec<_> = txs.iter().collect();
+
+        let TxsForBlock {
+            sorted,
+            priority,
+            ..
+        } = coordinator.fetch_transactions_for_block(0, &refs);
+        assert_eq!(priority, MAX_PRIORITY_TXS_PER_BLOCK);
+        assert_eq!(sorted.len(), txs.len());
+        assert!(coordinator.is_priority_transaction(&txs[0].tx));
+
+        let mut storage = CheckpointCounter::default();
+        let FilteredTxs {
+            low_priority,
+            ..
+        } = coordinator.filter_transactions(&mut storage, &mut txs.iter(), Some(0), None);
+        // The two taken last are out of the lane, so they can be evicted.
+        assert_eq!(low_priority, vec![&txs[1].tx, &txs[0].tx]);
+    }
+
+    #[test]
+    fn system_transactions_in_a_block_are_bounded() {
+        let txs: Vec<_> =
+            (0..MAX_SYSTEM_TXS_PER_BLOCK as u64 + 1).rev().map(|id| system_transaction(TxOrigin::System, id)).collect();
+        let coordinator = coordinator_with_system_transactions(vec![], vec![]);
+
+        let (sorted, _, invalid) = fetch(&coordinator, &txs);
+        let oldest: Vec<_> = txs.iter().rev().take(MAX_SYSTEM_TXS_PER_BLOCK).map(|tx| tx.tx.clone()).collect();
+        assert_eq!(sorted, oldest);
+        assert_eq!(invalid, vec![]);
+    }
+
+    #[test]
+    fn gas_of_composite_transaction_is_summed() {
+        let sub_transactions =
+            vec![Transaction::new("test".to_owned(), vec![0; 4]), Transaction::new("unknown".to_owned(), vec![0; 8])];
+        let composite = Transaction::new_composite(&sub_transactions);
+        let coordinator = coordinator_with_sorter(vec![], vec![]);
+
+        assert_eq!(coordinator.estimate_gas(&composite), 4);
+    }
+
+    #[test]
+    fn witness_of_composite_transaction_includes_the_envelopes() {
+        let sub_transactions =
+            vec![Transaction::new("test".to_owned(), vec![0; 4]), Transaction::new("unknown".to_owned(), vec![0; 8])];
+        let composite = Transaction::new_composite(&sub_transactions);
+        let coordinator = coordinator_with_sorter(vec![], vec![]);
+
+        let sizes = coordinator.transaction_sizes(&composite);
+        assert_eq!(sizes.payload, 3 + 8);
+        assert_eq!(sizes.total(), composite.size());
+    }
+
+    #[test]
+    fn gas_used_is_counted_by_the_host() {
+        let tx = Transaction::new("test".to_owned(), vec![0; 4]);
+        let coordinator = coordinator_with_sorter(vec![], vec![]);
+
+        assert_eq!(coordinator.execute_transaction(0, &tx).unwrap().gas_used, gas_of_writes(4));
+        assert_eq!(coordinator.gas_meters.get(0).used(), 0);
+    }
+
+    #[test]
+    fn outcomes_of_sub_transactions_are_kept_in_order() {
+        let sub_transactions = vec![
+            Transaction::new("test".to_owned(), vec![0; 4]),
+            Transaction::new("greedy".to_owned(), vec![0; 1]),
+            Transaction::new("test".to_owned(), vec![0; 8]),
+        ];
+        let composite = Transaction::new_composite(&sub_transactions);
+        let coordinator = coordinator_with_sorter(vec![], vec![]);
+
+        let outcome = coordinator.execute_transaction(0, &composite).unwrap();
+        let gas_used: Vec<_> = outcome.sub_outcomes.iter().map(|sub_outcome| sub_outcome.gas_used).collect();
+        assert_eq!(gas_used, vec![gas_of_writes(4), gas_of_writes(2), gas_of_writes(8)]);
+        assert_eq!(outcome.gas_used, gas_of_writes(14));
+    }
+
+    /// A storage without sub-storages, counting the checkpoints not yet reverted or discarded.
+    #[derive(Default)]
+    struct CheckpointCounter(usize);
+
+    impl StorageAccess for CheckpointCounter {
+        fn sub_storage(&mut self, _storage_id: StorageId) -> Box<dyn SubStorageAccess> {
+            unreachable!("The test coordinator has no stateful module")
+        }
+
+        fn create_checkpoint(&mut self) {
+            self.0 += 1;
+        }
+
+        fn revert_to_the_checkpoint(&mut self) {
+            self.0 -= 1;
+        }
+
+        fn discard_checkpoint(&mut self) {
+            self.0 -= 1;
+        }
+    }
+
+    #[test]
+    fn preview_leaves_the_block_as_it_was() {
+        let coordinator = coordinator_with_sorter(vec![], vec![]);
+        let mut storage = CheckpointCounter::default();
+
+        let tx = Transaction::new("test".to_owned(), vec![0; 4]);
+        match coordinator.preview_transaction(0, &mut storage, &tx) {
+            TransactionPreview::Executed {
+                gas_estimate,
+                outcome,
+            } => assert_eq!((gas_estimate, outcome.gas_used), (4, gas_of_writes(4))),
+            _ => panic!("The transaction must be executed"),
+        }
+
+        let tx = Transaction::new("test".to_owned(), Vec::new());
+        assert!(matches!(coordinator.preview_transaction(0, &mut storage, &tx), TransactionPreview::Failed {
+            gas_estimate: 0
+        }));
+
+        let tx = Transaction::new("unknown".to_owned(), vec![0; 4]);
+        assert!(matches!(coordinator.preview_transaction(0, &mut storage, &tx), TransactionPreview::Rejected(_)));
+        assert_eq!(storage.0, 0);
+    }
+
+    /// Executes the transactions in a block, answering the audit trail reported on closing it.
+    fn audit_trail(coordinator: &Coordinator, txs: &[Transaction]) -> Option<Vec<H256>> {
+        let mut storage = CheckpointCounter::default();
+        let header = Header::new(Default::default(), 0, 1, Default::default(), Vec::new(), Vec::new());
+        let execution_id = coordinator.open_block(&mut storage, &header, &H256::zero(), 0, &[]).unwrap();
+        coordinator.execute_transactions(execution_id, &mut storage, txs).unwrap();
+        let outcome = coordinator.close_block(execution_id).unwrap();
+        outcome
+            .events
+            .iter()
+            .find(|event| event.key == EXECUTION_AUDIT_EVENT)
+            .map(|event| serde_cbor::from_slice(&event.value).unwrap())
+    }
+
+    #[test]
+    fn audit_trail_commits_to_each_transaction() {
+        let txs: Vec<_> = ["test", "greedy", "unknown"]
+            .iter()
+            .map(|tx_type| Transaction::new((*tx_type).to_owned(), vec![0; 4]))
+            .collect();
+        assert_eq!(audit_trail(&coordinator_with_sorter(vec![], vec![]), &txs), None);
+
+        let coordinator = coordinator_with_sorter(vec![], vec![]).with_execution_audit(true).with_execution_threads(4);
+        let trail = audit_trail(&coordinator, &txs).unwrap();
+        assert_eq!(trail.len(), txs.len() + 1);
+        assert_eq!(audit_trail(&coordinator, &txs).unwrap(), trail);
+
+        let mut reordered = txs.clone();
+        reordered.swap(1, 2);
+        let reordered_trail = audit_trail(&coordinator, &reordered).unwrap();
+        assert_eq!(reordered_trail[..2], trail[..2]);
+        assert_ne!(reordered_trail[2], trail[2]);
+    }
+
+    struct FixedModuleConfigs(Vec<(String, Vec<u8>)>);
+
+    impl Service for FixedModuleConfigs {}
+
+    impl ProvideModuleConfigs for FixedModuleConfigs {
+        fn updated_module_configs(&self, _session_id: SessionId) -> Vec<(String, Vec<u8>)> {
+            self.0.clone()
+        }
+    }
+
+    /// Keeps the configs it's given, or rejects all of them.
+    struct ConfigHolder {
+        applied: Arc<Mutex<Vec<Vec<u8>>>>,
+        accepts: bool,
+    }
+
+    impl Service for ConfigHolder {}
+
+    impl UpdateConfig for ConfigHolder {
+        fn update_config(&self, _session_id: SessionId, config: &[u8]) -> Result<(), String> {
+            if !self.accepts {
+                return Err("rejected".to_owned())
+            }
+            self.applied.lock().push(config.to_vec());
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct RevertCounter {
+        depth: usize,
+        reverted: usize,
+    }
+
+    impl StorageAccess for RevertCounter {
+        fn sub_storage(&mut self, _storage_id: StorageId) -> Box<dyn SubStorageAccess> {
+            unreachable!("The test coordinator has no stateful module")
+        }
+
+        fn create_checkpoint(&mut self) {
+            self.depth += 1;
+        }
+
+        fn revert_to_the_checkpoint(&mut self) {
+            self.depth -= 1;
+            self.reverted += 1;
+        }
+
+        fn discard_checkpoint(&mut self) {
+            self.depth -= 1;
+        }
+    }
+
+    #[test]
+    fn only_configs_conforming_to_schema_and_accepted_are_applied() {
+        let fee_config = |fee: Value| {
+            let mut config = HashMap::new();
+            config.insert("fee".to_owned(), fee);
+            serde_cbor::to_vec(&Value::Map(config)).unwrap()
+        };
+        let schema = serde_yaml::from_str::<Value>("{ fee: int }").unwrap();
+        let applied = Arc::new(Mutex::new(Vec::new()));
+
+        let mut coordinator = coordinator_with_sorter(vec![], vec![]);
+        let services = &mut coordinator.services;
+        for (module, accepts) in &[("fee", true), ("picky", false)] {
+            services.config_schemas.insert((*module).to_owned(), schema.clone());
+            services.update_config.insert(
+                (*module).to_owned(),
+                Box::new(ConfigHolder {
+                    applied: Arc::clone(&applied),
+                    accepts: *accepts,
+                }) as Box<dyn UpdateConfig>,
+            );
+        }
+        services.provide_module_configs = Box::new(FixedModuleConfigs(vec![
+            ("fee".to_owned(), fee_config(Value::Int(10))),
+            ("fee".to_owned(), fee_config(Value::String("ten".to_owned()))),
+            ("picky".to_owned(), fee_config(Value::Int(10))),
+            ("unknown".to_owned(), fee_config(Value::Int(10))),
+        ])) as Box<dyn ProvideModuleConfigs>;
+
+        let mut storage = RevertCounter::default();
+        let rejections = coordinator.update_module_configs(0, &mut storage);
+
+        assert_eq!(*applied.lock(), vec![fee_config(Value::Int(10))]);
+        let rejected: Vec<String> = rejections
+            .iter()
+            .map(|event| {
+                assert_eq!(event.key, CONFIG_REJECTED_EVENT);
+                serde_cbor::from_slice::<(String, String)>(&event.value).unwrap().0
+            })
+            .collect();
+        assert_eq!(rejected, vec!["fee", "picky", "unknown"]);
+        assert_eq!(storage.depth, 0);
+        assert_eq!(storage.reverted, 1);
+    }
+
+    /// Vetoes the transactions with bodies longer than the given length, and refers to one more out of the range.
+    struct VetoLongerThan(usize);
+
+    impl Service for VetoLongerThan {}
+
+    impl TxVeto for VetoLongerThan {
+        fn vetoed_transactions(&self, _session_id: SessionId, transactions: &[Transaction]) -> Vec<usize> {
+            transactions
+                .iter()
+                .enumerate()
+                .filter(|(_, tx)| tx.body().len() > self.0)
+                .map(|(i, _)| i)
+                .chain(std::iter::once(transactions.len()))
+                .collect()
+        }
+    }
+
+    #[test]
+    fn transactions_vetoed_by_their_modules_are_dropped() {
+        let mut coordinator = coordinator_with_sorter(vec![], vec![]);
+        coordinator.services.tx_veto.insert("test".to_owned(), Box::new(VetoLongerThan(2)) as Box<dyn TxVeto>);
+
+        let mut txs = candidates();
+        let greedy = Transaction::new("greedy".to_owned(), vec![0; 5]);
+        let composite = Transaction::new_composite(&[Transaction::new("test".to_owned(), vec![0; 1]), greedy.clone()]);
+        let vetoed_composite =
+            Transaction::new_composite(&[Transaction::new("test".to_owned(), vec![0; 5]), greedy.clone()]);
+        for (i, tx) in vec![greedy, composite, vetoed_composite].into_iter().enumerate() {
+            txs.push(TransactionWithMetadata::new(tx, TxOrigin::External, 0, 0, 10 + i as u64, None));
+        }
+
+        let VetoedTxs {
+            kept,
+            dropped,
+        } = coordinator.remove_transactions(&mut CheckpointCounter::default(), &mut txs.iter());
+        assert_eq!(kept, vec![&txs[0].tx, &txs[1].tx, &txs[3].tx, &txs[4].tx]);
+        assert_eq!(dropped, vec![&txs[2].tx, &txs[5].tx]);
+    }
+}
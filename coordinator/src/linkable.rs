@@ -46,11 +46,22 @@ impl UserModule for HostModule {
         let services = guard.as_mut().unwrap();
 
         if let Some(cap) = TX_SERVICE_RE.captures(name) {
-            if &cap[2] == "tx-owner" {
-                services.tx_owner.insert(cap[1].to_owned(), import_service_from_handle(rto_context, handle));
-                return
+            match &cap[2] {
+                "tx-owner" => {
+                    services.tx_owner.insert(cap[1].to_owned(), import_service_from_handle(rto_context, handle));
+                }
+                "replay-guard" => {
+                    services.replay_guard.insert(cap[1].to_owned(), import_service_from_handle(rto_context, handle));
+                }
+                "tx-veto" => {
+                    services.tx_veto.insert(cap[1].to_owned(), import_service_from_handle(rto_context, handle));
+                }
+                "tx-inspector" => {
+                    services.tx_inspector.insert(cap[1].to_owned(), import_service_from_handle(rto_context, handle));
+                }
+                _ => panic!("Unknown import: {}", name),
             }
-            panic!("Unknown import: {}", name)
+            return
         }
         if let Some(cap) = SERVICE_RE.captures(name) {
             let module = &cap[2];
@@ -64,15 +75,42 @@ impl UserModule for HostModule {
                 "update-chain" => {
                     services.update_chain = import_service_from_handle(rto_context, handle);
                 }
+                "anchor-block" => {
+                    services.anchor_block = import_service_from_handle(rto_context, handle);
+                }
+                "provide-module-configs" => {
+                    services.provide_module_configs = import_service_from_handle(rto_context, handle);
+                }
+                "update-config" => {
+                    services.update_config.insert(module.to_owned(), import_service_from_handle(rto_context, handle));
+                }
+                "provide-network-identities" => {
+                    services.provide_network_identities = import_service_from_handle(rto_context, handle);
+                }
                 "stateful" => {
                     services.stateful.lock().push((module.to_owned(), import_service_from_handle(rto_context, handle)));
                 }
+                "use-randomness" => {
+                    services
+                        .use_randomness
+                        .lock()
+                        .push((module.to_owned(), import_service_from_handle(rto_context, handle)));
+                }
+                "use-time-oracle" => {
+                    services
+                        .use_time_oracle
+                        .lock()
+                        .push((module.to_owned(), import_service_from_handle(rto_context, handle)));
+                }
                 "tx-sorter" => {
                     services.tx_sorter = import_service_from_handle(rto_context, handle);
                 }
                 "handle-graphql-request" => {
                     services.handle_graphqls.push((module.to_owned(), import_service_from_handle(rto_context, handle)));
                 }
+                "handle-event" => {
+                    services.handle_event.insert(module.to_owned(), import_service_from_handle(rto_context, handle));
+                }
                 _ => panic!("Unknown import: {}", name),
             }
             return
@@ -14,11 +14,16 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::context::SubStorageAccess;
+use super::context::{RandomnessAccess, SubStorageAccess, TimeOracle};
+use crate::replay::ReplayTag;
 use crate::transaction::{Transaction, TransactionWithMetadata};
-use crate::types::{CloseBlockError, ErrorCode, Event, HeaderError, TransactionOutcome, VerifiedCrime};
+use crate::types::{
+    CloseBlockError, ErrorCode, Event, HeaderError, InspectedPayload, TransactionOutcome, VerifiedCrime,
+};
 use crate::Header;
-use ctypes::{CompactValidatorSet, ConsensusParams};
+use ckey::Ed25519Public as Public;
+use ctypes::{CompactValidatorSet, ConsensusParams, NetworkIdentity};
+use primitives::H256;
 use remote_trait_object::{service, Service, ServiceRef};
 use serde::{Deserialize, Serialize};
 
@@ -31,6 +36,24 @@ pub trait Stateful: Service {
     fn end_session(&mut self, id: SessionId);
 }
 
+/// Receives the predictable randomness of the block in each session opened for executing the block.
+/// `end_session` is called at the end of every session, including the ones without randomness.
+#[service]
+pub trait UseRandomness: Service {
+    fn new_session(&mut self, id: SessionId, randomness: ServiceRef<dyn RandomnessAccess>);
+
+    fn end_session(&mut self, id: SessionId);
+}
+
+/// Receives the median time of the block in each session opened for executing the block.
+/// `end_session` is called at the end of every session, including the ones without the time.
+#[service]
+pub trait UseTimeOracle: Service {
+    fn new_session(&mut self, id: SessionId, time_oracle: ServiceRef<dyn TimeOracle>);
+
+    fn end_session(&mut self, id: SessionId);
+}
+
 #[service]
 pub trait InitGenesis: Service {
     fn init_genesis(&self, session_id: SessionId, config: &[u8]);
@@ -44,9 +67,39 @@ pub trait TxOwner: Service {
 
     fn check_transaction(&self, transaction: &Transaction) -> Result<(), ErrorCode>;
 
+    /// Estimates the gas the transaction will use when it is executed.
+    fn estimate_gas(&self, transaction: &Transaction) -> u64;
+
+    /// Returns how many bytes of the body are the witness, e.g. the signatures and the keys.
+    fn witness_size(&self, transaction: &Transaction) -> usize;
+
     fn block_closed(&self, session_id: SessionId) -> Result<Vec<Event>, CloseBlockError>;
 }
 
+/// Selects the replay protection by an expiry and a nonce for the transactions of a type, instead of a sequence.
+/// The owner module still checks the tag against its `SeenNonces` when executing the transaction,
+/// while the mem pool uses it to drop the transaction once it expires.
+#[service]
+pub trait ReplayGuard: Service {
+    /// Returns `None` for a transaction protected by the sequence of its signer.
+    fn replay_tag(&self, transaction: &Transaction) -> Option<ReplayTag>;
+}
+
+/// Vetoes the pending transactions of a type that can never be executed again, e.g. expired nominations.
+/// The mem pool drops them at once, instead of keeping them aside in case they become valid again.
+#[service]
+pub trait TxVeto: Service {
+    /// Returns the indices of the vetoed transactions in the state of the session.
+    fn vetoed_transactions(&self, session_id: SessionId, transactions: &[Transaction]) -> Vec<usize>;
+}
+
+/// Decodes the transactions of a type for the RPC and the explorers, to which their payloads are opaque bytes.
+#[service]
+pub trait TxInspector: Service {
+    /// Returns why the payload can't be decoded if it is malformed.
+    fn inspect_transaction(&self, transaction: &Transaction) -> Result<InspectedPayload, String>;
+}
+
 #[service]
 pub trait InitChain: Service {
     fn init_chain(&self, session_id: SessionId) -> (CompactValidatorSet, ConsensusParams);
@@ -57,15 +110,62 @@ pub trait UpdateChain: Service {
     fn update_chain(&self, session_id: SessionId) -> (Option<CompactValidatorSet>, Option<ConsensusParams>);
 }
 
+/// Provides the configs of the modules changed by the governance.
+#[service]
+pub trait ProvideModuleConfigs: Service {
+    /// Returns the CBOR-encoded configs to apply from the next block, keyed by the module names.
+    /// They are expected only when the executing block closes a term.
+    fn updated_module_configs(&self, session_id: SessionId) -> Vec<(String, Vec<u8>)>;
+}
+
+#[service]
+pub trait UpdateConfig: Service {
+    /// Applies the new config, which conforms to the config schema of the module.
+    /// The module keeps the config it had if it returns an error,
+    /// and the coordinator reverts the changes it made to the storage meanwhile.
+    fn update_config(&self, session_id: SessionId, config: &[u8]) -> Result<(), String>;
+}
+
+/// Provides the external anchor of a block, e.g. a hash to be notarized on another chain.
+/// Only the module exporting this service can set the anchor in the block header.
+#[service]
+pub trait AnchorBlock: Service {
+    fn external_anchor(&self, session_id: SessionId) -> Option<H256>;
+}
+
+/// Provides the network identities the current validators registered on the chain.
+#[service]
+pub trait ProvideNetworkIdentities: Service {
+    fn network_identities(&self, session_id: SessionId) -> Vec<NetworkIdentity>;
+}
+
 #[service]
 pub trait TxSorter: Service {
     fn sort_txs(&self, session_id: SessionId, txs: &[TransactionWithMetadata]) -> SortedTxs;
+
+    /// Reads what the transaction is sorted by from its body, so that the host can query its pool by them.
+    fn sorting_metadata(&self, tx: &Transaction) -> TxSortingMetadata;
 }
 
 #[derive(Serialize, Deserialize, Default)]
 pub struct SortedTxs {
     pub invalid: Vec<usize>,
     pub sorted: Vec<usize>,
+    /// The Tx types the sorter tags as consensus-critical, e.g. validator set changes or slashing reports.
+    /// Up to `MAX_PRIORITY_TXS_PER_BLOCK` of their transactions are taken ahead of the others
+    /// and are never evicted to make room in the mempool.
+    /// A type users can submit freely should not be tagged, as the lane is only bounded by that count.
+    #[serde(default)]
+    pub priority_tx_types: Vec<String>,
+}
+
+/// The signer, the fee and the sequence of a transaction, which only its module can decode.
+/// The ones the sorter doesn't know, e.g. the fee of a transaction type charging none, are left out.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TxSortingMetadata {
+    pub signer: Option<Public>,
+    pub fee: Option<u64>,
+    pub seq: Option<u64>,
 }
 
 #[service]
@@ -77,3 +177,10 @@ pub trait HandleCrimes: Service {
 pub trait HandleGraphQlRequest: Service {
     fn execute(&self, session_id: SessionId, query: &str, variables: &str) -> String;
 }
+
+/// Receives the events published on the topics the module subscribes to.
+/// `topic` is the name of the topic, `<publisher>/<event-key>`.
+#[service]
+pub trait HandleEvent: Service {
+    fn handle_event(&self, session_id: SessionId, topic: &str, event: &Event);
+}
@@ -0,0 +1,187 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::context::{StorageAccess, SubStorageAccess};
+use crate::module::SessionId;
+use crate::types::{Transaction, TransactionOutcome};
+use crate::{BlockContext, Coordinator};
+use ctypes::StorageId;
+use parking_lot::Mutex;
+use remote_trait_object::Service;
+use std::collections::{BTreeMap, BTreeSet};
+use std::mem;
+use std::sync::Arc;
+use tracing::Span;
+
+/// The keys a transaction read from a sub-storage, and the values it left there.
+#[derive(Default)]
+struct AccessSet {
+    reads: BTreeSet<Vec<u8>>,
+    /// `None` for a removed key.
+    writes: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+}
+
+/// A sub-storage buffering the writes of a transaction on top of the shared one,
+/// recording what the transaction has read from the shared one.
+struct OverlaySubStorage {
+    base: Arc<dyn SubStorageAccess>,
+    accesses: Arc<Mutex<AccessSet>>,
+}
+
+impl Service for OverlaySubStorage {}
+
+impl SubStorageAccess for OverlaySubStorage {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let mut accesses = self.accesses.lock();
+        if let Some(value) = accesses.writes.get(key) {
+            return value.clone()
+        }
+        accesses.reads.insert(key.to_vec());
+        self.base.get(key)
+    }
+
+    fn set(&mut self, key: &[u8], value: Vec<u8>) {
+        self.accesses.lock().writes.insert(key.to_vec(), Some(value));
+    }
+
+    fn has(&self, key: &[u8]) -> bool {
+        let mut accesses = self.accesses.lock();
+        if let Some(value) = accesses.writes.get(key) {
+            return value.is_some()
+        }
+        accesses.reads.insert(key.to_vec());
+        self.base.has(key)
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        self.accesses.lock().writes.insert(key.to_vec(), None);
+    }
+}
+
+/// The result of a transaction executed in a session of its own, with its accesses per sub-storage.
+struct Isolated {
+    result: Result<TransactionOutcome, ()>,
+    accesses: Vec<AccessSet>,
+}
+
+impl Isolated {
+    fn conflicts_with(&self, written: &[BTreeSet<Vec<u8>>]) -> bool {
+        self.accesses.iter().zip(written).any(|(accesses, written)| !accesses.reads.is_disjoint(written))
+    }
+}
+
+impl Coordinator {
+    /// Executes the transactions of a block over `execution_threads` threads,
+    /// giving the same outcomes as executing them one by one in the block order.
+    ///
+    /// Each transaction runs in a session of its own, where its writes are buffered instead of
+    /// reaching the storage. The buffered writes are then applied in the block order.
+    /// A transaction that has read a key written by a preceding transaction of the block is
+    /// executed again at its turn, on top of the writes applied so far.
+    pub(crate) fn execute_transactions_in_parallel(
+        &self,
+        storage: &mut dyn StorageAccess,
+        transactions: &[Transaction],
+        block: Option<&BlockContext>,
+    ) -> Vec<TransactionOutcome> {
+        let number_of_storages = self.services.stateful.lock().len();
+        let bases: Vec<Arc<dyn SubStorageAccess>> =
+            (0..number_of_storages).map(|id| Arc::from(storage.sub_storage(id as StorageId))).collect();
+
+        let executables: Vec<usize> =
+            (0..transactions.len()).filter(|&i| self.is_executable(&transactions[i])).collect();
+        let chunk_size = (executables.len() + self.execution_threads - 1) / self.execution_threads;
+        let mut isolated: BTreeMap<usize, Isolated> = BTreeMap::new();
+        if chunk_size > 0 {
+            // The workers continue the span of the caller, so their module calls are nested in the block's span.
+            let span = Span::current();
+            crossbeam::thread::scope(|scope| {
+                let handles: Vec<_> = executables
+                    .chunks(chunk_size)
+                    .map(|chunk| {
+                        let bases = &bases;
+                        let span = &span;
+                        scope.spawn(move |_| {
+                            let _entered = span.enter();
+                            chunk
+                                .iter()
+                                .map(|&i| (i, self.execute_isolated(bases, &transactions[i], block)))
+                                .collect::<Vec<_>>()
+                        })
+                    })
+                    .collect();
+                for handle in handles {
+                    isolated.extend(handle.join().expect("Transactions are executed without panics"));
+                }
+            })
+            .expect("Transactions are executed without panics");
+        }
+
+        let mut writers: Vec<_> = (0..number_of_storages).map(|id| storage.sub_storage(id as StorageId)).collect();
+        let mut written: Vec<BTreeSet<Vec<u8>>> = vec![BTreeSet::new(); number_of_storages];
+        let mut outcomes = Vec::with_capacity(transactions.len());
+        for (i, tx) in transactions.iter().enumerate() {
+            let mut executed = match isolated.remove(&i) {
+                Some(executed) => executed,
+                None => {
+                    outcomes.push(TransactionOutcome::default());
+                    continue
+                }
+            };
+            if executed.conflicts_with(&written) {
+                executed = self.execute_isolated(&bases, tx, block);
+            }
+            if let Ok(outcome) = executed.result {
+                for ((writer, written), accesses) in writers.iter_mut().zip(written.iter_mut()).zip(executed.accesses) {
+                    for (key, value) in accesses.writes {
+                        match value {
+                            Some(value) => writer.set(&key, value),
+                            None => writer.remove(&key),
+                        }
+                        written.insert(key);
+                    }
+                }
+                outcomes.push(outcome);
+            }
+        }
+        outcomes
+    }
+
+    fn execute_isolated(
+        &self,
+        bases: &[Arc<dyn SubStorageAccess>],
+        tx: &Transaction,
+        block: Option<&BlockContext>,
+    ) -> Isolated {
+        let accesses: Vec<Arc<Mutex<AccessSet>>> = bases.iter().map(|_| Default::default()).collect();
+        let session_id: SessionId = self.open_session(
+            |storage_id| {
+                Box::new(OverlaySubStorage {
+                    base: Arc::clone(&bases[storage_id as usize]),
+                    accesses: Arc::clone(&accesses[storage_id as usize]),
+                })
+            },
+            block,
+        );
+        let result = self.execute_transaction(session_id, tx);
+        self.end_session(session_id);
+
+        Isolated {
+            result,
+            accesses: accesses.iter().map(|accesses| mem::take(&mut *accesses.lock())).collect(),
+        }
+    }
+}
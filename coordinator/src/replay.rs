@@ -0,0 +1,209 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Replay protection by an expiry block and a random nonce, an alternative to the sequence of an account.
+//!
+//! A transaction carrying a `ReplayTag` is valid until its expiry block, and only once: the nonces of
+//! the executed transactions are kept in the sub-storage of the owner module until they expire.
+//! So the signer can submit many transactions at once without coordinating their sequences.
+
+use crate::context::SubStorageAccess;
+use ctypes::BlockNumber;
+use primitives::H256;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+const SEEN_PREFIX: &[u8] = b"replay/seen/";
+const EXPIRY_PREFIX: &[u8] = b"replay/expiry/";
+const LAST_PRUNED_KEY: &[u8] = b"replay/last-pruned";
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ReplayTag {
+    /// The account the nonce belongs to, so that nobody can use up the nonce of another account.
+    pub signer: Vec<u8>,
+    /// The last block the transaction can be included in.
+    pub expires_at: BlockNumber,
+    pub nonce: H256,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ReplayError {
+    #[error("The transaction expired at block {expires_at}, before block {block_number}")]
+    Expired {
+        expires_at: BlockNumber,
+        block_number: BlockNumber,
+    },
+    #[error("The transaction expires at block {expires_at}, beyond the window ending at block {max_expires_at}")]
+    TooFarAhead {
+        expires_at: BlockNumber,
+        max_expires_at: BlockNumber,
+    },
+    #[error("The nonce has already been used")]
+    Seen,
+}
+
+/// The rolling set of the nonces used by the transactions that haven't expired yet.
+///
+/// The window bounds how far ahead a transaction may expire, and so how many nonces the set keeps.
+/// The module keeping the set must call `prune` at every block.
+pub struct SeenNonces {
+    window: BlockNumber,
+}
+
+impl SeenNonces {
+    pub fn new(window: BlockNumber) -> Self {
+        Self {
+            window,
+        }
+    }
+
+    /// Checks if the transaction with the tag can be included in the block of `block_number`.
+    pub fn check(
+        &self,
+        storage: &dyn SubStorageAccess,
+        tag: &ReplayTag,
+        block_number: BlockNumber,
+    ) -> Result<(), ReplayError> {
+        if tag.expires_at < block_number {
+            return Err(ReplayError::Expired {
+                expires_at: tag.expires_at,
+                block_number,
+            })
+        }
+        let max_expires_at = block_number.saturating_add(self.window);
+        if tag.expires_at > max_expires_at {
+            return Err(ReplayError::TooFarAhead {
+                expires_at: tag.expires_at,
+                max_expires_at,
+            })
+        }
+        if storage.has(&seen_key(tag)) {
+            return Err(ReplayError::Seen)
+        }
+        Ok(())
+    }
+
+    /// Records the nonce of an executed transaction until the transaction expires.
+    pub fn record(&self, storage: &mut dyn SubStorageAccess, tag: &ReplayTag) {
+        let key = seen_key(tag);
+        storage.set(&key, Vec::new());
+
+        let index_key = expiry_key(tag.expires_at);
+        let mut expiring: Vec<Vec<u8>> =
+            storage.get(&index_key).map(|value| serde_cbor::from_slice(&value).unwrap()).unwrap_or_default();
+        expiring.push(key);
+        storage.set(&index_key, serde_cbor::to_vec(&expiring).unwrap());
+    }
+
+    /// Forgets the nonces of the transactions expired before the block of `block_number`.
+    pub fn prune(&self, storage: &mut dyn SubStorageAccess, block_number: BlockNumber) {
+        let last_expired = match block_number.checked_sub(1) {
+            Some(last_expired) => last_expired,
+            None => return,
+        };
+        let first_unpruned = storage
+            .get(LAST_PRUNED_KEY)
+            .map(|value| serde_cbor::from_slice::<BlockNumber>(&value).unwrap() + 1)
+            .unwrap_or(last_expired);
+        for expires_at in first_unpruned..=last_expired {
+            let index_key = expiry_key(expires_at);
+            if let Some(value) = storage.get(&index_key) {
+                let expired: Vec<Vec<u8>> = serde_cbor::from_slice(&value).unwrap();
+                for key in expired {
+                    storage.remove(&key);
+                }
+                storage.remove(&index_key);
+            }
+        }
+        if first_unpruned <= last_expired {
+            storage.set(LAST_PRUNED_KEY, serde_cbor::to_vec(&last_expired).unwrap());
+        }
+    }
+}
+
+fn seen_key(tag: &ReplayTag) -> Vec<u8> {
+    let mut key = Vec::with_capacity(SEEN_PREFIX.len() + tag.signer.len() + 32);
+    key.extend_from_slice(SEEN_PREFIX);
+    key.extend_from_slice(&tag.signer);
+    key.extend_from_slice(tag.nonce.as_ref());
+    key
+}
+
+fn expiry_key(expires_at: BlockNumber) -> Vec<u8> {
+    let mut key = EXPIRY_PREFIX.to_vec();
+    key.extend_from_slice(&expires_at.to_be_bytes());
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::memory::Memory;
+
+    fn tag(expires_at: BlockNumber, nonce: u8) -> ReplayTag {
+        ReplayTag {
+            signer: vec![1; 32],
+            expires_at,
+            nonce: H256::from([nonce; 32]),
+        }
+    }
+
+    #[test]
+    fn nonce_is_accepted_once_until_it_expires() {
+        let seen = SeenNonces::new(10);
+        let mut storage = Memory::default();
+
+        assert_eq!(seen.check(&storage, &tag(15, 1), 10), Ok(()));
+        seen.record(&mut storage, &tag(15, 1));
+        assert_eq!(seen.check(&storage, &tag(15, 1), 11), Err(ReplayError::Seen));
+        assert_eq!(seen.check(&storage, &tag(15, 2), 11), Ok(()));
+
+        assert_eq!(
+            seen.check(&storage, &tag(15, 1), 16),
+            Err(ReplayError::Expired {
+                expires_at: 15,
+                block_number: 16,
+            })
+        );
+        assert_eq!(
+            seen.check(&storage, &tag(30, 3), 16),
+            Err(ReplayError::TooFarAhead {
+                expires_at: 30,
+                max_expires_at: 26,
+            })
+        );
+    }
+
+    #[test]
+    fn expired_nonces_are_pruned() {
+        let seen = SeenNonces::new(10);
+        let mut storage = Memory::default();
+
+        seen.prune(&mut storage, 10);
+        seen.record(&mut storage, &tag(12, 1));
+        seen.record(&mut storage, &tag(14, 2));
+
+        // A block without pruning must not leave the nonces expiring at it behind.
+        seen.prune(&mut storage, 12);
+        seen.prune(&mut storage, 14);
+        assert!(!storage.has(&seen_key(&tag(12, 1))));
+        assert!(storage.has(&seen_key(&tag(14, 2))));
+
+        seen.prune(&mut storage, 15);
+        assert!(!storage.has(&seen_key(&tag(14, 2))));
+        assert_eq!(storage.0.len(), 1, "Only the last pruned block is left");
+    }
+}
@@ -0,0 +1,155 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! The spans of the work the coordinator does for the host.
+//! They are collected only if the host installs a `tracing` subscriber, e.g. one exporting them over OTLP.
+//!
+//! A module runs in its own sandbox, so the span of a call into a module is timed on the coordinator side,
+//! including the round trip over the remote trait object.
+//! The same time is also collected for Prometheus in `module_call_seconds`,
+//! and the calls that have not returned yet are listed by `pending_module_calls`
+//! so that the host can tell a hung sandbox from a busy one.
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use prometheus::{register_histogram_vec, HistogramVec};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tracing::{debug_span, info_span, Span};
+
+static MODULE_CALL_SECONDS: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!("module_call_seconds", "The time taken by a call into a module", &[
+        "service", "method", "key"
+    ])
+    .unwrap()
+});
+
+static NEXT_CALL_ID: AtomicU64 = AtomicU64::new(0);
+static PENDING_CALLS: Lazy<Mutex<HashMap<u64, (PendingCall, Instant)>>> = Lazy::new(Default::default);
+
+#[derive(Clone)]
+struct PendingCall {
+    service: &'static str,
+    method: &'static str,
+    key: String,
+}
+
+/// A call into a module that has not returned yet.
+#[derive(Clone, Debug)]
+pub struct PendingModuleCall {
+    pub service: &'static str,
+    pub method: &'static str,
+    pub key: String,
+    /// How long the call has been running
+    pub elapsed: Duration,
+}
+
+/// Lists the calls into the modules that have not returned yet, the longest running first.
+pub fn pending_module_calls() -> Vec<PendingModuleCall> {
+    let now = Instant::now();
+    let mut calls: Vec<_> = PENDING_CALLS
+        .lock()
+        .values()
+        .map(|(call, started_at)| PendingModuleCall {
+            service: call.service,
+            method: call.method,
+            key: call.key.clone(),
+            elapsed: now.duration_since(*started_at),
+        })
+        .collect();
+    calls.sort_by(|a, b| b.elapsed.cmp(&a.elapsed));
+    calls
+}
+
+/// Keeps a call in `PENDING_CALLS` until it returns or unwinds.
+struct PendingCallGuard(u64);
+
+impl PendingCallGuard {
+    fn register(service: &'static str, method: &'static str, key: &str) -> Self {
+        let id = NEXT_CALL_ID.fetch_add(1, Ordering::Relaxed);
+        let call = PendingCall {
+            service,
+            method,
+            key: key.to_string(),
+        };
+        PENDING_CALLS.lock().insert(id, (call, Instant::now()));
+        PendingCallGuard(id)
+    }
+}
+
+impl Drop for PendingCallGuard {
+    fn drop(&mut self) {
+        PENDING_CALLS.lock().remove(&self.0);
+    }
+}
+
+/// Spans the execution of a block, from `open_block` to `close_block`.
+pub(crate) fn block_execution(number: u64) -> Span {
+    info_span!("block_execution", number)
+}
+
+/// Spans a call to the only provider of a service, e.g. `update-chain`.
+pub(crate) fn service_call(service: &'static str, method: &'static str) -> Span {
+    debug_span!("service_call", service, method)
+}
+
+/// Spans a call to one of the providers of a service, told apart by `key`:
+/// the transaction type for `tx-owner`, and the module name for the others.
+fn module_call(service: &'static str, method: &'static str, key: &str) -> Span {
+    debug_span!("module_call", service, method, key)
+}
+
+/// Calls into a module in its span, timing the call for `module_call_seconds`.
+pub(crate) fn in_module_call<T>(service: &'static str, method: &'static str, key: &str, f: impl FnOnce() -> T) -> T {
+    let timer = MODULE_CALL_SECONDS.with_label_values(&[service, method, key]).start_timer();
+    let pending = PendingCallGuard::register(service, method, key);
+    let result = module_call(service, method, key).in_scope(f);
+    drop(pending);
+    timer.observe_duration();
+    result
+}
+
+/// Spans the work on a batch of transactions of the mem pool.
+pub(crate) fn mem_pool_batch(operation: &'static str, count: usize) -> Span {
+    debug_span!("mem_pool_batch", operation, count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending_calls_with_key(key: &str) -> usize {
+        pending_module_calls().into_iter().filter(|call| call.key == key).count()
+    }
+
+    #[test]
+    fn module_call_is_pending_until_it_returns() {
+        let key = "pending-call-test";
+        in_module_call("tx-owner", "execute_transactions", key, || {
+            assert_eq!(pending_calls_with_key(key), 1);
+        });
+        assert_eq!(pending_calls_with_key(key), 0);
+    }
+
+    #[test]
+    fn module_call_is_not_pending_after_it_panics() {
+        let key = "panicking-call-test";
+        let result = std::panic::catch_unwind(|| in_module_call("tx-owner", "check_transaction", key, || panic!()));
+        assert!(result.is_err());
+        assert_eq!(pending_calls_with_key(key), 0);
+    }
+}
@@ -15,14 +15,17 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::context::StorageAccess;
-use crate::engine::{BlockExecutor, ExecutionId, GraphQlHandlerProvider, Initializer, TxFilter};
+use crate::engine::{
+    BlockExecutor, ExecutionId, GraphQlHandlerProvider, Initializer, NetworkIdentityProvider, TxFilter,
+};
 use crate::header::Header;
 use crate::transaction::{Transaction, TransactionWithMetadata};
 use crate::types::{
     BlockOutcome, CloseBlockError, ErrorCode, ExecuteTransactionError, FilteredTxs, HeaderError, TransactionOutcome,
-    VerifiedCrime,
+    TransactionPreview, VerifiedCrime,
 };
-use ctypes::{CompactValidatorSet, ConsensusParams};
+use ctypes::{CompactValidatorSet, ConsensusParams, NetworkIdentity, TxOrdering};
+use primitives::H256;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
@@ -60,6 +63,8 @@ impl BlockExecutor for TestCoordinator {
         &self,
         _storage: &mut dyn StorageAccess,
         _header: &Header,
+        _random_seed: &H256,
+        _median_time: u64,
         _verified_crime: &[VerifiedCrime],
     ) -> Result<ExecutionId, HeaderError> {
         self.body_count.store(0, Ordering::SeqCst);
@@ -79,6 +84,8 @@ impl BlockExecutor for TestCoordinator {
         Ok((0..self.body_count.load(Ordering::SeqCst))
             .map(|_| TransactionOutcome {
                 events: Vec::new(),
+                gas_used: 0,
+                sub_outcomes: Vec::new(),
             })
             .collect())
     }
@@ -88,8 +95,29 @@ impl BlockExecutor for TestCoordinator {
         _execution_id: ExecutionId,
         _storage: &mut dyn StorageAccess,
         transactions: &mut dyn Iterator<Item = &'a TransactionWithMetadata>,
+        _max_body_size: usize,
+        order_seed: Option<H256>,
     ) -> Vec<(&'a Transaction, TransactionOutcome)> {
-        transactions.map(|tx_with_metadata| (&tx_with_metadata.tx, TransactionOutcome::default())).collect()
+        let mut transactions: Vec<_> = transactions.map(|tx_with_metadata| &tx_with_metadata.tx).collect();
+        if let Some(seed) = order_seed {
+            transactions.sort_by_cached_key(|tx| TxOrdering::seeded_key(&seed, &tx.hash()));
+        }
+        transactions.into_iter().map(|tx| (tx, TransactionOutcome::default())).collect()
+    }
+
+    fn preview_transaction(
+        &self,
+        _execution_id: ExecutionId,
+        _storage: &mut dyn StorageAccess,
+        transaction: &Transaction,
+    ) -> TransactionPreview {
+        match self.check_transaction(transaction) {
+            Ok(()) => TransactionPreview::Executed {
+                gas_estimate: 0,
+                outcome: TransactionOutcome::default(),
+            },
+            Err(code) => TransactionPreview::Rejected(code),
+        }
     }
 
     fn close_block(&self, _execution_id: ExecutionId) -> Result<BlockOutcome, CloseBlockError> {
@@ -97,7 +125,7 @@ impl BlockExecutor for TestCoordinator {
             Ok(BlockOutcome {
                 updated_validator_set: Some(self.validator_set.clone()),
                 updated_consensus_params: Some(self.consensus_params),
-
+                external_anchor: None,
                 events: Vec::new(),
             })
         } else {
@@ -115,6 +143,10 @@ impl TxFilter for TestCoordinator {
         }
     }
 
+    fn is_system_transaction(&self, _transaction: &Transaction) -> bool {
+        false
+    }
+
     fn filter_transactions<'a>(
         &self,
         _storage: &mut dyn StorageAccess,
@@ -140,6 +172,12 @@ impl TxFilter for TestCoordinator {
     }
 }
 
+impl NetworkIdentityProvider for TestCoordinator {
+    fn network_identities(&self, _storage: &mut dyn StorageAccess) -> Vec<NetworkIdentity> {
+        Vec::new()
+    }
+}
+
 impl GraphQlHandlerProvider for TestCoordinator {
     fn get(&self) -> Vec<(String, Arc<dyn super::module::HandleGraphQlRequest>)> {
         vec![]
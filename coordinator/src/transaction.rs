@@ -21,6 +21,10 @@ use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 
+/// The type of the transactions bundling sub-transactions of other types.
+/// The sub-transactions of a composite transaction are executed atomically by the `Coordinator`.
+pub const COMPOSITE_TX_TYPE: &str = "composite";
+
 /// An encoded transaction.
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub struct Transaction {
@@ -44,13 +48,43 @@ impl Transaction {
         &self.body
     }
 
+    /// The encoded size of the transaction, which counts against `max_body_size` of a block.
     pub fn size(&self) -> usize {
         self.rlp_bytes().len()
     }
 
+    /// The bytes of the encoded transaction that are not the body: the type and the encoding overhead.
+    pub fn envelope_size(&self) -> usize {
+        self.size() - self.body.len()
+    }
+
     pub fn hash(&self) -> TxHash {
         blake256(self.rlp_bytes()).into()
     }
+
+    /// Bundles the sub-transactions into a composite transaction.
+    pub fn new_composite(sub_transactions: &[Transaction]) -> Self {
+        let mut s = RlpStream::new();
+        s.append_list(sub_transactions);
+        Self::new(COMPOSITE_TX_TYPE.to_owned(), s.out())
+    }
+
+    pub fn is_composite(&self) -> bool {
+        self.tx_type == COMPOSITE_TX_TYPE
+    }
+
+    /// Returns the sub-transactions if this is a well-formed composite transaction.
+    /// A well-formed composite transaction has at least one sub-transaction and doesn't nest composite transactions.
+    pub fn sub_transactions(&self) -> Option<Vec<Transaction>> {
+        if !self.is_composite() {
+            return None
+        }
+        let sub_transactions: Vec<Transaction> = Rlp::new(&self.body).as_list().ok()?;
+        if sub_transactions.is_empty() || sub_transactions.iter().any(Transaction::is_composite) {
+            return None
+        }
+        Some(sub_transactions)
+    }
 }
 
 impl Encodable for Transaction {
@@ -75,24 +109,53 @@ impl Decodable for Transaction {
     }
 }
 
+/// The encoded bytes of a transaction split into the witness and the payload.
+/// Fee policies may charge the witness less than the payload, since it isn't needed once the transaction is verified.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TxSizes {
+    /// The envelope and the bytes of the body the owner module counts as the witness, e.g. the signatures
+    pub witness: usize,
+    /// The rest of the body, e.g. the action
+    pub payload: usize,
+}
+
+impl TxSizes {
+    /// Splits the transaction, given how many bytes of its body are the witness.
+    pub fn new(tx: &Transaction, witness_in_body: usize) -> Self {
+        let witness_in_body = witness_in_body.min(tx.body().len());
+        Self {
+            witness: tx.envelope_size() + witness_in_body,
+            payload: tx.body().len() - witness_in_body,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.witness + self.payload
+    }
+}
+
 /// Transaction origin
-#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum TxOrigin {
     /// Transaction coming from local RPC
     Local,
     /// External transaction received from network
     External,
+    /// Transaction issued by the node itself, e.g. by a module. It is not propagated.
+    System,
 }
 
 type TxOriginType = u8;
 const LOCAL: TxOriginType = 0x01;
 const EXTERNAL: TxOriginType = 0x02;
+const SYSTEM: TxOriginType = 0x03;
 
 impl Encodable for TxOrigin {
     fn rlp_append(&self, s: &mut RlpStream) {
         match self {
             TxOrigin::Local => LOCAL.rlp_append(s),
             TxOrigin::External => EXTERNAL.rlp_append(s),
+            TxOrigin::System => SYSTEM.rlp_append(s),
         };
     }
 }
@@ -102,6 +165,7 @@ impl Decodable for TxOrigin {
         match d.as_val().expect("rlp decode Error") {
             LOCAL => Ok(TxOrigin::Local),
             EXTERNAL => Ok(TxOrigin::External),
+            SYSTEM => Ok(TxOrigin::System),
             _ => Err(DecoderError::Custom("Unexpected Txorigin type")),
         }
     }
@@ -121,14 +185,17 @@ impl Ord for TxOrigin {
 
         match (*self, *other) {
             (TxOrigin::Local, _) => Ordering::Less,
+            (_, TxOrigin::Local) => Ordering::Greater,
+            (TxOrigin::System, _) => Ordering::Less,
             _ => Ordering::Greater,
         }
     }
 }
 
 impl TxOrigin {
+    /// Whether the transaction comes from this node, either through RPC or from the node itself.
     pub fn is_local(self) -> bool {
-        self == TxOrigin::Local
+        self == TxOrigin::Local || self == TxOrigin::System
     }
 
     pub fn is_external(self) -> bool {
@@ -144,6 +211,8 @@ pub struct TransactionWithMetadata {
     pub inserted_timestamp: u64,
     /// ID assigned upon insertion, should be unique
     pub insertion_id: u64,
+    /// The block number from which the transaction can be included in a block
+    pub activation_block: Option<u64>,
 }
 
 impl<'a> TransactionWithMetadata {
@@ -153,6 +222,7 @@ impl<'a> TransactionWithMetadata {
         inserted_block_number: u64,
         inserted_timestamp: u64,
         insertion_id: u64,
+        activation_block: Option<u64>,
     ) -> Self {
         Self {
             tx,
@@ -160,6 +230,7 @@ impl<'a> TransactionWithMetadata {
             inserted_block_number,
             inserted_timestamp,
             insertion_id,
+            activation_block,
         }
     }
 
@@ -172,23 +243,80 @@ impl<'a> TransactionWithMetadata {
     }
 }
 
+/// An item kept in a pool of the host, e.g. a transaction of the mem pool.
+///
+/// The pools know their items only through this trait, so a pool of other items reuses the same queue.
+/// The signer, the sequence and the fee of a transaction are known only to its module,
+/// so a pool that orders its items by them keeps them along with the items.
+pub trait PoolTransaction {
+    fn hash(&self) -> TxHash;
+
+    /// The number of bytes the item takes up in the pool.
+    fn size(&self) -> usize;
+
+    /// Local items are not counted against the limits of the pool.
+    fn is_local(&self) -> bool;
+
+    /// The position of the item in the order the pool received them.
+    fn insertion_id(&self) -> u64;
+
+    /// The type the pool groups the item under, e.g. the type of a transaction.
+    fn tx_type(&self) -> &str;
+}
+
+impl PoolTransaction for TransactionWithMetadata {
+    fn hash(&self) -> TxHash {
+        TransactionWithMetadata::hash(self)
+    }
+
+    fn size(&self) -> usize {
+        TransactionWithMetadata::size(self)
+    }
+
+    fn is_local(&self) -> bool {
+        self.origin.is_local()
+    }
+
+    fn insertion_id(&self) -> u64 {
+        self.insertion_id
+    }
+
+    fn tx_type(&self) -> &str {
+        self.tx.tx_type()
+    }
+}
+
+// The activation block is appended only when it exists,
+// so the transactions backed up before it was introduced are still decodable.
 impl Encodable for TransactionWithMetadata {
     fn rlp_append(&self, s: &mut RlpStream) {
-        s.begin_list(5)
-            .append(&self.tx)
-            .append(&self.origin)
-            .append(&self.inserted_block_number)
-            .append(&self.inserted_timestamp)
-            .append(&self.insertion_id);
+        s.begin_list(if self.activation_block.is_some() {
+            6
+        } else {
+            5
+        })
+        .append(&self.tx)
+        .append(&self.origin)
+        .append(&self.inserted_block_number)
+        .append(&self.inserted_timestamp)
+        .append(&self.insertion_id);
+        if let Some(activation_block) = self.activation_block {
+            s.append(&activation_block);
+        }
     }
 }
 
 impl Decodable for TransactionWithMetadata {
     fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
         let item_count = rlp.item_count()?;
-        if item_count != 5 {
+        if item_count < 5 || item_count > 6 {
+            // Without the activation block, the transaction has 5 items.
             return Err(DecoderError::RlpIncorrectListLen {
-                expected: 5,
+                expected: if item_count < 5 {
+                    5
+                } else {
+                    6
+                },
                 got: item_count,
             })
         }
@@ -198,6 +326,11 @@ impl Decodable for TransactionWithMetadata {
             inserted_block_number: rlp.val_at(2)?,
             inserted_timestamp: rlp.val_at(3)?,
             insertion_id: rlp.val_at(4)?,
+            activation_block: if item_count == 6 {
+                Some(rlp.val_at(5)?)
+            } else {
+                None
+            },
         })
     }
 }
@@ -215,4 +348,64 @@ mod tests {
         };
         rlp_encode_and_decode_test!(transaction);
     }
+
+    #[test]
+    fn transaction_schema_matches_the_encoding() {
+        let bytes = rlp::encode(&tx("transfer"));
+        let schema = ctypes::schema::transaction_schema();
+        schema.check(&bytes).unwrap();
+
+        let rlp = Rlp::new(&bytes);
+        let golden = [("txType", rlp::encode(&"transfer".to_string())), ("body", rlp::encode(&vec![1u8, 2, 3]))];
+        for (name, expected) in &golden {
+            let field = schema.fields.iter().find(|field| field.name == *name).unwrap();
+            assert_eq!(&expected[..], rlp.at(field.position).unwrap().as_raw(), "{}", name);
+        }
+    }
+
+    fn tx(tx_type: &str) -> Transaction {
+        Transaction::new(tx_type.to_owned(), vec![1, 2, 3])
+    }
+
+    #[test]
+    fn metadata_of_a_wrong_length_is_rejected() {
+        for (len, expected) in &[(4, 5), (7, 6)] {
+            let mut s = RlpStream::new_list(*len);
+            for _ in 0..*len {
+                s.append(&0u64);
+            }
+            let err = TransactionWithMetadata::decode(&Rlp::new(&s.out())).unwrap_err();
+            assert_eq!(
+                DecoderError::RlpIncorrectListLen {
+                    expected: *expected,
+                    got: *len,
+                },
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn encode_and_decode_transaction_with_metadata() {
+        rlp_encode_and_decode_test!(TransactionWithMetadata::new(tx("transfer"), TxOrigin::Local, 3, 100, 7, None));
+        rlp_encode_and_decode_test!(TransactionWithMetadata::new(tx("transfer"), TxOrigin::Local, 3, 100, 7, Some(10)));
+    }
+
+    #[test]
+    fn composite_transaction_round_trip() {
+        let sub_transactions = vec![tx("transfer"), tx("stamp")];
+        let composite = Transaction::new_composite(&sub_transactions);
+        assert!(composite.is_composite());
+        assert_eq!(Some(sub_transactions), composite.sub_transactions());
+    }
+
+    #[test]
+    fn malformed_composite_transactions() {
+        assert_eq!(None, tx("transfer").sub_transactions());
+        assert_eq!(None, Transaction::new_composite(&[]).sub_transactions());
+        assert_eq!(None, Transaction::new(COMPOSITE_TX_TYPE.to_owned(), vec![1, 2, 3]).sub_transactions());
+
+        let nested = Transaction::new_composite(&[tx("transfer")]);
+        assert_eq!(None, Transaction::new_composite(&[tx("stamp"), nested]).sub_transactions());
+    }
 }
@@ -19,6 +19,7 @@ mod event;
 pub use self::event::Event;
 use crate::Transaction;
 use ctypes::{CompactValidatorSet, ConsensusParams};
+use primitives::H256;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
@@ -33,6 +34,14 @@ pub enum VerifiedCrime {
 #[derive(Serialize, Deserialize, Default)]
 pub struct TransactionOutcome {
     pub events: Vec<Event>,
+    /// The gas the host charged for the storage accesses of the transaction.
+    /// It is set by the host, so what the owner module reports here is overwritten.
+    #[serde(default)]
+    pub gas_used: u64,
+    /// The outcomes of the sub-transactions of a composite transaction, in their order.
+    /// The events and the gas used of the composite transaction add them up.
+    #[serde(default)]
+    pub sub_outcomes: Vec<TransactionOutcome>,
 }
 
 impl TransactionOutcome {
@@ -41,6 +50,20 @@ impl TransactionOutcome {
     }
 }
 
+/// The expected result of a transaction, previewed in a block without being included in it.
+pub enum TransactionPreview {
+    /// The transaction is rejected by `check_transaction` of its owner module.
+    Rejected(ErrorCode),
+    /// The transaction fails while being executed, so it would be reverted in a block.
+    Failed {
+        gas_estimate: u64,
+    },
+    Executed {
+        gas_estimate: u64,
+        outcome: TransactionOutcome,
+    },
+}
+
 pub type HeaderError = String;
 pub type ExecuteTransactionError = ();
 pub type CloseBlockError = String;
@@ -48,12 +71,42 @@ pub type CloseBlockError = String;
 pub struct BlockOutcome {
     pub updated_validator_set: Option<CompactValidatorSet>,
     pub updated_consensus_params: Option<ConsensusParams>,
+    pub external_anchor: Option<H256>,
     pub events: Vec<Event>,
 }
 
 pub type ErrorCode = u32;
 
+/// The payload of a transaction as decoded by the `TxInspector` of its type.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct InspectedPayload {
+    /// The name of the action, e.g. `transfer_token`.
+    pub action: String,
+    /// The fields of the action, encoded as a JSON object.
+    pub fields: String,
+}
+
 pub struct FilteredTxs<'a> {
     pub invalid: Vec<&'a Transaction>,
     pub low_priority: Vec<&'a Transaction>,
 }
+
+/// The pending transactions split by whether their owner modules vetoed them.
+pub struct VetoedTxs<'a> {
+    pub kept: Vec<&'a Transaction>,
+    pub dropped: Vec<&'a Transaction>,
+}
+
+/// A transaction to be included in a block, with the gas its owner module expects it to use.
+pub struct TxWithGas<'a> {
+    pub tx: &'a Transaction,
+    pub gas_estimate: u64,
+}
+
+/// Transactions fetched for a block, in the order given by the `TxSorter` service.
+pub struct TxsForBlock<'a> {
+    pub invalid: Vec<&'a Transaction>,
+    pub sorted: Vec<TxWithGas<'a>>,
+    /// The number of the leading `sorted` transactions in the priority lane.
+    pub priority: usize,
+}
@@ -59,4 +59,22 @@ mod tests {
         };
         rlp_encode_and_decode_test!(event);
     }
+
+    #[test]
+    fn event_schema_matches_the_encoding() {
+        let event = Event {
+            key: "test key".to_string(),
+            value: vec![0, 1, 2],
+        };
+        let bytes = rlp::encode(&event);
+        let schema = ctypes::schema::event_schema();
+        schema.check(&bytes).unwrap();
+
+        let rlp = Rlp::new(&bytes);
+        let golden = [("key", rlp::encode(&"test key".to_string())), ("value", rlp::encode(&vec![0u8, 1, 2]))];
+        for (name, expected) in &golden {
+            let field = schema.fields.iter().find(|field| field.name == *name).unwrap();
+            assert_eq!(&expected[..], rlp.at(field.position).unwrap().as_raw(), "{}", name);
+        }
+    }
 }
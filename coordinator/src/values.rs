@@ -20,7 +20,7 @@ use std::collections::HashMap;
 use std::fmt;
 
 /// Generic value that may be specified in the app descriptor and module manifests.
-#[derive(PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug)]
 pub enum Value {
     Null,
     Int(i128),
@@ -16,7 +16,7 @@
 
 use parking_lot::RwLock;
 use std::cell::RefCell;
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::ops::RangeBounds;
 use std::sync::Arc;
 
@@ -82,11 +82,36 @@ impl Weaver {
         self.import_services(HOST_ID, SERVICES_FOR_HOST)?;
         self.link_all()?;
 
-        let linkables = self.modules.into_iter().map(|(_, link_info)| link_info.linkable.into_inner()).collect();
+        let linkables = self
+            .shutdown_order()
+            .into_iter()
+            .map(|module| self.modules.remove(&module).unwrap().linkable.into_inner())
+            .collect();
 
         Ok((linkables, self.services.write().take().unwrap()))
     }
 
+    /// Orders the modules so that a module comes before the modules it imports services from,
+    /// so dropping the sandboxes in this order closes every module before its dependencies.
+    /// Among the modules depending on each other, the one with the smallest name comes first.
+    fn shutdown_order(&self) -> Vec<String> {
+        let mut remaining: BTreeSet<&String> = self.modules.keys().collect();
+        let mut order = Vec::with_capacity(remaining.len());
+        while !remaining.is_empty() {
+            let next = {
+                let is_imported = |module: &String| {
+                    remaining
+                        .iter()
+                        .any(|other| *other != module && self.modules[*other].imports.borrow().contains_key(module))
+                };
+                *remaining.iter().find(|module| !is_imported(module)).or_else(|| remaining.iter().next()).unwrap()
+            };
+            remaining.remove(next);
+            order.push(next.clone());
+        }
+        order
+    }
+
     fn process_host(&mut self, setup: &HostSetup) -> anyhow::Result<()> {
         let (exports, init_exports) = Self::process_exports(&setup.exports);
         let imports = Self::process_imports(&setup.imports);
@@ -134,6 +159,8 @@ impl Weaver {
             //        introduce a proper module registry.
             let path = if sandboxer_id == "multi-process" {
                 format!("../target/debug/{:x}", &setup.hash)
+            } else if sandboxer_id == "wasm" {
+                format!("../target/wasm32-unknown-unknown/debug/{:x}.wasm", &setup.hash)
             } else {
                 format!("{:x}", &setup.hash)
             };
@@ -0,0 +1,98 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+#![feature(test)]
+
+extern crate test;
+
+use codechain_core::BodyCompression;
+use coordinator::Transaction;
+use rlp::RlpStream;
+use rlp_compress::{blocks_swapper, compress, decompress};
+use test::Bencher;
+
+const TRANSACTIONS: usize = 200;
+
+/// A body whose transactions carry CBOR maps, like the ones of the app-chain modules.
+fn cbor_body() -> Vec<u8> {
+    let transactions: Vec<_> = (0..TRANSACTIONS)
+        .map(|i| {
+            let mut body = Vec::new();
+            for j in 0..32u32 {
+                body.extend_from_slice(b"\xa4fsenderx\x40");
+                body.extend_from_slice(format!("{:064x}", i * 31 + 7).as_bytes());
+                body.extend_from_slice(b"hreceiverx\x40");
+                body.extend_from_slice(format!("{:064x}", j).as_bytes());
+                body.extend_from_slice(b"famount\x1a");
+                body.extend_from_slice(&(i as u32 * j).to_be_bytes());
+                body.extend_from_slice(b"dmemo`");
+            }
+            Transaction::new("token".to_string(), body)
+        })
+        .collect();
+    let mut s = RlpStream::new_list(2);
+    s.begin_list(0);
+    s.append_list(&transactions);
+    s.out()
+}
+
+fn bench_compress(b: &mut Bencher, compression: BodyCompression) {
+    let body = cbor_body();
+    b.bytes = body.len() as u64;
+    b.iter(|| compression.compress_tagged(&body));
+}
+
+fn bench_decompress(b: &mut Bencher, compression: BodyCompression) {
+    let body = cbor_body();
+    let compressed = compression.compress_tagged(&body);
+    b.bytes = body.len() as u64;
+    b.iter(|| BodyCompression::decompress_tagged(&compressed, body.len()).unwrap().unwrap());
+}
+
+#[bench]
+fn compress_rlp(b: &mut Bencher) {
+    let body = cbor_body();
+    b.bytes = body.len() as u64;
+    b.iter(|| compress(&body, blocks_swapper()));
+}
+
+#[bench]
+fn decompress_rlp(b: &mut Bencher) {
+    let body = cbor_body();
+    let compressed = compress(&body, blocks_swapper());
+    b.bytes = body.len() as u64;
+    b.iter(|| decompress(&compressed, blocks_swapper()));
+}
+
+#[bench]
+fn compress_lz4(b: &mut Bencher) {
+    bench_compress(b, BodyCompression::Lz4);
+}
+
+#[bench]
+fn decompress_lz4(b: &mut Bencher) {
+    bench_decompress(b, BodyCompression::Lz4);
+}
+
+#[bench]
+fn compress_zstd(b: &mut Bencher) {
+    bench_compress(b, BodyCompression::Zstd);
+}
+
+#[bench]
+fn decompress_zstd(b: &mut Bencher) {
+    bench_decompress(b, BodyCompression::Zstd);
+}
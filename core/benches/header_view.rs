@@ -0,0 +1,83 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+#![feature(test)]
+
+extern crate test;
+
+use ccrypto::blake256;
+use ckey::Ed25519Public as Public;
+use codechain_core::{HeaderView, ValidatedHeaderView};
+use ctypes::Header;
+use rlp::{Encodable, RlpStream};
+use test::Bencher;
+
+const VALIDATORS: usize = 30;
+
+/// A header sealed by a validator set, like the ones the sync extension downloads.
+fn sealed_header() -> Vec<u8> {
+    let mut header = Header::default();
+    header.set_parent_hash(blake256("parent").into());
+    header.set_author(Public::random());
+    header.set_number(100);
+    header.set_timestamp(1_600_000_000);
+    header.set_extra_data(vec![0; 32]);
+    let mut signatures = RlpStream::new_list(VALIDATORS);
+    for _ in 0..VALIDATORS {
+        signatures.append(&vec![0u8; 64]);
+    }
+    header.set_seal(vec![
+        rlp::encode(&0u64),
+        rlp::encode(&1u64),
+        rlp::encode(&vec![0xffu8; VALIDATORS / 8 + 1]),
+        signatures.out(),
+    ]);
+    header.rlp_bytes()
+}
+
+#[bench]
+fn decode_header(b: &mut Bencher) {
+    let bytes = sealed_header();
+    b.iter(|| {
+        let header: Header = rlp::decode(&bytes).unwrap();
+        (header.hash(), *header.parent_hash(), header.number(), header.seal().len())
+    });
+}
+
+#[bench]
+fn read_header_view(b: &mut Bencher) {
+    let bytes = sealed_header();
+    b.iter(|| {
+        let view = HeaderView::new(&bytes);
+        (view.hash(), view.parent_hash(), view.number(), view.seal().len())
+    });
+}
+
+#[bench]
+fn validate_and_read_header_view(b: &mut Bencher) {
+    let bytes = sealed_header();
+    b.iter(|| {
+        let view = ValidatedHeaderView::new(&bytes).unwrap();
+        (view.hash(), view.parent_hash(), view.number(), view.seal().len())
+    });
+}
+
+#[bench]
+fn read_validated_header_view(b: &mut Bencher) {
+    let bytes = sealed_header();
+    let view = ValidatedHeaderView::new(&bytes).unwrap();
+    b.iter(|| (view.hash(), view.parent_hash(), view.number(), view.seal().len()));
+}
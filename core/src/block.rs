@@ -19,12 +19,15 @@ use crate::error::{BlockError, Error};
 use ccrypto::BLAKE_NULL_RLP;
 use ckey::Ed25519Public as Public;
 use coordinator::engine::{BlockExecutor, ExecutionId};
-use coordinator::types::Event;
+use coordinator::types::{Event, TransactionOutcome};
 use coordinator::{Header as PreHeader, Transaction, TransactionWithMetadata};
-use cstate::{CurrentValidatorSet, NextValidatorSet, StateDB, StateError, StateWithCache, TopLevelState, TopState};
+use cstate::{
+    CurrentValidatorSet, NextValidatorSet, StateDB, StateError, StateWithCache, TopLevelState, TopState, TopStateView,
+};
 use ctypes::header::{Header, Seal};
+pub use ctypes::module_roots_hash;
 use ctypes::util::unexpected::Mismatch;
-use ctypes::{CompactValidatorSet, ConsensusParams, TxHash};
+use ctypes::{CompactValidatorSet, ConsensusParams, TxHash, TxOrdering};
 use merkle_trie::skewed_merkle_root;
 use primitives::{Bytes, H256};
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
@@ -119,12 +122,30 @@ impl ExecutedBlock {
     pub fn header(&self) -> &Header {
         &self.header
     }
+
+    fn seal(&mut self, engine: &dyn ConsensusEngine, seal: Vec<Bytes>) -> Result<(), BlockError> {
+        let expected_seal_fields = engine.seal_fields(self.header());
+        if seal.len() != expected_seal_fields {
+            return Err(BlockError::InvalidSealArity(Mismatch {
+                expected: expected_seal_fields,
+                found: seal.len(),
+            }))
+        }
+        self.header.set_seal(seal);
+        Ok(())
+    }
 }
 
 /// Block that is ready for transactions to be added.
 pub struct OpenBlock {
     execution_id: Option<ExecutionId>,
     block: ExecutedBlock,
+    /// Derived from the seal of the parent. It seeds the randomness given to the modules,
+    /// and decides the order of the transactions when the consensus params ask for the seeded ordering.
+    seed: H256,
+    /// The delegations of the validators of the parent, indexed as the precommit bitset of the seal.
+    /// They weight the precommit timestamps.
+    parent_delegations: Vec<u64>,
 }
 
 impl OpenBlock {
@@ -138,6 +159,11 @@ impl OpenBlock {
         extra_data: Bytes,
     ) -> Result<Self, Error> {
         let state = TopLevelState::from_existing(db, *parent.state_root()).map_err(StateError::from)?;
+        let parent_delegations = CurrentValidatorSet::load_from_state(&state)?
+            .create_compact_validator_set()
+            .iter()
+            .map(|entry| entry.delegation)
+            .collect();
         let mut block = ExecutedBlock::new(state, parent);
         let header = &mut block.header;
 
@@ -153,14 +179,15 @@ impl OpenBlock {
         Ok(OpenBlock {
             execution_id: None,
             block,
+            seed: TxOrdering::seed(parent.seal()),
+            parent_delegations,
         })
     }
 
     pub fn open(&mut self, block_executor: &dyn BlockExecutor, engine: &dyn ConsensusEngine) -> Result<(), Error> {
+        let seal_view = TendermintSealView::new(self.header().seal());
         let last_committed_validators = {
-            let validator_bitset = TendermintSealView::new(self.header().seal())
-                .bitset()
-                .map_err(|_| Error::Block(BlockError::InvalidSeal))?;
+            let validator_bitset = seal_view.bitset().map_err(|_| Error::Block(BlockError::InvalidSeal))?;
             let possible_authors = engine.possible_authors(None)?.expect("Tendermint must have possible authors");
             let committed_validators_result: Result<Vec<_>, _> = validator_bitset
                 .true_index_iter()
@@ -177,8 +204,18 @@ impl OpenBlock {
             last_committed_validators,
             self.header().extra_data().clone(),
         );
+        let median_time = seal_view
+            .median_timestamp(&self.parent_delegations)
+            .map_err(|_| Error::Block(BlockError::InvalidSeal))?
+            .unwrap_or_else(|| self.header().timestamp());
         let verified_crimes: Vec<_> = self.block.evidences.iter().map(|e| e.into()).collect();
-        self.execution_id = Some(block_executor.open_block(self.block.state_mut(), &pre_header, &verified_crimes)?);
+        self.execution_id = Some(block_executor.open_block(
+            self.block.state_mut(),
+            &pre_header,
+            &self.seed,
+            median_time,
+            &verified_crimes,
+        )?);
 
         Ok(())
     }
@@ -193,12 +230,12 @@ impl OpenBlock {
         let transaction_results = block_executor
             .execute_transactions(execution_id, self.inner_mut().state_mut(), &transactions)
             .map_err(|_| Error::Other(String::from("Rejected while executing transactions")))?;
-        self.block.transactions.append(&mut transactions);
         // TODO: How to do this without copy?
         let mut tx_events: HashMap<TxHash, Vec<Event>> = HashMap::new();
-        for (tx, result) in transactions.into_iter().zip(transaction_results.into_iter()) {
-            tx_events.insert(tx.hash(), result.events);
+        for (tx, result) in transactions.iter().zip(transaction_results.into_iter()) {
+            tx_events.extend(events_by_hash(tx, result));
         }
+        self.block.transactions.append(&mut transactions);
         self.block.tx_events = tx_events;
         Ok(())
     }
@@ -217,14 +254,45 @@ impl OpenBlock {
         mut transactions: impl Iterator<Item = &'a TransactionWithMetadata> + 'a,
     ) {
         let execution_id = self.execution_id.expect("A block can be prepared only after opening the block");
-        let proposed_txs = block_executor.prepare_block(execution_id, self.block.state_mut(), &mut transactions);
+        let consensus_params = self.consensus_params();
+        let max_body_size = consensus_params.max_body_size() as usize;
+        // Before the exact body size, the assembly stopped short of the last byte of the max body size.
+        let max_body_size = if consensus_params.activations().exact_body_size(self.block.header.number()) {
+            max_body_size
+        } else {
+            max_body_size.saturating_sub(1)
+        };
+        let order_seed = match consensus_params.tx_ordering() {
+            TxOrdering::Proposer => None,
+            TxOrdering::Seeded => Some(self.seed),
+        };
+        let proposed_txs = block_executor.prepare_block(
+            execution_id,
+            self.block.state_mut(),
+            &mut transactions,
+            max_body_size,
+            order_seed,
+        );
         self.block.transactions.append(&mut proposed_txs.iter().map(|(tx, _)| (*tx).clone()).collect());
-        self.block.tx_events = proposed_txs.into_iter().map(|(tx, outcome)| (tx.hash(), outcome.events)).collect();
+        self.block.tx_events = proposed_txs.into_iter().flat_map(|(tx, outcome)| events_by_hash(tx, outcome)).collect();
+    }
+
+    /// The consensus params of this block.
+    /// The parent's closing updates are already in the state, and this block's are not until it's closed.
+    fn consensus_params(&self) -> ConsensusParams {
+        *self
+            .block
+            .state()
+            .metadata()
+            .expect("Metadata of an open block is readable")
+            .expect("Metadata always exist")
+            .consensus_params()
     }
 
     /// Turn this into a `ClosedBlock`.
     pub fn close(mut self, block_executor: &dyn BlockExecutor) -> Result<ClosedBlock, Error> {
         let execution_id = self.execution_id.expect("A block can be closed only when it's open");
+        let activations = *self.consensus_params().activations();
         let block_outcome = block_executor.close_block(execution_id)?;
 
         self.block.block_events = block_outcome.events;
@@ -244,8 +312,15 @@ impl OpenBlock {
             e
         })?;
         self.block.header.set_state_root(state_root);
+        if activations.module_roots(self.block.header.number()) {
+            let module_roots = self.block.state().module_roots().map_err(StateError::from)?;
+            self.block.header.set_module_roots_hash(Some(module_roots_hash(&module_roots)));
+        }
 
         self.block.header.set_next_validator_set_hash(next_validator_set_hash);
+        if activations.external_anchor(self.block.header.number()) {
+            self.block.header.set_external_anchor(block_outcome.external_anchor);
+        }
 
         if self.block.header.transactions_root() == &BLAKE_NULL_RLP {
             self.block.header.set_transactions_root(skewed_merkle_root(
@@ -274,15 +349,7 @@ impl OpenBlock {
     ///
     /// NOTE: This does not check the validity of `seal` with the engine.
     pub fn seal(&mut self, engine: &dyn ConsensusEngine, seal: Vec<Bytes>) -> Result<(), BlockError> {
-        let expected_seal_fields = engine.seal_fields(self.header());
-        if seal.len() != expected_seal_fields {
-            return Err(BlockError::InvalidSealArity(Mismatch {
-                expected: expected_seal_fields,
-                found: seal.len(),
-            }))
-        }
-        self.block.header.set_seal(seal);
-        Ok(())
+        self.block.seal(engine, seal)
     }
 
     pub fn inner_mut(&mut self) -> &mut ExecutedBlock {
@@ -329,6 +396,13 @@ impl ClosedBlock {
         self.header().rlp_blake(&Seal::Without)
     }
 
+    /// Replaces the seal given before the block was opened.
+    ///
+    /// NOTE: This does not check the validity of `seal` with the engine.
+    pub fn seal(&mut self, engine: &dyn ConsensusEngine, seal: Vec<Bytes>) -> Result<(), BlockError> {
+        self.block.seal(engine, seal)
+    }
+
     pub fn rlp_bytes(&self) -> Bytes {
         let mut block_rlp = RlpStream::new_list(3);
         self.block.header.stream_rlp(&mut block_rlp, &Seal::With);
@@ -400,6 +474,22 @@ impl IsBlock for ClosedBlock {
     }
 }
 
+/// Pairs the events of a transaction with its hash.
+/// The events of the sub-transactions of a composite transaction are kept by their hashes as well,
+/// so the outcome of each sub-transaction can be looked up.
+fn events_by_hash(tx: &Transaction, outcome: TransactionOutcome) -> Vec<(TxHash, Vec<Event>)> {
+    let mut events = vec![(tx.hash(), outcome.events)];
+    if let Some(sub_transactions) = tx.sub_transactions() {
+        events.extend(
+            sub_transactions
+                .iter()
+                .zip(outcome.sub_outcomes)
+                .map(|(sub_transaction, sub_outcome)| (sub_transaction.hash(), sub_outcome.events)),
+        );
+    }
+    events
+}
+
 /// Enact the block given by block header, transactions and uncles
 pub fn enact(
     header: &Header,
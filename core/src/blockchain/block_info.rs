@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use super::route::TreeRoute;
 use crate::views::{BlockView, HeaderView};
 use ctypes::BlockHash;
 use primitives::Bytes;
@@ -25,6 +26,12 @@ pub enum BestBlockChanged {
     CanonChainAppended {
         best_block: Bytes,
     },
+    /// Another branch becomes canonical chain.
+    BranchBecomingCanonChain {
+        best_block: Bytes,
+        /// The route from the previous best block to the parent of the new one.
+        tree_route: TreeRoute,
+    },
     /// Nothing changed.
     None,
 }
@@ -38,12 +45,26 @@ impl BestBlockChanged {
         let block = match self {
             BestBlockChanged::CanonChainAppended {
                 best_block,
+            }
+            | BestBlockChanged::BranchBecomingCanonChain {
+                best_block,
+                ..
             } => best_block,
             BestBlockChanged::None => return None,
         };
 
         Some(BlockView::new(block))
     }
+
+    pub fn tree_route(&self) -> Option<&TreeRoute> {
+        match self {
+            BestBlockChanged::BranchBecomingCanonChain {
+                tree_route,
+                ..
+            } => Some(tree_route),
+            _ => None,
+        }
+    }
 }
 
 /// Describes how best block is changed
@@ -53,6 +74,12 @@ pub enum BestHeaderChanged {
     CanonChainAppended {
         best_header: Vec<u8>,
     },
+    /// Another branch becomes canonical chain.
+    BranchBecomingCanonChain {
+        best_header: Vec<u8>,
+        /// The route from the previous best header to the parent of the new one.
+        tree_route: TreeRoute,
+    },
     /// Nothing changed.
     None,
 }
@@ -66,10 +93,24 @@ impl BestHeaderChanged {
         let header = match self {
             BestHeaderChanged::CanonChainAppended {
                 best_header,
+            }
+            | BestHeaderChanged::BranchBecomingCanonChain {
+                best_header,
+                ..
             } => best_header,
             BestHeaderChanged::None => return None,
         };
 
         Some(HeaderView::new(header))
     }
+
+    pub fn tree_route(&self) -> Option<&TreeRoute> {
+        match self {
+            BestHeaderChanged::BranchBecomingCanonChain {
+                tree_route,
+                ..
+            } => Some(tree_route),
+            _ => None,
+        }
+    }
 }
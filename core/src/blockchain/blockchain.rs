@@ -15,12 +15,14 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::block_info::BestBlockChanged;
+use super::body_compression::BodyCompression;
 use super::body_db::{BodyDB, BodyProvider};
 use super::event_db::{EventDB, EventProvider};
 use super::extras::{BlockDetails, TransactionAddress};
 use super::headerchain::{HeaderChain, HeaderProvider};
 use super::route::tree_route;
 use super::update_result::ChainUpdateResult;
+use super::validator_set_db::{ValidatorSetDB, ValidatorSetProvider};
 use crate::blockchain_info::BlockChainInfo;
 use crate::consensus::ConsensusEngine;
 use crate::db;
@@ -29,7 +31,7 @@ use crate::event::{EventSource, EventsWithSource};
 use crate::transaction::LocalizedTransaction;
 use crate::views::{BlockView, HeaderView};
 use coordinator::types::Event;
-use ctypes::{BlockHash, BlockNumber, TxHash};
+use ctypes::{BlockHash, BlockNumber, CompactValidatorSet, TxHash};
 use kvdb::{DBTransaction, KeyValueDB};
 use parking_lot::RwLock;
 use primitives::H256;
@@ -51,6 +53,7 @@ pub struct BlockChain {
     headerchain: HeaderChain,
     body_db: BodyDB,
     event_db: EventDB,
+    validator_set_db: ValidatorSetDB,
 
     pending_best_block_hash: RwLock<Option<BlockHash>>,
     pending_best_proposal_block_hash: RwLock<Option<BlockHash>>,
@@ -72,7 +75,7 @@ fn get_or_insert_with<F: FnOnce() -> BlockHash>(db: &dyn KeyValueDB, key: &[u8],
 
 impl BlockChain {
     /// Create new instance of blockchain from given Genesis.
-    pub fn new(genesis: &[u8], db: Arc<dyn KeyValueDB>) -> Self {
+    pub fn new(genesis: &[u8], db: Arc<dyn KeyValueDB>, body_compression: Option<BodyCompression>) -> Self {
         let genesis_block = BlockView::new(genesis);
 
         // load best block
@@ -84,8 +87,9 @@ impl BlockChain {
             best_proposal_block_hash: RwLock::new(best_proposal_block_hash),
 
             headerchain: HeaderChain::new(&genesis_block.header_view(), db.clone()),
-            body_db: BodyDB::new(&genesis_block, db.clone()),
+            body_db: BodyDB::new(&genesis_block, db.clone(), body_compression),
             event_db: EventDB::new(db.clone()),
+            validator_set_db: ValidatorSetDB::new(db.clone()),
 
             pending_best_block_hash: RwLock::new(None),
             pending_best_proposal_block_hash: RwLock::new(None),
@@ -143,6 +147,31 @@ impl BlockChain {
         *self.pending_best_proposal_block_hash.write() = Some(*hash);
     }
 
+    /// Rewrites the transaction addresses of the given canonical block.
+    pub fn reindex_transactions(&self, batch: &mut DBTransaction, block_hash: BlockHash, tx_hashes: Vec<TxHash>) {
+        ctrace!(BLOCKCHAIN, "Reindexing the transactions of {}", block_hash);
+        self.body_db.reindex_transactions(batch, block_hash, tx_hashes);
+    }
+
+    /// Records the validator set committed by the `next_validator_set_hash` of the block.
+    /// Expects the set to be already verified against the block's header.
+    pub fn insert_next_validator_set(
+        &self,
+        batch: &mut DBTransaction,
+        block_hash: BlockHash,
+        validator_set: CompactValidatorSet,
+    ) {
+        ctrace!(BLOCKCHAIN, "Inserting the next validator set of {}", block_hash);
+        self.validator_set_db.insert_next_validator_set(batch, block_hash, validator_set);
+    }
+
+    /// Inserts the events of a known block, which were not stored when the block was inserted.
+    pub fn insert_events(&self, batch: &mut DBTransaction, events_with_sources: Vec<EventsWithSource>) {
+        for events_with_source in events_with_sources {
+            self.event_db.insert_events(batch, events_with_source.source, events_with_source.events);
+        }
+    }
+
     /// Inserts the block into backing cache database.
     /// Expects the block to be valid and already verified.
     /// If the block is already known, does nothing.
@@ -195,7 +224,7 @@ impl BlockChain {
         ctrace!(BLOCKCHAIN, "Committing.");
         self.headerchain.commit();
         self.body_db.commit();
-        // NOTE: There are no commit for EventDB
+        // NOTE: There are no commit for EventDB and ValidatorSetDB
 
         let mut best_block_hash = self.best_block_hash.write();
         let mut pending_best_block_hash = self.pending_best_block_hash.write();
@@ -213,10 +242,13 @@ impl BlockChain {
     }
 
     /// Compare the number and the view of current block with these of best block
-    fn is_new_header_eligible_to_be_best(&self, new_header: &HeaderView<'_>) -> bool {
+    fn is_new_header_eligible_to_be_best(&self, new_header: &HeaderView<'_>, engine: &dyn ConsensusEngine) -> bool {
+        let best_hash = self.best_proposal_block_hash();
         let details_of_best_block = self.best_proposal_block_detail();
 
-        (new_header.number(), details_of_best_block.view) > (details_of_best_block.number, new_header.view())
+        let new = (new_header.number(), details_of_best_block.view);
+        let best = (details_of_best_block.number, new_header.view());
+        new > best || (new == best && engine.replaces_best_on_tie(new_header.hash(), best_hash))
     }
 
     /// Calculate how best block is changed
@@ -227,7 +259,7 @@ impl BlockChain {
         let grandparent_hash_of_new_block = parent_details_of_new_block.parent;
         let prev_best_hash = self.best_block_hash();
 
-        if self.is_new_header_eligible_to_be_best(&new_header)
+        if self.is_new_header_eligible_to_be_best(&new_header, engine)
             && engine.can_change_canon_chain(parent_hash_of_new_block, grandparent_hash_of_new_block, prev_best_hash)
         {
             cinfo!(
@@ -250,18 +282,21 @@ impl BlockChain {
             } else {
                 new_block.rlp().as_raw().to_vec()
             };
-            match route.retracted.len() {
-                0 => BestBlockChanged::CanonChainAppended {
+            if route.retracted.is_empty() {
+                BestBlockChanged::CanonChainAppended {
+                    best_block: new_best_block,
+                }
+            } else {
+                cinfo!(
+                    BLOCKCHAIN,
+                    "Block #{}({}) switches the canonical chain, retracting {} blocks",
+                    new_header.number(),
+                    new_header.hash(),
+                    route.retracted.len()
+                );
+                BestBlockChanged::BranchBecomingCanonChain {
                     best_block: new_best_block,
-                },
-                _ => {
-                    cerror!(
-                        BLOCKCHAIN,
-                        "Older/Forked block header #{}({}) is inserted as a new block",
-                        new_header.number(),
-                        new_header.hash()
-                    );
-                    BestBlockChanged::None
+                    tree_route: route,
                 }
             }
         } else {
@@ -465,4 +500,10 @@ impl EventProvider for BlockChain {
     }
 }
 
+impl ValidatorSetProvider for BlockChain {
+    fn next_validator_set(&self, hash: &BlockHash) -> Option<CompactValidatorSet> {
+        self.validator_set_db.next_validator_set(hash)
+    }
+}
+
 impl BlockProvider for BlockChain {}
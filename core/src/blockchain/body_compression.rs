@@ -0,0 +1,158 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::io::{self, Read};
+use std::str::FromStr;
+
+/// A compression of the block bodies, both in the DB and in the body responses of the block sync.
+///
+/// The bodies in the DB start with the tag of their compression.
+/// A body in the RLP form starts with a list header of 0xc0 or more, so it never looks like a tagged one.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BodyCompression {
+    Lz4,
+    Zstd,
+}
+
+const LZ4_TAG: u8 = 0x01;
+const ZSTD_TAG: u8 = 0x02;
+const ZSTD_LEVEL: i32 = 3;
+
+impl BodyCompression {
+    pub fn tag(self) -> u8 {
+        match self {
+            BodyCompression::Lz4 => LZ4_TAG,
+            BodyCompression::Zstd => ZSTD_TAG,
+        }
+    }
+
+    pub fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            LZ4_TAG => Some(BodyCompression::Lz4),
+            ZSTD_TAG => Some(BodyCompression::Zstd),
+            _ => None,
+        }
+    }
+
+    pub fn compress(self, raw: &[u8]) -> Vec<u8> {
+        match self {
+            BodyCompression::Lz4 => lz4::block::compress(raw, None, true),
+            BodyCompression::Zstd => zstd::encode_all(raw, ZSTD_LEVEL),
+        }
+        .expect("Compressing bytes in memory always succeeds")
+    }
+
+    /// Compresses the bytes, putting the tag first.
+    pub fn compress_tagged(self, raw: &[u8]) -> Vec<u8> {
+        let mut tagged = vec![self.tag()];
+        tagged.extend_from_slice(&self.compress(raw));
+        tagged
+    }
+
+    /// Decompresses the tagged bytes.
+    /// Returns `None` if the bytes don't start with the tag of a compression.
+    pub fn decompress_tagged(tagged: &[u8], limit: usize) -> Option<io::Result<Vec<u8>>> {
+        let (tag, compressed) = tagged.split_first()?;
+        let compression = Self::from_tag(*tag)?;
+        Some(compression.decompress(compressed, limit))
+    }
+
+    /// Decompresses the bytes, failing if they would be larger than `limit`.
+    pub fn decompress(self, compressed: &[u8], limit: usize) -> io::Result<Vec<u8>> {
+        let too_large = || io::Error::new(io::ErrorKind::InvalidData, "The decompressed body is too large");
+        match self {
+            BodyCompression::Lz4 => {
+                // The size is prepended as a little-endian u32.
+                let mut size = [0u8; 4];
+                size.copy_from_slice(compressed.get(..4).ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?);
+                if u32::from_le_bytes(size) as usize > limit {
+                    return Err(too_large())
+                }
+                lz4::block::decompress(compressed, None)
+            }
+            BodyCompression::Zstd => {
+                let mut decompressed = Vec::new();
+                zstd::stream::read::Decoder::new(compressed)?.take(limit as u64 + 1).read_to_end(&mut decompressed)?;
+                if decompressed.len() > limit {
+                    return Err(too_large())
+                }
+                Ok(decompressed)
+            }
+        }
+    }
+}
+
+impl fmt::Display for BodyCompression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BodyCompression::Lz4 => write!(f, "lz4"),
+            BodyCompression::Zstd => write!(f, "zstd"),
+        }
+    }
+}
+
+impl FromStr for BodyCompression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lz4" => Ok(BodyCompression::Lz4),
+            "zstd" => Ok(BodyCompression::Zstd),
+            _ => Err(format!("Invalid body compression given: {}. Expected lz4/zstd.", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cbor_like_body() -> Vec<u8> {
+        let mut body = vec![0xc0 + 55 + 2, 0x10, 0x00];
+        for i in 0..4096u32 {
+            body.extend_from_slice(b"\xa2dkindhtransfervamount");
+            body.extend_from_slice(&(i % 17).to_be_bytes());
+        }
+        body
+    }
+
+    #[test]
+    fn compressed_bodies_are_restored() {
+        let body = cbor_like_body();
+        for compression in &[BodyCompression::Lz4, BodyCompression::Zstd] {
+            let compressed = compression.compress_tagged(&body);
+            assert_eq!(compressed[0], compression.tag());
+            assert!(compressed.len() < body.len() / 4, "{} doesn't compress the body", compression);
+            assert_eq!(BodyCompression::decompress_tagged(&compressed, body.len()).unwrap().unwrap(), body);
+        }
+    }
+
+    #[test]
+    fn rlp_bodies_are_not_taken_for_compressed_ones() {
+        assert!(BodyCompression::decompress_tagged(&cbor_like_body(), usize::MAX).is_none());
+        assert!(BodyCompression::decompress_tagged(&[], usize::MAX).is_none());
+    }
+
+    #[test]
+    fn decompression_beyond_the_limit_fails() {
+        let body = cbor_like_body();
+        for compression in &[BodyCompression::Lz4, BodyCompression::Zstd] {
+            let compressed = compression.compress(&body);
+            assert!(compression.decompress(&compressed, body.len() - 1).is_err());
+        }
+    }
+}
@@ -15,8 +15,9 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::block_info::BestBlockChanged;
+use super::body_compression::BodyCompression;
 use super::extras::TransactionAddress;
-use crate::db::{self, CacheUpdatePolicy, Readable, Writable};
+use crate::db::{self, CacheUpdatePolicy, Key, Readable, Writable};
 use crate::encoded;
 use crate::views::BlockView;
 use ctypes::{BlockHash, TransactionIndex, TxHash};
@@ -37,17 +38,23 @@ pub struct BodyDB {
     body_cache: Mutex<LruCache<BlockHash, Bytes>>,
     address_by_hash_cache: RwLock<HashMap<TxHash, TransactionAddress>>,
     pending_addresses_by_hash: RwLock<HashMap<TxHash, TransactionAddress>>,
+    /// The transactions of the retracted blocks, which are not in the canonical chain anymore.
+    pending_retracted_tx_hashes: RwLock<Vec<TxHash>>,
+    /// The compression for the bodies to be inserted. `None` keeps the legacy RLP compression.
+    compression: Option<BodyCompression>,
 
     db: Arc<dyn KeyValueDB>,
 }
 
 impl BodyDB {
     /// Create new instance of blockchain from given Genesis.
-    pub fn new(genesis: &BlockView<'_>, db: Arc<dyn KeyValueDB>) -> Self {
+    pub fn new(genesis: &BlockView<'_>, db: Arc<dyn KeyValueDB>, compression: Option<BodyCompression>) -> Self {
         let bdb = Self {
             body_cache: Mutex::new(LruCache::new(BODY_CACHE_SIZE)),
             address_by_hash_cache: RwLock::new(HashMap::new()),
             pending_addresses_by_hash: RwLock::new(HashMap::new()),
+            pending_retracted_tx_hashes: RwLock::new(Vec::new()),
+            compression,
 
             db,
         };
@@ -73,18 +80,40 @@ impl BodyDB {
             return
         }
 
-        let compressed_body = compress(&Self::block_to_body(block), blocks_swapper());
+        let body = Self::block_to_body(block);
+        let compressed_body = match self.compression {
+            Some(compression) => compression.compress_tagged(&body),
+            None => compress(&body, blocks_swapper()),
+        };
 
         // store block in db
         batch.put(db::COL_BODIES, hash.as_ref(), &compressed_body);
     }
 
     pub fn update_best_block(&self, batch: &mut DBTransaction, best_block_changed: &BestBlockChanged) {
+        let new_addresses = self.new_transaction_address_entries(best_block_changed);
+        let retracted_tx_hashes = self.retracted_transaction_hashes(best_block_changed, &new_addresses);
+        for tx_hash in &retracted_tx_hashes {
+            batch.delete(db::COL_EXTRA, Key::<TransactionAddress>::key(tx_hash).as_ref());
+        }
+        self.pending_retracted_tx_hashes.write().extend(retracted_tx_hashes);
+
+        let mut pending_addresses_by_hash = self.pending_addresses_by_hash.write();
+        batch.extend_with_cache(
+            db::COL_EXTRA,
+            &mut *pending_addresses_by_hash,
+            new_addresses,
+            CacheUpdatePolicy::Overwrite,
+        );
+    }
+
+    /// Rewrites the addresses of the transactions in the given block.
+    pub fn reindex_transactions(&self, batch: &mut DBTransaction, block_hash: BlockHash, tx_hashes: Vec<TxHash>) {
         let mut pending_addresses_by_hash = self.pending_addresses_by_hash.write();
         batch.extend_with_cache(
             db::COL_EXTRA,
             &mut *pending_addresses_by_hash,
-            self.new_transaction_address_entries(best_block_changed),
+            tx_hash_and_address_entries(block_hash, tx_hashes).collect(),
             CacheUpdatePolicy::Overwrite,
         );
     }
@@ -96,6 +125,9 @@ impl BodyDB {
 
         let new_txs_by_hash = mem::replace(&mut *pending_addresses_by_hash, HashMap::new());
 
+        for tx_hash in self.pending_retracted_tx_hashes.write().drain(..) {
+            address_by_hash_cache.remove(&tx_hash);
+        }
         address_by_hash_cache.extend(new_txs_by_hash.into_iter());
     }
 
@@ -108,14 +140,33 @@ impl BodyDB {
             Some(block) => block,
             None => return HashMap::new(),
         };
-        let tx_hashes = block.transaction_hashes();
-
-        match best_block_changed {
-            BestBlockChanged::CanonChainAppended {
-                ..
-            } => tx_hash_and_address_entries(best_block_changed.new_best_hash().unwrap(), tx_hashes).collect(),
-            BestBlockChanged::None => HashMap::new(),
+        let mut addresses = HashMap::new();
+        if let Some(tree_route) = best_block_changed.tree_route() {
+            for hash in &tree_route.enacted {
+                let body = self.block_body(hash).expect("Enacted blocks are already inserted");
+                addresses.extend(tx_hash_and_address_entries(*hash, body.transaction_hashes()));
+            }
         }
+        addresses.extend(tx_hash_and_address_entries(block.hash(), block.transaction_hashes()));
+        addresses
+    }
+
+    /// This function returns the transactions of the retracted blocks that are not included in the new branch.
+    fn retracted_transaction_hashes(
+        &self,
+        best_block_changed: &BestBlockChanged,
+        new_addresses: &HashMap<TxHash, TransactionAddress>,
+    ) -> Vec<TxHash> {
+        let tree_route = match best_block_changed.tree_route() {
+            Some(tree_route) => tree_route,
+            None => return Vec::new(),
+        };
+        tree_route
+            .retracted
+            .iter()
+            .flat_map(|hash| self.block_body(hash).expect("Retracted blocks are already inserted").transaction_hashes())
+            .filter(|tx_hash| !new_addresses.contains_key(tx_hash))
+            .collect()
     }
 
     /// Create a block body from a block.
@@ -166,7 +217,11 @@ impl BodyProvider for BodyDB {
         let compressed_body =
             self.db.get(db::COL_BODIES, hash.as_ref()).expect("Low level database error. Some issue with disk?")?;
 
-        let raw_body = decompress(&compressed_body, blocks_swapper());
+        // Bodies written before the compression was changed are still readable.
+        let raw_body = match BodyCompression::decompress_tagged(&compressed_body, usize::MAX) {
+            Some(decompressed) => decompressed.expect("The body in the database is corrupted"),
+            None => decompress(&compressed_body, blocks_swapper()),
+        };
         let mut lock = self.body_cache.lock();
         lock.insert(*hash, raw_body.clone());
 
@@ -185,3 +240,102 @@ fn tx_hash_and_address_entries(
         })
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+    use crate::blockchain::route::TreeRoute;
+    use coordinator::Transaction;
+    use ctypes::header::{Header, Seal};
+
+    fn block_with_large_payload(number: u64) -> Bytes {
+        let mut header = Header::default();
+        header.set_number(number);
+        let transactions =
+            (0..8).map(|i| Transaction::new("token".to_string(), vec![i as u8; 4096])).collect::<Vec<_>>();
+        Block {
+            header,
+            evidences: Vec::new(),
+            transactions,
+        }
+        .rlp_bytes(&Seal::With)
+    }
+
+    fn block_with_transactions(number: u64, timestamp: u64, bodies: &[u8]) -> Bytes {
+        let mut header = Header::default();
+        header.set_number(number);
+        header.set_timestamp(timestamp);
+        let transactions = bodies.iter().map(|body| Transaction::new("token".to_string(), vec![*body])).collect();
+        Block {
+            header,
+            evidences: Vec::new(),
+            transactions,
+        }
+        .rlp_bytes(&Seal::With)
+    }
+
+    #[test]
+    fn bodies_in_every_compression_are_readable() {
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let genesis = block_with_large_payload(0);
+        let blocks: Vec<_> = (1..4).map(block_with_large_payload).collect();
+
+        let compressions = [None, Some(BodyCompression::Lz4), Some(BodyCompression::Zstd)];
+        for (block, compression) in blocks.iter().zip(compressions.iter()) {
+            let body_db = BodyDB::new(&BlockView::new(&genesis), db.clone(), *compression);
+            let mut batch = DBTransaction::new();
+            body_db.insert_body(&mut batch, &BlockView::new(block));
+            db.write(batch).unwrap();
+        }
+
+        let body_db = BodyDB::new(&BlockView::new(&genesis), db, None);
+        for block in blocks.iter().chain(Some(&genesis)) {
+            let block = BlockView::new(block);
+            let body = body_db.block_body(&block.hash()).unwrap();
+            assert_eq!(body.transactions(), block.transactions());
+        }
+    }
+
+    #[test]
+    fn transactions_of_the_retracted_blocks_are_unindexed() {
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let genesis = block_with_transactions(0, 0, &[]);
+        let retracted = block_with_transactions(1, 1, &[1, 2]);
+        let enacted = block_with_transactions(1, 2, &[2, 3]);
+        let body_db = BodyDB::new(&BlockView::new(&genesis), db.clone(), None);
+
+        let mut batch = DBTransaction::new();
+        body_db.insert_body(&mut batch, &BlockView::new(&retracted));
+        body_db.update_best_block(&mut batch, &BestBlockChanged::CanonChainAppended {
+            best_block: retracted.clone(),
+        });
+        db.write(batch).unwrap();
+        body_db.commit();
+
+        let mut batch = DBTransaction::new();
+        body_db.insert_body(&mut batch, &BlockView::new(&enacted));
+        body_db.update_best_block(&mut batch, &BestBlockChanged::BranchBecomingCanonChain {
+            best_block: enacted.clone(),
+            tree_route: TreeRoute {
+                ancestor: BlockView::new(&genesis).hash(),
+                enacted: Vec::new(),
+                retracted: vec![BlockView::new(&retracted).hash()],
+            },
+        });
+        db.write(batch).unwrap();
+        body_db.commit();
+
+        let tx_hashes = BlockView::new(&retracted).transaction_hashes();
+        assert_eq!(body_db.transaction_address(&tx_hashes[0]), None);
+        for (index, tx_hash) in BlockView::new(&enacted).transaction_hashes().into_iter().enumerate() {
+            assert_eq!(
+                body_db.transaction_address(&tx_hash),
+                Some(TransactionAddress {
+                    block_hash: BlockView::new(&enacted).hash(),
+                    index: index as TransactionIndex,
+                })
+            );
+        }
+    }
+}
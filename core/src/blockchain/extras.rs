@@ -16,7 +16,7 @@
 
 use crate::db::Key;
 use crate::types::TransactionId;
-use ctypes::{BlockHash, BlockNumber, TransactionIndex, TxHash};
+use ctypes::{BlockHash, BlockNumber, CompactValidatorSet, TransactionIndex, TxHash};
 use primitives::{H256, H264};
 
 /// Represents index of extra data in database
@@ -28,6 +28,8 @@ enum ExtrasIndex {
     BlockHash = 1,
     /// Transaction address index
     TransactionAddress = 2,
+    /// Next validator set index
+    NextValidatorSet = 3,
 }
 
 fn with_index(hash: &H256, i: ExtrasIndex) -> H264 {
@@ -67,6 +69,14 @@ impl Key<BlockDetails> for BlockHash {
     }
 }
 
+impl Key<CompactValidatorSet> for BlockHash {
+    type Target = H264;
+
+    fn key(&self) -> H264 {
+        with_index(self, ExtrasIndex::NextValidatorSet)
+    }
+}
+
 impl Key<TransactionAddress> for TxHash {
     type Target = H264;
 
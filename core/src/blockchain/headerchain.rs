@@ -235,15 +235,19 @@ impl HeaderChain {
     fn new_hash_entries(&self, best_header_changed: &BestHeaderChanged) -> HashMap<BlockNumber, BlockHash> {
         let mut hashes = HashMap::new();
 
-        match best_header_changed {
-            BestHeaderChanged::None => (),
-            BestHeaderChanged::CanonChainAppended {
-                best_header,
-            } => {
-                let best_header_view = HeaderView::new(best_header);
-                hashes.insert(best_header_view.number(), best_header_view.hash());
+        let best_header_view = match best_header_changed.header() {
+            Some(header) => header,
+            None => return hashes,
+        };
+        // The new branch is at least as long as the retracted one, so every number of the retracted blocks is
+        // overwritten.
+        if let Some(tree_route) = best_header_changed.tree_route() {
+            for hash in &tree_route.enacted {
+                let details = self.block_details(hash).expect("Enacted blocks are already inserted");
+                hashes.insert(details.number, *hash);
             }
         }
+        hashes.insert(best_header_view.number(), best_header_view.hash());
 
         hashes
     }
@@ -267,12 +271,14 @@ impl HeaderChain {
     }
 
     /// Compare the number and the view of current block with these of best block
-    fn is_new_header_eligible_to_be_best(&self, new_header: &HeaderView<'_>) -> bool {
+    fn is_new_header_eligible_to_be_best(&self, new_header: &HeaderView<'_>, engine: &dyn ConsensusEngine) -> bool {
         let best_proposal_block_hash = self.best_header_hash();
         let best_proposal_block_detail =
             self.block_details(&best_proposal_block_hash).expect("Best proposal block always exists");
 
-        (new_header.number(), best_proposal_block_detail.view) > (best_proposal_block_detail.number, new_header.view())
+        let new = (new_header.number(), best_proposal_block_detail.view);
+        let best = (best_proposal_block_detail.number, new_header.view());
+        new > best || (new == best && engine.replaces_best_on_tie(new_header.hash(), best_proposal_block_hash))
     }
 
     /// Calculate how best block is changed
@@ -281,7 +287,7 @@ impl HeaderChain {
         let parent_details_of_new_header = self.block_details(&parent_hash_of_new_header).expect("Invalid parent hash");
         let grandparent_hash_of_new_header = parent_details_of_new_header.parent;
         let prev_best_hash = self.best_header_hash();
-        let is_new_best = self.is_new_header_eligible_to_be_best(new_header)
+        let is_new_best = self.is_new_header_eligible_to_be_best(new_header, engine)
             && engine.can_change_canon_chain(parent_hash_of_new_header, grandparent_hash_of_new_header, prev_best_hash);
 
         if is_new_best {
@@ -305,18 +311,21 @@ impl HeaderChain {
             } else {
                 new_header.rlp().as_raw().to_vec()
             };
-            match route.retracted.len() {
-                0 => BestHeaderChanged::CanonChainAppended {
+            if route.retracted.is_empty() {
+                BestHeaderChanged::CanonChainAppended {
+                    best_header: new_best_header,
+                }
+            } else {
+                cinfo!(
+                    HEADERCHAIN,
+                    "Block #{}({}) switches the canonical chain, retracting {} blocks",
+                    new_header.number(),
+                    new_header.hash(),
+                    route.retracted.len()
+                );
+                BestHeaderChanged::BranchBecomingCanonChain {
                     best_header: new_best_header,
-                },
-                _ => {
-                    cerror!(
-                        HEADERCHAIN,
-                        "Older/Forked block header #{}({}) is inserted as a new block",
-                        new_header.number(),
-                        new_header.hash()
-                    );
-                    BestHeaderChanged::None
+                    tree_route: route,
                 }
             }
         } else {
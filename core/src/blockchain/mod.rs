@@ -17,16 +17,21 @@
 mod block_info;
 #[cfg_attr(feature = "cargo-clippy", allow(clippy::module_inception))]
 mod blockchain;
+mod body_compression;
 mod body_db;
 mod event_db;
 mod extras;
 mod headerchain;
 mod route;
 mod update_result;
+mod validator_set_db;
 
 pub use self::blockchain::{BlockChain, BlockProvider};
+pub use self::body_compression::BodyCompression;
 pub use self::body_db::BodyProvider;
 pub use self::event_db::EventProvider;
 pub use self::extras::{BlockDetails, TransactionAddress};
 pub use self::headerchain::HeaderProvider;
+pub use self::route::tree_route;
 pub use self::update_result::ChainUpdateResult;
+pub use self::validator_set_db::ValidatorSetProvider;
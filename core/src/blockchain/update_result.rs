@@ -15,6 +15,7 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::block_info::{BestBlockChanged, BestHeaderChanged};
+use super::route::TreeRoute;
 use ctypes::BlockHash;
 
 #[derive(Debug, PartialEq)]
@@ -22,44 +23,29 @@ pub struct ChainUpdateResult {
     // Some(updated_best_block_hash) if chain is updated
     // None if chain is not updated
     pub enacted: Option<BlockHash>,
+    // Some(route) if the best block is moved to another branch
+    pub tree_route: Option<TreeRoute>,
 }
 
 impl ChainUpdateResult {
     pub fn new(best_block_changed: &BestBlockChanged) -> Self {
-        match best_block_changed {
-            BestBlockChanged::CanonChainAppended {
-                ..
-            } => {
-                let enacted = Some(best_block_changed.new_best_hash().unwrap());
-                ChainUpdateResult {
-                    enacted,
-                }
-            }
-            BestBlockChanged::None => ChainUpdateResult {
-                enacted: None,
-            },
+        ChainUpdateResult {
+            enacted: best_block_changed.new_best_hash(),
+            tree_route: best_block_changed.tree_route().cloned(),
         }
     }
 
     pub fn new_from_best_header_changed(best_header_changed: &BestHeaderChanged) -> Self {
-        match best_header_changed {
-            BestHeaderChanged::CanonChainAppended {
-                ..
-            } => {
-                let enacted = Some(best_header_changed.new_best_hash().unwrap());
-                ChainUpdateResult {
-                    enacted,
-                }
-            }
-            BestHeaderChanged::None => ChainUpdateResult {
-                enacted: None,
-            },
+        ChainUpdateResult {
+            enacted: best_header_changed.new_best_hash(),
+            tree_route: best_header_changed.tree_route().cloned(),
         }
     }
 
     pub fn none() -> Self {
         ChainUpdateResult {
             enacted: None,
+            tree_route: None,
         }
     }
 
@@ -0,0 +1,90 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db::{self, CacheUpdatePolicy, Readable, Writable};
+use ctypes::{BlockHash, CompactValidatorSet};
+use kvdb::{DBTransaction, KeyValueDB};
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Stores the validator set committed by each block's `next_validator_set_hash`.
+pub struct ValidatorSetDB {
+    cache: RwLock<HashMap<BlockHash, CompactValidatorSet>>,
+    db: Arc<dyn KeyValueDB>,
+}
+
+impl ValidatorSetDB {
+    pub fn new(db: Arc<dyn KeyValueDB>) -> Self {
+        Self {
+            cache: Default::default(),
+            db,
+        }
+    }
+
+    /// Inserts the next validator set of the block.
+    /// Expects the set to be already verified against the block's header.
+    pub fn insert_next_validator_set(
+        &self,
+        batch: &mut DBTransaction,
+        block_hash: BlockHash,
+        validator_set: CompactValidatorSet,
+    ) {
+        let mut cache = self.cache.write();
+        batch.write_with_cache(db::COL_EXTRA, &mut *cache, block_hash, validator_set, CacheUpdatePolicy::Remove);
+    }
+}
+
+/// Interface for querying the validator sets of blocks.
+pub trait ValidatorSetProvider {
+    /// Get the validator set whose hash is the `next_validator_set_hash` of the given block.
+    fn next_validator_set(&self, hash: &BlockHash) -> Option<CompactValidatorSet>;
+}
+
+impl ValidatorSetProvider for ValidatorSetDB {
+    fn next_validator_set(&self, hash: &BlockHash) -> Option<CompactValidatorSet> {
+        self.db.read_with_cache(db::COL_EXTRA, &mut *self.cache.write(), hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ckey::Ed25519Public as Public;
+    use ctypes::CompactValidatorEntry;
+    use primitives::H256;
+
+    use super::*;
+
+    #[test]
+    fn insert_and_read_next_validator_set() {
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let validator_set_db = ValidatorSetDB::new(db.clone());
+
+        let block_hash = BlockHash::from(H256::random());
+        let validator_set = CompactValidatorSet::new(vec![CompactValidatorEntry {
+            public_key: Public::from(1),
+            delegation: 100,
+        }]);
+
+        assert_eq!(validator_set_db.next_validator_set(&block_hash), None);
+
+        let mut batch = DBTransaction::new();
+        validator_set_db.insert_next_validator_set(&mut batch, block_hash, validator_set.clone());
+        db.write_buffered(batch);
+
+        assert_eq!(validator_set_db.next_validator_set(&block_hash), Some(validator_set));
+    }
+}
@@ -0,0 +1,121 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db::{self, Readable};
+use crate::encoded;
+use ccrypto::BLAKE_NULL_RLP;
+use cdb::{new_journaldb, Algorithm};
+use cstate::{StateDB, TopLevelState};
+use ctypes::{BlockHash, BlockNumber};
+use kvdb::KeyValueDB;
+use merkle_trie::skewed_merkle_root;
+use primitives::H256;
+use rlp::{Rlp, RlpStream};
+use rlp_compress::{blocks_swapper, decompress};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// How often the history missing in the local database was looked up in the archive since the node started.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ArchiveStats {
+    /// The number of requested block bodies that were not in the local database.
+    pub block_fallbacks: u64,
+    /// The number of the block bodies the archive served among `block_fallbacks`.
+    pub blocks_served: u64,
+    /// The number of requested states that were not in the local database.
+    pub state_fallbacks: u64,
+    /// The number of the states the archive served among `state_fallbacks`.
+    pub states_served: u64,
+}
+
+/// A read-only database of another node of the same chain, e.g. a network mount of an archive node's database,
+/// consulted for the bodies and the states pruned from, or never synced to, the local database.
+///
+/// Nothing is ever written to it. The headers still come from the local chain, and what the archive returns is
+/// checked against them, so a stale or corrupted archive can't make the node serve wrong history.
+pub struct ArchiveDb {
+    db: Arc<dyn KeyValueDB>,
+    state_db: StateDB,
+    block_fallbacks: AtomicU64,
+    blocks_served: AtomicU64,
+    state_fallbacks: AtomicU64,
+    states_served: AtomicU64,
+}
+
+impl ArchiveDb {
+    /// Fails if the archive belongs to another chain, i.e. its genesis block is not `genesis_hash`.
+    pub fn try_new(db: Arc<dyn KeyValueDB>, genesis_hash: BlockHash) -> Result<Self, String> {
+        let archived_genesis: Option<BlockHash> = db.read(db::COL_EXTRA, &(0 as BlockNumber));
+        match archived_genesis {
+            Some(hash) if hash == genesis_hash => {}
+            Some(hash) => return Err(format!("The archive is of another chain whose genesis is {}", hash)),
+            None => return Err("The archive has no genesis block".to_string()),
+        }
+        let state_db = StateDB::new(new_journaldb(Arc::clone(&db), Algorithm::Archive, db::COL_STATE));
+        Ok(Self {
+            db,
+            state_db,
+            block_fallbacks: Default::default(),
+            blocks_served: Default::default(),
+            state_fallbacks: Default::default(),
+            states_served: Default::default(),
+        })
+    }
+
+    /// Assembles the block of `header` with the body stored in the archive.
+    pub fn block(&self, header: &encoded::Header) -> Option<encoded::Block> {
+        self.block_fallbacks.fetch_add(1, Ordering::Relaxed);
+        let compressed_body = self.db.get(db::COL_BODIES, header.hash().as_ref()).ok()??;
+        let body = decompress(&compressed_body, blocks_swapper());
+        let body_rlp = Rlp::new(&body);
+        let evidences = body_rlp.at(0).ok()?;
+        let transactions = body_rlp.at(1).ok()?;
+        if !matches_root(&evidences, header.evidences_root())
+            || !matches_root(&transactions, header.transactions_root())
+        {
+            cwarn!(CLIENT, "The body of block #{} in the archive doesn't match the header", header.number());
+            return None
+        }
+
+        let mut block = RlpStream::new_list(3);
+        block.append_raw(header.rlp().as_raw(), 1);
+        block.append_raw(evidences.as_raw(), 1);
+        block.append_raw(transactions.as_raw(), 1);
+        self.blocks_served.fetch_add(1, Ordering::Relaxed);
+        Some(encoded::Block::new(block.out()))
+    }
+
+    /// The state is looked up by its root, which the caller takes from a local header.
+    pub fn state(&self, root: H256) -> Option<TopLevelState> {
+        self.state_fallbacks.fetch_add(1, Ordering::Relaxed);
+        let state = TopLevelState::from_existing(self.state_db.clone(&root), root).ok()?;
+        self.states_served.fetch_add(1, Ordering::Relaxed);
+        Some(state)
+    }
+
+    pub fn stats(&self) -> ArchiveStats {
+        ArchiveStats {
+            block_fallbacks: self.block_fallbacks.load(Ordering::Relaxed),
+            blocks_served: self.blocks_served.load(Ordering::Relaxed),
+            state_fallbacks: self.state_fallbacks.load(Ordering::Relaxed),
+            states_served: self.states_served.load(Ordering::Relaxed),
+        }
+    }
+}
+
+fn matches_root(items: &Rlp<'_>, root: H256) -> bool {
+    skewed_merkle_root(BLAKE_NULL_RLP, items.iter().map(|item| item.as_raw().to_vec())) == root
+}
@@ -0,0 +1,165 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db;
+use ctypes::BlockNumber;
+use kvdb::{DBTransaction, KeyValueDB};
+use parking_lot::Mutex;
+use std::time::{Duration, Instant};
+
+/// Stores the number of the next block to backfill.
+const PROGRESS_KEY: &[u8] = b"event-backfill-progress";
+/// The maximum number of blocks executed in a batch.
+const BATCH_SIZE: usize = 16;
+/// A batch stops once it has taken this long, so that the backfill doesn't hold the node up.
+const BATCH_TIME_LIMIT: Duration = Duration::from_millis(200);
+
+/// The progress of storing the events of the blocks imported before the events were stored.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BackfillStatus {
+    /// The number of the next block to look at.
+    pub next_block: BlockNumber,
+    /// The number of blocks executed to store their events since the node started.
+    pub backfilled_blocks: u64,
+    /// Whether every block up to the best block has its events.
+    pub is_complete: bool,
+    /// Why the backfill has stopped, if it failed.
+    pub error: Option<String>,
+}
+
+/// Executes the old blocks again in small batches, while the node is idle, to store their events.
+/// The progress is kept in the DB, so the backfill resumes from where it stopped after a restart.
+pub struct EventBackfill {
+    status: Mutex<BackfillStatus>,
+}
+
+impl EventBackfill {
+    pub fn new(db: &dyn KeyValueDB) -> Self {
+        let next_block = db
+            .get(db::COL_EXTRA, PROGRESS_KEY)
+            .expect("Low level database error. Some issue with disk?")
+            .map(|bytes| rlp::decode(&bytes).expect("The backfill progress is stored in RLP"))
+            // The genesis block has no transactions to execute.
+            .unwrap_or(1);
+        Self {
+            status: Mutex::new(BackfillStatus {
+                next_block,
+                ..Default::default()
+            }),
+        }
+    }
+
+    pub fn status(&self) -> BackfillStatus {
+        self.status.lock().clone()
+    }
+
+    /// Runs a batch of the backfill unless it has failed.
+    /// `backfill_block` stores the events of the block of the given number if they are missing,
+    /// returning whether it executed the block.
+    pub fn run_batch(
+        &self,
+        db: &dyn KeyValueDB,
+        best_block_number: BlockNumber,
+        backfill_block: &mut dyn FnMut(BlockNumber) -> Result<bool, String>,
+    ) {
+        let mut status = self.status.lock();
+        if status.error.is_some() {
+            return
+        }
+
+        let started = Instant::now();
+        let first = status.next_block;
+        let mut executed = 0;
+        while status.next_block <= best_block_number && executed < BATCH_SIZE && started.elapsed() < BATCH_TIME_LIMIT {
+            match backfill_block(status.next_block) {
+                Ok(true) => executed += 1,
+                Ok(false) => {}
+                Err(err) => {
+                    cwarn!(CLIENT, "Stopped backfilling the events: {}", err);
+                    status.error = Some(err);
+                    break
+                }
+            }
+            status.next_block += 1;
+        }
+        status.backfilled_blocks += executed as u64;
+
+        let is_complete = status.next_block > best_block_number;
+        if is_complete && !status.is_complete && status.backfilled_blocks > 0 {
+            cinfo!(CLIENT, "Backfilled the events of {} blocks", status.backfilled_blocks);
+        } else if executed > 0 {
+            ctrace!(CLIENT, "Backfilled the events of {} blocks from #{}", executed, first);
+        }
+        status.is_complete = is_complete;
+
+        if status.next_block != first {
+            let mut batch = DBTransaction::new();
+            batch.put(db::COL_EXTRA, PROGRESS_KEY, &rlp::encode(&status.next_block));
+            db.write(batch).expect("DB flush failed.");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn memory_db() -> Arc<dyn KeyValueDB> {
+        Arc::new(kvdb_memorydb::create(db::NUM_COLUMNS.unwrap_or(0)))
+    }
+
+    #[test]
+    fn batches_are_bounded_and_resumable() {
+        let db = memory_db();
+        let backfill = EventBackfill::new(&*db);
+        let mut executed = Vec::new();
+        backfill.run_batch(&*db, 100, &mut |number| {
+            executed.push(number);
+            Ok(true)
+        });
+        assert_eq!((1..=BATCH_SIZE as BlockNumber).collect::<Vec<_>>(), executed);
+        assert!(!backfill.status().is_complete);
+
+        let backfill = EventBackfill::new(&*db);
+        assert_eq!(BATCH_SIZE as BlockNumber + 1, backfill.status().next_block);
+        backfill.run_batch(&*db, 100, &mut |_| Ok(false));
+        let status = backfill.status();
+        assert!(status.is_complete);
+        assert_eq!(101, status.next_block);
+        assert_eq!(0, status.backfilled_blocks);
+    }
+
+    #[test]
+    fn backfill_stops_at_failure() {
+        let db = memory_db();
+        let backfill = EventBackfill::new(&*db);
+        backfill.run_batch(&*db, 10, &mut |number| {
+            if number == 3 {
+                Err("missing state".to_string())
+            } else {
+                Ok(true)
+            }
+        });
+        let status = backfill.status();
+        assert_eq!(Some("missing state".to_string()), status.error);
+        assert_eq!(3, status.next_block);
+        assert_eq!(2, status.backfilled_blocks);
+
+        backfill.run_batch(&*db, 10, &mut |_| panic!("The backfill has stopped"));
+        assert_eq!(status, backfill.status());
+    }
+}
@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use coordinator::Transaction;
 use ctypes::BlockHash;
 
 /// Represents what has to be handled by actor listening to chain events
@@ -27,4 +28,15 @@ pub trait ChainNotify: Send + Sync {
     fn new_blocks(&self, _imported: Vec<BlockHash>, _invalid: Vec<BlockHash>, _enacted: Vec<BlockHash>) {
         // does nothing by default
     }
+
+    /// fires when blocks leave the block queue, either imported or found invalid.
+    /// Unlike `new_blocks`, it also fires for the blocks failed to be verified before the import.
+    fn blocks_completed(&self, _imported: Vec<BlockHash>, _invalid: Vec<BlockHash>) {
+        // does nothing by default
+    }
+
+    /// fires when transactions enter the mem pool.
+    fn transactions_received(&self, _transactions: Vec<Transaction>) {
+        // does nothing by default
+    }
 }
@@ -14,42 +14,56 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use super::archive::{ArchiveDb, ArchiveStats};
+use super::backfill::{BackfillStatus, EventBackfill};
+use super::fork::import_genesis_state;
 use super::importer::{Importer, VerifiedHeader};
+use super::integrity::{verify_chain, Inconsistency, IntegrityReport, INTEGRITY_PROGRESS_INTERVAL};
+use super::soft_limits::{NodeHealth, SoftLimitMonitor};
+use super::timestamp_index::TimestampIndex;
 use super::{
     BlockChainClient, BlockChainInfo, BlockChainTrait, BlockProducer, ChainNotify, ClientConfig, DatabaseClient,
     EngineClient, EngineInfo, ImportBlock, ImportResult, MiningBlockChainClient, StateInfo, StateOrBlock,
 };
 use crate::block::{Block, ClosedBlock, IsBlock, OpenBlock};
-use crate::blockchain::{BlockChain, BlockProvider, BodyProvider, EventProvider, HeaderProvider, TransactionAddress};
+use crate::blockchain::{
+    BlockChain, BlockProvider, BodyProvider, EventProvider, HeaderProvider, TransactionAddress, ValidatorSetProvider,
+};
 use crate::client::{ConsensusClient, SnapshotClient, TermInfo};
-use crate::consensus::{ConsensusEngine, EngineError};
+use crate::consensus::{ConsensusEngine, EngineError, HeightStats, TimeoutParams};
 use crate::encoded;
 use crate::error::{BlockImportError, Error, ImportError, SchemeError};
 use crate::event::EventSource;
-use crate::miner::{Miner, MinerService};
+use crate::metrics::{MEM_POOL_MEMORY, MEM_POOL_TRANSACTIONS};
+use crate::miner::{IngressStats, Miner, MinerService, OrphanStats, PoolPage, PoolQuery};
 use crate::scheme::Scheme;
 use crate::service::ClientIoMessage;
 use crate::transaction::{LocalizedTransaction, PendingTransactions};
 use crate::types::{BlockStatus, TransactionId, VerificationQueueInfo as BlockQueueInfo};
+use crate::verification::body_size;
+use crate::verification::queue::kind::blocks::Unverified;
 use ccrypto::BLAKE_NULL_RLP;
 use cdb::{new_journaldb, Algorithm, AsHashDB};
 use cio::IoChannel;
 use ckey::{Ed25519Public as Public, NetworkId, PlatformAddress};
 use coordinator::context::{ChainHistoryAccess, MemPoolAccess};
-use coordinator::engine::{BlockExecutor, GraphQlHandlerProvider, Initializer};
+use coordinator::engine::{BlockExecutor, GraphQlHandlerProvider, Initializer, NetworkIdentityProvider, TxFilter};
 use coordinator::module::SessionId;
-use coordinator::types::Event;
-use coordinator::Transaction;
-use cstate::{Metadata, NextValidatorSet, StateDB, StateWithCache, TopLevelState, TopState, TopStateView};
+use coordinator::types::{Event, InspectedPayload, TransactionPreview};
+use coordinator::{Transaction, TransactionWithMetadata, TxOrigin, TxSizes};
+use cstate::{Candidates, Metadata, NextValidatorSet, StateDB, StateWithCache, TopLevelState, TopState, TopStateView};
 use ctimer::{TimeoutHandler, TimerApi, TimerScheduleError, TimerToken};
-use ctypes::{BlockHash, BlockId, BlockNumber, CommonParams, ConsensusParams, Header, SyncHeader, TxHash};
+use ctypes::{
+    BlockHash, BlockId, BlockNumber, Commit, CommonParams, ConsensusParams, Header, ModuleDatumProof, NetworkIdentity,
+    StorageId, SyncHeader, TxHash,
+};
 use kvdb::{DBTransaction, KeyValueDB};
 use parking_lot::{Mutex, RwLock, RwLockReadGuard};
 use primitives::{Bytes, H256};
 use rlp::Rlp;
 use std::collections::HashMap;
 use std::ops::Range;
-use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering as AtomicOrdering};
 use std::sync::{Arc, Weak};
 
 const MAX_MEM_POOL_SIZE: usize = 4096;
@@ -72,6 +86,9 @@ pub struct Client {
     /// Count of pending transactions in the queue
     queue_transactions: AtomicUsize,
 
+    /// Set by `shutdown`, after which no block is imported or sealed.
+    shut_down: AtomicBool,
+
     importer: Importer,
 
     /// Handles block sealing
@@ -82,10 +99,23 @@ pub struct Client {
 
     session_allocator: Arc<dyn GraphQlHandlerProvider>,
     graphql_handlers: HashMap<String, Arc<dyn coordinator::module::HandleGraphQlRequest>>,
+
+    network_identity_provider: Arc<dyn NetworkIdentityProvider>,
+
+    soft_limits: SoftLimitMonitor,
+
+    event_backfill: EventBackfill,
+
+    timestamp_index: TimestampIndex,
+
+    /// Consulted for the history missing in `db`
+    archive: RwLock<Option<ArchiveDb>>,
 }
 
 impl Client {
-    pub fn try_new<C: 'static + Initializer + BlockExecutor + GraphQlHandlerProvider>(
+    pub fn try_new<
+        C: 'static + Initializer + BlockExecutor + TxFilter + GraphQlHandlerProvider + NetworkIdentityProvider,
+    >(
         config: &ClientConfig,
         scheme: &Scheme,
         db: Arc<dyn KeyValueDB>,
@@ -94,6 +124,8 @@ impl Client {
         message_channel: IoChannel<ClientIoMessage>,
         reseal_timer: TimerApi,
     ) -> Result<Arc<Client>, Error> {
+        crate::db_migration::migrate_schema(&*db).map_err(Error::Other)?;
+
         let journal_db = new_journaldb(Arc::clone(&db), Algorithm::Archive, crate::db::COL_STATE);
         let mut state_db = StateDB::new(journal_db);
         if !scheme.check_genesis_root(state_db.as_hashdb()) {
@@ -102,22 +134,34 @@ impl Client {
 
         if state_db.is_empty() {
             // it's genesis
-            let (db, root) = Self::initialize_state(state_db, &*coordinator)?;
-            scheme.set_state_root(root);
-            state_db = db;
+            match &scheme.genesis_state {
+                Some(chunks) => {
+                    import_genesis_state(&db, scheme.state_root(), chunks)?;
+                    state_db = StateDB::new(new_journaldb(Arc::clone(&db), Algorithm::Archive, crate::db::COL_STATE));
+                }
+                None => {
+                    let (db, root) = Self::initialize_state(state_db, &*coordinator)?;
+                    scheme.set_state_root(root);
+                    state_db = db;
+                }
+            }
         }
 
         let gb = scheme.genesis_block();
-        let chain = BlockChain::new(&gb, db.clone());
+        let chain = BlockChain::new(&gb, db.clone(), config.body_compression);
 
         let engine = scheme.engine.clone();
 
+        let event_backfill = EventBackfill::new(&*db);
+
         let importer = Importer::try_new(
             config,
             engine.clone(),
             message_channel.clone(),
             Arc::clone(&miner),
             Arc::clone(&coordinator) as Arc<dyn BlockExecutor>,
+            Arc::clone(&coordinator) as Arc<dyn TxFilter>,
+            scheme.checkpoints.clone(),
         )?;
 
         let client = Arc::new(Client {
@@ -128,11 +172,17 @@ impl Client {
             state_db: RwLock::new(state_db),
             notify: RwLock::new(Vec::new()),
             queue_transactions: AtomicUsize::new(0),
+            shut_down: AtomicBool::new(false),
             importer,
             miner,
             reseal_timer,
             session_allocator: Arc::clone(&coordinator) as Arc<dyn GraphQlHandlerProvider>,
             graphql_handlers: GraphQlHandlerProvider::get(coordinator.as_ref()).into_iter().collect(),
+            network_identity_provider: Arc::clone(&coordinator) as Arc<dyn NetworkIdentityProvider>,
+            soft_limits: Default::default(),
+            event_backfill,
+            timestamp_index: Default::default(),
+            archive: Default::default(),
         });
 
         // ensure buffered changes are flushed.
@@ -145,15 +195,90 @@ impl Client {
         &*self.engine
     }
 
+    /// Attaches a read-only database of an archive node of the same chain, consulted by the queries
+    /// when the local database lacks the requested bodies or states.
+    pub fn attach_archive(&self, db: Arc<dyn KeyValueDB>) -> Result<(), String> {
+        let genesis_hash = self.block_chain().genesis_hash();
+        *self.archive.write() = Some(ArchiveDb::try_new(db, genesis_hash)?);
+        Ok(())
+    }
+
+    fn note_mem_pool_usage(&self) {
+        let usage = self.miner.mem_pool_usage();
+        MEM_POOL_TRANSACTIONS.set(usage.count as i64);
+        MEM_POOL_MEMORY.set(usage.mem_usage as i64);
+        self.soft_limits.note_mem_pool(usage);
+    }
+
+    fn queue_transaction_of(&self, transaction: Transaction, origin: TxOrigin) -> Result<(), Error> {
+        self.miner.import_own_transaction(self, transaction.clone(), origin)?;
+        self.note_mem_pool_usage();
+        self.notify(|notify| notify.transactions_received(vec![transaction.clone()]));
+        Ok(())
+    }
+
+    /// Notes how many of the sentries of a validator in the sentry mode are connected, for the node health.
+    pub fn note_sentries(&self, connected: usize, sentries: usize) {
+        self.soft_limits.note_sentries(connected, sentries);
+    }
+
     /// Adds an actor to be notified on certain events
     pub fn add_notify(&self, target: Weak<dyn ChainNotify>) {
         self.notify.write().push(target);
     }
 
     pub fn new_blocks(&self, imported: &[BlockHash], invalid: &[BlockHash], enacted: &[BlockHash]) {
+        if !imported.is_empty() {
+            self.check_soft_limits(imported);
+        }
         self.notify(|notify| notify.new_blocks(imported.to_vec(), invalid.to_vec(), enacted.to_vec()));
     }
 
+    pub fn blocks_completed(&self, imported: &[BlockHash], invalid: &[BlockHash]) {
+        self.notify(|notify| notify.blocks_completed(imported.to_vec(), invalid.to_vec()));
+    }
+
+    /// Checks the block to be queued against the chain.
+    fn unverified_block(&self, bytes: Bytes) -> Result<Unverified, BlockImportError> {
+        use crate::verification::queue::kind::BlockLike;
+
+        let unverified = Unverified::new(bytes);
+        let chain = self.block_chain();
+        if chain.is_known(&unverified.hash()) {
+            return Err(BlockImportError::Import(ImportError::AlreadyInChain))
+        }
+        self.importer.check_checkpoints(unverified.header(), &*chain).map_err(BlockImportError::Block)?;
+        Ok(unverified)
+    }
+
+    /// Checks how close the imported blocks, the mem pool and the candidates are to their limits.
+    fn check_soft_limits(&self, imported: &[BlockHash]) {
+        for hash in imported {
+            let block = match self.block(&BlockId::Hash(*hash)) {
+                Some(block) => block,
+                None => continue,
+            };
+            let max_body_size = match self.consensus_params(BlockId::Hash(block.header_view().parent_hash())) {
+                Some(params) => params.max_body_size() as usize,
+                None => continue,
+            };
+            if let Ok(body) = block.rlp().at(2) {
+                self.soft_limits.note_block_body(body_size(&body), max_body_size);
+            }
+        }
+
+        self.note_mem_pool_usage();
+
+        if let (Some(state), Some(params)) = (self.state_at(BlockId::Latest), self.common_params(BlockId::Latest)) {
+            match Candidates::load_from_state(&state) {
+                Ok(candidates) => self
+                    .soft_limits
+                    .note_candidate_metadata(candidates.largest_metadata_size(), params.max_candidate_metadata_size()),
+                Err(err) => cwarn!(CLIENT, "Cannot read the candidates: {:?}", err),
+            }
+        }
+    }
+
     pub fn new_headers(&self, imported: &[BlockHash], enacted: &[BlockHash], new_best_proposal: Option<BlockHash>) {
         self.notify(|notify| {
             notify.new_headers(imported.to_vec(), enacted.to_vec(), new_best_proposal);
@@ -172,19 +297,68 @@ impl Client {
 
     /// This is triggered by a message coming from a header queue when the header is ready for insertion
     pub fn import_verified_headers(&self) -> usize {
+        if self.is_shut_down() {
+            return 0
+        }
         self.importer.import_verified_headers_from_queue(self)
     }
 
     /// This is triggered by a message coming from a block queue when the block is ready for insertion
     pub fn import_verified_blocks(&self) -> usize {
+        if self.is_shut_down() {
+            return 0
+        }
         self.importer.import_verified_blocks(self)
     }
 
+    /// The state of the header queue. `BlockChainClient::queue_info` gives the one of the block queue.
+    pub fn header_queue_info(&self) -> BlockQueueInfo {
+        self.importer.header_queue.queue_info()
+    }
+
     /// This is triggered by a message coming from a engine when a new block should be created
     pub fn update_sealing(&self, parent_block: BlockId, allow_empty_block: bool) {
+        if self.is_shut_down() {
+            return
+        }
         self.miner.update_sealing(self, parent_block, allow_empty_block);
     }
 
+    /// Stores the events of a few old blocks that were imported before the events were stored.
+    /// It does nothing while there are blocks to import, so that syncing isn't slowed down.
+    pub fn backfill_events(&self) {
+        if self.is_shut_down() || !self.queue_info().is_empty() {
+            return
+        }
+        let best_block_number = self.block_chain().best_block_detail().number;
+        self.event_backfill
+            .run_batch(&*self.db, best_block_number, &mut |number| self.importer.backfill_events(number, self));
+    }
+
+    /// Stops changing the chain and writes everything pending to the disk, so the block chain DB
+    /// and the mem pool backup are consistent when the process exits.
+    ///
+    /// Sealing and importing stop first, and the import in progress, if any, is waited for.
+    /// Then the mem pool backup and the DB are flushed.
+    /// The module sandboxes are closed later when the coordinator is dropped.
+    pub fn shutdown(&self) {
+        if self.shut_down.swap(true, AtomicOrdering::SeqCst) {
+            return
+        }
+        cinfo!(CLIENT, "Shutting down the client");
+        self.miner.stop_sealing();
+        drop(self.importer.import_lock.lock());
+
+        self.miner.flush_mem_pool();
+        if let Err(err) = self.db.flush() {
+            cerror!(CLIENT, "Failed to flush the DB on shutdown: {}", err);
+        }
+    }
+
+    fn is_shut_down(&self) -> bool {
+        self.shut_down.load(AtomicOrdering::SeqCst)
+    }
+
     fn block_hash(chain: &BlockChain, id: &BlockId) -> Option<BlockHash> {
         match id {
             BlockId::Hash(hash) => Some(*hash),
@@ -213,7 +387,17 @@ impl Client {
         self.queue_transactions.fetch_sub(transactions.len(), AtomicOrdering::SeqCst);
         let transactions: Vec<Transaction> =
             transactions.iter().filter_map(|bytes| Rlp::new(bytes).as_val().ok()).collect();
-        let results = self.miner.import_external_transactions(self, transactions);
+        let results = self.miner.import_external_transactions(self, transactions.clone());
+        self.note_mem_pool_usage();
+        let received: Vec<Transaction> = transactions
+            .into_iter()
+            .zip(&results)
+            .filter(|(_, result)| result.is_ok())
+            .map(|(transaction, _)| transaction)
+            .collect();
+        if !received.is_empty() {
+            self.notify(|notify| notify.transactions_received(received.clone()));
+        }
         results.len()
     }
 
@@ -248,7 +432,7 @@ impl Client {
     }
 
     /// Initializes the state at genesis.
-    fn initialize_state(db: StateDB, coordinator: &impl Initializer) -> Result<(StateDB, H256), Error> {
+    pub(crate) fn initialize_state(db: StateDB, coordinator: &impl Initializer) -> Result<(StateDB, H256), Error> {
         let root = BLAKE_NULL_RLP;
         let mut state = TopLevelState::from_existing(db, root)?;
 
@@ -286,10 +470,16 @@ impl Client {
     fn state_info(&self, state: StateOrBlock) -> Option<Box<dyn TopStateView>> {
         Some(match state {
             StateOrBlock::State(state) => state,
-            StateOrBlock::Block(id) => Box::new(self.state_at(id)?),
+            StateOrBlock::Block(id) => Box::new(self.state_at(id).or_else(|| self.archived_state(id))?),
         })
     }
 
+    fn archived_state(&self, id: BlockId) -> Option<TopLevelState> {
+        let archive = self.archive.read();
+        let header = self.block_header(&id)?;
+        archive.as_ref()?.state(header.state_root())
+    }
+
     pub fn state_db(&self) -> &RwLock<StateDB> {
         &self.state_db
     }
@@ -302,8 +492,79 @@ impl Client {
         &self.db
     }
 
-    pub fn new_session(&self, block: BlockId) -> SessionId {
-        self.session_allocator.new_session_for_query(&mut self.state_at(block).unwrap())
+    /// Re-verifies the stored canonical blocks in `range` and optionally repairs the repairable inconsistencies.
+    /// `progress` is called with the block number being checked, once per `INTEGRITY_PROGRESS_INTERVAL` blocks.
+    ///
+    /// The blocks are checked in chunks of `INTEGRITY_PROGRESS_INTERVAL` blocks, and the import waits only for
+    /// the chunk being checked. So each chunk is checked against the canonical chain at its time.
+    /// The blocks whose events are missing are executed again to repair them.
+    pub fn verify_database_with_progress(
+        &self,
+        range: Range<BlockNumber>,
+        repair: bool,
+        progress: &mut dyn FnMut(BlockNumber, &Range<BlockNumber>),
+    ) -> IntegrityReport {
+        let best_number = self.block_chain().best_block_detail().number;
+        let range = range.start..range.end.min(best_number + 1);
+        let mut report = IntegrityReport {
+            checked: range.start..range.start,
+            ..Default::default()
+        };
+        while report.checked.end < range.end {
+            let start = report.checked.end;
+            let end = ((start / INTEGRITY_PROGRESS_INTERVAL + 1) * INTEGRITY_PROGRESS_INTERVAL).min(range.end);
+            let chunk = {
+                let _import_lock = self.importer.import_lock.lock();
+                let chain = self.block_chain();
+                let mut batch = DBTransaction::new();
+                let chunk = {
+                    let state_db = self.state_db().read();
+                    let mut chunk_progress = |number, _: &Range<BlockNumber>| progress(number, &range);
+                    verify_chain(
+                        &chain,
+                        &**self.db(),
+                        state_db.as_hashdb(),
+                        start..end,
+                        repair,
+                        &mut batch,
+                        &mut chunk_progress,
+                    )
+                };
+                if chunk.repaired > 0 {
+                    self.db().write(batch).expect("DB flush failed.");
+                    chain.commit();
+                }
+                chunk
+            };
+            report.checked.end = chunk.checked.end.max(start);
+            report.repaired += chunk.repaired;
+            for inconsistency in chunk.inconsistencies {
+                if let Inconsistency::MissingBlockEvents {
+                    number,
+                    ..
+                } = inconsistency
+                {
+                    // It takes the import lock, so it is done after the chunk.
+                    if repair && self.importer.backfill_events(number, self) == Ok(true) {
+                        report.repaired += 1;
+                    }
+                }
+                report.inconsistencies.push(inconsistency);
+            }
+            if chunk.checked.end < end {
+                // The chain has become shorter than the range.
+                break
+            }
+        }
+        progress(report.checked.end, &range);
+        report
+    }
+
+    /// Opens a query session on the state of `block`.
+    /// Returns `None` if the block is unknown or its state has been pruned.
+    pub fn new_session(&self, block: BlockId) -> Option<SessionId> {
+        let mut state = self.state_at(block)?;
+        Some(self.session_allocator.new_session_for_query(&mut state))
     }
 
     pub fn end_session(&self, session: SessionId) {
@@ -313,11 +574,24 @@ impl Client {
     pub fn graphql_handlers(&self) -> &HashMap<String, Arc<dyn coordinator::module::HandleGraphQlRequest>> {
         &self.graphql_handlers
     }
+
+    /// Starts sealing at the interval of the engine, if it has one.
+    /// The handler of the reseal timer must be set before.
+    pub fn start_seal_interval_timer(&self) {
+        if let Some(interval) = self.engine().seal_interval() {
+            cinfo!(CLIENT, "Sealing a block every {:?}", interval);
+            self.reseal_timer
+                .schedule_repeat(interval, SEAL_INTERVAL_TIMER_TOKEN)
+                .expect("The seal interval timer is scheduled only once");
+        }
+    }
 }
 
 /// The minimum time between blocks, the miner creates a block when RESEAL_MIN_TIMER is invoked.
 /// Do not create a block before RESEAL_MIN_TIMER event.
 const RESEAL_MIN_TIMER_TOKEN: TimerToken = 1;
+/// Seals a block, empty or not, for the engines that have a seal interval.
+const SEAL_INTERVAL_TIMER_TOKEN: TimerToken = 2;
 
 impl TimeoutHandler for Client {
     fn on_timeout(&self, token: TimerToken) {
@@ -328,6 +602,11 @@ impl TimeoutHandler for Client {
                     self.update_sealing(BlockId::Latest, false);
                 }
             }
+            SEAL_INTERVAL_TIMER_TOKEN => {
+                if self.miner.is_sealing_enabled() {
+                    self.update_sealing(BlockId::Latest, true);
+                }
+            }
             _ => unreachable!(),
         }
     }
@@ -337,6 +616,16 @@ impl DatabaseClient for Client {
     fn database(&self) -> Arc<dyn KeyValueDB> {
         Arc::clone(&self.db())
     }
+
+    fn verify_database(&self, range: Range<BlockNumber>, repair: bool) -> IntegrityReport {
+        let report = self.verify_database_with_progress(range, repair, &mut |number, range| {
+            cinfo!(CLIENT, "Verifying the database: #{} of #{}..#{}", number, range.start, range.end);
+        });
+        for inconsistency in &report.inconsistencies {
+            cwarn!(CLIENT, "{}", inconsistency);
+        }
+        report
+    }
 }
 
 impl StateInfo for Client {
@@ -383,6 +672,32 @@ impl EngineInfo for Client {
         })
     }
 
+    fn module_roots(&self, block_id: BlockId) -> Option<Vec<H256>> {
+        self.state_info(block_id.into()).map(|state| {
+            state
+                .module_roots()
+                .unwrap_or_else(|err| unreachable!("Unexpected failure. Maybe DB was corrupted: {:?}", err))
+        })
+    }
+
+    fn module_datum_proof(&self, block_id: BlockId, storage_id: StorageId, key: &[u8]) -> Option<ModuleDatumProof> {
+        self.state_info(block_id.into()).and_then(|state| {
+            state
+                .module_datum_proof(storage_id, key)
+                .unwrap_or_else(|err| unreachable!("Unexpected failure. Maybe DB was corrupted: {:?}", err))
+        })
+    }
+
+    fn commit(&self, block_id: BlockId) -> Option<Commit> {
+        let header = self.block_header(&block_id)?;
+        let child = self.block_header(&BlockId::Number(header.number() + 1))?;
+        // The block is not on the canonical chain.
+        if child.parent_hash() != header.hash() {
+            return None
+        }
+        self.engine().parent_commit(&child.decode())
+    }
+
     fn possible_authors(&self, block_number: Option<u64>) -> Result<Option<Vec<PlatformAddress>>, EngineError> {
         let network_id = self.network_id();
         if block_number == Some(0) {
@@ -394,8 +709,32 @@ impl EngineInfo for Client {
             .map(|pubkeys| pubkeys.into_iter().map(|pubkey| PlatformAddress::new_v0(network_id, pubkey)).collect()))
     }
 
-    fn validator_set(&self, block_number: Option<u64>) -> Result<Option<ctypes::CompactValidatorSet>, EngineError> {
-        Ok(self.engine().current_validator_set(block_number)?)
+    fn validator_set(&self, block_id: BlockId) -> Result<Option<ctypes::CompactValidatorSet>, EngineError> {
+        let header = match self.block_header(&block_id) {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+        if header.number() == 0 {
+            return Ok(None)
+        }
+        // The validators of a block are committed by its parent.
+        if let Some(validator_set) = self.block_chain().next_validator_set(&header.parent_hash()) {
+            return Ok(Some(validator_set))
+        }
+        // Blocks imported before the validator sets were recorded
+        Ok(self.engine().current_validator_set(Some(header.number()))?)
+    }
+
+    fn recent_height_stats(&self) -> Vec<HeightStats> {
+        self.engine().recent_height_stats()
+    }
+
+    fn timeouts(&self) -> Option<TimeoutParams> {
+        self.engine().timeouts()
+    }
+
+    fn update_timeouts(&self, params: &cjson::scheme::TendermintParams) -> Option<TimeoutParams> {
+        self.engine().update_timeouts(params)
     }
 }
 
@@ -413,6 +752,10 @@ impl EngineClient for Client {
         }
     }
 
+    fn is_block_proposal_due(&self, parent_block: BlockId) -> bool {
+        self.block_header(&parent_block).map_or(true, |parent| self.miner.is_block_proposal_due(parent.timestamp()))
+    }
+
     /// Update the best block as the given block hash.
     ///
     /// Used in Tendermint, when going to the commit step.
@@ -469,21 +812,22 @@ impl BlockChainTrait for Client {
 
 impl ImportBlock for Client {
     fn import_block(&self, bytes: Bytes) -> Result<BlockHash, BlockImportError> {
-        use crate::verification::queue::kind::blocks::Unverified;
-        use crate::verification::queue::kind::BlockLike;
-
-        let unverified = Unverified::new(bytes);
-        {
-            if self.block_chain().is_known(&unverified.hash()) {
-                return Err(BlockImportError::Import(ImportError::AlreadyInChain))
-            }
-        }
+        let unverified = self.unverified_block(bytes)?;
         Ok(self.importer.block_queue.import(unverified)?)
     }
 
+    fn queue_block(&self, bytes: Bytes) -> Result<BlockHash, BlockImportError> {
+        let unverified = self.unverified_block(bytes)?;
+        Ok(self.importer.block_queue.try_import(unverified)?)
+    }
+
     fn import_header(&self, unverified: SyncHeader) -> Result<BlockHash, BlockImportError> {
-        if self.block_chain().is_known_header(&unverified.hash()) {
-            return Err(BlockImportError::Import(ImportError::AlreadyInChain))
+        {
+            let chain = self.block_chain();
+            if chain.is_known_header(&unverified.hash()) {
+                return Err(BlockImportError::Import(ImportError::AlreadyInChain))
+            }
+            self.importer.check_checkpoints(&unverified, &*chain).map_err(BlockImportError::Block)?;
         }
         Ok(self.importer.header_queue.import(unverified)?)
     }
@@ -553,7 +897,7 @@ impl MemPoolAccess for Client {
             .into_iter()
             .map(|tx| {
                 let hash = tx.hash();
-                self.queue_own_transaction(tx).map_err(|e| format!("{}", e)).map(|_| hash)
+                self.queue_transaction_of(tx, TxOrigin::System).map_err(|e| format!("{}", e)).map(|_| hash)
             })
             .collect()
     }
@@ -566,7 +910,15 @@ impl BlockChainClient for Client {
 
     /// Import own transaction
     fn queue_own_transaction(&self, transaction: Transaction) -> Result<(), Error> {
-        self.miner.import_own_transaction(self, transaction)?;
+        self.queue_transaction_of(transaction, TxOrigin::Local)
+    }
+
+    fn queue_scheduled_transaction(
+        &self,
+        transaction: Transaction,
+        activation_block: BlockNumber,
+    ) -> Result<(), Error> {
+        self.miner.import_scheduled_transaction(self, transaction, activation_block)?;
         Ok(())
     }
 
@@ -588,6 +940,14 @@ impl BlockChainClient for Client {
         }
     }
 
+    fn preview_transaction(
+        &self,
+        transaction: &Transaction,
+        parent: BlockId,
+    ) -> Result<Option<TransactionPreview>, Error> {
+        self.miner.preview_transaction(self, parent, transaction)
+    }
+
     fn delete_all_pending_transactions(&self) {
         self.miner.delete_all_pending_transactions();
     }
@@ -600,10 +960,62 @@ impl BlockChainClient for Client {
         self.miner.pending_transactions(size_limit as usize, range)
     }
 
+    fn propagatable_transaction_hashes(&self) -> Vec<TxHash> {
+        self.miner.propagatable_transaction_hashes()
+    }
+
+    fn prioritized_pending_transactions(&self, hashes: &[TxHash]) -> Vec<Transaction> {
+        self.miner.prioritized_transactions(self, hashes)
+    }
+
+    fn local_pending_transactions(&self) -> Vec<TransactionWithMetadata> {
+        self.miner.local_pending_transactions()
+    }
+
+    fn replacement_fee(&self, fee: u64) -> Option<u64> {
+        self.miner.replacement_fee(fee)
+    }
+
+    fn query_pending_transactions(&self, query: &PoolQuery) -> PoolPage {
+        self.miner.query_pending_transactions(query)
+    }
+
     fn count_pending_transactions(&self, range: Range<u64>) -> usize {
         self.miner.count_pending_transactions(range)
     }
 
+    fn ingress_stats(&self) -> Vec<IngressStats> {
+        self.miner.ingress_stats()
+    }
+
+    fn orphan_stats(&self) -> OrphanStats {
+        self.miner.orphan_stats()
+    }
+
+    fn transaction_sizes(&self, tx: &Transaction) -> TxSizes {
+        self.miner.transaction_sizes(tx)
+    }
+
+    fn inspect_transaction(&self, tx: &Transaction) -> Option<Result<InspectedPayload, String>> {
+        self.miner.inspect_transaction(tx)
+    }
+
+    fn scheduled_transactions(&self) -> Vec<(BlockNumber, Transaction)> {
+        self.miner.scheduled_transactions()
+    }
+
+    fn is_pending_transaction(&self, hash: &TxHash) -> bool {
+        self.miner.is_pending_transaction(hash)
+    }
+
+    fn dump_mem_pool(&self) -> Vec<TransactionWithMetadata> {
+        self.miner.dump_mem_pool()
+    }
+
+    fn load_mem_pool(&self, transactions: Vec<TransactionWithMetadata>) -> Vec<TxHash> {
+        self.miner.load_mem_pool(self, transactions)
+    }
+
     fn is_mem_pool_empty(&self) -> bool {
         self.miner.num_pending_transactions() == 0
     }
@@ -632,6 +1044,10 @@ impl BlockChainClient for Client {
         Self::block_hash(&chain, id)
     }
 
+    fn block_number_by_timestamp(&self, timestamp: u64) -> Option<BlockNumber> {
+        self.timestamp_index.block_number(&self.block_chain(), timestamp)
+    }
+
     fn transaction(&self, id: &TransactionId) -> Option<LocalizedTransaction> {
         let chain = self.block_chain();
         self.transaction_address(id).and_then(|pubkey| chain.transaction(&pubkey))
@@ -648,6 +1064,29 @@ impl BlockChainClient for Client {
         let source = EventSource::Block(*hash);
         chain.events(&source)
     }
+
+    fn network_identities(&self, id: &BlockId) -> Option<Vec<NetworkIdentity>> {
+        let mut state = self.state_at(*id)?;
+        Some(self.network_identity_provider.network_identities(&mut state))
+    }
+
+    fn node_health(&self) -> NodeHealth {
+        self.soft_limits.health()
+    }
+
+    fn event_backfill_status(&self) -> BackfillStatus {
+        self.event_backfill.status()
+    }
+
+    fn archived_block(&self, id: &BlockId) -> Option<encoded::Block> {
+        let archive = self.archive.read();
+        let header = self.block_header(id)?;
+        archive.as_ref()?.block(&header)
+    }
+
+    fn archive_stats(&self) -> Option<ArchiveStats> {
+        self.archive.read().as_ref().map(ArchiveDb::stats)
+    }
 }
 
 impl TermInfo for Client {
@@ -14,11 +14,62 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::blockchain::BodyCompression;
 use crate::verification::QueueConfig;
 use kvdb_rocksdb::CompactionProfile;
+use std::fmt;
 use std::path::Path;
 use std::str::FromStr;
 
+/// The key-value store the database of a chain is kept in.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DatabaseBackend {
+    /// RocksDB, tuned by the cache size and the compaction profile.
+    RocksDb,
+    /// sled, a pure-Rust store for the platforms where RocksDB is hard to build.
+    Sled,
+}
+
+impl Default for DatabaseBackend {
+    fn default() -> Self {
+        DatabaseBackend::RocksDb
+    }
+}
+
+impl DatabaseBackend {
+    /// The backend of the database at `path`, or `None` if there's no database.
+    pub fn detect(path: &Path) -> Option<Self> {
+        if path.join("CURRENT").exists() {
+            Some(DatabaseBackend::RocksDb)
+        } else if path.join("conf").exists() {
+            Some(DatabaseBackend::Sled)
+        } else {
+            None
+        }
+    }
+}
+
+impl FromStr for DatabaseBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rocksdb" => Ok(DatabaseBackend::RocksDb),
+            "sled" => Ok(DatabaseBackend::Sled),
+            _ => Err(format!("{} isn't a valid database backend. Possible values are rocksdb, sled", s)),
+        }
+    }
+}
+
+impl fmt::Display for DatabaseBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatabaseBackend::RocksDb => write!(f, "rocksdb"),
+            DatabaseBackend::Sled => write!(f, "sled"),
+        }
+    }
+}
+
 /// Client state db compaction profile
 #[derive(Debug, PartialEq, Clone)]
 pub enum DatabaseCompactionProfile {
@@ -65,12 +116,19 @@ impl FromStr for DatabaseCompactionProfile {
 pub struct ClientConfig {
     /// Block queue configuration.
     pub queue: QueueConfig,
-    /// RocksDB column cache-size if not default
+    /// The store the database is kept in.
+    pub db_backend: DatabaseBackend,
+    /// The cache size of the database in MB if not default.
+    /// RocksDB spreads it over the columns, and sled uses it as its page cache.
     pub db_cache_size: Option<usize>,
     /// State db compaction profile
     pub db_compaction: DatabaseCompactionProfile,
+    /// The maximum number of the files RocksDB keeps open if not default.
+    pub db_max_open_files: Option<i32>,
     /// State db cache-size.
     pub state_cache_size: usize,
+    /// Compression of the newly stored block bodies. `None` keeps the legacy RLP compression.
+    pub body_compression: Option<BodyCompression>,
 }
 
 impl Default for ClientConfig {
@@ -79,9 +137,12 @@ impl Default for ClientConfig {
         const DEFAULT_STATE_CACHE_SIZE: u32 = 25;
         Self {
             queue: Default::default(),
+            db_backend: Default::default(),
             db_cache_size: Default::default(),
             db_compaction: Default::default(),
+            db_max_open_files: None,
             state_cache_size: DEFAULT_STATE_CACHE_SIZE as usize * mb,
+            body_compression: None,
         }
     }
 }
@@ -0,0 +1,241 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Opens the database of a chain on the backend chosen in the `ClientConfig`, and converts it between the backends.
+
+use super::config::{ClientConfig, DatabaseBackend};
+use crate::db::NUM_COLUMNS;
+use kvdb::{DBOp, DBTransaction, DBValue, KeyValueDB};
+use kvdb_rocksdb::{Database, DatabaseConfig};
+use sled::transaction::ConflictableTransactionError;
+use sled::Transactional;
+use std::io;
+use std::mem;
+use std::path::Path;
+use std::sync::Arc;
+
+/// The number of the entries `migrate_database` writes at once.
+const MIGRATION_BATCH_SIZE: usize = 10_000;
+
+/// Opens the database at `path` on the backend of `config`, creating it if there's none.
+/// A database of another backend is refused rather than shadowed by a new empty one.
+pub fn open_database(path: &str, config: &ClientConfig) -> Result<Arc<dyn KeyValueDB>, String> {
+    if let Some(found) = DatabaseBackend::detect(Path::new(path)) {
+        if found != config.db_backend {
+            return Err(format!(
+                "The database at {} is kept in {}, not {}. Convert it with migrate-db first",
+                path, found, config.db_backend
+            ))
+        }
+    }
+    match config.db_backend {
+        DatabaseBackend::RocksDb => {
+            let mut db_config = DatabaseConfig::with_columns(NUM_COLUMNS);
+            db_config.memory_budget = config.db_cache_size;
+            db_config.compaction = config.db_compaction.compaction_profile(Path::new(path));
+            if let Some(max_open_files) = config.db_max_open_files {
+                db_config.max_open_files = max_open_files;
+            }
+            let db = Database::open(&db_config, path)
+                .map_err(|e| format!("Cannot open the RocksDB database at {}: {}", path, e))?;
+            Ok(Arc::new(db))
+        }
+        DatabaseBackend::Sled => Ok(Arc::new(SledDb::open(path, config.db_cache_size)?)),
+    }
+}
+
+/// Copies every column of `from` to `to`, which is expected to be empty.
+/// `progress` is called with the column and the number of its entries copied so far after each batch.
+/// Returns the number of all the copied entries.
+pub fn migrate_database(
+    from: &dyn KeyValueDB,
+    to: &dyn KeyValueDB,
+    mut progress: impl FnMut(Option<u32>, usize),
+) -> io::Result<usize> {
+    let mut total = 0;
+    for col in (0..NUM_COLUMNS.unwrap()).map(Some) {
+        let mut batch = DBTransaction::new();
+        let mut copied = 0;
+        for (key, value) in from.iter(col) {
+            batch.put(col, &key, &value);
+            copied += 1;
+            if batch.ops.len() >= MIGRATION_BATCH_SIZE {
+                to.write(mem::replace(&mut batch, DBTransaction::new()))?;
+                progress(col, copied);
+            }
+        }
+        to.write(batch)?;
+        progress(col, copied);
+        total += copied;
+    }
+    to.flush()?;
+    Ok(total)
+}
+
+/// A `KeyValueDB` on sled. Each column is a tree of its own, and the trees a transaction writes to
+/// are updated atomically.
+pub struct SledDb {
+    db: sled::Db,
+    /// The tree of the default column, followed by the trees of the numbered columns.
+    trees: Vec<sled::Tree>,
+}
+
+impl SledDb {
+    /// `cache_size` is the page cache in MB.
+    pub fn open(path: &str, cache_size: Option<usize>) -> Result<Self, String> {
+        let mut config = sled::Config::new().path(path);
+        if let Some(cache_size) = cache_size {
+            config = config.cache_capacity(cache_size as u64 * 1024 * 1024);
+        }
+        let db = config.open().map_err(|e| format!("Cannot open the sled database at {}: {}", path, e))?;
+        let mut trees = vec![(*db).clone()];
+        for col in 0..NUM_COLUMNS.unwrap() {
+            let tree = db
+                .open_tree(format!("col{}", col))
+                .map_err(|e| format!("Cannot open the column {} of the sled database at {}: {}", col, path, e))?;
+            trees.push(tree);
+        }
+        Ok(Self {
+            db,
+            trees,
+        })
+    }
+
+    fn tree_index(col: Option<u32>) -> usize {
+        col.map_or(0, |col| col as usize + 1)
+    }
+
+    fn tree(&self, col: Option<u32>) -> &sled::Tree {
+        &self.trees[Self::tree_index(col)]
+    }
+}
+
+fn io_error(error: impl std::fmt::Debug) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, format!("{:?}", error))
+}
+
+fn boxed_entry((key, value): (sled::IVec, sled::IVec)) -> (Box<[u8]>, Box<[u8]>) {
+    (key.to_vec().into_boxed_slice(), value.to_vec().into_boxed_slice())
+}
+
+impl KeyValueDB for SledDb {
+    fn get(&self, col: Option<u32>, key: &[u8]) -> io::Result<Option<DBValue>> {
+        let value = self.tree(col).get(key).map_err(io_error)?;
+        Ok(value.map(|value| DBValue::from_slice(&value)))
+    }
+
+    fn get_by_prefix(&self, col: Option<u32>, prefix: &[u8]) -> Option<Box<[u8]>> {
+        let (_, value) = self.tree(col).scan_prefix(prefix).next()?.ok()?;
+        Some(value.to_vec().into_boxed_slice())
+    }
+
+    /// sled buffers the writes until `flush` by itself.
+    fn write_buffered(&self, transaction: DBTransaction) {
+        self.write(transaction).expect("Low level database error. Some issue with disk?")
+    }
+
+    fn write(&self, transaction: DBTransaction) -> io::Result<()> {
+        let trees: &[sled::Tree] = &self.trees;
+        trees
+            .transaction(|trees| {
+                for op in &transaction.ops {
+                    match op {
+                        DBOp::Insert {
+                            col,
+                            key,
+                            value,
+                        } => {
+                            trees[Self::tree_index(*col)].insert(&key[..], &value[..])?;
+                        }
+                        DBOp::Delete {
+                            col,
+                            key,
+                        } => {
+                            trees[Self::tree_index(*col)].remove(&key[..])?;
+                        }
+                    }
+                }
+                Ok::<_, ConflictableTransactionError>(())
+            })
+            .map_err(io_error)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.db.flush().map(|_| ()).map_err(io_error)
+    }
+
+    fn iter<'a>(&'a self, col: Option<u32>) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+        Box::new(self.tree(col).iter().filter_map(Result::ok).map(boxed_entry))
+    }
+
+    /// Iterates from the first key not less than `prefix`, like RocksDB does.
+    fn iter_from_prefix<'a>(
+        &'a self,
+        col: Option<u32>,
+        prefix: &'a [u8],
+    ) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+        Box::new(self.tree(col).range(prefix..).filter_map(Result::ok).map(boxed_entry))
+    }
+
+    fn restore(&self, _new_db: &str) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Other, "The sled backend doesn't support restoring a database"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{COL_EXTRA, COL_STATE};
+    use primitives::H256;
+    use std::{env, fs};
+
+    #[test]
+    fn rocksdb_converts_to_sled() {
+        let dir = env::temp_dir().join(format!("database-test-{:x}", H256::random()));
+        let rocksdb_path = dir.join("rocksdb").to_str().unwrap().to_string();
+        let sled_path = dir.join("sled").to_str().unwrap().to_string();
+        let sled_config = ClientConfig {
+            db_backend: DatabaseBackend::Sled,
+            ..Default::default()
+        };
+
+        let from = open_database(&rocksdb_path, &ClientConfig::default()).unwrap();
+        let mut batch = DBTransaction::new();
+        batch.put(COL_STATE, b"state", b"root");
+        batch.put(COL_EXTRA, b"extra-1", b"one");
+        batch.put(COL_EXTRA, b"extra-2", b"two");
+        from.write(batch).unwrap();
+
+        let to = open_database(&sled_path, &sled_config).unwrap();
+        let mut progress = Vec::new();
+        assert_eq!(migrate_database(&*from, &*to, |col, copied| progress.push((col, copied))).unwrap(), 3);
+        assert!(progress.contains(&(COL_EXTRA, 2)));
+
+        assert_eq!(to.get(COL_STATE, b"state").unwrap().unwrap().to_vec(), b"root".to_vec());
+        assert_eq!(to.get(COL_EXTRA, b"state").unwrap(), None);
+        let extras: Vec<_> = to.iter_from_prefix(COL_EXTRA, b"extra-2").map(|(key, _)| key.to_vec()).collect();
+        assert_eq!(extras, vec![b"extra-2".to_vec()]);
+
+        let mut batch = DBTransaction::new();
+        batch.delete(COL_EXTRA, b"extra-1");
+        to.write(batch).unwrap();
+        assert_eq!(to.iter(COL_EXTRA).count(), 1);
+
+        drop(to);
+        assert!(open_database(&sled_path, &ClientConfig::default()).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,359 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Starting a new chain from the state of an existing chain.
+//!
+//! The old chain exports its state at a block as snapshot chunks. The new chain restores the chunks
+//! into an empty database, replaces the validators' keys and uses the resulting state as its genesis.
+//! Only the validator sets kept by the host are translated; the state of the modules is carried as it is.
+//!
+//! The chunks can also be carried in the genesis of the new chain's scheme, see `Scheme::export_genesis`.
+
+use crate::blockchain::{BlockChain, HeaderProvider};
+use crate::db;
+use crate::encoded;
+use crate::error::Error;
+use crate::scheme::Scheme;
+use cdb::{new_journaldb, Algorithm, AsHashDB, HashDB};
+use ckey::Ed25519Public as Public;
+use cstate::{CurrentValidatorSet, NextValidatorSet, StateDB, StateWithCache, TopLevelState, TopStateView};
+use ctypes::{BlockHash, BlockNumber};
+use kvdb::{DBTransaction, KeyValueDB};
+use merkle_trie::snapshot::{ChunkCompressor, ChunkDecompressor, Error as SnapshotError, Restore, Snapshot};
+use merkle_trie::{Trie, TrieError, TrieFactory};
+use primitives::{Bytes, H256};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::iter;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// The block of the old chain whose state the new chain starts from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ForkPoint {
+    pub block_hash: BlockHash,
+    pub block_number: BlockNumber,
+    pub state_root: H256,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ForkError {
+    UnknownBlock(BlockNumber),
+    /// The state of the block is not in the state DB.
+    MissingState(H256),
+    Export(String),
+    /// The new chain's database already has a state.
+    StateExists,
+    MissingChunk(H256),
+    InvalidChunk {
+        root: H256,
+        reason: String,
+    },
+    /// The restored trie doesn't have all the nodes under the root.
+    IncompleteState(H256),
+    /// The translation has a key that is not a validator at the fork point.
+    UnknownValidator(Public),
+}
+
+impl fmt::Display for ForkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ForkError::UnknownBlock(number) => write!(f, "Block #{} is not in the chain", number),
+            ForkError::MissingState(root) => write!(f, "The state {} is not in the database", root),
+            ForkError::Export(reason) => write!(f, "Cannot export the state: {}", reason),
+            ForkError::StateExists => write!(f, "The database already has a state"),
+            ForkError::MissingChunk(root) => write!(f, "The snapshot chunk {} is missing", root),
+            ForkError::InvalidChunk {
+                root,
+                reason,
+            } => write!(f, "The snapshot chunk {} is invalid: {}", root, reason),
+            ForkError::IncompleteState(root) => write!(f, "The state trie {} is incomplete", root),
+            ForkError::UnknownValidator(pubkey) => write!(f, "{:?} is not a validator", pubkey),
+        }
+    }
+}
+
+/// Exports the state of the chain stored in `db` at block `number` into `dir`.
+pub fn export_fork_state(
+    scheme: &Scheme,
+    db: Arc<dyn KeyValueDB>,
+    number: BlockNumber,
+    dir: &Path,
+) -> Result<ForkPoint, ForkError> {
+    let (header, state_db) = open_block_state(scheme, db, number)?;
+    let fork_point = ForkPoint::from(&header);
+    export_state(&state_db, fork_point.state_root, dir).map_err(|err| ForkError::Export(err.to_string()))?;
+    Ok(fork_point)
+}
+
+/// Finds block `number` in the chain stored in `db` and opens the state DB that has its state.
+pub(crate) fn open_block_state(
+    scheme: &Scheme,
+    db: Arc<dyn KeyValueDB>,
+    number: BlockNumber,
+) -> Result<(encoded::Header, StateDB), ForkError> {
+    let chain = BlockChain::new(&scheme.genesis_block(), Arc::clone(&db), None);
+    let block_hash = chain.block_hash(number).ok_or(ForkError::UnknownBlock(number))?;
+    let header = chain.block_header_data(&block_hash).ok_or(ForkError::UnknownBlock(number))?;
+
+    let state_db = StateDB::new(new_journaldb(db, Algorithm::Archive, db::COL_STATE));
+    if !state_db.as_hashdb().contains(&header.state_root()) {
+        return Err(ForkError::MissingState(header.state_root()))
+    }
+    Ok((header, state_db))
+}
+
+impl<'a> From<&'a encoded::Header> for ForkPoint {
+    fn from(header: &'a encoded::Header) -> Self {
+        Self {
+            block_hash: header.hash(),
+            block_number: header.number(),
+            state_root: header.state_root(),
+        }
+    }
+}
+
+/// Writes the state under `root` into `dir` as snapshot chunks, one file per chunk named after its root.
+///
+/// The top-level trie and the trie of every module are written.
+pub fn export_state(db: &StateDB, root: H256, dir: &Path) -> Result<(), SnapshotError> {
+    fs::create_dir_all(dir)?;
+    export_trie(db.as_hashdb(), root, dir)?;
+    for module_root in module_roots(db, root)? {
+        export_trie(db.as_hashdb(), module_root, dir)?;
+    }
+    Ok(())
+}
+
+fn export_trie(db: &dyn HashDB, root: H256, dir: &Path) -> Result<(), SnapshotError> {
+    for chunk in Snapshot::from_hashdb(db, root) {
+        let chunk_file = fs::File::create(chunk_path(dir, &chunk.root))?;
+        ChunkCompressor::new(chunk_file).compress_chunk(&chunk)?;
+    }
+    Ok(())
+}
+
+/// Collects the snapshot chunks of the state under `root` in memory, by their roots.
+pub fn state_chunks(db: &StateDB, root: H256) -> Result<HashMap<H256, Bytes>, SnapshotError> {
+    let mut chunks = HashMap::new();
+    for trie_root in iter::once(root).chain(module_roots(db, root)?) {
+        for chunk in Snapshot::from_hashdb(db.as_hashdb(), trie_root) {
+            let mut compressed = Vec::new();
+            ChunkCompressor::new(&mut compressed).compress_chunk(&chunk)?;
+            chunks.insert(chunk.root, compressed);
+        }
+    }
+    Ok(chunks)
+}
+
+/// Restores the state exported at the fork point into the empty database of the new chain.
+///
+/// Each chunk is checked against its root while it is restored,
+/// so the restored state is exactly the state the old chain committed to at the fork point.
+pub fn import_state(db: &Arc<dyn KeyValueDB>, fork_point: &ForkPoint, dir: &Path) -> Result<(), Error> {
+    restore_state(db, fork_point.state_root, &|chunk_root| fs::read(chunk_path(dir, chunk_root)).ok())
+}
+
+/// Restores the genesis state carried in the scheme into the empty database of the new chain.
+pub fn import_genesis_state(db: &Arc<dyn KeyValueDB>, root: H256, chunks: &HashMap<H256, Bytes>) -> Result<(), Error> {
+    restore_state(db, root, &|chunk_root| chunks.get(chunk_root).cloned())
+}
+
+fn restore_state(
+    db: &Arc<dyn KeyValueDB>,
+    root: H256,
+    read_chunk: &dyn Fn(&H256) -> Option<Bytes>,
+) -> Result<(), Error> {
+    let mut state_db = StateDB::new(new_journaldb(Arc::clone(db), Algorithm::Archive, db::COL_STATE));
+    if !state_db.is_empty() {
+        return Err(ForkError::StateExists.into())
+    }
+
+    restore_trie(&**db, &mut state_db, root, read_chunk)?;
+    for module_root in module_roots(&state_db, root)? {
+        restore_trie(&**db, &mut state_db, module_root, read_chunk)?;
+    }
+    Ok(())
+}
+
+fn restore_trie(
+    db: &dyn KeyValueDB,
+    state_db: &mut StateDB,
+    root: H256,
+    read_chunk: &dyn Fn(&H256) -> Option<Bytes>,
+) -> Result<(), Error> {
+    let mut restore = Restore::new(root);
+    while let Some(chunk_root) = restore.next_to_feed() {
+        let compressed = read_chunk(&chunk_root).ok_or(ForkError::MissingChunk(chunk_root))?;
+        let invalid_chunk = |reason: String| ForkError::InvalidChunk {
+            root: chunk_root,
+            reason,
+        };
+        let raw_chunk =
+            ChunkDecompressor::from_slice(&compressed).decompress().map_err(|err| invalid_chunk(err.to_string()))?;
+        let recovered = raw_chunk.recover(chunk_root).map_err(|err| invalid_chunk(err.to_string()))?;
+        restore.feed(state_db.as_hashdb_mut(), recovered);
+    }
+
+    let mut batch = DBTransaction::new();
+    state_db.journal_under(&mut batch, 0, H256::zero())?;
+    db.write(batch)?;
+
+    let is_complete =
+        TrieFactory::readonly(state_db.as_hashdb(), &root).map(|trie| trie.is_complete()).unwrap_or(false);
+    if !is_complete {
+        return Err(ForkError::IncompleteState(root).into())
+    }
+    Ok(())
+}
+
+/// Replaces the keys of the validators carried over to the new chain and stores the resulting state.
+///
+/// Validators that are not in `translation` keep their keys.
+/// Returns the state root of the new chain's genesis block.
+pub fn translate_validators(
+    db: &Arc<dyn KeyValueDB>,
+    root: H256,
+    translation: &HashMap<Public, Public>,
+) -> Result<H256, Error> {
+    let state_db = StateDB::new(new_journaldb(Arc::clone(db), Algorithm::Archive, db::COL_STATE));
+    let mut state = TopLevelState::from_existing(state_db, root)?;
+    let mut next_validators = NextValidatorSet::load_from_state(&state)?;
+    let mut current_validators = CurrentValidatorSet::load_from_state(&state)?;
+
+    let next = next_validators.create_compact_validator_set();
+    let current = current_validators.create_compact_validator_set();
+    if let Some(unknown) =
+        translation.keys().find(|pubkey| !next.iter().chain(current.iter()).any(|entry| entry.public_key == **pubkey))
+    {
+        return Err(ForkError::UnknownValidator(*unknown).into())
+    }
+
+    let translate = |pubkey: &Public| translation.get(pubkey).copied().unwrap_or(*pubkey);
+    next_validators.translate(&translate);
+    current_validators.translate(&translate);
+    next_validators.save_to_state(&mut state)?;
+    current_validators.save_to_state(&mut state)?;
+
+    let (mut state_db, genesis_root) = state.commit_and_clone_db()?;
+    let mut batch = DBTransaction::new();
+    state_db.journal_under(&mut batch, 0, H256::zero())?;
+    db.write(batch)?;
+    Ok(genesis_root)
+}
+
+fn module_roots(db: &StateDB, root: H256) -> Result<Vec<H256>, TrieError> {
+    TopLevelState::from_existing(db.clone(&root), root)?.module_roots()
+}
+
+fn chunk_path(dir: &Path, chunk_root: &H256) -> PathBuf {
+    dir.join(format!("{:x}", chunk_root))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cstate::{Metadata, TopState};
+    use ctypes::{CommonParams, CompactValidatorEntry, CompactValidatorSet, ConsensusParams};
+    use std::env;
+
+    fn empty_db() -> Arc<dyn KeyValueDB> {
+        Arc::new(kvdb_memorydb::create(db::NUM_COLUMNS.unwrap_or(0)))
+    }
+
+    fn pubkey(seed: u64) -> Public {
+        Public::from(seed)
+    }
+
+    fn old_chain_state(db: &Arc<dyn KeyValueDB>, validators: &[Public]) -> H256 {
+        let state_db = StateDB::new(new_journaldb(Arc::clone(db), Algorithm::Archive, db::COL_STATE));
+        let mut state = TopLevelState::from_existing(state_db, ccrypto::BLAKE_NULL_RLP).unwrap();
+        state.create_module().unwrap();
+        *state.get_metadata_mut().unwrap() =
+            Metadata::new(CommonParams::default(), ConsensusParams::default_for_test());
+        let validator_set = NextValidatorSet::from_compact_validator_set(CompactValidatorSet::new(
+            validators
+                .iter()
+                .map(|pubkey| CompactValidatorEntry {
+                    public_key: *pubkey,
+                    delegation: 100,
+                })
+                .collect(),
+        ));
+        validator_set.save_to_state(&mut state).unwrap();
+        let (mut state_db, root) = state.commit_and_clone_db().unwrap();
+        let mut batch = DBTransaction::new();
+        state_db.journal_under(&mut batch, 0, H256::zero()).unwrap();
+        db.write(batch).unwrap();
+        root
+    }
+
+    fn fork_point_at(state_root: H256) -> ForkPoint {
+        ForkPoint {
+            block_hash: H256::random().into(),
+            block_number: 10,
+            state_root,
+        }
+    }
+
+    #[test]
+    fn exported_state_is_restored_with_translated_validators() {
+        let old_db = empty_db();
+        let root = old_chain_state(&old_db, &[pubkey(1), pubkey(2)]);
+        let dir = env::temp_dir().join(format!("fork-test-{:x}", H256::random()));
+        let old_state_db = StateDB::new(new_journaldb(Arc::clone(&old_db), Algorithm::Archive, db::COL_STATE));
+        export_state(&old_state_db, root, &dir).unwrap();
+
+        let new_db = empty_db();
+        import_state(&new_db, &fork_point_at(root), &dir).unwrap();
+        let translation = vec![(pubkey(1), pubkey(3))].into_iter().collect();
+        let genesis_root = translate_validators(&new_db, root, &translation).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let state_db = StateDB::new(new_journaldb(new_db, Algorithm::Archive, db::COL_STATE));
+        let state = TopLevelState::from_existing(state_db, genesis_root).unwrap();
+        let validators: Vec<_> =
+            NextValidatorSet::load_from_state(&state).unwrap().iter().map(|v| *v.pubkey()).collect();
+        assert_eq!(vec![pubkey(2), pubkey(3)], validators);
+    }
+
+    #[test]
+    fn missing_chunk_is_detected() {
+        let old_db = empty_db();
+        let root = old_chain_state(&old_db, &[pubkey(1)]);
+        let dir = env::temp_dir().join(format!("fork-test-{:x}", H256::random()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let result = import_state(&empty_db(), &fork_point_at(root), &dir);
+        fs::remove_dir_all(&dir).unwrap();
+        match result {
+            Err(Error::Fork(ForkError::MissingChunk(chunk_root))) => assert_eq!(root, chunk_root),
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn unknown_validator_is_rejected() {
+        let db = empty_db();
+        let root = old_chain_state(&db, &[pubkey(1)]);
+        let translation = vec![(pubkey(2), pubkey(3))].into_iter().collect();
+        match translate_validators(&db, root, &translation) {
+            Err(Error::Fork(ForkError::UnknownValidator(unknown))) => assert_eq!(pubkey(2), unknown),
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+}
@@ -16,26 +16,30 @@
 
 use super::{BlockChainTrait, Client, ClientConfig};
 use crate::block::{enact, Block, ClosedBlock, IsBlock};
-use crate::blockchain::{BodyProvider, ChainUpdateResult, HeaderProvider};
+use crate::blockchain::{tree_route, BlockProvider, BodyProvider, ChainUpdateResult, EventProvider, HeaderProvider};
 use crate::client::EngineInfo;
 use crate::consensus::ConsensusEngine;
-use crate::error::Error;
+use crate::error::{BlockError, Error};
 use crate::event::{EventSource, EventsWithSource};
+use crate::metrics::{BLOCK_EXECUTION_SECONDS, BLOCK_IMPORT_SECONDS};
 use crate::miner::{Miner, MinerService};
+use crate::scheme::Checkpoints;
 use crate::service::ClientIoMessage;
 use crate::verification::queue::{BlockQueue, HeaderQueue};
-use crate::verification::{PreverifiedBlock, Verifier};
+use crate::verification::{verify_next_validator_set, PreverifiedBlock, Verifier};
 use crate::views::{BlockView, HeaderView};
 use cio::IoChannel;
-use coordinator::engine::BlockExecutor;
+use coordinator::engine::{BlockExecutor, TxFilter};
+use cstate::NextValidatorSet;
 use ctypes::header::{Header, Seal};
-use ctypes::{BlockHash, BlockId, SyncHeader};
+use ctypes::{BlockHash, BlockId, BlockNumber, SyncHeader};
 use kvdb::DBTransaction;
 use parking_lot::{Mutex, MutexGuard};
 use rlp::Encodable;
 use std::borrow::Borrow;
 use std::collections::HashSet;
 use std::iter::FromIterator;
+use std::time::Instant;
 use std::{ops::Deref, sync::Arc};
 
 pub struct Importer {
@@ -59,6 +63,9 @@ pub struct Importer {
 
     /// CodeChain engine to be used during import
     pub engine: Arc<dyn ConsensusEngine>,
+
+    /// The blocks pinned by the scheme
+    checkpoints: Checkpoints,
 }
 
 impl Importer {
@@ -68,10 +75,13 @@ impl Importer {
         message_channel: IoChannel<ClientIoMessage>,
         miner: Arc<Miner>,
         block_executor: Arc<dyn BlockExecutor>,
+        tx_filter: Arc<dyn TxFilter>,
+        checkpoints: Checkpoints,
     ) -> Result<Importer, Error> {
-        let block_queue = BlockQueue::new(&config.queue, engine.clone(), message_channel.clone());
+        let block_queue =
+            BlockQueue::new(&config.queue, engine.clone(), Arc::clone(&tx_filter), message_channel.clone());
 
-        let header_queue = HeaderQueue::new(&config.queue, engine.clone(), message_channel);
+        let header_queue = HeaderQueue::new(&config.queue, engine.clone(), tx_filter, message_channel);
 
         Ok(Importer {
             import_lock: Mutex::new(()),
@@ -81,20 +91,34 @@ impl Importer {
             miner,
             block_executor,
             engine,
+            checkpoints,
         })
     }
 
+    /// Rejects a header that disagrees with the checkpoints, before it enters the queues.
+    pub fn check_checkpoints(&self, header: &Header, chain: &impl HeaderProvider) -> Result<(), BlockError> {
+        self.checkpoints.verify(header)?;
+        if chain.is_known_header(&header.hash()) {
+            return Ok(())
+        }
+        // The chain has all the blocks below a checkpoint it has, so a new one there belongs to another history.
+        match self.checkpoints.at_or_above(header.number()).find(|checkpoint| chain.is_known_header(&checkpoint.hash)) {
+            Some(checkpoint) => Err(BlockError::BelowCheckpoint(checkpoint.number)),
+            None => Ok(()),
+        }
+    }
+
     /// This is triggered by a message coming from a block queue when the block is ready for insertion
     pub fn import_verified_blocks(&self, client: &Client) -> usize {
         let (imported_blocks, update_results, invalid_blocks, imported, is_empty) = {
             const MAX_BLOCKS_TO_IMPORT: usize = 1_000;
             let mut imported_blocks = Vec::with_capacity(MAX_BLOCKS_TO_IMPORT);
-            let mut invalid_blocks = HashSet::new();
             let mut update_results = Vec::with_capacity(MAX_BLOCKS_TO_IMPORT);
 
             let import_lock = self.import_lock.lock();
+            let mut invalid_blocks: HashSet<_> = self.block_queue.drain_rejected().into_iter().collect();
             let blocks = self.block_queue.drain(MAX_BLOCKS_TO_IMPORT);
-            if blocks.is_empty() {
+            if blocks.is_empty() && invalid_blocks.is_empty() {
                 return 0
             }
 
@@ -112,10 +136,12 @@ impl Importer {
                     invalid_blocks.insert(header.hash());
                     continue
                 }
+                let started_at = Instant::now();
                 if let Ok(closed_block) = self.check_and_close_block(&block, client) {
                     imported_blocks.push(header.hash());
                     let update_result = self.commit_block(&closed_block, &header, &block.bytes, client);
                     update_results.push(update_result);
+                    BLOCK_IMPORT_SECONDS.observe(started_at.elapsed().as_secs_f64());
                 } else {
                     invalid_blocks.insert(header.hash());
                 }
@@ -136,10 +162,16 @@ impl Importer {
                 if !is_empty {
                     ctrace!(CLIENT, "Call new_blocks even though block verification queue is not empty");
                 }
+                let tree_routes: Vec<_> =
+                    update_results.iter().filter_map(|result| result.tree_route.clone()).collect();
                 let enacted = self.extract_enacted(update_results);
                 self.miner.chain_new_blocks(client, &imported_blocks, &invalid_blocks, &enacted);
+                for tree_route in tree_routes {
+                    self.miner.chain_reorganized(client, &tree_route.retracted, &tree_route.enacted);
+                }
                 client.new_blocks(&imported_blocks, &invalid_blocks, &enacted);
             }
+            client.blocks_completed(&imported_blocks, &invalid_blocks);
         }
 
         client.db().flush().expect("DB flush failed.");
@@ -166,24 +198,7 @@ impl Importer {
 
         let chain = client.block_chain();
 
-        let mut events: Vec<EventsWithSource> = block
-            .tx_events()
-            .iter()
-            .map(|(tx_hash, events)| {
-                let source = EventSource::Transaction(*tx_hash);
-                EventsWithSource {
-                    source,
-                    events: events.clone(),
-                }
-            })
-            .collect();
-
-        let block_events = EventsWithSource {
-            source: EventSource::Block(block.header().hash()),
-            events: block.block_events().clone(),
-        };
-
-        events.push(block_events);
+        let events = events_of(block);
 
         assert_eq!(hash, BlockView::new(block_data).header_view().hash());
 
@@ -191,6 +206,16 @@ impl Importer {
 
         block.state().journal_under(&mut batch, number).expect("DB commit failed");
         let update_result = chain.insert_block(&mut batch, block_data, events, self.engine.borrow());
+        match NextValidatorSet::load_from_state(block.state()) {
+            Ok(next_validator_set) => {
+                let next_validator_set = next_validator_set.create_compact_validator_set();
+                match verify_next_validator_set(header, &next_validator_set) {
+                    Ok(()) => chain.insert_next_validator_set(&mut batch, hash, next_validator_set),
+                    Err(e) => cwarn!(CLIENT, "The next validator set of #{} ({}) is not recorded: {}", number, hash, e),
+                }
+            }
+            Err(e) => cwarn!(CLIENT, "Cannot read the next validator set of #{} ({}): {}", number, hash, e),
+        }
 
         // Final commit to the DB
         client.db().write_buffered(batch);
@@ -245,6 +270,19 @@ impl Importer {
             );
         })?;
 
+        if let Some(e) = block.rejected_transaction {
+            if consensus_params.activations().transaction_checks(header.number()) {
+                cwarn!(
+                    CLIENT,
+                    "Stage 3 block verification failed for #{} ({})\nError: {:?}",
+                    header.number(),
+                    header.hash(),
+                    e
+                );
+                return Err(())
+            }
+        }
+
         self.verifier.verify_block_external(header, engine).map_err(|e| {
             cwarn!(
                 CLIENT,
@@ -258,6 +296,7 @@ impl Importer {
         // Enact Verified Block
         let db = client.state_db().read().clone(&parent.state_root());
 
+        let timer = BLOCK_EXECUTION_SECONDS.start_timer();
         let enact_result = enact(
             &block.header,
             block.evidences.clone(),
@@ -267,6 +306,7 @@ impl Importer {
             db,
             &parent,
         );
+        timer.observe_duration();
         let closed_block = enact_result.map_err(|e| {
             cwarn!(CLIENT, "Block import failed for #{} ({})\nError: {:?}", header.number(), header.hash(), e);
         })?;
@@ -285,10 +325,40 @@ impl Importer {
         Ok(closed_block)
     }
 
+    /// Executes the canonical block of the number again to store its events, if they are missing.
+    /// Returns whether the block is executed.
+    pub fn backfill_events(&self, number: BlockNumber, client: &Client) -> Result<bool, String> {
+        let _import_lock = self.import_lock.lock();
+        let chain = client.block_chain();
+        let hash = chain.block_hash(number).ok_or_else(|| format!("The hash of block #{} is missing", number))?;
+        if chain.is_known_source(&EventSource::Block(hash)) {
+            return Ok(false)
+        }
+        let block = chain.block(&hash).ok_or_else(|| format!("Block #{}({}) is missing", number, hash))?;
+        let header = block.decode_header();
+        let parent = chain
+            .block_header(header.parent_hash())
+            .ok_or_else(|| format!("The parent of block #{}({}) is missing", number, hash))?;
+
+        let db = client.state_db().read().clone(&parent.state_root());
+        let closed_block =
+            enact(&header, block.evidences(), &block.transactions(), &*self.engine, &*self.block_executor, db, &parent)
+                .map_err(|err| format!("Cannot execute block #{}({}): {}", number, hash, err))?;
+        self.verifier
+            .verify_block_final(&header, closed_block.block().header())
+            .map_err(|err| format!("Block #{}({}) is executed differently: {}", number, hash, err))?;
+
+        let mut batch = DBTransaction::new();
+        chain.insert_events(&mut batch, events_of(&closed_block));
+        client.db().write(batch).expect("DB flush failed.");
+        Ok(true)
+    }
+
     /// This is triggered by a message coming from a header queue when the header is ready for insertion
     pub fn import_verified_headers_from_queue(&self, client: &Client) -> usize {
         const MAX_HEADERS_TO_IMPORT: usize = 1_000;
         let lock = self.import_lock.lock();
+        self.header_queue.mark_as_bad(&self.header_queue.drain_rejected());
         let headers = self.header_queue.drain(MAX_HEADERS_TO_IMPORT);
         self.import_verified_headers(
             headers.iter().map(|sync_header| VerifiedHeader::from_sync(sync_header)),
@@ -336,7 +406,7 @@ impl Importer {
                 // Do nothing if the header is already imported
             } else if self.check_header(&header, &parent_header, grand_parent.as_ref()) {
                 imported.push(hash);
-                update_results.push(self.commit_header(&header, client));
+                update_results.push(self.commit_header(&header, grand_parent.as_ref(), client));
             } else {
                 bad.insert(hash);
             }
@@ -395,13 +465,25 @@ impl Importer {
     }
 
     pub fn force_update_best_block(&self, hash: &BlockHash, client: &Client) {
-        let chain = client.block_chain();
-        let mut batch = DBTransaction::new();
-        chain.force_update_best_block(&mut batch, hash);
-        client.db().write_buffered(batch);
-        chain.commit();
+        let route = {
+            let chain = client.block_chain();
+            let route = tree_route(&*chain, chain.best_block_hash(), *hash);
+            let mut batch = DBTransaction::new();
+            chain.force_update_best_block(&mut batch, hash);
+            client.db().write_buffered(batch);
+            chain.commit();
+            route
+        };
 
         client.db().flush().expect("DB flush failed.");
+
+        match route {
+            Some(route) if !route.retracted.is_empty() => {
+                self.miner.chain_reorganized(client, &route.retracted, &route.enacted)
+            }
+            Some(_) => {}
+            None => cwarn!(CLIENT, "Cannot find the route to the new best block {}", hash),
+        }
     }
 
     /// grand_parent === None only when parent is genesis
@@ -433,12 +515,26 @@ impl Importer {
         true
     }
 
-    fn commit_header(&self, header: &Header, client: &Client) -> ChainUpdateResult {
+    fn commit_header(
+        &self,
+        header: &VerifiedHeader,
+        grand_parent: Option<&Header>,
+        client: &Client,
+    ) -> ChainUpdateResult {
         let chain = client.block_chain();
 
         let mut batch = DBTransaction::new();
         let update_result =
             chain.insert_header(&mut batch, &HeaderView::new(&header.rlp_bytes()), self.engine.borrow());
+        // The validator set that verified the seal of a synced header is the one committed by its grand parent.
+        // The genesis header does not commit a validator set, so it fails the verification and is skipped.
+        if let (VerifiedHeader::FromSync(sync_header), Some(grand_parent)) = (header, grand_parent) {
+            if let Some(validator_set) = sync_header.prev_validator_set() {
+                if verify_next_validator_set(grand_parent, validator_set).is_ok() {
+                    chain.insert_next_validator_set(&mut batch, grand_parent.hash(), validator_set.clone());
+                }
+            }
+        }
         client.db().write_buffered(batch);
         chain.commit();
 
@@ -446,6 +542,23 @@ impl Importer {
     }
 }
 
+/// The events of the transactions and of the block itself, as stored in the chain.
+fn events_of<B: IsBlock>(block: &B) -> Vec<EventsWithSource> {
+    let mut events: Vec<EventsWithSource> = block
+        .tx_events()
+        .iter()
+        .map(|(tx_hash, events)| EventsWithSource {
+            source: EventSource::Transaction(*tx_hash),
+            events: events.clone(),
+        })
+        .collect();
+    events.push(EventsWithSource {
+        source: EventSource::Block(block.header().hash()),
+        events: block.block_events().clone(),
+    });
+    events
+}
+
 pub enum VerifiedHeader<'a> {
     FromSync(&'a SyncHeader),
     Generated(&'a Header),
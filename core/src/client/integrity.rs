@@ -0,0 +1,478 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::blockchain::{BlockChain, BodyProvider, HeaderProvider, TransactionAddress};
+use crate::db::{self, Readable};
+use crate::event::{EventSource, Events};
+use crate::scheme::Scheme;
+use crate::views::{BodyView, HeaderView};
+use ccrypto::BLAKE_NULL_RLP;
+use cdb::{new_journaldb, Algorithm, AsHashDB, HashDB};
+use cstate::StateDB;
+use ctypes::{BlockHash, BlockNumber, TransactionIndex, TxHash};
+use kvdb::{DBTransaction, KeyValueDB};
+use merkle_trie::skewed_merkle_root;
+use primitives::H256;
+use rlp_compress::{blocks_swapper, decompress};
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Progress is reported once per this many blocks.
+pub const INTEGRITY_PROGRESS_INTERVAL: u64 = 1000;
+
+/// An inconsistency found in the stored chain data.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Inconsistency {
+    /// The canonical block hash of the number is missing.
+    MissingBlockHash(BlockNumber),
+    MissingHeader {
+        number: BlockNumber,
+        hash: BlockHash,
+    },
+    /// The stored header doesn't hash to the key it is stored under.
+    HeaderHashMismatch {
+        number: BlockNumber,
+        expected: BlockHash,
+        found: BlockHash,
+    },
+    /// The stored header's number or parent doesn't link it to the canonical chain.
+    BrokenLink {
+        number: BlockNumber,
+        hash: BlockHash,
+    },
+    MissingBody {
+        number: BlockNumber,
+        hash: BlockHash,
+    },
+    /// The transactions in the stored body don't match the transactions root of the header.
+    TransactionsRootMismatch {
+        number: BlockNumber,
+        expected: H256,
+        found: H256,
+    },
+    /// The transaction index doesn't point to the transaction. This is repairable.
+    WrongTransactionAddress {
+        tx_hash: TxHash,
+        expected: TransactionAddress,
+        found: Option<TransactionAddress>,
+    },
+    /// The state of the block is not in the state DB.
+    MissingState {
+        number: BlockNumber,
+        state_root: H256,
+    },
+    /// The outcome of executing the block is not stored. A running client repairs it by executing the block again.
+    MissingBlockEvents {
+        number: BlockNumber,
+        hash: BlockHash,
+    },
+    /// The outcome of the transaction is not stored, while the outcome of its block is.
+    MissingTransactionEvents {
+        number: BlockNumber,
+        tx_hash: TxHash,
+    },
+}
+
+impl Inconsistency {
+    pub fn is_repairable(&self) -> bool {
+        match self {
+            Inconsistency::WrongTransactionAddress {
+                ..
+            } => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Inconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Inconsistency::MissingBlockHash(number) => write!(f, "The hash of block #{} is missing", number),
+            Inconsistency::MissingHeader {
+                number,
+                hash,
+            } => write!(f, "The header of block #{}({}) is missing", number, hash),
+            Inconsistency::HeaderHashMismatch {
+                number,
+                expected,
+                found,
+            } => write!(f, "The header of block #{} is stored under {} but hashes to {}", number, expected, found),
+            Inconsistency::BrokenLink {
+                number,
+                hash,
+            } => write!(f, "Block #{}({}) is not linked to its canonical parent", number, hash),
+            Inconsistency::MissingBody {
+                number,
+                hash,
+            } => write!(f, "The body of block #{}({}) is missing", number, hash),
+            Inconsistency::TransactionsRootMismatch {
+                number,
+                expected,
+                found,
+            } => write!(f, "The transactions root of block #{} is {} but the body gives {}", number, expected, found),
+            Inconsistency::WrongTransactionAddress {
+                tx_hash,
+                expected,
+                found,
+            } => write!(f, "The address of transaction {} is {:?}, expected {:?}", tx_hash, found, expected),
+            Inconsistency::MissingState {
+                number,
+                state_root,
+            } => write!(f, "The state {} of block #{} is missing", state_root, number),
+            Inconsistency::MissingBlockEvents {
+                number,
+                hash,
+            } => write!(f, "The events of block #{}({}) are missing", number, hash),
+            Inconsistency::MissingTransactionEvents {
+                number,
+                tx_hash,
+            } => write!(f, "The events of transaction {} in block #{} are missing", tx_hash, number),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IntegrityReport {
+    /// The range of the blocks actually checked.
+    pub checked: Range<BlockNumber>,
+    pub inconsistencies: Vec<Inconsistency>,
+    /// The number of inconsistencies repaired.
+    pub repaired: usize,
+}
+
+impl IntegrityReport {
+    pub fn is_consistent(&self) -> bool {
+        self.inconsistencies.len() == self.repaired
+    }
+}
+
+fn read_decompressed(db: &dyn KeyValueDB, col: Option<u32>, hash: &BlockHash) -> Option<Vec<u8>> {
+    let bytes = db.get(col, hash.as_ref()).expect("Low level database error. Some issue with disk?")?;
+    Some(decompress(&bytes, blocks_swapper()))
+}
+
+/// Checks the canonical blocks in `range` against the DB.
+/// It reads headers, bodies and events from the DB directly, bypassing the caches of `chain`.
+/// If `repair` is set, the transaction addresses are fixed into `batch`.
+/// `progress` is called once per `INTEGRITY_PROGRESS_INTERVAL` blocks, but not at the end of the range.
+pub(super) fn verify_chain(
+    chain: &BlockChain,
+    db: &dyn KeyValueDB,
+    state_db: &dyn HashDB,
+    range: Range<BlockNumber>,
+    repair: bool,
+    batch: &mut DBTransaction,
+    progress: &mut dyn FnMut(BlockNumber, &Range<BlockNumber>),
+) -> IntegrityReport {
+    let best_number = chain.best_block_detail().number;
+    let range = range.start..range.end.min(best_number + 1);
+    let mut report = IntegrityReport {
+        checked: range.clone(),
+        ..Default::default()
+    };
+    let mut parent: Option<BlockHash> = if range.start == 0 {
+        None
+    } else {
+        chain.block_hash(range.start - 1)
+    };
+
+    for number in range.clone() {
+        if number % INTEGRITY_PROGRESS_INTERVAL == 0 {
+            progress(number, &range);
+        }
+        let found = verify_block(chain, db, state_db, number, parent, &mut report.inconsistencies);
+        parent = found.map(|(hash, _)| hash);
+
+        if let Some((hash, tx_hashes)) = found {
+            // The genesis block is not executed, so it has no events.
+            if number > 0 {
+                verify_events(db, number, hash, &tx_hashes, &mut report.inconsistencies);
+            }
+            let mut needs_reindex = false;
+            for (index, tx_hash) in tx_hashes.iter().enumerate() {
+                let expected = TransactionAddress {
+                    block_hash: hash,
+                    index: index as TransactionIndex,
+                };
+                let found = chain.transaction_address(tx_hash);
+                if found != Some(expected) {
+                    report.inconsistencies.push(Inconsistency::WrongTransactionAddress {
+                        tx_hash: *tx_hash,
+                        expected,
+                        found,
+                    });
+                    needs_reindex = true;
+                    if repair {
+                        report.repaired += 1;
+                    }
+                }
+            }
+            if repair && needs_reindex {
+                chain.reindex_transactions(batch, hash, tx_hashes);
+            }
+        }
+    }
+    report
+}
+
+/// Checks that the events of the block and of each of its transactions, which make up its outcome, are stored.
+fn verify_events(
+    db: &dyn KeyValueDB,
+    number: BlockNumber,
+    hash: BlockHash,
+    tx_hashes: &[TxHash],
+    inconsistencies: &mut Vec<Inconsistency>,
+) {
+    if !db.exists::<Events, H256>(db::COL_EVENT, &EventSource::Block(hash)) {
+        inconsistencies.push(Inconsistency::MissingBlockEvents {
+            number,
+            hash,
+        });
+        return
+    }
+    for tx_hash in tx_hashes {
+        if !db.exists::<Events, H256>(db::COL_EVENT, &EventSource::Transaction(*tx_hash)) {
+            inconsistencies.push(Inconsistency::MissingTransactionEvents {
+                number,
+                tx_hash: *tx_hash,
+            });
+        }
+    }
+}
+
+/// Returns the hash and the transaction hashes of the block if its header and body are usable.
+fn verify_block(
+    chain: &BlockChain,
+    db: &dyn KeyValueDB,
+    state_db: &dyn HashDB,
+    number: BlockNumber,
+    parent: Option<BlockHash>,
+    inconsistencies: &mut Vec<Inconsistency>,
+) -> Option<(BlockHash, Vec<TxHash>)> {
+    let hash = match chain.block_hash(number) {
+        Some(hash) => hash,
+        None => {
+            inconsistencies.push(Inconsistency::MissingBlockHash(number));
+            return None
+        }
+    };
+    let header_bytes = match read_decompressed(db, db::COL_HEADERS, &hash) {
+        Some(bytes) => bytes,
+        None => {
+            inconsistencies.push(Inconsistency::MissingHeader {
+                number,
+                hash,
+            });
+            return None
+        }
+    };
+    let header = HeaderView::new(&header_bytes);
+    let found = header.hash();
+    if found != hash {
+        inconsistencies.push(Inconsistency::HeaderHashMismatch {
+            number,
+            expected: hash,
+            found,
+        });
+        return None
+    }
+    if header.number() != number || parent.map_or(false, |parent| parent != header.parent_hash()) {
+        inconsistencies.push(Inconsistency::BrokenLink {
+            number,
+            hash,
+        });
+    }
+    let state_root = header.state_root();
+    if state_root != BLAKE_NULL_RLP && !state_db.contains(&state_root) {
+        inconsistencies.push(Inconsistency::MissingState {
+            number,
+            state_root,
+        });
+    }
+
+    let body_bytes = match read_decompressed(db, db::COL_BODIES, &hash) {
+        Some(bytes) => bytes,
+        None => {
+            inconsistencies.push(Inconsistency::MissingBody {
+                number,
+                hash,
+            });
+            return None
+        }
+    };
+    let body = BodyView::new(&body_bytes);
+    let raw_transactions = body.rlp().at(1).ok()?.iter().map(|rlp| rlp.as_raw().to_vec());
+    let expected = header.transactions_root();
+    let found = skewed_merkle_root(BLAKE_NULL_RLP, raw_transactions);
+    if expected != found {
+        inconsistencies.push(Inconsistency::TransactionsRootMismatch {
+            number,
+            expected,
+            found,
+        });
+        return None
+    }
+    Some((hash, body.transaction_hashes()))
+}
+
+/// Does the same as `Client::verify_database` on a DB that is not used by a running client.
+/// The missing events of blocks are not repaired, since it takes the modules to execute the blocks again.
+pub fn verify_database_offline(
+    scheme: &Scheme,
+    db: Arc<dyn KeyValueDB>,
+    range: Range<BlockNumber>,
+    repair: bool,
+    progress: &mut dyn FnMut(BlockNumber, &Range<BlockNumber>),
+) -> IntegrityReport {
+    let chain = BlockChain::new(&scheme.genesis_block(), Arc::clone(&db), None);
+    let state_db = StateDB::new(new_journaldb(Arc::clone(&db), Algorithm::Archive, db::COL_STATE));
+    let mut batch = DBTransaction::new();
+    let report = verify_chain(&chain, &*db, state_db.as_hashdb(), range, repair, &mut batch, progress);
+    if report.repaired > 0 {
+        db.write(batch).expect("DB flush failed.");
+        chain.commit();
+    }
+    progress(report.checked.end, &report.checked);
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+    use crate::event::EventsWithSource;
+    use crate::views::BlockView;
+    use ctypes::header::{Header, Seal};
+
+    fn genesis_db(scheme: &Scheme) -> (Arc<dyn KeyValueDB>, BlockHash) {
+        let db: Arc<dyn KeyValueDB> = Arc::new(kvdb_memorydb::create(db::NUM_COLUMNS.unwrap_or(0)));
+        let genesis = scheme.genesis_block();
+        BlockChain::new(&genesis, Arc::clone(&db), None);
+        (db, BlockView::new(&genesis).hash())
+    }
+
+    /// Stores an empty block #1 on top of the genesis block, with its events if `with_events` is set.
+    fn insert_first_block(
+        scheme: &Scheme,
+        db: &Arc<dyn KeyValueDB>,
+        genesis_hash: BlockHash,
+        with_events: bool,
+    ) -> BlockHash {
+        let mut header = Header::new();
+        header.set_parent_hash(genesis_hash);
+        header.set_number(1);
+        let hash = header.hash();
+        let block = Block {
+            header,
+            evidences: vec![],
+            transactions: vec![],
+        };
+        let events = if with_events {
+            vec![EventsWithSource {
+                source: EventSource::Block(hash),
+                events: vec![],
+            }]
+        } else {
+            vec![]
+        };
+
+        let chain = BlockChain::new(&scheme.genesis_block(), Arc::clone(db), None);
+        let mut batch = DBTransaction::new();
+        chain.insert_block(&mut batch, &block.rlp_bytes(&Seal::With), events, &*scheme.engine);
+        db.write(batch).unwrap();
+        chain.commit();
+        hash
+    }
+
+    /// The genesis state is not built in these tests.
+    fn without_missing_states(report: IntegrityReport) -> Vec<Inconsistency> {
+        report
+            .inconsistencies
+            .into_iter()
+            .filter(|inconsistency| match inconsistency {
+                Inconsistency::MissingState {
+                    ..
+                } => false,
+                _ => true,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn range_is_clamped_to_best_block() {
+        let scheme = Scheme::new_test_solo();
+        let (db, _) = genesis_db(&scheme);
+
+        let report = verify_database_offline(&scheme, db, 0..100, false, &mut |_, _| {});
+        assert_eq!(0..1, report.checked);
+        assert!(report.inconsistencies.iter().all(|inconsistency| match inconsistency {
+            Inconsistency::MissingState {
+                ..
+            } => true,
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn missing_header_is_detected() {
+        let scheme = Scheme::new_test_solo();
+        let (db, genesis_hash) = genesis_db(&scheme);
+        let mut batch = DBTransaction::new();
+        batch.delete(db::COL_HEADERS, genesis_hash.as_ref());
+        db.write(batch).unwrap();
+
+        let report = verify_database_offline(&scheme, db, 0..1, true, &mut |_, _| {});
+        assert_eq!(
+            vec![Inconsistency::MissingHeader {
+                number: 0,
+                hash: genesis_hash,
+            }],
+            report.inconsistencies
+        );
+        assert_eq!(0, report.repaired);
+        assert!(!report.is_consistent());
+    }
+
+    #[test]
+    fn missing_events_are_detected() {
+        let scheme = Scheme::new_test_solo();
+        let (db, genesis_hash) = genesis_db(&scheme);
+        let hash = insert_first_block(&scheme, &db, genesis_hash, false);
+
+        let report = verify_database_offline(&scheme, db, 0..2, true, &mut |_, _| {});
+        assert_eq!(0..2, report.checked);
+        assert_eq!(0, report.repaired);
+        assert_eq!(
+            vec![Inconsistency::MissingBlockEvents {
+                number: 1,
+                hash,
+            }],
+            without_missing_states(report)
+        );
+    }
+
+    #[test]
+    fn stored_events_are_consistent() {
+        let scheme = Scheme::new_test_solo();
+        let (db, genesis_hash) = genesis_db(&scheme);
+        insert_first_block(&scheme, &db, genesis_hash, true);
+
+        let report = verify_database_offline(&scheme, db, 0..2, false, &mut |_, _| {});
+        assert_eq!(0..2, report.checked);
+        assert_eq!(Vec::<Inconsistency>::new(), without_missing_states(report));
+    }
+}
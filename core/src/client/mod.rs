@@ -14,37 +14,56 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+mod archive;
+mod backfill;
 mod chain_notify;
 #[cfg_attr(feature = "cargo-clippy", allow(clippy::module_inception))]
 mod client;
 mod config;
+mod database;
+mod fork;
 mod importer;
+mod integrity;
 pub mod snapshot_notify;
+mod soft_limits;
 mod test_client;
+mod timestamp_index;
 
+pub use self::archive::{ArchiveDb, ArchiveStats};
+pub use self::backfill::BackfillStatus;
 pub use self::chain_notify::ChainNotify;
 
 pub use self::client::Client;
-pub use self::config::ClientConfig;
+pub use self::config::{ClientConfig, DatabaseBackend, DatabaseCompactionProfile};
+pub use self::database::{migrate_database, open_database, SledDb};
+pub(crate) use self::fork::open_block_state;
+pub use self::fork::{
+    export_fork_state, export_state, import_genesis_state, import_state, state_chunks, translate_validators, ForkError,
+    ForkPoint,
+};
+pub use self::integrity::{verify_database_offline, Inconsistency, IntegrityReport, INTEGRITY_PROGRESS_INTERVAL};
+pub use self::soft_limits::NodeHealth;
 pub use self::test_client::TestBlockChainClient;
 
 use crate::block::{Block, ClosedBlock, OpenBlock};
 use crate::blockchain_info::BlockChainInfo;
-use crate::consensus::EngineError;
+use crate::consensus::{EngineError, HeightStats, TimeoutParams};
 use crate::encoded;
 use crate::error::{BlockImportError, Error as GenericError};
+use crate::miner::{IngressStats, OrphanStats, PoolPage, PoolQuery};
 use crate::transaction::{LocalizedTransaction, PendingTransactions};
 use crate::types::{BlockStatus, TransactionId, VerificationQueueInfo as BlockQueueInfo};
 use cdb::DatabaseError;
 use ckey::{Ed25519Public as Public, NetworkId, PlatformAddress};
-use coordinator::types::Event;
-use coordinator::Transaction;
+use coordinator::types::{Event, InspectedPayload, TransactionPreview};
+use coordinator::{Transaction, TransactionWithMetadata, TxSizes};
 use cstate::{TopLevelState, TopStateView};
 use ctypes::{
-    BlockHash, BlockId, BlockNumber, CommonParams, CompactValidatorSet, ConsensusParams, Header, SyncHeader, TxHash,
+    BlockHash, BlockId, BlockNumber, Commit, CommonParams, CompactValidatorSet, ConsensusParams, Header,
+    ModuleDatumProof, NetworkIdentity, StorageId, SyncHeader, TxHash,
 };
 use kvdb::KeyValueDB;
-use primitives::Bytes;
+use primitives::{Bytes, H256};
 use std::ops::Range;
 use std::sync::Arc;
 
@@ -77,8 +96,26 @@ pub trait EngineInfo: Send + Sync {
     fn common_params(&self, block_id: BlockId) -> Option<CommonParams>;
     fn consensus_params(&self, block_id: BlockId) -> Option<ConsensusParams>;
     fn metadata_seq(&self, block_id: BlockId) -> Option<u64>;
+    /// Get the roots of the module substorages in the state of the given block,
+    /// which the `module_roots_hash` of its header commits to once the module roots are activated.
+    fn module_roots(&self, block_id: BlockId) -> Option<Vec<H256>>;
+    /// Get the proof of a module datum in the state of the given block,
+    /// which a light client checks against the `module_roots_hash` of its header.
+    fn module_datum_proof(&self, block_id: BlockId, storage_id: StorageId, key: &[u8]) -> Option<ModuleDatumProof>;
+    /// Get the precommits that finalized the given block, carried in the seal of its child.
+    /// `None` until the child is imported.
+    fn commit(&self, block_id: BlockId) -> Option<Commit>;
     fn possible_authors(&self, block_number: Option<u64>) -> Result<Option<Vec<PlatformAddress>>, EngineError>;
-    fn validator_set(&self, block_number: Option<u64>) -> Result<Option<CompactValidatorSet>, EngineError>;
+    /// Get the validator set that votes on the given block.
+    /// Its hash is the `next_validator_set_hash` of the parent block.
+    fn validator_set(&self, block_id: BlockId) -> Result<Option<CompactValidatorSet>, EngineError>;
+    /// Get the round statistics of the recent heights, the oldest first.
+    fn recent_height_stats(&self) -> Vec<HeightStats>;
+    /// Get the step timeouts of the consensus engine, if it uses them.
+    fn timeouts(&self) -> Option<TimeoutParams>;
+    /// Override the step timeouts of the consensus engine without restarting.
+    /// Returns the updated timeouts, or `None` if the engine doesn't use them.
+    fn update_timeouts(&self, params: &cjson::scheme::TendermintParams) -> Option<TimeoutParams>;
 }
 
 /// Client facilities used by internally sealing Engines.
@@ -86,6 +123,10 @@ pub trait EngineClient: Sync + Send + BlockChainTrait + ImportBlock {
     /// Make a new block and seal it.
     fn update_sealing(&self, parent_block: BlockId, allow_empty_block: bool);
 
+    /// Whether a block should be proposed on the parent now, as decided by the miner.
+    /// Used in Tendermint to wait for transactions instead of moving on to the next view.
+    fn is_block_proposal_due(&self, parent_block: BlockId) -> bool;
+
     /// Update the best block as the given block hash
     ///
     /// Used in Tendermint, when going to the commit step.
@@ -127,6 +168,11 @@ pub trait ImportBlock {
     /// Import a block into the blockchain.
     fn import_block(&self, bytes: Bytes) -> Result<BlockHash, BlockImportError>;
 
+    /// Queue a block received from a peer, unless the block queue is at its limits.
+    /// It fails with `ImportError::QueueFull` then, which the caller should take as the signal to back off
+    /// until `ChainNotify::blocks_completed` reports that queued blocks left the queue.
+    fn queue_block(&self, bytes: Bytes) -> Result<BlockHash, BlockImportError>;
+
     /// Import a header into the blockchain
     fn import_header(&self, header: SyncHeader) -> Result<BlockHash, BlockImportError>;
 
@@ -158,18 +204,75 @@ pub trait BlockChainClient: Sync + Send + BlockChainTrait + ImportBlock {
     /// Queue own transaction to mem_pool for importing
     fn queue_own_transaction(&self, transaction: Transaction) -> Result<(), GenericError>;
 
+    /// Queue own transaction to mem_pool to be included in a block from `activation_block`.
+    fn queue_scheduled_transaction(
+        &self,
+        transaction: Transaction,
+        activation_block: BlockNumber,
+    ) -> Result<(), GenericError>;
+
     /// Queue transactions to mem_pool for importing.
     fn queue_transactions(&self, transactions: Vec<Bytes>);
 
+    /// Preview the outcome of a transaction as if it were included in the block following `parent`.
+    /// Returns `None` if the state of `parent` is not available.
+    fn preview_transaction(
+        &self,
+        transaction: &Transaction,
+        parent: BlockId,
+    ) -> Result<Option<TransactionPreview>, GenericError>;
+
     /// Delete all pending transactions.
     fn delete_all_pending_transactions(&self);
 
     /// List all transactions in the mem_pool a.k.a pending transactions
     fn pending_transactions(&self, range: Range<u64>) -> PendingTransactions;
 
+    /// List the hashes of the pending transactions that can be propagated to peers.
+    fn propagatable_transaction_hashes(&self) -> Vec<TxHash>;
+
+    /// List the given pending transactions from the one a block would include first,
+    /// e.g. the one paying the highest fee. The ones no longer pending are left out.
+    fn prioritized_pending_transactions(&self, hashes: &[TxHash]) -> Vec<Transaction>;
+
+    /// List the pending transactions submitted to this node, with their metadata.
+    fn local_pending_transactions(&self) -> Vec<TransactionWithMetadata>;
+
+    /// Get the lowest fee a transaction must pay to replace a pending one paying `fee`.
+    fn replacement_fee(&self, fee: u64) -> Option<u64>;
+
+    /// List a page of the pending transactions matching the query, continuing from its cursor.
+    fn query_pending_transactions(&self, query: &PoolQuery) -> PoolPage;
+
     /// Get the count of all pending transactions currently in the mem_pool.
     fn count_pending_transactions(&self, range: Range<u64>) -> usize;
 
+    /// Get how many external transactions of each rate-limited type were admitted to the mem_pool and dropped.
+    fn ingress_stats(&self) -> Vec<IngressStats>;
+
+    /// Get how many transactions invalid against the state were checked again in the mem_pool, and how they ended up.
+    fn orphan_stats(&self) -> OrphanStats;
+
+    /// Split the bytes of the transaction into the witness and the payload, as its owner module counts them.
+    fn transaction_sizes(&self, tx: &Transaction) -> TxSizes;
+
+    /// Decode the payload of the transaction with the module inspecting its type.
+    /// Returns `None` if no module inspects the type.
+    fn inspect_transaction(&self, tx: &Transaction) -> Option<Result<InspectedPayload, String>>;
+
+    /// List the transactions in the mem_pool waiting for their activation blocks.
+    fn scheduled_transactions(&self) -> Vec<(BlockNumber, Transaction)>;
+
+    /// Check whether the transaction is in the mem_pool, waiting to be included in a block or for its activation block.
+    fn is_pending_transaction(&self, hash: &TxHash) -> bool;
+
+    /// Get all the transactions in the mem_pool with their metadata.
+    fn dump_mem_pool(&self) -> Vec<TransactionWithMetadata>;
+
+    /// Put the dumped transactions into the mem_pool if they pass the checks, returning the loaded ones.
+    /// This is for debugging.
+    fn load_mem_pool(&self, transactions: Vec<TransactionWithMetadata>) -> Vec<TxHash>;
+
     /// Check whether there is any pending transactions or not.
     fn is_mem_pool_empty(&self) -> bool;
 
@@ -186,6 +289,9 @@ pub trait BlockChainClient: Sync + Send + BlockChainTrait + ImportBlock {
     /// Get block hash.
     fn block_hash(&self, id: &BlockId) -> Option<BlockHash>;
 
+    /// Get the number of the last canonical block whose timestamp is not after the given timestamp.
+    fn block_number_by_timestamp(&self, timestamp: u64) -> Option<BlockNumber>;
+
     /// Get transaction with given hash.
     fn transaction(&self, id: &TransactionId) -> Option<LocalizedTransaction>;
 
@@ -194,6 +300,22 @@ pub trait BlockChainClient: Sync + Send + BlockChainTrait + ImportBlock {
 
     /// get events emitted by given block
     fn events_by_block_hash(&self, hash: &BlockHash) -> Vec<Event>;
+
+    /// Get the network identities the validators registered by the given block.
+    fn network_identities(&self, id: &BlockId) -> Option<Vec<NetworkIdentity>>;
+
+    /// Get the flags raised while a usage is near its limit.
+    fn node_health(&self) -> NodeHealth;
+
+    /// Get the progress of storing the events of the blocks imported before the events were stored.
+    fn event_backfill_status(&self) -> BackfillStatus;
+
+    /// Get the block whose body is not in the local database from the attached archive.
+    /// The header must be in the local chain.
+    fn archived_block(&self, id: &BlockId) -> Option<encoded::Block>;
+
+    /// Get how often the attached archive was consulted, or `None` if no archive is attached.
+    fn archive_stats(&self) -> Option<ArchiveStats>;
 }
 
 /// Result of import block operation.
@@ -211,6 +333,9 @@ pub trait MiningBlockChainClient: BlockChainClient + BlockProducer {}
 /// Provides methods to access database.
 pub trait DatabaseClient {
     fn database(&self) -> Arc<dyn KeyValueDB>;
+
+    /// Re-verifies the stored canonical blocks in `range`, repairing what can be repaired if `repair` is set.
+    fn verify_database(&self, range: Range<BlockNumber>, repair: bool) -> IntegrityReport;
 }
 
 pub trait StateInfo {
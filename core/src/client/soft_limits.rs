@@ -0,0 +1,268 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::miner::MemPoolUsage;
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+
+/// The usage, in percent of a limit, from which the usage is considered near the limit.
+const SOFT_LIMIT_PERCENT: usize = 90;
+/// The number of the latest imported blocks looked at for the body size.
+const RECENT_BLOCKS: usize = 20;
+/// How many of the latest imported blocks must be near `max_body_size` to raise the flag.
+const NEAR_FULL_BLOCKS_TO_WARN: usize = 10;
+/// How many checks in a row must find a sentry disconnected to raise the flag,
+/// so that the connections being made at the start are not taken for losses.
+const SENTRY_CHECKS_TO_WARN: usize = 3;
+
+fn is_near_limit(usage: usize, limit: usize) -> bool {
+    limit != 0 && usage.saturating_mul(100) >= limit.saturating_mul(SOFT_LIMIT_PERCENT)
+}
+
+/// Flags raised while a usage stays close to its limit, before the limit starts rejecting user activity,
+/// and how often each limit was approached since the node started.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NodeHealth {
+    /// Many of the latest blocks are nearly as large as `max_body_size`.
+    pub blocks_near_max_body_size: bool,
+    /// The mem pool is nearly full, either in the number of transactions or in memory.
+    pub mem_pool_near_limit: bool,
+    /// The metadata of a candidate is nearly as large as `max_candidate_metadata_size`.
+    pub candidate_metadata_near_limit: bool,
+    /// The number of imported blocks that were nearly as large as `max_body_size`.
+    pub near_full_blocks: u64,
+    /// The number of times the mem pool was found nearly full.
+    pub near_full_mem_pool_checks: u64,
+    /// The number of times a candidate metadata was found near the limit, checked as blocks are imported.
+    pub near_limit_candidate_metadata_checks: u64,
+    /// Some of the sentries a validator in the sentry mode relies on are disconnected.
+    pub sentry_lost: bool,
+    /// None of the sentries is connected, so the validator is cut off from the network.
+    pub all_sentries_lost: bool,
+    /// The number of times a sentry was lost.
+    pub sentry_losses: u64,
+}
+
+#[derive(Default)]
+struct Inner {
+    health: NodeHealth,
+    /// Whether each of the latest blocks was near `max_body_size`, from the oldest.
+    recent_blocks: VecDeque<bool>,
+    /// The number of the latest checks in a row that found a sentry disconnected.
+    sentry_missing_checks: usize,
+}
+
+/// Watches the usages that have soft limits below the hard limits of the common params and the node,
+/// and the sentries of a validator in the sentry mode, warning in the log when a flag of `NodeHealth` changes.
+#[derive(Default)]
+pub struct SoftLimitMonitor {
+    inner: Mutex<Inner>,
+}
+
+impl SoftLimitMonitor {
+    pub fn health(&self) -> NodeHealth {
+        self.inner.lock().health
+    }
+
+    pub fn note_block_body(&self, body_size: usize, max_body_size: usize) {
+        let mut inner = self.inner.lock();
+        let near_limit = is_near_limit(body_size, max_body_size);
+        if near_limit {
+            inner.health.near_full_blocks += 1;
+        }
+        inner.recent_blocks.push_back(near_limit);
+        if inner.recent_blocks.len() > RECENT_BLOCKS {
+            inner.recent_blocks.pop_front();
+        }
+
+        let near_full_blocks = inner.recent_blocks.iter().filter(|near_limit| **near_limit).count();
+        let flag = near_full_blocks >= NEAR_FULL_BLOCKS_TO_WARN;
+        if flag != inner.health.blocks_near_max_body_size {
+            inner.health.blocks_near_max_body_size = flag;
+            if flag {
+                cwarn!(
+                    CLIENT,
+                    "{} of the latest {} blocks are larger than {}% of max_body_size({})",
+                    near_full_blocks,
+                    inner.recent_blocks.len(),
+                    SOFT_LIMIT_PERCENT,
+                    max_body_size
+                );
+            } else {
+                cinfo!(CLIENT, "The latest blocks are no longer near max_body_size({})", max_body_size);
+            }
+        }
+    }
+
+    pub fn note_mem_pool(&self, usage: MemPoolUsage) {
+        let mut inner = self.inner.lock();
+        let flag = is_near_limit(usage.count, usage.count_limit) || is_near_limit(usage.mem_usage, usage.memory_limit);
+        if flag {
+            inner.health.near_full_mem_pool_checks += 1;
+        }
+        if flag != inner.health.mem_pool_near_limit {
+            inner.health.mem_pool_near_limit = flag;
+            if flag {
+                cwarn!(
+                    MEM_POOL,
+                    "The mem pool is nearly full: {}/{} transactions, {}/{} bytes",
+                    usage.count,
+                    usage.count_limit,
+                    usage.mem_usage,
+                    usage.memory_limit
+                );
+            } else {
+                cinfo!(MEM_POOL, "The mem pool is no longer nearly full");
+            }
+        }
+    }
+
+    pub fn note_candidate_metadata(&self, largest_metadata_size: usize, max_candidate_metadata_size: usize) {
+        let mut inner = self.inner.lock();
+        let flag = is_near_limit(largest_metadata_size, max_candidate_metadata_size);
+        if flag {
+            inner.health.near_limit_candidate_metadata_checks += 1;
+        }
+        if flag != inner.health.candidate_metadata_near_limit {
+            inner.health.candidate_metadata_near_limit = flag;
+            if flag {
+                cwarn!(
+                    CLIENT,
+                    "A candidate metadata of {} bytes is larger than {}% of max_candidate_metadata_size({})",
+                    largest_metadata_size,
+                    SOFT_LIMIT_PERCENT,
+                    max_candidate_metadata_size
+                );
+            } else {
+                cinfo!(
+                    CLIENT,
+                    "The candidate metadata are no longer near max_candidate_metadata_size({})",
+                    max_candidate_metadata_size
+                );
+            }
+        }
+    }
+}
+
+    pub fn note_sentries(&self, connected: usize, sentries: usize) {
+        let mut inner = self.inner.lock();
+        if connected < sentries {
+            inner.sentry_missing_checks += 1;
+        } else {
+            inner.sentry_missing_checks = 0;
+        }
+        let flag = inner.sentry_missing_checks >= SENTRY_CHECKS_TO_WARN;
+        inner.health.all_sentries_lost = flag && connected == 0;
+        if flag != inner.health.sentry_lost {
+            inner.health.sentry_lost = flag;
+            if flag {
+                inner.health.sentry_losses += 1;
+                cwarn!(NETWORK, "Only {} of the {} sentries are connected", connected, sentries);
+            } else {
+                cinfo!(NETWORK, "All the {} sentries are connected again", sentries);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flag_blocks_only_when_many_recent_blocks_are_near_full() {
+        let monitor = SoftLimitMonitor::default();
+        for _ in 0..NEAR_FULL_BLOCKS_TO_WARN - 1 {
+            monitor.note_block_body(95, 100);
+        }
+        assert!(!monitor.health().blocks_near_max_body_size);
+
+        monitor.note_block_body(90, 100);
+        assert!(monitor.health().blocks_near_max_body_size);
+        assert_eq!(NEAR_FULL_BLOCKS_TO_WARN as u64, monitor.health().near_full_blocks);
+
+        for _ in 0..RECENT_BLOCKS {
+            monitor.note_block_body(10, 100);
+        }
+        assert!(!monitor.health().blocks_near_max_body_size);
+        assert_eq!(NEAR_FULL_BLOCKS_TO_WARN as u64, monitor.health().near_full_blocks);
+    }
+
+    #[test]
+    fn flag_mem_pool_near_either_limit() {
+        let monitor = SoftLimitMonitor::default();
+        let usage = MemPoolUsage {
+            count: 10,
+            count_limit: 100,
+            mem_usage: 100,
+            memory_limit: 1000,
+        };
+        monitor.note_mem_pool(usage);
+        assert!(!monitor.health().mem_pool_near_limit);
+
+        monitor.note_mem_pool(MemPoolUsage {
+            count: 95,
+            ..usage
+        });
+        assert!(monitor.health().mem_pool_near_limit);
+
+        monitor.note_mem_pool(MemPoolUsage {
+            mem_usage: 950,
+            ..usage
+        });
+        assert!(monitor.health().mem_pool_near_limit);
+
+        monitor.note_mem_pool(usage);
+        assert!(!monitor.health().mem_pool_near_limit);
+        assert_eq!(2, monitor.health().near_full_mem_pool_checks);
+    }
+
+    #[test]
+    fn flag_candidate_metadata_near_limit() {
+        let monitor = SoftLimitMonitor::default();
+        monitor.note_candidate_metadata(89, 100);
+        assert!(!monitor.health().candidate_metadata_near_limit);
+        monitor.note_candidate_metadata(90, 100);
+        assert!(monitor.health().candidate_metadata_near_limit);
+        monitor.note_candidate_metadata(0, 0);
+        assert!(!monitor.health().candidate_metadata_near_limit);
+    }
+
+    #[test]
+    fn flag_sentries_lost_only_after_consecutive_checks() {
+        let monitor = SoftLimitMonitor::default();
+        for _ in 0..SENTRY_CHECKS_TO_WARN - 1 {
+            monitor.note_sentries(1, 2);
+        }
+        monitor.note_sentries(2, 2);
+        monitor.note_sentries(1, 2);
+        assert!(!monitor.health().sentry_lost);
+
+        for _ in 0..SENTRY_CHECKS_TO_WARN - 1 {
+            monitor.note_sentries(0, 2);
+        }
+        assert!(monitor.health().sentry_lost);
+        assert!(monitor.health().all_sentries_lost);
+
+        monitor.note_sentries(1, 2);
+        assert!(monitor.health().sentry_lost);
+        assert!(!monitor.health().all_sentries_lost);
+
+        monitor.note_sentries(2, 2);
+        assert!(!monitor.health().sentry_lost);
+        assert_eq!(1, monitor.health().sentry_losses);
+    }
+}
@@ -32,30 +32,31 @@
 
 use crate::block::{Block, ClosedBlock, OpenBlock};
 use crate::blockchain_info::BlockChainInfo;
+use crate::client::timestamp_index::search_by_timestamp;
 use crate::client::{
-    BlockChainClient, BlockChainTrait, BlockProducer, BlockStatus, ConsensusClient, EngineInfo, ImportBlock,
-    ImportResult, MiningBlockChainClient, StateInfo, TermInfo,
+    ArchiveStats, BackfillStatus, BlockChainClient, BlockChainTrait, BlockProducer, BlockStatus, ConsensusClient,
+    EngineInfo, ImportBlock, ImportResult, MiningBlockChainClient, NodeHealth, StateInfo, TermInfo,
 };
-use crate::consensus::EngineError;
+use crate::consensus::{EngineError, HeightStats, TimeoutParams};
 use crate::db::{COL_STATE, NUM_COLUMNS};
 use crate::encoded;
 use crate::error::{BlockImportError, Error as GenericError};
-use crate::miner::{Miner, MinerService};
+use crate::miner::{IngressStats, Miner, MinerService, OrphanStats, PoolPage, PoolQuery};
 use crate::scheme::Scheme;
 use crate::types::{TransactionId, VerificationQueueInfo as QueueInfo};
 use crate::{LocalizedTransaction, PendingTransactions};
 use ccrypto::BLAKE_NULL_RLP;
 use ckey::{Ed25519Private as Private, Ed25519Public as Public, NetworkId, PlatformAddress};
 use coordinator::test_coordinator::TestCoordinator;
-use coordinator::types::Event;
-use coordinator::Transaction;
+use coordinator::types::{Event, InspectedPayload, TransactionPreview};
+use coordinator::{Transaction, TransactionWithMetadata, TxOrigin, TxSizes};
 use cstate::tests::helpers::empty_top_state_with_metadata;
 use cstate::{NextValidatorSet, StateDB, TopLevelState};
 use ctimer::{TimeoutHandler, TimerToken};
 use ctypes::Header;
 use ctypes::{
-    BlockHash, BlockId, BlockNumber, CommonParams, CompactValidatorEntry, CompactValidatorSet, ConsensusParams,
-    Header as BlockHeader, SyncHeader, TxHash,
+    BlockHash, BlockId, BlockNumber, Commit, CommonParams, CompactValidatorEntry, CompactValidatorSet, ConsensusParams,
+    Header as BlockHeader, ModuleDatumProof, NetworkIdentity, StorageId, SyncHeader, TxHash,
 };
 use kvdb::KeyValueDB;
 use merkle_trie::skewed_merkle_root;
@@ -397,6 +398,10 @@ impl ImportBlock for TestBlockChainClient {
         Ok(h)
     }
 
+    fn queue_block(&self, b: Bytes) -> Result<BlockHash, BlockImportError> {
+        self.import_block(b)
+    }
+
     fn import_header(&self, _header: SyncHeader) -> Result<BlockHash, BlockImportError> {
         unimplemented!()
     }
@@ -433,7 +438,16 @@ impl BlockChainClient for TestBlockChainClient {
     }
 
     fn queue_own_transaction(&self, transaction: Transaction) -> Result<(), GenericError> {
-        self.miner.import_own_transaction(self, transaction)?;
+        self.miner.import_own_transaction(self, transaction, TxOrigin::Local)?;
+        Ok(())
+    }
+
+    fn queue_scheduled_transaction(
+        &self,
+        transaction: Transaction,
+        activation_block: BlockNumber,
+    ) -> Result<(), GenericError> {
+        self.miner.import_scheduled_transaction(self, transaction, activation_block)?;
         Ok(())
     }
 
@@ -443,6 +457,14 @@ impl BlockChainClient for TestBlockChainClient {
         self.miner.import_external_transactions(self, transactions);
     }
 
+    fn preview_transaction(
+        &self,
+        transaction: &Transaction,
+        parent: BlockId,
+    ) -> Result<Option<TransactionPreview>, GenericError> {
+        self.miner.preview_transaction(self, parent, transaction)
+    }
+
     fn delete_all_pending_transactions(&self) {
         self.miner.delete_all_pending_transactions();
     }
@@ -455,10 +477,62 @@ impl BlockChainClient for TestBlockChainClient {
         self.miner.pending_transactions(size_limit as usize, range)
     }
 
+    fn propagatable_transaction_hashes(&self) -> Vec<TxHash> {
+        self.miner.propagatable_transaction_hashes()
+    }
+
+    fn prioritized_pending_transactions(&self, hashes: &[TxHash]) -> Vec<Transaction> {
+        self.miner.prioritized_transactions(self, hashes)
+    }
+
+    fn local_pending_transactions(&self) -> Vec<TransactionWithMetadata> {
+        self.miner.local_pending_transactions()
+    }
+
+    fn replacement_fee(&self, fee: u64) -> Option<u64> {
+        self.miner.replacement_fee(fee)
+    }
+
+    fn query_pending_transactions(&self, query: &PoolQuery) -> PoolPage {
+        self.miner.query_pending_transactions(query)
+    }
+
     fn count_pending_transactions(&self, range: Range<u64>) -> usize {
         self.miner.count_pending_transactions(range)
     }
 
+    fn ingress_stats(&self) -> Vec<IngressStats> {
+        self.miner.ingress_stats()
+    }
+
+    fn orphan_stats(&self) -> OrphanStats {
+        self.miner.orphan_stats()
+    }
+
+    fn transaction_sizes(&self, tx: &Transaction) -> TxSizes {
+        self.miner.transaction_sizes(tx)
+    }
+
+    fn inspect_transaction(&self, tx: &Transaction) -> Option<Result<InspectedPayload, String>> {
+        self.miner.inspect_transaction(tx)
+    }
+
+    fn scheduled_transactions(&self) -> Vec<(BlockNumber, Transaction)> {
+        self.miner.scheduled_transactions()
+    }
+
+    fn is_pending_transaction(&self, hash: &TxHash) -> bool {
+        self.miner.is_pending_transaction(hash)
+    }
+
+    fn dump_mem_pool(&self) -> Vec<TransactionWithMetadata> {
+        self.miner.dump_mem_pool()
+    }
+
+    fn load_mem_pool(&self, transactions: Vec<TransactionWithMetadata>) -> Vec<TxHash> {
+        self.miner.load_mem_pool(self, transactions)
+    }
+
     fn is_mem_pool_empty(&self) -> bool {
         self.miner.num_pending_transactions() == 0
     }
@@ -493,6 +567,13 @@ impl BlockChainClient for TestBlockChainClient {
         Self::block_hash(self, id)
     }
 
+    fn block_number_by_timestamp(&self, timestamp: u64) -> Option<BlockNumber> {
+        let best = self.chain_info().best_block_number;
+        search_by_timestamp(timestamp, best, |number| {
+            self.block_header(&BlockId::Number(number)).map(|header| header.timestamp())
+        })
+    }
+
     fn transaction(&self, _id: &TransactionId) -> Option<LocalizedTransaction> {
         unimplemented!();
     }
@@ -504,6 +585,26 @@ impl BlockChainClient for TestBlockChainClient {
     fn events_by_block_hash(&self, _hash: &BlockHash) -> Vec<Event> {
         unimplemented!()
     }
+
+    fn network_identities(&self, _id: &BlockId) -> Option<Vec<NetworkIdentity>> {
+        Some(Vec::new())
+    }
+
+    fn node_health(&self) -> NodeHealth {
+        Default::default()
+    }
+
+    fn event_backfill_status(&self) -> BackfillStatus {
+        Default::default()
+    }
+
+    fn archived_block(&self, _id: &BlockId) -> Option<encoded::Block> {
+        None
+    }
+
+    fn archive_stats(&self) -> Option<ArchiveStats> {
+        None
+    }
 }
 
 impl TimeoutHandler for TestBlockChainClient {
@@ -515,6 +616,10 @@ impl super::EngineClient for TestBlockChainClient {
         self.miner.update_sealing(self, parent_block, allow_empty_block)
     }
 
+    fn is_block_proposal_due(&self, parent_block: BlockId) -> bool {
+        self.block_header(&parent_block).map_or(true, |parent| self.miner.is_block_proposal_due(parent.timestamp()))
+    }
+
     fn update_best_as_committed(&self, _block_hash: BlockHash) {}
 
     fn get_kvdb(&self) -> Arc<dyn KeyValueDB> {
@@ -540,11 +645,35 @@ impl EngineInfo for TestBlockChainClient {
         unimplemented!()
     }
 
+    fn module_roots(&self, _block_id: BlockId) -> Option<Vec<H256>> {
+        unimplemented!()
+    }
+
+    fn module_datum_proof(&self, _block_id: BlockId, _storage_id: StorageId, _key: &[u8]) -> Option<ModuleDatumProof> {
+        unimplemented!()
+    }
+
+    fn commit(&self, _block_id: BlockId) -> Option<Commit> {
+        unimplemented!()
+    }
+
     fn possible_authors(&self, _block_number: Option<u64>) -> Result<Option<Vec<PlatformAddress>>, EngineError> {
         unimplemented!()
     }
 
-    fn validator_set(&self, _block_number: Option<u64>) -> Result<Option<ctypes::CompactValidatorSet>, EngineError> {
+    fn validator_set(&self, _block_id: BlockId) -> Result<Option<ctypes::CompactValidatorSet>, EngineError> {
+        unimplemented!()
+    }
+
+    fn recent_height_stats(&self) -> Vec<HeightStats> {
+        unimplemented!()
+    }
+
+    fn timeouts(&self) -> Option<TimeoutParams> {
+        unimplemented!()
+    }
+
+    fn update_timeouts(&self, _params: &cjson::scheme::TendermintParams) -> Option<TimeoutParams> {
         unimplemented!()
     }
 }
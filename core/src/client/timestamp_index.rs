@@ -0,0 +1,122 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::blockchain::{BlockChain, HeaderProvider};
+use ctypes::{BlockHash, BlockNumber};
+use lru_cache::LruCache;
+use parking_lot::Mutex;
+
+/// The number of block timestamps remembered between lookups.
+const TIMESTAMP_CACHE_SIZE: usize = 4096;
+
+/// Finds the last block, among the blocks from the genesis to `best`, whose timestamp is not after `timestamp`.
+/// The timestamps never decrease along the chain, so a binary search probes O(log n) headers.
+/// Returns `None` if `timestamp` precedes the genesis block or a probed header is missing.
+pub(super) fn search_by_timestamp(
+    timestamp: u64,
+    best: BlockNumber,
+    mut timestamp_at: impl FnMut(BlockNumber) -> Option<u64>,
+) -> Option<BlockNumber> {
+    if timestamp_at(best)? <= timestamp {
+        return Some(best)
+    }
+    if timestamp_at(0)? > timestamp {
+        return None
+    }
+    // The timestamp of `low` is not after `timestamp` and the timestamp of `high` is after it.
+    let (mut low, mut high) = (0, best);
+    while high - low > 1 {
+        let mid = low + (high - low) / 2;
+        if timestamp_at(mid)? <= timestamp {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    Some(low)
+}
+
+/// Resolves wall-clock times to canonical block numbers.
+/// The timestamps are cached by block hash, so a reorganization never serves a timestamp of a retracted block.
+pub struct TimestampIndex {
+    timestamps: Mutex<LruCache<BlockHash, u64>>,
+}
+
+impl Default for TimestampIndex {
+    fn default() -> Self {
+        Self {
+            timestamps: Mutex::new(LruCache::new(TIMESTAMP_CACHE_SIZE)),
+        }
+    }
+}
+
+impl TimestampIndex {
+    pub fn block_number(&self, chain: &BlockChain, timestamp: u64) -> Option<BlockNumber> {
+        let best = chain.best_block_detail().number;
+        search_by_timestamp(timestamp, best, |number| {
+            let hash = chain.block_hash(number)?;
+            if let Some(timestamp) = self.timestamps.lock().get_mut(&hash) {
+                return Some(*timestamp)
+            }
+            let timestamp = chain.block_header_data(&hash)?.timestamp();
+            self.timestamps.lock().insert(hash, timestamp);
+            Some(timestamp)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search_in(timestamps: &[u64], timestamp: u64) -> Option<BlockNumber> {
+        search_by_timestamp(timestamp, timestamps.len() as BlockNumber - 1, |number| {
+            timestamps.get(number as usize).cloned()
+        })
+    }
+
+    #[test]
+    fn find_the_last_block_not_after_the_timestamp() {
+        let timestamps = [0, 10, 20, 20, 30, 45];
+        assert_eq!(Some(0), search_in(&timestamps, 0));
+        assert_eq!(Some(0), search_in(&timestamps, 9));
+        assert_eq!(Some(1), search_in(&timestamps, 10));
+        assert_eq!(Some(3), search_in(&timestamps, 20));
+        assert_eq!(Some(3), search_in(&timestamps, 29));
+        assert_eq!(Some(4), search_in(&timestamps, 44));
+        assert_eq!(Some(5), search_in(&timestamps, 45));
+        assert_eq!(Some(5), search_in(&timestamps, 1000));
+    }
+
+    #[test]
+    fn no_block_before_the_genesis() {
+        assert_eq!(None, search_in(&[100, 110], 99));
+        assert_eq!(None, search_in(&[100], 99));
+        assert_eq!(Some(0), search_in(&[100], 100));
+    }
+
+    #[test]
+    fn missing_header_fails_the_search() {
+        let result = search_by_timestamp(15, 10, |number| {
+            if number == 5 {
+                None
+            } else {
+                Some(number * 3)
+            }
+        });
+        assert_eq!(None, result);
+    }
+}
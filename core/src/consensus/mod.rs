@@ -16,16 +16,22 @@
 
 mod bit_set;
 mod null_engine;
+mod registry;
+mod remote_signer;
+mod round_robin;
 pub(crate) mod signer;
 mod solo;
 pub(crate) mod tendermint;
 mod validator_set;
 
 pub use self::null_engine::NullEngine;
+pub use self::registry::{create_engine, engine_factory, EngineFactory, ENGINES};
+pub use self::remote_signer::RemoteSigner;
+pub use self::round_robin::RoundRobin;
 pub use self::solo::Solo;
 pub use self::tendermint::{
-    types::TendermintSealView, ConsensusMessage, Height, Step, Tendermint, TendermintParams, TimeGapParams, View,
-    VoteOn, VoteStep,
+    types::TendermintSealView, ConsensusMessage, Height, HeightStats, Step, Tendermint, TendermintParams,
+    TimeGapParams, TimeoutParams, View, VoteOn, VoteStep,
 };
 pub use self::validator_set::{DynamicValidator, ValidatorSet};
 
@@ -41,10 +47,11 @@ use crate::Client;
 use ckey::{Ed25519Public as Public, Signature};
 use cnetwork::NetworkService;
 use ctypes::util::unexpected::{Mismatch, OutOfBounds};
-use ctypes::{BlockHash, CompactValidatorSet, Header, SyncHeader};
+use ctypes::{BlockHash, Commit, CompactValidatorSet, Header, SyncHeader};
 use primitives::Bytes;
 use std::fmt;
 use std::sync::{Arc, Weak};
+use std::time::Duration;
 
 pub enum Seal {
     Solo,
@@ -53,6 +60,11 @@ pub enum Seal {
         cur_view: View,
         precommits: Vec<Signature>,
         precommit_bitset: BitSet,
+        /// `None` before the votes sign their timestamps, when the seal lacks the field.
+        precommit_timestamps: Option<Vec<u64>>,
+    },
+    RoundRobin {
+        signature: Signature,
     },
     None,
 }
@@ -67,12 +79,22 @@ impl Seal {
                 cur_view,
                 precommits,
                 precommit_bitset,
-            } => Some(vec![
-                ::rlp::encode(prev_view),
-                ::rlp::encode(cur_view),
-                ::rlp::encode_list(precommits),
-                ::rlp::encode(precommit_bitset),
-            ]),
+                precommit_timestamps,
+            } => {
+                let mut fields = vec![
+                    ::rlp::encode(prev_view),
+                    ::rlp::encode(cur_view),
+                    ::rlp::encode_list(precommits),
+                    ::rlp::encode(precommit_bitset),
+                ];
+                if let Some(precommit_timestamps) = precommit_timestamps {
+                    fields.push(::rlp::encode_list(precommit_timestamps));
+                }
+                Some(fields)
+            }
+            Seal::RoundRobin {
+                signature,
+            } => Some(vec![::rlp::encode(signature)]),
         }
     }
 }
@@ -82,36 +104,53 @@ impl Seal {
 pub enum EngineType {
     PBFT,
     Solo,
+    InstantSeal,
+    RoundRobin,
 }
 
 impl EngineType {
     pub fn need_signer_key(&self) -> bool {
         match self {
-            EngineType::PBFT => true,
-            EngineType::Solo => false,
+            EngineType::PBFT | EngineType::RoundRobin => true,
+            EngineType::Solo | EngineType::InstantSeal => false,
         }
     }
 
     pub fn ignore_reseal_min_period(&self) -> bool {
         match self {
             EngineType::PBFT => true,
-            EngineType::Solo => false,
+            EngineType::Solo | EngineType::InstantSeal | EngineType::RoundRobin => false,
         }
     }
 
     pub fn ignore_reseal_on_transaction(&self) -> bool {
         match self {
             EngineType::PBFT => true,
-            EngineType::Solo => false,
+            EngineType::Solo | EngineType::InstantSeal | EngineType::RoundRobin => false,
+        }
+    }
+
+    /// Whether a block is sealed as soon as a transaction arrives, regardless of the reseal min period.
+    pub fn seals_instantly(&self) -> bool {
+        match self {
+            EngineType::InstantSeal => true,
+            EngineType::PBFT | EngineType::Solo | EngineType::RoundRobin => false,
         }
     }
 }
 
 /// A consensus mechanism for the chain.
 pub trait ConsensusEngine: Sync + Send {
+    /// The names of the additional header fields required for this engine, in order.
+    /// Headers are verified to have exactly these fields.
+    /// The first of them must not be an RLP list, or it would be read as the header extension.
+    fn seal_schema(&self) -> &'static [&'static str] {
+        &[]
+    }
+
     /// The number of additional header fields required for this engine.
     fn seal_fields(&self, _header: &Header) -> usize {
-        0
+        self.seal_schema().len()
     }
 
     /// true means the engine is currently prime for seal generation (i.e. node is the current validator).
@@ -134,8 +173,19 @@ pub trait ConsensusEngine: Sync + Send {
         Seal::None
     }
 
+    /// Seals again a block whose contents are final, for the engines whose seal signs the block itself.
+    /// `Some(Seal::None)` discards the block, and `None` keeps the seal given by `generate_seal`.
+    fn seal_closed_block(&self, _block: &ExecutedBlock) -> Option<Seal> {
+        None
+    }
+
     fn proposal_generated(&self, _block: &ClosedBlock) {}
 
+    /// The interval at which the node seals a block even if there are no transactions to include.
+    fn seal_interval(&self) -> Option<Duration> {
+        None
+    }
+
     /// Phase 1 quick block verification. Only does checks that are cheap. Returns either a null `Ok` or a general error detailing the problem with import.
     fn verify_header_basic(&self, _header: &Header) -> Result<(), Error> {
         Ok(())
@@ -146,6 +196,12 @@ pub trait ConsensusEngine: Sync + Send {
         Ok(())
     }
 
+    /// The precommits on the parent of the header carried in its seal, for the light clients of this chain.
+    /// `None` if the engine doesn't finalize blocks by votes.
+    fn parent_commit(&self, _header: &Header) -> Option<Commit> {
+        None
+    }
+
     /// Phase 3 verification. Check block information against parent. Returns either a null `Ok` or a general error detailing the problem with import.
     /// The verification must be conducted only with the two headers' information because it does not guarantee whether the two corresponding bodies have been imported.
     fn verify_block_family(&self, _header: &Header, _parent: &Header) -> Result<(), Error> {
@@ -195,6 +251,10 @@ pub trait ConsensusEngine: Sync + Send {
     /// Register an account which signs consensus messages.
     fn set_signer(&self, _ap: Arc<AccountProvider>, _pubkey: Public) {}
 
+    /// Register a remote signer which signs consensus messages.
+    /// The account of the same key in the `fallback` signs them while the remote signer fails.
+    fn set_remote_signer(&self, _signer: RemoteSigner, _fallback: Option<Arc<AccountProvider>>) {}
+
     fn register_network_extension_to_service(&self, _: &NetworkService) {}
 
     fn register_time_gap_config_to_worker(&self, _time_gap_params: TimeGapParams) {}
@@ -222,9 +282,31 @@ pub trait ConsensusEngine: Sync + Send {
         true
     }
 
+    /// Whether a block of the same number and view as the best block replaces it.
+    /// An engine without finality breaks the tie the same way on every node, so that the nodes converge on one chain.
+    fn replaces_best_on_tie(&self, _new_hash: BlockHash, _best_hash: BlockHash) -> bool {
+        false
+    }
+
     fn possible_authors(&self, block_number: Option<u64>) -> Result<Option<Vec<Public>>, EngineError>;
 
     fn current_validator_set(&self, block_number: Option<u64>) -> Result<Option<CompactValidatorSet>, EngineError>;
+
+    /// Returns the round statistics of the recent heights, the oldest first.
+    fn recent_height_stats(&self) -> Vec<HeightStats> {
+        Vec::new()
+    }
+
+    /// Returns the step timeouts if the engine uses them.
+    fn timeouts(&self) -> Option<TimeoutParams> {
+        None
+    }
+
+    /// Overrides the step timeouts given in `params` and returns the updated ones.
+    /// Returns `None` if the engine doesn't use timeouts.
+    fn update_timeouts(&self, _params: &cjson::scheme::TendermintParams) -> Option<TimeoutParams> {
+        None
+    }
 }
 
 /// Voting errors.
@@ -256,7 +338,15 @@ pub enum EngineError {
     BadSealFieldSize(OutOfBounds<usize>),
     /// Malformed consensus message.
     MalformedMessage(String),
+    /// The vote signed a time it couldn't be made at.
+    InvalidVoteTimestamp {
+        height: u64,
+        signer_index: usize,
+        timestamp: u64,
+    },
     CannotOpenBlock,
+    /// The remote signer couldn't sign a message.
+    RemoteSignerFailed(String),
 }
 
 impl fmt::Display for EngineError {
@@ -281,7 +371,15 @@ impl fmt::Display for EngineError {
             NotProposer(mis) => format!("Author is not a current proposer: {:?}", mis),
             BadSealFieldSize(oob) => format!("Seal field has an unexpected length: {}", oob),
             MalformedMessage(msg) => format!("Received malformed consensus message: {}", msg),
+            InvalidVoteTimestamp {
+                height,
+                signer_index,
+                timestamp,
+            } => {
+                format!("The {}th validator on height {} voted at an invalid time {}", signer_index, height, timestamp)
+            }
             CannotOpenBlock => "Cannot open a block".to_string(),
+            RemoteSignerFailed(msg) => format!("The remote signer failed: {}", msg),
         };
 
         f.write_fmt(format_args!("Engine error ({})", msg))
@@ -14,14 +14,21 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::ConsensusEngine;
+use super::{ConsensusEngine, EngineFactory, ENGINES};
 use crate::consensus::{EngineError, EngineType};
 use ckey::Ed25519Public as Public;
+use linkme::distributed_slice;
+use std::sync::Arc;
 
 /// An engine which does not provide any consensus mechanism and does not seal blocks.
 #[derive(Default)]
 pub struct NullEngine;
 
+#[distributed_slice(ENGINES)]
+fn register() -> (&'static str, EngineFactory) {
+    ("null", |_params| Ok(Arc::new(NullEngine::default())))
+}
+
 impl ConsensusEngine for NullEngine {
     fn seals_internally(&self) -> bool {
         true
@@ -0,0 +1,103 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::ConsensusEngine;
+use crate::error::SchemeError;
+use linkme::distributed_slice;
+use once_cell::sync;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Creates an engine from the params written in the scheme.
+pub type EngineFactory = fn(Value) -> Result<Arc<dyn ConsensusEngine>, String>;
+
+/// The engines a scheme can select, keyed by the names used in the scheme's `engine` field.
+///
+/// An engine registers itself with a function returning its name and `EngineFactory`:
+/// ```ignore
+/// #[distributed_slice(ENGINES)]
+/// fn register() -> (&'static str, EngineFactory) {
+///     ("solo", |_params| Ok(Arc::new(Solo::new())))
+/// }
+/// ```
+#[distributed_slice]
+pub static ENGINES: [fn() -> (&'static str, EngineFactory)] = [..];
+
+/// Returns the factory of the engine registered with the given `name`.
+pub fn engine_factory(name: &str) -> Option<EngineFactory> {
+    static MAP: sync::Lazy<HashMap<&'static str, EngineFactory>> =
+        sync::Lazy::new(|| ENGINES.iter().map(|register| register()).collect());
+    MAP.get(name).copied()
+}
+
+pub fn create_engine(engine: cjson::scheme::Engine) -> Result<Arc<dyn ConsensusEngine>, SchemeError> {
+    let factory = engine_factory(&engine.name).ok_or_else(|| SchemeError::UnknownEngine(engine.name.clone()))?;
+    factory(engine.params).map_err(|reason| SchemeError::InvalidEngineParams {
+        engine: engine.name,
+        reason,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::EngineType;
+
+    fn engine(name: &str, params: Value) -> cjson::scheme::Engine {
+        cjson::scheme::Engine {
+            name: name.to_string(),
+            params,
+        }
+    }
+
+    #[test]
+    fn bundled_engines_are_registered() {
+        for name in &["null", "solo", "instantSeal", "roundRobin", "tendermint"] {
+            assert!(engine_factory(name).is_some(), "{} is not registered", name);
+        }
+    }
+
+    #[test]
+    fn unknown_engine_is_rejected() {
+        match create_engine(engine("proofOfWork", Value::Null)) {
+            Err(SchemeError::UnknownEngine(name)) => assert_eq!(name, "proofOfWork"),
+            _ => panic!("An unknown engine must be rejected"),
+        }
+    }
+
+    #[test]
+    fn engine_is_created_by_name() {
+        let instant_seal = create_engine(engine("instantSeal", Value::Null)).unwrap();
+        assert_eq!(instant_seal.engine_type(), EngineType::InstantSeal);
+        assert_eq!(instant_seal.seal_interval(), None);
+
+        let interval = serde_json::json!({ "params": { "sealInterval": 500 } });
+        let instant_seal = create_engine(engine("instantSeal", interval)).unwrap();
+        assert_eq!(instant_seal.seal_interval(), Some(std::time::Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn invalid_params_are_rejected() {
+        match create_engine(engine("roundRobin", serde_json::json!({ "params": { "validators": [] } }))) {
+            Err(SchemeError::InvalidEngineParams {
+                engine,
+                ..
+            }) => assert_eq!(engine, "roundRobin"),
+            _ => panic!("A round-robin engine without validators must be rejected"),
+        }
+    }
+}
@@ -0,0 +1,151 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::EngineError;
+use crate::error::Error;
+use ckey::{verify, Ed25519Public as Public, Signature, SIGNATURE_LENGTH};
+use parking_lot::Mutex;
+use primitives::H256;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::time::Duration;
+
+/// Signs consensus messages with a key kept out of the node process, e.g. in an HSM.
+///
+/// For each message, the node sends the 32 bytes public key of the signer followed by the 32 bytes
+/// hash to sign, and the signer answers with the 64 bytes Ed25519 signature.
+/// The connection is kept while it works. It is opened again when a request fails.
+pub struct RemoteSigner {
+    address: SocketAddr,
+    public: Public,
+    /// Bounds each of connecting, sending a request and receiving its answer.
+    timeout: Duration,
+    connection: Mutex<Option<TcpStream>>,
+}
+
+impl RemoteSigner {
+    pub fn new(address: SocketAddr, public: Public, timeout: Duration) -> Self {
+        Self {
+            address,
+            public,
+            timeout,
+            connection: Mutex::new(None),
+        }
+    }
+
+    pub fn public(&self) -> &Public {
+        &self.public
+    }
+
+    /// Asks the remote signer to sign the hash.
+    /// The signer may have closed the kept connection, so the request is sent once more on a new
+    /// connection when it fails on the kept one.
+    pub fn sign(&self, hash: &H256) -> Result<Signature, Error> {
+        let mut connection = self.connection.lock();
+        let answered_on_kept_connection = connection
+            .take()
+            .and_then(|mut stream| self.request(&mut stream, hash).ok().map(|signature| (stream, signature)));
+        let (stream, signature) = match answered_on_kept_connection {
+            Some(answered) => answered,
+            None => self.request_on_new_connection(hash).map_err(|err| {
+                EngineError::RemoteSignerFailed(format!("Cannot get a signature from {}: {}", self.address, err))
+            })?,
+        };
+        if !verify(&signature, hash.as_ref(), &self.public) {
+            return Err(EngineError::RemoteSignerFailed(format!("{} answered an invalid signature", self.address)).into())
+        }
+        *connection = Some(stream);
+        Ok(signature)
+    }
+
+    fn request_on_new_connection(&self, hash: &H256) -> io::Result<(TcpStream, Signature)> {
+        let mut stream = self.connect()?;
+        let signature = self.request(&mut stream, hash)?;
+        Ok((stream, signature))
+    }
+
+    fn connect(&self) -> io::Result<TcpStream> {
+        let stream = TcpStream::connect_timeout(&self.address, self.timeout)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+        stream.set_nodelay(true)?;
+        Ok(stream)
+    }
+
+    fn request(&self, stream: &mut TcpStream, hash: &H256) -> io::Result<Signature> {
+        let mut request = Vec::with_capacity(64);
+        request.extend_from_slice(self.public.as_ref());
+        request.extend_from_slice(hash.as_ref());
+        stream.write_all(&request)?;
+
+        let mut answer = [0u8; SIGNATURE_LENGTH];
+        stream.read_exact(&mut answer)?;
+        Ok(Signature::from_slice(&answer).expect("The length of the answer is the length of a signature"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckey::{sign, Ed25519KeyPair as KeyPair, Generator, KeyPairTrait, Random};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Answers `requests` requests on the first connection, signing with `key_pair`.
+    fn serve(key_pair: KeyPair, requests: usize) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            for _ in 0..requests {
+                let mut request = [0u8; 64];
+                stream.read_exact(&mut request).unwrap();
+                let signature = sign(&request[32..], key_pair.private());
+                stream.write_all(signature.as_ref()).unwrap();
+            }
+        });
+        address
+    }
+
+    #[test]
+    fn sign_remotely() {
+        let key_pair: KeyPair = Random.generate().unwrap();
+        let public = *key_pair.public();
+        let signer = RemoteSigner::new(serve(key_pair, 2), public, Duration::from_secs(1));
+
+        for _ in 0..2 {
+            let hash = H256::random();
+            let signature = signer.sign(&hash).unwrap();
+            assert!(verify(&signature, hash.as_ref(), &public));
+        }
+    }
+
+    #[test]
+    fn reject_signature_of_another_key() {
+        let key_pair: KeyPair = Random.generate().unwrap();
+        let another: KeyPair = Random.generate().unwrap();
+        let signer = RemoteSigner::new(serve(key_pair, 1), *another.public(), Duration::from_secs(1));
+        assert!(signer.sign(&H256::random()).is_err());
+    }
+
+    #[test]
+    fn time_out_when_signer_does_not_answer() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let key_pair: KeyPair = Random.generate().unwrap();
+        let signer = RemoteSigner::new(listener.local_addr().unwrap(), *key_pair.public(), Duration::from_millis(100));
+        assert!(signer.sign(&H256::random()).is_err());
+    }
+}
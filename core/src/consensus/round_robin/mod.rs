@@ -0,0 +1,283 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::signer::EngineSigner;
+use super::{ConsensusEngine, EngineError, EngineFactory, EngineType, RemoteSigner, Seal, ENGINES};
+use crate::account_provider::AccountProvider;
+use crate::block::ExecutedBlock;
+use crate::client::snapshot_notify::NotifySender;
+use crate::error::{BlockError, Error};
+use ckey::{verify, Ed25519Public as Public, Signature};
+use ctypes::util::unexpected::{Mismatch, OutOfBounds};
+use ctypes::{BlockHash, CompactValidatorEntry, CompactValidatorSet, Header};
+use linkme::distributed_slice;
+use parking_lot::RwLock;
+use rlp::Rlp;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SEAL_SCHEMA: &[&str] = &["signature"];
+/// Unit: second
+const DEFAULT_BLOCK_PERIOD: u64 = 1;
+
+/// A proof-of-authority engine in which a fixed list of validators take turns authoring blocks.
+///
+/// Time is divided into slots of `block_period` seconds, and the slot of a block's timestamp decides its author.
+/// A validator that misses its slot is skipped, so the chain goes on while some validators are down.
+/// The author signs the block after its contents are final.
+/// A signed block is not final: a block signed by one validator can't tell which of two forks the others follow.
+/// So the longest chain wins, and the lowest hash wins between the forks of the same length.
+/// That way every node picks the same chain, and the forks made during a partition merge once it heals.
+pub struct RoundRobin {
+    validators: Vec<Public>,
+    block_period: u64,
+    signer: RwLock<EngineSigner>,
+    snapshot_notify_sender: RwLock<Option<NotifySender>>,
+}
+
+#[distributed_slice(ENGINES)]
+fn register() -> (&'static str, EngineFactory) {
+    ("roundRobin", create)
+}
+
+fn create(params: Value) -> Result<Arc<dyn ConsensusEngine>, String> {
+    let cjson::scheme::RoundRobin {
+        params,
+    } = serde_json::from_value(params).map_err(|err| err.to_string())?;
+    let block_period = params.block_period.map_or(DEFAULT_BLOCK_PERIOD, Into::into);
+    Ok(Arc::new(RoundRobin::new(params.validators, block_period)?))
+}
+
+impl RoundRobin {
+    pub fn new(validators: Vec<Public>, block_period: u64) -> Result<Self, String> {
+        if validators.is_empty() {
+            return Err("At least one validator is required".to_string())
+        }
+        if block_period == 0 {
+            return Err("The block period must be positive".to_string())
+        }
+        Ok(Self {
+            validators,
+            block_period,
+            signer: Default::default(),
+            snapshot_notify_sender: Default::default(),
+        })
+    }
+
+    fn slot(&self, timestamp: u64) -> u64 {
+        timestamp / self.block_period
+    }
+
+    fn author_of_slot(&self, slot: u64) -> &Public {
+        &self.validators[(slot % self.validators.len() as u64) as usize]
+    }
+
+    fn is_our_slot(&self, slot: u64) -> bool {
+        self.signer.read().is_signer(self.author_of_slot(slot))
+    }
+}
+
+impl ConsensusEngine for RoundRobin {
+    fn seal_schema(&self) -> &'static [&'static str] {
+        SEAL_SCHEMA
+    }
+
+    fn seals_internally(&self) -> bool {
+        self.signer.read().public().is_some()
+    }
+
+    fn engine_type(&self) -> EngineType {
+        EngineType::RoundRobin
+    }
+
+    /// Gives a placeholder seal if the current slot is ours. The block is signed in `seal_closed_block`.
+    fn generate_seal(&self, _block: Option<&ExecutedBlock>, parent: &Header) -> Seal {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("There is no time machine.").as_secs();
+        let slot = self.slot(now);
+        if slot <= self.slot(parent.timestamp()) || !self.is_our_slot(slot) {
+            return Seal::None
+        }
+        Seal::RoundRobin {
+            signature: Signature::default(),
+        }
+    }
+
+    fn seal_closed_block(&self, block: &ExecutedBlock) -> Option<Seal> {
+        let header = block.header();
+        if !self.is_our_slot(self.slot(header.timestamp())) {
+            // The slot has passed while the block was being built.
+            return Some(Seal::None)
+        }
+        match self.signer.read().sign(header.bare_hash()) {
+            Ok(signature) => Some(Seal::RoundRobin {
+                signature,
+            }),
+            Err(err) => {
+                cwarn!(ENGINE, "Cannot sign the block #{}: {}", header.number(), err);
+                Some(Seal::None)
+            }
+        }
+    }
+
+    fn verify_header_basic(&self, header: &Header) -> Result<(), Error> {
+        let expected = *self.author_of_slot(self.slot(header.timestamp()));
+        if header.author() != &expected {
+            return Err(EngineError::NotProposer(Mismatch {
+                expected,
+                found: *header.author(),
+            })
+            .into())
+        }
+        Ok(())
+    }
+
+    fn verify_header_seal(&self, header: &Header, _validator_set: &CompactValidatorSet) -> Result<(), Error> {
+        let signature: Signature =
+            Rlp::new(&header.seal()[0]).as_val().map_err(|err| EngineError::MalformedMessage(err.to_string()))?;
+        if !verify(&signature, header.bare_hash().as_ref(), header.author()) {
+            return Err(EngineError::BlockNotAuthorized(*header.author()).into())
+        }
+        Ok(())
+    }
+
+    fn verify_block_family(&self, header: &Header, parent: &Header) -> Result<(), Error> {
+        if self.slot(header.timestamp()) <= self.slot(parent.timestamp()) {
+            let next_slot_starts_at = (self.slot(parent.timestamp()) + 1) * self.block_period;
+            return Err(BlockError::InvalidTimestamp(OutOfBounds {
+                min: Some(next_slot_starts_at),
+                max: None,
+                found: header.timestamp(),
+            })
+            .into())
+        }
+        Ok(())
+    }
+
+    fn replaces_best_on_tie(&self, new_hash: BlockHash, best_hash: BlockHash) -> bool {
+        *new_hash < *best_hash
+    }
+
+    fn set_signer(&self, ap: Arc<AccountProvider>, pubkey: Public) {
+        self.signer.write().set_to_keep_decrypted_account(ap, pubkey);
+    }
+
+    fn set_remote_signer(&self, signer: RemoteSigner, fallback: Option<Arc<AccountProvider>>) {
+        self.signer.write().set_remote_signer(signer, fallback);
+    }
+
+    fn register_snapshot_notify_sender(&self, sender: NotifySender) {
+        let mut guard = self.snapshot_notify_sender.write();
+        assert!(guard.is_none(), "snapshot_notify_sender is registered twice");
+        *guard = Some(sender);
+    }
+
+    fn send_snapshot_notify(&self, block_hash: BlockHash) {
+        if let Some(sender) = self.snapshot_notify_sender.read().as_ref() {
+            sender.notify(block_hash)
+        }
+    }
+
+    fn possible_authors(&self, _block_number: Option<u64>) -> Result<Option<Vec<Public>>, EngineError> {
+        Ok(Some(self.validators.clone()))
+    }
+
+    fn current_validator_set(&self, _block_number: Option<u64>) -> Result<Option<CompactValidatorSet>, EngineError> {
+        Ok(Some(CompactValidatorSet::new(
+            self.validators
+                .iter()
+                .map(|public_key| CompactValidatorEntry {
+                    public_key: *public_key,
+                    delegation: 1,
+                })
+                .collect(),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckey::{Ed25519KeyPair as KeyPair, Generator, KeyPairTrait, Random};
+    use primitives::H256;
+
+    fn header(author: Public, timestamp: u64) -> Header {
+        let mut header = Header::default();
+        header.set_author(author);
+        header.set_timestamp(timestamp);
+        header
+    }
+
+    #[test]
+    fn validators_author_blocks_in_turn() {
+        let validators = vec![Public::from(1), Public::from(2), Public::from(3)];
+        let engine = RoundRobin::new(validators.clone(), 5).unwrap();
+
+        assert!(engine.verify_header_basic(&header(validators[0], 0)).is_ok());
+        assert!(engine.verify_header_basic(&header(validators[1], 5)).is_ok());
+        assert!(engine.verify_header_basic(&header(validators[2], 14)).is_ok());
+        assert!(engine.verify_header_basic(&header(validators[0], 15)).is_ok());
+        assert!(engine.verify_header_basic(&header(validators[1], 15)).is_err());
+    }
+
+    #[test]
+    fn block_in_the_slot_of_its_parent_is_rejected() {
+        let validators = vec![Public::from(1), Public::from(2)];
+        let engine = RoundRobin::new(validators.clone(), 5).unwrap();
+        let parent = header(validators[0], 10);
+
+        assert!(engine.verify_block_family(&header(validators[0], 14), &parent).is_err());
+        assert!(engine.verify_block_family(&header(validators[0], 20), &parent).is_ok());
+    }
+
+    #[test]
+    fn seal_is_signed_by_the_author() {
+        let key_pair: KeyPair = Random.generate().unwrap();
+        let engine = RoundRobin::new(vec![*key_pair.public()], 1).unwrap();
+        let mut header = header(*key_pair.public(), 1);
+
+        let signature = ckey::sign(header.bare_hash().as_ref(), key_pair.private());
+        header.set_seal(
+            Seal::RoundRobin {
+                signature,
+            }
+            .seal_fields()
+            .unwrap(),
+        );
+        assert!(engine.verify_header_seal(&header, &CompactValidatorSet::new(Vec::new())).is_ok());
+
+        header.set_timestamp(2);
+        assert!(engine.verify_header_seal(&header, &CompactValidatorSet::new(Vec::new())).is_err());
+    }
+
+    #[test]
+    fn forks_of_the_same_length_are_chosen_by_the_lowest_hash() {
+        let engine = RoundRobin::new(vec![Public::from(1)], 1).unwrap();
+        let low = BlockHash::from(H256::from_slice(&[1; 32]));
+        let high = BlockHash::from(H256::from_slice(&[2; 32]));
+
+        assert!(engine.replaces_best_on_tie(low, high));
+        assert!(!engine.replaces_best_on_tie(high, low));
+        assert!(!engine.replaces_best_on_tie(low, low));
+        // Any fork may become canonical, so that the forks of a partition merge.
+        assert!(engine.can_change_canon_chain(high, BlockHash::default(), low));
+    }
+
+    #[test]
+    fn no_validators_are_rejected() {
+        assert!(RoundRobin::new(Vec::new(), 1).is_err());
+    }
+}
@@ -14,7 +14,9 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::account_provider::{AccountProvider, Error as AccountProviderError};
+use super::RemoteSigner;
+use crate::account_provider::AccountProvider;
+use crate::error::Error;
 use ckey::{Ed25519Public as Public, Signature};
 use ckeystore::DecryptedAccount;
 use primitives::H256;
@@ -25,6 +27,7 @@ pub struct EngineSigner {
     account_provider: Arc<AccountProvider>,
     signer: Option<Public>,
     decrypted_account: Option<DecryptedAccount>,
+    remote_signer: Option<RemoteSigner>,
 }
 
 impl Default for EngineSigner {
@@ -33,6 +36,7 @@ impl Default for EngineSigner {
             account_provider: AccountProvider::transient_provider(),
             signer: Default::default(),
             decrypted_account: Default::default(),
+            remote_signer: None,
         }
     }
 }
@@ -49,8 +53,33 @@ impl EngineSigner {
         cinfo!(ENGINE, "Setting Engine signer to {:?} (retaining)", pubkey);
     }
 
+    /// Signs with the remote signer, falling back on the account of the same key in `fallback`
+    /// while the remote signer fails.
+    pub fn set_remote_signer(&mut self, remote_signer: RemoteSigner, fallback: Option<Arc<AccountProvider>>) {
+        let pubkey = *remote_signer.public();
+        match fallback {
+            Some(ap) => self.set_to_keep_decrypted_account(ap, pubkey),
+            None => {
+                self.account_provider = AccountProvider::transient_provider();
+                self.signer = Some(pubkey);
+                self.decrypted_account = None;
+            }
+        }
+        self.remote_signer = Some(remote_signer);
+        cinfo!(ENGINE, "Setting Engine signer to {:?} (remote)", pubkey);
+    }
+
     /// Sign a message hash with Ed25519.
-    pub fn sign(&self, hash: H256) -> Result<Signature, AccountProviderError> {
+    pub fn sign(&self, hash: H256) -> Result<Signature, Error> {
+        if let Some(remote_signer) = &self.remote_signer {
+            match remote_signer.sign(&hash) {
+                Ok(signature) => return Ok(signature),
+                Err(err) if self.decrypted_account.is_some() => {
+                    cwarn!(ENGINE, "Signing with the local key: {}", err);
+                }
+                Err(err) => return Err(err),
+            }
+        }
         let pubkey = self.signer.unwrap_or_else(Default::default);
         let result = match &self.decrypted_account {
             Some(account) => account.sign(&hash)?,
@@ -14,20 +14,51 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::{ConsensusEngine, Seal};
+use super::{ConsensusEngine, EngineFactory, Seal, ENGINES};
 use crate::block::ExecutedBlock;
 use crate::client::snapshot_notify::NotifySender;
 use crate::client::ConsensusClient;
 use crate::consensus::{EngineError, EngineType};
 use ckey::Ed25519Public as Public;
 use ctypes::{BlockHash, Header};
+use linkme::distributed_slice;
 use parking_lot::RwLock;
+use serde_json::Value;
 use std::sync::{Arc, Weak};
+use std::time::Duration;
 
 /// A consensus engine which does not provide any consensus mechanism.
 pub struct Solo {
     client: RwLock<Option<Weak<dyn ConsensusClient>>>,
     snapshot_notify_sender: Arc<RwLock<Option<NotifySender>>>,
+    instant: bool,
+    seal_interval: Option<Duration>,
+}
+
+#[distributed_slice(ENGINES)]
+fn register_solo() -> (&'static str, EngineFactory) {
+    ("solo", |_params| Ok(Arc::new(Solo::new())))
+}
+
+#[distributed_slice(ENGINES)]
+fn register_instant_seal() -> (&'static str, EngineFactory) {
+    ("instantSeal", create_instant_seal)
+}
+
+fn create_instant_seal(params: Value) -> Result<Arc<dyn ConsensusEngine>, String> {
+    let cjson::scheme::InstantSeal {
+        params,
+    } = if params.is_null() {
+        Default::default()
+    } else {
+        serde_json::from_value(params).map_err(|err| err.to_string())?
+    };
+    let engine = match params.seal_interval.map(u64::from) {
+        Some(0) => return Err("The seal interval must be positive".to_string()),
+        Some(interval) => Solo::instant_seal().with_seal_interval(Duration::from_millis(interval)),
+        None => Solo::instant_seal(),
+    };
+    Ok(Arc::new(engine))
 }
 
 impl Solo {
@@ -36,6 +67,25 @@ impl Solo {
         Solo {
             client: Default::default(),
             snapshot_notify_sender: Arc::new(RwLock::new(None)),
+            instant: false,
+            seal_interval: None,
+        }
+    }
+
+    /// Returns a Solo for development, which seals a block as soon as a transaction arrives
+    /// instead of waiting for the reseal min period.
+    pub fn instant_seal() -> Self {
+        Solo {
+            instant: true,
+            ..Self::new()
+        }
+    }
+
+    /// Makes the engine also seal a block at every `interval`, so that the chain grows while idle.
+    pub fn with_seal_interval(self, interval: Duration) -> Self {
+        Solo {
+            seal_interval: Some(interval),
+            ..self
         }
     }
 }
@@ -46,13 +96,21 @@ impl ConsensusEngine for Solo {
     }
 
     fn engine_type(&self) -> EngineType {
-        EngineType::Solo
+        if self.instant {
+            EngineType::InstantSeal
+        } else {
+            EngineType::Solo
+        }
     }
 
     fn generate_seal(&self, _block: Option<&ExecutedBlock>, _parent: &Header) -> Seal {
         Seal::Solo
     }
 
+    fn seal_interval(&self) -> Option<Duration> {
+        self.seal_interval
+    }
+
     fn register_client(&self, client: Weak<dyn ConsensusClient>) {
         *self.client.write() = Some(Weak::clone(&client));
     }
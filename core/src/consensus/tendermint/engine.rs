@@ -18,28 +18,43 @@ use super::super::{ConsensusEngine, EngineError, Seal};
 use super::network::TendermintExtension;
 pub use super::params::{TendermintParams, TimeoutParams};
 use super::{worker, Evidence};
-use super::{ChainNotify, Step, Tendermint, VoteOn, VoteStep, SEAL_FIELDS};
+use super::{
+    ChainNotify, HeightStats, Step, Tendermint, VoteOn, VoteStep, SEAL_FIELDS, SEAL_SCHEMA, UNTIMESTAMPED_SEAL_FIELDS,
+    VOTE_TIMESTAMP_DRIFT_SECS,
+};
 use crate::account_provider::AccountProvider;
 use crate::block::*;
 use crate::client::snapshot_notify::NotifySender as SnapshotNotifySender;
 use crate::client::{Client, ConsensusClient};
-use crate::consensus::tendermint::params::TimeGapParams;
-use crate::consensus::{EngineType, TendermintSealView};
+use crate::consensus::tendermint::params::{TimeGapParams, TimeoutParams};
+use crate::consensus::{EngineType, RemoteSigner, TendermintSealView};
 use crate::error::{BlockError, Error};
 use crate::views::HeaderView;
-use ckey::{verify, Ed25519Public as Public};
+use ckey::{verify_all, Ed25519Public as Public};
 use cnetwork::NetworkService;
 use crossbeam_channel as crossbeam;
 use cstate::CurrentValidators;
-use ctypes::{util::unexpected::OutOfBounds, BlockHash, BlockId, CompactValidatorSet, Header, SyncHeader};
+use ctypes::{
+    util::unexpected::OutOfBounds, BlockHash, BlockId, Commit, CompactValidatorSet, Header, Precommit, SyncHeader,
+};
 use std::iter::Iterator;
 use std::sync::atomic::Ordering as AtomicOrdering;
 use std::sync::{Arc, Weak};
 
 impl ConsensusEngine for Tendermint {
     /// (consensus view, proposal signature, authority signatures)
-    fn seal_fields(&self, _header: &Header) -> usize {
-        SEAL_FIELDS
+    fn seal_schema(&self) -> &'static [&'static str] {
+        SEAL_SCHEMA
+    }
+
+    /// The seals made before the timestamped votes are activated lack the precommit timestamps.
+    /// Whether a seal must carry them is checked once its parent is known.
+    fn seal_fields(&self, header: &Header) -> usize {
+        if header.seal().len() == UNTIMESTAMPED_SEAL_FIELDS {
+            UNTIMESTAMPED_SEAL_FIELDS
+        } else {
+            SEAL_FIELDS
+        }
     }
 
     /// Should this node participate.
@@ -112,6 +127,19 @@ impl ConsensusEngine for Tendermint {
             return Err(BlockError::InvalidSeal.into())
         }
 
+        // Without the parent only the latest time the precommits could be signed at is known.
+        if let Some(timestamps) = seal_view.precommit_timestamps()? {
+            let max = header.timestamp().saturating_add(VOTE_TIMESTAMP_DRIFT_SECS);
+            if let Some(found) = timestamps.into_iter().find(|timestamp| *timestamp == 0 || *timestamp > max) {
+                return Err(BlockError::InvalidTimestamp(OutOfBounds {
+                    min: Some(1),
+                    max: Some(max),
+                    found,
+                })
+                .into())
+            }
+        }
+
         let parent_block_finalized_view = seal_view.parent_block_finalized_view()?;
         let precommit_vote_on = VoteOn {
             step: VoteStep::new(header.number() - 1, parent_block_finalized_view, Step::Precommit),
@@ -119,7 +147,8 @@ impl ConsensusEngine for Tendermint {
         };
 
         let mut signed_delegation: u64 = 0;
-        for (bitset_index, signature) in seal_view.signatures()? {
+        let mut signed = Vec::new();
+        for (bitset_index, signature, timestamp) in seal_view.signatures()? {
             if validator_set.len() <= bitset_index {
                 cwarn!(
                     ENGINE,
@@ -130,12 +159,12 @@ impl ConsensusEngine for Tendermint {
                 );
                 return Err(BlockError::InvalidSeal.into())
             }
-            let public = validator_set[bitset_index].public_key;
-            let delegation = validator_set[bitset_index].delegation;
-            if !verify(&signature, precommit_vote_on.hash().as_ref(), &public) {
-                return Err(EngineError::BlockNotAuthorized(public).into())
-            }
-            signed_delegation += delegation;
+            signed.push((signature, precommit_vote_on.hash(timestamp), validator_set[bitset_index].public_key));
+            signed_delegation += validator_set[bitset_index].delegation;
+        }
+        if let Some(index) = verify_all(&signed).iter().position(|is_valid| !is_valid) {
+            let (_, _, public) = signed[index];
+            return Err(EngineError::BlockNotAuthorized(public).into())
         }
 
         let total_delegation: u64 = validator_set.iter().map(|entry| entry.delegation).sum();
@@ -152,6 +181,28 @@ impl ConsensusEngine for Tendermint {
         }
     }
 
+    fn parent_commit(&self, header: &Header) -> Option<Commit> {
+        if header.number() <= 1 {
+            return None
+        }
+        let seal_view = TendermintSealView::new(header.seal());
+        let view = seal_view.parent_block_finalized_view().ok()?;
+        let precommits = seal_view
+            .signatures()
+            .ok()?
+            .into_iter()
+            .map(|(signer_index, signature, timestamp)| Precommit {
+                signer_index,
+                signature,
+                timestamp,
+            })
+            .collect();
+        Some(Commit {
+            view,
+            precommits,
+        })
+    }
+
     fn verify_block_external(&self, header: &Header) -> Result<(), Error> {
         let (result, receiver) = crossbeam::bounded(1);
         self.inner
@@ -212,8 +263,18 @@ impl ConsensusEngine for Tendermint {
             .unwrap();
     }
 
+    fn set_remote_signer(&self, signer: RemoteSigner, fallback: Option<Arc<AccountProvider>>) {
+        self.has_signer.store(true, AtomicOrdering::SeqCst);
+        self.inner
+            .send(worker::Event::SetRemoteSigner {
+                signer,
+                fallback,
+            })
+            .unwrap();
+    }
+
     fn register_network_extension_to_service(&self, service: &NetworkService) {
-        let timeouts = self.timeouts;
+        let timeouts = *self.timeouts.read();
 
         let inner = self.inner.clone();
         let extension = service.register_extension(move |api| TendermintExtension::new(inner, timeouts, api));
@@ -287,6 +348,27 @@ impl ConsensusEngine for Tendermint {
         }
     }
 
+    fn recent_height_stats(&self) -> Vec<HeightStats> {
+        let (result, receiver) = crossbeam::bounded(1);
+        self.inner
+            .send(worker::Event::GetHeightStats {
+                result,
+            })
+            .unwrap();
+        receiver.recv().unwrap()
+    }
+
+    fn timeouts(&self) -> Option<TimeoutParams> {
+        Some(*self.timeouts.read())
+    }
+
+    fn update_timeouts(&self, params: &cjson::scheme::TendermintParams) -> Option<TimeoutParams> {
+        let mut timeouts = self.timeouts.write();
+        *timeouts = timeouts.updated(params);
+        self.inner.send(worker::Event::SetTimeouts(*timeouts)).unwrap();
+        Some(*timeouts)
+    }
+
     /// grand_parent === none only when parent is genesis
     fn verify_header_family(
         &self,
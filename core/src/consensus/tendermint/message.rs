@@ -16,9 +16,8 @@
 
 use super::super::BitSet;
 use super::{Height, Step, View};
-use ccrypto::blake256;
 use ckey::{verify, Ed25519Public as Public, Signature};
-use ctypes::BlockHash;
+use ctypes::{vote_hash, BlockHash};
 use primitives::{Bytes, H256};
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 use std::cmp;
@@ -338,17 +337,61 @@ pub struct VoteOn {
 }
 
 impl VoteOn {
-    pub fn hash(&self) -> H256 {
-        blake256(&self.rlp_bytes())
+    /// The hash a voter signs, which covers the time it voted at as well. See `ctypes::vote_hash`.
+    pub fn hash(&self, timestamp: u64) -> H256 {
+        vote_hash(&self.rlp_bytes(), timestamp)
     }
 }
 
 /// Message transmitted between consensus participants.
-#[derive(Debug, PartialEq, Eq, Clone, Hash, Default, RlpDecodable, RlpEncodable)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Default)]
 pub struct ConsensusMessage {
     pub on: VoteOn,
     pub signature: Signature,
     pub signer_index: usize,
+    /// The time in seconds the signer voted at, by its own clock, or 0 before the timestamped votes are activated.
+    /// The median of the precommit timestamps of a block is a time no single validator decides.
+    pub timestamp: u64,
+}
+
+/// A message without a timestamp is encoded in the three fields it had before the timestamped votes,
+/// so that the validators not knowing the timestamps can exchange the votes until the activation.
+impl Encodable for ConsensusMessage {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        if self.timestamp == 0 {
+            s.begin_list(3);
+        } else {
+            s.begin_list(4);
+        }
+        s.append(&self.on);
+        s.append(&self.signature);
+        s.append(&self.signer_index);
+        if self.timestamp != 0 {
+            s.append(&self.timestamp);
+        }
+    }
+}
+
+impl Decodable for ConsensusMessage {
+    fn decode(rlp: &Rlp<'_>) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        let timestamp = match item_count {
+            3 => 0,
+            4 => rlp.val_at(3)?,
+            _ => {
+                return Err(DecoderError::RlpIncorrectListLen {
+                    got: item_count,
+                    expected: 4,
+                })
+            }
+        };
+        Ok(ConsensusMessage {
+            on: rlp.val_at(0)?,
+            signature: rlp.val_at(1)?,
+            signer_index: rlp.val_at(2)?,
+            timestamp,
+        })
+    }
 }
 
 impl ConsensusMessage {
@@ -372,8 +415,12 @@ impl ConsensusMessage {
         self.on.step.height
     }
 
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
     pub fn verify(&self, signer_public: &Public) -> bool {
-        verify(&self.signature, self.on.hash().as_ref(), signer_public)
+        verify(&self.signature, self.on.hash(self.timestamp).as_ref(), signer_public)
     }
 }
 
@@ -381,6 +428,7 @@ impl ConsensusMessage {
 mod tests {
     use super::super::Step;
     use super::*;
+    use ccrypto::blake256;
     use rlp::rlp_encode_and_decode_test;
     use std::str::FromStr;
 
@@ -455,6 +503,7 @@ mod tests {
                 ConsensusMessage {
                     signature: Signature::random(),
                     signer_index: 0x1234,
+                    timestamp: 1_600_000_000,
                     on: VoteOn {
                         step: VoteStep::new(2, 3, Step::Commit),
                         block_hash: Some(
@@ -467,6 +516,7 @@ mod tests {
                 ConsensusMessage {
                     signature: Signature::random(),
                     signer_index: 0x1235,
+                    timestamp: 1_600_000_001,
                     on: VoteOn {
                         step: VoteStep::new(2, 3, Step::Commit),
                         block_hash: Some(
@@ -480,6 +530,17 @@ mod tests {
         });
     }
 
+    #[test]
+    fn vote_hash_covers_timestamp() {
+        let on = VoteOn {
+            step: VoteStep::new(2, 3, Step::Precommit),
+            block_hash: Some(H256::from_slice(&[1; 32]).into()),
+        };
+        assert_eq!(on.hash(1_600_000_000), on.hash(1_600_000_000));
+        assert_ne!(on.hash(1_600_000_000), on.hash(1_600_000_001));
+        assert_eq!(blake256(&on.rlp_bytes()), on.hash(0));
+    }
+
     #[test]
     fn encode_and_decode_consensus_message_1() {
         let message = ConsensusMessage::default();
@@ -491,6 +552,7 @@ mod tests {
         let message = ConsensusMessage {
             signature: Signature::random(),
             signer_index: 0x1234,
+            timestamp: 1_600_000_000,
             on: VoteOn {
                 step: VoteStep::new(2, 3, Step::Commit),
                 block_hash: Some(
@@ -513,6 +575,7 @@ mod tests {
         let consensus_message = ConsensusMessage {
             signature,
             signer_index,
+            timestamp: 1_600_000_000,
             on: VoteOn {
                 step: VoteStep::new(height, view, step),
                 block_hash,
@@ -522,4 +585,38 @@ mod tests {
         let decoded = rlp::decode::<ConsensusMessage>(&encoded).unwrap();
         assert_eq!(consensus_message, decoded);
     }
+
+    #[test]
+    fn consensus_message_without_timestamp_keeps_three_fields() {
+        let message = ConsensusMessage {
+            signature: Signature::random(),
+            signer_index: 0x1234,
+            timestamp: 0,
+            on: VoteOn {
+                step: VoteStep::new(2, 3, Step::Prevote),
+                block_hash: None,
+            },
+        };
+        let encoded = message.rlp_bytes();
+        assert_eq!(Rlp::new(&encoded).item_count().unwrap(), 3);
+        assert_eq!(message, rlp::decode::<ConsensusMessage>(&encoded).unwrap());
+
+        let timestamped = ConsensusMessage {
+            timestamp: 1_600_000_000,
+            ..message
+        };
+        assert_eq!(Rlp::new(&timestamped.rlp_bytes()).item_count().unwrap(), 4);
+    }
+
+    #[test]
+    fn precommit_hash_for_light_clients_is_the_signed_hash() {
+        let block_hash: BlockHash =
+            H256::from_str("07feab4c39250abf60b77d7589a5b61fdf409bd837e936376381d19db1e1f050").unwrap().into();
+        let on = VoteOn {
+            step: VoteStep::new(2, 3, Step::Precommit),
+            block_hash: Some(block_hash),
+        };
+        assert_eq!(on.hash(1_600_000_000), ctypes::precommit_hash(2, 3, &block_hash, 1_600_000_000));
+        assert_eq!(on.hash(0), ctypes::precommit_hash(2, 3, &block_hash, 0));
+    }
 }
@@ -24,21 +24,24 @@ mod params;
 pub mod types;
 pub mod vote_collector;
 mod vote_regression_checker;
+mod vote_verifier;
 mod worker;
 
 use self::chain_notify::TendermintChainNotify;
 pub use self::evidence_collector::Evidence;
 pub use self::message::{ConsensusMessage, VoteOn, VoteStep};
 pub use self::params::{TendermintParams, TimeGapParams, TimeoutParams};
-pub use self::types::{Height, Step, View};
+pub use self::types::{Height, HeightStats, Step, View};
 pub use super::ValidatorSet;
 use crate::client::ConsensusClient;
-use crate::consensus::DynamicValidator;
+use crate::consensus::{ConsensusEngine, DynamicValidator, EngineFactory, ENGINES};
 use crate::snapshot_notify::NotifySender as SnapshotNotifySender;
 use crate::ChainNotify;
 use crossbeam_channel as crossbeam;
 use ctimer::TimerToken;
+use linkme::distributed_slice;
 use parking_lot::RwLock;
+use serde_json::Value;
 use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Weak};
 use std::thread::JoinHandle;
@@ -59,7 +62,7 @@ pub struct Tendermint {
     external_params_initializer: crossbeam::Sender<TimeGapParams>,
     extension_initializer: crossbeam::Sender<(crossbeam::Sender<network::Event>, Weak<dyn ConsensusClient>)>,
     snapshot_notify_sender_initializer: crossbeam::Sender<SnapshotNotifySender>,
-    timeouts: TimeoutParams,
+    timeouts: RwLock<TimeoutParams>,
     join: Option<JoinHandle<()>>,
     quit_tendermint: crossbeam::Sender<()>,
     inner: crossbeam::Sender<worker::Event>,
@@ -99,7 +102,7 @@ impl Tendermint {
             external_params_initializer,
             extension_initializer,
             snapshot_notify_sender_initializer,
-            timeouts,
+            timeouts: RwLock::new(timeouts),
             join: Some(join),
             quit_tendermint,
             inner,
@@ -114,7 +117,24 @@ impl Tendermint {
     }
 }
 
-const SEAL_FIELDS: usize = 4;
+#[distributed_slice(ENGINES)]
+fn register() -> (&'static str, EngineFactory) {
+    ("tendermint", create)
+}
+
+fn create(params: Value) -> Result<Arc<dyn ConsensusEngine>, String> {
+    let tendermint: cjson::scheme::Tendermint = serde_json::from_value(params).map_err(|err| err.to_string())?;
+    Ok(Tendermint::new(tendermint.params.into()))
+}
+
+const SEAL_SCHEMA: &[&str] = &["prev_view", "cur_view", "precommits", "precommit_bitset", "precommit_timestamps"];
+const SEAL_FIELDS: usize = SEAL_SCHEMA.len();
+/// The seals made before the timestamped votes are activated lack the precommit timestamps.
+const UNTIMESTAMPED_SEAL_FIELDS: usize = SEAL_FIELDS - 1;
+
+/// How many seconds the time a vote signs may be off the time of the block below it,
+/// the time of the block carrying it and the local clock.
+const VOTE_TIMESTAMP_DRIFT_SECS: u64 = 15;
 
 #[cfg(test)]
 mod tests {
@@ -185,13 +205,14 @@ mod tests {
             step: VoteStep::new(3, 0, Step::Precommit),
             block_hash: Some(*header.parent_hash()),
         };
-        let signature2 = tap.get_account(&proposer, None).unwrap().sign(&vote_on.hash()).unwrap();
+        let signature2 = tap.get_account(&proposer, None).unwrap().sign(&vote_on.hash(0)).unwrap();
 
         let seal = Seal::Tendermint {
             prev_view: 0,
             cur_view: 0,
             precommits: vec![signature2],
             precommit_bitset: BitSet::new_with_indices(&[2]),
+            precommit_timestamps: None,
         }
         .seal_fields()
         .unwrap();
@@ -230,6 +230,10 @@ impl NetworkExtension<Event> for TendermintExtension {
         &VERSIONS
     }
 
+    fn protocol_of(_message: &[u8]) -> &'static str {
+        "consensus"
+    }
+
     fn on_node_added(&mut self, token: &NodeId, _version: u64) {
         self.peers.insert(*token, PeerState::new());
     }
@@ -452,6 +456,10 @@ impl NetworkExtension<Event> for TendermintExtension {
             } => {
                 self.broadcast_proposal_block(signature, view, message);
             }
+            Event::SetTimeouts(timeouts) => {
+                cinfo!(ENGINE, "Timeouts are updated to {:?}", timeouts);
+                self.timeouts = timeouts;
+            }
         }
     }
 }
@@ -487,4 +495,6 @@ pub enum Event {
         view: View,
         message: Bytes,
     },
+    /// Replaces the timeouts used from the next timer.
+    SetTimeouts(TimeoutParams),
 }
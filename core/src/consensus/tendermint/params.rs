@@ -26,17 +26,8 @@ pub struct TendermintParams {
 
 impl From<cjson::scheme::TendermintParams> for TendermintParams {
     fn from(p: cjson::scheme::TendermintParams) -> Self {
-        let dt = TimeoutParams::default();
         TendermintParams {
-            timeouts: TimeoutParams {
-                propose: p.timeout_propose.map_or(dt.propose, to_duration),
-                propose_delta: p.timeout_propose_delta.map_or(dt.propose_delta, to_duration),
-                prevote: p.timeout_prevote.map_or(dt.prevote, to_duration),
-                prevote_delta: p.timeout_prevote_delta.map_or(dt.prevote_delta, to_duration),
-                precommit: p.timeout_precommit.map_or(dt.precommit, to_duration),
-                precommit_delta: p.timeout_precommit_delta.map_or(dt.precommit_delta, to_duration),
-                commit: p.timeout_commit.map_or(dt.commit, to_duration),
-            },
+            timeouts: TimeoutParams::default().updated(&p),
         }
     }
 }
@@ -78,6 +69,20 @@ impl Default for TimeoutParams {
 }
 
 impl TimeoutParams {
+    /// Returns the timeouts overridden by the ones given in `p`.
+    /// The timeouts missing in `p` are kept.
+    pub fn updated(&self, p: &cjson::scheme::TendermintParams) -> Self {
+        TimeoutParams {
+            propose: p.timeout_propose.map_or(self.propose, to_duration),
+            propose_delta: p.timeout_propose_delta.map_or(self.propose_delta, to_duration),
+            prevote: p.timeout_prevote.map_or(self.prevote, to_duration),
+            prevote_delta: p.timeout_prevote_delta.map_or(self.prevote_delta, to_duration),
+            precommit: p.timeout_precommit.map_or(self.precommit, to_duration),
+            precommit_delta: p.timeout_precommit_delta.map_or(self.precommit_delta, to_duration),
+            commit: p.timeout_commit.map_or(self.commit, to_duration),
+        }
+    }
+
     pub fn initial(&self) -> Duration {
         self.propose
     }
@@ -98,3 +103,31 @@ impl TimeoutParams {
         base + delta * view as u32
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn updated_keeps_missing_timeouts() {
+        let timeouts = TimeoutParams::default();
+        let params = cjson::scheme::TendermintParams {
+            timeout_propose: Some(3000.into()),
+            timeout_propose_delta: None,
+            timeout_prevote: None,
+            timeout_prevote_delta: None,
+            timeout_precommit: None,
+            timeout_precommit_delta: Some(100.into()),
+            timeout_commit: None,
+            allowed_past_timegap: None,
+            allowed_future_timegap: None,
+        };
+
+        let updated = timeouts.updated(&params);
+        assert_eq!(Duration::from_millis(3000), updated.propose);
+        assert_eq!(Duration::from_millis(100), updated.precommit_delta);
+        assert_eq!(timeouts.propose_delta, updated.propose_delta);
+        assert_eq!(timeouts.prevote, updated.prevote);
+        assert_eq!(timeouts.commit, updated.commit);
+    }
+}
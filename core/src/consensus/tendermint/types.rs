@@ -207,6 +207,32 @@ impl PeerState {
     }
 }
 
+/// Round statistics of a height, collected to diagnose slow finality.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HeightStats {
+    pub height: Height,
+    /// The view in which the block was finalized.
+    /// It is unknown when the height was skipped by block sync.
+    pub finalized_view: Option<View>,
+    pub propose_timeouts: u64,
+    pub prevote_timeouts: u64,
+    pub precommit_timeouts: u64,
+}
+
+impl HeightStats {
+    pub fn new(height: Height) -> Self {
+        HeightStats {
+            height,
+            ..Default::default()
+        }
+    }
+
+    /// The number of views that were needed to finalize the block.
+    pub fn views(&self) -> Option<u64> {
+        self.finalized_view.map(|view| view + 1)
+    }
+}
+
 pub struct TendermintSealView<'a> {
     seal: &'a [Bytes],
 }
@@ -246,19 +272,71 @@ impl<'a> TendermintSealView<'a> {
         )
     }
 
-    pub fn signatures(&self) -> Result<Vec<(usize, Signature)>, DecoderError> {
+    /// The times the precommits were signed at, in the order of `precommits`.
+    /// It's `None` for the seals made before the timestamped votes were activated, which lack the field.
+    pub fn precommit_timestamps(&self) -> Result<Option<Vec<u64>>, DecoderError> {
+        match self.seal.get(4) {
+            Some(timestamps_rlp) => Rlp::new(timestamps_rlp.as_slice()).as_list().map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// The signer index, the signature and the timestamp of each precommit.
+    /// The timestamps are 0 if the seal doesn't carry them.
+    pub fn signatures(&self) -> Result<Vec<(usize, Signature, u64)>, DecoderError> {
         let precommits = self.precommits();
         let bitset = self.bitset()?;
-        debug_assert_eq!(bitset.count(), precommits.item_count()?);
+        let precommits_count = precommits.item_count()?;
+        let timestamps = self.precommit_timestamps()?.unwrap_or_else(|| vec![0; precommits_count]);
+        debug_assert_eq!(bitset.count(), precommits_count);
+        if timestamps.len() != precommits_count {
+            return Err(DecoderError::RlpIncorrectListLen {
+                expected: precommits_count,
+                got: timestamps.len(),
+            })
+        }
 
         let bitset_iter = bitset.true_index_iter();
 
         let signatures = precommits.iter().map(|rlp| rlp.as_val::<Signature>());
         bitset_iter
             .zip(signatures)
-            .map(|(index, signature)| signature.map(|signature| (index, signature)))
+            .zip(timestamps)
+            .map(|((index, signature), timestamp)| signature.map(|signature| (index, signature, timestamp)))
             .collect::<Result<_, _>>()
     }
+
+    /// The median of the precommit timestamps weighted by the delegations of the signers,
+    /// where `delegations` are indexed as the bitset.
+    ///
+    /// The proposer chooses which precommits go into the seal, but they must carry more than 2/3 of the delegation.
+    /// So more than 1/3 of the delegation signed at or before the median and more than 1/3 at or after it,
+    /// and while the faulty validators hold less than 1/3 of the delegation,
+    /// the median lies between the times some correct validators signed.
+    /// It's `None` if the seal has no precommits, as the seal of the first block, or doesn't carry their timestamps.
+    pub fn median_timestamp(&self, delegations: &[u64]) -> Result<Option<u64>, DecoderError> {
+        let timestamps = match self.precommit_timestamps()? {
+            Some(timestamps) => timestamps,
+            None => return Ok(None),
+        };
+        let mut weighted: Vec<(u64, u64)> = self
+            .bitset()?
+            .true_index_iter()
+            .zip(timestamps)
+            .map(|(index, timestamp)| (timestamp, delegations.get(index).copied().unwrap_or_default()))
+            .collect();
+        weighted.sort_unstable();
+
+        let total: u64 = weighted.iter().map(|(_, delegation)| delegation).sum();
+        let mut accumulated = 0;
+        for (timestamp, delegation) in weighted {
+            accumulated += delegation;
+            if accumulated * 2 >= total {
+                return Ok(Some(timestamp))
+            }
+        }
+        Ok(None)
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -329,3 +407,39 @@ impl Proposal {
         matches!(self, Proposal::None)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::Seal;
+
+    fn seal(indices: &[usize], precommit_timestamps: Option<Vec<u64>>) -> Vec<Bytes> {
+        Seal::Tendermint {
+            prev_view: 0,
+            cur_view: 0,
+            precommits: vec![Signature::default(); indices.len()],
+            precommit_bitset: BitSet::new_with_indices(indices),
+            precommit_timestamps,
+        }
+        .seal_fields()
+        .unwrap()
+    }
+
+    #[test]
+    fn median_timestamp_is_weighted_by_delegation() {
+        let seal = seal(&[0, 2, 3], Some(vec![300, 100, 200]));
+        let view = TendermintSealView::new(&seal);
+        assert_eq!(Some(200), view.median_timestamp(&[1, 0, 1, 1]).unwrap());
+        assert_eq!(Some(300), view.median_timestamp(&[5, 0, 1, 1]).unwrap());
+        assert_eq!(Some(100), view.median_timestamp(&[1, 0, 5, 1]).unwrap());
+    }
+
+    #[test]
+    fn untimestamped_seal_has_no_median_timestamp() {
+        let seal = seal(&[0, 1], None);
+        let view = TendermintSealView::new(&seal);
+        assert_eq!(None, view.median_timestamp(&[1, 1]).unwrap());
+        let timestamps: Vec<u64> = view.signatures().unwrap().into_iter().map(|(_, _, timestamp)| timestamp).collect();
+        assert_eq!(vec![0, 0], timestamps);
+    }
+}
@@ -76,6 +76,10 @@ impl StepCollector {
         if self.messages.contains(&message) {
             return Ok(false)
         }
+        // The same vote signed at another time is not a double vote, but the first one is kept.
+        if self.voted.get(&message.signer_index()).map_or(false, |previous| previous.on == message.on) {
+            return Ok(false)
+        }
         self.messages.push(message.clone());
         if let Some(previous) = self.voted.insert(message.signer_index(), message.clone()) {
             // Bad validator sent a different message.
@@ -159,21 +163,24 @@ impl VoteCollector {
         self.votes = new_collector;
     }
 
-    /// Collects the signatures and the indices for the given round and hash.
-    /// Returning indices is in ascending order, and signature and indices are matched with another.
-    pub fn round_signatures_and_indices(
+    /// Collects the signatures, the timestamps and the indices for the given round and hash.
+    /// Returning indices is in ascending order, and the signatures and the timestamps are matched with them.
+    pub fn round_signatures_with_timestamps(
         &self,
         round: &VoteStep,
         block_hash: &BlockHash,
-    ) -> (Vec<Signature>, Vec<usize>) {
-        self.votes
-            .get(round)
-            .and_then(|c| c.block_votes.get(&Some(*block_hash)))
-            .map(|votes| {
-                let (indices, sigs) = votes.iter().unzip();
-                (sigs, indices)
-            })
-            .unwrap_or_default()
+    ) -> (Vec<Signature>, Vec<u64>, Vec<usize>) {
+        let collector = match self.votes.get(round) {
+            Some(collector) => collector,
+            None => return Default::default(),
+        };
+        let votes = match collector.block_votes.get(&Some(*block_hash)) {
+            Some(votes) => votes,
+            None => return Default::default(),
+        };
+        let timestamps = votes.keys().map(|index| collector.voted[index].timestamp()).collect();
+        let (indices, sigs) = votes.iter().unzip();
+        (sigs, timestamps, indices)
     }
 
     /// Returns the first signature and the index of its signer for a given round and hash if exists.
@@ -0,0 +1,153 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::{ConsensusMessage, Height};
+use ckey::{verify, verify_all, Ed25519Public as Public};
+use std::collections::{BTreeMap, HashSet};
+
+/// Checks the signatures of the consensus messages and remembers the valid ones.
+///
+/// A vote reaches a validator many times: in the messages of each peer, in a commit message and again after it was
+/// put off as a future message. Only the first one is verified, and the votes that come together are verified over
+/// a few threads, so that a large validator set doesn't make the worker check them one by one.
+#[derive(Default)]
+pub struct VoteVerifier {
+    /// The verified messages with their signers, by height.
+    verified: BTreeMap<Height, HashSet<(Public, ConsensusMessage)>>,
+}
+
+impl VoteVerifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the message is signed by the signer. A message verified before is not verified again.
+    pub fn verify(&mut self, message: &ConsensusMessage, signer: &Public) -> bool {
+        if self.is_verified(message, signer) {
+            return true
+        }
+        if !verify(&message.signature, message.on.hash(message.timestamp).as_ref(), signer) {
+            return false
+        }
+        self.verified.entry(message.height()).or_default().insert((*signer, message.clone()));
+        true
+    }
+
+    /// Whether each message is signed by its signer.
+    /// The messages not verified before are verified in parallel, and a message given twice is verified once.
+    pub fn verify_all(&mut self, messages: &[(&ConsensusMessage, Public)]) -> Vec<bool> {
+        let mut unknown: Vec<(&ConsensusMessage, Public)> = Vec::new();
+        let mut seen = HashSet::new();
+        for (message, signer) in messages {
+            if !self.is_verified(message, signer) && seen.insert((*message, *signer)) {
+                unknown.push((*message, *signer));
+            }
+        }
+
+        let signed: Vec<_> = unknown
+            .iter()
+            .map(|(message, signer)| (message.signature, message.on.hash(message.timestamp), *signer))
+            .collect();
+        // Every signature is checked on its own, so a vote is accepted the same way on every node.
+        for ((message, signer), is_valid) in unknown.iter().zip(verify_all(&signed)) {
+            if is_valid {
+                self.verified.entry(message.height()).or_default().insert((*signer, (*message).clone()));
+            }
+        }
+        messages.iter().map(|(message, signer)| self.is_verified(message, signer)).collect()
+    }
+
+    /// Forgets the messages below the height.
+    pub fn throw_out_old(&mut self, height: Height) {
+        self.verified = self.verified.split_off(&height);
+    }
+
+    fn is_verified(&self, message: &ConsensusMessage, signer: &Public) -> bool {
+        // A message doesn't own anything on the heap, so the key is cheap to make.
+        self.verified.get(&message.height()).map_or(false, |verified| verified.contains(&(*signer, message.clone())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Step, VoteOn, VoteStep};
+    use super::*;
+    use ckey::{sign, Ed25519KeyPair as KeyPair, Generator, KeyPairTrait, Random, Signature};
+    use ctypes::BlockHash;
+    use primitives::H256;
+
+    fn signed_vote(height: Height, signer_index: usize) -> (ConsensusMessage, Public) {
+        let key_pair: KeyPair = Random.generate().unwrap();
+        let on = VoteOn {
+            step: VoteStep::new(height, 0, Step::Precommit),
+            block_hash: Some(BlockHash::from(H256::random())),
+        };
+        let timestamp = 1;
+        let signature = sign(on.hash(timestamp).as_ref(), key_pair.private());
+        let message = ConsensusMessage {
+            on,
+            signature,
+            signer_index,
+            timestamp,
+        };
+        (message, *key_pair.public())
+    }
+
+    #[test]
+    fn verify_signed_votes() {
+        let mut verifier = VoteVerifier::new();
+        let votes: Vec<_> = (0..100).map(|index| signed_vote(1, index)).collect();
+        let messages: Vec<_> = votes.iter().map(|(message, signer)| (message, *signer)).collect();
+        assert_eq!(vec![true; 100], verifier.verify_all(&messages));
+    }
+
+    #[test]
+    fn reject_votes_of_other_signers() {
+        let mut verifier = VoteVerifier::new();
+        let mut votes: Vec<_> = (0..40).map(|index| signed_vote(1, index)).collect();
+        votes[3].1 = votes[4].1;
+        votes[25].0.signature = Signature::random();
+        let messages: Vec<_> = votes.iter().map(|(message, signer)| (message, *signer)).collect();
+
+        let mut expected = vec![true; 40];
+        expected[3] = false;
+        expected[25] = false;
+        assert_eq!(expected, verifier.verify_all(&messages));
+        assert!(!verifier.verify(&votes[3].0, &votes[3].1));
+    }
+
+    #[test]
+    fn verify_a_vote_given_twice() {
+        let mut verifier = VoteVerifier::new();
+        let (message, signer) = signed_vote(1, 0);
+        assert_eq!(vec![true, true], verifier.verify_all(&[(&message, signer), (&message, signer)]));
+        assert!(verifier.verify(&message, &signer));
+    }
+
+    #[test]
+    fn remember_verified_votes_until_thrown_out() {
+        let mut verifier = VoteVerifier::new();
+        let (old, old_signer) = signed_vote(1, 0);
+        let (new, new_signer) = signed_vote(2, 0);
+        assert!(verifier.verify(&old, &old_signer));
+        assert!(verifier.verify(&new, &new_signer));
+        assert!(verifier.is_verified(&old, &old_signer));
+
+        verifier.throw_out_old(2);
+        assert!(!verifier.is_verified(&old, &old_signer));
+        assert!(verifier.is_verified(&new, &new_signer));
+    }
+}
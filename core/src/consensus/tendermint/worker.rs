@@ -19,38 +19,46 @@ use super::backup::{backup, restore, BackupView};
 use super::evidence_collector::{Evidence, EvidenceCollector};
 use super::message::*;
 use super::network;
-use super::params::TimeGapParams;
-use super::types::{Height, Proposal, Step, TendermintSealView, TendermintState, TwoThirdsMajority, View};
+use super::params::{TimeGapParams, TimeoutParams};
+use super::types::{Height, HeightStats, Proposal, Step, TendermintSealView, TendermintState, TwoThirdsMajority, View};
 use super::vote_collector::VoteCollector;
 use super::vote_regression_checker::VoteRegressionChecker;
+use super::vote_verifier::VoteVerifier;
 use super::{
     ENGINE_TIMEOUT_BROADCAST_STEP_STATE, ENGINE_TIMEOUT_EMPTY_PROPOSAL, ENGINE_TIMEOUT_TOKEN_NONCE_BASE, SEAL_FIELDS,
+    UNTIMESTAMPED_SEAL_FIELDS, VOTE_TIMESTAMP_DRIFT_SECS,
 };
 use crate::account_provider::AccountProvider;
 use crate::block::*;
 use crate::client::ConsensusClient;
 use crate::consensus::signer::EngineSigner;
 use crate::consensus::validator_set::{DynamicValidator, ValidatorSet};
-use crate::consensus::{EngineError, Seal};
+use crate::consensus::{EngineError, RemoteSigner, Seal};
 use crate::encoded;
 use crate::error::{BlockError, Error};
+use crate::metrics::CONSENSUS_VIEW_SECONDS;
 use crate::snapshot_notify::NotifySender as SnapshotNotifySender;
 use crate::types::BlockStatus;
 use crate::views::BlockView;
-use ckey::{verify, Ed25519Public as Public, Signature};
+use ckey::{verify_all, Ed25519Public as Public, Signature};
 use cnetwork::{EventSender, NodeId};
 use crossbeam_channel as crossbeam;
-use ctypes::util::unexpected::Mismatch;
+use ctypes::util::unexpected::{Mismatch, OutOfBounds};
 use ctypes::{BlockHash, BlockId, BlockNumber, Header};
 use primitives::Bytes;
 use rlp::{Encodable, Rlp};
 use std::cell::Cell;
 use std::cmp::Ordering;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
 use std::iter::Iterator;
 use std::mem;
 use std::sync::{Arc, Weak};
 use std::thread::{Builder, JoinHandle};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// The number of the recent heights whose statistics are kept.
+const MAX_RECENT_HEIGHT_STATS: usize = 128;
 
 type SpawnResult = (
     JoinHandle<()>,
@@ -77,6 +85,8 @@ struct Worker {
     votes_received: MutTrigger<BitSet>,
     /// Vote accumulator.
     votes: VoteCollector,
+    /// Verifies the signatures of the votes, remembering the verified ones.
+    vote_verifier: VoteVerifier,
     /// evidence accumulator
     evidences: EvidenceCollector,
     /// Used to sign messages and proposals.
@@ -98,6 +108,12 @@ struct Worker {
     timeout_token_nonce: usize,
     vote_regression_checker: VoteRegressionChecker,
     snapshot_notify_sender: SnapshotNotifySender,
+    /// Statistics of the current height.
+    height_stats: HeightStats,
+    /// Statistics of the recent heights, the oldest first.
+    recent_height_stats: VecDeque<HeightStats>,
+    /// When the current view started, for `CONSENSUS_VIEW_SECONDS`.
+    view_started_at: Instant,
 }
 
 pub enum Event {
@@ -139,6 +155,10 @@ pub enum Event {
         ap: Arc<AccountProvider>,
         pubkey: Public,
     },
+    SetRemoteSigner {
+        signer: RemoteSigner,
+        fallback: Option<Arc<AccountProvider>>,
+    },
     Restore(crossbeam::Sender<()>),
     ProposalBlock {
         signature: Signature,
@@ -174,6 +194,10 @@ pub enum Event {
         votes: Vec<ConsensusMessage>,
         result: crossbeam::Sender<Option<Arc<dyn ConsensusClient>>>,
     },
+    GetHeightStats {
+        result: crossbeam::Sender<Vec<HeightStats>>,
+    },
+    SetTimeouts(TimeoutParams),
 }
 
 impl Worker {
@@ -191,6 +215,7 @@ impl Worker {
             view: 0,
             step: TendermintState::Propose,
             votes: Default::default(),
+            vote_verifier: VoteVerifier::new(),
             evidences: Default::default(),
             signer: Default::default(),
             last_two_thirds_majority: TwoThirdsMajority::Empty,
@@ -204,6 +229,9 @@ impl Worker {
             timeout_token_nonce: ENGINE_TIMEOUT_TOKEN_NONCE_BASE,
             vote_regression_checker: VoteRegressionChecker::new(),
             snapshot_notify_sender,
+            height_stats: HeightStats::new(1),
+            recent_height_stats: VecDeque::with_capacity(MAX_RECENT_HEIGHT_STATS),
+            view_started_at: Instant::now(),
         }
     }
 
@@ -313,6 +341,7 @@ impl Worker {
                                 messages,
                                 result,
                             }) => {
+                                inner.verify_messages(&messages);
                                 for message in messages {
                                     result.send(inner.handle_message(&message, false)).unwrap();
                                 }
@@ -340,6 +369,12 @@ impl Worker {
                             }) => {
                                 inner.set_signer(ap, pubkey);
                             }
+                            Ok(Event::SetRemoteSigner {
+                                signer,
+                                fallback,
+                            }) => {
+                                inner.set_remote_signer(signer, fallback);
+                            }
                             Ok(Event::Restore(result)) => {
                                 inner.restore();
                                 result.send(()).unwrap();
@@ -387,6 +422,14 @@ impl Worker {
                                 let client = inner.on_commit_message(block, votes);
                                 result.send(client).unwrap();
                             }
+                            Ok(Event::GetHeightStats {
+                                result
+                            }) => {
+                                result.send(inner.height_stats()).unwrap();
+                            }
+                            Ok(Event::SetTimeouts(timeouts)) => {
+                                inner.extension.send(network::Event::SetTimeouts(timeouts)).unwrap();
+                            }
                             Err(crossbeam::RecvError) => {
                                 cerror!(ENGINE, "The event channel for tendermint thread had been closed.");
                                 break
@@ -421,6 +464,22 @@ impl Worker {
             .hash()
     }
 
+    /// Whether the votes at the height sign their timestamps, as the params of the block below the height decide.
+    fn votes_are_timestamped(&self, height: Height, parent_hash: &BlockHash) -> bool {
+        self.client()
+            .consensus_params(BlockId::Hash(*parent_hash))
+            .map_or(false, |params| params.activations().timestamped_votes(height))
+    }
+
+    /// The time a vote at the height signs, which is 0 before the timestamped votes are activated.
+    fn vote_timestamp(&self, height: Height, parent_hash: &BlockHash, now: u64) -> u64 {
+        if self.votes_are_timestamped(height, parent_hash) {
+            now
+        } else {
+            0
+        }
+    }
+
     /// Get previous block header of given height
     fn prev_block_header_of_height(&self, height: Height) -> Option<encoded::Header> {
         let prev_height = (height - 1) as u64;
@@ -620,6 +679,16 @@ impl Worker {
         self.client().update_sealing(BlockId::Hash(parent_block_hash), true);
     }
 
+    /// Whether the view should stay in the propose step until there are transactions,
+    /// because the miner does not propose an empty block yet or is in the maintenance mode.
+    /// Every validator waits instead of voting nil, so the quiet views are not counted as failed ones.
+    fn waits_for_transactions(&self) -> bool {
+        self.proposal.is_none()
+            && self.votes.get_block_hashes(&self.vote_step()).is_empty()
+            && !matches!(self.last_two_thirds_majority, TwoThirdsMajority::Lock(..))
+            && !self.client().is_block_proposal_due(BlockId::Hash(self.prev_block_hash()))
+    }
+
     /// Do we need this function?
     fn set_finalized_view_in_current_height(&mut self, view: View, is_restoring: bool) {
         if !is_restoring {
@@ -629,8 +698,16 @@ impl Worker {
         self.finalized_view_of_current_block = Some(view);
     }
 
+    /// Records how long the current view lasted and starts timing the next one.
+    fn end_view(&mut self) {
+        let now = Instant::now();
+        CONSENSUS_VIEW_SECONDS.observe(now.duration_since(self.view_started_at).as_secs_f64());
+        self.view_started_at = now;
+    }
+
     fn increment_view(&mut self, n: View) {
         cinfo!(ENGINE, "increment_view: New view.");
+        self.end_view();
         self.view += n;
         self.proposal = Proposal::None;
         self.votes_received = MutTrigger::new(BitSet::new());
@@ -646,6 +723,7 @@ impl Worker {
             self.step
         );
         cinfo!(ENGINE, "Transitioning to height {}.", self.height + 1);
+        self.end_view();
         self.last_two_thirds_majority = TwoThirdsMajority::Empty;
         self.height += 1;
         self.view = 0;
@@ -654,6 +732,8 @@ impl Worker {
         self.finalized_view_of_previous_block =
             self.finalized_view_of_current_block.expect("self.step == Step::Commit");
         self.finalized_view_of_current_block = None;
+        self.height_stats.finalized_view = Some(self.finalized_view_of_previous_block);
+        self.rotate_height_stats();
     }
 
     /// Jump to the height.
@@ -664,6 +744,7 @@ impl Worker {
     fn jump_to_height(&mut self, height: Height, finalized_view_of_previous_height: View) {
         assert!(height > self.height, "{} < {}", height, self.height);
         cinfo!(ENGINE, "Transitioning to height {}.", height);
+        self.end_view();
         self.last_two_thirds_majority = TwoThirdsMajority::Empty;
         self.height = height;
         self.view = 0;
@@ -671,6 +752,25 @@ impl Worker {
         self.votes_received = MutTrigger::new(BitSet::new());
         self.finalized_view_of_previous_block = finalized_view_of_previous_height;
         self.finalized_view_of_current_block = None;
+        // The heights between are finalized by the other validators, so only the current one is recorded.
+        if self.height_stats.height + 1 == height {
+            self.height_stats.finalized_view = Some(finalized_view_of_previous_height);
+        }
+        self.rotate_height_stats();
+    }
+
+    /// Records the statistics of the finished height and starts collecting the ones of the current height.
+    fn rotate_height_stats(&mut self) {
+        let finished = mem::replace(&mut self.height_stats, HeightStats::new(self.height));
+        if self.recent_height_stats.len() == MAX_RECENT_HEIGHT_STATS {
+            self.recent_height_stats.pop_front();
+        }
+        self.recent_height_stats.push_back(finished);
+    }
+
+    /// Returns the statistics of the recent heights including the current one, the oldest first.
+    fn height_stats(&self) -> Vec<HeightStats> {
+        self.recent_height_stats.iter().cloned().chain(std::iter::once(self.height_stats.clone())).collect()
     }
 
     #[allow(clippy::cognitive_complexity)]
@@ -733,6 +833,8 @@ impl Worker {
                         Ok(block) => self.repropose_block(block),
                         Err(error_msg) => cwarn!(ENGINE, "{}", error_msg),
                     }
+                } else if self.waits_for_transactions() {
+                    cdebug!(ENGINE, "I am a proposer, but there is no transaction to propose");
                 } else {
                     cinfo!(ENGINE, "I am a proposer, I'll create a block");
                     self.update_sealing(parent_block_hash);
@@ -959,11 +1061,12 @@ impl Worker {
             step: VoteStep::new(height - 1, parent_block_finalized_view, Step::Precommit),
             block_hash: Some(*proposal.parent_hash()),
         };
-        for (index, signature) in seal_view.signatures().expect("The proposal is verified") {
+        for (index, signature, timestamp) in seal_view.signatures().expect("The proposal is verified") {
             let message = ConsensusMessage {
                 signature,
                 signer_index: index,
                 on: on.clone(),
+                timestamp,
             };
             if !self.votes.is_old_or_known(&message) {
                 if let Err(double_vote) = self.votes.collect(message) {
@@ -980,6 +1083,7 @@ impl Worker {
             view: 0,
             step: Step::Propose,
         });
+        self.vote_verifier.throw_out_old(proposal.number() - 1);
 
         let current_height = self.height;
         let current_vote_step = VoteStep::new(self.height, self.view, self.step.to_step());
@@ -1065,6 +1169,7 @@ impl Worker {
             self.view = backup.view;
             self.finalized_view_of_previous_block = backup.finalized_view_of_previous_block;
             self.finalized_view_of_current_block = backup.finalized_view_of_current_block;
+            self.height_stats = HeightStats::new(backup.height);
 
             if let Some(proposal) = backup.proposal {
                 if client.block(&BlockId::Hash(proposal)).is_some() {
@@ -1106,9 +1211,23 @@ impl Worker {
         let last_block_view = &self.finalized_view_of_previous_block;
         assert_eq!(self.prev_block_hash(), parent_hash);
 
-        let (precommits, precommit_indices) = self
-            .votes
-            .round_signatures_and_indices(&VoteStep::new(height - 1, *last_block_view, Step::Precommit), &parent_hash);
+        let (mut precommits, mut precommit_timestamps, mut precommit_indices) =
+            self.votes.round_signatures_with_timestamps(
+                &VoteStep::new(height - 1, *last_block_view, Step::Precommit),
+                &parent_hash,
+            );
+        let parent = self.client().block_header(&BlockId::Hash(parent_hash)).expect("The parent is imported");
+        let timestamped = height > 1 && self.votes_are_timestamped(height - 1, &parent.parent_hash());
+        if timestamped {
+            // The precommits signed too long before the parent was made can't go into the seal.
+            let min = parent.timestamp().saturating_sub(VOTE_TIMESTAMP_DRIFT_SECS);
+            let in_bounds: Vec<bool> = precommit_timestamps.iter().map(|timestamp| *timestamp >= min).collect();
+            let mut keep = in_bounds.iter();
+            precommits.retain(|_| *keep.next().unwrap());
+            let mut keep = in_bounds.iter();
+            precommit_indices.retain(|_| *keep.next().unwrap());
+            precommit_timestamps.retain(|timestamp| *timestamp >= min);
+        }
         ctrace!(ENGINE, "Collected seal: {:?}({:?})", precommits, precommit_indices);
         let precommit_bitset = BitSet::new_with_indices(&precommit_indices);
         Seal::Tendermint {
@@ -1116,6 +1235,11 @@ impl Worker {
             cur_view: view,
             precommits,
             precommit_bitset,
+            precommit_timestamps: if timestamped {
+                Some(precommit_timestamps)
+            } else {
+                None
+            },
         }
     }
 
@@ -1169,7 +1293,8 @@ impl Worker {
     fn verify_header_basic(&self, header: &Header) -> Result<(), Error> {
         let seal_length = header.seal().len();
         let expected_seal_fields = self.seal_fields();
-        if seal_length != expected_seal_fields {
+        // Whether the seal must carry the precommit timestamps is checked once the parent is known.
+        if seal_length != expected_seal_fields && seal_length != UNTIMESTAMPED_SEAL_FIELDS {
             return Err(BlockError::InvalidSealArity(Mismatch {
                 expected: expected_seal_fields,
                 found: seal_length,
@@ -1216,24 +1341,65 @@ impl Worker {
         };
 
         let mut voted_validators = BitSet::new();
+        let mut signed = Vec::new();
         let parent_hash = header.parent_hash();
-        for (bitset_index, signature) in seal_view.signatures()? {
+        for (bitset_index, signature, timestamp) in seal_view.signatures()? {
             let public = self.validators.get_current(header.parent_hash(), bitset_index);
-            if !verify(&signature, precommit_vote_on.hash().as_ref(), &public) {
-                return Err(EngineError::BlockNotAuthorized(public).into())
-            }
+            signed.push((signature, precommit_vote_on.hash(timestamp), public));
             assert!(!voted_validators.is_set(bitset_index), "Double vote");
             voted_validators.set(bitset_index);
         }
+        if let Some(index) = verify_all(&signed).iter().position(|is_valid| !is_valid) {
+            let (_, _, public) = signed[index];
+            return Err(EngineError::BlockNotAuthorized(public).into())
+        }
 
         // Genesisblock does not have signatures
         if header.number() == 1 {
             return Ok(())
         }
+        self.verify_precommit_timestamps(header, &seal_view)?;
         self.validators.check_enough_votes_with_current(&parent_hash, &voted_validators)?;
         Ok(())
     }
 
+    /// Checks that the seal carries the precommit timestamps exactly when the timestamped votes are activated,
+    /// and that each of them is within the drift from the times of the block voted on and the block sealing it.
+    fn verify_precommit_timestamps(&self, header: &Header, seal_view: &TendermintSealView<'_>) -> Result<(), Error> {
+        let parent =
+            self.client().block_header(&BlockId::Hash(*header.parent_hash())).ok_or(BlockError::InvalidSeal)?;
+        let timestamped = self.votes_are_timestamped(parent.number(), &parent.parent_hash());
+        let timestamps = match (timestamped, seal_view.precommit_timestamps()?) {
+            (false, None) => return Ok(()),
+            (true, Some(timestamps)) => timestamps,
+            _ => return Err(BlockError::InvalidSeal.into()),
+        };
+        let min = parent.timestamp().saturating_sub(VOTE_TIMESTAMP_DRIFT_SECS);
+        let max = header.timestamp().saturating_add(VOTE_TIMESTAMP_DRIFT_SECS);
+        match timestamps.into_iter().find(|timestamp| *timestamp == 0 || *timestamp < min || *timestamp > max) {
+            Some(found) => Err(BlockError::InvalidTimestamp(OutOfBounds {
+                min: Some(min),
+                max: Some(max),
+                found,
+            })
+            .into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks the time a received vote signed. It's 0 before the timestamped votes are activated.
+    /// Afterwards it's no earlier than the drift before the time of the block below the height,
+    /// and no later than the drift after the local clock.
+    fn is_vote_timestamp_valid(&self, message: &ConsensusMessage, parent: &encoded::Header) -> bool {
+        let timestamp = message.timestamp();
+        if !self.votes_are_timestamped(message.height(), &parent.hash()) {
+            return timestamp == 0
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("There is no time machine.").as_secs();
+        let min = parent.timestamp().saturating_sub(VOTE_TIMESTAMP_DRIFT_SECS);
+        timestamp != 0 && min <= timestamp && timestamp <= now + VOTE_TIMESTAMP_DRIFT_SECS
+    }
+
     fn on_timeout(&mut self, token: usize) {
         // Timeout from empty block generation
         if token == ENGINE_TIMEOUT_EMPTY_PROPOSAL {
@@ -1282,8 +1448,13 @@ impl Worker {
         }
 
         let next_step = match self.step {
+            TendermintState::Propose if self.waits_for_transactions() => {
+                cdebug!(ENGINE, "Propose timeout without transactions, keep waiting.");
+                TendermintState::Propose
+            }
             TendermintState::Propose => {
                 cinfo!(ENGINE, "Propose timeout.");
+                self.height_stats.propose_timeouts += 1;
                 TendermintState::Prevote
             }
             TendermintState::ProposeWaitBlockGeneration {
@@ -1306,19 +1477,23 @@ impl Worker {
             }
             TendermintState::Prevote if self.has_enough_any_votes() => {
                 cinfo!(ENGINE, "Prevote timeout.");
+                self.height_stats.prevote_timeouts += 1;
                 TendermintState::Precommit
             }
             TendermintState::Prevote => {
                 cinfo!(ENGINE, "Prevote timeout without enough votes.");
+                self.height_stats.prevote_timeouts += 1;
                 TendermintState::Prevote
             }
             TendermintState::Precommit if self.has_enough_any_votes() => {
                 cinfo!(ENGINE, "Precommit timeout.");
+                self.height_stats.precommit_timeouts += 1;
                 self.increment_view(1);
                 TendermintState::Propose
             }
             TendermintState::Precommit => {
                 cinfo!(ENGINE, "Precommit timeout without enough votes.");
+                self.height_stats.precommit_timeouts += 1;
                 TendermintState::Precommit
             }
             TendermintState::Commit {
@@ -1354,6 +1529,36 @@ impl Worker {
         nonce < self.timeout_token_nonce
     }
 
+    /// Verifies the signatures of the messages together, so that they are found verified when handled one by one.
+    /// The messages that can't be handled now are left to `handle_message`.
+    fn verify_messages(&mut self, messages: &[Bytes]) {
+        let messages: Vec<ConsensusMessage> = messages
+            .iter()
+            .filter_map(|message| Rlp::new(message).as_val().ok())
+            .filter(|message: &ConsensusMessage| {
+                message.height() <= self.height && !self.votes.is_old_or_known(message)
+            })
+            .collect();
+        let mut prev_block_hashes = HashMap::new();
+        let mut signed = Vec::with_capacity(messages.len());
+        for message in &messages {
+            let height = message.height();
+            let prev_block_hash = match prev_block_hashes.entry(height) {
+                Entry::Occupied(entry) => *entry.get(),
+                Entry::Vacant(entry) => {
+                    match height.checked_sub(1).and_then(|number| self.client().block_hash(&BlockId::Number(number))) {
+                        Some(hash) => *entry.insert(hash),
+                        None => continue,
+                    }
+                }
+            };
+            if message.signer_index < self.validators.count(&prev_block_hash) {
+                signed.push((message, self.validators.get(&prev_block_hash, message.signer_index)));
+            }
+        }
+        self.vote_verifier.verify_all(&signed);
+    }
+
     fn handle_message(&mut self, rlp: &[u8], is_restoring: bool) -> Result<(), EngineError> {
         fn fmt_err<T: ::std::fmt::Debug>(x: T) -> EngineError {
             EngineError::MalformedMessage(format!("{:?}", x))
@@ -1372,11 +1577,11 @@ impl Worker {
                 })
             }
 
-            let prev_block_hash = self
+            let prev_block_header = self
                 .client()
                 .block_header(&BlockId::Number((message.on.step.height as u64) - 1))
-                .expect("self.height - 1 == the best block number")
-                .hash();
+                .expect("self.height - 1 == the best block number");
+            let prev_block_hash = prev_block_header.hash();
 
             if signer_index >= self.validators.count(&prev_block_hash) {
                 return Err(EngineError::ValidatorNotExist {
@@ -1387,7 +1592,7 @@ impl Worker {
 
             let sender = self.validators.get(&prev_block_hash, signer_index);
 
-            if !message.verify(&sender) {
+            if !self.vote_verifier.verify(&message, &sender) {
                 return Err(EngineError::MessageWithInvalidSignature {
                     height: prev_height,
                     signer_index,
@@ -1395,6 +1600,14 @@ impl Worker {
                 })
             }
 
+            if !self.is_vote_timestamp_valid(&message, &prev_block_header) {
+                return Err(EngineError::InvalidVoteTimestamp {
+                    height: prev_height,
+                    signer_index,
+                    timestamp: message.timestamp(),
+                })
+            }
+
             if message.on.step > self.vote_step() {
                 ctrace!(ENGINE, "Ignore future message {:?} from {:?}.", message, sender);
                 return Ok(())
@@ -1486,6 +1699,10 @@ impl Worker {
         self.signer.set_to_keep_decrypted_account(ap, pubkey);
     }
 
+    fn set_remote_signer(&mut self, signer: RemoteSigner, fallback: Option<Arc<AccountProvider>>) {
+        self.signer.set_remote_signer(signer, fallback);
+    }
+
     fn vote_on_block_hash(&mut self, block_hash: Option<BlockHash>) -> Result<Option<ConsensusMessage>, Error> {
         let signer_index = if let Some(signer_index) = self.signer_index() {
             signer_index
@@ -1500,12 +1717,15 @@ impl Worker {
         };
         assert!(self.vote_regression_checker.check(&on), "Vote should not regress");
 
-        let signature = self.signer.sign(on.hash())?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("There is no time machine.").as_secs();
+        let timestamp = self.vote_timestamp(self.height, &self.prev_block_hash(), now);
+        let signature = self.signer.sign(on.hash(timestamp))?;
 
         let vote = ConsensusMessage {
             signature,
             signer_index,
             on,
+            timestamp,
         };
 
         self.votes_received.set(vote.signer_index);
@@ -1526,12 +1746,15 @@ impl Worker {
         };
         assert!(self.vote_regression_checker.check(&on), "Vote should not regress");
 
-        let signature = self.signer.sign(on.hash())?;
+        // The proposer votes at the time it wrote in the header, so the vote can be recovered from the proposal.
+        let timestamp = self.vote_timestamp(self.height, parent_hash, header.timestamp());
+        let signature = self.signer.sign(on.hash(timestamp))?;
 
         let vote = ConsensusMessage {
             signature,
             signer_index,
             on,
+            timestamp,
         };
 
         self.votes.collect(vote.clone()).expect("Must not attempt double vote on proposal");
@@ -1556,6 +1779,7 @@ impl Worker {
             signature,
             signer_index,
             on,
+            timestamp: self.vote_timestamp(header.number(), header.parent_hash(), header.timestamp()),
         })
     }
 
@@ -1750,7 +1974,7 @@ impl Worker {
             }
 
             let signer_public = self.validators.get(&parent_hash, message.signer_index);
-            if !message.verify(&signer_public) {
+            if !self.vote_verifier.verify(&message, &signer_public) {
                 cwarn!(ENGINE, "Proposal verification failed: signer is different");
                 return None
             }
@@ -1972,11 +2196,13 @@ impl Worker {
                     block_hash: Some(block.hash()),
                 };
                 let mut votes = Vec::new();
-                for (index, signature) in child_block_seal_view.signatures().expect("The block is verified") {
+                for (index, signature, timestamp) in child_block_seal_view.signatures().expect("The block is verified")
+                {
                     let message = ConsensusMessage {
                         signature,
                         signer_index: index,
                         on: on.clone(),
+                        timestamp,
                     };
                     votes.push(message);
                 }
@@ -2062,6 +2288,14 @@ impl Worker {
             return None
         }
 
+        let signer_count = self.validators.count(&prev_block_hash);
+        let signed: Vec<_> = votes
+            .iter()
+            .filter(|vote| vote.signer_index < signer_count)
+            .map(|vote| (vote, self.validators.get(&prev_block_hash, vote.signer_index)))
+            .collect();
+        self.vote_verifier.verify_all(&signed);
+
         let mut vote_bitset = BitSet::new();
 
         for vote in &votes {
@@ -2089,7 +2323,7 @@ impl Worker {
 
             let sender = self.validators.get(&prev_block_hash, signer_index);
 
-            if !vote.verify(&sender) {
+            if !self.vote_verifier.verify(vote, &sender) {
                 cwarn!(
                     ENGINE,
                     "Invalid commit message-{} received: invalid signature signer_index: {} pubkey: {:?}",
@@ -0,0 +1,186 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db;
+use crate::db_version::{self, VERSION_KEY_SCHEMA};
+use kvdb::{DBTransaction, KeyValueDB};
+
+/// The schema version of the databases written by this release.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Report the progress of a long migration step every this many entries.
+const PROGRESS_INTERVAL: usize = 100_000;
+
+/// A step that upgrades the database layout from `version - 1` to `version`.
+///
+/// A step reads the database as left by the previous step and stages every change in the given batch.
+/// The batch is written together with the new version stamp, so a failing step leaves the database at the
+/// previous version.
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    pub migrate: fn(&dyn KeyValueDB, &mut DBTransaction, &mut dyn FnMut(usize)) -> Result<(), String>,
+}
+
+/// The upgrades from the version 1 in the ascending order of the versions. A release that changes the layout
+/// appends a step here and bumps `SCHEMA_VERSION`.
+const MIGRATIONS: &[Migration] = &[];
+
+/// Upgrades the database to `SCHEMA_VERSION`, returning the version it was at.
+///
+/// A fresh database is stamped with the current version without running any step. A database written by
+/// a newer release is refused since this release can't read its layout. So is a database without the stamp:
+/// it was written by a release before the versioning, whose layouts differ in ways no step can tell apart,
+/// so it has to be synced again.
+pub fn migrate_schema(db: &dyn KeyValueDB) -> Result<u32, String> {
+    run_migrations(db, MIGRATIONS, SCHEMA_VERSION)
+}
+
+fn run_migrations(db: &dyn KeyValueDB, migrations: &[Migration], target: u32) -> Result<u32, String> {
+    let current = db_version::get_version(db, VERSION_KEY_SCHEMA);
+    if current > target {
+        return Err(format!(
+            "The database has the schema version {} but this release supports up to {}. Use a newer release.",
+            current, target
+        ))
+    }
+    if current == target {
+        return Ok(current)
+    }
+    if is_fresh(db) {
+        let mut batch = DBTransaction::new();
+        db_version::set_version(&mut batch, VERSION_KEY_SCHEMA, target);
+        db.write(batch).map_err(|err| err.to_string())?;
+        return Ok(target)
+    }
+    if current == 0 {
+        return Err("The database has no schema version, so it was written by a release before the versioning \
+                    and can't be upgraded. Resync required: remove the database and sync the chain again."
+            .to_string())
+    }
+
+    for migration in migrations.iter().filter(|migration| migration.version > current && migration.version <= target) {
+        cinfo!(CLIENT, "Migrating the database to the schema version {}: {}", migration.version, migration.description);
+        let mut batch = DBTransaction::new();
+        let mut report = |processed: usize| {
+            if processed % PROGRESS_INTERVAL == 0 {
+                cinfo!(
+                    CLIENT,
+                    "Migrating the database to the schema version {}: {} entries",
+                    migration.version,
+                    processed
+                );
+            }
+        };
+        (migration.migrate)(db, &mut batch, &mut report).map_err(|err| {
+            format!(
+                "Failed to migrate the database to the schema version {}: {}. It is left at the version {}.",
+                migration.version,
+                err,
+                migration.version - 1
+            )
+        })?;
+        db_version::set_version(&mut batch, VERSION_KEY_SCHEMA, migration.version);
+        db.write(batch).map_err(|err| err.to_string())?;
+        db.flush().map_err(|err| err.to_string())?;
+    }
+    Ok(current)
+}
+
+/// A database without any header has never been initialized.
+fn is_fresh(db: &dyn KeyValueDB) -> bool {
+    db.iter(db::COL_HEADERS).next().is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database_with_header(version: u32) -> kvdb_memorydb::InMemory {
+        let db = kvdb_memorydb::create(db::NUM_COLUMNS.unwrap());
+        let mut batch = DBTransaction::new();
+        batch.put(db::COL_HEADERS, b"header", b"header");
+        batch.put(db::COL_EXTRA, b"old-key", b"value");
+        if version > 0 {
+            db_version::set_version(&mut batch, VERSION_KEY_SCHEMA, version);
+        }
+        db.write(batch).unwrap();
+        db
+    }
+
+    fn rename_key(
+        db: &dyn KeyValueDB,
+        batch: &mut DBTransaction,
+        progress: &mut dyn FnMut(usize),
+    ) -> Result<(), String> {
+        let value = db.get(db::COL_EXTRA, b"old-key").unwrap().ok_or("no key")?;
+        batch.delete(db::COL_EXTRA, b"old-key");
+        batch.put(db::COL_EXTRA, b"new-key", &value);
+        progress(1);
+        Ok(())
+    }
+
+    fn fail(_db: &dyn KeyValueDB, batch: &mut DBTransaction, _progress: &mut dyn FnMut(usize)) -> Result<(), String> {
+        batch.put(db::COL_EXTRA, b"partial", b"partial");
+        Err("broken".to_string())
+    }
+
+    const TEST_MIGRATIONS: &[Migration] = &[
+        Migration {
+            version: 2,
+            description: "rename the key",
+            migrate: rename_key,
+        },
+        Migration {
+            version: 3,
+            description: "fail",
+            migrate: fail,
+        },
+    ];
+
+    #[test]
+    fn fresh_database_is_stamped_without_migrating() {
+        let db = kvdb_memorydb::create(db::NUM_COLUMNS.unwrap());
+        assert_eq!(Ok(3), run_migrations(&db, TEST_MIGRATIONS, 3));
+        assert_eq!(3, db_version::get_version(&db, VERSION_KEY_SCHEMA));
+    }
+
+    #[test]
+    fn failed_step_is_rolled_back() {
+        let db = database_with_header(1);
+        assert!(run_migrations(&db, TEST_MIGRATIONS, 3).is_err());
+        assert_eq!(2, db_version::get_version(&db, VERSION_KEY_SCHEMA));
+        assert_eq!(None, db.get(db::COL_EXTRA, b"old-key").unwrap());
+        assert!(db.get(db::COL_EXTRA, b"new-key").unwrap().is_some());
+        assert_eq!(None, db.get(db::COL_EXTRA, b"partial").unwrap());
+    }
+
+    #[test]
+    fn newer_schema_is_refused() {
+        let db = database_with_header(2);
+        assert_eq!(Ok(2), run_migrations(&db, TEST_MIGRATIONS, 2));
+        assert!(run_migrations(&db, TEST_MIGRATIONS, 1).is_err());
+    }
+
+    #[test]
+    fn unstamped_database_is_refused() {
+        let db = database_with_header(0);
+        let err = run_migrations(&db, TEST_MIGRATIONS, 1).unwrap_err();
+        assert!(err.contains("Resync required"));
+        assert_eq!(0, db_version::get_version(&db, VERSION_KEY_SCHEMA));
+        assert!(db.get(db::COL_EXTRA, b"old-key").unwrap().is_some());
+    }
+}
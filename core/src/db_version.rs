@@ -19,6 +19,8 @@ use kvdb::{DBTransaction, KeyValueDB};
 pub const VERSION_KEY_PREFIX: &[u8] = b"version_";
 /// Save the version of Tendermint backup where the key below is pointing
 pub const VERSION_KEY_TENDERMINT_BACKUP: &[u8] = b"version_tendermint-backup";
+/// Save the version of the database layout, upgraded by the migrations on startup
+pub const VERSION_KEY_SCHEMA: &[u8] = b"version_schema";
 
 /// To support data values that are saved before the version scheme return 0 if the version does not exist
 pub fn get_version(db: &dyn KeyValueDB, key: &[u8]) -> u32 {
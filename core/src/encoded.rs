@@ -31,7 +31,7 @@ use ckey::Ed25519Public as Public;
 use coordinator::Transaction;
 use ctypes::{BlockHash, BlockNumber, Header as FullHeader, TxHash};
 use primitives::H256;
-use rlp::Rlp;
+use rlp::{DecoderError, Rlp};
 
 /// Owning header view.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -56,6 +56,11 @@ impl Header {
         views::HeaderView::new(&self.0)
     }
 
+    /// Get a borrowed header view onto the data, checking its structure once.
+    pub fn validated_view(&self) -> Result<views::ValidatedHeaderView<'_>, DecoderError> {
+        views::ValidatedHeaderView::new(&self.0)
+    }
+
     /// Get the rlp of the header.
     #[inline]
     pub fn rlp(&self) -> Rlp<'_> {
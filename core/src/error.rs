@@ -15,21 +15,44 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::account_provider::Error as AccountProviderError;
+use crate::client::ForkError;
 use crate::consensus::EngineError;
 use cdb::DatabaseError;
 use cio::IoError;
 use ckey::{Ed25519Public as Public, Error as KeyError};
-use coordinator::types::CloseBlockError;
+use coordinator::types::{CloseBlockError, ErrorCode};
 use cstate::StateError;
 use ctypes::errors::{HistoryError, RuntimeError, SyntaxError};
 use ctypes::util::unexpected::{Mismatch, OutOfBounds};
-use ctypes::{BlockHash, BlockNumber};
+use ctypes::{BlockHash, BlockNumber, TxHash};
 use merkle_trie::TrieError;
 use primitives::H256;
 use rlp::DecoderError;
 use std::fmt;
 use std::io::Error as StdIoError;
 
+/// What kind of failure an error is, for deciding how to react to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCategory {
+    /// The same request may succeed later, e.g. when the queue has room or the parent block arrives.
+    Transient,
+    /// The request is malformed or not acceptable, and fails whenever it is tried.
+    InvalidInput,
+    /// The request violates the consensus rules.
+    Consensus,
+    /// The node itself failed, e.g. the database is corrupted.
+    Internal,
+}
+
+/// Classifies errors into `ErrorCategory`s, so that callers can tell the errors worth retrying.
+pub trait Categorized {
+    fn category(&self) -> ErrorCategory;
+
+    fn is_retryable(&self) -> bool {
+        self.category() == ErrorCategory::Transient
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 /// Import to the block queue result
 pub enum ImportError {
@@ -39,6 +62,8 @@ pub enum ImportError {
     AlreadyQueued,
     /// Already marked as bad from a previous import (could mean parent is bad).
     KnownBad,
+    /// The block queue is at its limits, so the block is not queued.
+    QueueFull,
 }
 
 impl fmt::Display for ImportError {
@@ -47,6 +72,7 @@ impl fmt::Display for ImportError {
             ImportError::AlreadyInChain => "block already in chain",
             ImportError::AlreadyQueued => "block already in the block queue",
             ImportError::KnownBad => "block known to be bad",
+            ImportError::QueueFull => "block queue is full",
         };
 
         f.write_fmt(format_args!("Block import error ({})", msg))
@@ -87,6 +113,10 @@ pub enum BlockError {
     InvalidTransactionsRoot(Mismatch<H256>),
     /// Next validator set hash header field is invalid.
     InvalidNextValidatorSetHash(Mismatch<H256>),
+    /// External anchor header field is not the one provided by the module.
+    InvalidExternalAnchor(Mismatch<Option<H256>>),
+    /// Module roots hash header field is invalid.
+    InvalidModuleRootsHash(Mismatch<Option<H256>>),
     /// Some low-level aspect of the seal is incorrect.
     InvalidSeal,
     /// Timestamp header field is invalid.
@@ -108,11 +138,37 @@ pub enum BlockError {
     BodySizeIsTooBig,
     /// prev_validator_set field in SyncHeader struct is invalid.
     InvalidValidatorSet,
+    /// A transaction is rejected by its owner module.
+    InvalidTransaction(TxHash, ErrorCode),
+    /// A transaction is out of the seeded order the consensus params require.
+    InvalidTransactionOrder(TxHash),
+    /// The block is not the one the scheme pins at its number.
+    CheckpointMismatch(Mismatch<BlockHash>),
+    /// The block forks the chain at or below a checkpoint the chain already has.
+    BelowCheckpoint(BlockNumber),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum SchemeError {
     InvalidState,
+    /// No engine is registered with the name.
+    UnknownEngine(String),
+    InvalidEngineParams {
+        engine: String,
+        reason: String,
+    },
+    /// The scheme is not a well-formed scheme JSON.
+    InvalidJson(String),
+    InvalidParams(String),
+    InvalidGenesis(String),
+    /// A validator of the genesis validator set, which the modules build, is not usable.
+    InvalidValidator {
+        index: usize,
+        reason: String,
+    },
+    /// A module failed to initialize the genesis state, mostly due to its genesis config.
+    InvalidModuleGenesis(String),
+    InvalidCheckpoints(String),
 }
 
 impl fmt::Display for SchemeError {
@@ -120,6 +176,20 @@ impl fmt::Display for SchemeError {
         use self::SchemeError::*;
         let msg: String = match self {
             InvalidState => "Genesis state is not same with spec".into(),
+            UnknownEngine(name) => format!("Unknown engine {}", name),
+            InvalidEngineParams {
+                engine,
+                reason,
+            } => format!("Invalid params of {}: {}", engine, reason),
+            InvalidJson(reason) => format!("Invalid JSON: {}", reason),
+            InvalidParams(reason) => format!("Invalid params: {}", reason),
+            InvalidGenesis(reason) => format!("Invalid genesis: {}", reason),
+            InvalidValidator {
+                index,
+                reason,
+            } => format!("Invalid genesis validator #{}: {}", index, reason),
+            InvalidModuleGenesis(reason) => format!("The modules failed to initialize the genesis state: {}", reason),
+            InvalidCheckpoints(reason) => format!("Invalid checkpoints: {}", reason),
         };
         f.write_fmt(format_args!("Scheme file error ({})", msg))
     }
@@ -135,6 +205,8 @@ impl fmt::Display for BlockError {
             InvalidStateRoot(mis) => format!("Invalid state root in header: {}", mis),
             InvalidTransactionsRoot(mis) => format!("Invalid transactions root in header: {}", mis),
             InvalidNextValidatorSetHash(mis) => format!("Invalid next validator set hash in header: {}", mis),
+            InvalidExternalAnchor(mis) => format!("Invalid external anchor in header: {:?}", mis),
+            InvalidModuleRootsHash(mis) => format!("Invalid module roots hash in header: {:?}", mis),
             InvalidSeal => "Block has invalid seal.".into(),
             InvalidTimestamp(oob) => format!("Invalid timestamp in header: {}", oob),
             TemporarilyInvalid(oob) => format!("Future timestamp in header: {}", oob),
@@ -145,6 +217,10 @@ impl fmt::Display for BlockError {
             TooManyTransactions(pubkey) => format!("Too many transactions from: {:?}", pubkey),
             BodySizeIsTooBig => "Block's body size is too big".to_string(),
             InvalidValidatorSet => "Invalid prev_validator_set in SyncHeader".to_string(),
+            InvalidTransaction(hash, code) => format!("Transaction {} is rejected with error code {}", hash, code),
+            InvalidTransactionOrder(hash) => format!("Transaction {} is out of the seeded order", hash),
+            CheckpointMismatch(mis) => format!("Block doesn't match the checkpoint: {}", mis),
+            BelowCheckpoint(number) => format!("Block forks the chain below the checkpoint #{}", number),
         };
 
         f.write_fmt(format_args!("Block error ({})", msg))
@@ -174,6 +250,9 @@ pub enum Error {
     /// Error concerning a database.
     Database(DatabaseError),
     Rlp(DecoderError),
+    Fork(ForkError),
+    /// The node is in the maintenance mode, in which it accepts no transactions.
+    Maintenance,
     Other(String),
 }
 
@@ -193,6 +272,8 @@ impl fmt::Display for Error {
             Error::Syntax(err) => err.fmt(f),
             Error::Database(err) => err.fmt(f),
             Error::Rlp(err) => err.fmt(f),
+            Error::Fork(err) => err.fmt(f),
+            Error::Maintenance => write!(f, "The node is in the maintenance mode"),
             Error::Other(s) => write!(f, "{}", s),
         }
     }
@@ -295,6 +376,12 @@ impl From<DatabaseError> for Error {
     }
 }
 
+impl From<ForkError> for Error {
+    fn from(err: ForkError) -> Error {
+        Error::Fork(err)
+    }
+}
+
 impl From<DecoderError> for Error {
     fn from(err: DecoderError) -> Error {
         Error::Rlp(err)
@@ -306,3 +393,105 @@ impl From<CloseBlockError> for Error {
         Error::Other(err)
     }
 }
+
+impl Categorized for ImportError {
+    fn category(&self) -> ErrorCategory {
+        match self {
+            ImportError::AlreadyInChain | ImportError::AlreadyQueued => ErrorCategory::InvalidInput,
+            ImportError::KnownBad => ErrorCategory::Consensus,
+            ImportError::QueueFull => ErrorCategory::Transient,
+        }
+    }
+}
+
+impl Categorized for BlockError {
+    fn category(&self) -> ErrorCategory {
+        match self {
+            BlockError::TemporarilyInvalid(_) | BlockError::UnknownParent(_) => ErrorCategory::Transient,
+            _ => ErrorCategory::Consensus,
+        }
+    }
+}
+
+impl Categorized for BlockImportError {
+    fn category(&self) -> ErrorCategory {
+        match self {
+            BlockImportError::Import(err) => err.category(),
+            BlockImportError::Block(err) => err.category(),
+            BlockImportError::Other(_) => ErrorCategory::Internal,
+        }
+    }
+}
+
+impl Categorized for EngineError {
+    fn category(&self) -> ErrorCategory {
+        match self {
+            EngineError::FutureMessage {
+                ..
+            } => ErrorCategory::Transient,
+            EngineError::CannotOpenBlock => ErrorCategory::Internal,
+            EngineError::RemoteSignerFailed(_) => ErrorCategory::Transient,
+            _ => ErrorCategory::Consensus,
+        }
+    }
+}
+
+impl Categorized for HistoryError {
+    fn category(&self) -> ErrorCategory {
+        match self {
+            HistoryError::LimitReached => ErrorCategory::Transient,
+            HistoryError::Old | HistoryError::TooCheapToReplace | HistoryError::TransactionAlreadyImported => {
+                ErrorCategory::InvalidInput
+            }
+        }
+    }
+}
+
+impl Categorized for Error {
+    fn category(&self) -> ErrorCategory {
+        match self {
+            Error::Block(err) => err.category(),
+            Error::Import(err) => err.category(),
+            Error::Engine(err) => err.category(),
+            Error::History(err) => err.category(),
+            Error::Maintenance => ErrorCategory::Transient,
+            Error::Key(_) | Error::AccountProvider(_) | Error::Runtime(_) | Error::Syntax(_) | Error::Rlp(_) => {
+                ErrorCategory::InvalidInput
+            }
+            Error::Io(_)
+            | Error::Scheme(_)
+            | Error::Trie(_)
+            | Error::Database(_)
+            | Error::Fork(_)
+            | Error::Other(_) => ErrorCategory::Internal,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_transient_errors_are_retryable() {
+        assert!(Error::History(HistoryError::LimitReached).is_retryable());
+        assert!(Error::Block(BlockError::UnknownParent(Default::default())).is_retryable());
+        assert!(!Error::History(HistoryError::Old).is_retryable());
+        assert!(!Error::Block(BlockError::InvalidSeal).is_retryable());
+        assert!(!Error::Other("unknown".to_string()).is_retryable());
+        assert!(Error::Maintenance.is_retryable());
+        assert!(Error::Import(ImportError::QueueFull).is_retryable());
+    }
+
+    #[test]
+    fn block_import_error_follows_its_cause() {
+        let temporarily_invalid = BlockError::TemporarilyInvalid(OutOfBounds {
+            min: None,
+            max: Some(10),
+            found: 11,
+        });
+        assert_eq!(BlockImportError::Block(temporarily_invalid).category(), ErrorCategory::Transient);
+        assert_eq!(BlockImportError::Import(ImportError::KnownBad).category(), ErrorCategory::Consensus);
+        assert_eq!(BlockImportError::Other("unknown".to_string()).category(), ErrorCategory::Internal);
+    }
+}
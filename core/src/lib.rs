@@ -30,10 +30,12 @@ mod blockchain_info;
 mod client;
 mod consensus;
 mod db;
+mod db_migration;
 mod db_version;
 pub mod encoded;
 mod error;
 mod event;
+pub mod metrics;
 mod miner;
 mod peer_db;
 mod scheme;
@@ -47,22 +49,31 @@ mod views;
 mod tests;
 
 pub use crate::account_provider::{AccountProvider, Error as AccountProviderError};
-pub use crate::block::Block;
+pub use crate::block::{module_roots_hash, Block};
+pub use crate::blockchain::BodyCompression;
 pub use crate::client::snapshot_notify;
+pub use crate::client::verify_database_offline;
 pub use crate::client::ConsensusClient;
+pub use crate::client::{export_fork_state, export_state, import_state, translate_validators, ForkError, ForkPoint};
+pub use crate::client::{migrate_database, open_database, DatabaseBackend, DatabaseCompactionProfile, SledDb};
 pub use crate::client::{
-    BlockChainClient, BlockChainTrait, ChainNotify, Client, ClientConfig, DatabaseClient, EngineClient, EngineInfo,
-    ImportBlock, MiningBlockChainClient, SnapshotClient, StateInfo, TermInfo, TestBlockChainClient,
+    ArchiveDb, ArchiveStats, BackfillStatus, BlockChainClient, BlockChainTrait, ChainNotify, Client, ClientConfig,
+    DatabaseClient, EngineClient, EngineInfo, ImportBlock, Inconsistency, IntegrityReport, MiningBlockChainClient,
+    NodeHealth, SnapshotClient, StateInfo, TermInfo, TestBlockChainClient,
 };
 pub use crate::consensus::signer::EngineSigner;
 pub use crate::consensus::tendermint::Evidence;
-pub use crate::consensus::{EngineType, TimeGapParams};
+pub use crate::consensus::{EngineType, HeightStats, RemoteSigner, TimeGapParams, TimeoutParams};
 pub use crate::db::{COL_STATE, NUM_COLUMNS};
-pub use crate::error::{BlockImportError, Error, ImportError};
-pub use crate::miner::{Miner, MinerOptions, MinerService};
+pub use crate::error::{BlockImportError, Categorized, Error, ErrorCategory, ImportError, SchemeError};
+pub use crate::miner::{
+    EmptyBlockProposal, IngressStats, MemPoolFlush, MemPoolUsage, Miner, MinerOptions, MinerService, OrphanStats,
+    PoolCursor, PoolOrder, PoolPage, PoolQuery, PoolSort, TransactionPool,
+};
 pub use crate::peer_db::PeerDb;
-pub use crate::scheme::Scheme;
+pub use crate::scheme::{ForkedGenesis, Scheme};
 pub use crate::service::ClientService;
 pub use crate::transaction::{LocalizedTransaction, PendingTransactions};
 pub use crate::types::{BlockStatus, TransactionId};
+pub use crate::views::{HeaderView, ValidatedHeaderView};
 pub use rlp::Encodable;
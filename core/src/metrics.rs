@@ -0,0 +1,122 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! The metrics of the core subsystems, collected for Prometheus.
+//! They are registered to the default registry of `prometheus`, where the coordinator registers its own metrics too.
+
+use kvdb::{DBTransaction, DBValue, KeyValueDB};
+use once_cell::sync::Lazy;
+use prometheus::{register_histogram, register_int_counter, register_int_gauge, Histogram, IntCounter, IntGauge};
+use std::io;
+use std::sync::Arc;
+
+pub static MEM_POOL_TRANSACTIONS: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!("mem_pool_transactions", "The number of the external transactions in the mem pool").unwrap()
+});
+
+pub static MEM_POOL_MEMORY: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!("mem_pool_memory_bytes", "The memory used by the external transactions in the mem pool")
+        .unwrap()
+});
+
+pub static MEM_POOL_EVICTIONS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "mem_pool_evictions_total",
+        "The number of transactions evicted by the limits of the mem pool"
+    )
+    .unwrap()
+});
+
+pub static BLOCK_IMPORT_SECONDS: Lazy<Histogram> = Lazy::new(|| {
+    register_histogram!("block_import_seconds", "The time taken to verify, execute and commit an imported block")
+        .unwrap()
+});
+
+pub static BLOCK_EXECUTION_SECONDS: Lazy<Histogram> = Lazy::new(|| {
+    register_histogram!("block_execution_seconds", "The time taken to execute the transactions of a block").unwrap()
+});
+
+pub static CONSENSUS_VIEW_SECONDS: Lazy<Histogram> = Lazy::new(|| {
+    register_histogram!("consensus_view_seconds", "The time a consensus view lasted, until the next view or height")
+        .unwrap()
+});
+
+pub static DB_READS: Lazy<IntCounter> =
+    Lazy::new(|| register_int_counter!("db_reads_total", "The number of reads from the database").unwrap());
+
+pub static DB_WRITES: Lazy<IntCounter> =
+    Lazy::new(|| register_int_counter!("db_writes_total", "The number of keys written to the database").unwrap());
+
+/// Counts the reads and the writes of a database for `DB_READS` and `DB_WRITES`.
+pub struct MeteredDb {
+    db: Arc<dyn KeyValueDB>,
+}
+
+impl MeteredDb {
+    pub fn new(db: Arc<dyn KeyValueDB>) -> Self {
+        Self {
+            db,
+        }
+    }
+}
+
+impl KeyValueDB for MeteredDb {
+    fn get(&self, col: Option<u32>, key: &[u8]) -> io::Result<Option<DBValue>> {
+        DB_READS.inc();
+        self.db.get(col, key)
+    }
+
+    fn get_by_prefix(&self, col: Option<u32>, prefix: &[u8]) -> Option<Box<[u8]>> {
+        DB_READS.inc();
+        self.db.get_by_prefix(col, prefix)
+    }
+
+    fn write_buffered(&self, transaction: DBTransaction) {
+        for _ in &transaction.ops {
+            DB_WRITES.inc();
+        }
+        self.db.write_buffered(transaction)
+    }
+
+    fn write(&self, transaction: DBTransaction) -> io::Result<()> {
+        for _ in &transaction.ops {
+            DB_WRITES.inc();
+        }
+        self.db.write(transaction)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.db.flush()
+    }
+
+    fn iter<'a>(&'a self, col: Option<u32>) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+        DB_READS.inc();
+        self.db.iter(col)
+    }
+
+    fn iter_from_prefix<'a>(
+        &'a self,
+        col: Option<u32>,
+        prefix: &'a [u8],
+    ) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+        DB_READS.inc();
+        self.db.iter_from_prefix(col, prefix)
+    }
+
+    fn restore(&self, new_db: &str) -> io::Result<()> {
+        self.db.restore(new_db)
+    }
+}
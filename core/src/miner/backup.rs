@@ -15,20 +15,160 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::db as dblib;
-use coordinator::TransactionWithMetadata;
+use crossbeam::{RecvTimeoutError, Sender};
+use crossbeam_channel as crossbeam;
 use kvdb::{DBTransaction, KeyValueDB};
 use primitives::H256;
-use rlp::Encodable;
+use rlp::{Decodable, Encodable};
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
 const PREFIX_SIZE: usize = 5;
 const PREFIX_ITEM: &[u8; PREFIX_SIZE] = b"item_";
 
+/// When the changes to the mem pool are written to the DB.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MemPoolFlush {
+    /// Every batch of changes is written before the call making it returns.
+    Sync,
+    /// The changes are accumulated and written at the given interval.
+    /// On a crash, the changes made within the last interval are lost.
+    Periodic(Duration),
+    /// The changes are written in the background as they are made.
+    /// On a crash, the changes not yet written are lost.
+    /// All of them are written before shutting down.
+    WriteBehind,
+}
+
+impl Default for MemPoolFlush {
+    fn default() -> Self {
+        MemPoolFlush::Sync
+    }
+}
+
+enum Command {
+    Write(DBTransaction),
+    Flush(Sender<()>),
+}
+
+/// Writes the backup of the mem pool to the DB as configured by [`MemPoolFlush`].
+/// The items are written in RLP, so the backup is not bound to the transactions of the mem pool.
+///
+/// [`MemPoolFlush`]: ./enum.MemPoolFlush.html
+pub struct Backup {
+    db: Arc<dyn KeyValueDB>,
+    /// The queue of the background writer. It is `None` for `MemPoolFlush::Sync`.
+    queue: Option<Sender<Command>>,
+    writer: Option<JoinHandle<()>>,
+}
+
+impl Backup {
+    pub fn new(db: Arc<dyn KeyValueDB>, flush: MemPoolFlush) -> Self {
+        let (queue, writer) = match flush {
+            MemPoolFlush::Sync => (None, None),
+            MemPoolFlush::Periodic(interval) => {
+                let db = Arc::clone(&db);
+                let (queue, commands) = crossbeam::unbounded();
+                let writer = thread::Builder::new()
+                    .name("mem_pool_backup".to_string())
+                    .spawn(move || {
+                        let mut pending = DBTransaction::new();
+                        let mut next_flush = Instant::now() + interval;
+                        loop {
+                            let timeout = next_flush.saturating_duration_since(Instant::now());
+                            match commands.recv_timeout(timeout) {
+                                Ok(Command::Write(batch)) => pending.ops.extend(batch.ops),
+                                Ok(Command::Flush(done)) => {
+                                    write(db.as_ref(), &mut pending);
+                                    let _ = done.send(());
+                                }
+                                Err(RecvTimeoutError::Timeout) => {
+                                    write(db.as_ref(), &mut pending);
+                                    next_flush = Instant::now() + interval;
+                                }
+                                Err(RecvTimeoutError::Disconnected) => {
+                                    write(db.as_ref(), &mut pending);
+                                    break
+                                }
+                            }
+                        }
+                    })
+                    .unwrap();
+                (Some(queue), Some(writer))
+            }
+            MemPoolFlush::WriteBehind => {
+                let db = Arc::clone(&db);
+                let (queue, commands) = crossbeam::unbounded();
+                let writer = thread::Builder::new()
+                    .name("mem_pool_backup".to_string())
+                    .spawn(move || {
+                        for command in commands {
+                            match command {
+                                Command::Write(mut batch) => write(db.as_ref(), &mut batch),
+                                Command::Flush(done) => {
+                                    let _ = done.send(());
+                                }
+                            }
+                        }
+                    })
+                    .unwrap();
+                (Some(queue), Some(writer))
+            }
+        };
+        Self {
+            db,
+            queue,
+            writer,
+        }
+    }
+
+    pub fn write(&self, batch: DBTransaction) {
+        match &self.queue {
+            Some(queue) => queue.send(Command::Write(batch)).expect("The backup writer never stops before the backup"),
+            None => self.db.write(batch).expect("Low level database error. Some issue with disk?"),
+        }
+    }
+
+    /// Blocks until all the changes written so far are in the DB.
+    pub fn flush(&self) {
+        if let Some(queue) = &self.queue {
+            let (done, wait) = crossbeam::bounded(1);
+            queue.send(Command::Flush(done)).expect("The backup writer never stops before the backup");
+            wait.recv().expect("The backup writer replies to every flush");
+        }
+    }
+
+    pub fn recover<T: Decodable>(&self) -> HashMap<H256, T> {
+        self.flush();
+        recover_to_data(self.db.as_ref())
+    }
+}
+
+impl Drop for Backup {
+    fn drop(&mut self) {
+        // The writer writes everything queued before it stops.
+        self.queue.take();
+        if let Some(writer) = self.writer.take() {
+            writer.join().expect("The backup writer doesn't panic");
+        }
+    }
+}
+
+fn write(db: &dyn KeyValueDB, batch: &mut DBTransaction) {
+    if batch.ops.is_empty() {
+        return
+    }
+    let batch = std::mem::replace(batch, DBTransaction::new());
+    db.write(batch).expect("Low level database error. Some issue with disk?");
+}
+
 pub fn backup_batch_with_capacity(length: usize) -> DBTransaction {
     DBTransaction::with_capacity(length)
 }
 
-pub fn backup_item(batch: &mut DBTransaction, key: H256, item: &TransactionWithMetadata) {
+pub fn backup_item<T: Encodable>(batch: &mut DBTransaction, key: H256, item: &T) {
     let mut db_key = PREFIX_ITEM.to_vec();
     db_key.extend_from_slice(key.as_ref());
     batch.put(dblib::COL_MEMPOOL, db_key.as_ref(), item.rlp_bytes().as_ref());
@@ -40,7 +180,7 @@ pub fn remove_item(batch: &mut DBTransaction, key: &H256) {
     batch.delete(dblib::COL_MEMPOOL, db_key.as_ref());
 }
 
-pub fn recover_to_data(db: &dyn KeyValueDB) -> HashMap<H256, TransactionWithMetadata> {
+pub fn recover_to_data<T: Decodable>(db: &dyn KeyValueDB) -> HashMap<H256, T> {
     let mut by_hash = HashMap::new();
 
     for (key, value) in db.iter(dblib::COL_MEMPOOL) {
@@ -0,0 +1,154 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// How a transaction type is throttled and how many of its external transactions came.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IngressStats {
+    pub tx_type: String,
+    /// The transactions admitted per second.
+    pub limit: u32,
+    pub admitted: u64,
+    pub dropped: u64,
+}
+
+/// A token bucket holding up to a second worth of transactions.
+struct Bucket {
+    limit: u32,
+    tokens: f64,
+    refilled_at: Instant,
+    admitted: u64,
+    dropped: u64,
+}
+
+impl Bucket {
+    fn new(limit: u32, now: Instant) -> Self {
+        Self {
+            limit,
+            tokens: f64::from(limit),
+            refilled_at: now,
+            admitted: 0,
+            dropped: 0,
+        }
+    }
+
+    fn admit(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * f64::from(self.limit)).min(f64::from(self.limit));
+        self.refilled_at = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            self.admitted += 1;
+            true
+        } else {
+            self.dropped += 1;
+            false
+        }
+    }
+}
+
+/// Throttles the external transactions of each type before they reach the mem pool.
+/// The types without a limit, and the transactions of this node, are never throttled.
+pub struct IngressLimits {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl IngressLimits {
+    pub fn new(limits: &HashMap<String, u32>) -> Self {
+        let now = Instant::now();
+        Self {
+            buckets: Mutex::new(
+                limits.iter().map(|(tx_type, limit)| (tx_type.clone(), Bucket::new(*limit, now))).collect(),
+            ),
+        }
+    }
+
+    pub fn admit(&self, tx_type: &str, now: Instant) -> bool {
+        match self.buckets.lock().get_mut(tx_type) {
+            Some(bucket) => bucket.admit(now),
+            None => true,
+        }
+    }
+
+    pub fn stats(&self) -> Vec<IngressStats> {
+        let mut stats: Vec<_> = self
+            .buckets
+            .lock()
+            .iter()
+            .map(|(tx_type, bucket)| IngressStats {
+                tx_type: tx_type.clone(),
+                limit: bucket.limit,
+                admitted: bucket.admitted,
+                dropped: bucket.dropped,
+            })
+            .collect();
+        stats.sort_by(|a, b| a.tx_type.cmp(&b.tx_type));
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn limits(limits: &[(&str, u32)]) -> IngressLimits {
+        IngressLimits::new(&limits.iter().map(|(tx_type, limit)| (tx_type.to_string(), *limit)).collect())
+    }
+
+    #[test]
+    fn transactions_over_the_limit_are_dropped() {
+        let now = Instant::now();
+        let limits = limits(&[("stamp", 2)]);
+
+        assert!(limits.admit("stamp", now));
+        assert!(limits.admit("stamp", now));
+        assert!(!limits.admit("stamp", now));
+        assert!(limits.admit("token", now));
+
+        assert_eq!(limits.stats(), vec![IngressStats {
+            tx_type: "stamp".to_string(),
+            limit: 2,
+            admitted: 2,
+            dropped: 1,
+        }]);
+    }
+
+    #[test]
+    fn bucket_refills_over_time() {
+        let now = Instant::now();
+        let limits = limits(&[("stamp", 10)]);
+        for _ in 0..10 {
+            assert!(limits.admit("stamp", now));
+        }
+        assert!(!limits.admit("stamp", now));
+
+        let later = now + Duration::from_millis(250);
+        assert!(limits.admit("stamp", later));
+        assert!(limits.admit("stamp", later));
+        assert!(!limits.admit("stamp", later + Duration::from_millis(20)));
+
+        let much_later = now + Duration::from_secs(60);
+        for _ in 0..10 {
+            assert!(limits.admit("stamp", much_later));
+        }
+        assert!(!limits.admit("stamp", much_later));
+    }
+}
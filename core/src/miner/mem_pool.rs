@@ -14,25 +14,37 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::backup;
-use super::mem_pool_types::TransactionPool;
+use super::backup::{self, Backup, MemPoolFlush};
+use super::mem_pool_types::{PoolPage, PoolQuery, TransactionPool};
+use crate::error::{Categorized, ErrorCategory};
+use crate::metrics::MEM_POOL_EVICTIONS;
 use crate::transaction::PendingTransactions;
 use crate::Error as CoreError;
 use coordinator::context::StorageAccess;
 use coordinator::engine::TxFilter;
-use coordinator::types::{ErrorCode, FilteredTxs};
-use coordinator::{Transaction, TransactionWithMetadata, TxOrigin};
+use coordinator::types::{ErrorCode, FilteredTxs, InspectedPayload, VetoedTxs};
+use coordinator::{Transaction, TransactionWithMetadata, TxOrigin, TxSizes};
 use ctypes::errors::{HistoryError, SyntaxError};
 use ctypes::{BlockNumber, TxHash};
 use kvdb::{DBTransaction, KeyValueDB};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::ops::Range;
 use std::sync::Arc;
 
+/// The number of included transactions whose origins are remembered for a reorganization
+const INCLUDED_ORIGINS_LIMIT: usize = 4096;
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Error {
     History(HistoryError),
     Syntax(SyntaxError),
     App(ErrorCode),
+    /// A peer or an RPC client sent a system transaction, which only the node itself may issue.
+    ForeignSystemTransaction,
+    /// The transaction can't be included in the next block, since its replay tag expired.
+    Expired {
+        expires_at: BlockNumber,
+    },
 }
 
 impl Error {
@@ -43,6 +55,26 @@ impl Error {
             Error::App(err_code) => {
                 CoreError::Other(format!("Rejected by check_transaction with error code: {}", err_code))
             }
+            Error::ForeignSystemTransaction => {
+                CoreError::Other("A system transaction not issued by the node itself".to_string())
+            }
+            Error::Expired {
+                expires_at,
+            } => CoreError::Other(format!("The transaction expired at block {}", expires_at)),
+        }
+    }
+}
+
+impl Categorized for Error {
+    fn category(&self) -> ErrorCategory {
+        match self {
+            Error::History(err) => err.category(),
+            Error::Syntax(_)
+            | Error::App(_)
+            | Error::ForeignSystemTransaction
+            | Error::Expired {
+                ..
+            } => ErrorCategory::InvalidInput,
         }
     }
 }
@@ -59,19 +91,56 @@ impl From<SyntaxError> for Error {
     }
 }
 
+/// How much of its limits the mem pool uses.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemPoolUsage {
+    pub count: usize,
+    pub count_limit: usize,
+    pub mem_usage: usize,
+    pub memory_limit: usize,
+}
+
+/// What happened to the transactions that were invalid against the state of the best block.
+/// Such a transaction is checked again at the following blocks before it is finally dropped,
+/// because the state may be behind for a while, e.g. right after a reorganization.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OrphanStats {
+    /// The transactions waiting to be checked again
+    pub pending: usize,
+    /// The transactions kept aside so far
+    pub orphaned: u64,
+    /// The transactions that became valid again and went back into the pool
+    pub recovered: u64,
+    /// The transactions dropped because they were still invalid at their last block
+    pub expired: u64,
+}
+
 pub struct MemPool {
     /// Coordinator used for checking incoming transactions and fetching transactions
     tx_filter: Arc<dyn TxFilter>,
     /// list of all transactions in the pool
     transaction_pool: TransactionPool,
+    /// Transactions waiting for their activation block before they enter the pool
+    scheduled: HashMap<TxHash, TransactionWithMetadata>,
     /// The count(number) limit of each queue
     queue_count_limit: usize,
     /// The memory limit of each queue
     queue_memory_limit: usize,
     /// Next id that should be assigned to a transaction imported to the pool
     next_transaction_id: u64,
-    /// Arc of KeyValueDB in which the backup information is stored.
-    db: Arc<dyn KeyValueDB>,
+    /// Writes the backup information to the DB.
+    backup: Backup,
+    /// The era of the common params the transactions in the pool were checked under
+    params_era: u64,
+    /// The origins of the transactions recently removed because they were included in a block
+    included_origins: HashMap<TxHash, TxOrigin>,
+    /// The hashes in `included_origins` from the oldest
+    included_order: VecDeque<TxHash>,
+    /// Transactions invalid against the state of the best block, with the last block they are checked at
+    orphans: HashMap<TxHash, (TransactionWithMetadata, BlockNumber)>,
+    /// How many blocks an orphan is checked again for
+    orphan_retry_blocks: BlockNumber,
+    orphan_stats: OrphanStats,
 }
 
 impl MemPool {
@@ -80,23 +149,43 @@ impl MemPool {
         limit: usize,
         memory_limit: usize,
         db: Arc<dyn KeyValueDB>,
+        flush: MemPoolFlush,
         tx_filter: Arc<dyn TxFilter>,
     ) -> Self {
         MemPool {
             tx_filter,
             transaction_pool: TransactionPool::new(),
+            scheduled: HashMap::new(),
             queue_count_limit: limit,
             queue_memory_limit: memory_limit,
             next_transaction_id: 0,
-            db,
+            backup: Backup::new(db, flush),
+            params_era: 0,
+            included_origins: HashMap::new(),
+            included_order: VecDeque::new(),
+            orphans: HashMap::new(),
+            orphan_retry_blocks: 0,
+            orphan_stats: OrphanStats::default(),
         }
     }
 
+    /// Puts the transaction in the pool with what the sorter reads from it, by which the pool is queried.
+    fn insert_pending(&mut self, tx: TransactionWithMetadata) {
+        let metadata = self.tx_filter.sorting_metadata(&tx.tx);
+        self.transaction_pool.insert(tx, metadata);
+    }
+
     /// Set the new limit for the `current` queue.
     pub fn set_limit(&mut self, limit: usize) {
         self.queue_count_limit = limit;
     }
 
+    /// Set how many blocks a transaction invalid against the state is kept to be checked again.
+    /// With zero, such a transaction is dropped at once.
+    pub fn set_orphan_retry_blocks(&mut self, blocks: BlockNumber) {
+        self.orphan_retry_blocks = blocks;
+    }
+
     /// Enforce the limit to the current queue
     fn enforce_limit(&mut self, state: &mut dyn StorageAccess, batch: &mut DBTransaction) {
         let to_drop = if self.transaction_pool.mem_usage > self.queue_memory_limit
@@ -119,6 +208,33 @@ impl MemPool {
         for hash in to_drop {
             backup::remove_item(batch, &hash);
             self.transaction_pool.remove(&hash);
+            MEM_POOL_EVICTIONS.inc();
+        }
+        self.enforce_scheduled_limit(batch);
+    }
+
+    /// Drops the scheduled transactions beyond what the pending ones leave of the limits, the ones activating
+    /// the latest first. Like in the pool, the local transactions are not counted.
+    fn enforce_scheduled_limit(&mut self, batch: &mut DBTransaction) {
+        let count_limit = self.queue_count_limit.saturating_sub(self.transaction_pool.count);
+        let memory_limit = self.queue_memory_limit.saturating_sub(self.transaction_pool.mem_usage);
+        let mut external: Vec<_> = self.scheduled.values().filter(|tx| !tx.origin.is_local()).collect();
+        external.sort_by_key(|tx| (tx.activation_block, tx.insertion_id));
+
+        let (mut count, mut mem_usage) = (0, 0);
+        let to_drop: Vec<TxHash> = external
+            .into_iter()
+            .filter(|tx| {
+                count += 1;
+                mem_usage += tx.size();
+                count > count_limit || mem_usage > memory_limit
+            })
+            .map(|tx| tx.hash())
+            .collect();
+        for hash in to_drop {
+            backup::remove_item(batch, &hash);
+            self.scheduled.remove(&hash);
+            MEM_POOL_EVICTIONS.inc();
         }
     }
 
@@ -132,6 +248,30 @@ impl MemPool {
         self.transaction_pool.len()
     }
 
+    pub fn orphan_stats(&self) -> OrphanStats {
+        OrphanStats {
+            pending: self.orphans.len(),
+            ..self.orphan_stats
+        }
+    }
+
+    pub fn transaction_sizes(&self, tx: &Transaction) -> TxSizes {
+        self.tx_filter.transaction_sizes(tx)
+    }
+
+    pub fn inspect_transaction(&self, tx: &Transaction) -> Option<Result<InspectedPayload, String>> {
+        self.tx_filter.inspect_transaction(tx)
+    }
+
+    pub fn usage(&self) -> MemPoolUsage {
+        MemPoolUsage {
+            count: self.transaction_pool.count,
+            count_limit: self.queue_count_limit,
+            mem_usage: self.transaction_pool.mem_usage,
+            memory_limit: self.queue_memory_limit,
+        }
+    }
+
     /// Add signed transaction to pool to be verified and imported.
     ///
     /// NOTE details_provider methods should be cheap to compute
@@ -145,35 +285,41 @@ impl MemPool {
         inserted_timestamp: u64,
     ) -> Vec<Result<(), Error>> {
         ctrace!(MEM_POOL, "add() called, time: {}, timestamp: {}", inserted_block_number, inserted_timestamp);
+        let span = tracing::debug_span!("mem_pool_batch", operation = "add", count = transactions.len());
+        let _entered = span.enter();
         let mut insert_results = Vec::with_capacity(transactions.len());
         let mut batch = backup::backup_batch_with_capacity(transactions.len());
 
         for tx in transactions {
-            match self.tx_filter.check_transaction(&tx) {
+            match self.check_transaction(&tx, origin).and_then(|()| self.check_expiry(&tx, inserted_block_number)) {
                 Ok(()) => {
                     let id = self.next_transaction_id;
                     self.next_transaction_id += 1;
 
                     let hash = tx.hash();
-                    let tx = TransactionWithMetadata::new(tx, origin, inserted_block_number, inserted_timestamp, id);
-                    if self.transaction_pool.contains(&hash) {
+                    let tx =
+                        TransactionWithMetadata::new(tx, origin, inserted_block_number, inserted_timestamp, id, None);
+                    if self.transaction_pool.contains(&hash)
+                        || self.scheduled.contains_key(&hash)
+                        || self.orphans.contains_key(&hash)
+                    {
                         // This transaction is already in the pool.
                         insert_results.push(Err(HistoryError::TransactionAlreadyImported.into()));
                     } else {
                         backup::backup_item(&mut batch, *tx.hash(), &tx);
-                        self.transaction_pool.insert(tx);
+                        self.insert_pending(tx);
                         insert_results.push(Ok(hash));
                     }
                 }
-                Err(err_code) => {
+                Err(err) => {
                     // This transaction is invalid.
-                    insert_results.push(Err(Error::App(err_code)));
+                    insert_results.push(Err(err));
                 }
             }
         }
         self.enforce_limit(state, &mut batch);
 
-        self.db.write(batch).expect("Low level database error. Some issue with disk?");
+        self.backup.write(batch);
         insert_results
             .into_iter()
             .map(|v| {
@@ -187,14 +333,183 @@ impl MemPool {
             .collect()
     }
 
+    /// Checks the transaction with its owner module.
+    /// Only the node itself can issue system transactions, which are neither ordered by fee nor need one.
+    fn check_transaction(&self, tx: &Transaction, origin: TxOrigin) -> Result<(), Error> {
+        if origin != TxOrigin::System && self.tx_filter.is_system_transaction(tx) {
+            return Err(Error::ForeignSystemTransaction)
+        }
+        self.tx_filter.check_transaction(tx).map_err(Error::App)
+    }
+
+    /// Rejects the transaction whose replay tag expires before the block following `best_block_number`.
+    fn check_expiry(&self, tx: &Transaction, best_block_number: BlockNumber) -> Result<(), Error> {
+        match self.tx_filter.replay_tag(tx) {
+            Some(tag) if tag.expires_at <= best_block_number => Err(Error::Expired {
+                expires_at: tag.expires_at,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Keeps the transaction aside until the chain reaches `activation_block`.
+    pub fn schedule(
+        &mut self,
+        tx: Transaction,
+        origin: TxOrigin,
+        activation_block: BlockNumber,
+        inserted_block_number: BlockNumber,
+        inserted_timestamp: u64,
+    ) -> Result<(), Error> {
+        ctrace!(MEM_POOL, "schedule() called, activation block: {}", activation_block);
+        self.check_transaction(&tx, origin)?;
+        self.check_expiry(&tx, inserted_block_number)?;
+        let hash = tx.hash();
+        if self.transaction_pool.contains(&hash)
+            || self.scheduled.contains_key(&hash)
+            || self.orphans.contains_key(&hash)
+        {
+            return Err(HistoryError::TransactionAlreadyImported.into())
+        }
+
+        let id = self.next_transaction_id;
+        self.next_transaction_id += 1;
+        let tx = TransactionWithMetadata::new(
+            tx,
+            origin,
+            inserted_block_number,
+            inserted_timestamp,
+            id,
+            Some(activation_block),
+        );
+        let mut batch = backup::backup_batch_with_capacity(1);
+        backup::backup_item(&mut batch, *hash, &tx);
+        self.scheduled.insert(hash, tx);
+        self.enforce_scheduled_limit(&mut batch);
+        self.backup.write(batch);
+        if self.scheduled.contains_key(&hash) {
+            Ok(())
+        } else {
+            Err(HistoryError::LimitReached.into())
+        }
+    }
+
+    /// Moves the scheduled transactions that can be included in the next block into the pool.
+    /// They are checked again because the chain may have changed since they were scheduled.
+    pub fn promote_scheduled(
+        &mut self,
+        state: &mut dyn StorageAccess,
+        current_block_number: BlockNumber,
+        current_timestamp: u64,
+    ) {
+        let activated: Vec<TxHash> = self
+            .scheduled
+            .values()
+            .filter(|tx| {
+                tx.activation_block.map_or(true, |activation_block| activation_block <= current_block_number + 1)
+            })
+            .map(|tx| tx.hash())
+            .collect();
+        if activated.is_empty() {
+            return
+        }
+        ctrace!(MEM_POOL, "promote_scheduled() called, time: {}, count: {}", current_block_number, activated.len());
+
+        let mut batch = backup::backup_batch_with_capacity(activated.len());
+        for hash in activated {
+            let mut tx = self.scheduled.remove(&hash).expect("The hash is collected from the scheduled transactions");
+            if self.tx_filter.check_transaction(&tx.tx).is_err() {
+                backup::remove_item(&mut batch, &hash);
+                continue
+            }
+            tx.activation_block = None;
+            tx.inserted_block_number = current_block_number;
+            tx.inserted_timestamp = current_timestamp;
+            backup::backup_item(&mut batch, *hash, &tx);
+            self.insert_pending(tx);
+        }
+        self.enforce_limit(state, &mut batch);
+
+        self.backup.write(batch);
+    }
+
+    /// Returns the transactions waiting for their activation block.
+    pub fn scheduled_transactions(&self) -> impl Iterator<Item = &TransactionWithMetadata> {
+        self.scheduled.values()
+    }
+
+    /// Checks whether the transaction is in the pool, including the scheduled ones.
+    pub fn contains(&self, hash: &TxHash) -> bool {
+        self.transaction_pool.contains(hash) || self.scheduled.contains_key(hash)
+    }
+
+    /// Returns all the transactions in the pool, including the scheduled ones.
+    pub fn dump(&self) -> Vec<TransactionWithMetadata> {
+        self.transaction_pool.pool.values().chain(self.scheduled.values()).cloned().collect()
+    }
+
+    /// Puts the dumped transactions back with their metadata, checking them as `add` and `schedule` do.
+    /// They keep their order but get new insertion ids, which don't collide with the ones of this pool.
+    /// This is for reproducing the pool of another node while debugging. Returns the loaded transactions.
+    pub fn load(
+        &mut self,
+        mut transactions: Vec<TransactionWithMetadata>,
+        state: &mut dyn StorageAccess,
+        best_block_number: BlockNumber,
+    ) -> Vec<TxHash> {
+        ctrace!(MEM_POOL, "load() called, count: {}", transactions.len());
+        transactions.sort_by_key(|tx| tx.insertion_id);
+        let mut batch = backup::backup_batch_with_capacity(transactions.len());
+        let mut loaded = Vec::with_capacity(transactions.len());
+        for mut tx in transactions {
+            let hash = tx.hash();
+            if let Err(err) =
+                self.check_transaction(&tx.tx, tx.origin).and_then(|()| self.check_expiry(&tx.tx, best_block_number))
+            {
+                cdebug!(MEM_POOL, "Cannot load the transaction {}: {:?}", hash, err);
+                continue
+            }
+            if self.contains(&hash) || self.orphans.contains_key(&hash) {
+                continue
+            }
+            tx.insertion_id = self.next_transaction_id;
+            self.next_transaction_id += 1;
+            backup::backup_item(&mut batch, *hash, &tx);
+            if tx.activation_block.is_some() {
+                self.scheduled.insert(hash, tx);
+            } else {
+                self.insert_pending(tx);
+            }
+            loaded.push(hash);
+        }
+        self.enforce_limit(state, &mut batch);
+        self.enforce_scheduled_limit(&mut batch);
+
+        self.backup.write(batch);
+        loaded.retain(|hash| self.contains(hash));
+        loaded
+    }
+
     /// Clear current queue.
     pub fn remove_all(&mut self) {
+        let mut batch = backup::backup_batch_with_capacity(self.transaction_pool.len() + self.scheduled.len());
+        for hash in self.transaction_pool.pool.keys().chain(self.scheduled.keys()) {
+            backup::remove_item(&mut batch, hash);
+        }
+        self.backup.write(batch);
         self.transaction_pool.clear();
+        self.scheduled.clear();
+        self.orphans.clear();
+    }
+
+    /// Blocks until the changes to the pool are written to the DB.
+    pub fn flush(&self) {
+        self.backup.flush();
     }
 
     // Recover MemPool state from db stored data
     pub fn recover_from_db(&mut self) {
-        let by_hash = backup::recover_to_data(self.db.as_ref());
+        let by_hash: HashMap<_, TransactionWithMetadata> = self.backup.recover();
 
         let mut max_insertion_id = 0u64;
         for (_hash, item) in by_hash {
@@ -202,7 +517,11 @@ impl MemPool {
                 max_insertion_id = item.insertion_id;
             }
 
-            self.transaction_pool.insert(item);
+            if item.activation_block.is_some() {
+                self.scheduled.insert(item.hash(), item);
+            } else {
+                self.insert_pending(item);
+            }
         }
 
         self.next_transaction_id = max_insertion_id + 1;
@@ -212,6 +531,11 @@ impl MemPool {
         self.transaction_pool.pool.values()
     }
 
+    /// Returns the pending transactions submitted to this node, not including the scheduled ones.
+    pub fn local_transactions(&self) -> Vec<TransactionWithMetadata> {
+        self.transaction_pool.pool.values().filter(|item| item.origin == TxOrigin::Local).cloned().collect()
+    }
+
     /// Removes invalid transaction identified by hash from pool.
     /// Assumption is that this transaction seq is not related to client seq,
     /// so transactions left in pool are processed according to client seq.
@@ -220,12 +544,130 @@ impl MemPool {
         let mut batch = backup::backup_batch_with_capacity(transaction_hashes.len());
 
         for hash in transaction_hashes {
-            if self.transaction_pool.remove(hash) {
+            let origin = self.transaction_pool.pool.get(hash).or_else(|| self.scheduled.get(hash)).map(|tx| tx.origin);
+            if self.transaction_pool.remove(hash) || self.scheduled.remove(hash).is_some() {
                 backup::remove_item(&mut batch, hash);
             }
+            let origin = origin.or_else(|| self.orphans.remove(hash).map(|(tx, _)| tx.origin));
+            if let Some(origin) = origin {
+                self.remember_origin(*hash, origin);
+            }
+        }
+
+        self.backup.write(batch);
+    }
+
+    fn remember_origin(&mut self, hash: TxHash, origin: TxOrigin) {
+        if self.included_origins.insert(hash, origin).is_some() {
+            return
+        }
+        self.included_order.push_back(hash);
+        if self.included_order.len() > INCLUDED_ORIGINS_LIMIT {
+            let oldest = self.included_order.pop_front().expect("The queue is not empty");
+            self.included_origins.remove(&oldest);
+        }
+    }
+
+    /// Takes the transaction invalid against the state out of the pool, to check it again at the following blocks.
+    /// Returns false if the transaction is dropped instead.
+    fn orphan(&mut self, hash: &TxHash, current_block_number: BlockNumber, batch: &mut DBTransaction) -> bool {
+        backup::remove_item(batch, hash);
+        let tx = match self.transaction_pool.pool.get(hash) {
+            Some(tx) => tx.clone(),
+            None => return false,
+        };
+        self.transaction_pool.remove(hash);
+        if self.orphan_retry_blocks == 0 {
+            return false
+        }
+        self.orphans.insert(*hash, (tx, current_block_number + self.orphan_retry_blocks));
+        self.orphan_stats.orphaned += 1;
+        true
+    }
+
+    /// Puts the transactions of the blocks retracted by a reorganization back into the pool.
+    /// Each transaction keeps the origin it had when it was removed from the pool,
+    /// and is checked again against the state of the new best block.
+    /// The ones invalid against the state become orphans, since the state may not have caught up yet.
+    /// Returns the number of transactions dropped because they are no longer valid.
+    pub fn reinject(
+        &mut self,
+        transactions: Vec<Transaction>,
+        state: &mut dyn StorageAccess,
+        current_block_number: BlockNumber,
+        current_timestamp: u64,
+    ) -> usize {
+        ctrace!(MEM_POOL, "reinject() called, time: {}, count: {}", current_block_number, transactions.len());
+        let span = tracing::debug_span!("mem_pool_batch", operation = "reinject", count = transactions.len());
+        let _entered = span.enter();
+        let mut by_origin: HashMap<TxOrigin, Vec<Transaction>> = HashMap::new();
+        for tx in transactions {
+            let origin = self.included_origins.get(&tx.hash()).copied().unwrap_or(TxOrigin::External);
+            by_origin.entry(origin).or_default().push(tx);
+        }
+
+        let mut dropped = 0;
+        let mut reinjected = Vec::new();
+        for &origin in &[TxOrigin::Local, TxOrigin::System, TxOrigin::External] {
+            let transactions = by_origin.remove(&origin).unwrap_or_default();
+            let hashes: Vec<_> = transactions.iter().map(|tx| tx.hash()).collect();
+            let results = self.add(transactions, origin, state, current_block_number, current_timestamp);
+            for (hash, result) in hashes.into_iter().zip(results) {
+                match result {
+                    Ok(()) => reinjected.push(hash),
+                    Err(Error::History(HistoryError::TransactionAlreadyImported)) => {}
+                    Err(_) => dropped += 1,
+                }
+            }
         }
 
-        self.db.write(batch).expect("Low level database error. Some issue with disk?");
+        let invalid: Vec<TxHash> = {
+            let transactions: Vec<_> =
+                reinjected.iter().filter_map(|hash| self.transaction_pool.pool.get(hash)).collect();
+            let FilteredTxs {
+                invalid,
+                ..
+            } = self.tx_filter.filter_transactions(state, &mut transactions.into_iter(), None, None);
+            invalid.into_iter().map(|tx| tx.hash()).collect()
+        };
+        let mut batch = backup::backup_batch_with_capacity(invalid.len());
+        for hash in invalid {
+            if !self.orphan(&hash, current_block_number, &mut batch) {
+                dropped += 1;
+            }
+        }
+        self.backup.write(batch);
+
+        dropped
+    }
+
+    /// Drops the pending transactions, the orphans included, that their modules veto in the given state.
+    /// Returns the number of the dropped transactions.
+    pub fn remove_vetoed(&mut self, state: &mut dyn StorageAccess) -> usize {
+        let span = tracing::debug_span!(
+            "mem_pool_batch",
+            operation = "remove_vetoed",
+            count = self.transaction_pool.len() + self.orphans.len()
+        );
+        let _entered = span.enter();
+        let dropped: Vec<TxHash> = {
+            let transactions: Vec<_> =
+                self.transaction_pool.pool.values().chain(self.orphans.values().map(|(tx, _)| tx)).collect();
+            let VetoedTxs {
+                dropped,
+                ..
+            } = self.tx_filter.remove_transactions(state, &mut transactions.into_iter());
+            dropped.into_iter().map(|tx| tx.hash()).collect()
+        };
+
+        let mut batch = backup::backup_batch_with_capacity(dropped.len());
+        for hash in &dropped {
+            backup::remove_item(&mut batch, hash);
+            self.transaction_pool.remove(hash);
+            self.orphans.remove(hash);
+        }
+        self.backup.write(batch);
+        dropped.len()
     }
 
     pub fn remove_old(
@@ -235,34 +677,102 @@ impl MemPool {
         current_timestamp: u64,
     ) {
         ctrace!(MEM_POOL, "remove_old() called, time: {}, timestamp: {}", current_block_number, current_timestamp);
+        let span =
+            tracing::debug_span!("mem_pool_batch", operation = "remove_old", count = self.transaction_pool.len());
+        let _entered = span.enter();
         let mut batch = backup::backup_batch_with_capacity(0);
-        let to_be_removed: Vec<TxHash> = {
-            let transactions: Vec<_> = self.transaction_pool.pool.values().collect();
+
+        let expired: Vec<TxHash> = self
+            .transaction_pool
+            .pool
+            .values()
+            .chain(self.orphans.values().map(|(tx, _)| tx))
+            .filter(|tx| self.check_expiry(&tx.tx, current_block_number).is_err())
+            .map(|tx| tx.hash())
+            .collect();
+        for hash in &expired {
+            backup::remove_item(&mut batch, hash);
+            self.transaction_pool.remove(hash);
+            self.orphans.remove(hash);
+        }
+
+        let (invalid, low_priority): (HashSet<TxHash>, Vec<TxHash>) = {
+            let transactions: Vec<_> =
+                self.transaction_pool.pool.values().chain(self.orphans.values().map(|(tx, _)| tx)).collect();
             let FilteredTxs {
                 invalid,
                 low_priority,
             } = self.tx_filter.filter_transactions(state, &mut transactions.into_iter(), None, None);
-            invalid.into_iter().map(|tx| tx.hash()).chain(low_priority.into_iter().map(|tx| tx.hash())).collect()
+            (invalid.into_iter().map(|tx| tx.hash()).collect(), low_priority.into_iter().map(|tx| tx.hash()).collect())
         };
-        // TODO: mark invalid transactions
-        for hash in to_be_removed {
+        for hash in &low_priority {
+            backup::remove_item(&mut batch, hash);
+            self.transaction_pool.remove(hash);
+            self.orphans.remove(hash);
+        }
+
+        let orphans: Vec<TxHash> = self.orphans.keys().cloned().collect();
+        for hash in orphans {
+            if invalid.contains(&hash) {
+                if self.orphans[&hash].1 <= current_block_number {
+                    self.orphans.remove(&hash);
+                    self.orphan_stats.expired += 1;
+                }
+                continue
+            }
+            let (tx, _) = self.orphans.remove(&hash).expect("The hash is collected from the orphans");
+            backup::backup_item(&mut batch, *hash, &tx);
+            self.insert_pending(tx);
+            self.orphan_stats.recovered += 1;
+        }
+        for hash in invalid {
+            if self.transaction_pool.contains(&hash) {
+                self.orphan(&hash, current_block_number, &mut batch);
+            }
+        }
+
+        self.backup.write(batch)
+    }
+
+    /// Checks the transactions in the pool again when the common params change.
+    /// The transaction filter may depend on the params, e.g. the minimum fee of a transaction type,
+    /// so a transaction accepted under the old params can be invalid under the new ones.
+    pub fn update_params_era(&mut self, era: u64) {
+        if self.params_era == era {
+            return
+        }
+        ctrace!(MEM_POOL, "update_params_era() called, era: {} -> {}", self.params_era, era);
+        self.params_era = era;
+
+        let tx_filter = &self.tx_filter;
+        let invalid: Vec<TxHash> = self
+            .transaction_pool
+            .pool
+            .values()
+            .filter(|tx| tx_filter.check_transaction(&tx.tx).is_err())
+            .map(|tx| tx.hash())
+            .collect();
+        let mut batch = backup::backup_batch_with_capacity(invalid.len());
+        for hash in invalid {
             backup::remove_item(&mut batch, &hash);
             self.transaction_pool.remove(&hash);
         }
 
-        self.db.write(batch).expect("Low level database error. Some issue with disk?")
+        self.backup.write(batch)
     }
 
     /// Returns top transactions whose timestamp are in the given range from the pool ordered by priority.
+    /// The transactions in the priority lane of the `TxSorter` come first.
     // FIXME: current_timestamp should be `u64`, not `Option<u64>`.
     // FIXME: if range_contains becomes stable, use range.contains instead of inequality.
     pub fn pending_transactions(&self, size_limit: usize, range: Range<u64>) -> PendingTransactions {
+        let mut candidates: Vec<_> =
+            self.transaction_pool.pool.values().filter(|item| range.contains(&item.inserted_timestamp)).collect();
+        candidates.sort_by_key(|item| !self.tx_filter.is_priority_transaction(&item.tx));
+
         let mut current_size: usize = 0;
-        let items: Vec<_> = self
-            .transaction_pool
-            .pool
-            .values()
-            .filter(|item| range.contains(&item.inserted_timestamp))
+        let items: Vec<_> = candidates
+            .into_iter()
             .take_while(|item| {
                 let encoded_byte_array = rlp::encode(&item.tx);
                 let size_in_byte = encoded_byte_array.len();
@@ -279,20 +789,132 @@ impl MemPool {
         }
     }
 
+    /// Returns the hashes of the pending transactions that can be propagated, in no particular order.
+    /// The transactions the node issued for itself are not propagated.
+    pub fn propagatable_hashes(&self) -> Vec<TxHash> {
+        self.transaction_pool.pool.values().filter(|tx| tx.origin != TxOrigin::System).map(|tx| tx.hash()).collect()
+    }
+
+    /// Returns the given transactions to propagate, from the one a block would take first.
+    /// Only the given ones are ordered, so the cost doesn't grow with the pool.
+    /// The ones no longer pending or not propagatable are left out.
+    pub fn prioritized_transactions(&self, state: &mut dyn StorageAccess, hashes: &[TxHash]) -> Vec<Transaction> {
+        let mut transactions = hashes
+            .iter()
+            .filter_map(|hash| self.transaction_pool.pool.get(hash))
+            .filter(|tx| tx.origin != TxOrigin::System);
+        self.tx_filter.prioritize_transactions(state, &mut transactions).into_iter().cloned().collect()
+    }
+
+    /// Returns a page of the pending transactions matching the query.
+    /// It walks the indices of the pool from the cursor, so only the listed transactions are cloned.
+    pub fn query_pending_transactions(&self, query: &PoolQuery) -> PoolPage {
+        let mut matching = self
+            .transaction_pool
+            .iter_sorted(query)
+            .filter(|(_, item)| query.origin.map_or(true, |origin| item.origin == origin))
+            .filter(|(_, item)| query.inserted.contains(&item.inserted_timestamp));
+        let mut transactions = Vec::new();
+        let mut last = None;
+        for (cursor, item) in matching.by_ref().take(query.limit) {
+            transactions.push(item.clone());
+            last = Some(cursor);
+        }
+        let next = if transactions.len() == query.limit && matching.next().is_some() {
+            last
+        } else {
+            None
+        };
+        PoolPage {
+            transactions,
+            next,
+        }
+    }
+
     /// Return all transactions whose timestamp are in the given range in the memory pool.
     pub fn count_pending_transactions(&self, range: Range<u64>) -> usize {
         self.transaction_pool.pool.values().filter(|t| range.contains(&t.inserted_timestamp)).count()
     }
+
+    /// Checks that the queues, their counters, the remembered origins and the backup agree with one another.
+    /// The backup is flushed first, so that it can be compared with the pool.
+    #[cfg(test)]
+    pub(super) fn check_invariants(&self) -> Result<(), String> {
+        for (hash, tx) in &self.transaction_pool.pool {
+            if *hash != tx.hash() || tx.activation_block.is_some() {
+                return Err(format!("The pending transaction {:?} is kept as {:?}", tx.hash(), hash))
+            }
+        }
+        for (hash, tx) in &self.scheduled {
+            if *hash != tx.hash() || tx.activation_block.is_none() {
+                return Err(format!("The scheduled transaction {:?} is kept as {:?}", tx.hash(), hash))
+            }
+        }
+        for (hash, (tx, _)) in &self.orphans {
+            if *hash != tx.hash() {
+                return Err(format!("The orphan {:?} is kept as {:?}", tx.hash(), hash))
+            }
+            if self.transaction_pool.contains(hash) || self.scheduled.contains_key(hash) {
+                return Err(format!("The orphan {:?} is also in the pool", hash))
+            }
+        }
+        if let Some(hash) = self.scheduled.keys().find(|hash| self.transaction_pool.contains(hash)) {
+            return Err(format!("The scheduled transaction {:?} is also pending", hash))
+        }
+        self.transaction_pool.check_indices()?;
+
+        let external = self.transaction_pool.pool.values().filter(|tx| !tx.origin.is_local());
+        let (count, mem_usage) = external.fold((0, 0), |(count, mem_usage), tx| (count + 1, mem_usage + tx.size()));
+        if (count, mem_usage) != (self.transaction_pool.count, self.transaction_pool.mem_usage) {
+            return Err(format!(
+                "The pool counts {} transactions of {} bytes, but has {} of {} bytes",
+                self.transaction_pool.count, self.transaction_pool.mem_usage, count, mem_usage
+            ))
+        }
+
+        let mut all = self.dump().into_iter().chain(self.orphans.values().map(|(tx, _)| tx.clone()));
+        if let Some(tx) = all.find(|tx| tx.insertion_id >= self.next_transaction_id) {
+            return Err(format!("{:?} has the insertion id {} not yet assigned", tx.hash(), tx.insertion_id))
+        }
+
+        let order: HashSet<_> = self.included_order.iter().collect();
+        if order.len() != self.included_order.len()
+            || order.len() != self.included_origins.len()
+            || order.len() > INCLUDED_ORIGINS_LIMIT
+            || !order.iter().all(|hash| self.included_origins.contains_key(hash))
+        {
+            return Err("The remembered origins don't match their order".to_string())
+        }
+
+        self.backup.flush();
+        let backed_up: HashMap<_, TransactionWithMetadata> = self.backup.recover();
+        let kept: HashMap<_, _> = self.dump().into_iter().map(|tx| (*tx.hash(), tx)).collect();
+        if backed_up != kept {
+            return Err(format!("The backup has {} transactions, but the pool has {}", backed_up.len(), kept.len()))
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 pub mod test {
-    use crate::miner::mem_pool::MemPool;
+    use crate::miner::backup::MemPoolFlush;
+    use crate::miner::mem_pool::{Error, MemPool, OrphanStats};
+    use crate::miner::mem_pool_types::{PoolOrder, PoolPage, PoolQuery, PoolSort};
+    use ckey::Ed25519Public as Public;
     use coordinator::context::{StorageAccess, SubStorageAccess};
+    use coordinator::engine::TxFilter;
+    use coordinator::module::TxSortingMetadata;
+    use coordinator::replay::ReplayTag;
     use coordinator::test_coordinator::TestCoordinator;
-    use coordinator::{Transaction, TxOrigin};
+    use coordinator::types::{ErrorCode, FilteredTxs, VetoedTxs};
+    use coordinator::{Transaction, TransactionWithMetadata, TxOrigin};
+    use kvdb::KeyValueDB;
     use rand::Rng;
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
 
     fn create_random_transaction() -> Transaction {
         //FIXME: change this random to be reproducible
@@ -305,7 +927,7 @@ pub mod test {
     fn remove_all() {
         let validator = Arc::new(TestCoordinator::default());
         let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
-        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), db, validator);
+        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), db, MemPoolFlush::Sync, validator);
 
         let inserted_block_number = 1;
         let inserted_timestamp = 100;
@@ -329,7 +951,7 @@ pub mod test {
     fn add_and_remove_transactions() {
         let validator = Arc::new(TestCoordinator::default());
         let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
-        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), db, validator);
+        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), db, MemPoolFlush::Sync, validator);
 
         let inserted_block_number = 1;
         let inserted_timestamp = 100;
@@ -358,11 +980,149 @@ pub mod test {
         assert_eq!(mem_pool.transaction_pool.mem_usage, mem_usage);
     }
 
+    #[test]
+    fn pending_transactions_are_queried_page_by_page() {
+        let validator = Arc::new(TestCoordinator::default());
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), db, MemPoolFlush::Sync, validator);
+        let mut state = DummyStorage;
+
+        let external: Vec<_> = (0..5).map(|_| create_random_transaction()).collect();
+        let add_result = mem_pool.add(external.clone(), TxOrigin::External, &mut state, 1, 100);
+        assert!(add_result.iter().all(|r| r.is_ok()));
+        let add_result = mem_pool.add(vec![create_random_transaction()], TxOrigin::Local, &mut state, 2, 200);
+        assert!(add_result.iter().all(|r| r.is_ok()));
+
+        let mut query = PoolQuery {
+            tx_type: Some("Sample".to_string()),
+            origin: Some(TxOrigin::External),
+            signer: None,
+            inserted: 0..u64::MAX,
+            fee: None,
+            seq: None,
+            sort: PoolSort::Insertion,
+            order: PoolOrder::Descending,
+            after: None,
+            limit: 2,
+        };
+        let mut listed = Vec::new();
+        loop {
+            let page = mem_pool.query_pending_transactions(&query);
+            assert!(page.transactions.len() <= 2);
+            listed.extend(page.transactions.into_iter().map(|item| item.tx));
+            match page.next {
+                Some(cursor) => query.after = Some(cursor),
+                None => break,
+            }
+        }
+        assert_eq!(external.into_iter().rev().collect::<Vec<_>>(), listed);
+
+        query.after = None;
+        query.inserted = 0..150;
+        query.origin = Some(TxOrigin::Local);
+        assert_eq!(None, mem_pool.query_pending_transactions(&query).next);
+        assert!(mem_pool.query_pending_transactions(&query).transactions.is_empty());
+        mem_pool.check_invariants().unwrap();
+    }
+
+    /// Reads the signer, the fee and the sequence from the first three bytes of the body.
+    /// A "free" transaction has no fee.
+    struct SortingFilter;
+
+    impl TxFilter for SortingFilter {
+        fn check_transaction(&self, _transaction: &Transaction) -> Result<(), ErrorCode> {
+            Ok(())
+        }
+
+        fn is_system_transaction(&self, _transaction: &Transaction) -> bool {
+            false
+        }
+
+        fn filter_transactions<'a>(
+            &self,
+            _storage: &mut dyn StorageAccess,
+            _transactions: &mut dyn Iterator<Item = &'a TransactionWithMetadata>,
+            _memory_limit: Option<usize>,
+            _size_limit: Option<usize>,
+        ) -> FilteredTxs<'a> {
+            FilteredTxs {
+                invalid: Vec::new(),
+                low_priority: Vec::new(),
+            }
+        }
+
+        fn sorting_metadata(&self, transaction: &Transaction) -> TxSortingMetadata {
+            let body = transaction.body();
+            TxSortingMetadata {
+                signer: Some(Public::from(u64::from(body[0]))),
+                fee: Some(u64::from(body[1])).filter(|_| transaction.tx_type() != "free"),
+                seq: Some(u64::from(body[2])),
+            }
+        }
+    }
+
+    #[test]
+    fn pending_transactions_are_queried_by_their_metadata() {
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool =
+            MemPool::with_limits(8192, usize::max_value(), db.clone(), MemPoolFlush::Sync, Arc::new(SortingFilter));
+        let mut state = DummyStorage;
+
+        // The signer, the fee and the sequence of each transaction.
+        let transactions: Vec<_> = [(1, 30, 2), (2, 10, 0), (1, 20, 0), (1, 40, 1), (2, 50, 1)]
+            .iter()
+            .map(|&(signer, fee, seq)| Transaction::new("Sample".to_string(), vec![signer, fee, seq]))
+            .chain(std::iter::once(Transaction::new("free".to_string(), vec![2, 0, 2])))
+            .collect();
+        let add_result = mem_pool.add(transactions.clone(), TxOrigin::External, &mut state, 1, 100);
+        assert!(add_result.iter().all(|r| r.is_ok()));
+
+        let mut query = PoolQuery {
+            tx_type: None,
+            origin: None,
+            signer: Some(Public::from(1)),
+            inserted: 0..u64::MAX,
+            fee: None,
+            seq: None,
+            sort: PoolSort::Seq,
+            order: PoolOrder::Ascending,
+            after: None,
+            limit: 2,
+        };
+        let listed = |page: &PoolPage| page.transactions.iter().map(|item| item.tx.clone()).collect::<Vec<_>>();
+        let first = mem_pool.query_pending_transactions(&query);
+        assert_eq!(vec![transactions[2].clone(), transactions[3].clone()], listed(&first));
+        query.after = first.next;
+        let rest = mem_pool.query_pending_transactions(&query);
+        assert_eq!(vec![transactions[0].clone()], listed(&rest));
+        assert_eq!(None, rest.next);
+
+        // The free transaction has no fee, so it comes first from the lowest and matches no range of the fees.
+        query.signer = None;
+        query.sort = PoolSort::Fee;
+        query.after = None;
+        query.limit = 10;
+        assert_eq!(transactions[5], listed(&mem_pool.query_pending_transactions(&query))[0]);
+        query.order = PoolOrder::Descending;
+        query.fee = Some(20..50);
+        let expected = vec![transactions[3].clone(), transactions[0].clone(), transactions[2].clone()];
+        assert_eq!(expected, listed(&mem_pool.query_pending_transactions(&query)));
+        mem_pool.check_invariants().unwrap();
+
+        // The metadata is read again from the recovered transactions.
+        let mut recovered =
+            MemPool::with_limits(8192, usize::max_value(), db, MemPoolFlush::Sync, Arc::new(SortingFilter));
+        recovered.recover_from_db();
+        assert_eq!(recovered.transaction_pool, mem_pool.transaction_pool);
+        recovered.check_invariants().unwrap();
+    }
+
     #[test]
     fn db_backup_and_recover() {
         let validator = Arc::new(TestCoordinator::default());
         let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
-        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), db.clone(), validator.clone());
+        let mut mem_pool =
+            MemPool::with_limits(8192, usize::max_value(), db.clone(), MemPoolFlush::Sync, validator.clone());
 
         let inserted_block_number = 1;
         let inserted_timestamp = 100;
@@ -383,7 +1143,7 @@ pub mod test {
         let add_result = mem_pool.add(transactions, origin, &mut state, inserted_block_number, inserted_timestamp);
         assert!(add_result.iter().all(|r| r.is_ok()));
 
-        let mut mem_pool_recovered = MemPool::with_limits(8192, usize::max_value(), db, validator);
+        let mut mem_pool_recovered = MemPool::with_limits(8192, usize::max_value(), db, MemPoolFlush::Sync, validator);
         mem_pool_recovered.recover_from_db();
 
         assert_eq!(mem_pool_recovered.transaction_pool, mem_pool.transaction_pool);
@@ -392,7 +1152,543 @@ pub mod test {
         assert_eq!(mem_pool_recovered.next_transaction_id, mem_pool.next_transaction_id);
     }
 
-    struct DummyStorage;
+    fn add_random_transactions(mem_pool: &mut MemPool, count: usize) {
+        let transactions: Vec<_> = (0..count).map(|_| create_random_transaction()).collect();
+        let add_result = mem_pool.add(transactions, TxOrigin::External, &mut DummyStorage, 1, 100);
+        assert!(add_result.iter().all(|r| r.is_ok()));
+    }
+
+    /// Kills the `mem_pool` without flushing anything, and returns how many transactions are recovered.
+    fn recovered_after_crash(mem_pool: MemPool, db: Arc<dyn KeyValueDB>) -> usize {
+        std::mem::forget(mem_pool);
+        let validator = Arc::new(TestCoordinator::default());
+        let mut recovered = MemPool::with_limits(8192, usize::max_value(), db, MemPoolFlush::Sync, validator);
+        recovered.recover_from_db();
+        recovered.transaction_pool.len()
+    }
+
+    #[test]
+    fn sync_flush_loses_nothing_on_crash() {
+        let validator = Arc::new(TestCoordinator::default());
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), db.clone(), MemPoolFlush::Sync, validator);
+
+        for _ in 0..10 {
+            add_random_transactions(&mut mem_pool, 10);
+        }
+
+        assert_eq!(recovered_after_crash(mem_pool, db), 100);
+    }
+
+    #[test]
+    fn periodic_flush_loses_changes_within_the_interval_on_crash() {
+        let validator = Arc::new(TestCoordinator::default());
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let flush = MemPoolFlush::Periodic(Duration::from_secs(3600));
+        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), db.clone(), flush, validator);
+
+        for _ in 0..10 {
+            add_random_transactions(&mut mem_pool, 10);
+        }
+
+        assert_eq!(recovered_after_crash(mem_pool, db), 0);
+    }
+
+    #[test]
+    fn periodic_flush_keeps_changes_older_than_the_interval_on_crash() {
+        let validator = Arc::new(TestCoordinator::default());
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let flush = MemPoolFlush::Periodic(Duration::from_millis(50));
+        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), db.clone(), flush, validator);
+
+        for _ in 0..10 {
+            add_random_transactions(&mut mem_pool, 10);
+        }
+        thread::sleep(Duration::from_millis(500));
+
+        assert_eq!(recovered_after_crash(mem_pool, db), 100);
+    }
+
+    #[test]
+    fn write_behind_flush_keeps_flushed_changes_on_crash() {
+        let validator = Arc::new(TestCoordinator::default());
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool =
+            MemPool::with_limits(8192, usize::max_value(), db.clone(), MemPoolFlush::WriteBehind, validator);
+
+        for _ in 0..10 {
+            add_random_transactions(&mut mem_pool, 10);
+        }
+        mem_pool.flush();
+
+        assert_eq!(recovered_after_crash(mem_pool, db), 100);
+    }
+
+    #[test]
+    fn shutdown_writes_all_changes() {
+        for flush in &[MemPoolFlush::Periodic(Duration::from_secs(3600)), MemPoolFlush::WriteBehind] {
+            let validator = Arc::new(TestCoordinator::default());
+            let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+            let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), db.clone(), *flush, validator.clone());
+
+            for _ in 0..10 {
+                add_random_transactions(&mut mem_pool, 10);
+            }
+            let hashes: Vec<_> = mem_pool.transaction_pool.pool.keys().cloned().collect();
+            mem_pool.remove(&hashes[..50], 1, 100);
+            drop(mem_pool);
+
+            let mut recovered = MemPool::with_limits(8192, usize::max_value(), db, MemPoolFlush::Sync, validator);
+            recovered.recover_from_db();
+            assert_eq!(recovered.transaction_pool.len(), 50, "{:?}", flush);
+        }
+    }
+
+    #[test]
+    fn scheduled_transaction_enters_the_pool_at_activation_block() {
+        let validator = Arc::new(TestCoordinator::default());
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool =
+            MemPool::with_limits(8192, usize::max_value(), db.clone(), MemPoolFlush::Sync, validator.clone());
+
+        let tx = create_random_transaction();
+        mem_pool.schedule(tx.clone(), TxOrigin::Local, 10, 1, 100).unwrap();
+        assert!(!mem_pool.transaction_pool.contains(&tx.hash()));
+        assert_eq!(1, mem_pool.scheduled_transactions().count());
+
+        let mut mem_pool_recovered = MemPool::with_limits(8192, usize::max_value(), db, MemPoolFlush::Sync, validator);
+        mem_pool_recovered.recover_from_db();
+        assert_eq!(mem_pool_recovered.scheduled, mem_pool.scheduled);
+
+        let mut state = DummyStorage;
+        mem_pool.promote_scheduled(&mut state, 8, 180);
+        assert!(!mem_pool.transaction_pool.contains(&tx.hash()));
+
+        mem_pool.promote_scheduled(&mut state, 9, 190);
+        assert!(mem_pool.transaction_pool.contains(&tx.hash()));
+        assert_eq!(0, mem_pool.scheduled_transactions().count());
+    }
+
+    #[test]
+    fn scheduled_transactions_share_the_limits_of_the_pool() {
+        let validator = Arc::new(TestCoordinator::default());
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool =
+            MemPool::with_limits(4, usize::max_value(), db.clone(), MemPoolFlush::Sync, validator.clone());
+        let mut state = DummyStorage;
+        let pending: Vec<_> = (0..3).map(|_| create_random_transaction()).collect();
+        assert!(mem_pool.add(pending, TxOrigin::External, &mut state, 1, 100).iter().all(|r| r.is_ok()));
+
+        let early = create_random_transaction();
+        mem_pool.schedule(early.clone(), TxOrigin::External, 10, 1, 100).unwrap();
+        assert!(mem_pool.schedule(create_random_transaction(), TxOrigin::External, 20, 1, 100).is_err());
+
+        // The transaction activating the latest makes room for the one activating earlier.
+        let earlier = create_random_transaction();
+        mem_pool.schedule(earlier.clone(), TxOrigin::External, 5, 1, 100).unwrap();
+        assert!(!mem_pool.contains(&early.hash()));
+        assert!(mem_pool.contains(&earlier.hash()));
+
+        // The local transactions are not counted.
+        mem_pool.schedule(create_random_transaction(), TxOrigin::Local, 20, 1, 100).unwrap();
+        assert_eq!(2, mem_pool.scheduled_transactions().count());
+        mem_pool.check_invariants().unwrap();
+
+        let mut recovered = MemPool::with_limits(4, usize::max_value(), db, MemPoolFlush::Sync, validator);
+        recovered.recover_from_db();
+        assert_eq!(recovered.scheduled, mem_pool.scheduled);
+    }
+
+    #[test]
+    fn dumped_transactions_are_loaded() {
+        let validator = Arc::new(TestCoordinator::default());
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), db, MemPoolFlush::Sync, validator.clone());
+
+        let transactions: Vec<_> = (0..5).map(|_| create_random_transaction()).collect();
+        let mut state = DummyStorage;
+        let add_result = mem_pool.add(transactions, TxOrigin::External, &mut state, 1, 100);
+        assert!(add_result.iter().all(|r| r.is_ok()));
+        mem_pool.schedule(create_random_transaction(), TxOrigin::Local, 10, 1, 100).unwrap();
+
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut loaded = MemPool::with_limits(8192, usize::max_value(), db, MemPoolFlush::Sync, validator);
+        assert_eq!(6, loaded.load(mem_pool.dump(), &mut state, 1).len());
+
+        assert_eq!(loaded.transaction_pool, mem_pool.transaction_pool);
+        assert_eq!(loaded.scheduled, mem_pool.scheduled);
+        assert_eq!(loaded.next_transaction_id, mem_pool.next_transaction_id);
+    }
+
+    #[test]
+    fn loaded_transactions_are_checked_and_get_new_ids() {
+        let validator = Arc::new(TestCoordinator::default());
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), db, MemPoolFlush::Sync, validator);
+        let large = Transaction::new("Sample".to_string(), vec![0; 100]);
+        let small = Transaction::new("Sample".to_string(), vec![0]);
+        let mut state = DummyStorage;
+        let add_result = mem_pool.add(vec![large.clone(), small.clone()], TxOrigin::External, &mut state, 1, 100);
+        assert!(add_result.iter().all(|r| r.is_ok()));
+
+        let filter = Arc::new(MinSizeFilter(AtomicUsize::new(50)));
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut loaded = MemPool::with_limits(8192, usize::max_value(), db, MemPoolFlush::Sync, filter);
+        let existing = create_random_transaction();
+        let add_result = loaded.add(vec![existing], TxOrigin::External, &mut state, 1, 100);
+        assert!(add_result.iter().all(|r| r.is_ok()));
+
+        assert_eq!(vec![large.hash()], loaded.load(mem_pool.dump(), &mut state, 1));
+        assert!(!loaded.contains(&small.hash()));
+        assert_eq!(1, loaded.transaction_pool.pool[&large.hash()].insertion_id);
+    }
+
+    #[test]
+    fn transactions_are_checked_again_when_params_change() {
+        let filter = Arc::new(MinSizeFilter(AtomicUsize::new(0)));
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool = MemPool::with_limits(
+            8192,
+            usize::max_value(),
+            db,
+            MemPoolFlush::Sync,
+            Arc::clone(&filter) as Arc<dyn TxFilter>,
+        );
+
+        let small = Transaction::new("Sample".to_string(), vec![0; 10]);
+        let large = Transaction::new("Sample".to_string(), vec![0; 100]);
+        let mut state = DummyStorage;
+        let add_result = mem_pool.add(vec![small.clone(), large.clone()], TxOrigin::External, &mut state, 1, 100);
+        assert!(add_result.iter().all(|r| r.is_ok()));
+
+        filter.0.store(50, Ordering::SeqCst);
+        mem_pool.update_params_era(0);
+        assert!(mem_pool.transaction_pool.contains(&small.hash()));
+
+        mem_pool.update_params_era(1);
+        assert!(!mem_pool.transaction_pool.contains(&small.hash()));
+        assert!(mem_pool.transaction_pool.contains(&large.hash()));
+    }
+
+    #[test]
+    fn retracted_transactions_are_reinjected_with_their_origins() {
+        let filter = Arc::new(MinSizeFilter(AtomicUsize::new(0)));
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool = MemPool::with_limits(
+            8192,
+            usize::max_value(),
+            db,
+            MemPoolFlush::Sync,
+            Arc::clone(&filter) as Arc<dyn TxFilter>,
+        );
+
+        let small = Transaction::new("Sample".to_string(), vec![0; 10]);
+        let large = Transaction::new("Sample".to_string(), vec![0; 100]);
+        let external = Transaction::new("Sample".to_string(), vec![1; 100]);
+        let mut state = DummyStorage;
+        let add_result = mem_pool.add(vec![small.clone(), large.clone()], TxOrigin::Local, &mut state, 1, 100);
+        assert!(add_result.iter().all(|r| r.is_ok()));
+        mem_pool.remove(&[small.hash(), large.hash()], 2, 110);
+        assert_eq!(mem_pool.num_pending_transactions(), 0);
+
+        filter.0.store(50, Ordering::SeqCst);
+        let dropped = mem_pool.reinject(vec![small.clone(), large.clone(), external.clone()], &mut state, 2, 120);
+        assert_eq!(dropped, 1);
+        assert!(!mem_pool.transaction_pool.contains(&small.hash()));
+        assert_eq!(mem_pool.transaction_pool.pool[&large.hash()].origin, TxOrigin::Local);
+        assert_eq!(mem_pool.transaction_pool.pool[&external.hash()].origin, TxOrigin::External);
+    }
+
+    #[test]
+    fn system_transactions_are_accepted_only_from_the_node_itself() {
+        let filter = Arc::new(MinSizeFilter(AtomicUsize::new(0)));
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool =
+            MemPool::with_limits(8192, usize::max_value(), db, MemPoolFlush::Sync, filter as Arc<dyn TxFilter>);
+
+        let from_peer = Transaction::new("system".to_string(), vec![1]);
+        let from_rpc = Transaction::new("system".to_string(), vec![2]);
+        let own = Transaction::new("system".to_string(), vec![3]);
+        let add_result = mem_pool.add(vec![from_peer.clone()], TxOrigin::External, &mut DummyStorage, 1, 100);
+        assert_eq!(add_result, vec![Err(Error::ForeignSystemTransaction)]);
+        assert_eq!(
+            mem_pool.schedule(from_peer.clone(), TxOrigin::External, 10, 1, 100),
+            Err(Error::ForeignSystemTransaction)
+        );
+        let add_result = mem_pool.add(vec![from_rpc.clone()], TxOrigin::Local, &mut DummyStorage, 1, 100);
+        assert_eq!(add_result, vec![Err(Error::ForeignSystemTransaction)]);
+        let add_result = mem_pool.add(vec![own.clone()], TxOrigin::System, &mut DummyStorage, 1, 100);
+        assert_eq!(add_result, vec![Ok(())]);
+
+        assert!(!mem_pool.transaction_pool.contains(&from_peer.hash()));
+        assert!(!mem_pool.transaction_pool.contains(&from_rpc.hash()));
+        assert!(mem_pool.transaction_pool.contains(&own.hash()));
+        assert!(mem_pool.propagatable_hashes().is_empty());
+        assert!(mem_pool.prioritized_transactions(&mut DummyStorage, &[own.hash()]).is_empty());
+    }
+
+    #[test]
+    fn transactions_invalid_against_the_state_are_checked_again() {
+        let filter = Arc::new(StaleStateFilter(AtomicUsize::new(0)));
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool = MemPool::with_limits(
+            8192,
+            usize::max_value(),
+            db,
+            MemPoolFlush::Sync,
+            Arc::clone(&filter) as Arc<dyn TxFilter>,
+        );
+        mem_pool.set_orphan_retry_blocks(2);
+
+        let recovering = Transaction::new("Sample".to_string(), vec![0; 20]);
+        let expiring = Transaction::new("Sample".to_string(), vec![0; 10]);
+        let mut state = DummyStorage;
+        let add_result =
+            mem_pool.add(vec![recovering.clone(), expiring.clone()], TxOrigin::External, &mut state, 1, 100);
+        assert!(add_result.iter().all(|r| r.is_ok()));
+
+        filter.0.store(50, Ordering::SeqCst);
+        mem_pool.remove_old(&mut state, 2, 110);
+        assert_eq!(mem_pool.num_pending_transactions(), 0);
+        assert_eq!(mem_pool.orphan_stats().pending, 2);
+
+        filter.0.store(25, Ordering::SeqCst);
+        mem_pool.remove_old(&mut state, 3, 120);
+        assert!(mem_pool.transaction_pool.contains(&recovering.hash()));
+        assert!(!mem_pool.transaction_pool.contains(&expiring.hash()));
+
+        filter.0.store(50, Ordering::SeqCst);
+        mem_pool.remove_old(&mut state, 4, 130);
+        assert_eq!(mem_pool.orphan_stats(), OrphanStats {
+            pending: 1,
+            orphaned: 3,
+            recovered: 1,
+            expired: 1,
+        });
+        assert_eq!(mem_pool.num_pending_transactions(), 0);
+    }
+
+    /// Accepts every transaction, and puts the ones of the type "critical" in the priority lane.
+    struct PriorityFilter;
+
+    impl TxFilter for PriorityFilter {
+        fn check_transaction(&self, _transaction: &Transaction) -> Result<(), ErrorCode> {
+            Ok(())
+        }
+
+        fn is_system_transaction(&self, _transaction: &Transaction) -> bool {
+            false
+        }
+
+        fn is_priority_transaction(&self, transaction: &Transaction) -> bool {
+            transaction.tx_type() == "critical"
+        }
+
+        fn filter_transactions<'a>(
+            &self,
+            _storage: &mut dyn StorageAccess,
+            _transactions: &mut dyn Iterator<Item = &'a TransactionWithMetadata>,
+            _memory_limit: Option<usize>,
+            _size_limit: Option<usize>,
+        ) -> FilteredTxs<'a> {
+            FilteredTxs {
+                invalid: Vec::new(),
+                low_priority: Vec::new(),
+            }
+        }
+    }
+
+    #[test]
+    fn priority_transactions_are_pending_first() {
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool =
+            MemPool::with_limits(8192, usize::max_value(), db, MemPoolFlush::Sync, Arc::new(PriorityFilter));
+        let mut state = DummyStorage;
+
+        let ordinary: Vec<_> = (0..5).map(|_| create_random_transaction()).collect();
+        let critical: Vec<_> = (0..2u8).map(|i| Transaction::new("critical".to_string(), vec![i; 30])).collect();
+        let add_result = mem_pool.add(ordinary, TxOrigin::External, &mut state, 1, 100);
+        assert!(add_result.iter().all(|r| r.is_ok()));
+        let add_result = mem_pool.add(critical.clone(), TxOrigin::External, &mut state, 1, 100);
+        assert!(add_result.iter().all(|r| r.is_ok()));
+
+        let pending = mem_pool.pending_transactions(usize::max_value(), 0..u64::MAX).transactions;
+        assert_eq!(pending.len(), 7);
+        assert!(pending[..2].iter().all(|tx| critical.contains(tx)));
+
+        // Only the priority lane fits within a size limit a little larger than it.
+        let lane_size: usize = critical.iter().map(|tx| rlp::encode(tx).len()).sum();
+        let pending = mem_pool.pending_transactions(lane_size + 1, 0..u64::MAX).transactions;
+        assert_eq!(pending.len(), 2);
+        assert!(pending.iter().all(|tx| critical.contains(tx)));
+    }
+
+    #[test]
+    fn expired_transactions_are_rejected_and_dropped() {
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool =
+            MemPool::with_limits(8192, usize::max_value(), db, MemPoolFlush::Sync, Arc::new(ExpiringFilter));
+
+        let expired = Transaction::new("Sample".to_string(), vec![0; 10]);
+        let expiring = Transaction::new("Sample".to_string(), vec![0; 12]);
+        let lasting = Transaction::new("Sample".to_string(), vec![0; 20]);
+        let mut state = DummyStorage;
+        let add_result =
+            mem_pool.add(vec![expired, expiring.clone(), lasting.clone()], TxOrigin::External, &mut state, 10, 100);
+        assert_eq!(
+            add_result[0],
+            Err(Error::Expired {
+                expires_at: 10,
+            })
+        );
+        assert!(add_result[1..].iter().all(|r| r.is_ok()));
+
+        mem_pool.remove_old(&mut state, 12, 120);
+        assert!(!mem_pool.transaction_pool.contains(&expiring.hash()));
+        assert!(mem_pool.transaction_pool.contains(&lasting.hash()));
+    }
+
+    #[test]
+    fn vetoed_transactions_are_dropped_with_the_orphans() {
+        let filter = Arc::new(StaleStateFilter(AtomicUsize::new(0)));
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool = MemPool::with_limits(
+            8192,
+            usize::max_value(),
+            db,
+            MemPoolFlush::Sync,
+            Arc::clone(&filter) as Arc<dyn TxFilter>,
+        );
+        mem_pool.set_orphan_retry_blocks(2);
+
+        let orphan = Transaction::new("Sample".to_string(), vec![0; 10]);
+        let vetoed_orphan = Transaction::new("Sample".to_string(), vec![0; 30]);
+        let pending = Transaction::new("Sample".to_string(), vec![0; 20]);
+        let vetoed = Transaction::new("Sample".to_string(), vec![0; 40]);
+        let mut state = DummyStorage;
+        let add_result =
+            mem_pool.add(vec![orphan.clone(), vetoed_orphan.clone()], TxOrigin::External, &mut state, 1, 100);
+        assert!(add_result.iter().all(|r| r.is_ok()));
+        filter.0.store(50, Ordering::SeqCst);
+        mem_pool.remove_old(&mut state, 2, 110);
+        filter.0.store(0, Ordering::SeqCst);
+        let add_result = mem_pool.add(vec![pending.clone(), vetoed.clone()], TxOrigin::External, &mut state, 2, 110);
+        assert!(add_result.iter().all(|r| r.is_ok()));
+        assert_eq!(mem_pool.orphan_stats().pending, 2);
+
+        assert_eq!(mem_pool.remove_vetoed(&mut state), 2);
+        assert!(mem_pool.transaction_pool.contains(&pending.hash()));
+        assert!(!mem_pool.transaction_pool.contains(&vetoed.hash()));
+        assert!(mem_pool.orphans.contains_key(&orphan.hash()));
+        assert!(!mem_pool.orphans.contains_key(&vetoed_orphan.hash()));
+        mem_pool.check_invariants().unwrap();
+    }
+
+    /// Rejects the transactions smaller than the minimum size
+    struct MinSizeFilter(AtomicUsize);
+
+    impl TxFilter for MinSizeFilter {
+        fn check_transaction(&self, transaction: &Transaction) -> Result<(), ErrorCode> {
+            if transaction.size() < self.0.load(Ordering::SeqCst) {
+                Err(1)
+            } else {
+                Ok(())
+            }
+        }
+
+        fn is_system_transaction(&self, transaction: &Transaction) -> bool {
+            transaction.tx_type() == "system"
+        }
+
+        fn filter_transactions<'a>(
+            &self,
+            _storage: &mut dyn StorageAccess,
+            _transactions: &mut dyn Iterator<Item = &'a TransactionWithMetadata>,
+            _memory_limit: Option<usize>,
+            _size_limit: Option<usize>,
+        ) -> FilteredTxs<'a> {
+            FilteredTxs {
+                invalid: Vec::new(),
+                low_priority: Vec::new(),
+            }
+        }
+    }
+
+    /// Accepts every transaction, but finds the ones not larger than the given size invalid against the state.
+    /// The transactions with bodies longer than 25 bytes are vetoed.
+    struct StaleStateFilter(AtomicUsize);
+
+    impl TxFilter for StaleStateFilter {
+        fn check_transaction(&self, _transaction: &Transaction) -> Result<(), ErrorCode> {
+            Ok(())
+        }
+
+        fn is_system_transaction(&self, _transaction: &Transaction) -> bool {
+            false
+        }
+
+        fn filter_transactions<'a>(
+            &self,
+            _storage: &mut dyn StorageAccess,
+            transactions: &mut dyn Iterator<Item = &'a TransactionWithMetadata>,
+            _memory_limit: Option<usize>,
+            _size_limit: Option<usize>,
+        ) -> FilteredTxs<'a> {
+            let max_invalid_size = self.0.load(Ordering::SeqCst);
+            FilteredTxs {
+                invalid: transactions.filter(|tx| tx.size() <= max_invalid_size).collect(),
+                low_priority: Vec::new(),
+            }
+        }
+
+        fn remove_transactions<'a>(
+            &self,
+            _storage: &mut dyn StorageAccess,
+            transactions: &mut dyn Iterator<Item = &'a TransactionWithMetadata>,
+        ) -> VetoedTxs<'a> {
+            let (dropped, kept) = transactions.map(|tx| &tx.tx).partition(|tx| tx.body().len() > 25);
+            VetoedTxs {
+                kept,
+                dropped,
+            }
+        }
+    }
+
+    /// Protects every transaction with a replay tag expiring at the block numbered after its body length
+    struct ExpiringFilter;
+
+    impl TxFilter for ExpiringFilter {
+        fn check_transaction(&self, _transaction: &Transaction) -> Result<(), ErrorCode> {
+            Ok(())
+        }
+
+        fn is_system_transaction(&self, _transaction: &Transaction) -> bool {
+            false
+        }
+
+        fn replay_tag(&self, transaction: &Transaction) -> Option<ReplayTag> {
+            Some(ReplayTag {
+                signer: Vec::new(),
+                expires_at: transaction.body().len() as u64,
+                nonce: Default::default(),
+            })
+        }
+
+        fn filter_transactions<'a>(
+            &self,
+            _storage: &mut dyn StorageAccess,
+            _transactions: &mut dyn Iterator<Item = &'a TransactionWithMetadata>,
+            _memory_limit: Option<usize>,
+            _size_limit: Option<usize>,
+        ) -> FilteredTxs<'a> {
+            FilteredTxs {
+                invalid: Vec::new(),
+                low_priority: Vec::new(),
+            }
+        }
+    }
+
+    pub struct DummyStorage;
 
     impl StorageAccess for DummyStorage {
         fn sub_storage(&mut self, _storage_id: u16) -> Box<dyn SubStorageAccess> {
@@ -0,0 +1,368 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Drives the mem pool with random sequences of operations and checks its invariants after every step.
+//!
+//! A failing sequence is shrunk to the operations the failure needs before it is reported with its seed.
+//! Set `MEM_POOL_FUZZ_SEED` to replay a single seed.
+
+use super::backup::MemPoolFlush;
+use super::mem_pool::test::DummyStorage;
+use super::mem_pool::MemPool;
+use coordinator::context::StorageAccess;
+use coordinator::engine::TxFilter;
+use coordinator::replay::ReplayTag;
+use coordinator::types::{ErrorCode, FilteredTxs};
+use coordinator::{Transaction, TransactionWithMetadata, TxOrigin};
+use ctypes::BlockNumber;
+use kvdb::KeyValueDB;
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use std::any::Any;
+use std::env;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// The number of distinct transactions the operations pick from
+const UNIVERSE: u8 = 24;
+const COUNT_LIMIT: usize = 12;
+const MEMORY_LIMIT: usize = 600;
+const RUNS: u64 = 64;
+const STEPS: usize = 150;
+
+#[derive(Clone, Debug)]
+enum Op {
+    Add {
+        txs: Vec<u8>,
+        local: bool,
+    },
+    Schedule {
+        tx: u8,
+        delay: BlockNumber,
+    },
+    Remove {
+        txs: Vec<u8>,
+    },
+    /// Moves to the next block, where the transactions not larger than `max_invalid_size` are invalid.
+    RemoveOld {
+        max_invalid_size: usize,
+    },
+    Reinject {
+        txs: Vec<u8>,
+    },
+    /// Changes the common params, under which the transactions smaller than `min_size` are rejected.
+    UpdateParams {
+        min_size: usize,
+    },
+    PromoteScheduled,
+    RemoveAll,
+    /// Recovers the pool from its backup, as the node does after a restart.
+    Restart,
+}
+
+/// Every seventh transaction is a system transaction, and every fifth one has a replay tag.
+fn transaction(index: u8) -> Transaction {
+    let tx_type = if index % 7 == 3 {
+        "system"
+    } else {
+        "Sample"
+    };
+    Transaction::new(tx_type.to_string(), vec![index; 10 + index as usize * 3])
+}
+
+#[derive(Default)]
+struct FuzzFilter {
+    min_size: AtomicUsize,
+    max_invalid_size: AtomicUsize,
+}
+
+impl TxFilter for FuzzFilter {
+    fn check_transaction(&self, transaction: &Transaction) -> Result<(), ErrorCode> {
+        if transaction.size() < self.min_size.load(Ordering::SeqCst) {
+            Err(1)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn is_system_transaction(&self, transaction: &Transaction) -> bool {
+        transaction.tx_type() == "system"
+    }
+
+    fn replay_tag(&self, transaction: &Transaction) -> Option<ReplayTag> {
+        let index = transaction.body()[0];
+        if index % 5 == 0 {
+            Some(ReplayTag {
+                signer: Vec::new(),
+                expires_at: 4 + BlockNumber::from(index) / 2,
+                nonce: Default::default(),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Keeps the smallest external transactions within the limits.
+    fn filter_transactions<'a>(
+        &self,
+        _storage: &mut dyn StorageAccess,
+        transactions: &mut dyn Iterator<Item = &'a TransactionWithMetadata>,
+        memory_limit: Option<usize>,
+        size_limit: Option<usize>,
+    ) -> FilteredTxs<'a> {
+        let max_invalid_size = self.max_invalid_size.load(Ordering::SeqCst);
+        let (invalid, mut valid): (Vec<_>, Vec<_>) = transactions.partition(|tx| tx.size() <= max_invalid_size);
+        // The pool iterates over a hash map, so the order is fixed here to make a run reproducible.
+        valid.sort_by_key(|tx| (tx.size(), *tx.hash()));
+
+        let (mut count, mut mem_usage) = (0, 0);
+        let mut low_priority = Vec::new();
+        for tx in valid.into_iter().filter(|tx| !tx.origin.is_local()) {
+            count += 1;
+            mem_usage += tx.size();
+            if size_limit.map_or(false, |limit| count > limit) || memory_limit.map_or(false, |limit| mem_usage > limit)
+            {
+                low_priority.push(tx);
+            }
+        }
+        FilteredTxs {
+            invalid,
+            low_priority,
+        }
+    }
+}
+
+struct Harness {
+    db: Arc<dyn KeyValueDB>,
+    filter: Arc<FuzzFilter>,
+    mem_pool: MemPool,
+    block_number: BlockNumber,
+    era: u64,
+}
+
+impl Harness {
+    fn new() -> Self {
+        let db: Arc<dyn KeyValueDB> = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let filter = Arc::new(FuzzFilter::default());
+        let mem_pool = Self::open_pool(&db, &filter);
+        Self {
+            db,
+            filter,
+            mem_pool,
+            block_number: 1,
+            era: 0,
+        }
+    }
+
+    fn open_pool(db: &Arc<dyn KeyValueDB>, filter: &Arc<FuzzFilter>) -> MemPool {
+        let mut mem_pool = MemPool::with_limits(
+            COUNT_LIMIT,
+            MEMORY_LIMIT,
+            Arc::clone(db),
+            MemPoolFlush::Sync,
+            Arc::clone(filter) as Arc<dyn TxFilter>,
+        );
+        mem_pool.set_orphan_retry_blocks(2);
+        mem_pool
+    }
+
+    fn apply(&mut self, op: &Op) -> Result<(), String> {
+        let mut state = DummyStorage;
+        let timestamp = self.block_number * 10;
+        let transactions = |txs: &[u8]| txs.iter().map(|&index| transaction(index)).collect::<Vec<_>>();
+        match op {
+            Op::Add {
+                txs,
+                local,
+            } => {
+                let origin = if *local {
+                    TxOrigin::Local
+                } else {
+                    TxOrigin::External
+                };
+                self.mem_pool.add(transactions(txs), origin, &mut state, self.block_number, timestamp);
+            }
+            Op::Schedule {
+                tx,
+                delay,
+            } => {
+                let activation_block = self.block_number + delay;
+                let _ = self.mem_pool.schedule(
+                    transaction(*tx),
+                    TxOrigin::Local,
+                    activation_block,
+                    self.block_number,
+                    timestamp,
+                );
+            }
+            Op::Remove {
+                txs,
+            } => {
+                let hashes: Vec<_> = transactions(txs).iter().map(Transaction::hash).collect();
+                self.mem_pool.remove(&hashes, self.block_number, timestamp);
+            }
+            Op::RemoveOld {
+                max_invalid_size,
+            } => {
+                self.block_number += 1;
+                self.filter.max_invalid_size.store(*max_invalid_size, Ordering::SeqCst);
+                self.mem_pool.remove_old(&mut state, self.block_number, self.block_number * 10);
+            }
+            Op::Reinject {
+                txs,
+            } => {
+                self.mem_pool.reinject(transactions(txs), &mut state, self.block_number, timestamp);
+            }
+            Op::UpdateParams {
+                min_size,
+            } => {
+                self.filter.min_size.store(*min_size, Ordering::SeqCst);
+                self.era += 1;
+                self.mem_pool.update_params_era(self.era);
+            }
+            Op::PromoteScheduled => self.mem_pool.promote_scheduled(&mut state, self.block_number, timestamp),
+            Op::RemoveAll => self.mem_pool.remove_all(),
+            Op::Restart => {
+                let mut recovered = Self::open_pool(&self.db, &self.filter);
+                recovered.recover_from_db();
+                if sorted_dump(&recovered) != sorted_dump(&self.mem_pool) {
+                    return Err("The recovered pool differs from the one before the restart".to_string())
+                }
+                self.mem_pool = recovered;
+            }
+        }
+        self.mem_pool.check_invariants()
+    }
+}
+
+fn sorted_dump(mem_pool: &MemPool) -> Vec<TransactionWithMetadata> {
+    let mut transactions = mem_pool.dump();
+    transactions.sort_by_key(|tx| *tx.hash());
+    transactions
+}
+
+fn rng(seed: u64) -> XorShiftRng {
+    let mut bytes = [0u8; 16];
+    bytes[..8].copy_from_slice(&seed.to_le_bytes());
+    // The xorshift generator needs a seed that is not all zeros.
+    bytes[8..].copy_from_slice(&(!seed).to_le_bytes());
+    XorShiftRng::from_seed(bytes)
+}
+
+fn random_txs(rng: &mut XorShiftRng) -> Vec<u8> {
+    let len = rng.gen_range(1, 6);
+    (0..len).map(|_| rng.gen_range(0, UNIVERSE)).collect()
+}
+
+fn random_op(rng: &mut XorShiftRng) -> Op {
+    match rng.gen_range(0, 20) {
+        0..=6 => Op::Add {
+            txs: random_txs(rng),
+            local: rng.gen_bool(0.3),
+        },
+        7..=8 => Op::Schedule {
+            tx: rng.gen_range(0, UNIVERSE),
+            delay: rng.gen_range(0, 4),
+        },
+        9..=10 => Op::Remove {
+            txs: random_txs(rng),
+        },
+        11..=13 => Op::RemoveOld {
+            max_invalid_size: if rng.gen_bool(0.5) {
+                0
+            } else {
+                rng.gen_range(0, 60)
+            },
+        },
+        14..=15 => Op::Reinject {
+            txs: random_txs(rng),
+        },
+        16 => Op::UpdateParams {
+            min_size: if rng.gen_bool(0.7) {
+                0
+            } else {
+                rng.gen_range(0, 40)
+            },
+        },
+        17 => Op::PromoteScheduled,
+        18 => Op::Restart,
+        _ => Op::RemoveAll,
+    }
+}
+
+fn panic_message(panic: Box<dyn Any + Send>) -> String {
+    match panic.downcast::<String>() {
+        Ok(message) => *message,
+        Err(panic) => panic.downcast_ref::<&str>().map_or_else(|| "A panic".to_string(), |message| message.to_string()),
+    }
+}
+
+/// Applies the operations to a new pool.
+/// Returns the step that broke an invariant or panicked, with what went wrong.
+fn run(ops: &[Op]) -> Result<(), (usize, String)> {
+    let mut harness = Harness::new();
+    for (step, op) in ops.iter().enumerate() {
+        match panic::catch_unwind(AssertUnwindSafe(|| harness.apply(op))) {
+            Ok(Ok(())) => {}
+            Ok(Err(message)) => return Err((step, message)),
+            Err(panic) => return Err((step, panic_message(panic))),
+        }
+    }
+    Ok(())
+}
+
+/// Drops the chunks of operations the failure doesn't need, halving the chunks down to single operations.
+fn shrink(mut ops: Vec<Op>) -> Vec<Op> {
+    let mut chunk = ops.len() / 2;
+    while chunk > 0 {
+        let mut start = 0;
+        while start < ops.len() {
+            let end = (start + chunk).min(ops.len());
+            let candidate: Vec<_> = ops[..start].iter().chain(&ops[end..]).cloned().collect();
+            if run(&candidate).is_err() {
+                ops = candidate;
+            } else {
+                start += chunk;
+            }
+        }
+        chunk /= 2;
+    }
+    ops
+}
+
+#[test]
+fn random_operations_keep_the_invariants() {
+    let seeds = match env::var("MEM_POOL_FUZZ_SEED") {
+        Ok(seed) => {
+            let seed = seed.parse().expect("MEM_POOL_FUZZ_SEED must be a number");
+            seed..seed + 1
+        }
+        Err(_) => 0..RUNS,
+    };
+    for seed in seeds {
+        let mut ops: Vec<_> = {
+            let mut rng = rng(seed);
+            (0..STEPS).map(|_| random_op(&mut rng)).collect()
+        };
+        if let Err((step, _)) = run(&ops) {
+            ops.truncate(step + 1);
+            let ops = shrink(ops);
+            let (_, message) = run(&ops).expect_err("The shrunk operations still fail");
+            panic!("Seed {} breaks the mem pool: {}\nOperations: {:#?}", seed, message, ops);
+        }
+    }
+}
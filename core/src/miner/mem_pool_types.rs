@@ -14,25 +14,117 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use coordinator::TransactionWithMetadata;
+use ckey::Ed25519Public as Public;
+use coordinator::module::TxSortingMetadata;
+use coordinator::{PoolTransaction, TransactionWithMetadata, TxOrigin};
 use ctypes::TxHash;
-use std::collections::HashMap;
+use primitives::H256;
+use std::collections::{BTreeSet, HashMap};
+use std::ops::{Bound, Range};
 
+/// What the items of a pool are listed by.
+/// The items whose fee or sequence is unknown come before the others.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PoolSort {
+    Insertion,
+    Fee,
+    Seq,
+}
+
+/// Whether the items of a pool are listed from the lowest or the highest.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PoolOrder {
+    Ascending,
+    Descending,
+}
+
+/// The last item of a page, from which the next page continues.
+/// `key` is the fee or the sequence of the item if the page is sorted by it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PoolCursor {
+    pub key: Option<u64>,
+    pub insertion_id: u64,
+    pub hash: TxHash,
+}
+
+/// Selects a page of the pending transactions.
+/// The signer, the fee and the sequence are the ones the sorter of the application read from the transactions,
+/// so a transaction whose fee is unknown doesn't match any range of the fees.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PoolQuery {
+    pub tx_type: Option<String>,
+    pub origin: Option<TxOrigin>,
+    pub signer: Option<Public>,
+    /// The timestamps of the best blocks at which the transactions were inserted.
+    pub inserted: Range<u64>,
+    pub fee: Option<Range<u64>>,
+    pub seq: Option<Range<u64>>,
+    pub sort: PoolSort,
+    pub order: PoolOrder,
+    pub after: Option<PoolCursor>,
+    pub limit: usize,
+}
+
+/// A page of the pending transactions, with the cursor of the next page if there are more.
+#[derive(Debug, PartialEq)]
+pub struct PoolPage {
+    pub transactions: Vec<TransactionWithMetadata>,
+    pub next: Option<PoolCursor>,
+}
+
+/// The key of an item in the indices: the fee or the sequence the index is sorted by, if any,
+/// then the insertion id. The hash breaks the ties of the insertion ids loaded from a dump.
+type IndexKey = (Option<u64>, u64, H256);
+
+fn index_key<T: PoolTransaction>(item: &T, key: Option<u64>) -> IndexKey {
+    (key, item.insertion_id(), *item.hash())
+}
+
+fn cursor_of(&(key, insertion_id, hash): &IndexKey) -> PoolCursor {
+    PoolCursor {
+        key,
+        insertion_id,
+        hash: hash.into(),
+    }
+}
+
+/// The queue of a pool, keeping the items by their hashes and counting how much of the limits they use.
+/// The items are also indexed in the order they were received, overall and per type,
+/// and by their fees and sequences, overall and per signer for the sequences,
+/// so that a page of them is listed without going through the whole pool.
 #[derive(Debug, PartialEq)]
-pub struct TransactionPool {
-    pub pool: HashMap<TxHash, TransactionWithMetadata>,
+pub struct TransactionPool<T = TransactionWithMetadata> {
+    pub pool: HashMap<TxHash, T>,
     /// Memory usage of the transactions in the queue
     pub mem_usage: usize,
     /// Count of the external transactions in the queue
     pub count: usize,
+    metadata: HashMap<TxHash, TxSortingMetadata>,
+    by_insertion: BTreeSet<IndexKey>,
+    by_type: HashMap<String, BTreeSet<IndexKey>>,
+    by_fee: BTreeSet<IndexKey>,
+    by_seq: BTreeSet<IndexKey>,
+    by_signer: HashMap<Public, BTreeSet<IndexKey>>,
 }
 
-impl TransactionPool {
+impl<T: PoolTransaction> Default for TransactionPool<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: PoolTransaction> TransactionPool<T> {
     pub fn new() -> Self {
         Self {
             pool: Default::default(),
             mem_usage: 0,
             count: 0,
+            metadata: Default::default(),
+            by_insertion: Default::default(),
+            by_type: Default::default(),
+            by_fee: Default::default(),
+            by_seq: Default::default(),
+            by_signer: Default::default(),
         }
     }
 
@@ -40,17 +132,32 @@ impl TransactionPool {
         self.pool.clear();
         self.mem_usage = 0;
         self.count = 0;
+        self.metadata.clear();
+        self.by_insertion.clear();
+        self.by_type.clear();
+        self.by_fee.clear();
+        self.by_seq.clear();
+        self.by_signer.clear();
     }
 
     pub fn len(&self) -> usize {
         self.pool.len()
     }
 
-    pub fn insert(&mut self, item: TransactionWithMetadata) {
-        if !item.origin.is_local() {
+    pub fn insert(&mut self, item: T, metadata: TxSortingMetadata) {
+        self.remove(&item.hash());
+        if !item.is_local() {
             self.mem_usage += item.size();
             self.count += 1;
         }
+        self.by_insertion.insert(index_key(&item, None));
+        self.by_type.entry(item.tx_type().to_string()).or_default().insert(index_key(&item, None));
+        self.by_fee.insert(index_key(&item, metadata.fee));
+        self.by_seq.insert(index_key(&item, metadata.seq));
+        if let Some(signer) = metadata.signer {
+            self.by_signer.entry(signer).or_default().insert(index_key(&item, metadata.seq));
+        }
+        self.metadata.insert(item.hash(), metadata);
         self.pool.insert(item.hash(), item);
     }
 
@@ -60,13 +167,284 @@ impl TransactionPool {
 
     pub fn remove(&mut self, hash: &TxHash) -> bool {
         if let Some(item) = self.pool.remove(hash) {
-            if !item.origin.is_local() {
+            if !item.is_local() {
                 self.mem_usage -= item.size();
                 self.count -= 1;
             }
+            let metadata = self.metadata.remove(hash).expect("Every item has its metadata");
+            self.by_insertion.remove(&index_key(&item, None));
+            if let Some(index) = self.by_type.get_mut(item.tx_type()) {
+                index.remove(&index_key(&item, None));
+                if index.is_empty() {
+                    self.by_type.remove(item.tx_type());
+                }
+            }
+            self.by_fee.remove(&index_key(&item, metadata.fee));
+            self.by_seq.remove(&index_key(&item, metadata.seq));
+            if let Some(signer) = metadata.signer {
+                if let Some(index) = self.by_signer.get_mut(&signer) {
+                    index.remove(&index_key(&item, metadata.seq));
+                    if index.is_empty() {
+                        self.by_signer.remove(&signer);
+                    }
+                }
+            }
             true
         } else {
             false
         }
     }
+
+    /// Iterates over the items matching the type, the signer, the fee and the sequence of the query,
+    /// sorted as it asks, continuing right after its cursor.
+    /// The origin and the insertion time are not known to the pool, so the caller filters them.
+    /// The index of the type or the signer is walked if there is one for the sort, and the others are filtered.
+    pub fn iter_sorted<'a>(&'a self, query: &'a PoolQuery) -> Box<dyn Iterator<Item = (PoolCursor, &'a T)> + 'a> {
+        let index = match (query.sort, query.signer, query.tx_type.as_deref()) {
+            (PoolSort::Seq, Some(signer), _) => self.by_signer.get(&signer),
+            (PoolSort::Insertion, _, Some(tx_type)) => self.by_type.get(tx_type),
+            (PoolSort::Insertion, ..) => Some(&self.by_insertion),
+            (PoolSort::Fee, ..) => Some(&self.by_fee),
+            (PoolSort::Seq, ..) => Some(&self.by_seq),
+        };
+        let index = match index {
+            Some(index) => index,
+            None => return Box::new(std::iter::empty()),
+        };
+        let after = query.after.map(|cursor| (cursor.key, cursor.insertion_id, *cursor.hash));
+        let keys: Box<dyn Iterator<Item = &IndexKey> + 'a> = match (query.order, after) {
+            (PoolOrder::Ascending, None) => Box::new(index.iter()),
+            (PoolOrder::Descending, None) => Box::new(index.iter().rev()),
+            (PoolOrder::Ascending, Some(key)) => Box::new(index.range((Bound::Excluded(key), Bound::Unbounded))),
+            (PoolOrder::Descending, Some(key)) => Box::new(index.range(..key).rev()),
+        };
+        let within = |range: &Option<Range<u64>>, value: Option<u64>| match range {
+            Some(range) => value.map_or(false, |value| range.contains(&value)),
+            None => true,
+        };
+        Box::new(
+            keys.map(move |key| {
+                let hash = TxHash::from(key.2);
+                (cursor_of(key), &self.pool[&hash], &self.metadata[&hash])
+            })
+            .filter(move |(_, item, metadata)| {
+                query.tx_type.as_deref().map_or(true, |tx_type| item.tx_type() == tx_type)
+                    && query.signer.map_or(true, |signer| metadata.signer == Some(signer))
+                    && within(&query.fee, metadata.fee)
+                    && within(&query.seq, metadata.seq)
+            })
+            .map(|(cursor, item, _)| (cursor, item)),
+        )
+    }
+
+    /// Checks that the indices have exactly the items in the pool.
+    pub fn check_indices(&self) -> Result<(), String> {
+        let indexed_by_type: usize = self.by_type.values().map(BTreeSet::len).sum();
+        let indexed_by_signer: usize = self.by_signer.values().map(BTreeSet::len).sum();
+        let signed = self.metadata.values().filter(|metadata| metadata.signer.is_some()).count();
+        if self.by_insertion.len() != self.pool.len()
+            || indexed_by_type != self.pool.len()
+            || self.by_fee.len() != self.pool.len()
+            || self.by_seq.len() != self.pool.len()
+            || self.metadata.len() != self.pool.len()
+            || indexed_by_signer != signed
+        {
+            return Err(format!(
+                "The pool has {} items, but indexes {} in order, {} by type, {} by fee, {} by seq and {} by signer",
+                self.pool.len(),
+                self.by_insertion.len(),
+                indexed_by_type,
+                self.by_fee.len(),
+                self.by_seq.len(),
+                indexed_by_signer
+            ))
+        }
+        for item in self.pool.values() {
+            let metadata = match self.metadata.get(&item.hash()) {
+                Some(metadata) => metadata,
+                None => return Err(format!("The item {:?} has no metadata", item.hash())),
+            };
+            let by_type =
+                self.by_type.get(item.tx_type()).map_or(false, |index| index.contains(&index_key(item, None)));
+            let by_signer = metadata.signer.map_or(true, |signer| {
+                self.by_signer.get(&signer).map_or(false, |index| index.contains(&index_key(item, metadata.seq)))
+            });
+            if !self.by_insertion.contains(&index_key(item, None))
+                || !by_type
+                || !self.by_fee.contains(&index_key(item, metadata.fee))
+                || !self.by_seq.contains(&index_key(item, metadata.seq))
+                || !by_signer
+            {
+                return Err(format!("The item {:?} is not indexed", item.hash()))
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Evidence {
+        hash: TxHash,
+        size: usize,
+        local: bool,
+        insertion_id: u64,
+        kind: &'static str,
+    }
+
+    fn evidence(insertion_id: u64, kind: &'static str) -> Evidence {
+        Evidence {
+            hash: H256::random().into(),
+            size: 10,
+            local: false,
+            insertion_id,
+            kind,
+        }
+    }
+
+    impl PoolTransaction for Evidence {
+        fn hash(&self) -> TxHash {
+            self.hash
+        }
+
+        fn size(&self) -> usize {
+            self.size
+        }
+
+        fn is_local(&self) -> bool {
+            self.local
+        }
+
+        fn insertion_id(&self) -> u64 {
+            self.insertion_id
+        }
+
+        fn tx_type(&self) -> &str {
+            self.kind
+        }
+    }
+
+    #[test]
+    fn pool_of_other_items_counts_only_external_ones() {
+        let mut pool = TransactionPool::new();
+        let external = Evidence {
+            hash: H256::random().into(),
+            size: 100,
+            local: false,
+            insertion_id: 0,
+            kind: "vote",
+        };
+        let local = Evidence {
+            hash: H256::random().into(),
+            size: 200,
+            local: true,
+            insertion_id: 1,
+            kind: "vote",
+        };
+        let (external_hash, local_hash) = (external.hash, local.hash);
+        pool.insert(external, TxSortingMetadata::default());
+        pool.insert(local, TxSortingMetadata::default());
+        assert_eq!(2, pool.len());
+        assert_eq!(1, pool.count);
+        assert_eq!(100, pool.mem_usage);
+
+        assert!(pool.remove(&external_hash));
+        assert!(!pool.remove(&external_hash));
+        assert!(pool.contains(&local_hash));
+        assert_eq!(0, pool.count);
+        assert_eq!(0, pool.mem_usage);
+        assert_eq!(Ok(()), pool.check_indices());
+    }
+
+    fn query(tx_type: Option<&str>, sort: PoolSort, order: PoolOrder, after: Option<PoolCursor>) -> PoolQuery {
+        PoolQuery {
+            tx_type: tx_type.map(str::to_string),
+            origin: None,
+            signer: None,
+            inserted: 0..u64::max_value(),
+            fee: None,
+            seq: None,
+            sort,
+            order,
+            after,
+            limit: usize::max_value(),
+        }
+    }
+
+    fn ids<'a>(items: impl Iterator<Item = (PoolCursor, &'a Evidence)>) -> Vec<u64> {
+        items.map(|(_, item)| item.insertion_id).collect()
+    }
+
+    #[test]
+    fn pages_continue_after_the_cursor() {
+        let mut pool = TransactionPool::new();
+        for (insertion_id, kind) in [(3, "vote"), (1, "vote"), (2, "report"), (4, "vote")].iter() {
+            pool.insert(evidence(*insertion_id, kind), TxSortingMetadata::default());
+        }
+        let oldest_first = query(None, PoolSort::Insertion, PoolOrder::Ascending, None);
+
+        let first: Vec<_> = pool.iter_sorted(&oldest_first).take(2).collect();
+        let cursor = first.last().unwrap().0;
+        assert_eq!(vec![1, 2], ids(first.into_iter()));
+        let rest = query(None, PoolSort::Insertion, PoolOrder::Ascending, Some(cursor));
+        assert_eq!(vec![3, 4], ids(pool.iter_sorted(&rest)));
+
+        let votes = query(Some("vote"), PoolSort::Insertion, PoolOrder::Descending, Some(cursor));
+        assert_eq!(vec![1], ids(pool.iter_sorted(&votes)));
+        let unknown = query(Some("unknown"), PoolSort::Insertion, PoolOrder::Ascending, None);
+        assert_eq!(0, pool.iter_sorted(&unknown).count());
+
+        let reports = query(Some("report"), PoolSort::Insertion, PoolOrder::Ascending, None);
+        let report = pool.iter_sorted(&reports).next().unwrap().1.hash;
+        assert!(pool.remove(&report));
+        assert_eq!(0, pool.iter_sorted(&reports).count());
+        assert_eq!(Ok(()), pool.check_indices());
+    }
+
+    #[test]
+    fn items_are_sorted_by_their_metadata() {
+        let mut pool = TransactionPool::new();
+        // The insertion id, the signer, the fee and the sequence of each item.
+        for &(insertion_id, signer, fee, seq) in
+            [(1, 1, Some(30), 1), (2, 2, Some(10), 5), (3, 1, None, 0), (4, 1, Some(30), 2), (5, 2, Some(20), 4)].iter()
+        {
+            let metadata = TxSortingMetadata {
+                signer: Some(Public::from(signer)),
+                fee,
+                seq: Some(seq),
+            };
+            pool.insert(evidence(insertion_id, "vote"), metadata);
+        }
+
+        let by_fee = query(None, PoolSort::Fee, PoolOrder::Descending, None);
+        assert_eq!(vec![4, 1, 5, 2, 3], ids(pool.iter_sorted(&by_fee)));
+        let first: Vec<_> = pool.iter_sorted(&by_fee).take(2).collect();
+        let rest = query(None, PoolSort::Fee, PoolOrder::Descending, Some(first[1].0));
+        assert_eq!(Some(30), first[1].0.key);
+        assert_eq!(vec![5, 2, 3], ids(pool.iter_sorted(&rest)));
+
+        let mut cheap = query(None, PoolSort::Fee, PoolOrder::Ascending, None);
+        cheap.fee = Some(0..30);
+        assert_eq!(vec![2, 5], ids(pool.iter_sorted(&cheap)));
+
+        let mut of_signer = query(None, PoolSort::Seq, PoolOrder::Ascending, None);
+        of_signer.signer = Some(Public::from(1));
+        assert_eq!(vec![3, 1, 4], ids(pool.iter_sorted(&of_signer)));
+        of_signer.sort = PoolSort::Insertion;
+        of_signer.seq = Some(1..3);
+        assert_eq!(vec![1, 4], ids(pool.iter_sorted(&of_signer)));
+
+        let hashes: Vec<_> = pool
+            .iter_sorted(&query(None, PoolSort::Seq, PoolOrder::Ascending, None))
+            .map(|(cursor, _)| cursor.hash)
+            .collect();
+        for hash in hashes {
+            assert!(pool.remove(&hash));
+            assert_eq!(Ok(()), pool.check_indices());
+        }
+        assert_eq!(pool, TransactionPool::new());
+    }
 }
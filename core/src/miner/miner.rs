@@ -14,12 +14,14 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::mem_pool::{Error as MemPoolError, MemPool};
-use super::MinerService;
+use super::backup::MemPoolFlush;
+use super::ingress::{IngressLimits, IngressStats};
+use super::mem_pool::{Error as MemPoolError, MemPool, MemPoolUsage, OrphanStats};
+use super::{MinerService, PoolPage, PoolQuery};
 use crate::account_provider::{AccountProvider, Error as AccountProviderError};
 use crate::block::{ClosedBlock, IsBlock};
 use crate::client::{BlockChainTrait, BlockProducer, EngineInfo, ImportBlock, MiningBlockChainClient, TermInfo};
-use crate::consensus::{ConsensusEngine, EngineType};
+use crate::consensus::{ConsensusEngine, EngineType, RemoteSigner};
 use crate::error::Error;
 use crate::scheme::Scheme;
 use crate::transaction::PendingTransactions;
@@ -27,14 +29,16 @@ use crate::types::TransactionId;
 use crate::StateInfo;
 use ckey::Ed25519Public as Public;
 use coordinator::engine::{BlockExecutor, TxFilter};
-use coordinator::{Transaction, TxOrigin};
+use coordinator::types::{InspectedPayload, TransactionPreview};
+use coordinator::{Header as PreHeader, Transaction, TransactionWithMetadata, TxOrigin, TxSizes};
 use cstate::TopLevelState;
-use ctypes::errors::HistoryError;
-use ctypes::{BlockHash, BlockId};
+use ctypes::errors::{HistoryError, SyntaxError};
+use ctypes::{BlockHash, BlockId, BlockNumber, TxHash, TxOrdering};
 use kvdb::KeyValueDB;
 use parking_lot::{Mutex, RwLock};
 use primitives::Bytes;
 use std::borrow::Borrow;
+use std::collections::{HashMap, HashSet};
 use std::ops::Range;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
@@ -58,6 +62,46 @@ pub struct MinerOptions {
     /// then `new_fee > old_fee + old_fee >> mem_pool_fee_bump_shift` should be satisfied to replace.
     /// Local transactions ignore this option.
     pub mem_pool_fee_bump_shift: usize,
+    /// When the changes to the memory pool are written to the DB.
+    pub mem_pool_flush: MemPoolFlush,
+    /// How many blocks a transaction invalid against the state of the best block is checked again for.
+    /// Zero drops such a transaction right away.
+    pub mem_pool_orphan_retry_blocks: BlockNumber,
+    /// The external transactions of each type admitted to the mem pool per second.
+    /// The types not listed here are not limited.
+    pub ingress_rate_limits: HashMap<String, u32>,
+    /// When a proposer proposes a block while there is no pending transaction.
+    pub empty_block_proposal: EmptyBlockProposal,
+}
+
+/// When a proposer proposes a block without transactions.
+/// Suppressing empty blocks keeps a quiet chain small,
+/// but the timestamp of the best block no longer tells whether the validators are alive.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EmptyBlockProposal {
+    /// At every height.
+    Always,
+    /// Only once the parent block is older than the interval, so that the chain moves at a reduced cadence.
+    Interval(Duration),
+    /// Never. No block is made while there is no transaction.
+    Never,
+}
+
+impl Default for EmptyBlockProposal {
+    fn default() -> Self {
+        EmptyBlockProposal::Always
+    }
+}
+
+impl EmptyBlockProposal {
+    /// Whether an empty block may be proposed on a parent made at `parent_timestamp`, at `now` in seconds.
+    pub fn is_due(self, parent_timestamp: u64, now: u64) -> bool {
+        match self {
+            EmptyBlockProposal::Always => true,
+            EmptyBlockProposal::Interval(interval) => now.saturating_sub(parent_timestamp) >= interval.as_secs(),
+            EmptyBlockProposal::Never => false,
+        }
+    }
 }
 
 impl Default for MinerOptions {
@@ -69,10 +113,24 @@ impl Default for MinerOptions {
             mem_pool_size: 8192,
             mem_pool_memory_limit: Some(2 * 1024 * 1024),
             mem_pool_fee_bump_shift: 3,
+            mem_pool_flush: MemPoolFlush::Sync,
+            mem_pool_orphan_retry_blocks: 3,
+            ingress_rate_limits: HashMap::new(),
+            empty_block_proposal: EmptyBlockProposal::Always,
         }
     }
 }
 
+impl MinerOptions {
+    /// The lowest fee with which a transaction can replace a pending one paying `fee`,
+    /// i.e. the smallest `new_fee` satisfying `new_fee > fee + fee >> mem_pool_fee_bump_shift`.
+    /// Returns `None` if the fee would overflow.
+    pub fn replacement_fee(&self, fee: u64) -> Option<u64> {
+        let bump = fee.checked_shr(self.mem_pool_fee_bump_shift as u32).unwrap_or(0);
+        fee.checked_add(bump)?.checked_add(1)
+    }
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct AuthoringParams {
     pub author: Public,
@@ -85,8 +143,11 @@ pub struct Miner {
     params: Params,
     engine: Arc<dyn ConsensusEngine>,
     options: MinerOptions,
+    ingress_limits: IngressLimits,
 
     sealing_enabled: AtomicBool,
+    /// The UNIX time the node entered the maintenance mode at.
+    maintenance_since: Mutex<Option<u64>>,
 
     block_executor: Arc<dyn BlockExecutor>,
 }
@@ -159,16 +220,20 @@ impl Miner {
         coordinator: Arc<C>,
     ) -> Self {
         let mem_limit = options.mem_pool_memory_limit.unwrap_or_else(usize::max_value);
-        let mem_pool =
-            Arc::new(RwLock::new(MemPool::with_limits(options.mem_pool_size, mem_limit, db, coordinator.clone())));
+        let mut mem_pool =
+            MemPool::with_limits(options.mem_pool_size, mem_limit, db, options.mem_pool_flush, coordinator.clone());
+        mem_pool.set_orphan_retry_blocks(options.mem_pool_orphan_retry_blocks);
+        let mem_pool = Arc::new(RwLock::new(mem_pool));
 
         Self {
             mem_pool,
             next_allowed_reseal: NextAllowedReseal::new(Instant::now()),
             params: Params::new(AuthoringParams::default()),
             engine: scheme.engine.clone(),
+            ingress_limits: IngressLimits::new(&options.ingress_rate_limits),
             options,
             sealing_enabled: AtomicBool::new(true),
+            maintenance_since: Mutex::new(None),
             block_executor: coordinator,
         }
     }
@@ -177,10 +242,20 @@ impl Miner {
         self.mem_pool.write().recover_from_db();
     }
 
+    /// Blocks until the changes to the memory pool are written to the DB.
+    pub fn flush_mem_pool(&self) {
+        self.mem_pool.read().flush();
+    }
+
     pub fn get_options(&self) -> &MinerOptions {
         &self.options
     }
 
+    /// Whether sealing is on, which `stop_sealing` turns off.
+    pub fn is_sealing_enabled(&self) -> bool {
+        self.sealing_enabled.load(Ordering::Relaxed)
+    }
+
     fn add_transactions_to_pool<C: BlockChainTrait + EngineInfo + StateInfo>(
         &self,
         client: &C,
@@ -190,6 +265,10 @@ impl Miner {
     ) -> Vec<Result<(), Error>> {
         let current_block_number = client.chain_info().best_block_number;
         let current_timestamp = client.chain_info().best_block_timestamp;
+        let max_body_size = client
+            .consensus_params(BlockId::Number(current_block_number))
+            .expect("Consensus params of the best block always exist")
+            .max_body_size() as usize;
         let mut inserted = Vec::with_capacity(transactions.len());
         let mut to_insert = Vec::new();
         let mut tx_hashes = Vec::new();
@@ -202,6 +281,10 @@ impl Miner {
                     cdebug!(MINER, "Rejected transaction {:?}: already in the blockchain", hash);
                     return Err(HistoryError::TransactionAlreadyImported.into())
                 }
+                if tx.size() > max_body_size {
+                    cdebug!(MINER, "Rejected transaction {:?}: larger than the maximum body size", hash);
+                    return Err(SyntaxError::TransactionIsTooBig.into())
+                }
 
                 to_insert.push(tx);
                 tx_hashes.push(hash);
@@ -266,7 +349,15 @@ impl Miner {
             let transactions = mem_pool.all_pending_transactions_with_metadata();
             open_block.prepare_block_from_transactions(&*self.block_executor, transactions);
         }
-        let closed_block = open_block.close(&*self.block_executor)?;
+        let mut closed_block = open_block.close(&*self.block_executor)?;
+        if let Some(seal) = self.engine.seal_closed_block(closed_block.block()) {
+            match seal.seal_fields() {
+                Some(seal_bytes) => {
+                    closed_block.seal(self.engine.borrow(), seal_bytes).expect("Sealing always success")
+                }
+                None => return Ok(None),
+            }
+        }
         Ok(Some(closed_block))
     }
 
@@ -283,9 +374,71 @@ impl Miner {
         chain.import_generated_block(&block).is_ok()
     }
 
+    /// Seals a block on the parent and imports it, returning whether it's imported.
+    /// With `wait_for_timestamp`, it waits until the clock reaches the timestamp of the block before importing it.
+    fn seal_and_import<C>(
+        &self,
+        chain: &C,
+        parent_block: BlockId,
+        allow_empty_block: bool,
+        wait_for_timestamp: bool,
+    ) -> bool
+    where
+        C: BlockChainTrait + BlockProducer + EngineInfo + ImportBlock + TermInfo, {
+        if self.maintenance_since().is_some() {
+            ctrace!(MINER, "update_sealing: the node is in the maintenance mode");
+            return false
+        }
+        ctrace!(MINER, "update_sealing: preparing a block");
+
+        let block = match self.prepare_and_seal_block(parent_block, chain) {
+            Ok(Some(block)) => {
+                if !allow_empty_block && block.block().transactions().is_empty() {
+                    ctrace!(MINER, "update_sealing: block is empty, and allow_empty_block is false");
+                    return false
+                }
+                block
+            }
+            Ok(None) => {
+                ctrace!(MINER, "update_sealing: cannot prepare block");
+                return false
+            }
+            Err(err) => {
+                ctrace!(MINER, "update_sealing: cannot prepare block: {:?}", err);
+                return false
+            }
+        };
+
+        if wait_for_timestamp {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("There is no time machine.").as_secs();
+            if block.header().timestamp() > now {
+                let delta = block.header().timestamp() - now;
+                std::thread::sleep(std::time::Duration::from_secs(delta));
+            }
+        }
+
+        let imported = if self.engine.seals_internally() {
+            ctrace!(MINER, "update_sealing: engine indicates internal sealing");
+            let imported = self.import_block_internally(chain, block);
+            if imported {
+                ctrace!(MINER, "update_sealing: imported internally closed block");
+            }
+            imported
+        } else {
+            ctrace!(MINER, "update_sealing: engine is not keen to seal internally right now");
+            return false
+        };
+
+        // Sealing successful
+        self.next_allowed_reseal.set(Instant::now() + self.options.reseal_min_period);
+        chain.set_min_timer();
+        imported
+    }
+
     /// Are we allowed to do a non-mandatory reseal?
     fn transaction_reseal_allowed(&self) -> bool {
-        self.sealing_enabled.load(Ordering::Relaxed) && (Instant::now() > self.next_allowed_reseal.get())
+        self.sealing_enabled.load(Ordering::Relaxed)
+            && (self.engine_type().seals_instantly() || Instant::now() > self.next_allowed_reseal.get())
     }
 }
 
@@ -296,6 +449,40 @@ impl MinerService for Miner {
         self.mem_pool.read().num_pending_transactions()
     }
 
+    fn is_block_proposal_due(&self, parent_timestamp: u64) -> bool {
+        if self.maintenance_since().is_some() {
+            return false
+        }
+        if self.num_pending_transactions() > 0 {
+            return true
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("There is no time machine.").as_secs();
+        self.options.empty_block_proposal.is_due(parent_timestamp, now)
+    }
+
+    fn set_maintenance(&self, enabled: bool) -> bool {
+        let mut maintenance_since = self.maintenance_since.lock();
+        if maintenance_since.is_some() == enabled {
+            return false
+        }
+        *maintenance_since = if enabled {
+            cinfo!(MINER, "Entering the maintenance mode");
+            Some(SystemTime::now().duration_since(UNIX_EPOCH).expect("There is no time machine.").as_secs())
+        } else {
+            cinfo!(MINER, "Leaving the maintenance mode");
+            None
+        };
+        true
+    }
+
+    fn maintenance_since(&self) -> Option<u64> {
+        *self.maintenance_since.lock()
+    }
+
+    fn mem_pool_usage(&self) -> MemPoolUsage {
+        self.mem_pool.read().usage()
+    }
+
     fn authoring_params(&self) -> AuthoringParams {
         self.params.get()
     }
@@ -312,6 +499,27 @@ impl MinerService for Miner {
         Ok(())
     }
 
+    fn set_remote_author(&self, signer: RemoteSigner, fallback: Option<Arc<AccountProvider>>) -> Result<(), Error> {
+        let pubkey = *signer.public();
+        self.params.apply(|params| params.author = pubkey);
+
+        if self.engine_type().need_signer_key() {
+            ctrace!(MINER, "Set author to {:?} (remote)", pubkey);
+            if let Some(ap) = &fallback {
+                ap.get_unlocked_account(&pubkey)?.sign(&Default::default())?;
+            }
+            // Sign test message
+            if let Err(err) = signer.sign(&Default::default()) {
+                if fallback.is_none() {
+                    return Err(err)
+                }
+                cwarn!(MINER, "{}", err);
+            }
+            self.engine.set_remote_signer(signer, fallback);
+        }
+        Ok(())
+    }
+
     fn get_author(&self) -> Public {
         self.params.get().author
     }
@@ -344,12 +552,55 @@ impl MinerService for Miner {
                 .map(|tx| tx.hash())
                 .collect();
             mem_pool.remove(&to_remove, current_block_number, current_timestamp);
+            if let Some(params) = chain.common_params(BlockId::Number(current_block_number)) {
+                mem_pool.update_params_era(params.era());
+            }
             let mut state = chain.state_at(BlockId::Number(current_block_number)).expect("the block must exist");
+            mem_pool.promote_scheduled(&mut state, current_block_number, current_timestamp);
+            let vetoed = mem_pool.remove_vetoed(&mut state);
+            if vetoed > 0 {
+                cdebug!(MINER, "Dropped {} transactions vetoed by their modules", vetoed);
+            }
             mem_pool.remove_old(&mut state, current_block_number, current_timestamp);
         }
         chain.set_min_timer();
     }
 
+    fn chain_reorganized<C>(&self, chain: &C, retracted: &[BlockHash], enacted: &[BlockHash])
+    where
+        C: BlockChainTrait + StateInfo, {
+        ctrace!(MINER, "chain_reorganized: {} blocks retracted", retracted.len());
+
+        let transactions_of = |blocks: &[BlockHash]| -> Vec<Transaction> {
+            blocks
+                .iter()
+                .flat_map(|hash| chain.block(&BlockId::from(*hash)))
+                .flat_map(|block| block.view().transactions())
+                .collect()
+        };
+        let enacted: HashSet<_> = transactions_of(enacted).iter().map(|tx| tx.hash()).collect();
+        let retracted: Vec<_> =
+            transactions_of(retracted).into_iter().filter(|tx| !enacted.contains(&tx.hash())).collect();
+
+        let current_block_number = chain.chain_info().best_block_number;
+        let current_timestamp = chain.chain_info().best_block_timestamp;
+        let mut mem_pool = self.mem_pool.write();
+        let enacted: Vec<_> = enacted.into_iter().collect();
+        mem_pool.remove(&enacted, current_block_number, current_timestamp);
+        if retracted.is_empty() {
+            return
+        }
+        let count = retracted.len();
+        let mut state = chain.state_at(BlockId::Number(current_block_number)).expect("the block must exist");
+        let dropped = mem_pool.reinject(retracted, &mut state, current_block_number, current_timestamp);
+        cinfo!(
+            MINER,
+            "Reinjected {} transactions of the retracted blocks, dropped {} invalid ones",
+            count - dropped,
+            dropped
+        );
+    }
+
     fn engine_type(&self) -> EngineType {
         self.engine.engine_type()
     }
@@ -357,47 +608,13 @@ impl MinerService for Miner {
     fn update_sealing<C>(&self, chain: &C, parent_block: BlockId, allow_empty_block: bool)
     where
         C: BlockChainTrait + BlockProducer + EngineInfo + ImportBlock + TermInfo, {
-        ctrace!(MINER, "update_sealing: preparing a block");
-
-        let block = match self.prepare_and_seal_block(parent_block, chain) {
-            Ok(Some(block)) => {
-                if !allow_empty_block && block.block().transactions().is_empty() {
-                    ctrace!(MINER, "update_sealing: block is empty, and allow_empty_block is false");
-                    return
-                }
-                block
-            }
-            Ok(None) => {
-                ctrace!(MINER, "update_sealing: cannot prepare block");
-                return
-            }
-            Err(err) => {
-                ctrace!(MINER, "update_sealing: cannot prepare block: {:?}", err);
-                return
-            }
-        };
-
-        if true {
-            let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("There is no time machine.").as_secs();
-            if block.header().timestamp() > now {
-                let delta = block.header().timestamp() - now;
-                std::thread::sleep(std::time::Duration::from_secs(delta));
-            }
-        }
-
-        if self.engine.seals_internally() {
-            ctrace!(MINER, "update_sealing: engine indicates internal sealing");
-            if self.import_block_internally(chain, block) {
-                ctrace!(MINER, "update_sealing: imported internally closed block");
-            }
-        } else {
-            ctrace!(MINER, "update_sealing: engine is not keen to seal internally right now");
-            return
-        }
+        self.seal_and_import(chain, parent_block, allow_empty_block, true);
+    }
 
-        // Sealing successful
-        self.next_allowed_reseal.set(Instant::now() + self.options.reseal_min_period);
-        chain.set_min_timer();
+    fn mine_block<C>(&self, chain: &C) -> bool
+    where
+        C: BlockChainTrait + BlockProducer + EngineInfo + ImportBlock + TermInfo, {
+        self.seal_and_import(chain, BlockId::Latest, true, false)
     }
 
     fn import_external_transactions<C: MiningBlockChainClient + EngineInfo + TermInfo + StateInfo>(
@@ -406,10 +623,36 @@ impl MinerService for Miner {
         transactions: Vec<Transaction>,
     ) -> Vec<Result<(), Error>> {
         ctrace!(EXTERNAL_TX, "Importing external transactions");
-        let results = {
+        if self.maintenance_since().is_some() {
+            return transactions.iter().map(|_| Err(Error::Maintenance)).collect()
+        }
+        let now = Instant::now();
+        let admitted: Vec<bool> = transactions.iter().map(|tx| self.ingress_limits.admit(tx.tx_type(), now)).collect();
+        let throttled = admitted.iter().filter(|admitted| !**admitted).count();
+        if throttled > 0 {
+            cdebug!(EXTERNAL_TX, "Dropped {} external transactions over the ingress rate limits", throttled);
+        }
+        let transactions = transactions
+            .into_iter()
+            .zip(admitted.iter())
+            .filter(|(_, admitted)| **admitted)
+            .map(|(tx, _)| tx)
+            .collect();
+        let mut added = {
             let mut mem_pool = self.mem_pool.write();
             self.add_transactions_to_pool(client, transactions, TxOrigin::External, &mut mem_pool)
-        };
+        }
+        .into_iter();
+        let results: Vec<_> = admitted
+            .into_iter()
+            .map(|admitted| {
+                if admitted {
+                    added.next().expect("one result returned per admitted transaction")
+                } else {
+                    Err(HistoryError::LimitReached.into())
+                }
+            })
+            .collect();
 
         if !results.is_empty()
             && self.options.reseal_on_external_transaction
@@ -429,15 +672,19 @@ impl MinerService for Miner {
         &self,
         chain: &C,
         tx: Transaction,
+        origin: TxOrigin,
     ) -> Result<(), Error> {
         ctrace!(OWN_TX, "Importing transaction: {:?}", tx);
+        if self.maintenance_since().is_some() {
+            return Err(Error::Maintenance)
+        }
 
         let imported = {
             // Be sure to release the lock before we call prepare_work_sealing
             let mut mem_pool = self.mem_pool.write();
             // We need to re-validate transactions
             let import = self
-                .add_transactions_to_pool(chain, vec![tx], TxOrigin::Local, &mut mem_pool)
+                .add_transactions_to_pool(chain, vec![tx], origin, &mut mem_pool)
                 .pop()
                 .expect("one result returned per added transaction; one added => one result; qed");
 
@@ -470,14 +717,153 @@ impl MinerService for Miner {
         imported
     }
 
+    fn import_scheduled_transaction<C: MiningBlockChainClient + EngineInfo + TermInfo + StateInfo>(
+        &self,
+        chain: &C,
+        tx: Transaction,
+        activation_block: BlockNumber,
+    ) -> Result<(), Error> {
+        if self.maintenance_since().is_some() {
+            return Err(Error::Maintenance)
+        }
+        let current_block_number = chain.chain_info().best_block_number;
+        if activation_block <= current_block_number + 1 {
+            return self.import_own_transaction(chain, tx, TxOrigin::Local)
+        }
+        ctrace!(OWN_TX, "Scheduling transaction for block #{}: {:?}", activation_block, tx);
+
+        let hash = tx.hash();
+        if chain.transaction_block(&TransactionId::Hash(hash)).is_some() {
+            cdebug!(MINER, "Rejected transaction {:?}: already in the blockchain", hash);
+            return Err(HistoryError::TransactionAlreadyImported.into())
+        }
+        let max_body_size = chain
+            .consensus_params(BlockId::Number(current_block_number))
+            .expect("Consensus params of the best block always exist")
+            .max_body_size() as usize;
+        if tx.size() > max_body_size {
+            cdebug!(MINER, "Rejected transaction {:?}: larger than the maximum body size", hash);
+            return Err(SyntaxError::TransactionIsTooBig.into())
+        }
+
+        let current_timestamp = chain.chain_info().best_block_timestamp;
+        self.mem_pool
+            .write()
+            .schedule(tx, TxOrigin::Local, activation_block, current_block_number, current_timestamp)
+            .map_err(MemPoolError::into_core_error)
+    }
+
+    fn scheduled_transactions(&self) -> Vec<(BlockNumber, Transaction)> {
+        self.mem_pool
+            .read()
+            .scheduled_transactions()
+            .map(|tx| (tx.activation_block.expect("Scheduled transactions have activation blocks"), tx.tx.clone()))
+            .collect()
+    }
+
+    fn is_pending_transaction(&self, hash: &TxHash) -> bool {
+        self.mem_pool.read().contains(hash)
+    }
+
+    fn dump_mem_pool(&self) -> Vec<TransactionWithMetadata> {
+        self.mem_pool.read().dump()
+    }
+
+    fn load_mem_pool<C: BlockChainTrait + StateInfo>(
+        &self,
+        chain: &C,
+        transactions: Vec<TransactionWithMetadata>,
+    ) -> Vec<TxHash> {
+        let current_block_number = chain.chain_info().best_block_number;
+        let mut state = chain.state_at(BlockId::Number(current_block_number)).expect("the block must exist");
+        self.mem_pool.write().load(transactions, &mut state, current_block_number)
+    }
+
+    fn preview_transaction<C: BlockChainTrait + StateInfo>(
+        &self,
+        chain: &C,
+        parent: BlockId,
+        tx: &Transaction,
+    ) -> Result<Option<TransactionPreview>, Error> {
+        let parent = match chain.block_header(&parent) {
+            Some(parent) => parent,
+            None => return Ok(None),
+        };
+        let mut state = match chain.state_at(BlockId::Hash(parent.hash())) {
+            Some(state) => state,
+            None => return Ok(None),
+        };
+        let params = self.params.get();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("There is no time machine.").as_secs();
+        let pre_header = PreHeader::new(
+            parent.hash(),
+            now.max(parent.timestamp() + 1),
+            parent.number() + 1,
+            params.author,
+            Vec::new(),
+            params.extra_data,
+        );
+
+        let random_seed = TxOrdering::seed(&parent.seal());
+        // The precommits of the parent are not collected yet, so the preview runs at the time of its header.
+        let execution_id =
+            self.block_executor.open_block(&mut state, &pre_header, &random_seed, pre_header.timestamp(), &[])?;
+        let preview = self.block_executor.preview_transaction(execution_id, &mut state, tx);
+        // The block is only for the preview, so the outcome of closing it doesn't matter
+        let _ = self.block_executor.close_block(execution_id);
+        Ok(Some(preview))
+    }
+
     fn pending_transactions(&self, size_limit: usize, range: Range<u64>) -> PendingTransactions {
         self.mem_pool.read().pending_transactions(size_limit, range)
     }
 
+    fn local_pending_transactions(&self) -> Vec<TransactionWithMetadata> {
+        self.mem_pool.read().local_transactions()
+    }
+
+    fn replacement_fee(&self, fee: u64) -> Option<u64> {
+        self.options.replacement_fee(fee)
+    }
+
+    fn query_pending_transactions(&self, query: &PoolQuery) -> PoolPage {
+        self.mem_pool.read().query_pending_transactions(query)
+    }
+
+    fn propagatable_transaction_hashes(&self) -> Vec<TxHash> {
+        self.mem_pool.read().propagatable_hashes()
+    }
+
+    fn prioritized_transactions<C: BlockChainTrait + StateInfo>(
+        &self,
+        chain: &C,
+        hashes: &[TxHash],
+    ) -> Vec<Transaction> {
+        let current_block_number = chain.chain_info().best_block_number;
+        let mut state = chain.state_at(BlockId::Number(current_block_number)).expect("the block must exist");
+        self.mem_pool.read().prioritized_transactions(&mut state, hashes)
+    }
+
     fn count_pending_transactions(&self, range: Range<u64>) -> usize {
         self.mem_pool.read().count_pending_transactions(range)
     }
 
+    fn ingress_stats(&self) -> Vec<IngressStats> {
+        self.ingress_limits.stats()
+    }
+
+    fn orphan_stats(&self) -> OrphanStats {
+        self.mem_pool.read().orphan_stats()
+    }
+
+    fn transaction_sizes(&self, tx: &Transaction) -> TxSizes {
+        self.mem_pool.read().transaction_sizes(tx)
+    }
+
+    fn inspect_transaction(&self, tx: &Transaction) -> Option<Result<InspectedPayload, String>> {
+        self.mem_pool.read().inspect_transaction(tx)
+    }
+
     fn start_sealing<C: MiningBlockChainClient + EngineInfo + TermInfo>(&self, client: &C) {
         cdebug!(MINER, "Start sealing");
         self.sealing_enabled.store(true, Ordering::Relaxed);
@@ -515,7 +901,8 @@ pub mod test {
         let scheme = Scheme::new_test();
         let miner = Arc::new(Miner::with_scheme_for_test(&scheme, db.clone(), test_coordinator.clone()));
 
-        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), db.clone(), test_coordinator.clone());
+        let mut mem_pool =
+            MemPool::with_limits(8192, usize::max_value(), db.clone(), MemPoolFlush::Sync, test_coordinator.clone());
         let client = generate_test_client(db, Arc::clone(&miner), &scheme, test_coordinator).unwrap();
 
         let transaction1 = Transaction::new("sample".to_string(), vec![1, 2, 3, 4, 5]);
@@ -529,6 +916,33 @@ pub mod test {
         assert!(add_results[2].is_err());
     }
 
+    #[test]
+    fn maintenance_mode_rejects_transactions_and_proposals() {
+        let test_coordinator = Arc::new(TestCoordinator::default());
+        let db = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let scheme = Scheme::new_test();
+        let miner = Arc::new(Miner::with_scheme_for_test(&scheme, db.clone(), test_coordinator.clone()));
+        let client = generate_test_client(db, Arc::clone(&miner), &scheme, test_coordinator).unwrap();
+
+        assert!(miner.set_maintenance(true));
+        assert!(!miner.set_maintenance(true));
+        assert!(miner.maintenance_since().is_some());
+        assert!(!miner.is_block_proposal_due(0));
+
+        let transaction = Transaction::new("sample".to_string(), vec![1, 2, 3, 4, 5]);
+        assert!(matches!(
+            miner.import_own_transaction(client.as_ref(), transaction.clone(), TxOrigin::Local),
+            Err(Error::Maintenance)
+        ));
+        let results = miner.import_external_transactions(client.as_ref(), vec![transaction]);
+        assert!(matches!(results[..], [Err(Error::Maintenance)]));
+        assert_eq!(miner.num_pending_transactions(), 0);
+
+        assert!(miner.set_maintenance(false));
+        assert_eq!(miner.maintenance_since(), None);
+        assert!(miner.is_block_proposal_due(0));
+    }
+
     fn generate_test_client(
         db: Arc<dyn KeyValueDB>,
         miner: Arc<Miner>,
@@ -544,3 +958,38 @@ pub mod test {
         Client::try_new(&client_config, scheme, db, miner, coordinator, io_service.channel(), reseal_timer)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_block_proposal_is_due_after_the_interval() {
+        let proposal = EmptyBlockProposal::Interval(Duration::from_secs(60));
+        assert!(!proposal.is_due(1_000, 1_059));
+        assert!(proposal.is_due(1_000, 1_060));
+        // The parent can be ahead of the local clock within the allowed future gap.
+        assert!(!proposal.is_due(1_010, 1_000));
+    }
+
+    #[test]
+    fn empty_block_proposal_always_and_never() {
+        assert!(EmptyBlockProposal::Always.is_due(1_000, 1_000));
+        assert!(!EmptyBlockProposal::Never.is_due(0, u64::max_value()));
+    }
+
+    #[test]
+    fn replacement_fee_follows_the_bump_shift() {
+        let options = MinerOptions::default();
+        assert_eq!(options.replacement_fee(0), Some(1));
+        assert_eq!(options.replacement_fee(7), Some(8));
+        assert_eq!(options.replacement_fee(800), Some(901));
+
+        let options = MinerOptions {
+            mem_pool_fee_bump_shift: 64,
+            ..MinerOptions::default()
+        };
+        assert_eq!(options.replacement_fee(800), Some(801));
+        assert_eq!(options.replacement_fee(u64::max_value()), None);
+    }
+}
@@ -15,25 +15,33 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 mod backup;
+mod ingress;
 mod mem_pool;
+#[cfg(test)]
+mod mem_pool_fuzz;
 mod mem_pool_types;
 #[cfg_attr(feature = "cargo-clippy", allow(clippy::module_inception))]
 mod miner;
 
 use ckey::Ed25519Public as Public;
 use cstate::TopStateView;
-use ctypes::{BlockHash, BlockId};
+use ctypes::{BlockHash, BlockId, BlockNumber, TxHash};
 use primitives::Bytes;
 use std::ops::Range;
 use std::sync::Arc;
 
-pub use self::miner::{AuthoringParams, Miner, MinerOptions};
+pub use self::backup::MemPoolFlush;
+pub use self::ingress::IngressStats;
+pub use self::mem_pool::{MemPoolUsage, OrphanStats};
+pub use self::mem_pool_types::{PoolCursor, PoolOrder, PoolPage, PoolQuery, PoolSort, TransactionPool};
+pub use self::miner::{AuthoringParams, EmptyBlockProposal, Miner, MinerOptions};
 use crate::account_provider::{AccountProvider, Error as AccountProviderError};
 use crate::client::{BlockChainTrait, BlockProducer, EngineInfo, ImportBlock, MiningBlockChainClient, TermInfo};
-use crate::consensus::EngineType;
+use crate::consensus::{EngineType, RemoteSigner};
 use crate::error::Error;
 use crate::{PendingTransactions, StateInfo};
-use coordinator::Transaction;
+use coordinator::types::{InspectedPayload, TransactionPreview};
+use coordinator::{Transaction, TransactionWithMetadata, TxOrigin, TxSizes};
 
 /// Miner client API
 pub trait MinerService: Send + Sync {
@@ -43,12 +51,33 @@ pub trait MinerService: Send + Sync {
     /// Returns the number of pending transactions.
     fn num_pending_transactions(&self) -> usize;
 
+    /// Whether a block should be proposed on a parent made at `parent_timestamp`.
+    /// It is always due while there are pending transactions, and otherwise as `MinerOptions::empty_block_proposal` allows.
+    /// It is never due in the maintenance mode.
+    fn is_block_proposal_due(&self, parent_timestamp: u64) -> bool;
+
+    /// Puts the node into the maintenance mode, or takes it out of the mode.
+    /// In the mode, the node accepts no new transactions and proposes no blocks. It keeps voting,
+    /// so that the block being agreed on is finished, and keeps serving reads.
+    /// Returns false if the node is already in the requested mode.
+    fn set_maintenance(&self, enabled: bool) -> bool;
+
+    /// The UNIX time the node entered the maintenance mode at, if it's in the mode.
+    fn maintenance_since(&self) -> Option<u64>;
+
+    /// Returns how much of its limits the mem pool uses.
+    fn mem_pool_usage(&self) -> MemPoolUsage;
+
     /// Get current authoring parameters.
     fn authoring_params(&self) -> AuthoringParams;
 
     /// Set the author that we will seal blocks as.
     fn set_author(&self, ap: Arc<AccountProvider>, author: Public) -> Result<(), AccountProviderError>;
 
+    /// Set the author that we will seal blocks as, whose key is kept by the remote signer.
+    /// The account of the same key in `fallback` signs while the remote signer fails.
+    fn set_remote_author(&self, signer: RemoteSigner, fallback: Option<Arc<AccountProvider>>) -> Result<(), Error>;
+
     ///Get the address of block author.
     fn get_author(&self) -> Public;
 
@@ -66,6 +95,12 @@ pub trait MinerService: Send + Sync {
     where
         C: BlockChainTrait + BlockProducer + EngineInfo + ImportBlock + StateInfo;
 
+    /// Called when the best chain is switched to another branch.
+    /// The transactions of the retracted blocks that are not in the enacted ones are put back into the queue.
+    fn chain_reorganized<C>(&self, chain: &C, retracted: &[BlockHash], enacted: &[BlockHash])
+    where
+        C: BlockChainTrait + StateInfo;
+
     /// Get the type of consensus engine.
     fn engine_type(&self) -> EngineType;
 
@@ -74,6 +109,13 @@ pub trait MinerService: Send + Sync {
     where
         C: BlockChainTrait + BlockProducer + ImportBlock + EngineInfo + TermInfo;
 
+    /// Seals a block on the best block even if it's empty, returning whether it's imported.
+    /// Unlike `update_sealing`, it doesn't wait for the clock to reach the timestamp of the block,
+    /// so each block sealed in a row runs a second further ahead of the clock.
+    fn mine_block<C>(&self, chain: &C) -> bool
+    where
+        C: BlockChainTrait + BlockProducer + ImportBlock + EngineInfo + TermInfo;
+
     /// Imports transactions to mem pool.
     fn import_external_transactions<C: MiningBlockChainClient + EngineInfo + TermInfo + StateInfo>(
         &self,
@@ -82,18 +124,87 @@ pub trait MinerService: Send + Sync {
     ) -> Vec<Result<(), Error>>;
 
     /// Imports own (node owner) transaction to mem pool.
+    /// `origin` is `Local` for the transactions submitted through RPC,
+    /// and `System` for the ones the node issues itself.
     fn import_own_transaction<C: MiningBlockChainClient + EngineInfo + TermInfo + StateInfo>(
         &self,
         chain: &C,
         tx: Transaction,
+        origin: TxOrigin,
     ) -> Result<(), Error>;
 
+    /// Imports own transaction that can't be included in a block before `activation_block`.
+    /// It is kept out of the pending transactions until the chain reaches the activation block.
+    fn import_scheduled_transaction<C: MiningBlockChainClient + EngineInfo + TermInfo + StateInfo>(
+        &self,
+        chain: &C,
+        tx: Transaction,
+        activation_block: BlockNumber,
+    ) -> Result<(), Error>;
+
+    /// Get the transactions waiting for their activation blocks, with the activation blocks.
+    fn scheduled_transactions(&self) -> Vec<(BlockNumber, Transaction)>;
+
+    /// Check whether the transaction is in the mem pool, including the scheduled ones.
+    fn is_pending_transaction(&self, hash: &TxHash) -> bool;
+
+    /// Get all the transactions in the mem pool with their metadata, including the scheduled ones.
+    fn dump_mem_pool(&self) -> Vec<TransactionWithMetadata>;
+
+    /// Put the dumped transactions into the mem pool if they pass the checks, returning the loaded ones.
+    fn load_mem_pool<C: BlockChainTrait + StateInfo>(
+        &self,
+        chain: &C,
+        transactions: Vec<TransactionWithMetadata>,
+    ) -> Vec<TxHash>;
+
+    /// Executes a transaction in a block on top of `parent`, without importing it to the mem pool.
+    /// The block is discarded after the preview.
+    /// Returns `None` if `parent` is unknown or its state has been pruned.
+    fn preview_transaction<C: BlockChainTrait + StateInfo>(
+        &self,
+        chain: &C,
+        parent: BlockId,
+        tx: &Transaction,
+    ) -> Result<Option<TransactionPreview>, Error>;
+
     /// Get a list of all pending transactions in the mem pool.
     fn pending_transactions(&self, size_limit: usize, range: Range<u64>) -> PendingTransactions;
 
+    /// Get the pending transactions submitted to this node with their metadata.
+    fn local_pending_transactions(&self) -> Vec<TransactionWithMetadata>;
+
+    /// Get the lowest fee that replaces a pending transaction paying `fee`, under the fee bump shift.
+    fn replacement_fee(&self, fee: u64) -> Option<u64>;
+
+    /// Get a page of the pending transactions matching the query.
+    fn query_pending_transactions(&self, query: &PoolQuery) -> PoolPage;
+
+    /// Get the hashes of the pending transactions that can be propagated.
+    fn propagatable_transaction_hashes(&self) -> Vec<TxHash>;
+
+    /// Get the given pending transactions from the one a block would include first.
+    fn prioritized_transactions<C: BlockChainTrait + StateInfo>(
+        &self,
+        chain: &C,
+        hashes: &[TxHash],
+    ) -> Vec<Transaction>;
+
     /// Get a count of all pending transactions.
     fn count_pending_transactions(&self, range: Range<u64>) -> usize;
 
+    /// Get how many external transactions of each rate-limited type were admitted and dropped.
+    fn ingress_stats(&self) -> Vec<IngressStats>;
+
+    /// Get how many transactions invalid against the state were checked again, and how they ended up.
+    fn orphan_stats(&self) -> OrphanStats;
+
+    /// Split the bytes of the transaction into the witness and the payload.
+    fn transaction_sizes(&self, tx: &Transaction) -> TxSizes;
+
+    /// Decode the payload of the transaction with the inspector of its type, if there is one.
+    fn inspect_transaction(&self, tx: &Transaction) -> Option<Result<InspectedPayload, String>>;
+
     /// Start sealing.
     fn start_sealing<C: MiningBlockChainClient + EngineInfo + TermInfo>(&self, client: &C);
 
@@ -13,51 +13,113 @@
 //
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
 use crate::db::COL_EXTRA;
 use cnetwork::{ManagingPeerdb, SocketAddr};
 use kvdb::{DBTransaction, KeyValueDB};
 use parking_lot::Mutex;
-use rlp::RlpStream;
+use rlp::{Rlp, RlpStream};
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+const COLUMN_TO_WRITE: Option<u32> = COL_EXTRA;
+const PEER_DB_KEY: &[u8] = b"peer-list";
+
+/// The score of a peer that is seen for the first time.
+const INITIAL_SCORE: u8 = 8;
+const MAX_SCORE: u8 = 16;
+/// The score a peer loses when it fails the handshake. It is forgotten when its score reaches zero.
+const FAILURE_PENALTY: u8 = 4;
+/// The least reliable peers are forgotten beyond this.
+const MAX_KNOWN_PEERS: usize = 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct PeerRecord {
+    last_seen: u64,
+    score: u8,
+}
+
 pub struct PeerDb {
     db: Arc<dyn KeyValueDB>,
-    peers_and_count: Mutex<(HashMap<SocketAddr, u64>, usize)>,
+    peers_and_count: Mutex<(HashMap<SocketAddr, PeerRecord>, usize)>,
 }
 
 impl PeerDb {
     pub fn new(database: Arc<dyn KeyValueDB>) -> Box<Self> {
+        let peers = load_peers(&*database);
         Box::new(Self {
             db: database,
-            peers_and_count: Default::default(),
+            peers_and_count: Mutex::new((peers, 0)),
         })
     }
-}
 
-impl ManagingPeerdb for PeerDb {
-    fn insert(&self, key: SocketAddr) {
+    fn update(&self, f: impl FnOnce(&mut HashMap<SocketAddr, PeerRecord>) -> bool) {
         let (peers, count) = &mut *self.peers_and_count.lock();
-        peers.entry(key).or_insert_with(|| {
+        if f(peers) {
             *count += 1;
-            SystemTime::now().duration_since(UNIX_EPOCH).expect("There is no time machine.").as_secs()
-        });
+        }
 
         if let Some(batch) = get_db_transaction_if_enough_hit(peers, count) {
             self.db.write(batch).expect("The DB must alive");
         }
     }
+}
 
-    fn delete(&self, key: &SocketAddr) {
-        let (peers, count) = &mut *self.peers_and_count.lock();
-        if peers.remove(key).is_some() {
-            *count += 1;
-        }
+impl ManagingPeerdb for PeerDb {
+    fn on_connected(&self, key: SocketAddr) {
+        self.update(|peers| {
+            let record = peers.entry(key).or_insert(PeerRecord {
+                last_seen: 0,
+                score: INITIAL_SCORE,
+            });
+            record.last_seen = now();
+            record.score = record.score.saturating_add(1).min(MAX_SCORE);
+            if peers.len() > MAX_KNOWN_PEERS {
+                let least_reliable = *peers
+                    .iter()
+                    .filter(|(address, _)| **address != key)
+                    .min_by_key(|(_, record)| (record.score, record.last_seen))
+                    .expect("There are other peers")
+                    .0;
+                peers.remove(&least_reliable);
+            }
+            true
+        })
+    }
 
-        if let Some(batch) = get_db_transaction_if_enough_hit(peers, count) {
-            self.db.write(batch).expect("The DB must alive");
-        }
+    fn on_disconnected(&self, key: &SocketAddr) {
+        self.update(|peers| match peers.get_mut(key) {
+            Some(record) => {
+                record.last_seen = now();
+                true
+            }
+            None => false,
+        })
+    }
+
+    fn on_failure(&self, key: &SocketAddr) {
+        self.update(|peers| {
+            let forgotten = match peers.get_mut(key) {
+                Some(record) => {
+                    record.score = record.score.saturating_sub(FAILURE_PENALTY);
+                    record.score == 0
+                }
+                None => return false,
+            };
+            if forgotten {
+                cinfo!(NETWORK, "{} is forgotten after failing the handshakes", key);
+                peers.remove(key);
+            }
+            true
+        })
+    }
+
+    fn known_peers(&self) -> Vec<SocketAddr> {
+        let (peers, _) = &*self.peers_and_count.lock();
+        let mut known: Vec<_> = peers.iter().collect();
+        known.sort_by_key(|(_, record)| (std::cmp::Reverse(record.score), std::cmp::Reverse(record.last_seen)));
+        known.into_iter().map(|(address, _)| *address).collect()
     }
 }
 
@@ -72,7 +134,42 @@ impl Drop for PeerDb {
     }
 }
 
-fn get_db_transaction_if_enough_hit(peers: &HashMap<SocketAddr, u64>, count: &mut usize) -> Option<DBTransaction> {
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).expect("There is no time machine.").as_secs()
+}
+
+fn load_peers(db: &dyn KeyValueDB) -> HashMap<SocketAddr, PeerRecord> {
+    let encoded = match db.get(COLUMN_TO_WRITE, PEER_DB_KEY).expect("Low level database error") {
+        Some(encoded) => encoded,
+        None => return HashMap::new(),
+    };
+    let decoded = Rlp::new(&encoded)
+        .iter()
+        .map(|peer| {
+            let address = peer.val_at(0)?;
+            let last_seen = peer.val_at(1)?;
+            // The peers stored before the scores were introduced start with the initial score.
+            let score = if peer.item_count()? > 2 {
+                peer.val_at(2)?
+            } else {
+                INITIAL_SCORE
+            };
+            Ok((address, PeerRecord {
+                last_seen,
+                score,
+            }))
+        })
+        .collect::<Result<_, rlp::DecoderError>>();
+    decoded.unwrap_or_else(|err| {
+        cwarn!(NETWORK, "Cannot load the known peers: {:?}", err);
+        HashMap::new()
+    })
+}
+
+fn get_db_transaction_if_enough_hit(
+    peers: &HashMap<SocketAddr, PeerRecord>,
+    count: &mut usize,
+) -> Option<DBTransaction> {
     const UPDATE_AT: usize = 10;
     if *count < UPDATE_AT {
         return None
@@ -83,17 +180,66 @@ fn get_db_transaction_if_enough_hit(peers: &HashMap<SocketAddr, u64>, count: &mu
     Some(get_db_transaction(peers))
 }
 
-fn get_db_transaction(peers: &HashMap<SocketAddr, u64>) -> DBTransaction {
+fn get_db_transaction(peers: &HashMap<SocketAddr, PeerRecord>) -> DBTransaction {
     let mut s = RlpStream::new_list(peers.len());
-    for (address, time) in peers {
-        s.begin_list(2).append(address).append(time);
+    for (address, record) in peers {
+        s.begin_list(3).append(address).append(&record.last_seen).append(&record.score);
     }
     let encoded = s.drain();
 
     let mut batch = DBTransaction::new();
-
-    const COLUMN_TO_WRITE: Option<u32> = COL_EXTRA;
-    const PEER_DB_KEY: &[u8] = b"peer-list";
     batch.put(COLUMN_TO_WRITE, PEER_DB_KEY, &encoded);
     batch
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory_db() -> Arc<dyn KeyValueDB> {
+        Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)))
+    }
+
+    #[test]
+    fn known_peers_survive_a_restart_in_the_order_of_their_reliability() {
+        let db = memory_db();
+        let reliable = SocketAddr::v4(10, 0, 0, 1, 3485);
+        let flaky = SocketAddr::v4(10, 0, 0, 2, 3485);
+        {
+            let peer_db = PeerDb::new(Arc::clone(&db));
+            peer_db.on_connected(flaky);
+            peer_db.on_connected(reliable);
+            peer_db.on_connected(reliable);
+            peer_db.on_disconnected(&reliable);
+            peer_db.on_failure(&flaky);
+        }
+        let peer_db = PeerDb::new(db);
+        assert_eq!(vec![reliable, flaky], peer_db.known_peers());
+    }
+
+    #[test]
+    fn peers_failing_the_handshakes_are_forgotten() {
+        let peer_db = PeerDb::new(memory_db());
+        let address = SocketAddr::v4(10, 0, 0, 1, 3485);
+        peer_db.on_connected(address);
+        for _ in 0..2 {
+            peer_db.on_failure(&address);
+        }
+        assert_eq!(vec![address], peer_db.known_peers());
+        peer_db.on_failure(&address);
+        assert!(peer_db.known_peers().is_empty());
+    }
+
+    #[test]
+    fn peers_stored_without_scores_are_loaded() {
+        let db = memory_db();
+        let address = SocketAddr::v4(10, 0, 0, 1, 3485);
+        let mut s = RlpStream::new_list(1);
+        s.begin_list(2).append(&address).append(&1_600_000_000u64);
+        let mut batch = DBTransaction::new();
+        batch.put(COLUMN_TO_WRITE, PEER_DB_KEY, &s.drain());
+        db.write(batch).unwrap();
+
+        assert_eq!(vec![address], PeerDb::new(db).known_peers());
+    }
+}
@@ -0,0 +1,166 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::error::BlockError;
+use ctypes::util::unexpected::Mismatch;
+use ctypes::{BlockHash, BlockNumber, Header};
+use primitives::H256;
+use std::collections::BTreeMap;
+
+/// A block the scheme pins, so the nodes reject any history without it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub number: BlockNumber,
+    pub hash: BlockHash,
+    pub next_validator_set_hash: H256,
+}
+
+impl From<cjson::scheme::Checkpoint> for Checkpoint {
+    fn from(c: cjson::scheme::Checkpoint) -> Self {
+        Checkpoint {
+            number: c.number.into(),
+            hash: H256::from(c.hash).into(),
+            next_validator_set_hash: c.next_validator_set_hash.into(),
+        }
+    }
+}
+
+/// The checkpoints of a chain by their block numbers.
+#[derive(Debug, Clone, Default)]
+pub struct Checkpoints(BTreeMap<BlockNumber, Checkpoint>);
+
+impl Checkpoints {
+    pub fn new(checkpoints: impl IntoIterator<Item = Checkpoint>) -> Result<Self, String> {
+        let mut by_number = BTreeMap::new();
+        for checkpoint in checkpoints {
+            if checkpoint.number == 0 {
+                return Err("The genesis block can't be a checkpoint".to_string())
+            }
+            if by_number.insert(checkpoint.number, checkpoint).is_some() {
+                return Err(format!("Block #{} is pinned twice", checkpoint.number))
+            }
+        }
+        Ok(Checkpoints(by_number))
+    }
+
+    pub fn get(&self, number: BlockNumber) -> Option<&Checkpoint> {
+        self.0.get(&number)
+    }
+
+    /// The checkpoints at or above `number`, from the highest.
+    pub fn at_or_above(&self, number: BlockNumber) -> impl Iterator<Item = &Checkpoint> {
+        self.0.range(number..).rev().map(|(_, checkpoint)| checkpoint)
+    }
+
+    /// Checks that the header is the pinned one if a checkpoint is at its number.
+    pub fn verify(&self, header: &Header) -> Result<(), BlockError> {
+        let checkpoint = match self.get(header.number()) {
+            Some(checkpoint) => checkpoint,
+            None => return Ok(()),
+        };
+        let hash = header.hash();
+        if hash != checkpoint.hash {
+            return Err(BlockError::CheckpointMismatch(Mismatch {
+                expected: checkpoint.hash,
+                found: hash,
+            }))
+        }
+        if *header.next_validator_set_hash() != checkpoint.next_validator_set_hash {
+            return Err(BlockError::InvalidNextValidatorSetHash(Mismatch {
+                expected: checkpoint.next_validator_set_hash,
+                found: *header.next_validator_set_hash(),
+            }))
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(number: BlockNumber, next_validator_set_hash: H256) -> Header {
+        let mut header = Header::default();
+        header.set_number(number);
+        header.set_next_validator_set_hash(next_validator_set_hash);
+        header
+    }
+
+    fn checkpoint_of(header: &Header) -> Checkpoint {
+        Checkpoint {
+            number: header.number(),
+            hash: header.hash(),
+            next_validator_set_hash: *header.next_validator_set_hash(),
+        }
+    }
+
+    #[test]
+    fn only_the_pinned_header_passes_at_a_checkpoint() {
+        let pinned = header(10, H256::from(1));
+        let checkpoints = Checkpoints::new(vec![checkpoint_of(&pinned)]).unwrap();
+
+        assert_eq!(Ok(()), checkpoints.verify(&pinned));
+
+        let alternative = header(10, H256::from(2));
+        assert_eq!(
+            Err(BlockError::CheckpointMismatch(Mismatch {
+                expected: pinned.hash(),
+                found: alternative.hash(),
+            })),
+            checkpoints.verify(&alternative)
+        );
+
+        // Headers at the other numbers are left to the usual verification.
+        assert_eq!(Ok(()), checkpoints.verify(&header(9, H256::from(2))));
+        assert_eq!(Ok(()), checkpoints.verify(&header(11, H256::from(2))));
+    }
+
+    #[test]
+    fn a_checkpoint_with_a_wrong_validator_set_hash_rejects_the_header() {
+        let pinned = header(10, H256::from(1));
+        let checkpoints = Checkpoints::new(vec![Checkpoint {
+            next_validator_set_hash: H256::from(2),
+            ..checkpoint_of(&pinned)
+        }])
+        .unwrap();
+        assert_eq!(
+            Err(BlockError::InvalidNextValidatorSetHash(Mismatch {
+                expected: H256::from(2),
+                found: H256::from(1),
+            })),
+            checkpoints.verify(&pinned)
+        );
+    }
+
+    #[test]
+    fn checkpoints_are_ordered_by_number() {
+        let checkpoints = Checkpoints::new(vec![
+            checkpoint_of(&header(30, H256::from(3))),
+            checkpoint_of(&header(10, H256::from(1))),
+            checkpoint_of(&header(20, H256::from(2))),
+        ])
+        .unwrap();
+        assert_eq!(vec![30, 20], checkpoints.at_or_above(20).map(|checkpoint| checkpoint.number).collect::<Vec<_>>());
+        assert_eq!(0, checkpoints.at_or_above(31).count());
+    }
+
+    #[test]
+    fn a_block_cannot_be_pinned_twice() {
+        let checkpoint = checkpoint_of(&header(10, H256::from(1)));
+        assert!(Checkpoints::new(vec![checkpoint, checkpoint]).is_err());
+        assert!(Checkpoints::new(vec![checkpoint_of(&header(0, H256::from(1)))]).is_err());
+    }
+}
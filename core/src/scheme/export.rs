@@ -0,0 +1,192 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::Scheme;
+use crate::client::{import_genesis_state, open_block_state, state_chunks, translate_validators, ForkError, ForkPoint};
+use crate::db;
+use crate::error::{Error, SchemeError};
+use cdb::{new_journaldb, Algorithm};
+use ckey::Ed25519Public as Public;
+use cstate::StateDB;
+use ctypes::BlockNumber;
+use kvdb::KeyValueDB;
+use merkle_trie::snapshot::Error as SnapshotError;
+use primitives::{Bytes, H256};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The genesis of a new chain that starts from the state of another chain.
+pub struct ForkedGenesis {
+    /// The block of the old chain whose state is carried.
+    pub fork_point: ForkPoint,
+    /// The timestamp of the block at the fork point.
+    pub timestamp: u64,
+    /// The root of the genesis state, which differs from the one at the fork point when validators are replaced.
+    pub state_root: H256,
+    /// The snapshot chunks of the genesis state, by their roots.
+    pub chunks: HashMap<H256, Bytes>,
+}
+
+impl Scheme {
+    /// Exports the state of this chain at block `number` as the genesis of a new chain.
+    ///
+    /// The genesis state has the modules' substorages and the validator sets at the block as they are,
+    /// except that the keys of the validators in `translation` are replaced.
+    pub fn export_genesis(
+        &self,
+        db: Arc<dyn KeyValueDB>,
+        number: BlockNumber,
+        translation: &HashMap<Public, Public>,
+    ) -> Result<ForkedGenesis, Error> {
+        let (header, state_db) = open_block_state(self, db, number)?;
+        let fork_point = ForkPoint::from(&header);
+        let timestamp = header.timestamp();
+        let export_error = |err: SnapshotError| ForkError::Export(err.to_string());
+        let chunks = state_chunks(&state_db, fork_point.state_root).map_err(export_error)?;
+        if translation.is_empty() {
+            return Ok(ForkedGenesis {
+                fork_point,
+                timestamp,
+                state_root: fork_point.state_root,
+                chunks,
+            })
+        }
+
+        // The translated state is built on a scratch database so that the old chain's database is left untouched.
+        let scratch: Arc<dyn KeyValueDB> = Arc::new(kvdb_memorydb::create(db::NUM_COLUMNS.unwrap_or(0)));
+        import_genesis_state(&scratch, fork_point.state_root, &chunks)?;
+        let state_root = translate_validators(&scratch, fork_point.state_root, translation)?;
+        let scratch_state = StateDB::new(new_journaldb(scratch, Algorithm::Archive, db::COL_STATE));
+        let chunks = state_chunks(&scratch_state, state_root).map_err(export_error)?;
+        Ok(ForkedGenesis {
+            fork_point,
+            timestamp,
+            state_root,
+            chunks,
+        })
+    }
+}
+
+impl ForkedGenesis {
+    /// Writes this genesis into the JSON of a scheme, leaving the rest of the scheme as it is.
+    pub fn write_into(&self, scheme: &mut Value) -> Result<(), SchemeError> {
+        let genesis = scheme
+            .get_mut("genesis")
+            .and_then(Value::as_object_mut)
+            .ok_or_else(|| SchemeError::InvalidGenesis("The scheme has no genesis".to_string()))?;
+        let state = cjson::scheme::GenesisState {
+            chunks: self.chunks.iter().map(|(root, chunk)| ((*root).into(), chunk.clone().into())).collect(),
+        };
+        genesis.insert("parentHash".to_string(), json!(format!("0x{:x}", *self.fork_point.block_hash)));
+        genesis.insert("timestamp".to_string(), json!(format!("0x{:x}", self.timestamp)));
+        genesis.insert("stateRoot".to_string(), json!(format!("0x{:x}", self.state_root)));
+        genesis.insert("state".to_string(), serde_json::to_value(&state).expect("Genesis state is serializable"));
+        // The checkpoints pin the blocks of the old chain, which the new one doesn't have.
+        if let Some(scheme) = scheme.as_object_mut() {
+            scheme.remove("checkpoints");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cstate::{Metadata, NextValidatorSet, StateWithCache, TopLevelState, TopState};
+    use ctypes::{CommonParams, CompactValidatorEntry, CompactValidatorSet, ConsensusParams};
+    use kvdb::DBTransaction;
+
+    fn empty_db() -> Arc<dyn KeyValueDB> {
+        Arc::new(kvdb_memorydb::create(db::NUM_COLUMNS.unwrap_or(0)))
+    }
+
+    fn state_db(db: &Arc<dyn KeyValueDB>) -> StateDB {
+        StateDB::new(new_journaldb(Arc::clone(db), Algorithm::Archive, db::COL_STATE))
+    }
+
+    /// Returns a chain whose genesis state has a module and the given validators.
+    fn chain_with_validators(validators: &[u64]) -> (Scheme, Arc<dyn KeyValueDB>) {
+        let db = empty_db();
+        let mut state = TopLevelState::from_existing(state_db(&db), ccrypto::BLAKE_NULL_RLP).unwrap();
+        state.create_module().unwrap();
+        *state.get_metadata_mut().unwrap() =
+            Metadata::new(CommonParams::default(), ConsensusParams::default_for_test());
+        let entries = validators
+            .iter()
+            .map(|seed| CompactValidatorEntry {
+                public_key: Public::from(*seed),
+                delegation: 100,
+            })
+            .collect();
+        NextValidatorSet::from_compact_validator_set(CompactValidatorSet::new(entries))
+            .save_to_state(&mut state)
+            .unwrap();
+        let (mut committed, root) = state.commit_and_clone_db().unwrap();
+        let mut batch = DBTransaction::new();
+        committed.journal_under(&mut batch, 0, H256::zero()).unwrap();
+        db.write(batch).unwrap();
+
+        let scheme = Scheme::new_test();
+        scheme.set_state_root(root);
+        (scheme, db)
+    }
+
+    fn load_forked_scheme(genesis: &ForkedGenesis) -> Scheme {
+        let mut json: Value = serde_json::from_slice(include_bytes!("../../res/null.json")).unwrap();
+        genesis.write_into(&mut json).unwrap();
+        Scheme::load(json.to_string().as_bytes()).unwrap()
+    }
+
+    fn validators_of(scheme: &Scheme) -> Vec<Public> {
+        let db = empty_db();
+        import_genesis_state(&db, scheme.state_root(), scheme.genesis_state.as_ref().unwrap()).unwrap();
+        let state = TopLevelState::from_existing(state_db(&db), scheme.state_root()).unwrap();
+        NextValidatorSet::load_from_state(&state).unwrap().iter().map(|v| *v.pubkey()).collect()
+    }
+
+    #[test]
+    fn exported_genesis_carries_the_state() {
+        let (scheme, db) = chain_with_validators(&[1, 2]);
+        let genesis = scheme.export_genesis(db, 0, &HashMap::new()).unwrap();
+        assert_eq!(scheme.state_root(), genesis.state_root);
+
+        let forked = load_forked_scheme(&genesis);
+        assert_eq!(genesis.state_root, forked.state_root());
+        assert_eq!(scheme.genesis_header().hash(), forked.parent_hash);
+        assert_eq!(vec![Public::from(1), Public::from(2)], validators_of(&forked));
+    }
+
+    #[test]
+    fn exported_genesis_replaces_validators() {
+        let (scheme, db) = chain_with_validators(&[1, 2]);
+        let translation = vec![(Public::from(1), Public::from(3))].into_iter().collect();
+        let genesis = scheme.export_genesis(db, 0, &translation).unwrap();
+        assert_ne!(scheme.state_root(), genesis.state_root);
+
+        let forked = load_forked_scheme(&genesis);
+        assert_eq!(vec![Public::from(2), Public::from(3)], validators_of(&forked));
+    }
+
+    #[test]
+    fn unknown_block_is_rejected() {
+        let (scheme, db) = chain_with_validators(&[1]);
+        match scheme.export_genesis(db, 1, &HashMap::new()) {
+            Err(Error::Fork(ForkError::UnknownBlock(1))) => {}
+            result => panic!("Unexpected result: {:?}", result.map(|genesis| genesis.state_root)),
+        }
+    }
+}
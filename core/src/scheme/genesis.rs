@@ -19,6 +19,7 @@ use ccrypto::BLAKE_NULL_RLP;
 use ckey::{Ed25519Public as Public, PlatformAddress};
 use ctypes::BlockHash;
 use primitives::{Bytes, H256};
+use std::collections::HashMap;
 
 /// Genesis components.
 pub struct Genesis {
@@ -38,6 +39,8 @@ pub struct Genesis {
     pub next_validator_set_hash: Option<H256>,
     /// The genesis block's extra data field.
     pub extra_data: Bytes,
+    /// The snapshot chunks of the state exported from another chain, by their roots.
+    pub state: Option<HashMap<H256, Bytes>>,
 }
 
 impl From<cjson::scheme::Genesis> for Genesis {
@@ -51,6 +54,9 @@ impl From<cjson::scheme::Genesis> for Genesis {
             state_root: g.state_root.map(Into::into),
             next_validator_set_hash: g.next_validator_set_hash.map(Into::into),
             extra_data: g.extra_data.map_or_else(Vec::new, Into::into),
+            state: g
+                .state
+                .map(|state| state.chunks.into_iter().map(|(root, chunk)| (root.into(), chunk.into())).collect()),
         }
     }
 }
@@ -14,10 +14,15 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+mod checkpoint;
+mod export;
 mod genesis;
 #[cfg_attr(feature = "cargo-clippy", allow(clippy::module_inception))]
 mod scheme;
 mod seal;
+mod validation;
 
+pub use self::checkpoint::{Checkpoint, Checkpoints};
+pub use self::export::ForkedGenesis;
 pub use self::genesis::Genesis;
 pub use self::scheme::Scheme;
@@ -15,9 +15,9 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::seal::Generic as GenericSeal;
-use super::Genesis;
-use crate::consensus::{ConsensusEngine, NullEngine, Solo, Tendermint};
-use crate::error::Error;
+use super::{Checkpoints, Genesis};
+use crate::consensus::{create_engine, ConsensusEngine};
+use crate::error::{Error, SchemeError};
 use ccrypto::BLAKE_NULL_RLP;
 use cdb::HashDB;
 use ckey::Ed25519Public as Public;
@@ -25,6 +25,7 @@ use ctypes::{BlockHash, Header};
 use parking_lot::RwLock;
 use primitives::{Bytes, H256};
 use rlp::{Rlp, RlpStream};
+use std::collections::HashMap;
 use std::io::Read;
 use std::sync::Arc;
 
@@ -41,6 +42,9 @@ pub struct Scheme {
     /// Known nodes on the network in enode format.
     pub nodes: Vec<String>,
 
+    /// The blocks the nodes never revert, so the histories without them are rejected.
+    pub checkpoints: Checkpoints,
+
     /// The genesis block's parent hash field.
     pub parent_hash: BlockHash,
     /// The genesis block's author field.
@@ -53,6 +57,9 @@ pub struct Scheme {
     pub extra_data: Bytes,
     /// Each seal field, expressed as RLP, concatenated.
     pub seal_rlp: Bytes,
+    /// The snapshot chunks of the genesis state when the chain starts from the state of another chain.
+    /// The modules don't initialize the genesis state in that case.
+    pub genesis_state: Option<HashMap<H256, Bytes>>,
 
     /// May be prepopulated if we know this in advance.
     state_root_memo: RwLock<H256>,
@@ -74,16 +81,6 @@ macro_rules! load_bundled {
 }
 
 impl Scheme {
-    /// Convert engine scheme into a arc'd Engine of the right underlying type.
-    /// TODO avoid this hard-coded nastiness - use dynamic-linked plugin framework instead.
-    fn engine(engine_scheme: cjson::scheme::Engine) -> Arc<dyn ConsensusEngine> {
-        match engine_scheme {
-            cjson::scheme::Engine::Null => Arc::new(NullEngine::default()),
-            cjson::scheme::Engine::Solo => Arc::new(Solo::new()),
-            cjson::scheme::Engine::Tendermint(tendermint) => Tendermint::new(tendermint.params.into()),
-        }
-    }
-
     pub fn check_genesis_root(&self, db: &dyn HashDB) -> bool {
         if db.is_empty() {
             return true
@@ -158,22 +155,29 @@ impl Scheme {
 }
 
 /// Load from JSON object.
-fn load_from(s: cjson::scheme::Scheme) -> Result<Scheme, Error> {
+pub(super) fn load_from(s: cjson::scheme::Scheme) -> Result<Scheme, Error> {
     let g = Genesis::from(s.genesis);
+    if g.state.is_some() && g.state_root.is_none() {
+        return Err(SchemeError::InvalidGenesis("The genesis carries a state without its root".to_string()).into())
+    }
     let GenericSeal(seal_rlp) = g.seal.into();
-    let engine = Scheme::engine(s.engine);
+    let engine = create_engine(s.engine)?;
+    let checkpoints = Checkpoints::new(s.checkpoints.unwrap_or_default().into_iter().map(Into::into))
+        .map_err(SchemeError::InvalidCheckpoints)?;
 
     let mut s = Scheme {
         name: s.name.clone(),
         engine,
         data_dir: s.data_dir.unwrap_or(s.name),
         nodes: s.nodes.unwrap_or_else(Vec::new),
+        checkpoints,
         parent_hash: g.parent_hash,
         transactions_root: g.transactions_root,
         author: g.author,
         timestamp: g.timestamp,
         extra_data: g.extra_data,
         seal_rlp,
+        genesis_state: g.state,
         state_root_memo: RwLock::new(Default::default()), // will be overwritten right after.
     };
 
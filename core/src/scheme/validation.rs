@@ -0,0 +1,304 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::scheme::load_from;
+use super::Scheme;
+use crate::client::{import_genesis_state, Client};
+use crate::db::{COL_STATE, NUM_COLUMNS};
+use crate::error::{Error, SchemeError};
+use ccrypto::BLAKE_NULL_RLP;
+use cdb::{new_journaldb, Algorithm};
+use coordinator::context::StorageAccess;
+use coordinator::engine::Initializer;
+use cstate::{NextValidatorSet, StateDB, TopLevelState, TopStateView};
+use ctypes::{CommonParams, CompactValidatorSet, ConsensusParams};
+use kvdb::KeyValueDB;
+use parking_lot::Mutex;
+use primitives::{Bytes, H256};
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+
+impl Scheme {
+    /// Checks the scheme as a node would on its first boot, without booting one.
+    ///
+    /// The genesis state is built on a scratch database in memory with `initializer`,
+    /// so the genesis configs of the modules and the validator set they build are checked as well.
+    /// When the scheme carries the state of another chain, that state is restored and checked instead.
+    /// All the problems found are returned rather than the first one.
+    pub fn validate<R>(reader: R, initializer: &impl Initializer) -> Result<Scheme, Vec<SchemeError>>
+    where
+        R: Read, {
+        let json =
+            cjson::scheme::Scheme::load(reader).map_err(|err| vec![SchemeError::InvalidJson(err.to_string())])?;
+        let mut errors = Vec::new();
+
+        if let Err(reason) = CommonParams::from(json.params.clone()).verify() {
+            errors.push(SchemeError::InvalidParams(reason));
+        }
+        let params = ConsensusParams::from(json.params.clone());
+        if let Err(reason) = params.verify() {
+            errors.push(SchemeError::InvalidParams(reason));
+        }
+
+        let scheme = match load_from(json) {
+            Ok(scheme) => scheme,
+            Err(Error::Scheme(err)) => {
+                errors.push(err);
+                return Err(errors)
+            }
+            Err(err) => {
+                errors.push(SchemeError::InvalidGenesis(err.to_string()));
+                return Err(errors)
+            }
+        };
+        errors.extend(check_genesis_header(&scheme, &params));
+
+        let outcome = match &scheme.genesis_state {
+            Some(chunks) => match restore_carried_state(scheme.state_root(), chunks) {
+                Ok(outcome) => Some(outcome),
+                Err(err) => {
+                    errors.push(err);
+                    None
+                }
+            },
+            None => {
+                let recorder = GenesisRecorder {
+                    initializer,
+                    outcome: Mutex::new(None),
+                };
+                let initialized = panic::catch_unwind(AssertUnwindSafe(|| {
+                    Client::initialize_state(StateDB::new_with_memorydb(), &recorder)
+                }));
+                match initialized {
+                    Ok(Ok((_, root))) => {
+                        let memoized = scheme.state_root();
+                        if memoized != H256::zero() && memoized != root {
+                            errors.push(SchemeError::InvalidState);
+                        }
+                        scheme.set_state_root(root);
+                    }
+                    Ok(Err(err)) => errors.push(SchemeError::InvalidModuleGenesis(err.to_string())),
+                    Err(payload) => errors.push(SchemeError::InvalidModuleGenesis(panic_message(&*payload))),
+                }
+                recorder.outcome.into_inner()
+            }
+        };
+
+        if let Some((validators, genesis_params)) = outcome {
+            errors.extend(check_validators(&validators, scheme.engine.engine_type().need_signer_key()));
+            if let Err(reason) = genesis_params.verify() {
+                errors.push(SchemeError::InvalidParams(format!("The modules set invalid params: {}", reason)));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(scheme)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Restores the state carried in the genesis on a scratch database and reads what the modules would have set.
+fn restore_carried_state(
+    root: H256,
+    chunks: &HashMap<H256, Bytes>,
+) -> Result<(CompactValidatorSet, ConsensusParams), SchemeError> {
+    let invalid_state = |err: Error| SchemeError::InvalidGenesis(format!("The carried state is invalid: {}", err));
+    let db: Arc<dyn KeyValueDB> = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap_or(0)));
+    import_genesis_state(&db, root, chunks).map_err(invalid_state)?;
+
+    let state_db = StateDB::new(new_journaldb(db, Algorithm::Archive, COL_STATE));
+    let state = TopLevelState::from_existing(state_db, root).map_err(|err| invalid_state(err.into()))?;
+    let validators = NextValidatorSet::load_from_state(&state).map_err(|err| invalid_state(err.into()))?;
+    let metadata = state
+        .metadata()
+        .map_err(|err| invalid_state(err.into()))?
+        .ok_or_else(|| SchemeError::InvalidGenesis("The carried state has no metadata".to_string()))?;
+    Ok((validators.create_compact_validator_set(), metadata.consensus_params().clone()))
+}
+
+fn check_genesis_header(scheme: &Scheme, params: &ConsensusParams) -> Vec<SchemeError> {
+    let mut errors = Vec::new();
+    if scheme.transactions_root != BLAKE_NULL_RLP {
+        errors.push(SchemeError::InvalidGenesis(format!(
+            "The genesis block has no transactions but its transactions root is {}",
+            scheme.transactions_root
+        )));
+    }
+    let max_extra_data_size = params.max_extra_data_size() as usize;
+    if scheme.extra_data.len() > max_extra_data_size {
+        errors.push(SchemeError::InvalidGenesis(format!(
+            "The extra data is {} bytes while at most {} bytes are allowed",
+            scheme.extra_data.len(),
+            max_extra_data_size
+        )));
+    }
+    errors
+}
+
+fn check_validators(validators: &CompactValidatorSet, required: bool) -> Vec<SchemeError> {
+    let mut errors = Vec::new();
+    if required && validators.is_empty() {
+        errors.push(SchemeError::InvalidGenesis("The engine needs validators but the modules set none".to_string()));
+    }
+    let mut seen = HashSet::new();
+    for (index, validator) in validators.iter().enumerate() {
+        let reason = if validator.public_key.is_zero() {
+            "the public key is zero"
+        } else if !seen.insert(validator.public_key) {
+            "the public key is used by another validator"
+        } else if validator.delegation == 0 {
+            "no delegation"
+        } else {
+            continue
+        };
+        errors.push(SchemeError::InvalidValidator {
+            index,
+            reason: reason.to_string(),
+        });
+    }
+    errors
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    match payload.downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => match payload.downcast_ref::<String>() {
+            Some(message) => message.clone(),
+            None => "A module panicked".to_string(),
+        },
+    }
+}
+
+/// Keeps what the modules returned from the genesis, to check it after the genesis state is built.
+struct GenesisRecorder<'a, I> {
+    initializer: &'a I,
+    outcome: Mutex<Option<(CompactValidatorSet, ConsensusParams)>>,
+}
+
+impl<'a, I: Initializer> Initializer for GenesisRecorder<'a, I> {
+    fn number_of_sub_storages(&self) -> usize {
+        self.initializer.number_of_sub_storages()
+    }
+
+    fn initialize_chain(&self, storage: &mut dyn StorageAccess) -> (CompactValidatorSet, ConsensusParams) {
+        let outcome = self.initializer.initialize_chain(storage);
+        *self.outcome.lock() = Some(outcome.clone());
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckey::Ed25519Public as Public;
+    use coordinator::test_coordinator::TestCoordinator;
+    use ctypes::CompactValidatorEntry;
+
+    const SOLO: &[u8] = include_bytes!("../../res/solo.json");
+
+    struct PanickingGenesis;
+
+    impl Initializer for PanickingGenesis {
+        fn number_of_sub_storages(&self) -> usize {
+            1
+        }
+
+        fn initialize_chain(&self, _storage: &mut dyn StorageAccess) -> (CompactValidatorSet, ConsensusParams) {
+            panic!("invalid genesis config of the module")
+        }
+    }
+
+    #[test]
+    fn bundled_scheme_is_valid() {
+        let scheme = Scheme::validate(SOLO, &TestCoordinator::default()).unwrap();
+        assert_ne!(scheme.state_root(), H256::zero());
+    }
+
+    #[test]
+    fn reports_every_problem_of_the_scheme() {
+        let json = String::from_utf8(SOLO.to_vec())
+            .unwrap()
+            .replace(r#""maxBodySize": 4194304"#, r#""maxBodySize": 0"#)
+            .replace(r#""minDeposit": 1"#, r#""minDeposit": 0"#);
+        let errors = Scheme::validate(json.as_bytes(), &TestCoordinator::default()).unwrap_err();
+        assert_eq!(errors, vec![
+            SchemeError::InvalidParams("You should set the minimum deposit".to_string()),
+            SchemeError::InvalidParams("You should set the maximum body size".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn reports_invalid_json() {
+        let errors = Scheme::validate(&b"{}"[..], &TestCoordinator::default()).unwrap_err();
+        assert!(matches!(errors.as_slice(), [SchemeError::InvalidJson(_)]));
+    }
+
+    #[test]
+    fn reports_a_block_pinned_twice() {
+        let checkpoint = r#"{
+            "number": 10,
+            "hash": "0x3ab5e1f1ab7b4c4d8b2c5b3c7f49e2c24c2e8d6a7f4cde3f2a1b9c8d7e6f5a40",
+            "nextValidatorSetHash": "0x45b0cfc220ceec5b7c1c62c4d4193d38e4eba48e8815729ce75f9c0ab0e4c1c0"
+        }"#;
+        let json = String::from_utf8(SOLO.to_vec())
+            .unwrap()
+            .replace(r#""genesis": {"#, &format!(r#""checkpoints": [{0}, {0}], "genesis": {{"#, checkpoint));
+        let errors = Scheme::validate(json.as_bytes(), &TestCoordinator::default()).unwrap_err();
+        assert_eq!(errors, vec![SchemeError::InvalidCheckpoints("Block #10 is pinned twice".to_string())]);
+    }
+
+    #[test]
+    fn reports_panics_of_the_modules() {
+        let errors = Scheme::validate(SOLO, &PanickingGenesis).unwrap_err();
+        assert_eq!(errors, vec![SchemeError::InvalidModuleGenesis("invalid genesis config of the module".to_string())]);
+    }
+
+    #[test]
+    fn reports_invalid_validators() {
+        let validator = |public_key, delegation| CompactValidatorEntry {
+            public_key,
+            delegation,
+        };
+        let public = Public::random();
+        let validators = CompactValidatorSet::new(vec![
+            validator(public, 10),
+            validator(Public::default(), 10),
+            validator(public, 10),
+            validator(Public::random(), 0),
+        ]);
+        let errors = check_validators(&validators, true);
+        assert_eq!(
+            errors
+                .into_iter()
+                .map(|err| match err {
+                    SchemeError::InvalidValidator {
+                        index,
+                        ..
+                    } => index,
+                    err => panic!("Unexpected {}", err),
+                })
+                .collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(check_validators(&CompactValidatorSet::new(Vec::new()), true).len(), 1);
+        assert!(check_validators(&CompactValidatorSet::new(Vec::new()), false).is_empty());
+    }
+}
@@ -18,13 +18,14 @@ use crate::client::{Client, ClientConfig};
 use crate::error::Error;
 use crate::miner::Miner;
 use crate::scheme::Scheme;
-use cio::{IoContext, IoHandler, IoHandlerResult, IoService};
-use coordinator::Coordinator;
+use cio::{IoContext, IoHandler, IoHandlerResult, IoService, TimerToken};
+use coordinator::engine::{BlockExecutor, GraphQlHandlerProvider, Initializer, NetworkIdentityProvider, TxFilter};
 use ctimer::TimerApi;
 use ctypes::{BlockHash, BlockId};
 use kvdb::KeyValueDB;
 use primitives::Bytes;
 use std::sync::Arc;
+use std::time::Duration;
 
 /// Client service setup.
 pub struct ClientService {
@@ -33,12 +34,14 @@ pub struct ClientService {
 }
 
 impl ClientService {
-    pub fn start(
+    pub fn start<
+        C: 'static + Initializer + BlockExecutor + TxFilter + GraphQlHandlerProvider + NetworkIdentityProvider,
+    >(
         config: &ClientConfig,
         scheme: &Scheme,
         db: Arc<dyn KeyValueDB>,
         miner: Arc<Miner>,
-        coordinator: Arc<Coordinator>,
+        coordinator: Arc<C>,
         reseal_timer: TimerApi,
     ) -> Result<ClientService, Error> {
         let io_service = IoService::<ClientIoMessage>::start("Client")?;
@@ -81,12 +84,27 @@ pub enum ClientIoMessage {
     UpdateBestAsCommitted(BlockHash),
 }
 
+const EVENT_BACKFILL_TIMER_TOKEN: TimerToken = 0;
+const EVENT_BACKFILL_INTERVAL: Duration = Duration::from_secs(1);
+
 /// IO interface for the Client handler
 struct ClientIoHandler {
     client: Arc<Client>,
 }
 
 impl IoHandler<ClientIoMessage> for ClientIoHandler {
+    fn initialize(&self, io: &IoContext<ClientIoMessage>) -> IoHandlerResult<()> {
+        io.register_timer(EVENT_BACKFILL_TIMER_TOKEN, EVENT_BACKFILL_INTERVAL);
+        Ok(())
+    }
+
+    fn timeout(&self, _io: &IoContext<ClientIoMessage>, timer: TimerToken) -> IoHandlerResult<()> {
+        if timer == EVENT_BACKFILL_TIMER_TOKEN {
+            self.client.backfill_events();
+        }
+        Ok(())
+    }
+
     fn message(&self, _io: &IoContext<ClientIoMessage>, net_message: ClientIoMessage) -> IoHandlerResult<()> {
         match net_message {
             ClientIoMessage::BlockVerified => {
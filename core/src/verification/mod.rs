@@ -16,9 +16,11 @@
 
 pub mod queue;
 #[cfg_attr(feature = "cargo-clippy", allow(clippy::module_inception))]
+mod transaction_verifier;
 mod verification;
 mod verifier;
 
 pub use self::queue::{BlockQueue, Config as QueueConfig};
+pub use self::transaction_verifier::TransactionVerifier;
 pub use self::verification::*;
 pub use self::verifier::Verifier;
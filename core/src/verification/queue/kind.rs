@@ -19,6 +19,7 @@ pub use self::headers::Headers;
 use crate::consensus::ConsensusEngine;
 use crate::error::Error;
 use crate::service::ClientIoMessage;
+use crate::verification::TransactionVerifier;
 use ctypes::BlockHash;
 use rlp::*;
 
@@ -67,8 +68,12 @@ pub trait Kind: 'static + Sized + Send + Sync {
     /// Attempt to create the `Unverified` item from the input.
     fn create(input: Self::Input, engine: &dyn ConsensusEngine) -> Result<Self::Unverified, Error>;
 
-    /// Attempt to verify the `Unverified` item using the given engine.
-    fn verify(unverified: Self::Unverified, engine: &dyn ConsensusEngine) -> Result<Self::Verified, Error>;
+    /// Attempt to verify the `Unverified` item using the given engine and transaction verifier.
+    fn verify(
+        unverified: Self::Unverified,
+        engine: &dyn ConsensusEngine,
+        transaction_verifier: &TransactionVerifier,
+    ) -> Result<Self::Verified, Error>;
 
     fn signal() -> ClientIoMessage;
 }
@@ -82,7 +87,7 @@ pub mod headers {
     use crate::consensus::ConsensusEngine;
     use crate::error::{BlockError, Error};
     use crate::service::ClientIoMessage;
-    use crate::verification::verify_header_with_engine;
+    use crate::verification::{verify_header_with_engine, TransactionVerifier};
 
     impl BlockLike for SyncHeader {
         fn hash(&self) -> BlockHash {
@@ -113,7 +118,11 @@ pub mod headers {
             Ok(input)
         }
 
-        fn verify(un: Self::Unverified, engine: &dyn ConsensusEngine) -> Result<Self::Verified, Error> {
+        fn verify(
+            un: Self::Unverified,
+            engine: &dyn ConsensusEngine,
+            _transaction_verifier: &TransactionVerifier,
+        ) -> Result<Self::Verified, Error> {
             if un.number() <= 1 {
                 return Ok(un)
             }
@@ -137,7 +146,7 @@ pub mod blocks {
     use primitives::Bytes;
 
     use super::super::super::verification::{
-        verify_block_basic, verify_block_seal, verify_header_with_engine, PreverifiedBlock,
+        verify_block_basic, verify_block_seal, verify_header_with_engine, PreverifiedBlock, TransactionVerifier,
     };
     use super::{BlockLike, Kind, MemUsage};
     use crate::consensus::ConsensusEngine;
@@ -168,9 +177,13 @@ pub mod blocks {
             }
         }
 
-        fn verify(un: Self::Unverified, _engine: &dyn ConsensusEngine) -> Result<Self::Verified, Error> {
+        fn verify(
+            un: Self::Unverified,
+            _engine: &dyn ConsensusEngine,
+            transaction_verifier: &TransactionVerifier,
+        ) -> Result<Self::Verified, Error> {
             let hash = un.hash();
-            match verify_block_seal(un.header, un.bytes) {
+            match verify_block_seal(un.header, un.bytes, transaction_verifier) {
                 Ok(verified) => Ok(verified),
                 Err(e) => {
                     cwarn!(CLIENT, "Stage 2 block verification failed for {}: {:?}", hash, e);
@@ -204,6 +217,10 @@ pub mod blocks {
                 mem_usage,
             }
         }
+
+        pub fn header(&self) -> &Header {
+            &self.header
+        }
     }
 
     impl BlockLike for Unverified {
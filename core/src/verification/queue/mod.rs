@@ -17,11 +17,13 @@
 pub mod kind;
 
 use self::kind::{BlockLike, Kind, MemUsage};
+use super::TransactionVerifier;
 use crate::consensus::ConsensusEngine;
 use crate::error::{BlockError, Error, ImportError};
 use crate::service::ClientIoMessage;
 use crate::types::{BlockStatus as Status, VerificationQueueInfo as QueueInfo};
 use cio::IoChannel;
+use coordinator::engine::TxFilter;
 use ctypes::BlockHash;
 use parking_lot::{Mutex, RwLock};
 use std::cmp;
@@ -49,6 +51,8 @@ pub struct Config {
     /// Maximum heap memory to use.
     /// When the limit is reached, is_full returns true.
     pub max_mem_use: usize,
+    /// Number of threads verifying the transactions of a block.
+    pub transaction_verification_threads: usize,
 }
 
 impl Default for Config {
@@ -56,6 +60,7 @@ impl Default for Config {
         Config {
             max_queue_size: 30000,
             max_mem_use: 50 * 1024 * 1024,
+            transaction_verification_threads: 4,
         }
     }
 }
@@ -114,12 +119,20 @@ impl QueueSignal {
 }
 
 impl<K: Kind> VerificationQueue<K> {
-    pub fn new(config: &Config, engine: Arc<dyn ConsensusEngine>, message_channel: IoChannel<ClientIoMessage>) -> Self {
+    pub fn new(
+        config: &Config,
+        engine: Arc<dyn ConsensusEngine>,
+        tx_filter: Arc<dyn TxFilter>,
+        message_channel: IoChannel<ClientIoMessage>,
+    ) -> Self {
+        let transaction_verifier =
+            Arc::new(TransactionVerifier::new(tx_filter, config.transaction_verification_threads));
         let verification = Arc::new(Verification {
             unverified: Mutex::new(VecDeque::new()),
             verifying: Mutex::new(VecDeque::new()),
             verified: Mutex::new(VecDeque::new()),
             bad: Mutex::new(HashSet::new()),
+            rejected: Mutex::new(Vec::new()),
             sizes: Sizes {
                 unverified: AtomicUsize::new(0),
                 verifying: AtomicUsize::new(0),
@@ -141,6 +154,7 @@ impl<K: Kind> VerificationQueue<K> {
 
         for i in 0..NUM_VERIFIERS {
             let engine = engine.clone();
+            let transaction_verifier = Arc::clone(&transaction_verifier);
             let verification = verification.clone();
             let more_to_verify = more_to_verify.clone();
             let ready_signal = ready_signal.clone();
@@ -153,6 +167,7 @@ impl<K: Kind> VerificationQueue<K> {
                     VerificationQueue::verify(
                         &verification,
                         &*engine,
+                        &*transaction_verifier,
                         &*ready_signal,
                         &*empty,
                         &*more_to_verify,
@@ -180,6 +195,7 @@ impl<K: Kind> VerificationQueue<K> {
     fn verify(
         verification: &Verification<K>,
         engine: &dyn ConsensusEngine,
+        transaction_verifier: &TransactionVerifier,
         ready_signal: &QueueSignal,
         empty: &SCondvar,
         more_to_verify: &SCondvar,
@@ -226,7 +242,7 @@ impl<K: Kind> VerificationQueue<K> {
             };
 
             let hash = item.hash();
-            let is_ready = match K::verify(item, engine) {
+            let is_ready = match K::verify(item, engine, transaction_verifier) {
                 Ok(verified) => {
                     let mut verifying = verification.verifying.lock();
                     let mut idx = None;
@@ -261,6 +277,7 @@ impl<K: Kind> VerificationQueue<K> {
                     let mut bad = verification.bad.lock();
 
                     bad.insert(hash);
+                    verification.rejected.lock().push(hash);
                     verifying.retain(|e| e.hash != hash);
 
                     if verifying.front().map_or(false, |x| x.output.is_some()) {
@@ -270,10 +287,9 @@ impl<K: Kind> VerificationQueue<K> {
                             &mut bad,
                             &verification.sizes,
                         );
-                        true
-                    } else {
-                        false
                     }
+                    // Wake the importer up even if nothing is verified, so that it reports the rejection.
+                    true
                 }
             };
             if is_ready {
@@ -363,6 +379,21 @@ impl<K: Kind> VerificationQueue<K> {
         }
     }
 
+    /// Adds an item to the queue unless the queue is at its limits.
+    /// It fails with `ImportError::QueueFull` then, so that the caller backs off instead of piling up the items.
+    pub fn try_import(&self, input: K::Input) -> Result<BlockHash, Error> {
+        if self.queue_info().is_full() {
+            return Err(ImportError::QueueFull.into())
+        }
+        self.import(input)
+    }
+
+    /// Removes the items failed to be verified since the last call.
+    /// They must be passed to `mark_as_bad` to be removed from the processing ones.
+    pub fn drain_rejected(&self) -> Vec<BlockHash> {
+        std::mem::take(&mut *self.verification.rejected.lock())
+    }
+
     /// Removes up to `max` verified items from the queue
     pub fn drain(&self, max: usize) -> Vec<K::Verified> {
         let mut verified = self.verification.verified.lock();
@@ -479,6 +510,8 @@ struct Verification<K: Kind> {
     verifying: Mutex<VecDeque<Verifying<K>>>,
     verified: Mutex<VecDeque<K::Verified>>,
     bad: Mutex<HashSet<BlockHash>>,
+    /// The items failed to be verified, not yet taken by `drain_rejected`.
+    rejected: Mutex<Vec<BlockHash>>,
     sizes: Sizes,
     more_to_verify_mutex: SMutex<()>,
 }
@@ -492,6 +525,8 @@ pub struct Verifying<K: Kind> {
 #[cfg(test)]
 mod tests {
     use cio::IoChannel;
+    use coordinator::test_coordinator::TestCoordinator;
+    use std::sync::Arc;
 
     use super::kind::blocks::Unverified;
     use super::{BlockQueue, Config};
@@ -506,7 +541,7 @@ mod tests {
         let engine = scheme.engine;
 
         let config = Config::default();
-        BlockQueue::new(&config, engine, IoChannel::disconnected())
+        BlockQueue::new(&config, engine, Arc::new(TestCoordinator::default()), IoChannel::disconnected())
     }
 
     #[test]
@@ -516,7 +551,7 @@ mod tests {
         let engine = scheme.engine;
 
         let config = Config::default();
-        let _ = BlockQueue::new(&config, engine, IoChannel::disconnected());
+        let _ = BlockQueue::new(&config, engine, Arc::new(TestCoordinator::default()), IoChannel::disconnected());
     }
 
     #[test]
@@ -0,0 +1,155 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::error::BlockError;
+use coordinator::engine::TxFilter;
+use coordinator::Transaction;
+use std::cmp;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// The number of transactions a verifier thread takes at a time.
+const BATCH_SIZE: usize = 64;
+
+/// Runs the checks of the transactions that don't need the state, such as the signature checks,
+/// over a pool of threads.
+///
+/// The checks are done while a block waits in the verification queue,
+/// so that they don't take place one by one under the import lock.
+/// Whether a rejected transaction invalidates the block is decided on import,
+/// from the consensus params of the parent.
+pub struct TransactionVerifier {
+    tx_filter: Arc<dyn TxFilter>,
+    threads: usize,
+}
+
+impl TransactionVerifier {
+    pub fn new(tx_filter: Arc<dyn TxFilter>, threads: usize) -> Self {
+        Self {
+            tx_filter,
+            threads: cmp::max(threads, 1),
+        }
+    }
+
+    /// Fails with the first transaction rejected by its owner module found.
+    pub fn verify(&self, transactions: &[Transaction]) -> Result<(), BlockError> {
+        let batches: Vec<_> = transactions.chunks(BATCH_SIZE).collect();
+        let threads = cmp::min(self.threads, batches.len());
+        if threads <= 1 {
+            return batches.into_iter().try_for_each(|batch| self.verify_batch(batch))
+        }
+
+        let next_batch = AtomicUsize::new(0);
+        let failed = AtomicBool::new(false);
+        crossbeam::thread::scope(|scope| {
+            let handles: Vec<_> = (0..threads)
+                .map(|_| {
+                    scope.spawn(|_| {
+                        while !failed.load(Ordering::Relaxed) {
+                            let batch = match batches.get(next_batch.fetch_add(1, Ordering::Relaxed)) {
+                                Some(batch) => batch,
+                                None => break,
+                            };
+                            if let Err(err) = self.verify_batch(batch) {
+                                failed.store(true, Ordering::Relaxed);
+                                return Err(err)
+                            }
+                        }
+                        Ok(())
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("Transactions are verified without panics"))
+                .collect::<Result<(), _>>()
+        })
+        .expect("Transactions are verified without panics")
+    }
+
+    fn verify_batch(&self, batch: &[Transaction]) -> Result<(), BlockError> {
+        for tx in batch {
+            if let Err(code) = self.tx_filter.check_transaction(tx) {
+                return Err(BlockError::InvalidTransaction(tx.hash(), code))
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use coordinator::context::StorageAccess;
+    use coordinator::types::{ErrorCode, FilteredTxs};
+    use coordinator::TransactionWithMetadata;
+
+    /// Rejects the transactions of the type "bad".
+    struct TypeFilter;
+
+    impl TxFilter for TypeFilter {
+        fn check_transaction(&self, transaction: &Transaction) -> Result<(), ErrorCode> {
+            if transaction.tx_type() == "bad" {
+                Err(7)
+            } else {
+                Ok(())
+            }
+        }
+
+        fn is_system_transaction(&self, _transaction: &Transaction) -> bool {
+            false
+        }
+
+        fn filter_transactions<'a>(
+            &self,
+            _storage: &mut dyn StorageAccess,
+            _transactions: &mut dyn Iterator<Item = &'a TransactionWithMetadata>,
+            _memory_limit: Option<usize>,
+            _size_limit: Option<usize>,
+        ) -> FilteredTxs<'a> {
+            unreachable!()
+        }
+    }
+
+    fn good_transactions(n: usize) -> Vec<Transaction> {
+        (0..n).map(|i| Transaction::new("good".to_string(), (i as u32).to_be_bytes().to_vec())).collect()
+    }
+
+    #[test]
+    fn accept_valid_transactions() {
+        for threads in &[1, 4] {
+            let verifier = TransactionVerifier::new(Arc::new(TypeFilter), *threads);
+            assert!(verifier.verify(&[]).is_ok());
+            assert!(verifier.verify(&good_transactions(BATCH_SIZE * 10 + 1)).is_ok());
+        }
+    }
+
+    #[test]
+    fn reject_invalid_transaction_in_any_batch() {
+        for threads in &[1, 4] {
+            let verifier = TransactionVerifier::new(Arc::new(TypeFilter), *threads);
+            let bad = Transaction::new("bad".to_string(), Vec::new());
+            let bad_hash = bad.hash();
+            let mut transactions = good_transactions(BATCH_SIZE * 10);
+            transactions.insert(BATCH_SIZE * 7 + 3, bad);
+
+            match verifier.verify(&transactions) {
+                Err(BlockError::InvalidTransaction(hash, 7)) => assert_eq!(bad_hash, hash),
+                result => panic!("Unexpected result: {:?}", result),
+            }
+        }
+    }
+}
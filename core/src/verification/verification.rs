@@ -14,13 +14,14 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use super::TransactionVerifier;
 use crate::consensus::{ConsensusEngine, Evidence};
 use crate::error::{BlockError, Error};
 use crate::views::BlockView;
 use ccrypto::BLAKE_NULL_RLP;
 use coordinator::Transaction;
 use ctypes::util::unexpected::{Mismatch, OutOfBounds};
-use ctypes::{BlockNumber, ConsensusParams, Header};
+use ctypes::{BlockNumber, CompactValidatorSet, ConsensusParams, Header, TxOrdering};
 use merkle_trie::skewed_merkle_root;
 use primitives::{Bytes, H256};
 use rlp::Rlp;
@@ -34,6 +35,9 @@ pub struct PreverifiedBlock {
     pub evidences: Vec<Evidence>,
     /// Populated block transactions
     pub transactions: Vec<Transaction>,
+    /// The first transaction rejected by its owner module found, which invalidates the block
+    /// from the transaction checks activation of the consensus params of its parent.
+    pub rejected_transaction: Option<BlockError>,
     /// Block bytes
     pub bytes: Bytes,
 }
@@ -73,12 +77,24 @@ pub fn verify_block_with_params(
     verify_header_with_params(&header, consensus_params)?;
 
     let body_rlp = Rlp::new(bytes).at(2).expect("verify_block_basic already checked it");
-    if body_rlp.as_raw().len() > consensus_params.max_body_size() as usize {
+    // The blocks before the exact body size counted the list header of the body too.
+    let size = if consensus_params.activations().exact_body_size(header.number()) {
+        body_size(&body_rlp)
+    } else {
+        body_rlp.as_raw().len()
+    };
+    if size > consensus_params.max_body_size() as usize {
         return Err(BlockError::BodySizeIsTooBig.into())
     }
     Ok(())
 }
 
+/// The size of the transactions in a block body, which `max_body_size` limits from the exact body size activation.
+/// Each transaction counts with its encoded size, witness included, as the coordinator counts it while assembling a block.
+pub fn body_size(transactions: &Rlp) -> usize {
+    transactions.iter().map(|tx| tx.as_raw().len()).sum()
+}
+
 /// Check basic header parameters.
 pub fn verify_header_basic(header: &Header) -> Result<(), Error> {
     let block_number = header.number();
@@ -146,14 +162,20 @@ fn verify_transactions_root(
 /// Phase 2 verification. Perform costly checks such as transaction signatures and block nonce for ethash.
 /// Still operates on a individual block
 /// Returns a `PreverifiedBlock` structure populated with transactions
-pub fn verify_block_seal(header: Header, bytes: Bytes) -> Result<PreverifiedBlock, Error> {
+pub fn verify_block_seal(
+    header: Header,
+    bytes: Bytes,
+    transaction_verifier: &TransactionVerifier,
+) -> Result<PreverifiedBlock, Error> {
     let view = BlockView::new(&bytes);
     let transactions: Vec<_> = view.transactions();
+    let rejected_transaction = transaction_verifier.verify(&transactions).err();
     let evidences = view.evidences();
     Ok(PreverifiedBlock {
         header,
         evidences,
         transactions,
+        rejected_transaction,
         bytes,
     })
 }
@@ -171,10 +193,29 @@ pub fn verify_block_family(
     // TODO: verify timestamp
     verify_parent(&header, &parent)?;
     engine.verify_block_family(&header, &parent)?;
+    if consensus_params.tx_ordering() == TxOrdering::Seeded {
+        verify_seeded_order(block, parent)?;
+    }
 
     Ok(())
 }
 
+/// Checks that the transactions are sorted by their keys seeded by the parent's seal.
+fn verify_seeded_order(block: &[u8], parent: &Header) -> Result<(), Error> {
+    let seed = TxOrdering::seed(parent.seal());
+    let transactions: Vec<Transaction> = Rlp::new(block).list_at(2)?;
+    let mut last_key = None;
+    for tx in &transactions {
+        let hash = tx.hash();
+        let key = TxOrdering::seeded_key(&seed, &hash);
+        if last_key.map_or(false, |last_key| key <= last_key) {
+            return Err(BlockError::InvalidTransactionOrder(hash).into())
+        }
+        last_key = Some(key);
+    }
+    Ok(())
+}
+
 /// Check header parameters agains parent header.
 fn verify_parent(header: &Header, parent: &Header) -> Result<(), Error> {
     if !header.parent_hash().is_zero() && &parent.hash() != header.parent_hash() {
@@ -209,6 +250,19 @@ fn verify_parent(header: &Header, parent: &Header) -> Result<(), Error> {
     Ok(())
 }
 
+/// Check that the validator set is the one committed by the header's `next_validator_set_hash`.
+/// The set verifies the seal of the grandchild, which carries the precommits on the child.
+pub fn verify_next_validator_set(header: &Header, validator_set: &CompactValidatorSet) -> Result<(), Error> {
+    let found = validator_set.hash();
+    if header.next_validator_set_hash() != &found {
+        return Err(From::from(BlockError::InvalidNextValidatorSetHash(Mismatch {
+            expected: *header.next_validator_set_hash(),
+            found,
+        })))
+    }
+    Ok(())
+}
+
 /// Phase 4 verification. Check block information against transaction enactment results,
 pub fn verify_block_final(expected: &Header, got: &Header) -> Result<(), Error> {
     if expected.state_root() != got.state_root() {
@@ -223,5 +277,18 @@ pub fn verify_block_final(expected: &Header, got: &Header) -> Result<(), Error>
             found: *got.next_validator_set_hash(),
         })))
     }
+    // Only the module providing the anchor on closing the block can set it.
+    if expected.external_anchor() != got.external_anchor() {
+        return Err(From::from(BlockError::InvalidExternalAnchor(Mismatch {
+            expected: expected.external_anchor().copied(),
+            found: got.external_anchor().copied(),
+        })))
+    }
+    if expected.module_roots_hash() != got.module_roots_hash() {
+        return Err(From::from(BlockError::InvalidModuleRootsHash(Mismatch {
+            expected: expected.module_roots_hash().copied(),
+            found: got.module_roots_hash().copied(),
+        })))
+    }
     Ok(())
 }
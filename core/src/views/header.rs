@@ -14,11 +14,13 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use super::ValidatedHeaderView;
 use ccrypto::blake256;
 use ckey::Ed25519Public as Public;
+use ctypes::header::{decode_extension, has_extension, Extension, EXTENSION_INDEX, FIXED_FIELDS};
 use ctypes::{BlockHash, BlockNumber};
 use primitives::{Bytes, H256};
-use rlp::Rlp;
+use rlp::{DecoderError, Rlp};
 
 /// View onto block header rlp.
 pub struct HeaderView<'a> {
@@ -40,6 +42,11 @@ impl<'a> HeaderView<'a> {
         }
     }
 
+    /// Checks the structure of the header once, to read its fields without decoding them again.
+    pub fn validate(&self) -> Result<ValidatedHeaderView<'a>, DecoderError> {
+        ValidatedHeaderView::new_from_rlp(self.rlp.clone())
+    }
+
     /// Returns header hash.
     pub fn hash(&self) -> BlockHash {
         blake256(self.rlp.as_raw()).into()
@@ -99,13 +106,34 @@ impl<'a> HeaderView<'a> {
         self.rlp.val_at(9).unwrap()
     }
 
+    /// Returns the external anchor.
+    pub fn external_anchor(&self) -> Option<H256> {
+        self.extension().external_anchor
+    }
+
+    /// Returns the hash of the module roots.
+    pub fn module_roots_hash(&self) -> Option<H256> {
+        self.extension().module_roots_hash
+    }
+
+    fn extension(&self) -> Extension {
+        if self.has_extension() {
+            decode_extension(&self.rlp.at(EXTENSION_INDEX).unwrap()).unwrap()
+        } else {
+            Extension::default()
+        }
+    }
+
+    fn has_extension(&self) -> bool {
+        has_extension(&self.rlp).unwrap()
+    }
+
     /// Returns a vector of post-RLP-encoded seal fields.
     pub fn seal(&self) -> Vec<Bytes> {
-        const SIZE_WITHOUT_SEAL: usize = 10;
-
+        let seal_index = FIXED_FIELDS + self.has_extension() as usize;
         let item_count = self.rlp.item_count().unwrap();
-        let mut seal = Vec::with_capacity(item_count - SIZE_WITHOUT_SEAL);
-        for i in SIZE_WITHOUT_SEAL..item_count {
+        let mut seal = Vec::with_capacity(item_count - seal_index);
+        for i in seal_index..item_count {
             seal.push(self.rlp.at(i).unwrap().as_raw().to_vec());
         }
         seal
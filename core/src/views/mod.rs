@@ -17,7 +17,9 @@
 mod block;
 mod body;
 mod header;
+mod validated_header;
 
 pub use self::block::BlockView;
 pub use self::body::BodyView;
 pub use self::header::HeaderView;
+pub use self::validated_header::ValidatedHeaderView;
@@ -0,0 +1,362 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::HeaderView;
+use ccrypto::blake256;
+use ckey::Ed25519Public as Public;
+use ctypes::header::{decode_extension, has_extension, Extension, EXTENSION_INDEX, FIXED_FIELDS};
+use ctypes::{BlockHash, BlockNumber, Header};
+use primitives::{H256, U256};
+use rlp::{DecoderError, Rlp, RlpStream};
+use std::cell::Cell;
+use std::cmp;
+
+/// The length of an RLP-encoded 32-byte string: a one-byte prefix and the bytes.
+const ENCODED_HASH_LENGTH: usize = 33;
+
+/// View onto block header rlp whose structure is checked once.
+///
+/// `HeaderView` trusts its bytes and panics on a malformed field, so a header from a peer has to be decoded into a
+/// `Header` first. This view checks every field on creation and then reads the fields straight from the bytes:
+/// the hashes are copied out of the slice, the extra data and the seal are borrowed, and the hashes of the header
+/// are computed once on the first request.
+pub struct ValidatedHeaderView<'a> {
+    rlp: Rlp<'a>,
+    /// The raw rlp of each field before the extension.
+    fields: [&'a [u8]; FIXED_FIELDS],
+    /// The raw rlp of the extension, if the header has it.
+    raw_extension: Option<&'a [u8]>,
+    extension: Extension,
+    /// The raw rlp of each seal field.
+    seal: Vec<&'a [u8]>,
+    number: BlockNumber,
+    timestamp: u64,
+    view: u64,
+    hash: Cell<Option<BlockHash>>,
+    bare_hash: Cell<Option<H256>>,
+}
+
+impl<'a> ValidatedHeaderView<'a> {
+    /// Creates new view onto header from raw bytes, checking that they are a well-formed header.
+    pub fn new(bytes: &'a [u8]) -> Result<ValidatedHeaderView<'a>, DecoderError> {
+        Self::new_from_rlp(Rlp::new(bytes))
+    }
+
+    /// Creates new view onto header from rlp, checking that it is a well-formed header.
+    pub fn new_from_rlp(rlp: Rlp<'a>) -> Result<ValidatedHeaderView<'a>, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count < FIXED_FIELDS {
+            return Err(DecoderError::RlpIncorrectListLen {
+                expected: FIXED_FIELDS,
+                got: item_count,
+            })
+        }
+
+        let mut fields: [&'a [u8]; FIXED_FIELDS] = [&[][..]; FIXED_FIELDS];
+        for (index, field) in fields.iter_mut().enumerate() {
+            *field = rlp.at(index)?.as_raw();
+        }
+        for &index in &[0, 1, 2, 3, 4, 5] {
+            check_hash(&Rlp::new(fields[index]))?;
+        }
+        let number = Rlp::new(fields[6]).as_val()?;
+        // The same as `Header` does, a timestamp too large is clamped.
+        let timestamp = cmp::min(Rlp::new(fields[7]).as_val::<U256>()?, u64::max_value().into()).as_u64();
+        let validators = Rlp::new(fields[8]);
+        if !validators.is_list() {
+            return Err(DecoderError::RlpExpectedToBeList)
+        }
+        for index in 0..validators.item_count()? {
+            check_hash(&validators.at(index)?)?;
+        }
+        Rlp::new(fields[9]).data()?;
+        let (raw_extension, extension) = if has_extension(&rlp)? {
+            let extension = rlp.at(EXTENSION_INDEX)?;
+            (Some(extension.as_raw()), decode_extension(&extension)?)
+        } else {
+            (None, Extension::default())
+        };
+
+        let seal_index = FIXED_FIELDS + raw_extension.is_some() as usize;
+        let seal = (seal_index..item_count)
+            .map(|index| rlp.at(index).map(|field| field.as_raw()))
+            .collect::<Result<Vec<_>, DecoderError>>()?;
+        let view = match seal.get(1) {
+            Some(view) => Rlp::new(view).as_val()?,
+            None => 0,
+        };
+
+        Ok(ValidatedHeaderView {
+            rlp,
+            fields,
+            raw_extension,
+            extension,
+            seal,
+            number,
+            timestamp,
+            view,
+            hash: Cell::new(None),
+            bare_hash: Cell::new(None),
+        })
+    }
+
+    /// Returns header hash.
+    pub fn hash(&self) -> BlockHash {
+        if let Some(hash) = self.hash.get() {
+            return hash
+        }
+        let hash: BlockHash = blake256(self.rlp.as_raw()).into();
+        self.hash.set(Some(hash));
+        hash
+    }
+
+    /// Returns the hash of the header without the seal.
+    pub fn bare_hash(&self) -> H256 {
+        if let Some(hash) = self.bare_hash.get() {
+            return hash
+        }
+        let mut s = RlpStream::new_list(FIXED_FIELDS + self.raw_extension.is_some() as usize);
+        for field in &self.fields {
+            s.append_raw(field, 1);
+        }
+        if let Some(extension) = self.raw_extension {
+            s.append_raw(extension, 1);
+        }
+        let hash = blake256(s.out());
+        self.bare_hash.set(Some(hash));
+        hash
+    }
+
+    /// Returns raw rlp.
+    pub fn rlp(&self) -> &Rlp<'a> {
+        &self.rlp
+    }
+
+    /// Returns the unchecked view onto the same rlp.
+    pub fn header_view(&self) -> HeaderView<'a> {
+        HeaderView::new_from_rlp(self.rlp.clone())
+    }
+
+    /// Decodes the whole header.
+    pub fn decode(&self) -> Header {
+        self.rlp.as_val().expect("The header is validated")
+    }
+
+    /// Returns parent hash.
+    pub fn parent_hash(&self) -> BlockHash {
+        self.hash_at(0).into()
+    }
+
+    /// Returns author.
+    pub fn author(&self) -> Public {
+        Public::from_slice(self.payload_at(1)).expect("The author is validated")
+    }
+
+    /// Returns state root.
+    pub fn state_root(&self) -> H256 {
+        self.hash_at(2)
+    }
+
+    /// Returns evidences root.
+    pub fn evidences_root(&self) -> H256 {
+        self.hash_at(3)
+    }
+
+    /// Returns transactions root.
+    pub fn transactions_root(&self) -> H256 {
+        self.hash_at(4)
+    }
+
+    /// Returns next validator set hash.
+    pub fn next_validator_set_hash(&self) -> H256 {
+        self.hash_at(5)
+    }
+
+    /// Returns block number.
+    pub fn number(&self) -> BlockNumber {
+        self.number
+    }
+
+    /// Returns timestamp.
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// Returns the validators who committed the parent block, without collecting them.
+    pub fn last_committed_validators(&self) -> impl Iterator<Item = Public> + 'a {
+        let raw = self.fields[8];
+        // Every validator is checked to be a 32-byte string, so the list ends with them back to back.
+        let count = Rlp::new(raw).item_count().expect("The validators are validated");
+        raw[raw.len() - count * ENCODED_HASH_LENGTH..]
+            .chunks(ENCODED_HASH_LENGTH)
+            .map(|validator| Public::from_slice(&validator[1..]).expect("The validators are validated"))
+    }
+
+    /// Returns block extra data.
+    pub fn extra_data(&self) -> &'a [u8] {
+        self.payload_at(9)
+    }
+
+    /// Returns the external anchor.
+    pub fn external_anchor(&self) -> Option<H256> {
+        self.extension.external_anchor
+    }
+
+    /// Returns the hash of the module roots.
+    pub fn module_roots_hash(&self) -> Option<H256> {
+        self.extension.module_roots_hash
+    }
+
+    /// Returns the post-RLP-encoded seal fields.
+    pub fn seal(&self) -> &[&'a [u8]] {
+        &self.seal
+    }
+
+    /// Get view in the seal field of the header.
+    pub fn view(&self) -> u64 {
+        self.view
+    }
+
+    fn payload_at(&self, index: usize) -> &'a [u8] {
+        Rlp::new(self.fields[index]).data().expect("The fields are validated")
+    }
+
+    fn hash_at(&self, index: usize) -> H256 {
+        H256::from_slice(self.payload_at(index))
+    }
+}
+
+/// Checks that the rlp is a 32-byte string. The decoder accepts only the shortest encoding of it.
+fn check_hash(rlp: &Rlp<'_>) -> Result<(), DecoderError> {
+    let got = rlp.data()?.len();
+    if got != 32 {
+        return Err(DecoderError::RlpInvalidLength {
+            expected: 32,
+            got,
+        })
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rlp::Encodable;
+
+    fn header() -> Header {
+        let mut header = Header::default();
+        header.set_parent_hash(blake256("parent").into());
+        header.set_author(Public::random());
+        header.set_state_root(blake256("state"));
+        header.set_number(7);
+        header.set_timestamp(1_600_000_000);
+        header.set_extra_data(b"extra".to_vec());
+        header.set_external_anchor(Some(blake256("anchor")));
+        header.set_module_roots_hash(Some(blake256("module roots")));
+        header.set_seal(vec![::rlp::encode(&2u64), ::rlp::encode(&3u64)]);
+        header
+    }
+
+    /// Replaces the field at the index with the raw rlp.
+    fn replace_field(bytes: &[u8], index: usize, field: &[u8]) -> Vec<u8> {
+        let rlp = Rlp::new(bytes);
+        let mut s = RlpStream::new_list(rlp.item_count().unwrap());
+        for (i, original) in rlp.iter().enumerate() {
+            let raw = if i == index {
+                field
+            } else {
+                original.as_raw()
+            };
+            s.append_raw(raw, 1);
+        }
+        s.out()
+    }
+
+    #[test]
+    fn read_the_fields_of_a_header() {
+        let validators = vec![Public::random(), Public::random(), Public::random()];
+        let bytes = replace_field(&header().rlp_bytes(), 8, &::rlp::encode_list(&validators));
+        let header: Header = ::rlp::decode(&bytes).unwrap();
+        let view = ValidatedHeaderView::new(&bytes).unwrap();
+
+        assert_eq!(header.hash(), view.hash());
+        assert_eq!(header.bare_hash(), view.bare_hash());
+        assert_eq!(*header.parent_hash(), view.parent_hash());
+        assert_eq!(*header.author(), view.author());
+        assert_eq!(*header.state_root(), view.state_root());
+        assert_eq!(*header.evidences_root(), view.evidences_root());
+        assert_eq!(*header.transactions_root(), view.transactions_root());
+        assert_eq!(*header.next_validator_set_hash(), view.next_validator_set_hash());
+        assert_eq!(header.number(), view.number());
+        assert_eq!(header.timestamp(), view.timestamp());
+        assert_eq!(validators, view.last_committed_validators().collect::<Vec<_>>());
+        assert_eq!(&header.extra_data()[..], view.extra_data());
+        assert_eq!(header.external_anchor().cloned(), view.external_anchor());
+        assert_eq!(header.module_roots_hash().cloned(), view.module_roots_hash());
+        assert_eq!(header.seal(), &view.seal().iter().map(|field| field.to_vec()).collect::<Vec<_>>()[..]);
+        assert_eq!(3, view.view());
+        assert_eq!(header.hash(), view.decode().hash());
+    }
+
+    #[test]
+    fn read_a_header_without_seal() {
+        let header = Header::default();
+        let bytes = header.rlp_bytes();
+        let view = HeaderView::new(&bytes).validate().unwrap();
+        assert_eq!(header.hash(), view.hash());
+        assert_eq!(0, view.last_committed_validators().count());
+        assert_eq!(None, view.external_anchor());
+        assert!(view.seal().is_empty());
+        assert_eq!(0, view.view());
+    }
+
+    #[test]
+    fn reject_a_header_with_missing_fields() {
+        let bytes = header().rlp_bytes();
+        let mut s = RlpStream::new_list(FIXED_FIELDS - 1);
+        for field in Rlp::new(&bytes).iter().take(FIXED_FIELDS - 1) {
+            s.append_raw(field.as_raw(), 1);
+        }
+        assert_eq!(
+            Some(DecoderError::RlpIncorrectListLen {
+                expected: FIXED_FIELDS,
+                got: FIXED_FIELDS - 1,
+            }),
+            ValidatedHeaderView::new(&s.out()).err()
+        );
+    }
+
+    #[test]
+    fn reject_a_header_with_a_malformed_field() {
+        let bytes = header().rlp_bytes();
+        let short_hash = ::rlp::encode(&vec![0u8; 31]);
+        let hash = ::rlp::encode(&vec![0u8; 32]);
+        let mut s = RlpStream::new_list(1);
+        s.append(&vec![0u8; 31]);
+        let list_of_short_hash = s.out();
+        let mut s = RlpStream::new_list(2);
+        s.append(&blake256("anchor").as_bytes().to_vec()).append(&vec![0u8; 31]);
+        let extension_of_short_hash = s.out();
+
+        assert!(ValidatedHeaderView::new(&replace_field(&bytes, 2, &short_hash)).is_err());
+        assert!(ValidatedHeaderView::new(&replace_field(&bytes, 8, &hash)).is_err());
+        assert!(ValidatedHeaderView::new(&replace_field(&bytes, 8, &list_of_short_hash)).is_err());
+        assert!(ValidatedHeaderView::new(&replace_field(&bytes, EXTENSION_INDEX, &list_of_short_hash)).is_err());
+        assert!(ValidatedHeaderView::new(&replace_field(&bytes, EXTENSION_INDEX, &extension_of_short_hash)).is_err());
+        assert!(ValidatedHeaderView::new(&replace_field(&bytes, EXTENSION_INDEX + 2, &hash)).is_err());
+        assert!(ValidatedHeaderView::new(&replace_field(&bytes, 3, &hash)).is_ok());
+    }
+}
@@ -0,0 +1,150 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Runs several app-chains in one node process.
+//!
+//! Each app-chain has its own scheme, module graph and DB, and its RPC is served at `/chains/<id>`
+//! of the HTTP server of the main chain.
+//! The app-chains share the timer loop, the key store and the mining options with the main chain.
+//! They also share its peers: the network extensions of an app-chain are registered as `<name>@<id>`,
+//! so its blocks, transactions and consensus messages reach only the peers running an app-chain of the same id.
+
+use crate::config::{self, Config};
+use crate::run_node::{app_chain_db_path, client_start, new_miner, open_db};
+use ccore::metrics::MeteredDb;
+use ccore::{AccountProvider, ChainNotify, ClientConfig, ClientService, Miner, MinerService, Scheme};
+use cnetwork::{EventSender, NetworkService};
+use coordinator::{AppDesc, Coordinator};
+use csync::{BlockSyncEvent, BlockSyncExtension, BlockSyncSender, TransactionSyncEvent, TransactionSyncExtension};
+use ctimer::TimerLoop;
+use kvdb::KeyValueDB;
+use std::fs;
+use std::sync::{Arc, Weak};
+
+/// The client and the miner of a chain, running the modules of its app descriptor.
+pub struct ChainInstance {
+    pub client: ClientService,
+    pub miner: Arc<Miner>,
+    pub app_desc: AppDesc,
+}
+
+/// Weaves the modules of `app_desc` into a coordinator, and starts the miner and the client of the chain on `db`.
+pub fn start_chain(
+    config: &Config,
+    scheme: &Scheme,
+    app_desc: AppDesc,
+    db: Arc<dyn KeyValueDB>,
+    client_config: &ClientConfig,
+    ap: Arc<AccountProvider>,
+    timer_loop: &TimerLoop,
+) -> Result<ChainInstance, String> {
+    let execution_threads = config.operating.execution_threads.unwrap_or(1);
+    let coordinator = Arc::new(
+        Coordinator::from_app_desc(&app_desc)
+            .map_err(|e| format!("Cannot weave the modules of the chain: {}", e))?
+            .with_execution_audit(config.operating.execution_audit.unwrap_or(false))
+            .with_execution_threads(execution_threads),
+    );
+    if coordinator.execution_threads() < execution_threads {
+        cwarn!(CLIENT, "The transactions are executed one by one, since not every module sets parallel-execution");
+    }
+    let miner = new_miner(config, scheme, ap, Arc::clone(&db), Arc::clone(&coordinator))?;
+    let client = client_start(client_config, timer_loop, db, scheme, Arc::clone(&miner), coordinator)?;
+    miner.recover_from_db();
+    Ok(ChainInstance {
+        client,
+        miner,
+        app_desc,
+    })
+}
+
+pub struct AppChainInstance {
+    pub id: String,
+    pub db_path: String,
+    pub chain: ChainInstance,
+}
+
+/// Starts an app-chain in its own DB, which is cleared like the one of the main chain.
+/// Its engine completes the registration once the network extensions of the app-chain are registered.
+pub fn start_app_chain(
+    config: &Config,
+    app_chain: &config::AppChain,
+    ap: Arc<AccountProvider>,
+    timer_loop: &TimerLoop,
+) -> Result<AppChainInstance, String> {
+    let id = &app_chain.id;
+    let scheme = app_chain.chain.scheme()?;
+    scheme.engine.register_time_gap_config_to_worker(config.mining.create_time_gaps());
+
+    let app_desc = fs::read_to_string(&app_chain.app_desc_path)
+        .map_err(|e| format!("Cannot read the app descriptor of the app-chain {}: {}", id, e))
+        .and_then(|source| {
+            AppDesc::from_str(&source).map_err(|e| format!("Invalid app descriptor of the app-chain {}: {}", id, e))
+        })?;
+
+    let client_config = config.client_config()?;
+    let db_path = app_chain_db_path(&config.operating, id);
+    let db: Arc<dyn KeyValueDB> = Arc::new(MeteredDb::new(open_db(&db_path, &client_config)?));
+    let chain = start_chain(config, &scheme, app_desc, db, &client_config, ap, timer_loop)?;
+
+    scheme.engine.register_chain_notify(chain.client.client().as_ref());
+    cinfo!(CLIENT, "Started the app-chain {} in {}", id, db_path);
+
+    Ok(AppChainInstance {
+        id: id.clone(),
+        db_path,
+        chain,
+    })
+}
+
+/// The network extensions of an app-chain, which are kept while the node runs.
+#[derive(Default)]
+pub struct AppChainNetwork {
+    pub block_sync: Option<EventSender<BlockSyncEvent>>,
+    pub transaction_sync: Option<EventSender<TransactionSyncEvent>>,
+    /// Holds the notifier of the block sync, so that it is not dropped.
+    _sync: Option<Arc<BlockSyncSender>>,
+}
+
+/// Registers the sync extensions and the engine of the app-chain in its namespace of the network service.
+/// An app-chain starts from its genesis, so its blocks are synced without a snapshot.
+pub fn register_app_chain_extensions(
+    config: &Config,
+    client_config: &ClientConfig,
+    service: &NetworkService,
+    app_chain: &AppChainInstance,
+) -> AppChainNetwork {
+    let service = service.in_namespace(&app_chain.id);
+    let client = app_chain.chain.client.client();
+    let mut network = AppChainNetwork::default();
+    if config.network.sync.unwrap() {
+        let sync_client = Arc::clone(&client);
+        let body_compression = client_config.body_compression;
+        let sync_sender = service
+            .register_extension(move |api| BlockSyncExtension::new(sync_client, api, None, None, body_compression));
+        let sync = Arc::new(BlockSyncSender::from(sync_sender.clone()));
+        client.add_notify(Arc::downgrade(&sync) as Weak<dyn ChainNotify>);
+        network._sync = Some(sync);
+        network.block_sync = Some(sync_sender);
+    }
+    if config.network.transaction_relay.unwrap() {
+        let relay_client = Arc::clone(&client);
+        network.transaction_sync =
+            Some(service.register_extension(move |api| TransactionSyncExtension::new(relay_client, api)));
+    }
+    client.engine().register_network_extension_to_service(&service);
+    network
+}
@@ -16,13 +16,16 @@
 
 mod chain_type;
 
-use ccore::{MinerOptions, TimeGapParams};
+use ccore::{
+    BodyCompression, ClientConfig, EmptyBlockProposal, MemPoolFlush, MinerOptions, RemoteSigner, TimeGapParams,
+};
 use cidr::IpCidr;
 use cinformer::InformerConfig;
-use ckey::PlatformAddress;
+use ckey::{Ed25519Public as Public, PlatformAddress};
 use cnetwork::{FilterEntry, NetworkConfig, SocketAddr};
 use primitives::H256;
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::fs;
 use std::str::{self, FromStr};
 use std::time::Duration;
@@ -44,6 +47,13 @@ pub struct Config {
     pub snapshot: Snapshot,
     #[serde(default)]
     pub email_alarm: EmailAlarm,
+    #[serde(default)]
+    pub metrics: Metrics,
+    #[serde(default)]
+    pub database: Database,
+    /// The app-chains the node runs besides the main chain
+    #[serde(default)]
+    pub app_chains: Vec<AppChain>,
 }
 
 impl Config {
@@ -57,6 +67,11 @@ impl Config {
         self.informer.merge(&other.informer);
         self.snapshot.merge(&other.snapshot);
         self.email_alarm.merge(&other.email_alarm);
+        self.metrics.merge(&other.metrics);
+        self.database.merge(&other.database);
+        if !other.app_chains.is_empty() {
+            self.app_chains = other.app_chains.clone();
+        }
     }
 
     pub fn miner_options(&self) -> Result<MinerOptions, String> {
@@ -73,6 +88,34 @@ impl Config {
             }
             None => unreachable!(),
         };
+        let mem_pool_flush = match self.mining.mem_pool_flush.as_deref() {
+            Some("sync") => MemPoolFlush::Sync,
+            Some("periodic") => {
+                MemPoolFlush::Periodic(Duration::from_millis(self.mining.mem_pool_flush_interval.unwrap()))
+            }
+            Some("write-behind") => MemPoolFlush::WriteBehind,
+            Some(x) => {
+                return Err(format!(
+                    "{} isn't a valid value for mem-pool-flush. Possible values are sync, periodic, write-behind",
+                    x
+                ))
+            }
+            None => unreachable!(),
+        };
+        let empty_block_proposal = match self.mining.empty_blocks.as_deref() {
+            Some("always") => EmptyBlockProposal::Always,
+            Some("interval") => {
+                EmptyBlockProposal::Interval(Duration::from_millis(self.mining.empty_block_interval.unwrap()))
+            }
+            Some("never") => EmptyBlockProposal::Never,
+            Some(x) => {
+                return Err(format!(
+                    "{} isn't a valid value for empty-blocks. Possible values are always, interval, never",
+                    x
+                ))
+            }
+            None => unreachable!(),
+        };
 
         Ok(MinerOptions {
             mem_pool_size: self.mining.mem_pool_size.unwrap(),
@@ -81,12 +124,37 @@ impl Config {
                 mem_size => Some(mem_size * 1024 * 1024),
             },
             mem_pool_fee_bump_shift: self.mining.mem_pool_fee_bump_shift.unwrap(),
+            mem_pool_flush,
+            mem_pool_orphan_retry_blocks: self.mining.mem_pool_orphan_retry_blocks.unwrap(),
             reseal_on_own_transaction,
             reseal_on_external_transaction,
             reseal_min_period: Duration::from_millis(self.mining.reseal_min_period.unwrap()),
+            ingress_rate_limits: self.mining.ingress_rate_limits.clone().unwrap_or_default(),
+            empty_block_proposal,
+        })
+    }
+
+    /// The config of the clients of the main chain and the app-chains.
+    pub fn client_config(&self) -> Result<ClientConfig, String> {
+        Ok(ClientConfig {
+            db_backend: self.database.backend.as_deref().unwrap_or("rocksdb").parse()?,
+            db_cache_size: self.database.cache_size,
+            db_compaction: self.database.compaction.as_deref().unwrap_or("auto").parse()?,
+            db_max_open_files: self.database.max_open_files,
+            body_compression: self.body_compression()?,
+            ..Default::default()
         })
     }
 
+    pub fn body_compression(&self) -> Result<Option<BodyCompression>, String> {
+        match self.operating.body_compression.as_deref() {
+            None | Some("none") => Ok(None),
+            Some(x) => x.parse().map(Some).map_err(|_| {
+                format!("{} isn't a valid value for body-compression. Possible values are none, lz4, zstd", x)
+            }),
+        }
+    }
+
     pub fn rpc_http_config(&self) -> RpcHttpConfig {
         debug_assert!(!self.rpc.disable.unwrap());
 
@@ -125,7 +193,39 @@ impl Config {
             interface: self.informer.interface.clone().unwrap(),
             port: self.informer.port.unwrap(),
             max_connections: self.informer.max_connections.unwrap(),
+            max_subscriptions_per_connection: self.informer.max_subscriptions_per_connection.unwrap(),
+        }
+    }
+
+    /// Checks that the ids of the app-chains are distinct and can be put in a path.
+    pub fn validate_app_chains(&self) -> Result<(), String> {
+        let mut ids = std::collections::HashSet::new();
+        for app_chain in &self.app_chains {
+            let id = &app_chain.id;
+            if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+                return Err(format!("{:?} isn't a valid app-chain id. Use letters, digits, '-' and '_'", id))
+            }
+            if !ids.insert(id) {
+                return Err(format!("The app-chain id {} is used more than once", id))
+            }
         }
+        Ok(())
+    }
+
+    /// Reads the token of the admin APIs, ignoring the surrounding whitespace.
+    pub fn admin_token(&self) -> Result<Option<String>, String> {
+        let path = match &self.rpc.admin_token_path {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        let token = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read the admin token file {:?}: {:?}", path, e))?
+            .trim()
+            .to_string();
+        if token.is_empty() {
+            return Err(format!("The admin token file {:?} is empty", path))
+        }
+        Ok(Some(token))
     }
 
     pub fn network_config(&self) -> Result<NetworkConfig, String> {
@@ -161,28 +261,64 @@ impl Config {
             }
         }
 
-        let bootstrap_addresses = self
-            .network
-            .bootstrap_addresses
-            .clone()
-            .unwrap()
-            .iter()
-            .map(|s| SocketAddr::from_str(s).unwrap())
-            .collect::<Vec<_>>();
-
-        let whitelist = make_ipaddr_list(self.network.whitelist_path.as_ref(), "white")?;
+        let sentry_addresses = self.sentry_addresses()?;
+        // A validator in the sentry mode connects to its sentries only, and lets no one else connect to it.
+        let (bootstrap_addresses, dns_seeds, whitelist) = if sentry_addresses.is_empty() {
+            let bootstrap_addresses = self
+                .network
+                .bootstrap_addresses
+                .clone()
+                .unwrap()
+                .iter()
+                .map(|s| SocketAddr::from_str(s).unwrap())
+                .collect::<Vec<_>>();
+            let dns_seeds = self.network.dns_seeds.clone().unwrap_or_default();
+            for seed in &dns_seeds {
+                match seed.rfind(':') {
+                    Some(colon) if seed[colon + 1..].parse::<u16>().is_ok() => {}
+                    _ => return Err(format!("The DNS seed {} should be given as host:port", seed)),
+                }
+            }
+            (bootstrap_addresses, dns_seeds, make_ipaddr_list(self.network.whitelist_path.as_ref(), "white")?)
+        } else {
+            if self.network.whitelist_path.is_some() {
+                return Err("The whitelist cannot be given in the sentry mode".to_string())
+            }
+            let whitelist = sentry_addresses
+                .iter()
+                .map(|addr| {
+                    Ok(FilterEntry {
+                        cidr: IpCidr::from_str(&addr.ip().to_string())
+                            .map_err(|e| format!("Cannot parse IP address {}: {:?}", addr.ip(), e))?,
+                        tag: "sentry".to_string(),
+                    })
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            (sentry_addresses, Vec::new(), whitelist)
+        };
         let blacklist = make_ipaddr_list(self.network.blacklist_path.as_ref(), "black")?;
 
         Ok(NetworkConfig {
             address: self.network.interface.clone().unwrap(),
             port: self.network.port.unwrap(),
             bootstrap_addresses,
+            dns_seeds,
             min_peers: self.network.min_peers.unwrap(),
             max_peers: self.network.max_peers.unwrap(),
             whitelist,
             blacklist,
         })
     }
+
+    /// The sentries of a validator in the sentry mode. It is empty if the node is not in the sentry mode.
+    pub fn sentry_addresses(&self) -> Result<Vec<SocketAddr>, String> {
+        self.network
+            .sentry_addresses
+            .iter()
+            .flatten()
+            .map(|s| SocketAddr::from_str(s).map_err(|_| format!("Invalid sentry address {}", s)))
+            .collect()
+    }
 }
 
 #[derive(Deserialize)]
@@ -199,9 +335,19 @@ pub struct Operating {
     pub instance_id: Option<usize>,
     pub base_path: Option<String>,
     pub db_path: Option<String>,
+    pub archive_db_path: Option<String>,
     pub keys_path: Option<String>,
     pub password_path: Option<String>,
+    pub password_env: Option<String>,
     pub chain: Option<ChainType>,
+    /// The OTLP collector the tracing spans are exported to. Nothing is exported if it is not set.
+    pub otlp_endpoint: Option<String>,
+    /// The compression of the block bodies in the database and in the responses to the syncing peers.
+    pub body_compression: Option<String>,
+    /// Records a state commitment after each transaction, reported as an event of the block.
+    pub execution_audit: Option<bool>,
+    /// The number of threads executing the transactions of a block, if the modules allow it.
+    pub execution_threads: Option<usize>,
 }
 
 #[derive(Deserialize)]
@@ -209,6 +355,9 @@ pub struct Operating {
 pub struct Mining {
     pub author: Option<PlatformAddress>,
     pub engine_signer: Option<PlatformAddress>,
+    pub remote_signer: Option<String>,
+    pub remote_signer_timeout: Option<u64>,
+    pub remote_signer_fallback: Option<bool>,
     pub mem_pool_size: Option<usize>,
     pub mem_pool_mem_limit: Option<usize>,
     pub self_nomination_metadata: Option<String>,
@@ -216,10 +365,17 @@ pub struct Mining {
     pub self_nomination_enable: bool,
     pub self_nomination_interval: Option<u64>,
     pub mem_pool_fee_bump_shift: Option<usize>,
+    pub mem_pool_flush: Option<String>,
+    pub mem_pool_flush_interval: Option<u64>,
+    pub mem_pool_orphan_retry_blocks: Option<u64>,
     pub reseal_on_txs: Option<String>,
     pub reseal_min_period: Option<u64>,
+    pub empty_blocks: Option<String>,
+    pub empty_block_interval: Option<u64>,
     pub allowed_past_gap: Option<u64>,
     pub allowed_future_gap: Option<u64>,
+    /// The external transactions of each type admitted per second.
+    pub ingress_rate_limits: Option<HashMap<String, u32>>,
 }
 
 #[derive(Deserialize)]
@@ -229,6 +385,8 @@ pub struct Network {
     pub disable: Option<bool>,
     pub port: Option<u16>,
     pub bootstrap_addresses: Option<Vec<String>>,
+    /// The `host:port` names resolving to the nodes to bootstrap from.
+    pub dns_seeds: Option<Vec<String>>,
     pub min_peers: Option<usize>,
     pub max_peers: Option<usize>,
     pub sync: Option<bool>,
@@ -241,6 +399,8 @@ pub struct Network {
     pub discovery_bucket_size: Option<u8>,
     pub blacklist_path: Option<String>,
     pub whitelist_path: Option<String>,
+    /// The sentries a validator in the sentry mode exclusively connects to.
+    pub sentry_addresses: Option<Vec<String>>,
 }
 
 #[derive(Deserialize)]
@@ -253,6 +413,11 @@ pub struct Rpc {
     pub port: Option<u16>,
     #[serde(default = "default_enable_devel_api")]
     pub enable_devel_api: bool,
+    /// Serves a subset of Ethereum's JSON-RPC under the `eth_` prefix.
+    #[serde(default)]
+    pub enable_eth_api: bool,
+    /// The file holding the token the admin APIs require. The admin APIs are served only when it's given.
+    pub admin_token_path: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -275,6 +440,7 @@ pub struct Informer {
     pub interface: Option<String>,
     pub port: Option<u16>,
     pub max_connections: Option<usize>,
+    pub max_subscriptions_per_connection: Option<usize>,
 }
 
 #[derive(Deserialize)]
@@ -294,6 +460,37 @@ pub struct EmailAlarm {
     pub sendgrid_key: Option<String>,
 }
 
+/// An app-chain run in the same process as the main chain.
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AppChain {
+    /// Addresses the chain, e.g. in the RPC path `/chains/<id>`
+    pub id: String,
+    pub chain: ChainType,
+    /// The app descriptor of the modules the chain runs
+    pub app_desc_path: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Metrics {
+    pub disable: Option<bool>,
+    pub interface: Option<String>,
+    pub port: Option<u16>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Database {
+    /// The store the database is kept in, either rocksdb or sled.
+    pub backend: Option<String>,
+    /// The cache size in MB.
+    pub cache_size: Option<usize>,
+    /// The compaction profile of RocksDB, either auto, ssd or hdd.
+    pub compaction: Option<String>,
+    pub max_open_files: Option<i32>,
+}
+
 impl Ipc {
     pub fn merge(&mut self, other: &Ipc) {
         if other.disable.is_some() {
@@ -329,15 +526,33 @@ impl Operating {
         if other.db_path.is_some() {
             self.db_path = other.db_path.clone();
         }
+        if other.archive_db_path.is_some() {
+            self.archive_db_path = other.archive_db_path.clone();
+        }
         if other.keys_path.is_some() {
             self.keys_path = other.keys_path.clone();
         }
         if other.password_path.is_some() {
             self.password_path = other.password_path.clone();
         }
+        if other.password_env.is_some() {
+            self.password_env = other.password_env.clone();
+        }
         if other.chain.is_some() {
             self.chain = other.chain.clone();
         }
+        if other.otlp_endpoint.is_some() {
+            self.otlp_endpoint = other.otlp_endpoint.clone();
+        }
+        if other.body_compression.is_some() {
+            self.body_compression = other.body_compression.clone();
+        }
+        if other.execution_audit.is_some() {
+            self.execution_audit = other.execution_audit;
+        }
+        if other.execution_threads.is_some() {
+            self.execution_threads = other.execution_threads;
+        }
     }
 
     pub fn overwrite_with(&mut self, matches: &clap::ArgMatches<'_>) -> Result<(), String> {
@@ -353,15 +568,33 @@ impl Operating {
         if let Some(db_path) = matches.value_of("db-path") {
             self.db_path = Some(db_path.to_string());
         }
+        if let Some(archive_db_path) = matches.value_of("archive-db-path") {
+            self.archive_db_path = Some(archive_db_path.to_string());
+        }
         if let Some(keys_path) = matches.value_of("keys-path") {
             self.keys_path = Some(keys_path.to_string());
         }
         if let Some(password_path) = matches.value_of("password-path") {
             self.password_path = Some(password_path.to_string());
         }
+        if let Some(password_env) = matches.value_of("password-env") {
+            self.password_env = Some(password_env.to_string());
+        }
         if let Some(chain) = matches.value_of("chain") {
             self.chain = Some(chain.parse().unwrap());
         }
+        if let Some(otlp_endpoint) = matches.value_of("otlp-endpoint") {
+            self.otlp_endpoint = Some(otlp_endpoint.to_string());
+        }
+        if let Some(body_compression) = matches.value_of("body-compression") {
+            self.body_compression = Some(body_compression.to_string());
+        }
+        if matches.is_present("execution-audit") {
+            self.execution_audit = Some(true);
+        }
+        if let Some(execution_threads) = matches.value_of("execution-threads") {
+            self.execution_threads = Some(execution_threads.parse().map_err(|e| format!("{}", e))?);
+        }
         Ok(())
     }
 }
@@ -375,6 +608,15 @@ impl Mining {
         if other.engine_signer.is_some() {
             self.engine_signer = other.engine_signer;
         }
+        if other.remote_signer.is_some() {
+            self.remote_signer = other.remote_signer.clone();
+        }
+        if other.remote_signer_timeout.is_some() {
+            self.remote_signer_timeout = other.remote_signer_timeout;
+        }
+        if other.remote_signer_fallback.is_some() {
+            self.remote_signer_fallback = other.remote_signer_fallback;
+        }
         if other.self_nomination_metadata.is_some() {
             self.self_nomination_metadata = other.self_nomination_metadata.clone();
         }
@@ -390,12 +632,30 @@ impl Mining {
         if other.mem_pool_mem_limit.is_some() {
             self.mem_pool_mem_limit = other.mem_pool_mem_limit;
         }
+        if other.mem_pool_flush.is_some() {
+            self.mem_pool_flush = other.mem_pool_flush.clone();
+        }
+        if other.mem_pool_flush_interval.is_some() {
+            self.mem_pool_flush_interval = other.mem_pool_flush_interval;
+        }
+        if other.mem_pool_orphan_retry_blocks.is_some() {
+            self.mem_pool_orphan_retry_blocks = other.mem_pool_orphan_retry_blocks;
+        }
         if other.reseal_on_txs.is_some() {
             self.reseal_on_txs = other.reseal_on_txs.clone();
         }
         if other.reseal_min_period.is_some() {
             self.reseal_min_period = other.reseal_min_period;
         }
+        if other.empty_blocks.is_some() {
+            self.empty_blocks = other.empty_blocks.clone();
+        }
+        if other.empty_block_interval.is_some() {
+            self.empty_block_interval = other.empty_block_interval;
+        }
+        if other.ingress_rate_limits.is_some() {
+            self.ingress_rate_limits = other.ingress_rate_limits.clone();
+        }
     }
 
     pub fn overwrite_with(&mut self, matches: &clap::ArgMatches<'_>) -> Result<(), String> {
@@ -405,6 +665,15 @@ impl Mining {
         if let Some(engine_signer) = matches.value_of("engine-signer") {
             self.engine_signer = Some(engine_signer.parse().map_err(|_| "Invalid address format")?);
         }
+        if let Some(remote_signer) = matches.value_of("remote-signer") {
+            self.remote_signer = Some(remote_signer.to_string());
+        }
+        if let Some(remote_signer_timeout) = matches.value_of("remote-signer-timeout") {
+            self.remote_signer_timeout = Some(remote_signer_timeout.parse().map_err(|_| "Invalid timeout")?);
+        }
+        if matches.is_present("remote-signer-fallback") {
+            self.remote_signer_fallback = Some(true);
+        }
         if let Some(self_nomination_metadata) = matches.value_of("self-nomination-metadata") {
             self.self_nomination_metadata =
                 Some(self_nomination_metadata.parse().map_err(|_| "Invalid self nomination metadata format")?);
@@ -436,18 +705,47 @@ impl Mining {
         if let Some(mem_pool_size) = matches.value_of("mem-pool-size") {
             self.mem_pool_size = Some(mem_pool_size.parse().map_err(|_| "Invalid size")?);
         }
+        if let Some(mem_pool_flush) = matches.value_of("mem-pool-flush") {
+            self.mem_pool_flush = Some(mem_pool_flush.to_string());
+        }
+        if let Some(mem_pool_flush_interval) = matches.value_of("mem-pool-flush-interval") {
+            self.mem_pool_flush_interval =
+                Some(mem_pool_flush_interval.parse().map_err(|_| "Invalid mem pool flush interval")?);
+        }
+        if let Some(mem_pool_orphan_retry_blocks) = matches.value_of("mem-pool-orphan-retry-blocks") {
+            self.mem_pool_orphan_retry_blocks =
+                Some(mem_pool_orphan_retry_blocks.parse().map_err(|_| "Invalid mem pool orphan retry blocks")?);
+        }
         if let Some(reseal_on_txs) = matches.value_of("reseal-on-txs") {
             self.reseal_on_txs = Some(reseal_on_txs.to_string());
         }
         if let Some(reseal_min_period) = matches.value_of("reseal-min-period") {
             self.reseal_min_period = Some(reseal_min_period.parse().map_err(|_| "Invalid period")?);
         }
+        if let Some(empty_blocks) = matches.value_of("empty-blocks") {
+            self.empty_blocks = Some(empty_blocks.to_string());
+        }
+        if let Some(empty_block_interval) = matches.value_of("empty-block-interval") {
+            self.empty_block_interval = Some(empty_block_interval.parse().map_err(|_| "Invalid empty block interval")?);
+        }
         if let Some(allowed_past_gap) = matches.value_of("allowed-past-gap") {
             self.allowed_past_gap = Some(allowed_past_gap.parse().map_err(|_| "Invalid time gap")?);
         }
         if let Some(allowed_future_gap) = matches.value_of("allowed-future-gap") {
             self.allowed_future_gap = Some(allowed_future_gap.parse().map_err(|_| "Invalid time gap")?);
         }
+        if let Some(limits) = matches.values_of("ingress-rate-limit") {
+            let ingress_rate_limits = self.ingress_rate_limits.get_or_insert_with(Default::default);
+            for limit in limits {
+                let mut split = limit.splitn(2, '=');
+                let (tx_type, limit) = match (split.next(), split.next()) {
+                    (Some(tx_type), Some(limit)) => (tx_type, limit),
+                    _ => return Err(format!("Invalid ingress rate limit {}. It should be TYPE=LIMIT", limit)),
+                };
+                let limit = limit.parse().map_err(|_| format!("Invalid ingress rate limit of {}", tx_type))?;
+                ingress_rate_limits.insert(tx_type.to_string(), limit);
+            }
+        }
         Ok(())
     }
 
@@ -460,6 +758,15 @@ impl Mining {
             allowed_future_gap,
         }
     }
+
+    pub fn create_remote_signer(&self, public: Public) -> Result<Option<RemoteSigner>, String> {
+        let address = match &self.remote_signer {
+            Some(address) => address.parse().map_err(|_| format!("Invalid remote signer address: {}", address))?,
+            None => return Ok(None),
+        };
+        let timeout = Duration::from_millis(self.remote_signer_timeout.unwrap_or(500));
+        Ok(Some(RemoteSigner::new(address, public, timeout)))
+    }
 }
 
 impl Network {
@@ -476,6 +783,9 @@ impl Network {
         if other.bootstrap_addresses.is_some() {
             self.bootstrap_addresses = other.bootstrap_addresses.clone();
         }
+        if other.dns_seeds.is_some() {
+            self.dns_seeds = other.dns_seeds.clone();
+        }
         if other.min_peers.is_some() {
             self.min_peers = other.min_peers;
         }
@@ -512,6 +822,9 @@ impl Network {
         if other.whitelist_path.is_some() {
             self.whitelist_path = other.whitelist_path.clone();
         }
+        if other.sentry_addresses.is_some() {
+            self.sentry_addresses = other.sentry_addresses.clone();
+        }
     }
 
     pub fn overwrite_with(&mut self, matches: &clap::ArgMatches<'_>) -> Result<(), String> {
@@ -522,6 +835,9 @@ impl Network {
         if let Some(addresses) = matches.values_of("bootstrap-addresses") {
             self.bootstrap_addresses = Some(addresses.map(|a| a.into()).collect());
         }
+        if let Some(seeds) = matches.values_of("dns-seeds") {
+            self.dns_seeds = Some(seeds.map(|s| s.into()).collect());
+        }
 
         if let Some(interface) = matches.value_of("interface") {
             self.interface = Some(interface.to_string());
@@ -572,6 +888,9 @@ impl Network {
         if let Some(file_path) = matches.value_of("blacklist-path") {
             self.blacklist_path = Some(file_path.to_string());
         }
+        if let Some(addresses) = matches.values_of("sentry-addresses") {
+            self.sentry_addresses = Some(addresses.map(|a| a.into()).collect());
+        }
 
         Ok(())
     }
@@ -594,6 +913,12 @@ impl Rpc {
         if other.port.is_some() {
             self.port = other.port;
         }
+        if other.enable_eth_api {
+            self.enable_eth_api = true;
+        }
+        if other.admin_token_path.is_some() {
+            self.admin_token_path = other.admin_token_path.clone();
+        }
     }
 
     pub fn overwrite_with(&mut self, matches: &clap::ArgMatches<'_>) -> Result<(), String> {
@@ -615,6 +940,12 @@ impl Rpc {
         if matches.is_present("enable-devel-api") {
             self.enable_devel_api = true;
         }
+        if matches.is_present("enable-eth-api") {
+            self.enable_eth_api = true;
+        }
+        if let Some(path) = matches.value_of("admin-token-path") {
+            self.admin_token_path = Some(path.to_string());
+        }
         Ok(())
     }
 }
@@ -633,6 +964,9 @@ impl Informer {
         if other.max_connections.is_some() {
             self.max_connections = other.max_connections;
         }
+        if other.max_subscriptions_per_connection.is_some() {
+            self.max_subscriptions_per_connection = other.max_subscriptions_per_connection;
+        }
     }
     pub fn overwrite_with(&mut self, matches: &clap::ArgMatches<'_>) -> Result<(), String> {
         if matches.is_present("no-informer") {
@@ -648,6 +982,10 @@ impl Informer {
         if let Some(max_connections) = matches.value_of("informer-max-connections") {
             self.max_connections = Some(max_connections.parse().map_err(|_| "Invalid max connections")?);
         }
+        if let Some(max_subscriptions) = matches.value_of("informer-max-subscriptions") {
+            self.max_subscriptions_per_connection =
+                Some(max_subscriptions.parse().map_err(|_| "Invalid max subscriptions")?);
+        }
         Ok(())
     }
 }
@@ -749,6 +1087,79 @@ impl Default for EmailAlarm {
     }
 }
 
+impl Metrics {
+    pub fn merge(&mut self, other: &Metrics) {
+        if other.disable.is_some() {
+            self.disable = other.disable;
+        }
+        if other.interface.is_some() {
+            self.interface = other.interface.clone();
+        }
+        if other.port.is_some() {
+            self.port = other.port;
+        }
+    }
+
+    pub fn overwrite_with(&mut self, matches: &clap::ArgMatches<'_>) -> Result<(), String> {
+        if matches.is_present("enable-metrics") {
+            self.disable = Some(false);
+        }
+        if let Some(interface) = matches.value_of("metrics-interface") {
+            self.interface = Some(interface.to_string());
+        }
+        if let Some(port) = matches.value_of("metrics-port") {
+            self.port = Some(port.parse().map_err(|_| "Invalid metrics port")?);
+        }
+        Ok(())
+    }
+
+    pub fn address(&self) -> Result<std::net::SocketAddr, String> {
+        let interface = self.interface.as_ref().unwrap();
+        let ip = interface.parse().map_err(|_| format!("Invalid metrics interface {}", interface))?;
+        Ok(std::net::SocketAddr::new(ip, self.port.unwrap()))
+    }
+}
+
+impl Database {
+    pub fn merge(&mut self, other: &Database) {
+        if other.backend.is_some() {
+            self.backend = other.backend.clone();
+        }
+        if other.cache_size.is_some() {
+            self.cache_size = other.cache_size;
+        }
+        if other.compaction.is_some() {
+            self.compaction = other.compaction.clone();
+        }
+        if other.max_open_files.is_some() {
+            self.max_open_files = other.max_open_files;
+        }
+    }
+
+    pub fn overwrite_with(&mut self, matches: &clap::ArgMatches<'_>) -> Result<(), String> {
+        if let Some(backend) = matches.value_of("db-backend") {
+            self.backend = Some(backend.to_string());
+        }
+        if let Some(cache_size) = matches.value_of("db-cache-size") {
+            self.cache_size = Some(cache_size.parse().map_err(|_| "Invalid db-cache-size")?);
+        }
+        if let Some(compaction) = matches.value_of("db-compaction") {
+            self.compaction = Some(compaction.to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            disable: Some(true),
+            interface: Some("127.0.0.1".to_string()),
+            port: Some(9615),
+        }
+    }
+}
+
 #[cfg(not(debug_assertions))]
 pub fn read_preset_config() -> &'static str {
     let bytes = include_bytes!("presets/config.prod.toml");
@@ -783,5 +1194,7 @@ pub fn load_config(matches: &clap::ArgMatches<'_>) -> Result<Config, String> {
     config.informer.overwrite_with(&matches)?;
     config.snapshot.overwrite_with(&matches)?;
     config.email_alarm.overwrite_with(&matches)?;
+    config.metrics.overwrite_with(&matches)?;
+    config.database.overwrite_with(&matches)?;
     Ok(config)
 }
@@ -16,9 +16,11 @@
 
 use cidr::IpCidr;
 use ckey::X25519Public as Public;
-use cnetwork::{FilterEntry, NetworkControl, NetworkControlError, SocketAddr};
+use cnetwork::{FilterEntry, NetworkControl, NetworkControlError, PeerReputation, PeerStats, SocketAddr};
+use ctypes::NetworkIdentity;
 use std::collections::HashMap;
 use std::net::IpAddr;
+use std::time::Duration;
 
 pub struct DummyNetworkService {}
 
@@ -109,4 +111,24 @@ impl NetworkControl for DummyNetworkService {
     fn recent_network_usage(&self) -> Result<HashMap<String, usize>, NetworkControlError> {
         Err(NetworkControlError::Disabled)
     }
+
+    fn peer_stats(&self) -> Result<Vec<PeerStats>, NetworkControlError> {
+        Err(NetworkControlError::Disabled)
+    }
+
+    fn peer_reputations(&self) -> Result<Vec<PeerReputation>, NetworkControlError> {
+        Err(NetworkControlError::Disabled)
+    }
+
+    fn ban_peer(&self, _addr: SocketAddr, _duration: Option<Duration>) -> Result<Duration, NetworkControlError> {
+        Err(NetworkControlError::Disabled)
+    }
+
+    fn update_validator_address_book(&self, _identities: Vec<NetworkIdentity>) -> Result<(), NetworkControlError> {
+        Err(NetworkControlError::Disabled)
+    }
+
+    fn validator_address_book(&self) -> Result<Vec<NetworkIdentity>, NetworkControlError> {
+        Err(NetworkControlError::Disabled)
+    }
 }
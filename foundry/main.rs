@@ -25,15 +25,18 @@ use clap::load_yaml;
 pub use crate::run_node::run_node;
 use crate::subcommand::run_subcommand;
 
+mod app_chain;
 mod config;
 mod constants;
 mod dummy_network_service;
 mod json;
+mod metrics;
 mod rpc;
 mod rpc_apis;
 mod run_node;
 mod subcommand;
 mod tests;
+mod tracing_export;
 
 pub const APP_INFO: AppInfo = AppInfo {
     name: "foundry",
@@ -100,6 +103,30 @@ mod timestamp_setup {
         );
     }
 
+    #[distributed_slice(MODULE_INITS)]
+    fn ibc() {
+        add_function_pool(
+            "a060000000012345678901234567890123456789012345678901234567890123".to_owned(),
+            Arc::new(start::<<SingleProcess as ExecutionScheme>::Ipc, codechain_timestamp::ibc::Module>),
+        );
+    }
+
+    #[distributed_slice(MODULE_INITS)]
+    fn nft() {
+        add_function_pool(
+            "a070000000012345678901234567890123456789012345678901234567890123".to_owned(),
+            Arc::new(start::<<SingleProcess as ExecutionScheme>::Ipc, codechain_timestamp::nft::Module>),
+        );
+    }
+
+    #[distributed_slice(MODULE_INITS)]
+    fn dex() {
+        add_function_pool(
+            "a080000000012345678901234567890123456789012345678901234567890123".to_owned(),
+            Arc::new(start::<<SingleProcess as ExecutionScheme>::Ipc, codechain_timestamp::dex::Module>),
+        );
+    }
+
     #[distributed_slice(MODULE_INITS)]
     fn sorting() {
         add_function_pool(
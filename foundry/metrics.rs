@@ -0,0 +1,63 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use prometheus::{Encoder, TextEncoder};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Serves the metrics registered to the default registry of `prometheus` at `GET /metrics`.
+pub fn start_metrics_server(address: SocketAddr) -> Result<JoinHandle<()>, String> {
+    let listener =
+        TcpListener::bind(address).map_err(|e| format!("Cannot listen for metrics at {}: {}", address, e))?;
+    thread::Builder::new()
+        .name("metrics".to_string())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let result = stream.and_then(respond);
+                if let Err(err) = result {
+                    cdebug!(RPC, "Cannot serve the metrics: {}", err);
+                }
+            }
+        })
+        .map_err(|e| format!("Cannot start the metrics server: {}", e))
+}
+
+fn respond(mut stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+
+    let encoder = TextEncoder::new();
+    let (status, content_type, body) = if request_line.starts_with("GET /metrics ") {
+        let mut body = Vec::new();
+        encoder.encode(&prometheus::gather(), &mut body).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        ("200 OK", encoder.format_type(), body)
+    } else {
+        ("404 Not Found", "text/plain", b"Not Found".to_vec())
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(&body)
+}
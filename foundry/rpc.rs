@@ -17,9 +17,11 @@
 use crate::config::Config;
 use crate::rpc_apis;
 use crpc::{
-    jsonrpc_core, start_http, start_ipc, start_ws, HttpServer, IpcServer, MetaIoHandler, Middleware, WsError, WsServer,
+    jsonrpc_core, start_http_with_chains, start_ipc, start_ws, HttpServer, IpcServer, MetaIoHandler, Middleware,
+    WsError, WsServer,
 };
 use futures::future::Either;
+use std::collections::HashMap;
 use std::io;
 
 #[derive(Debug, PartialEq)]
@@ -30,13 +32,15 @@ pub struct RpcHttpConfig {
     pub hosts: Option<Vec<String>>,
 }
 
-pub fn rpc_http_start(
-    server: MetaIoHandler<(), impl Middleware<()>>,
+/// Serves the main chain at the root path and each app-chain in `chains` at `/chains/<id>`.
+pub fn rpc_http_start<S: Middleware<()>>(
+    server: MetaIoHandler<(), S>,
+    chains: HashMap<String, MetaIoHandler<(), S>>,
     config: RpcHttpConfig,
 ) -> Result<HttpServer, String> {
     let url = format!("{}:{}", config.interface, config.port);
     let addr = url.parse().map_err(|_| format!("Invalid JSONRPC listen host/port given: {}", url))?;
-    let start_result = start_http(&addr, config.cors.clone(), config.hosts.clone(), server);
+    let start_result = start_http_with_chains(&addr, config.cors.clone(), config.hosts.clone(), server, chains);
     match start_result {
         Err(ref err) if err.kind() == io::ErrorKind::AddrInUse => {
             Err(format!("RPC address {} is already in use, make sure that another instance of a CodeChain node is not running or change the address using the --jsonrpc-port option.", url))
@@ -135,6 +139,10 @@ impl LogMiddleware {
 
     fn print_call(call: &jsonrpc_core::Call) {
         match call {
+            // The admin APIs take the admin token as a parameter, which must not be logged.
+            jsonrpc_core::Call::MethodCall(method_call) if method_call.method.starts_with("admin_") => {
+                cinfo!(RPC, "RPC call({}(..))", method_call.method);
+            }
             jsonrpc_core::Call::MethodCall(method_call) => {
                 cinfo!(
                     RPC,
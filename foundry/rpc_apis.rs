@@ -19,7 +19,7 @@ use ccore::{AccountProvider, Client, Miner};
 use clogger::SLOGGER;
 use cnetwork::{EventSender, NetworkControl};
 use crpc::{MetaIoHandler, Middleware, Params, Value};
-use csync::BlockSyncEvent;
+use csync::{BlockSyncEvent, TransactionSyncEvent};
 use std::sync::Arc;
 
 pub struct ApiDependencies {
@@ -28,20 +28,40 @@ pub struct ApiDependencies {
     pub network_control: Arc<dyn NetworkControl>,
     pub account_provider: Arc<AccountProvider>,
     pub block_sync: Option<EventSender<BlockSyncEvent>>,
+    pub transaction_sync: Option<EventSender<TransactionSyncEvent>>,
+    pub db_path: String,
+    pub admin_token: Option<String>,
 }
 
 impl ApiDependencies {
     pub fn extend_api(&self, config: &Config, handler: &mut MetaIoHandler<(), impl Middleware<()>>) {
         use crpc::v1::*;
         handler.extend_with(ChainClient::new(Arc::clone(&self.client)).to_delegate());
-        handler.extend_with(MempoolClient::new(Arc::clone(&self.client)).to_delegate());
+        handler.extend_with(MempoolClient::new(Arc::clone(&self.client), self.transaction_sync.clone()).to_delegate());
         handler.extend_with(SnapshotClient::new(Arc::clone(&self.client), config.snapshot.path.clone()).to_delegate());
+        handler.extend_with(
+            NodeClient::new(
+                Arc::clone(&self.client),
+                Arc::clone(&self.miner),
+                self.block_sync.clone(),
+                self.db_path.clone(),
+            )
+            .to_delegate(),
+        );
         if config.rpc.enable_devel_api {
             handler.extend_with(
                 DevelClient::new(Arc::clone(&self.client), Arc::clone(&self.miner), self.block_sync.clone())
                     .to_delegate(),
             );
         }
+        if let Some(token) = &self.admin_token {
+            handler.extend_with(
+                AdminClient::new(Arc::clone(&self.client), Arc::clone(&self.miner), token.clone()).to_delegate(),
+            );
+        }
+        if config.rpc.enable_eth_api {
+            handler.extend_with(EthClient::new(Arc::clone(&self.client)).to_delegate());
+        }
         handler.extend_with(NetClient::new(Arc::clone(&self.network_control)).to_delegate());
     }
 }
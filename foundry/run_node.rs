@@ -14,45 +14,59 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::app_chain::{register_app_chain_extensions, start_app_chain, start_chain, AppChainNetwork, ChainInstance};
 use crate::config::{self, load_config};
-use crate::constants::{DEFAULT_DB_PATH, DEFAULT_KEYS_PATH};
+use crate::constants::{DEFAULT_DB_PATH, DEFAULT_KEYS_PATH, DEFAULT_NODE_KEY_PATH};
 use crate::dummy_network_service::DummyNetworkService;
 use crate::json::PasswordFile;
+use crate::metrics;
 use crate::rpc::{rpc_http_start, rpc_ipc_start, rpc_ws_start, setup_rpc_server};
 use crate::rpc_apis::ApiDependencies;
-use ccore::{snapshot_notify, EngineClient};
+use crate::tracing_export;
+use ccore::metrics::MeteredDb;
 use ccore::{
-    AccountProvider, AccountProviderError, ChainNotify, Client, ClientConfig, ClientService, EngineInfo, EngineType,
-    Miner, MinerService, PeerDb, Scheme, NUM_COLUMNS,
+    open_database, AccountProvider, AccountProviderError, BlockChainClient, ChainNotify, Client, ClientConfig,
+    ClientService, EngineInfo, EngineType, Miner, MinerService, PeerDb, Scheme,
 };
+use ccore::{snapshot_notify, EngineClient};
 use cdiscovery::{Config, Discovery};
-use cinformer::{handler::Handler, InformerEventSender, InformerService, MetaIoHandler, PubSubHandler, Session};
-use ckey::{Ed25519Public as Public, NetworkId, PlatformAddress};
+use cinformer::{
+    handler::Handler, ChainEventNotifier, InformerEventSender, InformerService, MetaIoHandler, PubSubHandler, Session,
+};
+use ckey::hex::ToHex;
+use ckey::{
+    Ed25519KeyPair as KeyPair, Ed25519Private as Private, Ed25519Public as Public, Generator, KeyPairTrait, NetworkId,
+    PlatformAddress, Random,
+};
 use ckeystore::accounts_dir::RootDiskDirectory;
-use ckeystore::KeyStore;
+use ckeystore::{KeyDerivation, KeyStore};
 use clap::ArgMatches;
 use clogger::{EmailAlarm, LoggerConfig};
 use cnetwork::{Filters, ManagingPeerdb, NetworkConfig, NetworkControl, NetworkService, RoutingTable, SocketAddr};
+use coordinator::values::Value;
 use coordinator::{AppDesc, Coordinator};
 use crossbeam::unbounded;
 use crossbeam_channel as crossbeam;
 use csync::snapshot::Service as SnapshotService;
 use csync::{BlockSyncExtension, BlockSyncSender, TransactionSyncExtension};
-use ctimer::TimerLoop;
+use ctimer::{TimeoutHandler, TimerApi, TimerLoop, TimerToken};
 use ctrlc::CtrlC;
+use ctypes::{BlockHash, BlockId};
 use fdlimit::raise_fd_limit;
+use foundry_graphql::SchedulingPolicy;
 use kvdb::KeyValueDB;
-use kvdb_rocksdb::{Database, DatabaseConfig};
 use parking_lot::{Condvar, Mutex};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::env;
 use std::fs;
-use std::path::Path;
 use std::sync::{Arc, Weak};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 struct ClientWrapper(Arc<Client>);
 
 impl foundry_graphql::ManageSession for ClientWrapper {
-    fn new_session(&self, block: ctypes::BlockId) -> coordinator::module::SessionId {
+    fn new_session(&self, block: ctypes::BlockId) -> Option<coordinator::module::SessionId> {
         self.0.new_session(block)
     }
 
@@ -61,10 +75,60 @@ impl foundry_graphql::ManageSession for ClientWrapper {
     }
 }
 
+/// Keeps the validator address book of the network layer up to date with the best block.
+struct AddressBookUpdater {
+    client: Arc<Client>,
+    network_control: Arc<dyn NetworkControl>,
+}
+
+impl AddressBookUpdater {
+    fn update(&self) {
+        if let Some(identities) = self.client.network_identities(&BlockId::Latest) {
+            if let Err(err) = self.network_control.update_validator_address_book(identities) {
+                cwarn!(NETWORK, "Cannot update the validator address book: {:?}", err);
+            }
+        }
+    }
+}
+
+impl ChainNotify for AddressBookUpdater {
+    fn new_blocks(&self, _imported: Vec<BlockHash>, _invalid: Vec<BlockHash>, enacted: Vec<BlockHash>) {
+        if !enacted.is_empty() {
+            self.update();
+        }
+    }
+}
+
+/// Watches the sentries of a validator in the sentry mode, connecting to the lost ones again.
+struct SentryWatcher {
+    client: Arc<Client>,
+    network_control: Arc<dyn NetworkControl>,
+    sentries: Vec<SocketAddr>,
+}
+
+const SENTRY_CHECK_TIMER_TOKEN: TimerToken = 1;
+const SENTRY_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+impl TimeoutHandler for SentryWatcher {
+    fn on_timeout(&self, token: TimerToken) {
+        debug_assert_eq!(SENTRY_CHECK_TIMER_TOKEN, token);
+        let mut connected = 0;
+        for sentry in &self.sentries {
+            if self.network_control.is_connected(sentry).unwrap_or(false) {
+                connected += 1;
+            } else if let Err(err) = self.network_control.connect(*sentry) {
+                cwarn!(NETWORK, "Cannot connect to the sentry {}: {:?}", sentry, err);
+            }
+        }
+        self.client.note_sentries(connected, self.sentries.len());
+    }
+}
+
 fn network_start(
     network_id: NetworkId,
     timer_loop: TimerLoop,
     cfg: &NetworkConfig,
+    node_key_pair: KeyPair,
     routing_table: Arc<RoutingTable>,
     peer_db: Box<dyn ManagingPeerdb>,
     sender: InformerEventSender,
@@ -76,7 +140,9 @@ fn network_start(
         network_id,
         timer_loop,
         sockaddress,
+        node_key_pair,
         cfg.bootstrap_addresses.clone(),
+        cfg.dns_seeds.clone(),
         cfg.min_peers,
         cfg.max_peers,
         filters,
@@ -108,7 +174,7 @@ fn discovery_start(
     Ok(())
 }
 
-fn client_start(
+pub fn client_start(
     client_config: &ClientConfig,
     timer_loop: &TimerLoop,
     db: Arc<dyn KeyValueDB>,
@@ -121,11 +187,12 @@ fn client_start(
     let service = ClientService::start(client_config, &scheme, db, miner, coordinator, reseal_timer.clone())
         .map_err(|e| format!("Client service error: {}", e))?;
     reseal_timer.set_handler(Arc::downgrade(&service.client()));
+    service.client().start_seal_interval_timer();
 
     Ok(service)
 }
 
-fn new_miner(
+pub fn new_miner(
     config: &config::Config,
     scheme: &Scheme,
     ap: Arc<AccountProvider>,
@@ -134,8 +201,23 @@ fn new_miner(
 ) -> Result<Arc<Miner>, String> {
     let miner = Miner::new(config.miner_options()?, scheme, db, coordinator);
 
-    match miner.engine_type() {
-        EngineType::PBFT => match &config.mining.engine_signer {
+    let engine_type = miner.engine_type();
+    match engine_type {
+        EngineType::PBFT | EngineType::RoundRobin => match &config.mining.engine_signer {
+            Some(ref engine_signer) if config.mining.remote_signer.is_some() => {
+                let remote_signer = config
+                    .mining
+                    .create_remote_signer((*engine_signer).into_pubkey())?
+                    .expect("The remote signer is configured");
+                let fallback = if config.mining.remote_signer_fallback == Some(true) {
+                    Some(ap)
+                } else {
+                    None
+                };
+                miner
+                    .set_remote_author(remote_signer, fallback)
+                    .map_err(|e| format!("Cannot sign with the remote signer of {}: {}", engine_signer, e))?;
+            }
             Some(ref engine_signer) => match miner.set_author(ap, (*engine_signer).into_pubkey()) {
                 Err(AccountProviderError::NotUnlocked) => {
                     return Err(
@@ -146,12 +228,11 @@ fn new_miner(
                 _ => (),
             },
             None if config.mining.author.is_some() => {
-                return Err("PBFT type engine needs not an author but an engine signer for mining. Specify the engine signer using --engine-signer option."
-                    .to_string())
+                return Err(format!("{:?} type engine needs not an author but an engine signer for mining. Specify the engine signer using --engine-signer option.", engine_type))
             }
             None => (),
         },
-        EngineType::Solo => miner
+        EngineType::Solo | EngineType::InstantSeal => miner
             .set_author(ap, config.mining.author.map_or(Public::default(), PlatformAddress::into_pubkey))
             .expect("set_author never fails when Solo is used"),
     }
@@ -173,9 +254,11 @@ fn wait_for_exit() {
     exit.1.wait(&mut l);
 }
 
-fn prepare_account_provider(keys_path: &str) -> Result<Arc<AccountProvider>, String> {
+pub fn prepare_account_provider(keys_path: &str) -> Result<Arc<AccountProvider>, String> {
     let keystore_dir = RootDiskDirectory::create(keys_path).map_err(|_| "Cannot read key path directory")?;
-    let keystore = KeyStore::open(Box::new(keystore_dir)).map_err(|_| "Cannot open key store")?;
+    // New keys are encrypted with Argon2id, while the keys encrypted with PBKDF2 or scrypt are still unlocked
+    let keystore = KeyStore::open_with_derivation(Box::new(keystore_dir), KeyDerivation::ARGON2ID)
+        .map_err(|_| "Cannot open key store")?;
     Ok(AccountProvider::new(keystore))
 }
 
@@ -204,29 +287,103 @@ fn unlock_accounts(ap: &AccountProvider, pf: &PasswordFile) -> Result<(), String
     Ok(())
 }
 
-fn prepare_coordinator() -> Arc<Coordinator> {
-    let app_desc = AppDesc::from_str(&fs::read_to_string("./app-desc.yml").unwrap()).unwrap();
-    Arc::new(Coordinator::from_app_desc(&app_desc).unwrap())
+fn unlock_account_from_env(ap: &AccountProvider, address: PlatformAddress, name: &str) -> Result<(), String> {
+    let password =
+        env::var(name).map_err(|e| format!("Cannot read the password of {} from ${}: {}", address, name, e))?;
+    // Do not leave the password to the processes that the node spawns.
+    env::remove_var(name);
+    ap.unlock_account_permanently(address.into_pubkey(), password.into())
+        .map_err(|e| format!("Failed to unlock account {}: {}", address, e))
 }
 
-pub fn open_db(cfg: &config::Operating, client_config: &ClientConfig) -> Result<Arc<dyn KeyValueDB>, String> {
-    let base_path = cfg.base_path.as_ref().unwrap().clone();
-    let db_path = cfg.db_path.as_ref().map(String::clone).unwrap_or_else(|| base_path + "/" + DEFAULT_DB_PATH);
+pub fn load_app_desc() -> AppDesc {
+    AppDesc::from_str(&fs::read_to_string("./app-desc.yml").unwrap()).unwrap()
+}
+
+pub fn prepare_coordinator(app_desc: &AppDesc) -> Arc<Coordinator> {
+    Arc::new(Coordinator::from_app_desc(app_desc).unwrap())
+}
+
+/// Reads how the module shares the GraphQL server from its `graphql-weight` and `graphql-max-concurrency` tags.
+fn graphql_scheduling(app_desc: &AppDesc, module: &str) -> Result<SchedulingPolicy, String> {
+    let mut policy = SchedulingPolicy::default();
+    let tags = match app_desc.modules.get(module) {
+        Some(setup) => &setup.tags,
+        None => return Ok(policy),
+    };
+    let tag = |name: &str| -> Result<Option<i128>, String> {
+        match tags.get(name) {
+            None => Ok(None),
+            Some(Value::Int(value)) if *value > 0 => Ok(Some(*value)),
+            Some(value) => Err(format!("{} of {} must be a positive integer, but {:?}", name, module, value)),
+        }
+    };
+    if let Some(weight) = tag("graphql-weight")? {
+        policy.weight = u32::try_from(weight).map_err(|_| format!("graphql-weight of {} is too large", module))?;
+    }
+    if let Some(max_concurrency) = tag("graphql-max-concurrency")? {
+        policy.max_concurrency = usize::try_from(max_concurrency).unwrap_or(usize::MAX);
+    }
+    Ok(policy)
+}
+
+pub fn open_db(db_path: &str, client_config: &ClientConfig) -> Result<Arc<dyn KeyValueDB>, String> {
     // this is for debug
-    std::process::Command::new("rm").arg("-rf").arg(&db_path).output().unwrap();
+    std::process::Command::new("rm").arg("-rf").arg(db_path).output().unwrap();
 
-    let client_path = Path::new(&db_path);
-    let mut db_config = DatabaseConfig::with_columns(NUM_COLUMNS);
+    open_database(db_path, client_config)
+}
 
-    db_config.memory_budget = client_config.db_cache_size;
-    db_config.compaction = client_config.db_compaction.compaction_profile(client_path);
+/// Opens the DB without clearing it.
+pub fn open_existing_db(cfg: &config::Operating, client_config: &ClientConfig) -> Result<Arc<dyn KeyValueDB>, String> {
+    open_database(&db_path(cfg), client_config)
+}
 
-    let db = Arc::new(
-        Database::open(&db_config, &client_path.to_str().expect("DB path could not be converted to string."))
-            .map_err(|_e| "Low level database error. Some issue with disk?".to_string())?,
-    );
+/// The archive is only read, but it is opened like the local DB since it has the same columns.
+/// It must be kept in the same store as the local DB.
+fn open_archive_db(path: &str, client_config: &ClientConfig) -> Result<Arc<dyn KeyValueDB>, String> {
+    open_database(path, client_config).map_err(|e| format!("Cannot open the archive database: {}", e))
+}
+
+fn db_path(cfg: &config::Operating) -> String {
+    let base_path = cfg.base_path.as_ref().unwrap().clone();
+    cfg.db_path.as_ref().map(String::clone).unwrap_or_else(|| base_path + "/" + DEFAULT_DB_PATH)
+}
+
+/// Each app-chain has its own DB under the base path, apart from the one of the main chain.
+pub fn app_chain_db_path(cfg: &config::Operating, id: &str) -> String {
+    let base_path = cfg.base_path.as_ref().unwrap();
+    format!("{}/chains/{}/{}", base_path, id, DEFAULT_DB_PATH)
+}
 
-    Ok(db)
+pub fn keys_path(cfg: &config::Operating) -> String {
+    let base_path = cfg.base_path.as_ref().unwrap().clone();
+    cfg.keys_path.as_ref().map(String::clone).unwrap_or_else(|| base_path + "/" + DEFAULT_KEYS_PATH)
+}
+
+pub fn node_key_path(cfg: &config::Operating) -> String {
+    let base_path = cfg.base_path.as_ref().unwrap().clone();
+    base_path + "/" + DEFAULT_NODE_KEY_PATH
+}
+
+/// Loads the key the node proves in the p2p handshake, creating it on the first run.
+/// Validators sign it into their network identities, so it must outlive restarts.
+pub fn load_or_create_node_key(path: &str) -> Result<KeyPair, String> {
+    match fs::read_to_string(path) {
+        Ok(hex) => {
+            let private =
+                hex.trim().parse::<Private>().map_err(|err| format!("Invalid node key in {}: {}", path, err))?;
+            Ok(KeyPair::from_private(private))
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            let key_pair: KeyPair = Random.generate().expect("ed25519 context has generation capabilities; qed");
+            fs::write(path, key_pair.private().as_ref().to_hex())
+                .map_err(|err| format!("Cannot write the node key to {}: {}", path, err))?;
+            cinfo!(NETWORK, "Created the node key {:?} in {}", key_pair.public(), path);
+            Ok(key_pair)
+        }
+        Err(err) => Err(format!("Cannot read the node key from {}: {}", path, err)),
+    }
 }
 
 pub fn run_node(matches: &ArgMatches<'_>, test_cmd: Option<&str>) -> Result<(), String> {
@@ -236,6 +393,15 @@ pub fn run_node(matches: &ArgMatches<'_>, test_cmd: Option<&str>) -> Result<(),
     let timer_loop = TimerLoop::new(2);
 
     let config = load_config(matches)?;
+    config.validate_app_chains()?;
+    let _otlp_exporter = match &config.operating.otlp_endpoint {
+        Some(endpoint) => {
+            let exporter = tracing_export::init_otlp(endpoint)?;
+            cinfo!(CLIENT, "Exporting the tracing spans to {}", endpoint);
+            Some(exporter)
+        }
+        None => None,
+    };
 
     let time_gap_params = config.mining.create_time_gaps();
     let scheme = match &config.operating.chain {
@@ -244,25 +410,34 @@ pub fn run_node(matches: &ArgMatches<'_>, test_cmd: Option<&str>) -> Result<(),
     };
     scheme.engine.register_time_gap_config_to_worker(time_gap_params);
 
-    let coordinator = prepare_coordinator();
-
     let pf = load_password_file(&config.operating.password_path)?;
-    let base_path = config.operating.base_path.as_ref().unwrap().clone();
-    let keys_path =
-        config.operating.keys_path.as_ref().map(String::clone).unwrap_or_else(|| base_path + "/" + DEFAULT_KEYS_PATH);
-    let ap = prepare_account_provider(&keys_path)?;
+    let ap = prepare_account_provider(&keys_path(&config.operating))?;
     unlock_accounts(&*ap, &pf)?;
+    if let (Some(engine_signer), Some(password_env)) = (config.mining.engine_signer, &config.operating.password_env) {
+        unlock_account_from_env(&*ap, engine_signer, password_env)?;
+    }
 
-    let client_config: ClientConfig = Default::default();
-    let db = open_db(&config.operating, &client_config)?;
-
-    let miner = new_miner(&config, &scheme, ap.clone(), Arc::clone(&db), coordinator.clone())?;
-    let client = client_start(&client_config, &timer_loop, db, &scheme, miner.clone(), coordinator)?;
-    miner.recover_from_db();
+    let client_config = config.client_config()?;
+    let db: Arc<dyn KeyValueDB> = Arc::new(MeteredDb::new(open_db(&db_path(&config.operating), &client_config)?));
+
+    let ChainInstance {
+        client,
+        miner,
+        app_desc,
+    } = start_chain(&config, &scheme, load_app_desc(), db, &client_config, Arc::clone(&ap), &timer_loop)?;
+    if let Some(archive_db_path) = &config.operating.archive_db_path {
+        let archive = open_archive_db(archive_db_path, &client_config)?;
+        client.client().attach_archive(archive).map_err(|e| format!("Cannot attach the archive database: {}", e))?;
+        cinfo!(CLIENT, "Attached the archive database at {}", archive_db_path);
+    }
+    let app_chains = config
+        .app_chains
+        .iter()
+        .map(|app_chain| start_app_chain(&config, app_chain, Arc::clone(&ap), &timer_loop))
+        .collect::<Result<Vec<_>, _>>()?;
 
     let _graphql_webserver = {
         use foundry_graphql::{GraphQlRequestHandler, ServerData};
-        use std::collections::HashMap;
         use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 
         let handlers: HashMap<String, GraphQlRequestHandler> = client
@@ -270,12 +445,13 @@ pub fn run_node(matches: &ArgMatches<'_>, test_cmd: Option<&str>) -> Result<(),
             .graphql_handlers()
             .iter()
             .map(|(k, v)| {
-                (k.to_string(), GraphQlRequestHandler {
+                Ok((k.to_string(), GraphQlRequestHandler {
                     handler: Arc::clone(v),
                     session_needed: true,
-                })
+                    scheduling: graphql_scheduling(&app_desc, k)?,
+                }))
             })
-            .collect();
+            .collect::<Result<_, String>>()?;
         // add chain-level handlers
 
         let server_data = ServerData::new(Arc::new(ClientWrapper(client.client())), handlers);
@@ -305,6 +481,11 @@ pub fn run_node(matches: &ArgMatches<'_>, test_cmd: Option<&str>) -> Result<(),
     if let Some(email_alarm) = email_alarm {
         panic_hook::set_with_email_alarm(email_alarm);
     }
+    if !config.metrics.disable.unwrap() {
+        let address = config.metrics.address()?;
+        metrics::start_metrics_server(address)?;
+        cinfo!(CLIENT, "Serving the metrics at http://{}/metrics", address);
+    }
 
     // FIXME: unbound would cause memory leak.
     // FIXME: The full queue should be handled.
@@ -320,29 +501,57 @@ pub fn run_node(matches: &ArgMatches<'_>, test_cmd: Option<&str>) -> Result<(),
         }
     };
 
+    let _maybe_informer_notifier = if !config.informer.disable.unwrap() {
+        let notifier = Arc::new(ChainEventNotifier::new(client.client(), informer_event_sender.clone()));
+        client.client().add_notify(Arc::downgrade(&notifier) as Weak<dyn ChainNotify>);
+        Some(notifier) // Hold the notifier to ensure it not to be destroyed.
+    } else {
+        None
+    };
+
     let mut _maybe_sync = None;
     let mut maybe_sync_sender = None;
+    let mut maybe_transaction_sync_sender = None;
+    let mut _maybe_address_book_updater = None;
+    let mut _maybe_sentry_watcher: Option<(Arc<SentryWatcher>, TimerApi)> = None;
+    let mut app_chain_networks: HashMap<String, AppChainNetwork> = HashMap::new();
 
     scheme.engine.register_chain_notify(client.client().as_ref());
 
     let network_service: Arc<dyn NetworkControl> = {
         if !config.network.disable.unwrap() {
             let network_config = config.network_config()?;
+            let sentry_addresses = config.sentry_addresses()?;
+            let sentry_timer = timer_loop.new_timer_with_name("Sentry watcher");
             // XXX: What should we do if the network id has been changed.
             let c = client.client();
             let network_id = c.network_id();
             let routing_table = RoutingTable::new();
             let peer_db = PeerDb::new(c.get_kvdb());
+            let node_key_pair = load_or_create_node_key(&node_key_path(&config.operating))?;
             let service = network_start(
                 network_id,
                 timer_loop,
                 &network_config,
+                node_key_pair,
                 Arc::clone(&routing_table),
                 peer_db,
                 informer_event_sender,
             )?;
 
-            if config.network.discovery.unwrap() {
+            if !sentry_addresses.is_empty() {
+                cinfo!(NETWORK, "Node runs in the sentry mode behind {} sentries", sentry_addresses.len());
+                let sentry_watcher = Arc::new(SentryWatcher {
+                    client: client.client(),
+                    network_control: Arc::clone(&service) as Arc<dyn NetworkControl>,
+                    sentries: sentry_addresses,
+                });
+                sentry_timer.set_handler(Arc::downgrade(&sentry_watcher));
+                sentry_timer
+                    .schedule_repeat(SENTRY_CHECK_INTERVAL, SENTRY_CHECK_TIMER_TOKEN)
+                    .map_err(|e| format!("Cannot schedule the sentry watcher: {:?}", e))?;
+                _maybe_sentry_watcher = Some((sentry_watcher, sentry_timer));
+            } else if config.network.discovery.unwrap() {
                 discovery_start(&service, &config.network, routing_table)?;
             } else {
                 cwarn!(DISCOVERY, "Node runs without discovery extension");
@@ -355,9 +564,20 @@ pub fn run_node(matches: &ArgMatches<'_>, test_cmd: Option<&str>) -> Result<(),
                         (Some(hash), Some(num)) => Some((hash, num)),
                         _ => None,
                     };
+                    if let Some((hash, num)) = snapshot_target {
+                        if let Some(checkpoint) = scheme.checkpoints.get(num) {
+                            if *checkpoint.hash != hash {
+                                return Err(format!(
+                                    "The snapshot block #{} ({}) is not the checkpoint {}",
+                                    num, hash, checkpoint.hash
+                                ))
+                            }
+                        }
+                    }
                     let snapshot_dir = config.snapshot.path.clone();
+                    let body_compression = client_config.body_compression;
                     service.register_extension(move |api| {
-                        BlockSyncExtension::new(client, api, snapshot_target, snapshot_dir)
+                        BlockSyncExtension::new(client, api, snapshot_target, snapshot_dir, body_compression)
                     })
                 };
                 let sync = Arc::new(BlockSyncSender::from(sync_sender.clone()));
@@ -367,10 +587,23 @@ pub fn run_node(matches: &ArgMatches<'_>, test_cmd: Option<&str>) -> Result<(),
             }
             if config.network.transaction_relay.unwrap() {
                 let client = client.client();
-                service.register_extension(move |api| TransactionSyncExtension::new(client, api));
+                maybe_transaction_sync_sender =
+                    Some(service.register_extension(move |api| TransactionSyncExtension::new(client, api)));
             }
 
             scheme.engine.register_network_extension_to_service(&service);
+            for app_chain in &app_chains {
+                let network = register_app_chain_extensions(&config, &client_config, &service, app_chain);
+                app_chain_networks.insert(app_chain.id.clone(), network);
+            }
+
+            let address_book_updater = Arc::new(AddressBookUpdater {
+                client: client.client(),
+                network_control: Arc::clone(&service) as Arc<dyn NetworkControl>,
+            });
+            address_book_updater.update();
+            client.client().add_notify(Arc::downgrade(&address_book_updater) as Weak<dyn ChainNotify>);
+            _maybe_address_book_updater = Some(address_book_updater);
 
             service
         } else {
@@ -382,9 +615,10 @@ pub fn run_node(matches: &ArgMatches<'_>, test_cmd: Option<&str>) -> Result<(),
         if !config.informer.disable.unwrap() {
             let io: PubSubHandler<Arc<Session>> = PubSubHandler::new(MetaIoHandler::default());
             let mut informer_handler = Handler::new(io);
-            informer_handler.event_subscription(informer_sub_sender);
+            let informer_config = config.informer_config();
+            informer_handler.event_subscription(informer_sub_sender, informer_config.max_subscriptions_per_connection);
 
-            Some(informer_handler.start_ws(config.informer_config())?)
+            Some(informer_handler.start_ws(informer_config)?)
         } else {
             None
         }
@@ -396,12 +630,33 @@ pub fn run_node(matches: &ArgMatches<'_>, test_cmd: Option<&str>) -> Result<(),
             network_control: Arc::clone(&network_service),
             account_provider: ap,
             block_sync: maybe_sync_sender,
+            transaction_sync: maybe_transaction_sync_sender,
+            db_path: db_path(&config.operating),
+            admin_token: config.admin_token()?,
         };
 
         let rpc_server = {
             if !config.rpc.disable.unwrap() {
                 let server = setup_rpc_server(&config, &rpc_apis_deps);
-                Some(rpc_http_start(server, config.rpc_http_config())?)
+                // The app-chains share the peers of the main chain, but have their own sync extensions.
+                let chains = app_chains
+                    .iter()
+                    .map(|app_chain| {
+                        let network = app_chain_networks.get(&app_chain.id);
+                        let deps = ApiDependencies {
+                            client: app_chain.chain.client.client(),
+                            miner: Arc::clone(&app_chain.chain.miner),
+                            network_control: Arc::clone(&network_service),
+                            account_provider: Arc::clone(&rpc_apis_deps.account_provider),
+                            block_sync: network.and_then(|network| network.block_sync.clone()),
+                            transaction_sync: network.and_then(|network| network.transaction_sync.clone()),
+                            db_path: app_chain.db_path.clone(),
+                            admin_token: rpc_apis_deps.admin_token.clone(),
+                        };
+                        (app_chain.id.clone(), setup_rpc_server(&config, &deps))
+                    })
+                    .collect();
+                Some(rpc_http_start(server, chains, config.rpc_http_config())?)
             } else {
                 None
             }
@@ -444,6 +699,9 @@ pub fn run_node(matches: &ArgMatches<'_>, test_cmd: Option<&str>) -> Result<(),
     // drop the scheme to free up genesis state.
     drop(scheme);
     client.client().engine().complete_register();
+    for app_chain in &app_chains {
+        app_chain.chain.client.client().engine().complete_register();
+    }
 
     cinfo!(TEST_SCRIPT, "Initialization complete");
 
@@ -468,6 +726,11 @@ pub fn run_node(matches: &ArgMatches<'_>, test_cmd: Option<&str>) -> Result<(),
         server.close_handle().close();
         server.wait().map_err(|err| format!("Error while closing jsonrpc ws server: {}", err))?;
     }
+    // The servers are closed, so no more transactions come in.
+    client.client().shutdown();
+    for app_chain in &app_chains {
+        app_chain.chain.client.client().shutdown();
+    }
 
     Ok(())
 }
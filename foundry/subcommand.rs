@@ -14,7 +14,32 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::config::{self, load_config, ChainType};
+use crate::run_node::{
+    keys_path, load_app_desc, load_or_create_node_key, node_key_path, open_existing_db, prepare_account_provider,
+    prepare_coordinator,
+};
+use ccore::{
+    export_fork_state, import_state, migrate_database, open_database, translate_validators, verify_database_offline,
+    ClientConfig, ForkPoint, Scheme,
+};
+use ckey::{
+    sign, Ed25519KeyPair as KeyPair, Ed25519Public as Public, Generator, KeyPairTrait, NetworkId, Password,
+    PlatformAddress, Random,
+};
 use clap::ArgMatches;
+use ctypes::{BlockNumber, NetworkIdentity, ValidatorKeyChange};
+use primitives::H256;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::str::FromStr;
+
+/// The file in the export directory that describes the fork point.
+const FORK_MANIFEST: &str = "fork.json";
 
 pub fn run_subcommand(matches: &ArgMatches<'_>) -> Result<(), String> {
     let subcommand = matches.subcommand.as_ref().unwrap();
@@ -23,6 +48,340 @@ pub fn run_subcommand(matches: &ArgMatches<'_>) -> Result<(), String> {
             println!("{}", env!("VERGEN_SHA"));
             Ok(())
         }
+        "schema" => {
+            let schema = serde_json::to_string_pretty(&ctypes::schema()).map_err(|e| e.to_string())?;
+            println!("{}", schema);
+            Ok(())
+        }
+        "verify-db" => verify_db(matches, &subcommand.matches),
+        "migrate-db" => migrate_db(matches, &subcommand.matches),
+        "check-scheme" => check_scheme(matches),
+        "export-fork-state" => export_fork(matches, &subcommand.matches),
+        "export-genesis" => export_genesis(matches, &subcommand.matches),
+        "import-fork-state" => import_fork(matches, &subcommand.matches),
+        "account-new" => account_new(matches),
+        "rotate-validator-key" => rotate_validator_key(matches, &subcommand.matches),
+        "network-identity" => network_identity(matches, &subcommand.matches),
         _ => Err("Invalid subcommand.rs".into()),
     }
 }
+
+fn verify_db(matches: &ArgMatches<'_>, sub_matches: &ArgMatches<'_>) -> Result<(), String> {
+    let parse_number = |name: &str| -> Result<Option<BlockNumber>, String> {
+        sub_matches
+            .value_of(name)
+            .map(|value| value.parse().map_err(|_| format!("Invalid {}: {}", name, value)))
+            .transpose()
+    };
+    let from = parse_number("from")?.unwrap_or(0);
+    let to = parse_number("to")?.unwrap_or_else(BlockNumber::max_value);
+    let repair = sub_matches.is_present("repair");
+
+    let config = load_config(matches)?;
+    let scheme = match &config.operating.chain {
+        Some(chain) => chain.scheme()?,
+        None => return Err("chain is not specified".to_string()),
+    };
+    let db = open_existing_db(&config.operating, &config.client_config()?)?;
+
+    let report = verify_database_offline(&scheme, db, from..to, repair, &mut |number, range| {
+        println!("Verifying #{} of #{}..#{}", number, range.start, range.end);
+    });
+    for inconsistency in &report.inconsistencies {
+        println!("{}", inconsistency);
+    }
+    println!(
+        "Verified #{}..#{}: {} inconsistencies found, {} repaired",
+        report.checked.start,
+        report.checked.end,
+        report.inconsistencies.len(),
+        report.repaired
+    );
+    if report.is_consistent() {
+        Ok(())
+    } else {
+        Err("The database is inconsistent".to_string())
+    }
+}
+
+fn migrate_db(matches: &ArgMatches<'_>, sub_matches: &ArgMatches<'_>) -> Result<(), String> {
+    let config = load_config(matches)?;
+    let from_config = config.client_config()?;
+    let to_config = ClientConfig {
+        db_backend: sub_matches.value_of("to").expect("to is required").parse()?,
+        ..config.client_config()?
+    };
+    let out = sub_matches.value_of("out").expect("out is required");
+    if Path::new(out).exists() {
+        return Err(format!("{} already exists", out))
+    }
+
+    let from = open_existing_db(&config.operating, &from_config)?;
+    let to = open_database(out, &to_config)?;
+    let copied = migrate_database(&*from, &*to, |col, copied| {
+        println!("Copied {} entries of the column {:?}", copied, col);
+    })
+    .map_err(|e| format!("Cannot migrate the database: {}", e))?;
+    println!(
+        "Copied {} entries from the {} database to the {} database at {}",
+        copied, from_config.db_backend, to_config.db_backend, out
+    );
+    Ok(())
+}
+
+fn check_scheme(matches: &ArgMatches<'_>) -> Result<(), String> {
+    let config = load_config(matches)?;
+    let filename = match &config.operating.chain {
+        Some(ChainType::Custom(filename)) => filename,
+        Some(chain) => {
+            println!("The {} scheme is bundled and needs no check", chain);
+            return Ok(())
+        }
+        None => return Err("chain is not specified".to_string()),
+    };
+    let file =
+        fs::File::open(filename).map_err(|e| format!("Could not load specification file at {}: {}", filename, e))?;
+    let coordinator = prepare_coordinator(&load_app_desc());
+
+    match Scheme::validate(file, &*coordinator) {
+        Ok(scheme) => {
+            println!("{} is valid. Its genesis state is {}", filename, scheme.state_root());
+            Ok(())
+        }
+        Err(errors) => {
+            for error in &errors {
+                println!("{}", error);
+            }
+            Err(format!("{} problems found in {}", errors.len(), filename))
+        }
+    }
+}
+
+fn export_fork(matches: &ArgMatches<'_>, sub_matches: &ArgMatches<'_>) -> Result<(), String> {
+    let block = sub_matches.value_of("block").unwrap();
+    let number: BlockNumber = block.parse().map_err(|_| format!("Invalid block: {}", block))?;
+    let out = Path::new(sub_matches.value_of("out").unwrap());
+
+    let config = load_config(matches)?;
+    let scheme = match &config.operating.chain {
+        Some(chain) => chain.scheme()?,
+        None => return Err("chain is not specified".to_string()),
+    };
+    let db = open_existing_db(&config.operating, &config.client_config()?)?;
+
+    let fork_point = export_fork_state(&scheme, db, number, out).map_err(|err| err.to_string())?;
+    let manifest = json!({
+        "blockHash": format!("0x{:x}", *fork_point.block_hash),
+        "blockNumber": fork_point.block_number,
+        "stateRoot": format!("0x{:x}", fork_point.state_root),
+    });
+    fs::write(out.join(FORK_MANIFEST), manifest.to_string()).map_err(|err| err.to_string())?;
+    println!("Exported the state {} of block #{} ({})", fork_point.state_root, number, fork_point.block_hash);
+    Ok(())
+}
+
+fn export_genesis(matches: &ArgMatches<'_>, sub_matches: &ArgMatches<'_>) -> Result<(), String> {
+    let block = sub_matches.value_of("block").unwrap();
+    let number: BlockNumber = block.parse().map_err(|_| format!("Invalid block: {}", block))?;
+    let translation = match sub_matches.value_of("validators") {
+        Some(path) => read_validator_translation(Path::new(path))?,
+        None => HashMap::new(),
+    };
+
+    let config = load_config(matches)?;
+    let template_path = match (sub_matches.value_of("scheme-template"), &config.operating.chain) {
+        (Some(path), _) => path.to_string(),
+        (None, Some(ChainType::Custom(filename))) => filename.clone(),
+        (None, Some(chain)) => return Err(format!("The {} scheme is bundled; specify scheme-template", chain)),
+        (None, None) => return Err("chain is not specified".to_string()),
+    };
+    let scheme = match &config.operating.chain {
+        Some(chain) => chain.scheme()?,
+        None => return Err("chain is not specified".to_string()),
+    };
+    let mut scheme_json = read_json(Path::new(&template_path))?;
+    let db = open_existing_db(&config.operating, &config.client_config()?)?;
+
+    let genesis = scheme.export_genesis(db, number, &translation).map_err(|err| err.to_string())?;
+    genesis.write_into(&mut scheme_json).map_err(|err| format!("{}: {}", template_path, err))?;
+
+    let scheme_out = sub_matches.value_of("scheme-out").unwrap();
+    let scheme_string = serde_json::to_string_pretty(&scheme_json).map_err(|err| err.to_string())?;
+    fs::write(scheme_out, &scheme_string).map_err(|err| err.to_string())?;
+    let forked = Scheme::load(scheme_string.as_bytes())?;
+    if forked.state_root() != genesis.state_root {
+        return Err(format!("{} doesn't carry the genesis state {}", scheme_out, genesis.state_root))
+    }
+    println!(
+        "Wrote the scheme with the state {} of block #{} ({} chunks) to {}",
+        genesis.state_root,
+        number,
+        genesis.chunks.len(),
+        scheme_out
+    );
+    Ok(())
+}
+
+fn import_fork(matches: &ArgMatches<'_>, sub_matches: &ArgMatches<'_>) -> Result<(), String> {
+    let from = Path::new(sub_matches.value_of("from").unwrap());
+    let fork_point = read_fork_manifest(&from.join(FORK_MANIFEST))?;
+    let translation = match sub_matches.value_of("validators") {
+        Some(path) => read_validator_translation(Path::new(path))?,
+        None => HashMap::new(),
+    };
+    let template_path = sub_matches.value_of("scheme-template").unwrap();
+    let mut scheme_json = read_json(Path::new(template_path))?;
+
+    let config = load_config(matches)?;
+    let db = open_existing_db(&config.operating, &config.client_config()?)?;
+    import_state(&db, &fork_point, from).map_err(|err| err.to_string())?;
+    println!("Restored the state {} of block #{}", fork_point.state_root, fork_point.block_number);
+    let genesis_root = translate_validators(&db, fork_point.state_root, &translation).map_err(|err| err.to_string())?;
+
+    let genesis = scheme_json
+        .get_mut("genesis")
+        .and_then(Value::as_object_mut)
+        .ok_or_else(|| format!("{} has no genesis", template_path))?;
+    if let Some(state_root) = genesis.get("stateRoot").and_then(Value::as_str) {
+        if parse_h256(state_root)? != genesis_root {
+            return Err(format!(
+                "The scheme expects the genesis state {} but the carried state is {}",
+                state_root, genesis_root
+            ))
+        }
+    }
+    genesis.insert("stateRoot".to_string(), json!(format!("0x{:x}", genesis_root)));
+    genesis.insert("parentHash".to_string(), json!(format!("0x{:x}", *fork_point.block_hash)));
+
+    let scheme_out = sub_matches.value_of("scheme-out").unwrap();
+    let scheme_string = serde_json::to_string_pretty(&scheme_json).map_err(|err| err.to_string())?;
+    fs::write(scheme_out, &scheme_string).map_err(|err| err.to_string())?;
+    let scheme = Scheme::load(scheme_string.as_bytes())?;
+    if scheme.state_root() != genesis_root {
+        return Err(format!("{} doesn't carry the genesis state {}", scheme_out, genesis_root))
+    }
+    println!("Wrote the scheme with the genesis state {} to {}", genesis_root, scheme_out);
+    Ok(())
+}
+
+fn account_new(matches: &ArgMatches<'_>) -> Result<(), String> {
+    let config = load_config(matches)?;
+    let password = read_new_password(&config.operating)?;
+    let ap = prepare_account_provider(&keys_path(&config.operating))?;
+    let public = ap.new_account_and_public(&password).map_err(|err| err.to_string())?;
+    println!("{}", json!({ "public": public }));
+    Ok(())
+}
+
+/// Creates the next consensus key of a validator and signs the change to it.
+/// The change takes effect at the end of the term in which the change_validator_key staking transaction is executed.
+fn rotate_validator_key(matches: &ArgMatches<'_>, sub_matches: &ArgMatches<'_>) -> Result<(), String> {
+    let config = load_config(matches)?;
+    let network_id: NetworkId =
+        sub_matches.value_of("network-id").unwrap().parse().map_err(|err| format!("Invalid network-id: {}", err))?;
+    let current = match sub_matches.value_of("current") {
+        Some(current) => current.parse::<PlatformAddress>().map_err(|err| format!("Invalid current: {}", err))?,
+        None => {
+            config.mining.engine_signer.ok_or_else(|| "Neither current nor engine-signer is specified".to_string())?
+        }
+    };
+    let password = read_new_password(&config.operating)?;
+    let ap = prepare_account_provider(&keys_path(&config.operating))?;
+
+    let next: KeyPair = Random.generate().expect("ed25519 context has generation capabilities; qed");
+    ap.insert_account(next.private().clone(), &password).map_err(|err| err.to_string())?;
+    let change = ValidatorKeyChange::new_signed(network_id, current.into_pubkey(), *next.public(), next.private());
+    println!("{}", serde_json::to_string_pretty(&change).map_err(|err| err.to_string())?);
+    Ok(())
+}
+
+/// Signs the network identity of the engine signer's node with the engine signer and the node key.
+/// The node proves the node key in the p2p handshake, so the identity only vouches for this node.
+fn network_identity(matches: &ArgMatches<'_>, sub_matches: &ArgMatches<'_>) -> Result<(), String> {
+    let config = load_config(matches)?;
+    let validator = config.mining.engine_signer.ok_or_else(|| "The engine signer is not specified".to_string())?;
+    let node_address: SocketAddr = sub_matches
+        .value_of("node-address")
+        .unwrap()
+        .parse()
+        .map_err(|err| format!("Invalid node-address: {}", err))?;
+    let node_key_pair = load_or_create_node_key(&node_key_path(&config.operating))?;
+    let password = read_password(&config.operating)?;
+    let ap = prepare_account_provider(&keys_path(&config.operating))?;
+    let account = ap.get_account(&validator.into_pubkey(), Some(&password)).map_err(|err| err.to_string())?;
+
+    let message = NetworkIdentity::message(&validator.into_pubkey(), node_key_pair.public(), &node_address);
+    let identity = NetworkIdentity {
+        validator: validator.into_pubkey(),
+        node_key: *node_key_pair.public(),
+        node_address,
+        signature: account.sign(&message).map_err(|err| err.to_string())?,
+        node_signature: sign(message.as_ref(), node_key_pair.private()),
+    };
+    println!("{}", serde_json::to_string_pretty(&identity).map_err(|err| err.to_string())?);
+    Ok(())
+}
+
+/// Reads the password of an existing key from the environment variable named by password-env or from the terminal.
+fn read_password(operating: &config::Operating) -> Result<Password, String> {
+    if let Some(name) = &operating.password_env {
+        return env::var(name)
+            .map(Password::from)
+            .map_err(|err| format!("Cannot read the password from ${}: {}", name, err))
+    }
+    let password = rpassword::prompt_password_stdout("Password: ").map_err(|err| err.to_string())?;
+    Ok(password.into())
+}
+
+/// Reads the password of a new key from the environment variable named by password-env or from the terminal.
+fn read_new_password(operating: &config::Operating) -> Result<Password, String> {
+    if let Some(name) = &operating.password_env {
+        return env::var(name)
+            .map(Password::from)
+            .map_err(|err| format!("Cannot read the password from ${}: {}", name, err))
+    }
+    let password = rpassword::prompt_password_stdout("Password: ").map_err(|err| err.to_string())?;
+    let confirmation = rpassword::prompt_password_stdout("Repeat the password: ").map_err(|err| err.to_string())?;
+    if password != confirmation {
+        return Err("The passwords do not match".to_string())
+    }
+    Ok(password.into())
+}
+
+fn read_json(path: &Path) -> Result<Value, String> {
+    let file = fs::File::open(path).map_err(|err| format!("Cannot open {}: {}", path.display(), err))?;
+    serde_json::from_reader(file).map_err(|err| format!("Cannot parse {}: {}", path.display(), err))
+}
+
+fn read_fork_manifest(path: &Path) -> Result<ForkPoint, String> {
+    let manifest = read_json(path)?;
+    let field = |name: &str| manifest.get(name).ok_or_else(|| format!("{} has no {}", path.display(), name));
+    Ok(ForkPoint {
+        block_hash: parse_h256(field("blockHash")?.as_str().unwrap_or_default())?.into(),
+        block_number: field("blockNumber")?
+            .as_u64()
+            .ok_or_else(|| format!("Invalid blockNumber in {}", path.display()))?,
+        state_root: parse_h256(field("stateRoot")?.as_str().unwrap_or_default())?,
+    })
+}
+
+/// Reads a JSON object from the public keys of the old validators to their keys on the new chain.
+fn read_validator_translation(path: &Path) -> Result<HashMap<Public, Public>, String> {
+    let translation = read_json(path)?;
+    let entries = translation.as_object().ok_or_else(|| format!("{} is not a JSON object", path.display()))?;
+    entries
+        .iter()
+        .map(|(old, new)| {
+            let new = new.as_str().ok_or_else(|| format!("Invalid public key for {}", old))?;
+            Ok((parse_public(old)?, parse_public(new)?))
+        })
+        .collect()
+}
+
+fn parse_h256(value: &str) -> Result<H256, String> {
+    H256::from_str(value.trim_start_matches("0x")).map_err(|_| format!("Invalid hash: {}", value))
+}
+
+fn parse_public(value: &str) -> Result<Public, String> {
+    Public::from_str(value.trim_start_matches("0x")).map_err(|_| format!("Invalid public key: {}", value))
+}
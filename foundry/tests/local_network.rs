@@ -0,0 +1,245 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Runs several nodes in one process and connects them over a `SimNetwork` of the test kit.
+//!
+//! Every node has its own coordinator, client and miner, and all of them are validators of a round-robin chain. The
+//! nodes author blocks only while the test waits on the network, so a test decides when the chain grows and can
+//! inject faults in between. Their slots follow the wall clock, so the waiting takes real time.
+
+use ccore::{BlockChainClient, Client, Scheme};
+use ckey::{Ed25519KeyPair, Ed25519Public as Public, Generator, KeyPairTrait, Random};
+use coordinator::{AppDesc, Coordinator, Transaction};
+use ctypes::{BlockHash, BlockId, BlockNumber};
+use kvdb::KeyValueDB;
+use std::fs;
+use std::sync::Arc;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use test_kit::{SimNetwork, TestNode};
+
+/// The real time between the rounds in which the nodes author blocks.
+const TICK: Duration = Duration::from_millis(100);
+
+struct Node {
+    key_pair: Ed25519KeyPair,
+    /// Outlives the running node, so a restarted node continues from the chain it had.
+    db: Arc<dyn KeyValueDB>,
+    running: Option<TestNode>,
+}
+
+pub struct LocalNetwork {
+    network: SimNetwork,
+    scheme: String,
+    app_desc: AppDesc,
+    nodes: Vec<Node>,
+}
+
+impl LocalNetwork {
+    /// Starts `size` validators that author blocks in turn and connects every pair of them.
+    pub fn start(size: usize) -> Result<Self, String> {
+        assert!(size > 0, "A network needs at least one node");
+        let key_pairs: Vec<Ed25519KeyPair> = (0..size).map(|_| Random.generate().unwrap()).collect();
+        let validators: Vec<Public> = key_pairs.iter().map(|key_pair| *key_pair.public()).collect();
+        let app_desc = AppDesc::from_str(&fs::read_to_string("./app-desc.yml").map_err(|err| err.to_string())?)
+            .map_err(|err| err.to_string())?;
+
+        let mut network = Self {
+            network: SimNetwork::new(size, 0),
+            scheme: round_robin_scheme(&validators),
+            app_desc,
+            nodes: key_pairs
+                .into_iter()
+                .map(|key_pair| Node {
+                    key_pair,
+                    db: TestNode::new_db(),
+                    running: None,
+                })
+                .collect(),
+        };
+        for index in 0..size {
+            network.start_node(index)?;
+        }
+        Ok(network)
+    }
+
+    pub fn is_running(&self, index: usize) -> bool {
+        self.nodes[index].running.is_some()
+    }
+
+    /// The client of the node. It panics if the node is stopped.
+    pub fn client(&self, index: usize) -> &Arc<Client> {
+        self.running(index).client()
+    }
+
+    pub fn best_block_number(&self, index: usize) -> BlockNumber {
+        self.running(index).best_block_number()
+    }
+
+    pub fn best_block_hash(&self, index: usize) -> BlockHash {
+        self.running(index).best_block_hash()
+    }
+
+    /// Gives the transactions to the mem pool of the node, from which they are relayed to the others.
+    pub fn inject_transactions(&self, index: usize, transactions: Vec<Transaction>) -> Result<(), String> {
+        let client = self.client(index);
+        for transaction in transactions {
+            client.queue_own_transaction(transaction).map_err(|err| err.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Lets every running node author a block if it's its turn, and delivers the messages that follow.
+    pub fn produce_blocks(&mut self) {
+        for node in self.running_nodes() {
+            node.mine(1);
+        }
+        self.network.run_for(Duration::from_secs(1));
+    }
+
+    /// Produces blocks until the condition holds. It returns false if the condition doesn't hold in time.
+    pub fn wait_until<F>(&mut self, timeout: Duration, condition: F) -> bool
+    where
+        F: Fn(&Self) -> bool, {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if condition(self) {
+                return true
+            }
+            if Instant::now() >= deadline {
+                return false
+            }
+            self.produce_blocks();
+            sleep(TICK);
+        }
+    }
+
+    /// Produces blocks until every running node has the block of the given number.
+    pub fn wait_for_height(&mut self, height: BlockNumber, timeout: Duration) -> bool {
+        self.wait_until(timeout, |network| network.running_nodes().all(|node| node.best_block_number() >= height))
+    }
+
+    /// Produces blocks until every running node has the same best block.
+    pub fn wait_for_convergence(&mut self, timeout: Duration) -> bool {
+        self.wait_until(timeout, |network| {
+            let mut hashes = network.running_nodes().map(TestNode::best_block_hash);
+            let first = hashes.next();
+            hashes.all(|hash| Some(hash) == first)
+        })
+    }
+
+    /// Panics if two running nodes have different blocks at a height both of them have reached.
+    pub fn assert_consistent(&self) {
+        let common_height = self.running_nodes().map(TestNode::best_block_number).min().unwrap_or(0);
+        for number in 0..=common_height {
+            let hashes: Vec<_> =
+                self.running_nodes().map(|node| node.client().block_hash(&BlockId::Number(number))).collect();
+            assert!(hashes.windows(2).all(|pair| pair[0] == pair[1]), "The nodes disagree on the block #{}", number);
+        }
+    }
+
+    /// Splits the network so that the nodes only talk to the nodes in the same group.
+    /// A node that is not in any group keeps talking to everyone.
+    pub fn partition(&mut self, groups: &[&[usize]]) {
+        self.network.partition(groups);
+    }
+
+    /// Removes all partitions and reconnects the nodes that were kept apart.
+    pub fn heal(&mut self) {
+        self.network.heal();
+    }
+
+    /// Stops the node, keeping its database for a restart.
+    pub fn stop(&mut self, index: usize) {
+        if let Some(node) = self.nodes[index].running.take() {
+            node.stop(&mut self.network);
+        }
+    }
+
+    /// Starts the stopped node from its database and connects it to the others again.
+    pub fn restart(&mut self, index: usize) -> Result<(), String> {
+        assert!(!self.is_running(index), "The node #{} is running", index);
+        self.start_node(index)
+    }
+
+    fn running(&self, index: usize) -> &TestNode {
+        self.nodes[index].running.as_ref().unwrap_or_else(|| panic!("The node #{} is stopped", index))
+    }
+
+    fn running_nodes(&self) -> impl Iterator<Item = &TestNode> {
+        self.nodes.iter().filter_map(|node| node.running.as_ref())
+    }
+
+    fn start_node(&mut self, index: usize) -> Result<(), String> {
+        let scheme = Scheme::load(self.scheme.as_bytes())?;
+        let coordinator = Arc::new(Coordinator::from_app_desc(&self.app_desc).map_err(|err| err.to_string())?);
+        let node = &self.nodes[index];
+        let running = TestNode::start(
+            &mut self.network,
+            index,
+            &scheme,
+            Arc::clone(&node.db),
+            coordinator,
+            Some(&node.key_pair),
+        )?;
+        self.nodes[index].running = Some(running);
+        Ok(())
+    }
+}
+
+fn round_robin_scheme(validators: &[Public]) -> String {
+    serde_json::json!({
+        "name": "MultiNode",
+        "engine": {
+            "roundRobin": {
+                "params": {
+                    "validators": validators,
+                    "blockPeriod": 1
+                }
+            }
+        },
+        "params": {
+            "maxExtraDataSize": "0x20",
+            "networkID": "tc",
+            "minPayCost": 10,
+            "minCreateShardCost": 10,
+            "minSetShardOwnersCost": 10,
+            "minSetShardUsersCost": 10,
+            "minCustomCost": 10,
+            "maxBodySize": 4194304,
+            "snapshotPeriod": 16384,
+            "termSeconds": 0,
+            "nominationExpiration": 100,
+            "custodyPeriod": 100,
+            "releasePeriod": 200,
+            "maxNumOfValidators": 100,
+            "minNumOfValidators": 1,
+            "delegationThreshold": 1,
+            "minDeposit": 1,
+            "maxCandidateMetadataSize": 500
+        },
+        "genesis": {
+            "seal": {
+                "generic": "0x0"
+            },
+            "author": "fjjh0000AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAtc0",
+            "timestamp": "0x00",
+            "parentHash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+            "extraData": "0x"
+        }
+    })
+    .to_string()
+}
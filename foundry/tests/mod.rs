@@ -15,6 +15,10 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 mod basic;
+#[cfg(test)]
+mod local_network;
+#[cfg(test)]
+mod multi_node;
 mod timestamp;
 
 use ccore::Client;
@@ -43,3 +47,23 @@ fn inject_hello_txes() {
 fn graphql() {
     super::run_node(&clap::ArgMatches::new(), Some("graphql")).unwrap()
 }
+
+#[test]
+fn blocks_propagate() {
+    multi_node::blocks_propagate()
+}
+
+#[test]
+fn transactions_are_relayed() {
+    multi_node::transactions_are_relayed()
+}
+
+#[test]
+fn partitioned_node_catches_up() {
+    multi_node::partitioned_node_catches_up()
+}
+
+#[test]
+fn restarted_node_catches_up() {
+    multi_node::restarted_node_catches_up()
+}
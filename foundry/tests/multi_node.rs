@@ -0,0 +1,78 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::local_network::LocalNetwork;
+use super::timestamp::tx_hello;
+use ccore::BlockChainClient;
+use ckey::{Ed25519KeyPair, Generator, KeyPairTrait, Random};
+use ctypes::BlockId;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+pub fn blocks_propagate() {
+    let mut network = LocalNetwork::start(3).unwrap();
+
+    assert!(network.wait_for_height(5, TIMEOUT), "The chain is not growing");
+    assert!(network.wait_for_convergence(TIMEOUT), "The nodes don't agree on the best block");
+    network.assert_consistent();
+}
+
+pub fn transactions_are_relayed() {
+    let mut network = LocalNetwork::start(3).unwrap();
+    let user: Ed25519KeyPair = Random.generate().unwrap();
+    let transactions: Vec<_> = (0..10).map(|seq| tx_hello(user.public(), user.private(), seq)).collect();
+    network.inject_transactions(2, transactions).unwrap();
+
+    let included = network.wait_until(TIMEOUT, |network| {
+        let client = network.client(0);
+        let best = network.best_block_number(0);
+        let count: usize =
+            (1..=best).map(|number| client.block_body(&BlockId::Number(number)).unwrap().transactions_count()).sum();
+        count == 10
+    });
+    assert!(included, "The transactions are not included in the chain");
+    network.assert_consistent();
+}
+
+pub fn partitioned_node_catches_up() {
+    let mut network = LocalNetwork::start(4).unwrap();
+    assert!(network.wait_for_height(2, TIMEOUT));
+
+    network.partition(&[&[0, 1, 2], &[3]]);
+    let target = network.best_block_number(0) + 5;
+    assert!(network.wait_until(TIMEOUT, |network| network.best_block_number(0) >= target));
+    network.assert_consistent();
+
+    network.heal();
+    assert!(network.wait_for_height(target, TIMEOUT), "The partitioned node doesn't catch up");
+    assert!(network.wait_for_convergence(TIMEOUT), "The minority chain is not abandoned");
+    network.assert_consistent();
+}
+
+pub fn restarted_node_catches_up() {
+    let mut network = LocalNetwork::start(3).unwrap();
+    assert!(network.wait_for_height(2, TIMEOUT));
+
+    network.stop(2);
+    let target = network.best_block_number(0) + 5;
+    assert!(network.wait_for_height(target, TIMEOUT), "The chain stops while a validator is down");
+
+    network.restart(2).unwrap();
+    assert!(network.wait_for_height(target, TIMEOUT), "The restarted node doesn't catch up");
+    assert!(network.wait_for_convergence(TIMEOUT));
+    network.assert_consistent();
+}
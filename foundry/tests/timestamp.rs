@@ -27,7 +27,7 @@ use std::sync::Arc;
 use std::thread::sleep;
 use std::time::Duration;
 
-fn tx_hello(public: &Public, private: &Private, seq: u64) -> Transaction {
+pub fn tx_hello(public: &Public, private: &Private, seq: u64) -> Transaction {
     let tx = TxHello;
     let tx = UserTransaction {
         seq,
@@ -83,7 +83,7 @@ pub fn inject_hello_txes(client: Arc<Client>) {
         seq += tx_per_step;
     }
 
-    let session = client.new_session(BlockId::Latest);
+    let session = client.new_session(BlockId::Latest).unwrap();
     let result = client.graphql_handlers().get("module-account").unwrap().execute(
         session,
         &format!("{{ account(public: \"{}\") {{ seq }} }}", hex::encode(user1.public().as_ref())),
@@ -0,0 +1,28 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use opentelemetry_otlp::Uninstall;
+use tracing_subscriber::layer::SubscriberExt;
+
+/// Installs the subscriber exporting the tracing spans to the OTLP collector at `endpoint`.
+/// The spans are flushed and the exporter is shut down when the returned guard is dropped.
+pub fn init_otlp(endpoint: &str) -> Result<Uninstall, String> {
+    let (tracer, uninstall) = opentelemetry_otlp::new_pipeline().with_endpoint(endpoint).install();
+    let subscriber = tracing_subscriber::registry().with(tracing_opentelemetry::layer().with_tracer(tracer));
+    tracing::subscriber::set_global_default(subscriber)
+        .map_err(|e| format!("Cannot install the OTLP exporter of the tracing spans: {}", e))?;
+    Ok(uninstall)
+}
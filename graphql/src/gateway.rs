@@ -0,0 +1,501 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Serves the queries over several modules at one endpoint.
+//!
+//! Each top-level field of a gateway query names a module, and its selection set is the query sent to the module:
+//!
+//! ```graphql
+//! query($public: String) {
+//!     account { account(public: $public) { seq } }
+//!     balance: token { token(public: $public) { amount } }
+//! }
+//! ```
+//!
+//! The modules are queried in one session, so that all of them read the state of the same block.
+//! The result of each module is put under the alias of its field, or under the module name if there is no alias.
+
+use crate::{ManageSession, ServerData};
+use async_graphql::parser::query::{
+    Definition, Directive, Document as ParsedDocument, FragmentDefinition, OperationDefinition, Selection,
+    SelectionSet, TypeCondition, VariableDefinition,
+};
+use async_graphql::parser::{parse_query, Positioned, Value as GraphQlValue};
+use coordinator::module::SessionId;
+use ctypes::BlockId;
+use serde_json::{json, Map, Value};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// The part of a gateway query answered by a module.
+#[derive(Debug, PartialEq)]
+pub struct ModuleQuery {
+    /// The key of the module's result in the response.
+    pub response_key: String,
+    pub module: String,
+    /// The query sent to the module, with the variable definitions and the fragments it uses.
+    pub query: String,
+}
+
+/// A query parsed by the gateway, which has one operation made of module fields.
+struct Document<'a> {
+    variables: Vec<&'a VariableDefinition>,
+    selection_set: &'a SelectionSet,
+    /// The fragment definitions in the order of the query.
+    fragments: Vec<(&'a str, &'a FragmentDefinition)>,
+}
+
+impl<'a> Document<'a> {
+    fn new(document: &'a ParsedDocument) -> Result<Self, String> {
+        let mut operation = None;
+        let mut fragments = Vec::new();
+        for definition in document.definitions() {
+            let (variables, selection_set) = match &definition.node {
+                Definition::Fragment(fragment) => {
+                    fragments.push((fragment.name.node.as_str(), &fragment.node));
+                    continue
+                }
+                Definition::Operation(operation) => match &operation.node {
+                    OperationDefinition::SelectionSet(selection_set) => (Vec::new(), &selection_set.node),
+                    OperationDefinition::Query(query) => {
+                        if !query.directives.is_empty() {
+                            return Err("A gateway query must have no directives".to_string())
+                        }
+                        let variables = query.variable_definitions.iter().map(|definition| &definition.node).collect();
+                        (variables, &query.selection_set.node)
+                    }
+                    OperationDefinition::Mutation(_) | OperationDefinition::Subscription(_) => {
+                        return Err("The gateway serves only queries".to_string())
+                    }
+                },
+            };
+            if operation.is_some() {
+                return Err("A gateway query must have only one operation".to_string())
+            }
+            operation = Some((variables, selection_set));
+        }
+        let (variables, selection_set) = operation.ok_or("A gateway query must have an operation")?;
+        Ok(Document {
+            variables,
+            selection_set,
+            fragments,
+        })
+    }
+
+    /// The module fields at the top level of the operation, as the response key, the module name and the selection
+    /// set sent to the module.
+    fn module_fields(&self) -> Result<Vec<(&'a str, &'a str, &'a SelectionSet)>, String> {
+        self.selection_set
+            .items
+            .iter()
+            .map(|selection| match &selection.node {
+                Selection::Field(field) => {
+                    let module = field.name.node.as_str();
+                    if !field.arguments.is_empty() || !field.directives.is_empty() {
+                        return Err(format!("The module field '{}' must have no arguments and no directives", module))
+                    }
+                    if field.selection_set.items.is_empty() {
+                        return Err(format!("The module field '{}' must have a selection set", module))
+                    }
+                    let response_key = field.alias.as_ref().map_or(module, |alias| alias.node.as_str());
+                    Ok((response_key, module, &field.selection_set.node))
+                }
+                Selection::FragmentSpread(_) | Selection::InlineFragment(_) => {
+                    Err("Fragments can't be spread at the top level of a gateway query".to_string())
+                }
+            })
+            .collect()
+    }
+
+    fn module_query(&self, selection_set: &'a SelectionSet) -> Result<String, String> {
+        let mut references = References::default();
+        references.visit_selection_set(selection_set);
+        let mut fragments: Vec<&str> = Vec::new();
+        while let Some(name) = references.fragments.pop() {
+            if fragments.contains(&name) {
+                continue
+            }
+            let (_, fragment) = self
+                .fragments
+                .iter()
+                .find(|(fragment, _)| *fragment == name)
+                .ok_or_else(|| format!("Unknown fragment '{}'", name))?;
+            references.visit_selection_set(&fragment.selection_set);
+            fragments.push(name);
+        }
+
+        let definitions: Vec<String> = self
+            .variables
+            .iter()
+            .filter(|definition| references.variables.contains(definition.name.node.as_str()))
+            .map(|definition| {
+                let mut text = format!("${}: {}", definition.name.node, definition.var_type.node);
+                if let Some(default_value) = &definition.default_value {
+                    text.push_str(&format!(" = {}", default_value.node));
+                }
+                text
+            })
+            .collect();
+        let mut query = "query".to_string();
+        if !definitions.is_empty() {
+            query.push_str(&format!("({})", definitions.join(", ")));
+        }
+        query.push(' ');
+        write_selection_set(&mut query, selection_set);
+        // Keeps the order of the definitions in the gateway query.
+        for (name, fragment) in self.fragments.iter().filter(|(name, _)| fragments.contains(name)) {
+            let TypeCondition::On(type_name) = &fragment.type_condition.node;
+            query.push_str(&format!("\nfragment {} on {}", name, type_name.node));
+            write_directives(&mut query, &fragment.directives);
+            query.push(' ');
+            write_selection_set(&mut query, &fragment.selection_set);
+        }
+        Ok(query)
+    }
+}
+
+/// The variables and the fragments a selection set refers to.
+#[derive(Default)]
+struct References<'a> {
+    variables: HashSet<&'a str>,
+    fragments: Vec<&'a str>,
+}
+
+impl<'a> References<'a> {
+    fn visit_selection_set(&mut self, selection_set: &'a SelectionSet) {
+        for selection in &selection_set.items {
+            match &selection.node {
+                Selection::Field(field) => {
+                    for (_, value) in &field.arguments {
+                        self.visit_value(value);
+                    }
+                    self.visit_directives(&field.directives);
+                    self.visit_selection_set(&field.selection_set);
+                }
+                Selection::FragmentSpread(spread) => {
+                    self.visit_directives(&spread.directives);
+                    self.fragments.push(spread.fragment_name.node.as_str());
+                }
+                Selection::InlineFragment(fragment) => {
+                    self.visit_directives(&fragment.directives);
+                    self.visit_selection_set(&fragment.selection_set);
+                }
+            }
+        }
+    }
+
+    fn visit_directives(&mut self, directives: &'a [Positioned<Directive>]) {
+        for directive in directives {
+            for (_, value) in &directive.arguments {
+                self.visit_value(value);
+            }
+        }
+    }
+
+    fn visit_value(&mut self, value: &'a GraphQlValue) {
+        match value {
+            GraphQlValue::Variable(name) => {
+                self.variables.insert(name.as_str());
+            }
+            GraphQlValue::List(items) => items.iter().for_each(|item| self.visit_value(item)),
+            GraphQlValue::Object(fields) => fields.values().for_each(|field| self.visit_value(field)),
+            _ => {}
+        }
+    }
+}
+
+fn write_arguments(out: &mut String, arguments: &[(Positioned<String>, Positioned<GraphQlValue>)]) {
+    if !arguments.is_empty() {
+        let arguments: Vec<String> =
+            arguments.iter().map(|(name, value)| format!("{}: {}", name.node, value.node)).collect();
+        out.push_str(&format!("({})", arguments.join(", ")));
+    }
+}
+
+fn write_directives(out: &mut String, directives: &[Positioned<Directive>]) {
+    for directive in directives {
+        out.push_str(&format!(" @{}", directive.name.node));
+        write_arguments(out, &directive.arguments);
+    }
+}
+
+/// Writes the selection set back in the query language.
+fn write_selection_set(out: &mut String, selection_set: &SelectionSet) {
+    out.push('{');
+    for selection in &selection_set.items {
+        out.push(' ');
+        match &selection.node {
+            Selection::Field(field) => {
+                if let Some(alias) = &field.alias {
+                    out.push_str(&format!("{}: ", alias.node));
+                }
+                out.push_str(&field.name.node);
+                write_arguments(out, &field.arguments);
+                write_directives(out, &field.directives);
+                if !field.selection_set.items.is_empty() {
+                    out.push(' ');
+                    write_selection_set(out, &field.selection_set);
+                }
+            }
+            Selection::FragmentSpread(spread) => {
+                out.push_str(&format!("...{}", spread.fragment_name.node));
+                write_directives(out, &spread.directives);
+            }
+            Selection::InlineFragment(fragment) => {
+                out.push_str("...");
+                if let Some(type_condition) = &fragment.type_condition {
+                    let TypeCondition::On(type_name) = &type_condition.node;
+                    out.push_str(&format!(" on {}", type_name.node));
+                }
+                write_directives(out, &fragment.directives);
+                out.push(' ');
+                write_selection_set(out, &fragment.selection_set);
+            }
+        }
+    }
+    out.push_str(" }");
+}
+
+/// Splits a gateway query into the queries of the modules it names.
+pub fn split_query(query: &str) -> Result<Vec<ModuleQuery>, String> {
+    let parsed = parse_query(query).map_err(|err| err.to_string())?;
+    let document = Document::new(&parsed)?;
+    let mut response_keys = HashSet::new();
+    document
+        .module_fields()?
+        .into_iter()
+        .map(|(response_key, module, selection_set)| {
+            if !response_keys.insert(response_key) {
+                return Err(format!("'{}' appears more than once. Give the modules different aliases.", response_key))
+            }
+            Ok(ModuleQuery {
+                response_key: response_key.to_string(),
+                module: module.to_string(),
+                query: document.module_query(selection_set)?,
+            })
+        })
+        .collect()
+}
+
+struct SessionGuard<'a> {
+    session_manager: &'a dyn ManageSession,
+    session_id: SessionId,
+}
+
+impl<'a> Drop for SessionGuard<'a> {
+    fn drop(&mut self) {
+        self.session_manager.end_session(self.session_id)
+    }
+}
+
+fn error_at(response_key: &str, message: &str) -> Value {
+    json!({
+        "message": message,
+        "path": [response_key],
+    })
+}
+
+/// Puts the data of a module's response under the response key, and the errors with the path from the response key.
+fn merge_response(data: &mut Map<String, Value>, errors: &mut Vec<Value>, response_key: &str, response: &str) {
+    let response: Value = match serde_json::from_str(response) {
+        Ok(response) => response,
+        Err(_) => {
+            data.insert(response_key.to_string(), Value::Null);
+            errors.push(error_at(response_key, "The module returned an invalid response"));
+            return
+        }
+    };
+    data.insert(response_key.to_string(), response.get("data").cloned().unwrap_or(Value::Null));
+    if let Some(Value::Array(module_errors)) = response.get("errors") {
+        for mut error in module_errors.iter().cloned() {
+            if let Some(error) = error.as_object_mut() {
+                let mut path = vec![Value::from(response_key)];
+                if let Some(Value::Array(module_path)) = error.get("path") {
+                    path.extend(module_path.iter().cloned());
+                }
+                error.insert("path".to_string(), Value::Array(path));
+            }
+            errors.push(error);
+        }
+    }
+}
+
+/// Executes a gateway query and returns the merged response of the modules.
+pub(crate) async fn execute(server_data: Arc<ServerData>, block: BlockId, query: &str, variables: &str) -> String {
+    let module_queries = match split_query(query) {
+        Ok(module_queries) => module_queries,
+        Err(message) => {
+            return json!({
+                "data": null,
+                "errors": [{ "message": message }],
+            })
+            .to_string()
+        }
+    };
+
+    let session_needed = module_queries.iter().any(|module_query| {
+        server_data.graphql_handlers.get(&module_query.module).map_or(false, |handler| handler.session_needed)
+    });
+    let session = if session_needed {
+        let session_id = match server_data.session_manager.new_session(block) {
+            Some(session_id) => session_id,
+            None => {
+                return json!({
+                    "data": null,
+                    "errors": [{ "message": format!("The state of the block is not available: {:?}", block) }],
+                })
+                .to_string()
+            }
+        };
+        Some(SessionGuard {
+            session_manager: server_data.session_manager.as_ref(),
+            session_id,
+        })
+    } else {
+        None
+    };
+    let session_id = session.as_ref().map_or(0, |session| session.session_id);
+
+    let mut data = Map::new();
+    let mut errors = Vec::new();
+    for module_query in module_queries {
+        let handler = match server_data.graphql_handlers.get(&module_query.module) {
+            Some(handler) => &handler.handler,
+            None => {
+                data.insert(module_query.response_key.clone(), Value::Null);
+                errors
+                    .push(error_at(&module_query.response_key, &format!("Module not found: {}", module_query.module)));
+                continue
+            }
+        };
+        let _permit = Arc::clone(&server_data.scheduler).acquire(module_query.module.clone()).await;
+        let response = handler.execute(session_id, &module_query.query, variables);
+        merge_response(&mut data, &mut errors, &module_query.response_key, &response);
+    }
+
+    let mut response = Map::new();
+    response.insert("data".to_string(), Value::Object(data));
+    if !errors.is_empty() {
+        response.insert("errors".to_string(), Value::Array(errors));
+    }
+    Value::Object(response).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module_query(response_key: &str, module: &str, query: &str) -> ModuleQuery {
+        ModuleQuery {
+            response_key: response_key.to_string(),
+            module: module.to_string(),
+            query: query.to_string(),
+        }
+    }
+
+    #[test]
+    fn split_by_module() {
+        let query = r#"{ account { account(public: "a") { seq } } balance: token { token(public: "a") { amount } } }"#;
+        assert_eq!(
+            Ok(vec![
+                module_query("account", "account", r#"query { account(public: "a") { seq } }"#),
+                module_query("balance", "token", r#"query { token(public: "a") { amount } }"#),
+            ]),
+            split_query(query)
+        );
+    }
+
+    #[test]
+    fn keep_only_the_variables_each_module_uses() {
+        let query = r#"query Both($public: String!, $id: Int = 3) {
+            account { account(public: $public) { seq } }
+            stamp { stamp(id: $id) { owner } }
+        }"#;
+        assert_eq!(
+            Ok(vec![
+                module_query("account", "account", "query($public: String!) { account(public: $public) { seq } }"),
+                module_query("stamp", "stamp", "query($id: Int = 3) { stamp(id: $id) { owner } }"),
+            ]),
+            split_query(query)
+        );
+    }
+
+    #[test]
+    fn include_the_fragments_each_module_uses() {
+        let query = r#"
+            fragment Seq on Account { seq }
+            fragment Full on Account { ...Seq public(format: $format) }
+            query($format: String) {
+                account { a: account(public: "a") { ...Full } b: account(public: "b") { ... on Account { seq } } }
+                token { token(public: "a") { amount } }
+            }
+        "#;
+        let module_queries = split_query(query).unwrap();
+        assert_eq!(
+            "query($format: String) { a: account(public: \"a\") { ...Full } b: account(public: \"b\") { ... on Account { seq } } }\n\
+             fragment Seq on Account { seq }\n\
+             fragment Full on Account { ...Seq public(format: $format) }",
+            module_queries[0].query
+        );
+        assert_eq!("query { token(public: \"a\") { amount } }", module_queries[1].query);
+    }
+
+    #[test]
+    fn keep_the_directives_and_their_variables() {
+        let query = r#"query($full: Boolean!) { account { account(public: "a") { seq public @include(if: $full) } } }"#;
+        assert_eq!(
+            Ok(vec![module_query(
+                "account",
+                "account",
+                r#"query($full: Boolean!) { account(public: "a") { seq public @include(if: $full) } }"#
+            )]),
+            split_query(query)
+        );
+    }
+
+    #[test]
+    fn reject_what_the_gateway_does_not_serve() {
+        assert!(split_query("mutation { account { seq } }").is_err());
+        assert!(split_query("{ account { seq } } { token { amount } }").is_err());
+        assert!(split_query("fragment F on Account { seq } { ...F }").is_err());
+        assert!(split_query(r#"{ account(public: "a") { seq } }"#).is_err());
+        assert!(split_query("{ account { seq } account { public } }").is_err());
+        assert!(split_query("{ account { ...Unknown } }").is_err());
+        assert!(split_query("{ account { seq }").is_err());
+        assert!(split_query(r#"{ account { account(public: "a) { seq } } }"#).is_err());
+    }
+
+    #[test]
+    fn merge_data_and_errors_under_the_response_key() {
+        let mut data = Map::new();
+        let mut errors = Vec::new();
+        merge_response(
+            &mut data,
+            &mut errors,
+            "balance",
+            r#"{"data":{"token":null},"errors":[{"message":"no","path":["token"]}]}"#,
+        );
+        merge_response(&mut data, &mut errors, "account", "not json");
+        assert_eq!(json!({"balance": {"token": null}, "account": null}), Value::Object(data));
+        assert_eq!(
+            vec![
+                json!({"message": "no", "path": ["balance", "token"]}),
+                json!({"message": "The module returned an invalid response", "path": ["account"]}),
+            ],
+            errors
+        );
+    }
+}
@@ -14,8 +14,10 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+mod gateway;
 mod graphiql;
 mod handler;
+mod scheduler;
 
 use actix_web::{
     dev::Server,
@@ -26,27 +28,34 @@ use actix_web::{
 };
 use coordinator::module::{HandleGraphQlRequest, SessionId};
 use futures::Future;
+pub use gateway::{split_query, ModuleQuery};
 use graphiql::graphiql_source;
 pub use handler::handle_gql_query;
+pub use scheduler::SchedulingPolicy;
+use scheduler::{Permit, Scheduler};
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::{pin::Pin, sync::Arc};
 
 pub trait ManageSession: Send + Sync {
-    fn new_session(&self, block: ctypes::BlockId) -> SessionId;
+    /// Opens a session on the state of `block`, or returns `None` if the state is not retained.
+    fn new_session(&self, block: ctypes::BlockId) -> Option<SessionId>;
     fn end_session(&self, session: SessionId);
 }
 
 pub struct GraphQlRequestHandler {
     pub handler: Arc<dyn HandleGraphQlRequest>,
     pub session_needed: bool,
+    pub scheduling: SchedulingPolicy,
 }
 
 pub struct ServerData {
     session_manager: Arc<dyn ManageSession>,
     /// Name to (session_needed, handler)
     graphql_handlers: HashMap<String, GraphQlRequestHandler>,
+    /// Shares the worker threads among the modules.
+    scheduler: Arc<Scheduler>,
 }
 
 impl ServerData {
@@ -54,9 +63,15 @@ impl ServerData {
         session_manager: Arc<dyn ManageSession>,
         graphql_handlers: HashMap<String, GraphQlRequestHandler>,
     ) -> Self {
+        // Requests are executed on the worker threads, whose number is the number of CPUs by default.
+        let scheduler = Scheduler::new(
+            num_cpus::get(),
+            graphql_handlers.iter().map(|(module, handler)| (module.clone(), handler.scheduling)),
+        );
         Self {
             session_manager,
             graphql_handlers,
+            scheduler: Arc::new(scheduler),
         }
     }
 }
@@ -67,6 +82,34 @@ struct GraphQlArgs {
     variables: Option<String>,
 }
 
+/// The `block` URL parameter choosing the state a query reads, the latest block by default.
+#[derive(Deserialize)]
+struct BlockArg {
+    block: Option<String>,
+}
+
+impl BlockArg {
+    fn block_id(&self) -> Result<ctypes::BlockId> {
+        match &self.block {
+            Some(block) => parse_block_id(block).map_err(ErrorBadRequest),
+            None => Ok(ctypes::BlockId::Latest),
+        }
+    }
+}
+
+/// Parses a block number, a `0x`-prefixed block hash, `latest` or `earliest`.
+pub fn parse_block_id(block: &str) -> std::result::Result<ctypes::BlockId, String> {
+    match block {
+        "latest" => Ok(ctypes::BlockId::Latest),
+        "earliest" => Ok(ctypes::BlockId::Earliest),
+        _ if block.starts_with("0x") => block[2..]
+            .parse::<primitives::H256>()
+            .map(|hash| ctypes::BlockId::Hash(hash.into()))
+            .map_err(|_| format!("Invalid block hash: {}", block)),
+        _ => block.parse().map(ctypes::BlockId::Number).map_err(|_| format!("Invalid block: {}", block)),
+    }
+}
+
 async fn handle_post(session: Session, args: web::Json<GraphQlArgs>) -> Result<HttpResponse> {
     let query = &args.query;
     let variables = args.variables.as_deref().unwrap_or("{}");
@@ -83,6 +126,35 @@ async fn handle_get(session: Session, args: web::Query<GraphQlArgs>) -> Result<H
     Ok(HttpResponse::Ok().content_type("application/json").body(graphql_response))
 }
 
+async fn handle_gateway_post(
+    server_data: web::Data<Arc<ServerData>>,
+    block: web::Query<BlockArg>,
+    args: web::Json<GraphQlArgs>,
+) -> Result<HttpResponse> {
+    let variables = args.variables.as_deref().unwrap_or("{}");
+    let block = block.block_id()?;
+
+    let graphql_response = gateway::execute(Arc::clone(&server_data), block, &args.query, variables).await;
+    Ok(HttpResponse::Ok().content_type("application/json").body(graphql_response))
+}
+
+async fn handle_gateway_get(
+    server_data: web::Data<Arc<ServerData>>,
+    block: web::Query<BlockArg>,
+    args: web::Query<GraphQlArgs>,
+) -> Result<HttpResponse> {
+    let variables = args.variables.as_deref().unwrap_or("{}");
+    let block = block.block_id()?;
+
+    let graphql_response = gateway::execute(Arc::clone(&server_data), block, &args.query, variables).await;
+    Ok(HttpResponse::Ok().content_type("application/json").body(graphql_response))
+}
+
+async fn handle_gateway_graphiql() -> Result<HttpResponse> {
+    let html = graphiql_source("/graphql");
+    Ok(HttpResponse::Ok().content_type("text/html; charset=utf-8").body(html))
+}
+
 async fn handle_graphiql(path: web::Path<String>) -> Result<HttpResponse> {
     let module_name = path.into_inner();
     let graphql_endpoint_url = format! {"/{}/graphql", module_name};
@@ -96,14 +168,21 @@ pub fn app_configure(config: &mut ServiceConfig, server_data: Arc<ServerData>) {
         .service(
             web::resource("/{module_name}/graphql").route(web::post().to(handle_post)).route(web::get().to(handle_get)),
         )
-        .service(web::resource("/{module_name}/__graphql").route(web::get().to(handle_graphiql)));
+        .service(web::resource("/{module_name}/__graphql").route(web::get().to(handle_graphiql)))
+        // The gateway answering the queries over several modules
+        .service(
+            web::resource("/graphql").route(web::post().to(handle_gateway_post)).route(web::get().to(handle_gateway_get)),
+        )
+        .service(web::resource("/__graphql").route(web::get().to(handle_gateway_graphiql)));
 }
 
 pub fn run_server(server_data: ServerData, addr: SocketAddr) -> Result<Server> {
     let server_data = Arc::new(server_data);
+    let workers = server_data.scheduler.capacity();
     let server = HttpServer::new(move || {
         App::new().configure(|config: &mut ServiceConfig| app_configure(config, Arc::clone(&server_data)))
-    });
+    })
+    .workers(workers);
     Ok(server.bind(addr)?.run())
 }
 
@@ -111,6 +190,7 @@ struct Session {
     pub session_id: SessionId,
     pub session_manager: Arc<dyn ManageSession>,
     pub handler: Arc<dyn HandleGraphQlRequest>,
+    _permit: Permit,
 }
 
 impl Drop for Session {
@@ -126,18 +206,24 @@ impl FromRequest for Session {
 
     fn from_request(req: &HttpRequest, _payload: &mut actix_http::Payload) -> Self::Future {
         let module_name = req.match_info().get("module_name").map(|string| string.to_owned());
+        let block = web::Query::<BlockArg>::from_query(req.query_string()).map_err(Error::from);
         let server_data = req.app_data::<web::Data<Arc<ServerData>>>().unwrap().clone();
         Box::pin(async move {
             let module_name = module_name.ok_or_else(|| ErrorBadRequest("module_name not found"))?;
+            let block = block?.block_id()?;
 
             if let Some(GraphQlRequestHandler {
                 session_needed,
                 handler,
+                ..
             }) = server_data.graphql_handlers.get(&module_name)
             {
+                let permit = Arc::clone(&server_data.scheduler).acquire(module_name).await;
                 let session_id = if *session_needed {
-                    let _height = ();
-                    server_data.session_manager.new_session(ctypes::BlockId::Latest)
+                    server_data
+                        .session_manager
+                        .new_session(block)
+                        .ok_or_else(|| ErrorNotFound(format!("The state of the block is not available: {:?}", block)))?
                 } else {
                     0
                 };
@@ -146,6 +232,7 @@ impl FromRequest for Session {
                     session_id,
                     session_manager: Arc::clone(&server_data.session_manager),
                     handler: handler.clone(),
+                    _permit: permit,
                 })
             } else {
                 Err(ErrorNotFound(format!("Module not found: {}", module_name)))
@@ -153,3 +240,22 @@ impl FromRequest for Session {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ctypes::BlockId;
+
+    #[test]
+    fn parse_block_ids() {
+        assert_eq!(Ok(BlockId::Latest), parse_block_id("latest"));
+        assert_eq!(Ok(BlockId::Earliest), parse_block_id("earliest"));
+        assert_eq!(Ok(BlockId::Number(42)), parse_block_id("42"));
+        assert_eq!(
+            Ok(BlockId::Hash(primitives::H256::repeat_byte(0xab).into())),
+            parse_block_id(&format!("0x{}", "ab".repeat(32)))
+        );
+        assert!(parse_block_id("0x1234").is_err());
+        assert!(parse_block_id("-1").is_err());
+    }
+}
@@ -0,0 +1,304 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use futures::channel::oneshot;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+/// How a module shares the threads serving GraphQL requests with other modules.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SchedulingPolicy {
+    /// The relative share of the threads the module gets when requests of several modules are waiting.
+    pub weight: u32,
+    /// The maximum number of requests of the module executed at the same time.
+    pub max_concurrency: usize,
+}
+
+impl Default for SchedulingPolicy {
+    fn default() -> Self {
+        SchedulingPolicy {
+            weight: 1,
+            max_concurrency: usize::MAX,
+        }
+    }
+}
+
+/// Admits GraphQL requests of modules with a smooth weighted round-robin.
+///
+/// At most `capacity` requests are executed at the same time. When a request finishes,
+/// the next one is taken from the modules having waiting requests and not reaching their
+/// maximum concurrency, in proportion to their weights.
+pub struct Scheduler {
+    capacity: usize,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    running: usize,
+    queues: BTreeMap<String, Queue>,
+}
+
+#[derive(Default)]
+struct Queue {
+    policy: SchedulingPolicy,
+    running: usize,
+    waiting: VecDeque<oneshot::Sender<()>>,
+    current_weight: i64,
+}
+
+impl Queue {
+    fn is_eligible(&self) -> bool {
+        !self.waiting.is_empty() && self.running < self.policy.max_concurrency
+    }
+
+    fn weight(&self) -> i64 {
+        self.policy.weight.max(1).into()
+    }
+}
+
+/// A slot for executing a request, given back to the scheduler when dropped.
+pub struct Permit {
+    scheduler: Arc<Scheduler>,
+    module: String,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.scheduler.release(&self.module)
+    }
+}
+
+/// A request waiting in the queue of its module.
+///
+/// If the request is dropped after it is admitted but before it takes the admission,
+/// e.g. when the connection is closed, the slot is given back on drop.
+/// An admission already taken is owned by a `Permit` instead.
+struct Waiting<'a> {
+    scheduler: &'a Scheduler,
+    module: &'a str,
+    admitted: oneshot::Receiver<()>,
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        // No admission is sent once the receiver is closed, so an admission is either here or never comes.
+        self.admitted.close();
+        if let Ok(Some(())) = self.admitted.try_recv() {
+            self.scheduler.release(self.module)
+        }
+    }
+}
+
+impl Scheduler {
+    pub fn new(capacity: usize, policies: impl IntoIterator<Item = (String, SchedulingPolicy)>) -> Self {
+        let queues = policies
+            .into_iter()
+            .map(|(module, policy)| {
+                (module, Queue {
+                    policy,
+                    ..Default::default()
+                })
+            })
+            .collect();
+        Scheduler {
+            capacity: capacity.max(1),
+            state: Mutex::new(State {
+                running: 0,
+                queues,
+            }),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Waits until a request of the module can be executed.
+    pub async fn acquire(self: Arc<Self>, module: String) -> Permit {
+        if let Err(admitted) = self.enter(&module) {
+            let mut waiting = Waiting {
+                scheduler: &self,
+                module: &module,
+                admitted,
+            };
+            (&mut waiting.admitted).await.expect("A waiting request is dropped only after being admitted");
+        }
+        Permit {
+            scheduler: self,
+            module,
+        }
+    }
+
+    /// Takes a slot right away if the module is allowed to, or puts the request in the module's queue.
+    fn enter(&self, module: &str) -> Result<(), oneshot::Receiver<()>> {
+        let mut state = self.state.lock().unwrap();
+        let State {
+            running,
+            queues,
+        } = &mut *state;
+        let queue = queues.entry(module.to_owned()).or_default();
+        if *running < self.capacity && queue.running < queue.policy.max_concurrency && queue.waiting.is_empty() {
+            *running += 1;
+            queue.running += 1;
+            return Ok(())
+        }
+        let (sender, receiver) = oneshot::channel();
+        queue.waiting.push_back(sender);
+        Err(receiver)
+    }
+
+    fn release(&self, module: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.running -= 1;
+        state.queues.get_mut(module).expect("A permit is given only for a known module").running -= 1;
+        state.admit(self.capacity);
+    }
+}
+
+impl State {
+    fn admit(&mut self, capacity: usize) {
+        while self.running < capacity {
+            let module = match self.pick() {
+                Some(module) => module,
+                None => break,
+            };
+            let queue = self.queues.get_mut(&module).unwrap();
+            let sender = queue.waiting.pop_front().unwrap();
+            // The request is gone if its receiver is dropped, so the slot goes to the next one.
+            if sender.send(()).is_ok() {
+                queue.running += 1;
+                self.running += 1;
+            }
+        }
+    }
+
+    /// Picks the module of the next request, with the smooth weighted round-robin among the eligible modules.
+    fn pick(&mut self) -> Option<String> {
+        let total: i64 = self.queues.values().filter(|queue| queue.is_eligible()).map(Queue::weight).sum();
+        let mut picked: Option<(&String, &mut Queue)> = None;
+        for (module, queue) in self.queues.iter_mut().filter(|(_, queue)| queue.is_eligible()) {
+            queue.current_weight += queue.weight();
+            if picked.as_ref().map_or(true, |(_, picked)| queue.current_weight > picked.current_weight) {
+                picked = Some((module, queue));
+            }
+        }
+        let (module, queue) = picked?;
+        queue.current_weight -= total;
+        Some(module.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scheduler(capacity: usize, policies: &[(&str, u32, usize)]) -> Arc<Scheduler> {
+        Arc::new(Scheduler::new(
+            capacity,
+            policies.iter().map(|(module, weight, max_concurrency)| {
+                ((*module).to_owned(), SchedulingPolicy {
+                    weight: *weight,
+                    max_concurrency: *max_concurrency,
+                })
+            }),
+        ))
+    }
+
+    fn permit(scheduler: &Arc<Scheduler>, module: &str) -> Permit {
+        Permit {
+            scheduler: Arc::clone(scheduler),
+            module: module.to_owned(),
+        }
+    }
+
+    fn running(scheduler: &Scheduler) -> usize {
+        scheduler.state.lock().unwrap().running
+    }
+
+    #[test]
+    fn waiting_requests_are_admitted_in_proportion_to_weights() {
+        let scheduler = scheduler(1, &[("a", 2, usize::MAX), ("b", 1, usize::MAX)]);
+        scheduler.enter("a").unwrap();
+        let mut permits = vec![permit(&scheduler, "a")];
+
+        let mut waiting: Vec<_> = ["a", "a", "a", "b", "b", "b"]
+            .iter()
+            .map(|module| (*module, scheduler.enter(module).unwrap_err()))
+            .collect();
+
+        let mut admitted = Vec::new();
+        while !waiting.is_empty() {
+            permits.clear();
+            let index = waiting.iter_mut().position(|(_, receiver)| receiver.try_recv().unwrap().is_some()).unwrap();
+            let (module, _) = waiting.remove(index);
+            admitted.push(module);
+            permits.push(permit(&scheduler, module));
+        }
+        assert_eq!(admitted, vec!["a", "b", "a", "a", "b", "b"]);
+    }
+
+    #[test]
+    fn module_does_not_exceed_its_max_concurrency() {
+        let scheduler = scheduler(4, &[("a", 1, 1), ("b", 1, usize::MAX)]);
+        scheduler.enter("a").unwrap();
+        let first = permit(&scheduler, "a");
+
+        let mut second = scheduler.enter("a").unwrap_err();
+        assert!(scheduler.enter("b").is_ok());
+        assert_eq!(second.try_recv().unwrap(), None);
+
+        drop(first);
+        assert_eq!(second.try_recv().unwrap(), Some(()));
+        assert_eq!(running(&scheduler), 2);
+    }
+
+    #[test]
+    fn cancelled_request_does_not_take_a_slot() {
+        let scheduler = scheduler(1, &[("a", 1, usize::MAX), ("b", 1, usize::MAX)]);
+        scheduler.enter("a").unwrap();
+        let current = permit(&scheduler, "a");
+
+        drop(scheduler.enter("a").unwrap_err());
+        let mut waiting = scheduler.enter("b").unwrap_err();
+
+        drop(current);
+        assert_eq!(waiting.try_recv().unwrap(), Some(()));
+        assert_eq!(running(&scheduler), 1);
+    }
+
+    #[test]
+    fn request_dropped_after_admission_gives_back_the_slot() {
+        let scheduler = scheduler(1, &[("a", 1, usize::MAX), ("b", 1, usize::MAX)]);
+        scheduler.enter("a").unwrap();
+        let current = permit(&scheduler, "a");
+
+        let dropped = Waiting {
+            scheduler: &scheduler,
+            module: "a",
+            admitted: scheduler.enter("a").unwrap_err(),
+        };
+        let mut waiting = scheduler.enter("b").unwrap_err();
+
+        drop(current);
+        assert_eq!(running(&scheduler), 1);
+        assert_eq!(waiting.try_recv().unwrap(), None);
+
+        drop(dropped);
+        assert_eq!(waiting.try_recv().unwrap(), Some(()));
+        assert_eq!(running(&scheduler), 1);
+    }
+}
@@ -26,10 +26,18 @@ use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::sync::Arc;
 
 fn graphql_handlers() -> HashMap<String, GraphQlRequestHandler> {
-    (vec![("module1".to_owned(), GraphQlRequestHandler {
-        session_needed: true,
-        handler: Arc::from(common::create_handler()),
-    })])
+    (vec![
+        ("module1".to_owned(), GraphQlRequestHandler {
+            session_needed: true,
+            handler: Arc::from(common::create_handler()),
+            scheduling: Default::default(),
+        }),
+        ("module2".to_owned(), GraphQlRequestHandler {
+            session_needed: true,
+            handler: Arc::from(common::create_handler()),
+            scheduling: Default::default(),
+        }),
+    ])
     .into_iter()
     .collect()
 }
@@ -135,3 +143,47 @@ async fn request_post_with_variables() {
     let response = std::str::from_utf8(&response_bytes).expect("GraphQL server must return utf8-encoded string");
     assert_eq!(response, expected);
 }
+
+#[actix_rt::test]
+async fn request_gateway() {
+    let port = 4005;
+    let _server = create_server(port);
+    let client = Client::new();
+    let query: HashMap<String, String> = vec![
+        (
+            "query".to_owned(),
+            r#"{module1{account(name: "John"){balance}} other: module2{account(name: "James"){balance}}}"#.to_owned(),
+        ),
+        ("variables".to_owned(), "{}".to_owned()),
+    ]
+    .into_iter()
+    .collect();
+
+    let request = client.get(&format!("http://localhost:{}/graphql", port)).query(&query).unwrap();
+    let response_bytes = request.send().await.unwrap().body().await.unwrap();
+    let response = std::str::from_utf8(&response_bytes).expect("GraphQL server must return utf8-encoded string");
+    assert_eq!(response, r#"{"data":{"module1":{"account":{"balance":10}},"other":{"account":{"balance":30}}}}"#);
+}
+
+#[actix_rt::test]
+async fn request_gateway_with_variables() {
+    let port = 4006;
+    let _server = create_server(port);
+    let client = Client::new();
+    let query: HashMap<String, String> = vec![
+        (
+            "query".to_owned(),
+            r#"query($name: String, $other: String){module1{account(name: $name){balance}} module2{account(name: $other){balance}}}"#
+                .to_owned(),
+        ),
+        ("variables".to_owned(), r#"{"name": "John", "other": "Matthew"}"#.to_owned()),
+    ]
+    .into_iter()
+    .collect();
+    let body = Body::Bytes(serde_json::to_vec(&query).unwrap().into());
+
+    let request = client.post(&format!("http://localhost:{}/graphql", port)).header("content-type", "application/json");
+    let response_bytes = request.send_body(body).await.unwrap().body().await.unwrap();
+    let response = std::str::from_utf8(&response_bytes).expect("GraphQL server must return utf8-encoded string");
+    assert_eq!(response, r#"{"data":{"module1":{"account":{"balance":10}},"module2":{"account":{"balance":20}}}}"#);
+}
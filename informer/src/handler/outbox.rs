@@ -0,0 +1,77 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::Params;
+use crossbeam::{Sender, TrySendError};
+use crossbeam_channel as crossbeam;
+use jsonrpc_core::futures::Future;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+/// The number of notifications that may wait for a slow subscriber. Newer notifications are dropped beyond it.
+const OUTBOX_CAPACITY: usize = 1024;
+/// A subscriber is given up after this many notifications in a row are dropped.
+const MAX_CONSECUTIVE_DROPS: usize = 4096;
+
+/// Delivers the notifications of a subscription on its own thread, so that a slow subscriber never blocks the
+/// informer service or the other subscribers.
+pub struct Outbox {
+    sender: Sender<Params>,
+    consecutive_drops: AtomicUsize,
+}
+
+impl Outbox {
+    pub fn new(sink: crate::Sink, is_subscribing: Arc<AtomicBool>) -> Self {
+        let (sender, receiver) = crossbeam::bounded(OUTBOX_CAPACITY);
+        thread::Builder::new()
+            .name("informer outbox".to_string())
+            .spawn(move || {
+                for params in receiver {
+                    // FIXME : We should use `.await` instead of wait. The standard Future is not supported by the current paritytech/jsonrpc crate.
+                    if sink.notify(params).wait().is_err() {
+                        cinfo!(INFORMER, "Subscription has ended, finishing.");
+                        is_subscribing.store(false, Ordering::SeqCst);
+                        break
+                    }
+                }
+            })
+            .expect("Spawning an informer outbox thread must succeed");
+        Self {
+            sender,
+            consecutive_drops: AtomicUsize::new(0),
+        }
+    }
+
+    /// Queues a notification, dropping it if the subscriber is behind.
+    /// Returns false once the subscriber has gone or has been behind for too long.
+    pub fn push(&self, params: Params) -> bool {
+        match self.sender.try_send(params) {
+            Ok(()) => {
+                self.consecutive_drops.store(0, Ordering::SeqCst);
+                true
+            }
+            Err(TrySendError::Full(_)) => {
+                let drops = self.consecutive_drops.fetch_add(1, Ordering::SeqCst) + 1;
+                if drops == 1 {
+                    cwarn!(INFORMER, "A subscriber is too slow, so the notifications are being dropped");
+                }
+                drops < MAX_CONSECUTIVE_DROPS
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        }
+    }
+}
@@ -21,6 +21,8 @@ use crate::{
 use crossbeam::Sender;
 use crossbeam_channel as crossbeam;
 use jsonrpc_core::{futures, BoxFuture};
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
 use std::io;
 use std::sync::Arc;
 
@@ -34,6 +36,41 @@ pub struct InformerConfig {
     pub interface: String,
     pub port: u16,
     pub max_connections: usize,
+    pub max_subscriptions_per_connection: usize,
+}
+
+/// Tracks the live subscriptions of each connection, which is told apart by its session.
+#[derive(Clone, Default)]
+struct SubscriptionCounts {
+    subscriptions: Arc<Mutex<HashMap<usize, HashSet<SubscriptionId>>>>,
+}
+
+impl SubscriptionCounts {
+    fn key(session: &Arc<Session>) -> usize {
+        Arc::as_ptr(session) as usize
+    }
+
+    /// Adds a new subscription unless the connection already has `limit` subscriptions.
+    fn try_add(&self, session: &Arc<Session>, id: SubscriptionId, limit: usize) -> bool {
+        let key = Self::key(session);
+        let mut subscriptions = self.subscriptions.lock();
+        if !subscriptions.contains_key(&key) {
+            let subscriptions = Arc::clone(&self.subscriptions);
+            session.on_drop(move || {
+                subscriptions.lock().remove(&key);
+            });
+        }
+        let owned = subscriptions.entry(key).or_default();
+        if owned.len() >= limit {
+            return false
+        }
+        owned.insert(id)
+    }
+
+    /// Removes the subscription if the connection has it, returning whether it had.
+    fn remove(&self, session: &Arc<Session>, id: &SubscriptionId) -> bool {
+        self.subscriptions.lock().get_mut(&Self::key(session)).map_or(false, |owned| owned.remove(id))
+    }
 }
 
 pub struct Handler {
@@ -64,12 +101,14 @@ impl Handler {
             },
         }
     }
-    pub fn event_subscription(&mut self, sender: Sender<Registration>) {
+    pub fn event_subscription(&mut self, sender: Sender<Registration>, max_subscriptions_per_connection: usize) {
         let register_sender = sender;
         let deregister_sender = register_sender.clone();
+        let register_counts = SubscriptionCounts::default();
+        let deregister_counts = register_counts.clone();
         self.handler.add_subscription(
             "register",
-            ("register", move |params: Params, _, subscriber: Subscriber| {
+            ("register", move |params: Params, session: Arc<Session>, subscriber: Subscriber| {
                 if params == Params::None {
                     subscriber
                         .reject(Error {
@@ -95,13 +134,32 @@ impl Handler {
                     }
                 };
                 let sub_id = Handler::next_id();
+                if !register_counts.try_add(&session, SubscriptionId::Number(sub_id), max_subscriptions_per_connection)
+                {
+                    subscriber
+                        .reject(Error {
+                            code: ErrorCode::InvalidRequest,
+                            message: format!(
+                                "Too many subscriptions. A connection can have at most {} subscriptions.",
+                                max_subscriptions_per_connection
+                            ),
+                            data: None,
+                        })
+                        .expect("Connection is alive");
+                    return
+                }
                 let sink = subscriber.assign_id(SubscriptionId::Number(sub_id)).expect("Connection is alive");
                 let mut subscription = Subscription::new(sink, SubscriptionId::Number(sub_id));
                 subscription.add_events(all_params);
                 let register = Registration::Register(subscription);
                 register_sender.send(register).expect("The subscription channel is not full and also it is connected");
             }),
-            ("deregister", move |id: SubscriptionId, _meta| -> BoxFuture<Value> {
+            ("deregister", move |id: SubscriptionId, session: Option<Arc<Session>>| -> BoxFuture<Value> {
+                // A connection can close only the subscriptions it made.
+                let owned = session.map_or(false, |session| deregister_counts.remove(&session, &id));
+                if !owned {
+                    return Box::new(futures::future::ok(Value::Bool(false)))
+                }
                 cinfo!(INFORMER, "Closing subscription");
                 let deregister = Registration::Deregister(id);
                 deregister_sender
@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use super::outbox::Outbox;
 use crate::{ColdEvents, EventTags, Events, Params, Sink, SubscriptionId};
 use jsonrpc_core::futures::Future;
 use std::sync::atomic::AtomicBool;
@@ -30,17 +31,20 @@ pub struct Subscription {
     pub subscription_id: SubscriptionId,
     pub interested_events: Vec<EventTags>,
     sink: Sink,
+    outbox: Arc<Outbox>,
     pub is_subscribing: Arc<AtomicBool>,
 }
 
 impl Subscription {
     pub fn new(sink: Sink, sub_id: SubscriptionId) -> Self {
+        let is_subscribing = Arc::new(AtomicBool::new(true));
         Self {
             status: ConnectionState::Connected,
             subscription_id: sub_id,
             interested_events: Vec::new(),
+            outbox: Arc::new(Outbox::new(sink.clone(), Arc::clone(&is_subscribing))),
             sink,
-            is_subscribing: Arc::new(AtomicBool::new(true)),
+            is_subscribing,
         }
     }
     pub fn add_events(&mut self, params: Vec<String>) {
@@ -63,6 +67,21 @@ impl Subscription {
                 cinfo!(INFORMER, "The event is successfully added to user's interested events");
                 self.interested_events.push(cold_event);
             }
+            "NewHeads" => {
+                let event = EventTags::NewHeads;
+                cinfo!(INFORMER, "The event is successfully added to user's interested events");
+                self.interested_events.push(event);
+            }
+            "PendingTransactions" => {
+                let event = EventTags::PendingTransactions(params.get(1).cloned());
+                cinfo!(INFORMER, "The event is successfully added to user's interested events");
+                self.interested_events.push(event);
+            }
+            "ModuleEvents" => {
+                let event = EventTags::ModuleEvents(params.get(1).cloned());
+                cinfo!(INFORMER, "The event is successfully added to user's interested events");
+                self.interested_events.push(event);
+            }
             _ => {
                 cinfo!(INFORMER, "invalid Event: the event is not supported");
             }
@@ -83,17 +102,12 @@ impl Subscription {
         }
     }
 
-    pub fn notify_client(&self, event: &Events) {
+    /// Returns false if the subscriber has gone or can't keep up with the notifications.
+    pub fn notify_client(&self, event: &Events) -> bool {
         let json_object = serde_json::to_value(event).expect("json format is not valid").as_object_mut().cloned();
         let params = Params::Map(json_object.expect("Event is serialized as object"));
         match self.status {
-            // FIXME : We should use `.await` instead of wait. The standard Future is not supported by the current paritytech/jsonrpc crate.
-            ConnectionState::Connected => match self.sink.notify(params).wait() {
-                Ok(_) => {}
-                Err(_) => {
-                    cinfo!(INFORMER, "Subscription has ended, finishing.");
-                }
-            },
+            ConnectionState::Connected => self.outbox.push(params),
         }
     }
 }
@@ -0,0 +1,101 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{Events, InformerEventSender};
+use ccore::{BlockChainClient, BlockChainTrait, ChainNotify, Client, EngineInfo};
+use coordinator::Transaction;
+use crpc::v1::{BlockHeader, TransactionEvent};
+use ctypes::{BlockHash, BlockId, BlockNumber, TxHash};
+use std::sync::Arc;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PendingTransaction {
+    hash: TxHash,
+    tx_type: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TransactionEvents {
+    block_number: BlockNumber,
+    block_hash: BlockHash,
+    transaction_hash: TxHash,
+    events: Vec<TransactionEvent>,
+}
+
+/// Turns the chain and mem pool notifications of the client into the events of the informer.
+pub struct ChainEventNotifier {
+    client: Arc<Client>,
+    sender: InformerEventSender,
+}
+
+impl ChainEventNotifier {
+    pub fn new(client: Arc<Client>, sender: InformerEventSender) -> Self {
+        Self {
+            client,
+            sender,
+        }
+    }
+
+    fn notify_enacted(&self, hash: BlockHash) {
+        let block = match self.client.block(&BlockId::Hash(hash)) {
+            Some(block) => block.decode(),
+            None => return,
+        };
+        let header = BlockHeader::from_core(&block.header, self.client.network_id());
+        self.sender.notify(Events::NewHead(serde_json::to_value(header).expect("A header is serialized as an object")));
+
+        for transaction in &block.transactions {
+            let transaction_hash = transaction.hash();
+            let events = self.client.events_by_tx_hash(&transaction_hash);
+            if events.is_empty() {
+                continue
+            }
+            let events = TransactionEvents {
+                block_number: block.header.number(),
+                block_hash: hash,
+                transaction_hash,
+                events: events.into_iter().map(Into::into).collect(),
+            };
+            self.sender.notify(Events::ModuleEvents(
+                transaction.tx_type().to_string(),
+                serde_json::to_value(events).expect("Events are serialized as an object"),
+            ));
+        }
+    }
+}
+
+impl ChainNotify for ChainEventNotifier {
+    fn new_blocks(&self, _imported: Vec<BlockHash>, _invalid: Vec<BlockHash>, enacted: Vec<BlockHash>) {
+        for hash in enacted {
+            self.notify_enacted(hash);
+        }
+    }
+
+    fn transactions_received(&self, transactions: Vec<Transaction>) {
+        for transaction in transactions {
+            let pending = PendingTransaction {
+                hash: transaction.hash(),
+                tx_type: transaction.tx_type().to_string(),
+            };
+            self.sender.notify(Events::PendingTransaction(
+                pending.tx_type.clone(),
+                serde_json::to_value(pending).expect("A transaction is serialized as an object"),
+            ));
+        }
+    }
+}
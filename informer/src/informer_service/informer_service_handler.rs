@@ -129,17 +129,32 @@ impl InformerService {
     }
 
     fn compare_event_types(tag: &EventTags, event: &Events) -> bool {
-        matches!((tag, event), (EventTags::PeerAdded, Events::PeerAdded(..)))
+        match (tag, event) {
+            (EventTags::PeerAdded, Events::PeerAdded(..)) => true,
+            (EventTags::NewHeads, Events::NewHead(..)) => true,
+            (EventTags::PendingTransactions(filter), Events::PendingTransaction(tx_type, ..))
+            | (EventTags::ModuleEvents(filter), Events::ModuleEvents(tx_type, ..)) => {
+                filter.as_ref().map_or(true, |filter| filter == tx_type)
+            }
+            _ => false,
+        }
     }
 
-    pub fn notify_client(&self, popup_event: Events) {
+    pub fn notify_client(&mut self, popup_event: Events) {
+        let mut ended = Vec::new();
         for subscription in &self.subscriptions {
-            for interested_event in subscription.interested_events.clone() {
-                if InformerService::compare_event_types(&interested_event, &popup_event) {
-                    subscription.notify_client(&popup_event);
-                }
+            let interested = subscription
+                .interested_events
+                .iter()
+                .any(|interested_event| InformerService::compare_event_types(interested_event, &popup_event));
+            if interested && !subscription.notify_client(&popup_event) {
+                ended.push(subscription.subscription_id.clone());
             }
         }
+        for sub_id in ended {
+            cwarn!(INFORMER, "A subscription is closed since its subscriber has gone or fallen behind");
+            self.remove_subscription(sub_id);
+        }
     }
 }
 
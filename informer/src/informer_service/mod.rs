@@ -14,8 +14,10 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+mod chain_events;
 mod informer_service_handler;
 mod rate_limiter;
 
+pub use chain_events::ChainEventNotifier;
 pub use informer_service_handler::{ColdEvents, InformerService};
 pub use rate_limiter::RateLimiter;
@@ -28,7 +28,7 @@ pub mod rpc_server;
 
 pub use cinfo_courier::{informer_notify, EventTags, Events, InformerEventSender};
 pub use handler::{InformerConfig, Registration, Subscription};
-pub use informer_service::{ColdEvents, InformerService, RateLimiter};
+pub use informer_service::{ChainEventNotifier, ColdEvents, InformerService, RateLimiter};
 pub use jsonrpc_core;
 pub use jsonrpc_core::{Compatibility, Error, ErrorCode, MetaIoHandler, Metadata, Middleware, Params, Value};
 pub use jsonrpc_pubsub::{PubSubHandler, PubSubMetadata, Session, Sink, Subscriber, SubscriptionId};
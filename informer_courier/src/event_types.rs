@@ -14,14 +14,27 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use serde_json::Value;
+
 #[derive(Clone)]
 pub enum EventTags {
     PeerAdded,
     ColdBlockGenerationNumerical(u64),
     ColdBlockGenerationHash(String),
+    NewHeads,
+    /// Only the transactions of the given type if there is one.
+    PendingTransactions(Option<String>),
+    /// Only the events emitted by the transactions of the given type if there is one.
+    ModuleEvents(Option<String>),
 }
 
 #[derive(Serialize)]
 pub enum Events {
     PeerAdded(String, String, usize),
+    /// The header of a block that became a part of the canonical chain.
+    NewHead(Value),
+    /// A transaction of the type that entered the mem pool.
+    PendingTransaction(String, Value),
+    /// The events emitted by a transaction of the type in a block that became a part of the canonical chain.
+    ModuleEvents(String, Value),
 }
@@ -0,0 +1,59 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::uint::Uint;
+
+/// The block numbers from which the consensus rules added after the chain started apply.
+/// A missing one applies from the genesis.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Activations {
+    /// The first height whose votes sign the time they are made at.
+    pub timestamped_votes: Option<Uint>,
+    /// The first block whose header may carry the external anchor set by a module.
+    pub external_anchor: Option<Uint>,
+    /// The first block whose header commits to the roots of the module substorages.
+    pub module_roots: Option<Uint>,
+    /// The first block whose body size counts only its transactions, which fill it up to the max body size.
+    pub exact_body_size: Option<Uint>,
+    /// The first block invalid when any of its transactions fails the checks of its owner module without the state.
+    pub transaction_checks: Option<Uint>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Activations;
+
+    #[test]
+    fn activations_deserialization() {
+        let s = r#"{
+            "timestampedVotes": 1000,
+            "externalAnchor": 2000,
+            "moduleRoots": 3000,
+            "exactBodySize": 4000,
+            "transactionChecks": 5000
+        }"#;
+        let deserialized: Activations = serde_json::from_str(s).unwrap();
+        assert_eq!(deserialized.timestamped_votes, Some(1000.into()));
+        assert_eq!(deserialized.external_anchor, Some(2000.into()));
+        assert_eq!(deserialized.module_roots, Some(3000.into()));
+        assert_eq!(deserialized.exact_body_size, Some(4000.into()));
+        assert_eq!(deserialized.transaction_checks, Some(5000.into()));
+
+        let deserialized: Activations = serde_json::from_str("{}").unwrap();
+        assert_eq!(deserialized.timestamped_votes, None);
+    }
+}
@@ -0,0 +1,55 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::hash::H256;
+use crate::uint::Uint;
+
+/// A block every node of the chain must have, pinned in the scheme.
+#[derive(Debug, PartialEq, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Checkpoint {
+    /// Block number.
+    pub number: Uint,
+    /// Block hash.
+    pub hash: H256,
+    /// The hash of the validator set the block commits to.
+    pub next_validator_set_hash: H256,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Checkpoint;
+    use crate::hash::H256;
+    use primitives::H256 as Core256;
+    use std::str::FromStr;
+
+    #[test]
+    fn checkpoint_deserialization() {
+        let s = r#"{
+            "number": 100000,
+            "hash": "0x3ab5e1f1ab7b4c4d8b2c5b3c7f49e2c24c2e8d6a7f4cde3f2a1b9c8d7e6f5a40",
+            "nextValidatorSetHash": "0x45b0cfc220ceec5b7c1c62c4d4193d38e4eba48e8815729ce75f9c0ab0e4c1c0"
+        }"#;
+        let deserialized: Checkpoint = serde_json::from_str(s).unwrap();
+        assert_eq!(deserialized, Checkpoint {
+            number: 100_000.into(),
+            hash: H256(Core256::from_str("3ab5e1f1ab7b4c4d8b2c5b3c7f49e2c24c2e8d6a7f4cde3f2a1b9c8d7e6f5a40").unwrap()),
+            next_validator_set_hash: H256(
+                Core256::from_str("45b0cfc220ceec5b7c1c62c4d4193d38e4eba48e8815729ce75f9c0ab0e4c1c0").unwrap()
+            ),
+        });
+    }
+}
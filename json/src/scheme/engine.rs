@@ -14,21 +14,40 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::Tendermint;
+use serde::de::{Deserialize, Deserializer, Error};
+use serde_json::{Map, Value};
 
 /// Engine deserialization.
-#[derive(Debug, PartialEq, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub enum Engine {
-    /// Null engine.
-    Null,
-    Solo,
-    Tendermint(Box<Tendermint>),
+///
+/// An engine is written as an object with a single key, the name of the engine.
+/// Its value holds the params, which are interpreted by the engine registered with the name.
+#[derive(Debug, PartialEq)]
+pub struct Engine {
+    pub name: String,
+    pub params: Value,
+}
+
+impl<'de> Deserialize<'de> for Engine {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>, {
+        let map = Map::<String, Value>::deserialize(deserializer)?;
+        if map.len() != 1 {
+            return Err(D::Error::custom(format!("An engine must have exactly one name, but {} are given", map.len())))
+        }
+        let (name, params) = map.into_iter().next().expect("The map has an entry");
+        Ok(Engine {
+            name,
+            params,
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::super::{InstantSeal, RoundRobin, Tendermint};
     use super::Engine;
+    use serde_json::Value;
 
     #[test]
     fn engine_deserialization() {
@@ -37,17 +56,29 @@ mod tests {
         }"#;
 
         let deserialized: Engine = serde_json::from_str(s).unwrap();
-        assert_eq!(deserialized, Engine::Null);
+        assert_eq!(deserialized, Engine {
+            name: "null".to_string(),
+            params: Value::Null,
+        });
 
         let s = r#"{
             "solo": null
         }"#;
 
         let deserialized: Engine = serde_json::from_str(s).unwrap();
-        match deserialized {
-            Engine::Solo => {} // solo is unit tested in its own file.
-            _ => panic!(),
-        };
+        assert_eq!(deserialized.name, "solo");
+
+        let s = r#"{
+            "instantSeal": {
+                "params": {
+                    "sealInterval": 1000
+                }
+            }
+        }"#;
+        let deserialized: Engine = serde_json::from_str(s).unwrap();
+        assert_eq!(deserialized.name, "instantSeal");
+        let instant_seal: InstantSeal = serde_json::from_value(deserialized.params).unwrap();
+        assert_eq!(instant_seal.params.seal_interval, Some(1000u64.into()));
 
         let s = r#"{
             "tendermint": {
@@ -57,9 +88,30 @@ mod tests {
             }
         }"#;
         let deserialized: Engine = serde_json::from_str(s).unwrap();
-        match deserialized {
-            Engine::Tendermint(_) => {} // Tendermint is unit tested in its own file.
-            _ => panic!(),
-        };
+        assert_eq!(deserialized.name, "tendermint");
+        // Tendermint is unit tested in its own file.
+        let _: Tendermint = serde_json::from_value(deserialized.params).unwrap();
+
+        let s = r#"{
+            "roundRobin": {
+                "params": {
+                    "validators": ["0x6f57729dbeeae75cb180984f0bf65c56f822135c47337d68a0aef41d7f932375"]
+                }
+            }
+        }"#;
+        let deserialized: Engine = serde_json::from_str(s).unwrap();
+        assert_eq!(deserialized.name, "roundRobin");
+        let round_robin: RoundRobin = serde_json::from_value(deserialized.params).unwrap();
+        assert_eq!(round_robin.params.validators.len(), 1);
+    }
+
+    #[test]
+    fn engine_with_two_names_is_rejected() {
+        let s = r#"{
+            "solo": null,
+            "null": null
+        }"#;
+
+        assert!(serde_json::from_str::<Engine>(s).is_err());
     }
 }
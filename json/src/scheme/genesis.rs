@@ -19,6 +19,7 @@ use crate::bytes::Bytes;
 use crate::hash::H256;
 use crate::uint::Uint;
 use ckey::PlatformAddress;
+use std::collections::BTreeMap;
 
 /// Scheme genesis.
 #[derive(Debug, PartialEq, Deserialize)]
@@ -40,12 +41,21 @@ pub struct Genesis {
     pub next_validator_set_hash: Option<H256>,
     /// Extra data.
     pub extra_data: Option<Bytes>,
+    /// The state the chain starts from instead of the one the modules initialize.
+    pub state: Option<GenesisState>,
+}
+
+/// A state exported from another chain.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct GenesisState {
+    /// The compressed snapshot chunks of the top-level trie and the module tries, by their roots.
+    pub chunks: BTreeMap<H256, Bytes>,
 }
 
 #[cfg(test)]
 mod tests {
     use super::super::{Seal, TendermintSeal};
-    use super::Genesis;
+    use super::{Genesis, GenesisState};
     use crate::bytes::Bytes;
     use crate::hash::{H256, H520};
     use ckey::PlatformAddress;
@@ -87,6 +97,21 @@ mod tests {
             state_root: Some(H256(Core256::from_str("d7f8974fb5ac78d9ac099b9ad5018bedc2ce0a72dad1827a1709da30580f0544").unwrap())),
             next_validator_set_hash: Some(H256(Core256::from_str("d7f8974fb5ac78d9ac099b9ad5018bedc2ce0a72dad1827a1709da30580f0544").unwrap())),
             extra_data: Some(Bytes::from_str("0x11bbe8db4e347b4e8c937c1c8370e4b5ed33adb3db69cbdb7a38e1e50b1b82fa").unwrap()),
+            state: None,
         });
     }
+
+    #[test]
+    fn genesis_state_serialization() {
+        let root = H256(Core256::from_str("d7f8974fb5ac78d9ac099b9ad5018bedc2ce0a72dad1827a1709da30580f0544").unwrap());
+        let state = GenesisState {
+            chunks: vec![(root, Bytes::new(vec![0x01, 0xab]))].into_iter().collect(),
+        };
+        let serialized = serde_json::to_string(&state).unwrap();
+        assert_eq!(
+            r#"{"chunks":{"0xd7f8974fb5ac78d9ac099b9ad5018bedc2ce0a72dad1827a1709da30580f0544":"0x01ab"}}"#,
+            serialized
+        );
+        assert_eq!(state, serde_json::from_str(&serialized).unwrap());
+    }
 }
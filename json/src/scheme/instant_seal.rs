@@ -0,0 +1,32 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::uint::Uint;
+
+/// Instant seal params deserialization.
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstantSealParams {
+    /// Seal a block at this interval in milliseconds, even when there are no transactions.
+    pub seal_interval: Option<Uint>,
+}
+
+/// Instant seal engine deserialization.
+#[derive(Debug, Default, PartialEq, Deserialize)]
+pub struct InstantSeal {
+    #[serde(default)]
+    pub params: InstantSealParams,
+}
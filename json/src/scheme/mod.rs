@@ -14,17 +14,25 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+mod activations;
+mod checkpoint;
 mod engine;
 mod genesis;
+mod instant_seal;
 mod params;
+mod round_robin;
 #[cfg_attr(feature = "cargo-clippy", allow(clippy::module_inception))]
 mod scheme;
 mod seal;
 mod tendermint;
 
+pub use self::activations::Activations;
+pub use self::checkpoint::Checkpoint;
 pub use self::engine::Engine;
-pub use self::genesis::Genesis;
+pub use self::genesis::{Genesis, GenesisState};
+pub use self::instant_seal::{InstantSeal, InstantSealParams};
 pub use self::params::Params;
+pub use self::round_robin::{RoundRobin, RoundRobinParams};
 pub use self::scheme::Scheme;
 pub use self::seal::{Seal, TendermintSeal};
 pub use self::tendermint::{StakeAccount, Tendermint, TendermintParams};
@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use super::Activations;
 use crate::uint::Uint;
 use ckey::NetworkId;
 
@@ -41,6 +42,11 @@ pub struct Params {
     pub delegation_threshold: Uint,
     pub min_deposit: Uint,
     pub max_candidate_metadata_size: Uint,
+    /// Whether the transactions in a block are ordered by keys seeded by the seal of the parent block
+    /// rather than by the proposer. The keys can be ground, so it doesn't prevent front-running.
+    pub seeded_tx_ordering: Option<bool>,
+    /// The block numbers from which the consensus rules added after the chain started apply.
+    pub activations: Option<Activations>,
 
     /// A monotonically increasing number to denote the consensus version.
     /// It is increased when we fork.
@@ -0,0 +1,34 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::uint::Uint;
+use ckey::Ed25519Public as Public;
+
+/// Round-robin params deserialization.
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoundRobinParams {
+    /// The validators in the order they author blocks.
+    pub validators: Vec<Public>,
+    /// The minimum time between two blocks in seconds.
+    pub block_period: Option<Uint>,
+}
+
+/// Round-robin engine deserialization.
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct RoundRobin {
+    pub params: RoundRobinParams,
+}
@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::{Engine, Genesis, Params};
+use super::{Checkpoint, Engine, Genesis, Params};
 use serde_json::Error;
 use std::io::Read;
 
@@ -32,6 +32,8 @@ pub struct Scheme {
     pub params: Params,
     /// Genesis header.
     pub genesis: Genesis,
+    /// Blocks the chain never reverts, from the oldest.
+    pub checkpoints: Option<Vec<Checkpoint>>,
 
     /// Boot nodes.
     pub nodes: Option<Vec<String>>,
@@ -100,11 +102,19 @@ mod tests {
                 "timestamp": "0x00",
                 "parentHash": "0x0000000000000000000000000000000000000000000000000000000000000000"
             },
+            "checkpoints": [
+                {
+                    "number": 100000,
+                    "hash": "0x3ab5e1f1ab7b4c4d8b2c5b3c7f49e2c24c2e8d6a7f4cde3f2a1b9c8d7e6f5a40",
+                    "nextValidatorSetHash": "0x45b0cfc220ceec5b7c1c62c4d4193d38e4eba48e8815729ce75f9c0ab0e4c1c0"
+                }
+            ],
             "nodes": [
             "enode://b1217cbaa440e35ed471157123fe468e19e8b5ad5bedb4b1fdbcbdab6fb2f5ed3e95dd9c24a22a79fdb2352204cea207df27d92bfd21bfd41545e8b16f637499@104.44.138.37:30303"
             ]
         }"#;
-        let _deserialized: Scheme = serde_json::from_str(s).unwrap();
+        let deserialized: Scheme = serde_json::from_str(s).unwrap();
+        assert_eq!(deserialized.checkpoints.map(|checkpoints| checkpoints.len()), Some(1));
         // TODO: validate all fields
     }
 }
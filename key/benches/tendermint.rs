@@ -20,7 +20,9 @@ extern crate codechain_crypto as ccrypto;
 extern crate codechain_key as ckey;
 extern crate test;
 
-use ckey::{sign, verify, Ed25519KeyPair, Generator, KeyPairTrait, Message, Random};
+use ckey::{
+    sign, verify, verify_all, Ed25519KeyPair, Ed25519Public, Generator, KeyPairTrait, Message, Random, Signature,
+};
 use test::Bencher;
 
 #[bench]
@@ -56,3 +58,55 @@ fn tendermint_max_step_time(b: &mut Bencher) {
         }
     });
 }
+
+#[bench]
+fn tendermint_max_step_time_with_parallel_verification(b: &mut Bencher) {
+    // Based on prevote/precommit state.
+    let num_validators = 30;
+
+    let key_pair_self: Ed25519KeyPair = Random.generate().unwrap();
+    let message_self = Message::random();
+    let mut signed = vec![];
+
+    for _ in 0..num_validators - 1 {
+        let key_pair: Ed25519KeyPair = Random.generate().unwrap();
+        let message = Message::random();
+        let signature = sign(message.as_ref(), key_pair.private());
+        signed.push((signature, message, *key_pair.public()));
+    }
+    b.iter(|| {
+        sign(message_self.as_ref(), key_pair_self.private());
+
+        assert!(verify_all(&signed).into_iter().all(|is_valid| is_valid));
+    });
+}
+
+/// The votes of a large validator set, each signed by its own validator.
+fn large_validator_set_votes() -> Vec<(Signature, Message, Ed25519Public)> {
+    let num_validators = 100;
+    (0..num_validators)
+        .map(|_| {
+            let key_pair: Ed25519KeyPair = Random.generate().unwrap();
+            let message = Message::random();
+            (sign(message.as_ref(), key_pair.private()), message, *key_pair.public())
+        })
+        .collect()
+}
+
+#[bench]
+fn tendermint_large_validator_set_votes(b: &mut Bencher) {
+    let votes = large_validator_set_votes();
+    b.iter(|| {
+        for (signature, message, public) in &votes {
+            assert!(verify(signature, message.as_ref(), public));
+        }
+    });
+}
+
+#[bench]
+fn tendermint_large_validator_set_votes_with_parallel_verification(b: &mut Bencher) {
+    let votes = large_validator_set_votes();
+    b.iter(|| {
+        assert!(verify_all(&votes).into_iter().all(|is_valid| is_valid));
+    });
+}
@@ -22,4 +22,4 @@ mod signature;
 pub use keypair::KeyPair;
 pub use private::Private;
 pub use public::Public;
-pub use signature::{sign, verify, Ed25519Signature as Signature, SIGNATUREBYTES as SIGNATURE_LENGTH};
+pub use signature::{sign, verify, verify_all, Ed25519Signature as Signature, SIGNATUREBYTES as SIGNATURE_LENGTH};
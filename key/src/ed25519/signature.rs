@@ -15,11 +15,13 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::{Private, Public};
+use crate::Message;
 use primitives::H512;
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 pub use sodiumoxide::crypto::sign::SIGNATUREBYTES;
 use sodiumoxide::crypto::sign::{sign_detached, verify_detached, Signature};
+use std::cmp;
 use std::fmt;
 
 pub fn sign(message: &[u8], private: &Private) -> Ed25519Signature {
@@ -33,6 +35,37 @@ pub fn verify(signature: &Ed25519Signature, message: &[u8], public: &Public) ->
     verify_detached(signature, message, public)
 }
 
+/// The most threads `verify_all` spreads the signatures over.
+const MAX_THREADS: usize = 4;
+/// The fewest signatures worth a thread of their own.
+const MIN_SIGNATURES_PER_THREAD: usize = 8;
+
+/// Verifies each signature on its own with `verify`, over a few threads when there are many of them.
+/// The results are in the order of the items.
+///
+/// This is not Ed25519 batch verification. Its random weights and cofactored equation can accept a signature that
+/// `verify` rejects, and whether a seal is valid must not depend on the node that checks it.
+pub fn verify_all(items: &[(Ed25519Signature, Message, Public)]) -> Vec<bool> {
+    let threads = cmp::min(MAX_THREADS, items.len() / MIN_SIGNATURES_PER_THREAD);
+    if threads <= 1 {
+        return items.iter().map(verify_item).collect()
+    }
+
+    let chunk_size = (items.len() + threads - 1) / threads;
+    crossbeam::thread::scope(|scope| {
+        let handles: Vec<_> = items
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move |_| chunk.iter().map(verify_item).collect::<Vec<_>>()))
+            .collect();
+        handles.into_iter().flat_map(|handle| handle.join().expect("Signatures are verified without panics")).collect()
+    })
+    .expect("Signatures are verified without panics")
+}
+
+fn verify_item((signature, message, public): &(Ed25519Signature, Message, Public)) -> bool {
+    verify(signature, message.as_ref(), public)
+}
+
 #[derive(Clone, Copy, Hash, PartialEq, Eq)]
 pub struct Ed25519Signature(Signature);
 
@@ -115,10 +148,52 @@ impl<'de> Deserialize<'de> for Ed25519Signature {
 mod tests {
     use rlp::rlp_encode_and_decode_test;
 
+    use super::super::KeyPair;
     use super::*;
+    use crate::{Generator, KeyPairTrait, Random};
 
     #[test]
     fn signature_rlp() {
         rlp_encode_and_decode_test!(Ed25519Signature::random());
     }
+
+    fn signed_items(count: usize) -> Vec<(Ed25519Signature, Message, Public)> {
+        (0..count)
+            .map(|_| {
+                let key_pair: KeyPair = Random.generate().unwrap();
+                let message = Message::random();
+                (sign(message.as_ref(), key_pair.private()), message, *key_pair.public())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn verify_all_of_valid_signatures() {
+        assert_eq!(Vec::<bool>::new(), verify_all(&[]));
+        assert_eq!(vec![true; 30], verify_all(&signed_items(30)));
+    }
+
+    #[test]
+    fn verify_all_finds_invalid_signatures() {
+        let mut items = signed_items(10);
+        items[3].1 = Message::random();
+        items[7].0 = Ed25519Signature::random();
+
+        let mut expected = vec![true; 10];
+        expected[3] = false;
+        expected[7] = false;
+        assert_eq!(expected, verify_all(&items));
+    }
+
+    #[test]
+    fn verify_all_over_threads_keeps_the_order() {
+        let mut items = signed_items(100);
+        let invalid = [0, 31, 32, 64, 99];
+        for &index in &invalid {
+            items[index].1 = Message::random();
+        }
+
+        let expected: Vec<_> = (0..100).map(|index| !invalid.contains(&index)).collect();
+        assert_eq!(expected, verify_all(&items));
+    }
 }
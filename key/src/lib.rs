@@ -28,7 +28,7 @@ mod random;
 mod x25519;
 
 pub use crate::ed25519::{
-    sign, verify, KeyPair as Ed25519KeyPair, Private as Ed25519Private, Public as Ed25519Public, Signature,
+    sign, verify, verify_all, KeyPair as Ed25519KeyPair, Private as Ed25519Private, Public as Ed25519Public, Signature,
     SIGNATURE_LENGTH,
 };
 pub use crate::error::Error;
@@ -15,7 +15,7 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
-use crate::account::{Aes128Ctr, Cipher, Kdf, Pbkdf2, Prf};
+use crate::account::{Aes128Ctr, Argon2, Cipher, Kdf, Pbkdf2, Prf};
 use crate::random::Random;
 use crate::{json, Error};
 use ckey::{Ed25519Private as Private, Ed25519Public as Public, Password};
@@ -23,6 +23,51 @@ use smallvec::SmallVec;
 use std::num::NonZeroU32;
 use std::str;
 
+/// How the key encrypting new data is derived from the password.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum KeyDerivation {
+    /// PBKDF2 with HMAC-SHA256 and the number of iterations.
+    Pbkdf2(u32),
+    /// Argon2id with the memory cost in KiB, the number of passes and the number of lanes.
+    Argon2id {
+        m: u32,
+        t: u32,
+        p: u32,
+    },
+}
+
+impl KeyDerivation {
+    /// Argon2id with 64 MiB of memory, which takes a fraction of a second to unlock a key.
+    pub const ARGON2ID: KeyDerivation = KeyDerivation::Argon2id {
+        m: 65536,
+        t: 3,
+        p: 4,
+    };
+
+    fn new_kdf(self) -> Kdf {
+        let salt: [u8; 32] = Random::random();
+        match self {
+            KeyDerivation::Pbkdf2(iterations) => Kdf::Pbkdf2(Pbkdf2 {
+                dklen: ccrypto::KEY_LENGTH as u32,
+                salt,
+                c: iterations,
+                prf: Prf::HmacSha256,
+            }),
+            KeyDerivation::Argon2id {
+                m,
+                t,
+                p,
+            } => Kdf::Argon2(Argon2 {
+                dklen: ccrypto::KEY_LENGTH as u32,
+                m,
+                t,
+                p,
+                salt,
+            }),
+        }
+    }
+}
+
 /// Encrypted data
 #[derive(Debug, PartialEq, Clone)]
 pub struct Crypto {
@@ -68,47 +113,37 @@ impl str::FromStr for Crypto {
 
 impl Crypto {
     /// Encrypt account secret
-    pub fn with_secret(secret: &Private, password: &Password, iterations: u32) -> Result<Self, ccrypto::Error> {
-        Crypto::with_plain(secret.as_ref(), password, iterations)
+    pub fn with_secret(secret: &Private, password: &Password, derivation: KeyDerivation) -> Result<Self, Error> {
+        Crypto::with_plain(secret.as_ref(), password, derivation)
     }
 
     /// Encrypt custom plain data
-    pub fn with_plain(plain: &[u8], password: &Password, iterations: u32) -> Result<Self, ccrypto::Error> {
-        let salt: [u8; 32] = Random::random();
+    pub fn with_plain(plain: &[u8], password: &Password, derivation: KeyDerivation) -> Result<Self, Error> {
+        let kdf = derivation.new_kdf();
         let iv: [u8; 16] = Random::random();
 
-        if let Some(non_zero_iterations) = NonZeroU32::new(iterations) {
-            // two parts of derived key
-            // DK = [ DK[0..15] DK[16..31] ] = [derived_left_bits, derived_right_bits]
-            let (derived_left_bits, derived_right_bits) =
-                ccrypto::derive_key_iterations(&password.as_crypto_password(), &salt, non_zero_iterations);
-
-            // preallocated (on-stack in case of `Secret`) buffer to hold cipher
-            // length = length(plain) as we are using CTR-approach
-            let plain_len = plain.len();
-            let mut ciphertext: SmallVec<[u8; 64]> = SmallVec::from_vec(vec![0; plain_len]);
-
-            // aes-128-ctr with initial vector of iv
-            ccrypto::aes::encrypt_128_ctr(&derived_left_bits, &iv, plain, &mut *ciphertext)?;
-
-            let mac = ccrypto::blake256(ccrypto::derive_mac(&derived_right_bits, &*ciphertext));
-
-            Ok(Crypto {
-                cipher: Cipher::Aes128Ctr(Aes128Ctr {
-                    iv,
-                }),
-                ciphertext: ciphertext.into_vec(),
-                kdf: Kdf::Pbkdf2(Pbkdf2 {
-                    dklen: ccrypto::KEY_LENGTH as u32,
-                    salt,
-                    c: iterations,
-                    prf: Prf::HmacSha256,
-                }),
-                mac: mac.into(),
-            })
-        } else {
-            Err(ccrypto::Error::ZeroIterations)
-        }
+        // two parts of derived key
+        // DK = [ DK[0..15] DK[16..31] ] = [derived_left_bits, derived_right_bits]
+        let (derived_left_bits, derived_right_bits) = derive_key(&kdf, password)?;
+
+        // preallocated (on-stack in case of `Secret`) buffer to hold cipher
+        // length = length(plain) as we are using CTR-approach
+        let plain_len = plain.len();
+        let mut ciphertext: SmallVec<[u8; 64]> = SmallVec::from_vec(vec![0; plain_len]);
+
+        // aes-128-ctr with initial vector of iv
+        ccrypto::aes::encrypt_128_ctr(&derived_left_bits, &iv, plain, &mut *ciphertext)?;
+
+        let mac = ccrypto::blake256(ccrypto::derive_mac(&derived_right_bits, &*ciphertext));
+
+        Ok(Crypto {
+            cipher: Cipher::Aes128Ctr(Aes128Ctr {
+                iv,
+            }),
+            ciphertext: ciphertext.into_vec(),
+            kdf,
+            mac: mac.into(),
+        })
     }
 
     /// Try to decrypt and convert result to account secret
@@ -134,16 +169,7 @@ impl Crypto {
     }
 
     fn do_decrypt(&self, password: &Password, expected_len: usize) -> Result<Vec<u8>, Error> {
-        let (derived_left_bits, derived_right_bits) = match self.kdf {
-            Kdf::Pbkdf2(ref params) => {
-                NonZeroU32::new(params.c).map_or(Err(ccrypto::Error::ZeroIterations), |non_zero_c| {
-                    Ok(ccrypto::derive_key_iterations(&password.as_crypto_password(), &params.salt, non_zero_c))
-                })?
-            }
-            Kdf::Scrypt(ref params) => {
-                ccrypto::scrypt::derive_key(&password.as_crypto_password(), &params.salt, params.n, params.p, params.r)?
-            }
-        };
+        let (derived_left_bits, derived_right_bits) = derive_key(&self.kdf, password)?;
 
         let mac = ccrypto::blake256(ccrypto::derive_mac(&derived_right_bits, &self.ciphertext));
 
@@ -166,16 +192,50 @@ impl Crypto {
     }
 }
 
+/// Derives the key to encrypt with and the key to authenticate the ciphertext with.
+fn derive_key(kdf: &Kdf, password: &Password) -> Result<(Vec<u8>, Vec<u8>), Error> {
+    match kdf {
+        Kdf::Pbkdf2(params) => NonZeroU32::new(params.c)
+            .map_or(Err(ccrypto::Error::ZeroIterations.into()), |non_zero_c| {
+                Ok(ccrypto::derive_key_iterations(&password.as_crypto_password(), &params.salt, non_zero_c))
+            }),
+        Kdf::Scrypt(params) => {
+            Ok(ccrypto::scrypt::derive_key(&password.as_crypto_password(), &params.salt, params.n, params.p, params.r)?)
+        }
+        Kdf::Argon2(params) => {
+            if params.dklen as usize != ccrypto::KEY_LENGTH {
+                return Err(Error::InvalidKeyFile(format!("Argon2 dklen must be {}", ccrypto::KEY_LENGTH)))
+            }
+            let config = argon2::Config {
+                variant: argon2::Variant::Argon2id,
+                version: argon2::Version::Version13,
+                mem_cost: params.m,
+                time_cost: params.t,
+                lanes: params.p,
+                thread_mode: argon2::ThreadMode::Sequential,
+                secret: &[],
+                ad: &[],
+                hash_length: params.dklen,
+            };
+            let mut derived_key = argon2::hash_raw(password.as_crypto_password().0.as_bytes(), &params.salt, &config)
+                .map_err(|err| Error::Custom(format!("Argon2: {}", err)))?;
+            let derived_right_bits = derived_key.split_off(ccrypto::KEY_LENGTH / 2);
+            Ok((derived_key, derived_right_bits))
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{Crypto, Error};
+    use super::{Crypto, Error, KeyDerivation};
     use ckey::{Ed25519KeyPair as KeyPair, Generator, KeyPairTrait, Random};
 
     #[test]
     fn crypto_with_secret_create() {
         let keypair: KeyPair = Random.generate().unwrap();
         let private_key = keypair.private();
-        let crypto = Crypto::with_secret(keypair.private(), &"this is sparta".into(), 10240).unwrap();
+        let crypto =
+            Crypto::with_secret(keypair.private(), &"this is sparta".into(), KeyDerivation::Pbkdf2(10240)).unwrap();
         let secret = crypto.secret(&"this is sparta".into()).unwrap();
         assert_eq!(private_key, &secret);
     }
@@ -183,14 +243,16 @@ mod tests {
     #[test]
     fn crypto_with_secret_invalid_password() {
         let keypair: KeyPair = Random.generate().unwrap();
-        let crypto = Crypto::with_secret(keypair.private(), &"this is sparta".into(), 10240).unwrap();
+        let crypto =
+            Crypto::with_secret(keypair.private(), &"this is sparta".into(), KeyDerivation::Pbkdf2(10240)).unwrap();
         assert_matches!(crypto.secret(&"this is sparta!".into()), Err(Error::InvalidPassword))
     }
 
     #[test]
     fn crypto_with_null_plain_data() {
         let original_data = b"";
-        let crypto = Crypto::with_plain(&original_data[..], &"this is sparta".into(), 10240).unwrap();
+        let crypto =
+            Crypto::with_plain(&original_data[..], &"this is sparta".into(), KeyDerivation::Pbkdf2(10240)).unwrap();
         let decrypted_data = crypto.decrypt(&"this is sparta".into()).unwrap();
         assert_eq!(original_data[..], *decrypted_data);
     }
@@ -198,7 +260,8 @@ mod tests {
     #[test]
     fn crypto_with_tiny_plain_data() {
         let original_data = b"{}";
-        let crypto = Crypto::with_plain(&original_data[..], &"this is sparta".into(), 10240).unwrap();
+        let crypto =
+            Crypto::with_plain(&original_data[..], &"this is sparta".into(), KeyDerivation::Pbkdf2(10240)).unwrap();
         let decrypted_data = crypto.decrypt(&"this is sparta".into()).unwrap();
         assert_eq!(original_data[..], *decrypted_data);
     }
@@ -206,8 +269,22 @@ mod tests {
     #[test]
     fn crypto_with_huge_plain_data() {
         let original_data: Vec<_> = (1..65536).map(|i| (i % 256) as u8).collect();
-        let crypto = Crypto::with_plain(&original_data, &"this is sparta".into(), 10240).unwrap();
+        let crypto =
+            Crypto::with_plain(&original_data, &"this is sparta".into(), KeyDerivation::Pbkdf2(10240)).unwrap();
         let decrypted_data = crypto.decrypt(&"this is sparta".into()).unwrap();
         assert_eq!(&original_data, &decrypted_data);
     }
+
+    #[test]
+    fn crypto_with_argon2id() {
+        let keypair: KeyPair = Random.generate().unwrap();
+        let derivation = KeyDerivation::Argon2id {
+            m: 64,
+            t: 1,
+            p: 1,
+        };
+        let crypto = Crypto::with_secret(keypair.private(), &"this is sparta".into(), derivation).unwrap();
+        assert_eq!(keypair.private(), &crypto.secret(&"this is sparta".into()).unwrap());
+        assert_matches!(crypto.secret(&"this is sparta!".into()), Err(Error::InvalidPassword))
+    }
 }
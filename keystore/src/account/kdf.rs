@@ -38,10 +38,24 @@ pub struct Scrypt {
     pub salt: [u8; 32],
 }
 
+/// The parameters of Argon2id.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Argon2 {
+    pub dklen: u32,
+    /// The memory cost in KiB.
+    pub m: u32,
+    /// The number of passes over the memory.
+    pub t: u32,
+    /// The number of lanes.
+    pub p: u32,
+    pub salt: [u8; 32],
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Kdf {
     Pbkdf2(Pbkdf2),
     Scrypt(Scrypt),
+    Argon2(Argon2),
 }
 
 impl From<json::Prf> for Prf {
@@ -106,11 +120,36 @@ impl From<Scrypt> for json::Scrypt {
     }
 }
 
+impl From<json::Argon2> for Argon2 {
+    fn from(json: json::Argon2) -> Self {
+        Argon2 {
+            dklen: json.dklen,
+            m: json.m,
+            t: json.t,
+            p: json.p,
+            salt: json.salt.into(),
+        }
+    }
+}
+
+impl From<Argon2> for json::Argon2 {
+    fn from(a: Argon2) -> Self {
+        Self {
+            dklen: a.dklen,
+            m: a.m,
+            t: a.t,
+            p: a.p,
+            salt: From::from(a.salt),
+        }
+    }
+}
+
 impl From<json::Kdf> for Kdf {
     fn from(json: json::Kdf) -> Self {
         match json {
             json::Kdf::Pbkdf2(params) => Kdf::Pbkdf2(From::from(params)),
             json::Kdf::Scrypt(params) => Kdf::Scrypt(From::from(params)),
+            json::Kdf::Argon2(params) => Kdf::Argon2(From::from(params)),
         }
     }
 }
@@ -120,6 +159,7 @@ impl From<Kdf> for json::Kdf {
         match kdf {
             Kdf::Pbkdf2(params) => json::Kdf::Pbkdf2(params.into()),
             Kdf::Scrypt(params) => json::Kdf::Scrypt(params.into()),
+            Kdf::Argon2(params) => json::Kdf::Argon2(params.into()),
         }
     }
 }
@@ -15,7 +15,7 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
-use super::crypto::Crypto;
+use super::crypto::{Crypto, KeyDerivation};
 use crate::account::Version;
 use crate::{json, DecryptedAccount, Error};
 use ckey::{Ed25519KeyPair as KeyPair, Ed25519Public as Public, KeyPairTrait, Password};
@@ -57,13 +57,13 @@ impl SafeAccount {
         keypair: &KeyPair,
         id: [u8; 16],
         password: &Password,
-        iterations: u32,
+        derivation: KeyDerivation,
         meta: String,
-    ) -> Result<Self, ccrypto::Error> {
+    ) -> Result<Self, Error> {
         Ok(SafeAccount {
             id,
             version: Version::V3,
-            crypto: Crypto::with_secret(keypair.private(), password, iterations)?,
+            crypto: Crypto::with_secret(keypair.private(), password, derivation)?,
             pubkey: *keypair.public(),
             filename: None,
             meta,
@@ -111,13 +111,13 @@ impl SafeAccount {
         &self,
         old_password: &Password,
         new_password: &Password,
-        iterations: u32,
+        derivation: KeyDerivation,
     ) -> Result<Self, Error> {
         let secret = self.crypto.secret(old_password)?;
         let result = SafeAccount {
             id: self.id,
             version: self.version,
-            crypto: Crypto::with_secret(&secret, new_password, iterations)?,
+            crypto: Crypto::with_secret(&secret, new_password, derivation)?,
             pubkey: self.pubkey,
             filename: self.filename.clone(),
             meta: self.meta.clone(),
@@ -151,8 +151,14 @@ mod tests {
         let keypair = Random.generate().unwrap();
         let password = &"hello world".into();
         let message = Message::default();
-        let account =
-            SafeAccount::create(&keypair, [0u8; 16], password, 10240, "{\"name\":\"Test\"}".to_string()).unwrap();
+        let account = SafeAccount::create(
+            &keypair,
+            [0u8; 16],
+            password,
+            KeyDerivation::Pbkdf2(10240),
+            "{\"name\":\"Test\"}".to_string(),
+        )
+        .unwrap();
         let signature = account.decrypt(password).unwrap().sign(&message).unwrap();
         assert!(verify(&signature, message.as_ref(), keypair.public()));
     }
@@ -162,7 +168,7 @@ mod tests {
         let keypair = Random.generate().unwrap();
         let first_password = &"hello world".into();
         let sec_password = &"this is sparta".into();
-        let i = 10240;
+        let i = KeyDerivation::Pbkdf2(10240);
         let account =
             SafeAccount::create(&keypair, [0u8; 16], first_password, i, "{\"name\":\"Test\"}".to_string()).unwrap();
         let new_account = account.change_password(first_password, sec_password, i).unwrap();
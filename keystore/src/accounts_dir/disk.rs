@@ -277,6 +277,7 @@ fn account_filename(account: &SafeAccount) -> String {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::account::KeyDerivation;
     use ckey::{Generator, Random};
     use std::env;
     use tempdir::TempDir;
@@ -292,7 +293,13 @@ mod test {
         let directory = RootDiskDirectory::create(dir.clone()).unwrap();
 
         // when
-        let account = SafeAccount::create(&keypair, [0u8; 16], password, 1024, "{\"name\":\"Test\"}".to_string());
+        let account = SafeAccount::create(
+            &keypair,
+            [0u8; 16],
+            password,
+            KeyDerivation::Pbkdf2(1024),
+            "{\"name\":\"Test\"}".to_string(),
+        );
         let res = directory.insert(account.unwrap());
 
         // then
@@ -314,8 +321,14 @@ mod test {
         let directory = RootDiskDirectory::create(dir.clone()).unwrap();
 
         // when
-        let account =
-            SafeAccount::create(&keypair, [0u8; 16], password, 1024, "{\"name\":\"Test\"}".to_string()).unwrap();
+        let account = SafeAccount::create(
+            &keypair,
+            [0u8; 16],
+            password,
+            KeyDerivation::Pbkdf2(1024),
+            "{\"name\":\"Test\"}".to_string(),
+        )
+        .unwrap();
         let filename = "test".to_string();
         let dedup = true;
 
@@ -347,7 +360,13 @@ mod test {
 
         let keypair = Random.generate().unwrap();
         let password = &"test pass".into();
-        let account = SafeAccount::create(&keypair, [0u8; 16], password, 1024, "{\"name\":\"Test\"}".to_string());
+        let account = SafeAccount::create(
+            &keypair,
+            [0u8; 16],
+            password,
+            KeyDerivation::Pbkdf2(1024),
+            "{\"name\":\"Test\"}".to_string(),
+        );
         directory.insert(account.unwrap()).expect("Account should be inserted ok");
 
         let new_hash = directory.files_hash().expect("New files hash should be calculated ok");
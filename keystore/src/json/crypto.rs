@@ -147,6 +147,7 @@ impl<'a> Visitor<'a> for CryptoVisitor {
         let kdf = match (kdf, kdfparams) {
             (Some(KdfSer::Pbkdf2), Some(KdfSerParams::Pbkdf2(params))) => Kdf::Pbkdf2(params),
             (Some(KdfSer::Scrypt), Some(KdfSerParams::Scrypt(params))) => Kdf::Scrypt(params),
+            (Some(KdfSer::Argon2id), Some(KdfSerParams::Argon2(params))) => Kdf::Argon2(params),
             (Some(_), Some(_)) => return Err(V::Error::custom("Invalid cipherparams")),
             (None, _) => return Err(V::Error::missing_field("kdf")),
             (Some(_), None) => return Err(V::Error::missing_field("kdfparams")),
@@ -189,6 +190,10 @@ impl Serialize for Crypto {
                 crypto.serialize_field("kdf", &KdfSer::Scrypt)?;
                 crypto.serialize_field("kdfparams", params)?;
             }
+            Kdf::Argon2(ref params) => {
+                crypto.serialize_field("kdf", &KdfSer::Argon2id)?;
+                crypto.serialize_field("kdfparams", params)?;
+            }
         }
 
         crypto.serialize_field("mac", &self.mac)?;
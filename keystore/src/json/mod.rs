@@ -30,6 +30,6 @@ pub use self::crypto::{CipherText, Crypto};
 pub use self::error::Error;
 pub use self::hash::{H128, H160, H256};
 pub use self::id::Uuid;
-pub use self::kdf::{Kdf, KdfSer, KdfSerParams, Pbkdf2, Prf, Scrypt};
+pub use self::kdf::{Argon2, Kdf, KdfSer, KdfSerParams, Pbkdf2, Prf, Scrypt};
 pub use self::key_file::{KeyFile, OpaqueKeyFile};
 pub use self::version::Version;
@@ -15,7 +15,7 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
-use crate::account::{DecryptedAccount, SafeAccount};
+use crate::account::{DecryptedAccount, KeyDerivation, SafeAccount};
 use crate::accounts_dir::KeyDirectory;
 use crate::json::{self, OpaqueKeyFile, Uuid};
 use crate::random::Random;
@@ -41,8 +41,13 @@ impl KeyStore {
 
     /// Open a new account store with given key directory backend and custom number of iterations.
     pub fn open_with_iterations(directory: Box<dyn KeyDirectory>, iterations: u32) -> Result<Self, Error> {
+        Self::open_with_derivation(directory, KeyDerivation::Pbkdf2(iterations))
+    }
+
+    /// Open a new account store with given key directory backend and the key derivation for new keys.
+    pub fn open_with_derivation(directory: Box<dyn KeyDirectory>, derivation: KeyDerivation) -> Result<Self, Error> {
         Ok(KeyStore {
-            store: KeyMultiStore::open_with_iterations(directory, iterations)?,
+            store: KeyMultiStore::open_with_derivation(directory, derivation)?,
         })
     }
 
@@ -154,7 +159,7 @@ impl SecretStore for KeyStore {
 /// Similar to `KeyStore` but may store many accounts (with different passwords) for the same `Public`
 pub struct KeyMultiStore {
     dir: Box<dyn KeyDirectory>,
-    iterations: u32,
+    derivation: KeyDerivation,
     // order lock: cache
     cache: RwLock<BTreeMap<Public, Vec<SafeAccount>>>,
     timestamp: Mutex<Timestamp>,
@@ -174,9 +179,14 @@ impl KeyMultiStore {
 
     /// Open new multi-accounts store with given key directory backend and custom number of iterations for new keys.
     pub fn open_with_iterations(directory: Box<dyn KeyDirectory>, iterations: u32) -> Result<Self, Error> {
+        Self::open_with_derivation(directory, KeyDerivation::Pbkdf2(iterations))
+    }
+
+    /// Open new multi-accounts store with given key directory backend and the key derivation for new keys.
+    pub fn open_with_derivation(directory: Box<dyn KeyDirectory>, derivation: KeyDerivation) -> Result<Self, Error> {
         let store = KeyMultiStore {
             dir: directory,
-            iterations,
+            derivation,
             cache: Default::default(),
             timestamp: Mutex::new(Timestamp {
                 dir_hash: None,
@@ -329,7 +339,7 @@ impl SimpleSecretStore for KeyMultiStore {
     fn insert_account(&self, secret: Private, password: &Password) -> Result<Public, Error> {
         let keypair = KeyPair::from_private(secret);
         let id: [u8; 16] = Random::random();
-        let account = SafeAccount::create(&keypair, id, password, self.iterations, "{}".to_string())?;
+        let account = SafeAccount::create(&keypair, id, password, self.derivation, "{}".to_string())?;
         self.import(account)
     }
 
@@ -364,7 +374,7 @@ impl SimpleSecretStore for KeyMultiStore {
     ) -> Result<(), Error> {
         let mut changed_any = false;
         for account in self.get_safe_accounts(account_ref)? {
-            let new_account = match account.change_password(old_password, new_password, self.iterations) {
+            let new_account = match account.change_password(old_password, new_password, self.derivation) {
                 Ok(new_account) => new_account,
                 Err(Error::InvalidPassword) => continue,
                 Err(err) => return Err(err),
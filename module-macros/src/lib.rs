@@ -13,3 +13,277 @@
 //
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+extern crate proc_macro;
+
+mod module;
+mod scalar;
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, Attribute, Data, DeriveInput, Error, Fields, Ident, ItemStruct, Lit, Meta, NestedMeta, Variant,
+};
+
+/// Generates a module around the service handler of the module.
+///
+/// It is put on a unit struct, which becomes the `UserModule` of the module holding an `Arc` of the handler:
+///
+/// ```ignore
+/// #[foundry_module(handler = ServiceHandler)]
+/// #[export_service("tx-owner", dyn TxOwner)]
+/// #[export_service("stateful", handler.get_stateful())]
+/// #[import_service("account-manager", account_manager)]
+/// pub struct Module;
+/// ```
+///
+/// - `handler` is the type of the handler, and `new = expr` creates it instead of `Handler::new()`.
+/// - `#[export_service(name, dyn Trait)]` exports the handler itself as the trait object.
+///   `#[export_service(name, expr)]` exports the value of `expr`,
+///   in which `handler` is the `&Arc` of the handler.
+/// - `#[import_service(name, field)]` puts the imported service in `field` of the handler,
+///   which must be a `RwLock<Box<dyn Trait>>`.
+///
+/// The services are exported with no constructor arguments,
+/// and the generated module panics on the names it doesn't know, as the hand-written modules do.
+#[proc_macro_attribute]
+pub fn foundry_module(args: proc_macro::TokenStream, item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let args = parse_macro_input!(args as module::ModuleArgs);
+    let item = parse_macro_input!(item as ItemStruct);
+    match module::expand(args, item) {
+        Ok(expanded) => expanded.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Derives an `async_graphql` scalar for a newtype, written as a hex string of the bytes of the inner value.
+///
+/// `#[hex_scalar(from = "function")]` names a function from `&[u8]` to `Option` of the inner type,
+/// and `#[hex_scalar(error = "message")]` replaces the error for an invalid input.
+#[proc_macro_derive(HexScalar, attributes(hex_scalar))]
+pub fn derive_hex_scalar(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match scalar::expand(input) {
+        Ok(expanded) => expanded.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Derives typed action handling for an `enum` of transaction actions.
+///
+/// For `enum Action` it generates
+/// - `Action::action_name()`, the snake_case name of the variant,
+/// - `Action::fee()`, the fee given by `#[action(fee = N)]` on the variant (0 if omitted),
+/// - `Action::decode()`, which decodes an action from CBOR,
+/// - `trait ActionHandler` with one method per variant, taking the variant's fields,
+/// - `Action::dispatch()`, which calls the handler method of the variant.
+///
+/// With `#[action(graphql)]` on the enum, an `async_graphql` input object named
+/// `Action<Variant>Input` is also generated for each variant with named fields,
+/// together with a `From` conversion into the action.
+/// The field types must then be GraphQL input types.
+#[proc_macro_derive(ActionEnum, attributes(action))]
+pub fn derive_action_enum(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(input) {
+        Ok(expanded) => expanded.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+struct ActionVariant<'a> {
+    variant: &'a Variant,
+    method: Ident,
+    fee: u64,
+    bindings: Vec<Ident>,
+}
+
+fn expand(input: DeriveInput) -> Result<TokenStream, Error> {
+    let data = match &input.data {
+        Data::Enum(data) => data,
+        _ => return Err(Error::new_spanned(&input.ident, "ActionEnum can be derived only for enums")),
+    };
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(&input.generics, "ActionEnum does not support generic enums"))
+    }
+
+    let name = &input.ident;
+    let vis = &input.vis;
+    let handler = format_ident!("{}Handler", name);
+    let graphql = parse_enum_attributes(&input.attrs)?;
+
+    let variants = data
+        .variants
+        .iter()
+        .map(|variant| {
+            let bindings = match &variant.fields {
+                Fields::Named(fields) => fields.named.iter().map(|field| field.ident.clone().unwrap()).collect(),
+                Fields::Unnamed(fields) => (0..fields.unnamed.len()).map(|i| format_ident!("arg{}", i)).collect(),
+                Fields::Unit => Vec::new(),
+            };
+            Ok(ActionVariant {
+                variant,
+                method: Ident::new(&to_snake_case(&variant.ident.to_string()), variant.ident.span()),
+                fee: parse_variant_fee(&variant.attrs)?,
+                bindings,
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let name_arms = variants.iter().map(|v| {
+        let pattern = wildcard_pattern(name, v.variant);
+        let action_name = v.method.to_string();
+        quote! { #pattern => #action_name }
+    });
+    let fee_arms = variants.iter().map(|v| {
+        let pattern = wildcard_pattern(name, v.variant);
+        let fee = v.fee;
+        quote! { #pattern => #fee }
+    });
+    let dispatch_arms = variants.iter().map(|v| {
+        let ident = &v.variant.ident;
+        let method = &v.method;
+        let bindings = &v.bindings;
+        let pattern = match &v.variant.fields {
+            Fields::Named(_) => quote! { #name::#ident { #(#bindings),* } },
+            Fields::Unnamed(_) => quote! { #name::#ident ( #(#bindings),* ) },
+            Fields::Unit => quote! { #name::#ident },
+        };
+        quote! { #pattern => handler.#method(context, #(#bindings),*) }
+    });
+    let handler_methods = variants.iter().map(|v| {
+        let method = &v.method;
+        let params = v.bindings.iter().zip(v.variant.fields.iter()).map(|(binding, field)| {
+            let ty = &field.ty;
+            quote! { #binding: #ty }
+        });
+        quote! { fn #method(&self, context: Self::Context, #(#params),*) -> Self::Output; }
+    });
+    let graphql_inputs = if graphql {
+        variants.iter().filter_map(|v| graphql_input(name, vis, v)).collect()
+    } else {
+        Vec::new()
+    };
+
+    Ok(quote! {
+        impl #name {
+            pub fn action_name(&self) -> &'static str {
+                match self {
+                    #(#name_arms,)*
+                }
+            }
+
+            pub fn fee(&self) -> u64 {
+                match self {
+                    #(#fee_arms,)*
+                }
+            }
+
+            pub fn decode(bytes: &[u8]) -> Result<Self, ::serde_cbor::Error> {
+                ::serde_cbor::from_slice(bytes)
+            }
+
+            pub fn dispatch<H: #handler + ?Sized>(self, handler: &H, context: H::Context) -> H::Output {
+                match self {
+                    #(#dispatch_arms,)*
+                }
+            }
+        }
+
+        #vis trait #handler {
+            type Context;
+            type Output;
+
+            #(#handler_methods)*
+        }
+
+        #(#graphql_inputs)*
+    })
+}
+
+fn graphql_input(name: &Ident, vis: &syn::Visibility, v: &ActionVariant) -> Option<TokenStream> {
+    let fields = match &v.variant.fields {
+        Fields::Named(fields) => fields,
+        _ => return None,
+    };
+    let ident = &v.variant.ident;
+    let input = format_ident!("{}{}Input", name, ident);
+    let bindings = &v.bindings;
+    let types = fields.named.iter().map(|field| &field.ty);
+    Some(quote! {
+        #[::async_graphql::InputObject]
+        #vis struct #input {
+            #(pub #bindings: #types,)*
+        }
+
+        impl From<#input> for #name {
+            fn from(input: #input) -> Self {
+                #name::#ident {
+                    #(#bindings: input.#bindings,)*
+                }
+            }
+        }
+    })
+}
+
+fn wildcard_pattern(name: &Ident, variant: &Variant) -> TokenStream {
+    let ident = &variant.ident;
+    match &variant.fields {
+        Fields::Named(_) => quote! { #name::#ident { .. } },
+        Fields::Unnamed(_) => quote! { #name::#ident ( .. ) },
+        Fields::Unit => quote! { #name::#ident },
+    }
+}
+
+fn action_attributes(attrs: &[Attribute]) -> Result<Vec<NestedMeta>, Error> {
+    let mut nested = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("action")) {
+        match attr.parse_meta()? {
+            Meta::List(list) => nested.extend(list.nested.into_iter()),
+            meta => return Err(Error::new_spanned(meta, "expected #[action(...)]")),
+        }
+    }
+    Ok(nested)
+}
+
+fn parse_enum_attributes(attrs: &[Attribute]) -> Result<bool, Error> {
+    let mut graphql = false;
+    for meta in action_attributes(attrs)? {
+        match meta {
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("graphql") => graphql = true,
+            meta => return Err(Error::new_spanned(meta, "unknown action attribute for an enum")),
+        }
+    }
+    Ok(graphql)
+}
+
+fn parse_variant_fee(attrs: &[Attribute]) -> Result<u64, Error> {
+    let mut fee = 0;
+    for meta in action_attributes(attrs)? {
+        match meta {
+            NestedMeta::Meta(Meta::NameValue(name_value)) if name_value.path.is_ident("fee") => {
+                fee = match &name_value.lit {
+                    Lit::Int(lit) => lit.base10_parse()?,
+                    lit => return Err(Error::new_spanned(lit, "the fee must be an integer")),
+                }
+            }
+            meta => return Err(Error::new_spanned(meta, "unknown action attribute for a variant")),
+        }
+    }
+    Ok(fee)
+}
+
+fn to_snake_case(ident: &str) -> String {
+    let mut snake = String::with_capacity(ident.len() + 4);
+    for (i, c) in ident.chars().enumerate() {
+        if c.is_uppercase() {
+            if i != 0 {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
@@ -0,0 +1,192 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::{Attribute, Error, Expr, Fields, Ident, ItemStruct, LitStr, Token, Type};
+
+/// The arguments of `#[foundry_module(handler = Type, new = expr)]`.
+pub struct ModuleArgs {
+    handler: Type,
+    new: Option<Expr>,
+}
+
+impl Parse for ModuleArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut handler = None;
+        let mut new = None;
+        while !input.is_empty() {
+            let key: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            match key.to_string().as_str() {
+                "handler" => handler = Some(input.parse()?),
+                "new" => new = Some(input.parse()?),
+                _ => return Err(Error::new_spanned(key, "expected `handler` or `new`")),
+            }
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        Ok(ModuleArgs {
+            handler: handler.ok_or_else(|| input.error("`handler = Type` is required"))?,
+            new,
+        })
+    }
+}
+
+/// A service given by `#[export_service("name", ...)]`.
+enum Export {
+    /// `dyn Trait`, implemented by the handler itself.
+    Handler(Type),
+    /// Any other value for `Skeleton::new`, in which `handler` is the `&Arc` of the handler.
+    Value(Expr),
+}
+
+struct ExportAttr {
+    name: LitStr,
+    export: Export,
+}
+
+impl Parse for ExportAttr {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let export = if input.peek(Token![dyn]) {
+            Export::Handler(input.parse()?)
+        } else {
+            Export::Value(input.parse()?)
+        };
+        Ok(ExportAttr {
+            name,
+            export,
+        })
+    }
+}
+
+/// `#[import_service("name", field)]`, where `field` is a `RwLock<Box<dyn Trait>>` of the handler.
+struct ImportAttr {
+    name: LitStr,
+    field: Ident,
+}
+
+impl Parse for ImportAttr {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let field = input.parse()?;
+        Ok(ImportAttr {
+            name,
+            field,
+        })
+    }
+}
+
+pub fn expand(args: ModuleArgs, mut item: ItemStruct) -> Result<TokenStream, Error> {
+    if !matches!(item.fields, Fields::Unit) {
+        return Err(Error::new_spanned(&item.ident, "foundry_module must be put on a unit struct"))
+    }
+    let exports = take_attributes::<ExportAttr>(&mut item.attrs, "export_service")?;
+    let imports = take_attributes::<ImportAttr>(&mut item.attrs, "import_service")?;
+
+    let attrs = &item.attrs;
+    let vis = &item.vis;
+    let name = &item.ident;
+    let handler = &args.handler;
+    let new = match &args.new {
+        Some(new) => quote! { #new },
+        None => quote! { <#handler>::new() },
+    };
+
+    let export_arms = exports.iter().map(|export| {
+        let name = &export.name;
+        let service = match &export.export {
+            Export::Handler(service) => quote! { ::std::sync::Arc::clone(handler) as ::std::sync::Arc<#service> },
+            Export::Value(value) => quote! { #value },
+        };
+        quote! { #name => ::remote_trait_object::raw_exchange::Skeleton::new(#service) }
+    });
+    let import_arms = imports.iter().map(|import| {
+        let name = &import.name;
+        let field = &import.field;
+        quote! {
+            #name => {
+                *(self.service_handler.#field.write()) =
+                    ::remote_trait_object::raw_exchange::import_service_from_handle(rto_context, handle);
+            }
+        }
+    });
+
+    Ok(quote! {
+        #(#attrs)*
+        #vis struct #name {
+            service_handler: ::std::sync::Arc<#handler>,
+        }
+
+        impl ::foundry_module_rt::UserModule for #name {
+            fn new(_arg: &[u8]) -> Self {
+                #name {
+                    service_handler: ::std::sync::Arc::new(#new),
+                }
+            }
+
+            fn prepare_service_to_export(
+                &mut self,
+                ctor_name: &str,
+                ctor_arg: &[u8],
+            ) -> ::remote_trait_object::raw_exchange::Skeleton {
+                let arg: ::std::collections::HashMap<String, String> = ::serde_cbor::from_slice(ctor_arg).unwrap();
+                assert!(arg.is_empty(), "Unexpected arguments for {}", ctor_name);
+                let handler = &self.service_handler;
+                match ctor_name {
+                    #(#export_arms,)*
+                    _ => panic!("Unsupported ctor_name in prepare_service_to_export() : {}", ctor_name),
+                }
+            }
+
+            fn import_service(
+                &mut self,
+                rto_context: &::remote_trait_object::Context,
+                name: &str,
+                handle: ::remote_trait_object::raw_exchange::HandleToExchange,
+            ) {
+                match name {
+                    #(#import_arms)*
+                    _ => panic!("Unsupported name in import_service() : {}", name),
+                }
+            }
+
+            fn debug(&mut self, _arg: &[u8]) -> Vec<u8> {
+                unimplemented!()
+            }
+        }
+    })
+}
+
+/// Removes the attributes of the name from `attrs`, and parses their arguments.
+fn take_attributes<T: Parse>(attrs: &mut Vec<Attribute>, name: &str) -> Result<Vec<T>, Error> {
+    let mut taken = Vec::new();
+    let mut rest = Vec::new();
+    for attr in attrs.drain(..) {
+        if attr.path.is_ident(name) {
+            taken.push(attr.parse_args()?);
+        } else {
+            rest.push(attr);
+        }
+    }
+    *attrs = rest;
+    Ok(taken)
+}
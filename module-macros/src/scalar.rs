@@ -0,0 +1,72 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Error, Expr, Fields, Lit, LitStr, Meta, NestedMeta};
+
+pub fn expand(input: DeriveInput) -> Result<TokenStream, Error> {
+    match &input.data {
+        Data::Struct(data) if matches!(&data.fields, Fields::Unnamed(fields) if fields.unnamed.len() == 1) => {}
+        _ => return Err(Error::new_spanned(&input.ident, "HexScalar can be derived only for a newtype struct")),
+    }
+    let name = &input.ident;
+    let (from, error) = parse_attributes(&input)?;
+    let error = error.unwrap_or_else(|| LitStr::new(&format!("Invalid {}", name), name.span()));
+
+    Ok(quote! {
+        #[::async_graphql::Scalar]
+        impl ::async_graphql::ScalarType for #name {
+            fn parse(value: ::async_graphql::Value) -> ::async_graphql::InputValueResult<Self> {
+                let invalid = || ::async_graphql::InputValueError::Custom(#error.to_owned());
+                if let ::async_graphql::Value::String(s) = value {
+                    let bytes = ::hex::decode(&s).map_err(|_| invalid())?;
+                    Ok(#name((#from)(&bytes).ok_or_else(invalid)?))
+                } else {
+                    Err(invalid())
+                }
+            }
+
+            fn to_value(&self) -> ::async_graphql::Value {
+                ::async_graphql::Value::String(::hex::encode(self.0.as_ref()))
+            }
+        }
+    })
+}
+
+/// Parses `#[hex_scalar(from = "path::to::function", error = "message")]`.
+fn parse_attributes(input: &DeriveInput) -> Result<(Expr, Option<LitStr>), Error> {
+    let mut from = None;
+    let mut error = None;
+    for attr in input.attrs.iter().filter(|attr| attr.path.is_ident("hex_scalar")) {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(Error::new_spanned(meta, "expected #[hex_scalar(...)]")),
+        };
+        for nested in list.nested {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(name_value)) => match (&name_value.lit, name_value.path.get_ident()) {
+                    (Lit::Str(lit), Some(ident)) if ident == "from" => from = Some(lit.parse()?),
+                    (Lit::Str(lit), Some(ident)) if ident == "error" => error = Some(lit.clone()),
+                    _ => return Err(Error::new_spanned(name_value, "unknown hex_scalar attribute")),
+                },
+                nested => return Err(Error::new_spanned(nested, "unknown hex_scalar attribute")),
+            }
+        }
+    }
+    let from = from.ok_or_else(|| Error::new_spanned(&input.ident, "#[hex_scalar(from = \"...\")] is required"))?;
+    Ok((from, error))
+}
@@ -14,4 +14,6 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+pub mod inproc;
 pub mod process;
+pub mod wasm;
@@ -0,0 +1,229 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Modules compiled into the host process.
+//!
+//! Two such modules are linked by handing over their service objects as they are,
+//! so calls between them are plain calls on trait objects without any serialization.
+//! Links with other modules go through remote-trait-object as usual.
+
+use super::process::ProcessPort;
+use crate::link::{self, Linkable, Linker, Port, LINKERS};
+use crate::sandbox::{LoadError, Sandbox, Sandboxer, SANDBOXERS};
+use anyhow::anyhow;
+use foundry_module_rt::coordinator_interface::FoundryModule;
+use foundry_module_rt::UserModule;
+use linkme::distributed_slice;
+use once_cell::sync;
+use parking_lot::Mutex;
+use remote_trait_object::raw_exchange::{HandleToExchange, Skeleton};
+use remote_trait_object::Context as RtoContext;
+use std::any::Any;
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::Arc;
+
+#[distributed_slice(SANDBOXERS)]
+fn in_process() -> (&'static str, Arc<dyn Sandboxer>) {
+    ("in-process", Arc::new(InProcSandboxer))
+}
+
+#[distributed_slice(LINKERS)]
+fn in_process_linker() -> (&'static str, Arc<dyn Linker>) {
+    ("in-process-linker", Arc::new(InProcLinker))
+}
+
+/// A service object handed over to another module as it is.
+///
+/// It holds what the service would be imported as, e.g. `Arc<dyn Trait>`,
+/// which the importing module downcasts to.
+pub type DirectService = Arc<dyn Any + Send + Sync>;
+
+/// A module that can be linked with other modules in the host process without serialization.
+pub trait InProcModule: UserModule + Send + 'static {
+    /// Creates the service object for the export, as `prepare_service_to_export()` does.
+    fn export_direct(&mut self, ctor_name: &str, ctor_arg: &[u8]) -> DirectService;
+
+    /// Takes the service object exported by another module, as `import_service()` does.
+    fn import_direct(&mut self, name: &str, service: DirectService);
+}
+
+type Loader = fn(&[u8], &[(String, Vec<u8>)]) -> InProcSandbox;
+
+static MODULES: sync::Lazy<Mutex<HashMap<String, Loader>>> = sync::Lazy::new(Default::default);
+
+/// Registers an in-process module with the `name` to be loaded by the "in-process" sandboxer.
+pub fn add_in_proc_module<M: InProcModule>(name: String) {
+    MODULES.lock().insert(name, InProcSandbox::new::<M>);
+}
+
+pub struct InProcSandboxer;
+
+impl Sandboxer for InProcSandboxer {
+    fn load(
+        &self,
+        path: &dyn AsRef<Path>,
+        init: &dyn erased_serde::Serialize,
+        exports: &[(&str, &dyn erased_serde::Serialize)],
+    ) -> Result<Box<dyn Sandbox>, LoadError> {
+        let path = path.as_ref();
+        let loader = path.to_str().and_then(|name| MODULES.lock().get(name).cloned()).ok_or_else(|| {
+            LoadError::ModuleCorrupted {
+                path: path.to_owned(),
+                source: Some(anyhow!("no in-process module is registered with the name")),
+            }
+        })?;
+
+        let mut init_buffer = Vec::<u8>::new();
+        let cbor = &mut serde_cbor::Serializer::new(serde_cbor::ser::IoWrite::new(Cursor::new(&mut init_buffer)));
+        init.erased_serialize(&mut erased_serde::Serializer::erase(cbor)).unwrap();
+
+        let exports: Vec<(String, Vec<u8>)> = exports
+            .iter()
+            .map(|(name, data)| {
+                let mut buffer = Vec::<u8>::new();
+                let cbor = &mut serde_cbor::Serializer::new(serde_cbor::ser::IoWrite::new(Cursor::new(&mut buffer)));
+                data.erased_serialize(&mut erased_serde::Serializer::erase(cbor)).unwrap();
+                (name.to_string(), buffer)
+            })
+            .collect();
+
+        Ok(Box::new(loader(&init_buffer, &exports)))
+    }
+}
+
+/// Lets the module be shared by the direct links and the remote-trait-object ones.
+struct Shared<M>(Arc<Mutex<M>>);
+
+impl<M: InProcModule> UserModule for Shared<M> {
+    fn new(_arg: &[u8]) -> Self {
+        unreachable!("InProcSandbox creates the module")
+    }
+
+    fn prepare_service_to_export(&mut self, ctor_name: &str, ctor_arg: &[u8]) -> Skeleton {
+        self.0.lock().prepare_service_to_export(ctor_name, ctor_arg)
+    }
+
+    fn import_service(&mut self, rto_context: &RtoContext, name: &str, handle: HandleToExchange) {
+        self.0.lock().import_service(rto_context, name, handle)
+    }
+
+    fn debug(&mut self, arg: &[u8]) -> Vec<u8> {
+        self.0.lock().debug(arg)
+    }
+}
+
+/// What the ports of an `InProcSandbox` need for direct links.
+struct Direct {
+    exports: Vec<DirectService>,
+    import: Box<dyn Fn(&str, DirectService) + Send + Sync>,
+}
+
+pub struct InProcSandbox {
+    direct: Arc<Direct>,
+    module: Box<dyn FoundryModule>,
+}
+
+impl InProcSandbox {
+    fn new<M: InProcModule>(init: &[u8], exports: &[(String, Vec<u8>)]) -> Self {
+        let module = Arc::new(Mutex::new(M::new(init)));
+        let direct_exports =
+            exports.iter().map(|(ctor_name, ctor_arg)| module.lock().export_direct(ctor_name, ctor_arg)).collect();
+        let importer = Arc::clone(&module);
+        let direct = Arc::new(Direct {
+            exports: direct_exports,
+            import: Box::new(move |name, service| importer.lock().import_direct(name, service)),
+        });
+
+        Self {
+            direct,
+            module: Box::new(foundry_module_rt::create_foundry_module(Shared(module), exports)),
+        }
+    }
+}
+
+impl Sandbox for InProcSandbox {
+    fn debug(&mut self, arg: &[u8]) -> Vec<u8> {
+        self.module.debug(arg)
+    }
+}
+
+impl Linkable for InProcSandbox {
+    fn supported_linkers(&self) -> &'static [&'static str] {
+        &["in-process-linker", "single-process-linker", "multi-process-linker"]
+    }
+
+    fn new_port(&mut self) -> Box<dyn Port> {
+        Box::new(InProcPort {
+            process: ProcessPort::new(&mut *self.module),
+            direct: Arc::clone(&self.direct),
+            ids: Vec::new(),
+            slots: Vec::new(),
+        })
+    }
+
+    fn seal(&mut self) {
+        self.module.seal()
+    }
+}
+
+/// A port of an `InProcSandbox`, which can be linked by the process linkers as well.
+pub struct InProcPort {
+    pub(super) process: ProcessPort,
+    direct: Arc<Direct>,
+    ids: Vec<usize>,
+    slots: Vec<String>,
+}
+
+impl Port for InProcPort {
+    fn export(&mut self, ids: &[usize]) {
+        self.process.export(ids);
+        self.ids = ids.to_vec();
+    }
+
+    fn import(&mut self, slots: &[&str]) {
+        self.process.import(slots);
+        self.slots = slots.iter().map(|x| x.to_string()).collect();
+    }
+}
+
+impl InProcPort {
+    fn import_from(&self, other: &InProcPort) {
+        assert_eq!(self.slots.len(), other.ids.len());
+        for (slot, id) in self.slots.iter().zip(other.ids.iter()) {
+            (self.direct.import)(slot, Arc::clone(&other.direct.exports[*id]));
+        }
+    }
+}
+
+pub struct InProcLinker;
+
+impl Linker for InProcLinker {
+    fn link(&self, a: &mut dyn Port, b: &mut dyn Port) -> Result<(), link::Error> {
+        let port_a: &mut InProcPort = a.mut_any().downcast_mut().ok_or_else(|| link::Error::UnsupportedPortType {
+            id: "Unknown",
+        })?;
+        let port_b: &mut InProcPort = b.mut_any().downcast_mut().ok_or_else(|| link::Error::UnsupportedPortType {
+            id: "Unknown",
+        })?;
+
+        port_a.import_from(port_b);
+        port_b.import_from(port_a);
+
+        Ok(())
+    }
+}
@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use super::inproc::InProcPort;
 use crate::link::{self, Linkable, Linker, Port, LINKERS};
 use crate::sandbox::{LoadError, Sandbox, Sandboxer, SANDBOXERS};
 use anyhow::anyhow;
@@ -181,14 +182,7 @@ where
     }
 
     fn new_port(&mut self) -> Box<dyn Port> {
-        // TODO: use module name.
-        // It MUST be unique anyway, for now.
-        let random_name = fproc_sndbx::ipc::generate_random_name();
-        Box::new(ProcessPort {
-            module_side_port: self.create_port(&random_name).into_object(),
-            ids: Vec::new(),
-            slots: Vec::new(),
-        })
+        Box::new(ProcessPort::new(self))
     }
 
     fn seal(&mut self) {
@@ -238,6 +232,31 @@ impl Port for ProcessPort {
 }
 
 impl ProcessPort {
+    pub(super) fn new<M: FoundryModule + ?Sized>(module: &mut M) -> Self {
+        // TODO: use module name.
+        // It MUST be unique anyway, for now.
+        let random_name = fproc_sndbx::ipc::generate_random_name();
+        ProcessPort {
+            module_side_port: module.create_port(&random_name).into_object(),
+            ids: Vec::new(),
+            slots: Vec::new(),
+        }
+    }
+
+    /// Takes the `ProcessPort` of a port, which may be a part of another type of port.
+    fn from_port(port: &mut dyn Port) -> Result<&mut ProcessPort, link::Error> {
+        let port = port.mut_any();
+        if port.is::<ProcessPort>() {
+            Ok(port.downcast_mut::<ProcessPort>().unwrap())
+        } else {
+            port.downcast_mut::<InProcPort>().map(|port| &mut port.process).ok_or_else(|| {
+                link::Error::UnsupportedPortType {
+                    id: "Unknown",
+                }
+            })
+        }
+    }
+
     fn initialize(&mut self, rto_config: PartialRtoConfig, ipc_arg: Vec<u8>, intra: bool) {
         self.module_side_port.initialize(rto_config, ipc_arg, intra);
     }
@@ -258,12 +277,8 @@ impl<E: ExecutionScheme> ProcessLinker<E> {
 
 impl<E: ExecutionScheme> Linker for ProcessLinker<E> {
     fn link(&self, a: &mut dyn Port, b: &mut dyn Port) -> Result<(), link::Error> {
-        let port_a: &mut ProcessPort = a.mut_any().downcast_mut().ok_or_else(|| link::Error::UnsupportedPortType {
-            id: "Unknown",
-        })?;
-        let port_b: &mut ProcessPort = b.mut_any().downcast_mut().ok_or_else(|| link::Error::UnsupportedPortType {
-            id: "Unknown",
-        })?;
+        let port_a = ProcessPort::from_port(a)?;
+        let port_b = ProcessPort::from_port(b)?;
 
         let (ipc_arg_a, ipc_arg_b) = E::Ipc::arguments_for_both_ends();
 
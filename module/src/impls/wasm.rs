@@ -0,0 +1,644 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! A sandbox running modules compiled to `wasm32` in an interpreter.
+//!
+//! The module never runs native code, and every entry into the module is metered with fuel given by the host,
+//! so a module can't take over the host or hang it. The fuel is charged by code injected into the module on load:
+//! each block charges its instructions before it runs, and `memory.grow` charges the pages it adds.
+//! The memory of a module is capped at `MAX_MEMORY_PAGES`.
+//!
+//! A module interacts with the host through the following ABI.
+//!
+//! The module exports
+//! - `memory`,
+//! - `foundry_alloc(len: u32) -> u32`, which allocates `len` bytes for the host to write into,
+//! - `foundry_initialize(init_ptr: u32, init_len: u32, exports_ptr: u32, exports_len: u32)`,
+//!   which takes the CBOR `init` and the CBOR list of `(constructor, CBOR args)` to export,
+//! - `foundry_link(port: u32, ptr: u32, len: u32)`, which takes the CBOR list of
+//!   `(slot, handle)` imported through the port,
+//! - `foundry_receive(port: u32, ptr: u32, len: u32)`, which takes a message from the port,
+//! - optionally `foundry_seal()` and `foundry_debug(ptr: u32, len: u32) -> u64`.
+//!   The latter returns the address of the output in the upper 32 bits and its length
+//!   in the lower 32 bits.
+//!
+//! And imports `foundry.send(port: u32, ptr: u32, len: u32)` to send a message through a port.
+//! A module importing anything else is not loaded.
+//!
+//! A message is framed by a kind byte and a little endian `u32` handle. For a call,
+//! the handle is an index into the handles imported through the port. The receiving side
+//! replaces it with the id of the exported service before passing it to the module,
+//! and drops calls to handles that were not exported through the port.
+//!
+//! An instance of the interpreter can't be moved to another thread, so each sandbox runs its module
+//! in a thread of its own and the host talks to it through a channel.
+//! wasm modules can't be linked with the host or process sandboxes yet, because those only speak
+//! remote-trait-object over the process linkers.
+
+use crate::link::{self, Linkable, Linker, Port, LINKERS};
+use crate::sandbox::{LoadError, Sandbox, Sandboxer, SANDBOXERS};
+use anyhow::anyhow;
+use crossbeam::channel::{self, Receiver, Sender};
+use linkme::distributed_slice;
+use parity_wasm::elements::{self, External, MemoryType};
+use std::cmp;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fmt;
+use std::io::Cursor;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use wasmi::{
+    Externals, FuncInstance, FuncRef, HostError, ImportsBuilder, MemoryRef, ModuleImportResolver, ModuleInstance,
+    ModuleRef, RuntimeArgs, RuntimeValue, Signature, Trap, TrapKind, ValueType,
+};
+
+#[distributed_slice(SANDBOXERS)]
+fn wasm() -> (&'static str, Arc<dyn Sandboxer>) {
+    ("wasm", Arc::new(WasmSandboxer::new(FuelLimits::default())))
+}
+
+#[distributed_slice(LINKERS)]
+fn wasm_linker() -> (&'static str, Arc<dyn Linker>) {
+    ("wasm-linker", Arc::new(WasmLinker))
+}
+
+const HOST_MODULE: &str = "foundry";
+/// The module the injected metering code imports its `gas` function from.
+const METERING_MODULE: &str = "env";
+
+const SEND_INDEX: usize = 0;
+const GAS_INDEX: usize = 1;
+
+/// The most 64 KiB pages a module can have in its memory.
+pub const MAX_MEMORY_PAGES: u32 = 1024;
+/// The fuel charged for each page added by `memory.grow`, on top of the instruction itself.
+const GROW_FUEL_PER_PAGE: u32 = 10_000;
+
+const FRAME_HEADER_LEN: usize = 5;
+const KIND_CALL: u8 = 0;
+const KIND_RETURN: u8 = 1;
+
+/// The amount of fuel given to a module on each entry from the host.
+///
+/// The fuel is reset, not accumulated, on each entry.
+#[derive(Clone, Copy, Debug)]
+pub struct FuelLimits {
+    /// For initialization, linking and sealing.
+    pub setup: u64,
+    /// For handling a message or a debug request.
+    pub message: u64,
+}
+
+impl Default for FuelLimits {
+    fn default() -> Self {
+        Self {
+            setup: 100_000_000,
+            message: 10_000_000,
+        }
+    }
+}
+
+pub struct WasmSandboxer {
+    limits: FuelLimits,
+}
+
+impl WasmSandboxer {
+    pub fn new(limits: FuelLimits) -> Self {
+        Self {
+            limits,
+        }
+    }
+}
+
+impl Sandboxer for WasmSandboxer {
+    fn load(
+        &self,
+        path: &dyn AsRef<Path>,
+        init: &dyn erased_serde::Serialize,
+        exports: &[(&str, &dyn erased_serde::Serialize)],
+    ) -> Result<Box<dyn Sandbox>, LoadError> {
+        let path = path.as_ref().to_owned();
+        let bytes = std::fs::read(&path).map_err(|e| corrupted(&path, anyhow!(e)))?;
+        let init = to_cbor(init);
+        let exports: Vec<(String, Vec<u8>)> =
+            exports.iter().map(|(name, args)| (name.to_string(), to_cbor(*args))).collect();
+        let exports = serde_cbor::to_vec(&exports).unwrap();
+
+        let (commands, inbox) = channel::unbounded();
+        let (loaded, load_result) = channel::bounded(1);
+        let limits = self.limits;
+        let worker = thread::Builder::new()
+            .name("wasm-sandbox".to_string())
+            .spawn(move || {
+                let instance = instrument(&bytes)
+                    .and_then(|module| Instance::new(&module, limits))
+                    .map_err(|e| corrupted(&path, e))
+                    .and_then(|mut instance| instance.initialize(&init, &exports).map(|_| instance));
+                match instance {
+                    Ok(instance) => {
+                        loaded.send(Ok(())).unwrap();
+                        instance.serve(inbox);
+                    }
+                    Err(err) => loaded.send(Err(err)).unwrap(),
+                }
+            })
+            .map_err(|e| LoadError::Other(anyhow!(e)))?;
+
+        let load_result = load_result.recv().expect("The sandbox thread reports how the loading went");
+        match load_result {
+            Ok(()) => Ok(Box::new(WasmSandbox {
+                commands,
+                next_port: 0,
+                worker: Some(worker),
+            })),
+            Err(err) => {
+                worker.join().unwrap();
+                Err(err)
+            }
+        }
+    }
+}
+
+fn corrupted(path: &Path, source: anyhow::Error) -> LoadError {
+    LoadError::ModuleCorrupted {
+        path: PathBuf::from(path),
+        source: Some(source),
+    }
+}
+
+fn to_cbor(value: &dyn erased_serde::Serialize) -> Vec<u8> {
+    let mut buffer = Vec::<u8>::new();
+    let cbor = &mut serde_cbor::Serializer::new(serde_cbor::ser::IoWrite::new(Cursor::new(&mut buffer)));
+    value.erased_serialize(&mut erased_serde::Serializer::erase(cbor)).unwrap();
+    buffer
+}
+
+/// Parses the module, caps its memory and injects the code charging its fuel.
+fn instrument(bytes: &[u8]) -> anyhow::Result<wasmi::Module> {
+    let mut module: elements::Module = parity_wasm::deserialize_buffer(bytes).map_err(|e| anyhow!("{}", e))?;
+    if let Some(imports) = module.import_section() {
+        for entry in imports.entries() {
+            match (entry.module(), entry.field(), entry.external()) {
+                (HOST_MODULE, "send", External::Function(_)) => {}
+                (module, field, _) => return Err(anyhow!("The host doesn't provide {}.{}", module, field)),
+            }
+        }
+    }
+    if let Some(memories) = module.memory_section_mut() {
+        for memory in memories.entries_mut() {
+            let initial = memory.limits().initial();
+            if initial > MAX_MEMORY_PAGES {
+                return Err(anyhow!("The memory of {} pages is larger than {} pages", initial, MAX_MEMORY_PAGES))
+            }
+            let maximum = memory.limits().maximum().map_or(MAX_MEMORY_PAGES, |max| cmp::min(max, MAX_MEMORY_PAGES));
+            *memory = MemoryType::new(initial, Some(maximum));
+        }
+    }
+
+    let rules = pwasm_utils::rules::Set::default().with_grow_cost(GROW_FUEL_PER_PAGE);
+    let module = pwasm_utils::inject_gas_counter(module, &rules).map_err(|_| anyhow!("The module can't be metered"))?;
+    wasmi::Module::from_parity_wasm_module(module).map_err(|e| anyhow!("{}", e))
+}
+
+/// Resolves the function the module imports as `field_name` if it's the host function `name` taking `params`.
+fn resolve_host_func(
+    field_name: &str,
+    signature: &Signature,
+    name: &str,
+    params: &'static [ValueType],
+    index: usize,
+) -> Result<FuncRef, wasmi::Error> {
+    if field_name != name || signature.params() != params || signature.return_type().is_some() {
+        return Err(wasmi::Error::Instantiation(format!("The host doesn't provide {} of {:?}", field_name, signature)))
+    }
+    Ok(FuncInstance::alloc_host(Signature::new(params, None), index))
+}
+
+struct SendResolver;
+
+impl ModuleImportResolver for SendResolver {
+    fn resolve_func(&self, field_name: &str, signature: &Signature) -> Result<FuncRef, wasmi::Error> {
+        resolve_host_func(field_name, signature, "send", &[ValueType::I32; 3], SEND_INDEX)
+    }
+}
+
+struct MeteringResolver;
+
+impl ModuleImportResolver for MeteringResolver {
+    fn resolve_func(&self, field_name: &str, signature: &Signature) -> Result<FuncRef, wasmi::Error> {
+        resolve_host_func(field_name, signature, "gas", &[ValueType::I32], GAS_INDEX)
+    }
+}
+
+/// Why the host stopped a module.
+#[derive(Debug)]
+enum HostTrap {
+    OutOfFuel,
+    Message(String),
+}
+
+impl fmt::Display for HostTrap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HostTrap::OutOfFuel => write!(f, "out of fuel"),
+            HostTrap::Message(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl HostError for HostTrap {}
+
+fn host_trap(trap: HostTrap) -> Trap {
+    Trap::new(TrapKind::Host(Box::new(trap)))
+}
+
+struct Peer {
+    commands: Sender<Command>,
+    port: u32,
+}
+
+struct PortState {
+    /// Service ids exported through the port, indexed by handle.
+    exports: Vec<usize>,
+    peer: Peer,
+}
+
+/// What the module reaches through its imports.
+struct Host {
+    /// `None` while the start function of the module runs.
+    memory: Option<MemoryRef>,
+    ports: HashMap<u32, PortState>,
+    fuel: u64,
+}
+
+impl Host {
+    fn send(&self, port: u32, ptr: u32, len: u32) -> Result<(), Trap> {
+        let memory =
+            self.memory.as_ref().ok_or_else(|| host_trap(HostTrap::Message("sent while starting".to_string())))?;
+        let message = memory
+            .get(ptr, len as usize)
+            .map_err(|_| host_trap(HostTrap::Message("message out of bounds".to_string())))?;
+        if message.len() < FRAME_HEADER_LEN {
+            return Err(host_trap(HostTrap::Message("message is shorter than a frame header".to_string())))
+        }
+        let peer = &self
+            .ports
+            .get(&port)
+            .ok_or_else(|| host_trap(HostTrap::Message(format!("port {} is not linked", port))))?
+            .peer;
+        // The other end may have been dropped already, in which case the message is lost anyway.
+        let _ = peer.commands.send(Command::Message {
+            port: peer.port,
+            message,
+        });
+        Ok(())
+    }
+
+    fn charge(&mut self, fuel: u32) -> Result<(), Trap> {
+        let fuel = u64::from(fuel);
+        if fuel > self.fuel {
+            self.fuel = 0;
+            return Err(host_trap(HostTrap::OutOfFuel))
+        }
+        self.fuel -= fuel;
+        Ok(())
+    }
+}
+
+impl Externals for Host {
+    fn invoke_index(&mut self, index: usize, args: RuntimeArgs<'_>) -> Result<Option<RuntimeValue>, Trap> {
+        match index {
+            SEND_INDEX => self.send(args.nth_checked(0)?, args.nth_checked(1)?, args.nth_checked(2)?)?,
+            GAS_INDEX => self.charge(args.nth_checked(0)?)?,
+            _ => unreachable!("Only the functions of the resolvers are imported"),
+        }
+        Ok(None)
+    }
+}
+
+enum Command {
+    Connect {
+        port: u32,
+        exports: Vec<usize>,
+        peer: Peer,
+    },
+    Import {
+        port: u32,
+        slots: Vec<String>,
+    },
+    Seal,
+    Message {
+        port: u32,
+        message: Vec<u8>,
+    },
+    Debug {
+        arg: Vec<u8>,
+        reply: Sender<Vec<u8>>,
+    },
+    Shutdown,
+}
+
+struct Instance {
+    module: ModuleRef,
+    memory: MemoryRef,
+    host: Host,
+    limits: FuelLimits,
+    /// The trap that stopped the module, which isn't run anymore once trapped.
+    trapped: Option<String>,
+    sealed: bool,
+    /// The messages that came before the sandbox was sealed.
+    queued: Vec<(u32, Vec<u8>)>,
+}
+
+impl Instance {
+    fn new(module: &wasmi::Module, limits: FuelLimits) -> anyhow::Result<Self> {
+        let imports = ImportsBuilder::new()
+            .with_resolver(HOST_MODULE, &SendResolver)
+            .with_resolver(METERING_MODULE, &MeteringResolver);
+        let mut host = Host {
+            memory: None,
+            ports: HashMap::new(),
+            fuel: limits.setup,
+        };
+        let module = ModuleInstance::new(module, &imports)
+            .map_err(|e| anyhow!("{}", e))?
+            .run_start(&mut host)
+            .map_err(|e| anyhow!("the module has trapped: {}", e))?;
+
+        let memory = module
+            .export_by_name("memory")
+            .and_then(|export| export.as_memory().cloned())
+            .ok_or_else(|| anyhow!("no memory exported"))?;
+        for name in &["foundry_alloc", "foundry_initialize", "foundry_link", "foundry_receive"] {
+            module
+                .export_by_name(name)
+                .and_then(|export| export.as_func().cloned())
+                .ok_or_else(|| anyhow!("no {}", name))?;
+        }
+        host.memory = Some(memory.clone());
+
+        Ok(Self {
+            module,
+            memory,
+            host,
+            limits,
+            trapped: None,
+            sealed: false,
+            queued: Vec::new(),
+        })
+    }
+
+    /// Handles the commands from the host and the other sandboxes until it's shut down.
+    fn serve(mut self, commands: Receiver<Command>) {
+        for command in commands {
+            match command {
+                Command::Connect {
+                    port,
+                    exports,
+                    peer,
+                } => {
+                    self.host.ports.insert(port, PortState {
+                        exports,
+                        peer,
+                    });
+                }
+                Command::Import {
+                    port,
+                    slots,
+                } => self.import(port, &slots),
+                Command::Seal => self.seal(),
+                Command::Message {
+                    port,
+                    message,
+                } => {
+                    if self.sealed {
+                        self.deliver(port, message)
+                    } else {
+                        self.queued.push((port, message))
+                    }
+                }
+                Command::Debug {
+                    arg,
+                    reply,
+                } => {
+                    let _ = reply.send(self.debug(&arg));
+                }
+                Command::Shutdown => break,
+            }
+        }
+    }
+
+    fn initialize(&mut self, init: &[u8], exports: &[u8]) -> Result<(), LoadError> {
+        self.host.fuel = self.limits.setup;
+        let (init_ptr, init_len) = self.write(init)?;
+        let (exports_ptr, exports_len) = self.write(exports)?;
+        self.call("foundry_initialize", &[init_ptr, init_len, exports_ptr, exports_len])?;
+        Ok(())
+    }
+
+    fn import(&mut self, port: u32, slots: &[String]) {
+        let imports: Vec<(&str, u32)> =
+            slots.iter().enumerate().map(|(handle, slot)| (&**slot, handle as u32)).collect();
+        let imports = serde_cbor::to_vec(&imports).unwrap();
+        self.host.fuel = self.limits.setup;
+        let _ = self.write(&imports).and_then(|(ptr, len)| self.call("foundry_link", &[port.into(), ptr, len]));
+    }
+
+    fn seal(&mut self) {
+        if self.module.export_by_name("foundry_seal").is_some() {
+            self.host.fuel = self.limits.setup;
+            let _ = self.call("foundry_seal", &[]);
+        }
+        self.sealed = true;
+        for (port, message) in mem::take(&mut self.queued) {
+            self.deliver(port, message);
+        }
+    }
+
+    fn deliver(&mut self, port: u32, mut message: Vec<u8>) {
+        let exports = match self.host.ports.get(&port) {
+            Some(state) => &state.exports,
+            None => return,
+        };
+        match message[0] {
+            KIND_CALL => {
+                let handle = u32::from_le_bytes(message[1..FRAME_HEADER_LEN].try_into().unwrap());
+                match exports.get(handle as usize) {
+                    Some(id) => message[1..FRAME_HEADER_LEN].copy_from_slice(&(*id as u32).to_le_bytes()),
+                    None => return,
+                }
+            }
+            KIND_RETURN => {}
+            _ => return,
+        }
+        self.host.fuel = self.limits.message;
+        let _ = self.write(&message).and_then(|(ptr, len)| self.call("foundry_receive", &[port.into(), ptr, len]));
+    }
+
+    fn debug(&mut self, arg: &[u8]) -> Vec<u8> {
+        if self.module.export_by_name("foundry_debug").is_none() {
+            return Vec::new()
+        }
+        self.host.fuel = self.limits.message;
+        let output = self.write(arg).and_then(|(ptr, len)| self.call("foundry_debug", &[ptr, len]));
+        let output = match output {
+            Ok(Some(RuntimeValue::I64(output))) => output as u64,
+            _ => return Vec::new(),
+        };
+        let (ptr, len) = ((output >> 32) as u32, (output & 0xffff_ffff) as usize);
+        self.memory.get(ptr, len).unwrap_or_default()
+    }
+
+    /// Copies the `bytes` into a buffer allocated by the module.
+    fn write(&mut self, bytes: &[u8]) -> anyhow::Result<(RuntimeValue, RuntimeValue)> {
+        let len = RuntimeValue::I32(bytes.len() as i32);
+        let ptr = match self.call("foundry_alloc", &[len])? {
+            Some(RuntimeValue::I32(ptr)) => ptr as u32,
+            _ => return Err(anyhow!("foundry_alloc didn't return an address")),
+        };
+        self.memory.set(ptr, bytes).map_err(|_| anyhow!("the module allocated a buffer out of its memory"))?;
+        Ok((RuntimeValue::I32(ptr as i32), len))
+    }
+
+    fn call(&mut self, name: &str, args: &[RuntimeValue]) -> anyhow::Result<Option<RuntimeValue>> {
+        if let Some(trap) = &self.trapped {
+            return Err(anyhow!("the module has trapped: {}", trap))
+        }
+        self.module.invoke_export(name, args, &mut self.host).map_err(|e| {
+            let trap = e.to_string();
+            self.trapped = Some(trap.clone());
+            anyhow!("the module has trapped: {}", trap)
+        })
+    }
+}
+
+pub struct WasmSandbox {
+    commands: Sender<Command>,
+    next_port: u32,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl Sandbox for WasmSandbox {
+    fn debug(&mut self, arg: &[u8]) -> Vec<u8> {
+        let (reply, output) = channel::bounded(1);
+        self.commands
+            .send(Command::Debug {
+                arg: arg.to_vec(),
+                reply,
+            })
+            .unwrap();
+        output.recv().unwrap_or_default()
+    }
+}
+
+impl Linkable for WasmSandbox {
+    fn supported_linkers(&self) -> &'static [&'static str] {
+        &["wasm-linker"]
+    }
+
+    fn new_port(&mut self) -> Box<dyn Port> {
+        let id = self.next_port;
+        self.next_port += 1;
+        Box::new(WasmPort {
+            commands: self.commands.clone(),
+            id,
+            exports: Vec::new(),
+            slots: Vec::new(),
+        })
+    }
+
+    fn seal(&mut self) {
+        self.commands.send(Command::Seal).unwrap();
+    }
+}
+
+impl Drop for WasmSandbox {
+    fn drop(&mut self) {
+        if let Some(worker) = self.worker.take() {
+            self.commands.send(Command::Shutdown).unwrap();
+            worker.join().unwrap();
+        }
+    }
+}
+
+pub struct WasmPort {
+    commands: Sender<Command>,
+    id: u32,
+    exports: Vec<usize>,
+    slots: Vec<String>,
+}
+
+impl Port for WasmPort {
+    fn export(&mut self, ids: &[usize]) {
+        self.exports = ids.to_vec()
+    }
+
+    fn import(&mut self, slots: &[&str]) {
+        self.slots = slots.iter().map(|x| x.to_string()).collect();
+    }
+}
+
+impl WasmPort {
+    fn connect(&self, other: &WasmPort) {
+        self.commands
+            .send(Command::Connect {
+                port: self.id,
+                exports: self.exports.clone(),
+                peer: Peer {
+                    commands: other.commands.clone(),
+                    port: other.id,
+                },
+            })
+            .unwrap();
+    }
+
+    fn link(&self) {
+        self.commands
+            .send(Command::Import {
+                port: self.id,
+                slots: self.slots.clone(),
+            })
+            .unwrap();
+    }
+}
+
+pub struct WasmLinker;
+
+impl Linker for WasmLinker {
+    fn link(&self, a: &mut dyn Port, b: &mut dyn Port) -> Result<(), link::Error> {
+        let port_a: &mut WasmPort = a.mut_any().downcast_mut().ok_or_else(|| link::Error::UnsupportedPortType {
+            id: "Unknown",
+        })?;
+        let port_b: &mut WasmPort = b.mut_any().downcast_mut().ok_or_else(|| link::Error::UnsupportedPortType {
+            id: "Unknown",
+        })?;
+
+        assert_eq!(port_a.exports.len(), port_b.slots.len());
+        assert_eq!(port_b.exports.len(), port_a.slots.len());
+
+        // Both ends must be able to send before either module learns of the link.
+        port_a.connect(port_b);
+        port_b.connect(port_a);
+
+        port_a.link();
+        port_b.link();
+
+        Ok(())
+    }
+}
@@ -17,6 +17,7 @@
 extern crate codechain_module as cmodule;
 extern crate foundry_process_sandbox as fproc_sndbx;
 
+use cmodule::impls::inproc::*;
 use cmodule::impls::process::*;
 use cmodule::link::*;
 use cmodule::sandbox::*;
@@ -53,6 +54,8 @@ struct ModuleA {
     others_greeting: String,
     /// along with expected value from hello()
     hello_list: Vec<(Box<dyn Hello>, i32)>,
+    /// The number of services imported without remote-trait-object
+    direct_imports: u8,
 }
 
 impl UserModule for ModuleA {
@@ -62,6 +65,7 @@ impl UserModule for ModuleA {
             my_greeting,
             others_greeting,
             hello_list: Vec::new(),
+            direct_imports: 0,
         }
     }
 
@@ -83,7 +87,37 @@ impl UserModule for ModuleA {
             assert_eq!(hello.hello(), *value);
             assert_eq!(hello.hi(), self.others_greeting);
         }
-        Vec::new()
+        vec![self.direct_imports]
+    }
+}
+
+/// A `Hello` handed over without remote-trait-object.
+struct DirectHello(Arc<dyn Hello>);
+impl Service for DirectHello {}
+impl Hello for DirectHello {
+    fn hello(&self) -> i32 {
+        self.0.hello()
+    }
+
+    fn hi(&self) -> String {
+        self.0.hi()
+    }
+}
+
+impl InProcModule for ModuleA {
+    fn export_direct(&mut self, ctor_name: &str, ctor_arg: &[u8]) -> DirectService {
+        assert_eq!(ctor_name, "Constructor");
+        let value: i32 = serde_cbor::from_slice(ctor_arg).unwrap();
+        Arc::new(Arc::new(SimpleHello {
+            value,
+            greeting: self.my_greeting.clone(),
+        }) as Arc<dyn Hello>)
+    }
+
+    fn import_direct(&mut self, name: &str, service: DirectService) {
+        let hello = Arc::clone(service.downcast_ref::<Arc<dyn Hello>>().unwrap());
+        self.hello_list.push((Box::new(DirectHello(hello)), name.parse().unwrap()));
+        self.direct_imports += 1;
     }
 }
 
@@ -135,3 +169,67 @@ fn module_bootstrap1() {
     drop(port_a);
     drop(port_b);
 }
+
+fn link_modules(sandbox_a: &mut dyn Sandbox, sandbox_b: &mut dyn Sandbox, n: usize) {
+    let linker = best_linker(sandbox_a, sandbox_b).unwrap();
+    let mut port_a = sandbox_a.new_port();
+    let mut port_b = sandbox_b.new_port();
+
+    let zero_to_n: Vec<usize> = (0..n).collect();
+    let zero_to_n_in_string: Vec<String> = (0..n).map(|x| x.to_string()).collect();
+    let zero_to_n_in_string_: Vec<&str> = zero_to_n_in_string.iter().map(|x| x.as_str()).collect();
+
+    port_a.export(&zero_to_n);
+    port_a.import(&zero_to_n_in_string_);
+
+    port_b.export(&zero_to_n);
+    port_b.import(&zero_to_n_in_string_);
+
+    linker.link(&mut *port_a, &mut *port_b).unwrap();
+}
+
+#[test]
+fn in_process_modules_are_linked_directly() {
+    let sandboxer = sandboxer("in-process").unwrap();
+
+    let name_a = generate_random_name();
+    add_in_proc_module::<ModuleA>(name_a.clone());
+    let name_b = generate_random_name();
+    add_in_proc_module::<ModuleA>(name_b.clone());
+
+    let n = 10;
+    let exports: Vec<(String, i32)> = (0..n).map(|i| ("Constructor".to_owned(), i)).collect();
+    let exports_ref: Vec<(&str, &dyn erased_serde::Serialize)> =
+        exports.iter().map(|(name, i)| (name.as_str(), i as &dyn erased_serde::Serialize)).collect();
+
+    let mut sandbox_a = sandboxer.load(&name_a, &("Annyeong", "Konnichiwa"), &exports_ref).unwrap();
+    let mut sandbox_b = sandboxer.load(&name_b, &("Konnichiwa", "Annyeong"), &exports_ref).unwrap();
+
+    link_modules(&mut *sandbox_a, &mut *sandbox_b, n as usize);
+
+    assert_eq!(sandbox_a.debug(&[]), vec![n as u8]);
+    assert_eq!(sandbox_b.debug(&[]), vec![n as u8]);
+}
+
+#[test]
+fn in_process_module_is_linked_with_process_module() {
+    let name_a = generate_random_name();
+    add_in_proc_module::<ModuleA>(name_a.clone());
+    let name_b = generate_random_name();
+    executor::add_function_pool(name_b.clone(), Arc::new(execute_module::<ModuleA>));
+
+    let n = 10;
+    let exports: Vec<(String, i32)> = (0..n).map(|i| ("Constructor".to_owned(), i)).collect();
+    let exports_ref: Vec<(&str, &dyn erased_serde::Serialize)> =
+        exports.iter().map(|(name, i)| (name.as_str(), i as &dyn erased_serde::Serialize)).collect();
+
+    let mut sandbox_a =
+        sandboxer("in-process").unwrap().load(&name_a, &("Annyeong", "Konnichiwa"), &exports_ref).unwrap();
+    let mut sandbox_b =
+        sandboxer("single-process").unwrap().load(&name_b, &("Konnichiwa", "Annyeong"), &exports_ref).unwrap();
+
+    link_modules(&mut *sandbox_a, &mut *sandbox_b, n as usize);
+
+    assert_eq!(sandbox_a.debug(&[]), vec![0]);
+    assert_eq!(sandbox_b.debug(&[]), vec![0]);
+}
@@ -0,0 +1,163 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+extern crate codechain_module as cmodule;
+
+use cmodule::impls::wasm::{FuelLimits, WasmSandboxer};
+use cmodule::link::*;
+use cmodule::sandbox::*;
+use std::convert::TryInto;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Calls the first handle imported through every port on link,
+/// returns to every call, and counts the returns it gets.
+const PING_PONG: &str = r#"
+(module
+  (import "foundry" "send" (func $send (param i32 i32 i32)))
+  (memory (export "memory") 1)
+  (global $heap (mut i32) (i32.const 1024))
+  (global $returns (mut i32) (i32.const 0))
+  (func (export "foundry_alloc") (param $len i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $heap))
+    (global.set $heap (i32.add (global.get $heap) (local.get $len)))
+    (local.get $ptr))
+  (func (export "foundry_initialize") (param i32 i32 i32 i32))
+  (func (export "foundry_link") (param $port i32) (param $ptr i32) (param $len i32)
+    (i32.store8 (i32.const 0) (i32.const 0))
+    (i32.store (i32.const 1) (i32.const 0))
+    (call $send (local.get $port) (i32.const 0) (i32.const 5)))
+  (func (export "foundry_receive") (param $port i32) (param $ptr i32) (param $len i32)
+    (if (i32.eqz (i32.load8_u (local.get $ptr)))
+      (then
+        (i32.store8 (i32.const 8) (i32.const 1))
+        (i32.store (i32.const 9) (i32.const 0))
+        (call $send (local.get $port) (i32.const 8) (i32.const 5)))
+      (else
+        (global.set $returns (i32.add (global.get $returns) (i32.const 1))))))
+  (func (export "foundry_debug") (param i32 i32) (result i64)
+    (i32.store (i32.const 16) (global.get $returns))
+    (i64.or (i64.shl (i64.const 16) (i64.const 32)) (i64.const 4))))
+"#;
+
+const INFINITE_LOOP: &str = r#"
+(module
+  (memory (export "memory") 1)
+  (func (export "foundry_alloc") (param i32) (result i32) (i32.const 0))
+  (func (export "foundry_initialize") (param i32 i32 i32 i32) (loop $forever (br $forever)))
+  (func (export "foundry_link") (param i32 i32 i32))
+  (func (export "foundry_receive") (param i32 i32 i32)))
+"#;
+
+const UNKNOWN_IMPORT: &str = r#"
+(module
+  (import "env" "abort" (func (param i32)))
+  (memory (export "memory") 1)
+  (func (export "foundry_alloc") (param i32) (result i32) (i32.const 0))
+  (func (export "foundry_initialize") (param i32 i32 i32 i32))
+  (func (export "foundry_link") (param i32 i32 i32))
+  (func (export "foundry_receive") (param i32 i32 i32)))
+"#;
+
+const HUGE_MEMORY: &str = r#"
+(module
+  (memory (export "memory") 2048)
+  (func (export "foundry_alloc") (param i32) (result i32) (i32.const 0))
+  (func (export "foundry_initialize") (param i32 i32 i32 i32))
+  (func (export "foundry_link") (param i32 i32 i32))
+  (func (export "foundry_receive") (param i32 i32 i32)))
+"#;
+
+fn write_module(name: &str, wat: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("foundry-wasm-test-{}-{}.wasm", name, std::process::id()));
+    std::fs::write(&path, wat::parse_str(wat).unwrap()).unwrap();
+    path
+}
+
+fn returns(sandbox: &mut dyn Sandbox, expected: u32) -> u32 {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let returns = u32::from_le_bytes(sandbox.debug(&[])[..].try_into().unwrap());
+        if returns == expected || Instant::now() > deadline {
+            return returns
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn calls_only_exported_handles() {
+    let path = write_module("ping-pong", PING_PONG);
+    let sandboxer = sandboxer("wasm").unwrap();
+    let mut a = sandboxer.load(&path, &(), &[]).unwrap();
+    let mut b = sandboxer.load(&path, &(), &[("Constructor", &0 as &dyn erased_serde::Serialize)]).unwrap();
+
+    let linker = best_linker(&*a, &*b).unwrap();
+    let mut port_a = a.new_port();
+    let mut port_b = b.new_port();
+    // a imports a service of b, but b imports nothing from a.
+    port_a.import(&["hello"]);
+    port_b.export(&[0]);
+    linker.link(&mut *port_a, &mut *port_b).unwrap();
+    a.seal();
+    b.seal();
+
+    assert_eq!(returns(&mut *a, 1), 1);
+    assert_eq!(returns(&mut *b, 0), 0);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn module_running_out_of_fuel_is_not_loaded() {
+    let path = write_module("infinite-loop", INFINITE_LOOP);
+    // The default fuel takes the interpreter too long to burn in a debug build.
+    let sandboxer = WasmSandboxer::new(FuelLimits {
+        setup: 1_000_000,
+        message: 1_000_000,
+    });
+    assert!(sandboxer.load(&path, &(), &[]).is_err());
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn module_importing_other_than_send_is_not_loaded() {
+    let path = write_module("unknown-import", UNKNOWN_IMPORT);
+    assert!(sandboxer("wasm").unwrap().load(&path, &(), &[]).is_err());
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn module_asking_for_too_much_memory_is_not_loaded() {
+    let path = write_module("huge-memory", HUGE_MEMORY);
+    assert!(sandboxer("wasm").unwrap().load(&path, &(), &[]).is_err());
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn corrupted_module_is_reported() {
+    let path = std::env::temp_dir().join(format!("foundry-wasm-test-corrupted-{}.wasm", std::process::id()));
+    std::fs::write(&path, b"not a wasm module").unwrap();
+    match sandboxer("wasm").unwrap().load(&path, &(), &[]) {
+        Err(LoadError::ModuleCorrupted {
+            ..
+        }) => {}
+        _ => panic!("a corrupted module must not be loaded"),
+    }
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,157 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::SocketAddr;
+use ckey::Ed25519Public as Public;
+use ctypes::NetworkIdentity;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+
+/// The network addresses of the validators, learned from the network identities they registered on the chain.
+///
+/// An identity is trusted only if it is signed by its validator and its node,
+/// and no other validator claims the same address or node key.
+/// A peer is known as a validator's node only after it proves the node key in the handshake,
+/// so a node sharing an IP with a validator gets no privilege from it.
+#[derive(Default)]
+pub struct AddressBook {
+    validators: RwLock<HashMap<Public, NetworkIdentity>>,
+}
+
+impl AddressBook {
+    /// Replaces the entries with the given identities.
+    pub fn update(&self, identities: Vec<NetworkIdentity>) {
+        let mut address_claims: HashMap<SocketAddr, usize> = HashMap::new();
+        let mut node_key_claims: HashMap<Public, usize> = HashMap::new();
+        let identities: Vec<_> = identities
+            .into_iter()
+            .filter(|identity| {
+                if !identity.verify() {
+                    cwarn!(
+                        NETWORK,
+                        "The network identity of {:?} claiming {} is not signed by the validator and the node",
+                        identity.validator,
+                        identity.node_address
+                    );
+                    return false
+                }
+                if !identity.node_address.is_ipv4() {
+                    cwarn!(NETWORK, "The network identity of {:?} has an IPv6 address", identity.validator);
+                    return false
+                }
+                *address_claims.entry(identity.node_address.into()).or_default() += 1;
+                *node_key_claims.entry(identity.node_key).or_default() += 1;
+                true
+            })
+            .collect();
+
+        let mut validators = HashMap::with_capacity(identities.len());
+        for identity in identities {
+            if address_claims[&SocketAddr::from(identity.node_address)] > 1 {
+                cwarn!(NETWORK, "{} is claimed by more than one validator", identity.node_address);
+                continue
+            }
+            if node_key_claims[&identity.node_key] > 1 {
+                cwarn!(NETWORK, "The node key {:?} is claimed by more than one validator", identity.node_key);
+                continue
+            }
+            validators.insert(identity.node_key, identity);
+        }
+        *self.validators.write() = validators;
+    }
+
+    /// The validator running the node that proved the node key.
+    pub fn validator_of_node(&self, node_key: &Public) -> Option<Public> {
+        self.validators.read().get(node_key).map(|identity| identity.validator)
+    }
+
+    /// The node key that a peer at the address must prove, if the address belongs to a validator.
+    pub fn node_key_at(&self, address: &SocketAddr) -> Option<Public> {
+        self.validators
+            .read()
+            .values()
+            .find(|identity| SocketAddr::from(identity.node_address) == *address)
+            .map(|identity| identity.node_key)
+    }
+
+    pub fn addresses(&self) -> Vec<SocketAddr> {
+        self.validators.read().values().map(|identity| identity.node_address.into()).collect()
+    }
+
+    pub fn identities(&self) -> Vec<NetworkIdentity> {
+        self.validators.read().values().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckey::{Ed25519KeyPair as KeyPair, Generator, KeyPairTrait, Random};
+
+    fn identity(validator: &KeyPair, node: &KeyPair, address: &str) -> NetworkIdentity {
+        NetworkIdentity::new_signed(
+            *validator.public(),
+            validator.private(),
+            *node.public(),
+            node.private(),
+            address.parse().unwrap(),
+        )
+    }
+
+    #[test]
+    fn signed_identities_are_trusted() {
+        let validator: KeyPair = Random.generate().unwrap();
+        let node: KeyPair = Random.generate().unwrap();
+        let book = AddressBook::default();
+        book.update(vec![identity(&validator, &node, "10.0.0.1:3485")]);
+
+        assert_eq!(Some(*validator.public()), book.validator_of_node(node.public()));
+        assert_eq!(Some(*node.public()), book.node_key_at(&"10.0.0.1:3485".parse().unwrap()));
+        assert_eq!(None, book.node_key_at(&"10.0.0.1:3486".parse().unwrap()));
+    }
+
+    #[test]
+    fn identity_signed_by_another_key_is_not_trusted() {
+        let validator: KeyPair = Random.generate().unwrap();
+        let impersonator: KeyPair = Random.generate().unwrap();
+        let node: KeyPair = Random.generate().unwrap();
+        let mut forged = identity(&impersonator, &node, "10.0.0.1:3485");
+        forged.validator = *validator.public();
+
+        let book = AddressBook::default();
+        book.update(vec![forged]);
+        assert_eq!(None, book.validator_of_node(node.public()));
+    }
+
+    #[test]
+    fn address_or_node_key_claimed_by_two_validators_is_not_trusted() {
+        let validators: Vec<KeyPair> = (0..5).map(|_| Random.generate().unwrap()).collect();
+        let nodes: Vec<KeyPair> = (0..4).map(|_| Random.generate().unwrap()).collect();
+
+        let book = AddressBook::default();
+        book.update(vec![
+            identity(&validators[0], &nodes[0], "10.0.0.1:3485"),
+            identity(&validators[1], &nodes[1], "10.0.0.1:3485"),
+            identity(&validators[2], &nodes[2], "10.0.0.2:3485"),
+            identity(&validators[3], &nodes[2], "10.0.0.3:3485"),
+            identity(&validators[4], &nodes[3], "10.0.0.4:3485"),
+        ]);
+        for node in &nodes[..3] {
+            assert_eq!(None, book.validator_of_node(node.public()));
+        }
+        assert_eq!(Some(*validators[4].public()), book.validator_of_node(nodes[3].public()));
+    }
+}
@@ -15,7 +15,9 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::p2p::Message as P2pMessage;
-use crate::{Api, IntoSocketAddr, NetworkExtension, NetworkExtensionResult, NodeId};
+use crate::reputation::Reputation;
+use crate::traffic::Traffic;
+use crate::{Api, IntoSocketAddr, Misbehavior, NetworkExtension, NetworkExtensionResult, NodeId};
 use cio::IoChannel;
 use crossbeam_channel as crossbeam;
 use ctimer::{TimeoutHandler, TimerApi, TimerLoop, TimerToken};
@@ -31,6 +33,9 @@ struct ClientApi {
     timer: TimerApi,
     name: &'static str,
     need_encryption: bool,
+    protocol_of: fn(&[u8]) -> &'static str,
+    traffic: Arc<Traffic>,
+    reputation: Arc<Reputation>,
 }
 
 impl Api for ClientApi {
@@ -39,6 +44,7 @@ impl Api for ClientApi {
         let extension_name = self.name;
         let node_id = *id;
         let bytes = data.len();
+        let protocol = (self.protocol_of)(&data);
         if let Err(err) = self.p2p_channel.send(P2pMessage::SendExtensionMessage {
             node_id,
             extension_name,
@@ -55,6 +61,7 @@ impl Api for ClientApi {
             );
         } else {
             cdebug!(NETAPI, "`{}` sends {} bytes to {}", extension_name, bytes, id.into_addr());
+            self.traffic.record_sent(id.into_addr(), protocol, bytes);
         }
     }
 
@@ -72,11 +79,32 @@ impl Api for ClientApi {
         self.timer.cancel(token)?;
         Ok(())
     }
+
+    fn peer_score(&self, id: &NodeId) -> i64 {
+        self.traffic.score(&id.into_addr())
+    }
+
+    fn record_useful_response(&self, id: &NodeId) {
+        self.traffic.record_useful_response(id.into_addr())
+    }
+
+    fn report(&self, id: &NodeId, misbehavior: Misbehavior) {
+        let addr = id.into_addr();
+        cdebug!(NETAPI, "`{}` reports {:?} of {}", self.name, misbehavior, addr);
+        self.traffic.record_failure(addr);
+        if let Some(duration) = self.reputation.report(addr, misbehavior) {
+            cinfo!(NETAPI, "{} is banned for {:?} because of {:?}", addr, duration, misbehavior);
+            if let Err(err) = self.p2p_channel.send(P2pMessage::Ban(addr)) {
+                cerror!(NETAPI, "Cannot disconnect the banned peer {}: {:?}", addr, err);
+            }
+        }
+    }
 }
 
 struct Extension {
     versions: Vec<u64>,
     name: &'static str,
+    protocol_of: fn(&[u8]) -> &'static str,
     sender: Mutex<crossbeam::Sender<ExtensionMessage>>,
     quit: Mutex<crossbeam::Sender<()>>,
     join: Mutex<Option<JoinHandle<()>>>,
@@ -103,19 +131,37 @@ pub struct Client {
     extensions: RwLock<HashMap<&'static str, Arc<Extension>>>,
     p2p_channel: IoChannel<P2pMessage>,
     timer_loop: TimerLoop,
+    traffic: Arc<Traffic>,
+    reputation: Arc<Reputation>,
 }
 
 impl Client {
     pub fn register_extension<T, E, F>(&self, factory: F) -> crossbeam::Sender<E>
+    where
+        T: 'static + Sized + NetworkExtension<E>,
+        E: 'static + Sized + Send,
+        F: 'static + FnOnce(Box<dyn Api>) -> T + Send, {
+        self.register_extension_in(None, factory)
+    }
+
+    /// Registers the extension as `<name>@<namespace>` if the namespace is given,
+    /// so that the same extension can be registered once for each chain.
+    pub fn register_extension_in<T, E, F>(&self, namespace: Option<&str>, factory: F) -> crossbeam::Sender<E>
     where
         T: 'static + Sized + NetworkExtension<E>,
         E: 'static + Sized + Send,
         F: 'static + FnOnce(Box<dyn Api>) -> T + Send, {
         let mut extensions = self.extensions.write();
-        let name = T::name();
+        // The extensions live as long as the node, so the namespaced names are leaked.
+        let name: &'static str = match namespace {
+            Some(namespace) => Box::leak(format!("{}@{}", T::name(), namespace).into_boxed_str()),
+            None => T::name(),
+        };
         let timer = self.timer_loop.new_timer_with_name(name);
         let cloned_timer = timer.clone();
         let p2p_channel = self.p2p_channel.clone();
+        let traffic = Arc::clone(&self.traffic);
+        let reputation = Arc::clone(&self.reputation);
         let (channel, rx) = crossbeam::unbounded();
         let sender = channel.into();
 
@@ -131,8 +177,11 @@ impl Client {
                     let api = ClientApi {
                         name,
                         need_encryption: T::need_encryption(),
+                        protocol_of: T::protocol_of,
                         p2p_channel,
                         timer,
+                        traffic,
+                        reputation,
                     };
                     let mut extension = factory(Box::from(api));
 
@@ -206,6 +255,7 @@ impl Client {
         let extension = Arc::new(Extension {
             name,
             versions: T::versions().to_vec(),
+            protocol_of: T::protocol_of,
             sender,
             quit: quit_sender.into(),
             join,
@@ -218,11 +268,18 @@ impl Client {
         event_sender
     }
 
-    pub fn new(p2p_channel: IoChannel<P2pMessage>, timer_loop: TimerLoop) -> Arc<Self> {
+    pub fn new(
+        p2p_channel: IoChannel<P2pMessage>,
+        timer_loop: TimerLoop,
+        traffic: Arc<Traffic>,
+        reputation: Arc<Reputation>,
+    ) -> Arc<Self> {
         Arc::new(Self {
             extensions: RwLock::new(HashMap::new()),
             p2p_channel,
             timer_loop,
+            traffic,
+            reputation,
         })
     }
 
@@ -232,6 +289,7 @@ impl Client {
     }
 
     pub fn on_node_removed(&self, id: &NodeId) {
+        self.traffic.remove(&id.into_addr());
         let extensions = self.extensions.read();
         for (name, extension) in extensions.iter() {
             if let Err(err) = extension.sender.lock().send(ExtensionMessage::NodeRemoved(*id)) {
@@ -255,6 +313,7 @@ impl Client {
         let extensions = self.extensions.read();
         if let Some(extension) = extensions.get(name) {
             cdebug!(NETAPI, "`{}` receives {} bytes from {}", name, data.len(), id.into_addr());
+            self.traffic.record_received(id.into_addr(), (extension.protocol_of)(&data), data.len());
             if let Err(err) = extension.sender.lock().send(ExtensionMessage::Message(*id, data)) {
                 cwarn!(NETAPI, "{} cannot message {}: {:?}", name, id, err);
             }
@@ -298,6 +357,18 @@ mod tests {
         fn clear_timer(&self, _timer_id: usize) -> NetworkExtensionResult<()> {
             unimplemented!()
         }
+
+        fn peer_score(&self, _id: &NodeId) -> i64 {
+            unimplemented!()
+        }
+
+        fn record_useful_response(&self, _id: &NodeId) {
+            unimplemented!()
+        }
+
+        fn report(&self, _id: &NodeId, _misbehavior: Misbehavior) {
+            unimplemented!()
+        }
     }
 
     #[derive(Debug, Eq, PartialEq)]
@@ -367,7 +438,7 @@ mod tests {
         let p2p_service = IoService::start("P2P").unwrap();
         let timer_loop = TimerLoop::new(2);
 
-        let client = Client::new(p2p_service.channel(), timer_loop);
+        let client = Client::new(p2p_service.channel(), timer_loop, Default::default(), Default::default());
 
         let node_id1 = SocketAddr::v4(127, 0, 0, 1, 8081).into();
         let node_id5 = SocketAddr::v4(127, 0, 0, 1, 8085).into();
@@ -384,4 +455,19 @@ mod tests {
         client.on_message(&"e2".to_string(), &node_id5, Default::default());
         client.on_message(&"e2".to_string(), &node_id1, Default::default());
     }
+
+    #[test]
+    fn namespaced_extensions_are_registered_apart() {
+        let p2p_service = IoService::start("P2P").unwrap();
+        let timer_loop = TimerLoop::new(2);
+
+        let client = Client::new(p2p_service.channel(), timer_loop, Default::default(), Default::default());
+
+        let _e1 = client.register_extension(|_| TestExtension1::new());
+        let _e1_in_app = client.register_extension_in(Some("app"), |_| TestExtension1::new());
+
+        let mut names: Vec<_> = client.extension_versions().into_iter().map(|(name, _)| name).collect();
+        names.sort();
+        assert_eq!(names, vec!["e1".to_string(), "e1@app".to_string()]);
+    }
 }
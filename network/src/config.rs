@@ -21,6 +21,8 @@ pub struct Config {
     pub address: String,
     pub port: u16,
     pub bootstrap_addresses: Vec<SocketAddr>,
+    /// The `host:port` names resolving to the nodes to bootstrap from.
+    pub dns_seeds: Vec<String>,
     pub min_peers: usize,
     pub max_peers: usize,
     pub whitelist: Vec<FilterEntry>,
@@ -16,11 +16,15 @@
 
 use crate::addr::SocketAddr;
 use crate::filters::FilterEntry;
+use crate::reputation::PeerReputation;
+use crate::traffic::PeerStats;
 use cidr::IpCidr;
 use ckey::X25519Public as Public;
+use ctypes::NetworkIdentity;
 use std::collections::HashMap;
 use std::net::IpAddr;
 use std::result::Result;
+use std::time::Duration;
 
 pub trait Control: Send + Sync {
     fn local_key_for(&self, address: IpAddr, port: u16) -> Result<Public, Error>;
@@ -48,6 +52,16 @@ pub trait Control: Send + Sync {
     fn get_blacklist(&self) -> Result<(Vec<FilterEntry>, bool), Error>;
 
     fn recent_network_usage(&self) -> Result<HashMap<String, usize>, Error>;
+
+    fn peer_stats(&self) -> Result<Vec<PeerStats>, Error>;
+
+    fn peer_reputations(&self) -> Result<Vec<PeerReputation>, Error>;
+    /// Disconnects the peer and refuses it for the duration, or for the next step of its backoff if it's `None`.
+    fn ban_peer(&self, addr: SocketAddr, duration: Option<Duration>) -> Result<Duration, Error>;
+
+    /// Replaces the validators' network identities the network layer trusts.
+    fn update_validator_address_book(&self, identities: Vec<NetworkIdentity>) -> Result<(), Error>;
+    fn validator_address_book(&self) -> Result<Vec<NetworkIdentity>, Error>;
 }
 
 #[derive(Clone, Debug)]
@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::NodeId;
+use crate::{Misbehavior, NodeId};
 use cio::IoError;
 use ctimer::{TimerScheduleError, TimerToken};
 use primitives::Bytes;
@@ -52,12 +52,23 @@ pub trait Api {
     fn set_timer(&self, timer: TimerToken, d: Duration) -> Result<()>;
     fn set_timer_once(&self, timer: TimerToken, d: Duration) -> Result<()>;
     fn clear_timer(&self, timer: TimerToken) -> Result<()>;
+
+    /// The score of the peer by its recent responses. Peers with lower scores should be requested less.
+    fn peer_score(&self, node: &NodeId) -> i64;
+    /// Raises the score of the peer for answering a request with something useful.
+    fn record_useful_response(&self, node: &NodeId);
+    /// Lowers the reputation and the score of the peer. The peer is disconnected if it gets banned.
+    fn report(&self, node: &NodeId, misbehavior: Misbehavior);
 }
 
 pub trait Extension<Event: Send> {
     fn name() -> &'static str;
     fn need_encryption() -> bool;
     fn versions() -> &'static [u64];
+    /// The protocol of the message, which the traffic statistics are accounted by.
+    fn protocol_of(_message: &[u8]) -> &'static str {
+        Self::name()
+    }
 
     fn on_node_added(&mut self, _node: &NodeId, _version: u64) {}
     fn on_node_removed(&mut self, _node: &NodeId) {}
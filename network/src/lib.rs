@@ -24,14 +24,18 @@ extern crate log;
 extern crate rlp_derive;
 
 mod addr;
+mod address_book;
 mod client;
 mod config;
 mod extension;
 mod filters;
 mod node_id;
+mod reputation;
 mod routing_table;
+mod seeds;
 mod service;
 mod stream;
+mod traffic;
 
 pub mod control;
 mod p2p;
@@ -45,10 +49,12 @@ pub use crate::extension::{
     Api, Error as NetworkExtensionError, Extension as NetworkExtension, Result as NetworkExtensionResult,
 };
 pub use crate::node_id::{IntoSocketAddr, NodeId};
+pub use crate::reputation::{Misbehavior, PeerReputation};
 pub use crate::service::{Error as NetworkServiceError, Service as NetworkService};
 
 pub use crate::filters::{FilterEntry, Filters, FiltersControl};
 pub use crate::routing_table::RoutingTable;
+pub use crate::traffic::{PeerStats, PeerTraffic, Usage as TrafficUsage, TRAFFIC_WINDOWS};
 
 pub type EventSender<E> = crossbeam_channel::Sender<E>;
 pub type EventReceiver<E> = crossbeam_channel::Receiver<E>;
@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::{EstablishedConnection, IncomingMessage, OutgoingMessage, Result};
+use super::{EstablishedConnection, IncomingMessage, NodeProof, OutgoingMessage, Result};
 use crate::session::Session;
 use crate::stream::Stream;
 use crate::SocketAddr;
@@ -46,10 +46,11 @@ impl IncomingConnection {
         Ready::writable() | Ready::readable() | UnixReady::hup()
     }
 
-    pub fn send_ack(&mut self, recipient_pub_key: Public, encrypted_nonce: Bytes) -> usize {
+    pub fn send_ack(&mut self, recipient_pub_key: Public, encrypted_nonce: Bytes, recipient_proof: NodeProof) -> usize {
         self.stream.write(&IncomingMessage::Ack {
             recipient_pub_key,
             encrypted_nonce,
+            recipient_proof,
         })
     }
 
@@ -14,22 +14,58 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use ckey::{NetworkId, X25519Public as Public};
-use primitives::Bytes;
+use ccrypto::blake256;
+use ckey::{
+    sign, verify, Ed25519KeyPair as NodeKeyPair, Ed25519Public as NodeKey, KeyPairTrait, NetworkId, Signature,
+    X25519Public as Public,
+};
+use primitives::{Bytes, H256};
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 
+/// Proves that the sender of a handshake message holds its node key.
+///
+/// It signs the handshake key, which is exchanged for the key of the session.
+/// So the proof can't be replayed on another connection: a replayer doesn't have the private handshake key.
+#[derive(Clone, Debug, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct NodeProof {
+    pub node_key: NodeKey,
+    signature: Signature,
+}
+
+impl NodeProof {
+    pub fn new(handshake_key: &Public, network_id: NetworkId, node_key_pair: &NodeKeyPair) -> Self {
+        Self {
+            node_key: *node_key_pair.public(),
+            signature: sign(proof_message(handshake_key, network_id).as_ref(), node_key_pair.private()),
+        }
+    }
+
+    pub fn verify(&self, handshake_key: &Public, network_id: NetworkId) -> bool {
+        verify(&self.signature, proof_message(handshake_key, network_id).as_ref(), &self.node_key)
+    }
+}
+
+fn proof_message(handshake_key: &Public, network_id: NetworkId) -> H256 {
+    let mut s = RlpStream::new_list(2);
+    s.append(handshake_key);
+    s.append(&network_id);
+    blake256(s.out())
+}
+
 #[derive(Debug, PartialEq)]
 pub enum OutgoingMessage {
     Sync1 {
         initiator_pub_key: Public,
         network_id: NetworkId,
         initiator_port: u16,
+        initiator_proof: NodeProof,
     },
     Sync2 {
         initiator_pub_key: Public,
         recipient_pub_key: Public,
         network_id: NetworkId,
         initiator_port: u16,
+        initiator_proof: NodeProof,
     },
 }
 
@@ -38,6 +74,7 @@ pub enum IncomingMessage {
     Ack {
         recipient_pub_key: Public,
         encrypted_nonce: Bytes,
+        recipient_proof: NodeProof,
     },
     Nack,
 }
@@ -54,21 +91,29 @@ impl Encodable for OutgoingMessage {
                 initiator_pub_key,
                 network_id,
                 initiator_port,
+                initiator_proof,
             } => {
-                s.begin_list(4).append(&SYNC1_ID).append(initiator_pub_key).append(network_id).append(initiator_port);
+                s.begin_list(5)
+                    .append(&SYNC1_ID)
+                    .append(initiator_pub_key)
+                    .append(network_id)
+                    .append(initiator_port)
+                    .append(initiator_proof);
             }
             OutgoingMessage::Sync2 {
                 initiator_pub_key,
                 recipient_pub_key,
                 network_id,
                 initiator_port,
+                initiator_proof,
             } => {
-                s.begin_list(5)
+                s.begin_list(6)
                     .append(&SYNC2_ID)
                     .append(initiator_pub_key)
                     .append(recipient_pub_key)
                     .append(network_id)
-                    .append(initiator_port);
+                    .append(initiator_port)
+                    .append(initiator_proof);
             }
         }
     }
@@ -79,9 +124,9 @@ impl Decodable for OutgoingMessage {
         match rlp.val_at(0)? {
             SYNC1_ID => {
                 let item_count = rlp.item_count()?;
-                if item_count != 4 {
+                if item_count != 5 {
                     return Err(DecoderError::RlpInvalidLength {
-                        expected: 4,
+                        expected: 5,
                         got: item_count,
                     })
                 }
@@ -89,13 +134,14 @@ impl Decodable for OutgoingMessage {
                     initiator_pub_key: rlp.val_at(1)?,
                     network_id: rlp.val_at(2)?,
                     initiator_port: rlp.val_at(3)?,
+                    initiator_proof: rlp.val_at(4)?,
                 })
             }
             SYNC2_ID => {
                 let item_count = rlp.item_count()?;
-                if item_count != 5 {
+                if item_count != 6 {
                     return Err(DecoderError::RlpInvalidLength {
-                        expected: 5,
+                        expected: 6,
                         got: item_count,
                     })
                 }
@@ -104,6 +150,7 @@ impl Decodable for OutgoingMessage {
                     recipient_pub_key: rlp.val_at(2)?,
                     network_id: rlp.val_at(3)?,
                     initiator_port: rlp.val_at(4)?,
+                    initiator_proof: rlp.val_at(5)?,
                 })
             }
             _ => Err(DecoderError::Custom("Invalid id")),
@@ -117,8 +164,13 @@ impl Encodable for IncomingMessage {
             IncomingMessage::Ack {
                 recipient_pub_key,
                 encrypted_nonce,
+                recipient_proof,
             } => {
-                s.begin_list(3).append(&ACK_ID).append(recipient_pub_key).append(encrypted_nonce);
+                s.begin_list(4)
+                    .append(&ACK_ID)
+                    .append(recipient_pub_key)
+                    .append(encrypted_nonce)
+                    .append(recipient_proof);
             }
             IncomingMessage::Nack => {
                 s.begin_list(1).append(&NACK_ID);
@@ -132,15 +184,16 @@ impl Decodable for IncomingMessage {
         match rlp.val_at(0)? {
             ACK_ID => {
                 let item_count = rlp.item_count()?;
-                if item_count != 3 {
+                if item_count != 4 {
                     return Err(DecoderError::RlpInvalidLength {
-                        expected: 3,
+                        expected: 4,
                         got: item_count,
                     })
                 }
                 Ok(IncomingMessage::Ack {
                     recipient_pub_key: rlp.val_at(1)?,
                     encrypted_nonce: rlp.val_at(2)?,
+                    recipient_proof: rlp.val_at(3)?,
                 })
             }
             NACK_ID => {
@@ -163,11 +216,19 @@ mod tests {
     use rlp::rlp_encode_and_decode_test;
 
     use super::*;
+    use ckey::{Generator, Random};
+
+    fn proof(handshake_key: &Public) -> NodeProof {
+        let node_key_pair: NodeKeyPair = Random.generate().unwrap();
+        NodeProof::new(handshake_key, "ab".into(), &node_key_pair)
+    }
 
     #[test]
     fn encode_and_decode_sync1() {
+        let initiator_pub_key = Public::random();
         rlp_encode_and_decode_test!(OutgoingMessage::Sync1 {
-            initiator_pub_key: Public::random(),
+            initiator_proof: proof(&initiator_pub_key),
+            initiator_pub_key,
             network_id: "ab".into(),
             initiator_port: 3100
         });
@@ -175,8 +236,10 @@ mod tests {
 
     #[test]
     fn encode_and_decode_sync2() {
+        let initiator_pub_key = Public::random();
         rlp_encode_and_decode_test!(OutgoingMessage::Sync2 {
-            initiator_pub_key: Public::random(),
+            initiator_proof: proof(&initiator_pub_key),
+            initiator_pub_key,
             recipient_pub_key: Public::random(),
             network_id: "ab".into(),
             initiator_port: 3100
@@ -185,12 +248,23 @@ mod tests {
 
     #[test]
     fn encode_and_decode_ack() {
+        let recipient_pub_key = Public::random();
         rlp_encode_and_decode_test!(IncomingMessage::Ack {
-            recipient_pub_key: Public::random(),
+            recipient_proof: proof(&recipient_pub_key),
+            recipient_pub_key,
             encrypted_nonce: vec![1, 23, 4, 5, 6],
         });
     }
 
+    #[test]
+    fn proof_is_bound_to_the_handshake_key_and_the_network() {
+        let handshake_key = Public::random();
+        let proof = proof(&handshake_key);
+        assert!(proof.verify(&handshake_key, "ab".into()));
+        assert!(!proof.verify(&Public::random(), "ab".into()));
+        assert!(!proof.verify(&handshake_key, "cd".into()));
+    }
+
     #[test]
     fn encode_and_decode_nack() {
         rlp_encode_and_decode_test!(IncomingMessage::Nack);
@@ -27,7 +27,7 @@ use std::result;
 
 pub use self::established::EstablishedConnection;
 pub use self::incoming::IncomingConnection;
-pub use self::message::{IncomingMessage, OutgoingMessage};
+pub use self::message::{IncomingMessage, NodeProof, OutgoingMessage};
 pub use self::outgoing::OutgoingConnection;
 
 use super::super::stream::Error as StreamError;
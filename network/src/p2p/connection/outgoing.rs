@@ -14,12 +14,12 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::{EstablishedConnection, IncomingMessage, OutgoingMessage, Result};
+use super::{EstablishedConnection, IncomingMessage, NodeProof, OutgoingMessage, Result};
 use crate::session::Session;
 use crate::stream::Stream;
 use crate::SocketAddr;
 use cio::IoManager;
-use ckey::{NetworkId, X25519Public as Public};
+use ckey::{Ed25519KeyPair as NodeKeyPair, NetworkId, X25519Public as Public};
 use mio::deprecated::EventLoop;
 use mio::unix::UnixReady;
 use mio::{PollOpt, Ready, Token};
@@ -54,19 +54,22 @@ impl OutgoingConnection {
         Ready::writable() | Ready::readable() | UnixReady::hup()
     }
 
-    pub fn send_sync(&mut self, recipient_pub_key: Option<Public>) -> usize {
+    pub fn send_sync(&mut self, recipient_pub_key: Option<Public>, node_key_pair: &NodeKeyPair) -> usize {
+        let initiator_proof = NodeProof::new(&self.initiator_pub_key, self.network_id, node_key_pair);
         if let Some(recipient_pub_key) = recipient_pub_key {
             self.stream.write(&OutgoingMessage::Sync2 {
                 initiator_pub_key: self.initiator_pub_key.clone(),
                 network_id: self.network_id,
                 initiator_port: self.initiator_port,
                 recipient_pub_key,
+                initiator_proof,
             })
         } else {
             self.stream.write(&OutgoingMessage::Sync1 {
                 initiator_pub_key: self.initiator_pub_key.clone(),
                 network_id: self.network_id,
                 initiator_port: self.initiator_port,
+                initiator_proof,
             })
         }
     }
@@ -15,19 +15,22 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::connection::{
-    EstablishedConnection, IncomingConnection, IncomingMessage, OutgoingConnection, OutgoingMessage,
+    EstablishedConnection, IncomingConnection, IncomingMessage, NodeProof, OutgoingConnection, OutgoingMessage,
 };
 use super::listener::Listener;
 use super::{NegotiationMessage, NetworkMessage};
+use crate::address_book::AddressBook;
 use crate::client::Client;
 use crate::p2p::connection::Error as P2PConnectionError;
+use crate::reputation::Reputation;
+use crate::seeds::Seeds;
 use crate::session::Session;
 use crate::stream::Stream;
 use crate::{FiltersControl, NodeId, RoutingTable, SocketAddr};
 use ccrypto::error::SymmError;
 use cinfo_courier::{Events as InformerEvents, InformerEventSender};
 use cio::{IoChannel, IoContext, IoHandler, IoHandlerResult, IoManager, StreamToken, TimerToken};
-use ckey::NetworkId;
+use ckey::{Ed25519KeyPair as NodeKeyPair, NetworkId, X25519Public};
 use finally_block::finally;
 use mio::deprecated::EventLoop;
 use mio::{PollOpt, Ready, Token};
@@ -36,7 +39,7 @@ use primitives::Bytes;
 use rand::prelude::SliceRandom;
 use rand::rngs::OsRng;
 use rand::Rng;
-use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
 use std::iter::FromIterator;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
@@ -80,9 +83,14 @@ const RETRY_SYNC_MAX: Duration = Duration::from_secs(10); // T1
 const RTT: Duration = Duration::from_secs(10); // T2
 const WAIT_SYNC: Duration = Duration::from_secs(30); // T3 >> T1 + RTT
 
+/// The peers known across restarts, with how reliably they have been reached.
 pub trait ManagingPeerdb: Send + Sync {
-    fn insert(&self, key: SocketAddr);
-    fn delete(&self, key: &SocketAddr);
+    fn on_connected(&self, key: SocketAddr);
+    fn on_disconnected(&self, key: &SocketAddr);
+    /// The peer didn't complete the handshake, e.g. because it is on another network, or it was banned.
+    fn on_failure(&self, key: &SocketAddr);
+    /// The peers worth dialing, from the most reliable one.
+    fn known_peers(&self) -> Vec<SocketAddr>;
 }
 
 pub struct Handler {
@@ -92,6 +100,8 @@ pub struct Handler {
     network_id: NetworkId,
     socket_address: SocketAddr,
     listener: Listener,
+    /// The key the node proves in the handshake, which validators bind to their network identities.
+    node_key_pair: NodeKeyPair,
 
     inbound_connections: RwLock<HashMap<StreamToken, EstablishedConnection>>,
     outbound_connections: RwLock<HashMap<StreamToken, EstablishedConnection>>,
@@ -104,17 +114,22 @@ pub struct Handler {
     outgoing_tokens: Mutex<TokenGenerator>,
 
     establishing_incoming_session: Mutex<HashMap<StreamToken, (u16, Session)>>,
+    /// The incoming connections accepted beyond the peer limit.
+    /// They are kept only if they prove the node key of a validator.
+    awaiting_validator_proof: Mutex<HashSet<StreamToken>>,
     establishing_outgoing_session: Mutex<HashMap<StreamToken, Session>>,
 
     routing_table: Arc<RoutingTable>,
     filters: Arc<dyn FiltersControl>,
+    address_book: Arc<AddressBook>,
+    reputation: Arc<Reputation>,
 
     remote_node_ids: RwLock<HashMap<StreamToken, NodeId>>,
     remote_node_ids_reverse: RwLock<HashMap<NodeId, StreamToken>>,
 
     client: Arc<Client>,
 
-    bootstrap_addresses: Vec<SocketAddr>,
+    seeds: Arc<Seeds>,
 
     network_usage_in_10_seconds: Mutex<HashMap<String, VecDeque<(Instant, usize)>>>,
 
@@ -132,10 +147,13 @@ impl Handler {
         channel: IoChannel<Message>,
         network_id: NetworkId,
         socket_address: SocketAddr,
+        node_key_pair: NodeKeyPair,
         client: Arc<Client>,
         routing_table: Arc<RoutingTable>,
         filters: Arc<dyn FiltersControl>,
-        bootstrap_addresses: Vec<SocketAddr>,
+        address_book: Arc<AddressBook>,
+        reputation: Arc<Reputation>,
+        seeds: Arc<Seeds>,
         min_peers: usize,
         max_peers: usize,
         peer_db: Box<dyn ManagingPeerdb>,
@@ -151,6 +169,7 @@ impl Handler {
             network_id,
             socket_address,
             listener: Listener::bind(&socket_address).expect("Cannot listen TCP port"),
+            node_key_pair,
 
             inbound_connections: Default::default(),
             outbound_connections: Default::default(),
@@ -163,10 +182,13 @@ impl Handler {
             outgoing_tokens: Mutex::new(TokenGenerator::new(FIRST_OUTGOING, MAX_OUTGOING_CONNECTIONS)),
 
             establishing_incoming_session: Default::default(),
+            awaiting_validator_proof: Default::default(),
             establishing_outgoing_session: Default::default(),
 
             routing_table,
             filters,
+            address_book,
+            reputation,
 
             remote_node_ids: Default::default(),
             remote_node_ids_reverse: Default::default(),
@@ -175,7 +197,7 @@ impl Handler {
 
             network_usage_in_10_seconds: Default::default(),
 
-            bootstrap_addresses,
+            seeds,
             min_peers,
             max_peers,
             peer_db,
@@ -211,6 +233,9 @@ impl Handler {
         if !self.filters.is_allowed(&ip) {
             return Err(format!("New connection to {} is requested. But it's not allowed", ip).into())
         }
+        if self.reputation.is_banned(&socket_address) {
+            return Err(format!("New connection to {} is requested. But it's banned", socket_address).into())
+        }
 
         let initiator_pub_key = if let Some(initiator_pub_key) = self.routing_table.local_public(socket_address) {
             initiator_pub_key
@@ -259,6 +284,45 @@ impl Handler {
         }
         result
     }
+
+    /// Checks that the initiator of an incoming connection holds the node key it claims.
+    /// A connection accepted beyond the peer limit must also prove the node key of a validator.
+    fn check_initiator_proof(
+        &self,
+        stream_token: StreamToken,
+        from: &SocketAddr,
+        initiator_pub_key: &X25519Public,
+        initiator_proof: &NodeProof,
+    ) -> Result<(), String> {
+        if !initiator_proof.verify(initiator_pub_key, self.network_id) {
+            return Err(format!("{} failed to prove its node key", from))
+        }
+        if self.awaiting_validator_proof.lock().contains(&stream_token)
+            && self.address_book.validator_of_node(&initiator_proof.node_key).is_none()
+        {
+            return Err(format!("{} is dropped because there are too many connections", from))
+        }
+        Ok(())
+    }
+
+    /// Checks that the recipient of an outgoing connection holds the node key it claims,
+    /// and that it is the validator's node if the address belongs to a validator.
+    fn check_recipient_proof(
+        &self,
+        from: &SocketAddr,
+        recipient_pub_key: &X25519Public,
+        recipient_proof: &NodeProof,
+    ) -> Result<(), String> {
+        if !recipient_proof.verify(recipient_pub_key, self.network_id) {
+            return Err(format!("{} failed to prove its node key", from))
+        }
+        match self.address_book.node_key_at(from) {
+            Some(node_key) if node_key != recipient_proof.node_key => {
+                Err(format!("{} is not the node of the validator registered at the address", from))
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 fn retry_sync_timer(stream: StreamToken) -> TimerToken {
@@ -325,7 +389,10 @@ impl IoHandler<Message> for Handler {
                 };
 
                 let mut candidates = self.routing_table.candidates();
+                candidates.retain(|addr| !self.reputation.is_banned(addr));
                 candidates.shuffle(&mut *self.rng.lock());
+                // The validators are connected first.
+                candidates.sort_by_key(|addr| self.address_book.node_key_at(addr).is_none());
                 for addr in candidates.into_iter().take(self.min_peers - current_connections) {
                     if let Err(err) = self.connect(io, addr) {
                         self.routing_table.remove(&addr);
@@ -356,19 +423,26 @@ impl IoHandler<Message> for Handler {
                     }
                 }
 
-                let mut boots: Vec<_> = self
-                    .bootstrap_addresses
-                    .iter()
+                self.seeds.refresh();
+                let known_peers = self.peer_db.known_peers();
+                let mut seeds = self.seeds.addresses();
+                seeds.retain(|addr| !known_peers.contains(addr));
+                seeds.shuffle(&mut *self.rng.lock());
+                // The peers reached in the earlier runs are tried before the seeds.
+                let boots: Vec<_> = known_peers
+                    .into_iter()
+                    .chain(seeds)
                     .filter(|addr| !self.routing_table.is_establishing_or_established(addr))
                     .filter(|addr| !self.routing_table.is_banned(addr))
+                    .filter(|addr| !self.reputation.is_banned(addr))
                     .filter(|addr| self.filters.is_allowed(&addr.ip()))
                     .collect();
-                boots.shuffle(&mut *self.rng.lock());
                 // It tries to connect to 3 of bootstrap nodes.
                 // FIXME: 3 is a magic number.
                 for addr in boots.into_iter().take(3) {
-                    if let Err(err) = self.connect(io, *addr) {
-                        self.routing_table.remove(addr);
+                    if let Err(err) = self.connect(io, addr) {
+                        self.routing_table.remove(&addr);
+                        self.peer_db.on_failure(&addr);
                         cwarn!(NETWORK, "Cannot connect to bootstrap address {}: {:?}", addr, err);
                     }
                 }
@@ -382,7 +456,11 @@ impl IoHandler<Message> for Handler {
             }
             FIRST_WAIT_ACK..=LAST_WAIT_ACK => {
                 cwarn!(NETWORK, "No ack message from {}", timer);
-                io.deregister_stream(wait_ack_stream(timer));
+                let stream = wait_ack_stream(timer);
+                if let Some(con) = self.outgoing_connections.read().get(&stream) {
+                    self.peer_db.on_failure(con.peer_addr());
+                }
+                io.deregister_stream(stream);
             }
             FIRST_TRY_SYNC..=LAST_TRY_SYNC => {
                 let stream = retry_sync_stream(timer);
@@ -396,7 +474,7 @@ impl IoHandler<Message> for Handler {
                             return Err(err.into())
                         }
                     };
-                    let network_message_size = con.send_sync(maybe_remote_public);
+                    let network_message_size = con.send_sync(maybe_remote_public, &self.node_key_pair);
                     {
                         let mut network_usage_in_10_seconds = self.network_usage_in_10_seconds.lock();
                         insert_network_usage(
@@ -489,6 +567,19 @@ impl IoHandler<Message> for Handler {
                 }
                 self.routing_table.ban(socket_address);
             }
+            Message::Ban(socket_address) => {
+                self.peer_db.on_failure(&socket_address);
+                // The ban covers the IP address, so every connection from it is closed.
+                for addr in self.routing_table.established_addresses() {
+                    if addr.ip() != socket_address.ip() {
+                        continue
+                    }
+                    if let Some(stream) = self.remote_node_ids_reverse.read().get(&addr.into()) {
+                        io.deregister_stream(*stream);
+                        cinfo!(NETWORK, "Disconnect the banned peer {}:{}", addr, stream);
+                    }
+                }
+            }
             Message::ApplyFilters => {
                 for addr in self.routing_table.established_addresses() {
                     if !self.filters.is_allowed(&addr.ip()) {
@@ -507,7 +598,7 @@ impl IoHandler<Message> for Handler {
             } => {
                 let mut inbound_connections = self.inbound_connections.write();
                 let target = connection.peer_addr();
-                self.peer_db.insert(*target);
+                self.peer_db.on_connected(*target);
                 if let Some(token) = self.inbound_tokens.lock().gen() {
                     let remote_node_id = connection.peer_addr().into();
                     assert_eq!(
@@ -539,6 +630,7 @@ impl IoHandler<Message> for Handler {
                 let mut outbound_connections = self.outbound_connections.write();
                 if let Some(token) = self.outbound_tokens.lock().gen() {
                     let peer_addr = *connection.peer_addr();
+                    self.peer_db.on_connected(peer_addr);
                     let remote_node_id = peer_addr.into();
                     assert_eq!(
                         None,
@@ -621,7 +713,7 @@ impl IoHandler<Message> for Handler {
                     io.update_registration(stream_token);
                 });
                 while let Some((stream, socket_address)) = self.listener.accept()? {
-                    let mut incoming_connections = {
+                    let (mut incoming_connections, is_over_limit) = {
                         let inbound_connections = self.inbound_connections.read();
                         let outbound_connections = self.outbound_connections.read();
                         let incoming_connections = self.incoming_connections.write();
@@ -632,7 +724,10 @@ impl IoHandler<Message> for Handler {
                             + incoming_connections.len()
                             + outgoing_connections.len();
 
-                        if self.max_peers < current_connections {
+                        // Beyond the limit, only validators' nodes are accepted.
+                        // They are known after the handshake, where they prove their node keys.
+                        let is_over_limit = self.max_peers < current_connections;
+                        if is_over_limit && self.address_book.identities().is_empty() {
                             cinfo!(
                                 NETWORK,
                                 "New connection from {} is dropped because there are too many connections({} < {})",
@@ -642,7 +737,7 @@ impl IoHandler<Message> for Handler {
                             );
                             return Ok(())
                         }
-                        incoming_connections
+                        (incoming_connections, is_over_limit)
                     };
                     let ip = socket_address.ip();
                     if !self.filters.is_allowed(&ip) {
@@ -657,6 +752,9 @@ impl IoHandler<Message> for Handler {
                     // Please make sure there is no early return after it.
                     let t = incoming_connections.insert(token, IncomingConnection::new(stream));
                     assert!(t.is_none());
+                    if is_over_limit {
+                        self.awaiting_validator_proof.lock().insert(token);
+                    }
                     cinfo!(NETWORK, "New connection from {}({})", socket_address, token);
                     io.register_stream(token);
                     io.register_timer_once(wait_sync_timer(token), WAIT_SYNC);
@@ -808,6 +906,7 @@ impl IoHandler<Message> for Handler {
                             initiator_pub_key,
                             network_id,
                             initiator_port,
+                            initiator_proof,
                         }) => {
                             let from = con.remote_addr(initiator_port)?;
                             if network_id != self.network_id {
@@ -815,11 +914,20 @@ impl IoHandler<Message> for Handler {
                                 should_update.store(false, Ordering::SeqCst);
                                 return Err(format!("An invalid network id({}) from {}", network_id, from).into())
                             }
+                            if let Err(err) =
+                                self.check_initiator_proof(stream_token, &from, &initiator_pub_key, &initiator_proof)
+                            {
+                                io.deregister_stream(stream_token);
+                                should_update.store(false, Ordering::SeqCst);
+                                return Err(err.into())
+                            }
                             let network_message_size = if let Some((encrypted_nonce, local_public, session)) =
                                 self.routing_table.set_recipient_establish1(from, initiator_pub_key)?
                             {
                                 cinfo!(NETWORK, "Send ack to {}", from);
-                                let network_message_size = con.send_ack(local_public, encrypted_nonce);
+                                let recipient_proof =
+                                    NodeProof::new(&local_public, self.network_id, &self.node_key_pair);
+                                let network_message_size = con.send_ack(local_public, encrypted_nonce, recipient_proof);
                                 let t = self
                                     .establishing_incoming_session
                                     .lock()
@@ -846,6 +954,7 @@ impl IoHandler<Message> for Handler {
                             recipient_pub_key,
                             network_id,
                             initiator_port,
+                            initiator_proof,
                         }) => {
                             let from = con.remote_addr(initiator_port)?;
                             if network_id != self.network_id {
@@ -853,12 +962,21 @@ impl IoHandler<Message> for Handler {
                                 io.deregister_stream(stream_token);
                                 return Err(format!("An invalid network id({}) from {}", network_id, from).into())
                             }
+                            if let Err(err) =
+                                self.check_initiator_proof(stream_token, &from, &initiator_pub_key, &initiator_proof)
+                            {
+                                should_update.store(false, Ordering::SeqCst);
+                                io.deregister_stream(stream_token);
+                                return Err(err.into())
+                            }
                             let network_message_size = if let Some((encrypted_nonce, local_public, session)) = self
                                 .routing_table
                                 .set_recipient_establish2(from, recipient_pub_key, initiator_pub_key)?
                             {
                                 cinfo!(NETWORK, "Send ack to {}", from);
-                                let network_message_size = con.send_ack(local_public, encrypted_nonce);
+                                let recipient_proof =
+                                    NodeProof::new(&local_public, self.network_id, &self.node_key_pair);
+                                let network_message_size = con.send_ack(local_public, encrypted_nonce, recipient_proof);
                                 let t = self
                                     .establishing_incoming_session
                                     .lock()
@@ -908,7 +1026,13 @@ impl IoHandler<Message> for Handler {
                         Some(IncomingMessage::Ack {
                             recipient_pub_key,
                             encrypted_nonce,
+                            recipient_proof,
                         }) => {
+                            if let Err(err) = self.check_recipient_proof(&from, &recipient_pub_key, &recipient_proof) {
+                                should_update.store(false, Ordering::SeqCst);
+                                io.deregister_stream(stream_token);
+                                return Err(err.into())
+                            }
                             let session = self.routing_table.set_initiator_establish(
                                 from,
                                 recipient_pub_key,
@@ -1117,8 +1241,7 @@ impl IoHandler<Message> for Handler {
                         unreachable!("{} has no node id", stream);
                     }
                     con.deregister(event_loop)?;
-                    let remove_target = con.peer_addr();
-                    self.peer_db.delete(&remove_target);
+                    self.peer_db.on_disconnected(con.peer_addr());
                     self.routing_table.remove(con.peer_addr());
                     self.inbound_tokens.lock().restore(stream);
                     ctrace!(NETWORK, "Inbound connect({}) removed", stream);
@@ -1136,8 +1259,7 @@ impl IoHandler<Message> for Handler {
                         unreachable!("{} has no node id", stream);
                     }
                     con.deregister(event_loop)?;
-                    let remove_target = con.peer_addr();
-                    self.peer_db.delete(&remove_target);
+                    self.peer_db.on_disconnected(con.peer_addr());
                     self.routing_table.remove(con.peer_addr());
                     self.outbound_tokens.lock().restore(stream);
                     ctrace!(NETWORK, "Outbound connect({}) removed", stream);
@@ -1150,6 +1272,7 @@ impl IoHandler<Message> for Handler {
                 if let Some(con) = incoming_connections.remove(&stream) {
                     con.deregister(event_loop)?;
                     self.incoming_tokens.lock().restore(stream);
+                    self.awaiting_validator_proof.lock().remove(&stream);
                     if let Some((port, session)) = self.establishing_incoming_session.lock().remove(&stream) {
                         let connection = con.establish(session, port)?;
                         {
@@ -1161,6 +1284,13 @@ impl IoHandler<Message> for Handler {
                                 )
                                 .into())
                             }
+                            if self.reputation.is_banned(peer_addr) {
+                                return Err(format!(
+                                    "Incoming connection from {} cannot be established because it's banned",
+                                    peer_addr
+                                )
+                                .into())
+                            }
                         }
                         self.channel.send(Message::Established {
                             connection,
@@ -1227,6 +1357,9 @@ pub enum Message {
         data: Arc<Bytes>,
     },
     Disconnect(SocketAddr),
+    /// Disconnects the peers at the IP address without touching the routing table.
+    /// The reputation refuses the address until the ban expires.
+    Ban(SocketAddr),
     ApplyFilters,
     Established {
         connection: EstablishedConnection,
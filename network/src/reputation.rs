@@ -0,0 +1,261 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::SocketAddr;
+use parking_lot::Mutex;
+use std::cmp;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+/// A peer is banned when its score falls to this.
+const BAN_THRESHOLD: i64 = -100;
+/// The points a peer earns back per minute of good behavior, up to 0.
+const RECOVERY_PER_MINUTE: i64 = 10;
+/// The length of the first ban. It doubles on each subsequent ban.
+const BASE_BAN: Duration = Duration::from_secs(60);
+const MAX_BAN: Duration = Duration::from_secs(24 * 60 * 60);
+/// A record is forgotten once its peer has behaved for this long after its last report or ban.
+const RECORD_LIFETIME: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Misbehavior {
+    InvalidBlock,
+    InvalidTransaction,
+    Timeout,
+    ProtocolViolation,
+}
+
+impl Misbehavior {
+    fn penalty(self) -> i64 {
+        match self {
+            Misbehavior::InvalidBlock => 50,
+            Misbehavior::InvalidTransaction => 10,
+            Misbehavior::Timeout => 5,
+            Misbehavior::ProtocolViolation => 100,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct PeerReputation {
+    pub address: IpAddr,
+    pub score: i64,
+    pub bans: u32,
+    /// The remaining time of the current ban.
+    pub banned_for: Option<Duration>,
+}
+
+struct Record {
+    score: i64,
+    updated_at: Instant,
+    /// When the peer was last reported or banned.
+    reported_at: Instant,
+    bans: u32,
+    banned_until: Option<Instant>,
+}
+
+impl Record {
+    fn new(now: Instant) -> Self {
+        Self {
+            score: 0,
+            updated_at: now,
+            reported_at: now,
+            bans: 0,
+            banned_until: None,
+        }
+    }
+
+    fn is_expired(&self, now: Instant) -> bool {
+        let last_seen = self.banned_until.map_or(self.reported_at, |until| cmp::max(until, self.reported_at));
+        now.saturating_duration_since(last_seen) >= RECORD_LIFETIME
+    }
+
+    fn recover(&mut self, now: Instant) {
+        let minutes = now.saturating_duration_since(self.updated_at).as_secs() / 60;
+        if minutes == 0 {
+            return
+        }
+        self.score = cmp::min(0, self.score + minutes as i64 * RECOVERY_PER_MINUTE);
+        self.updated_at += Duration::from_secs(minutes * 60);
+    }
+
+    fn is_banned(&self, now: Instant) -> bool {
+        self.banned_until.map_or(false, |until| now < until)
+    }
+
+    /// Bans the peer for the given duration, or for the next step of the backoff.
+    fn ban(&mut self, now: Instant, duration: Option<Duration>) -> Duration {
+        let duration = duration.unwrap_or_else(|| {
+            let backoff = BASE_BAN.checked_mul(1 << cmp::min(self.bans, 16)).unwrap_or(MAX_BAN);
+            cmp::min(backoff, MAX_BAN)
+        });
+        self.bans += 1;
+        self.score = 0;
+        self.updated_at = now;
+        self.reported_at = now;
+        self.banned_until = Some(now + duration);
+        duration
+    }
+}
+
+/// Scores peers by their misbehaviors and bans the ones scoring too low.
+///
+/// Peers are told apart by their IP addresses, so that a peer can't escape its record
+/// by reconnecting from another port.
+#[derive(Default)]
+pub struct Reputation {
+    records: Mutex<HashMap<IpAddr, Record>>,
+}
+
+impl Reputation {
+    /// Returns the duration of the ban if the misbehavior gets the peer banned.
+    pub fn report(&self, peer: SocketAddr, misbehavior: Misbehavior) -> Option<Duration> {
+        self.report_at(Instant::now(), peer, misbehavior)
+    }
+
+    fn report_at(&self, now: Instant, peer: SocketAddr, misbehavior: Misbehavior) -> Option<Duration> {
+        let mut records = self.records.lock();
+        records.retain(|_, record| !record.is_expired(now));
+        let record = records.entry(peer.ip()).or_insert_with(|| Record::new(now));
+        if record.is_banned(now) {
+            return None
+        }
+        record.recover(now);
+        record.reported_at = now;
+        record.score -= misbehavior.penalty();
+        if record.score > BAN_THRESHOLD {
+            return None
+        }
+        Some(record.ban(now, None))
+    }
+
+    /// Bans the peer for the duration, or for the next step of its backoff if it's `None`.
+    pub fn ban(&self, peer: SocketAddr, duration: Option<Duration>) -> Duration {
+        let now = Instant::now();
+        let mut records = self.records.lock();
+        records.entry(peer.ip()).or_insert_with(|| Record::new(now)).ban(now, duration)
+    }
+
+    pub fn is_banned(&self, peer: &SocketAddr) -> bool {
+        self.records.lock().get(&peer.ip()).map_or(false, |record| record.is_banned(Instant::now()))
+    }
+
+    pub fn reputations(&self) -> Vec<PeerReputation> {
+        self.reputations_at(Instant::now())
+    }
+
+    fn reputations_at(&self, now: Instant) -> Vec<PeerReputation> {
+        let mut records = self.records.lock();
+        records.retain(|_, record| !record.is_expired(now));
+        records
+            .iter_mut()
+            .map(|(address, record)| {
+                record.recover(now);
+                PeerReputation {
+                    address: *address,
+                    score: record.score,
+                    bans: record.bans,
+                    banned_for: record.banned_until.filter(|until| now < *until).map(|until| until - now),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    fn peer(host: u8) -> SocketAddr {
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, host)), 3485)
+    }
+
+    fn peer_at_port(host: u8, port: u16) -> SocketAddr {
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, host)), port)
+    }
+
+    #[test]
+    fn misbehaving_peers_are_banned() {
+        let reputation = Reputation::default();
+        let now = Instant::now();
+        assert_eq!(None, reputation.report_at(now, peer(1), Misbehavior::InvalidBlock));
+        assert_eq!(None, reputation.report_at(now, peer(2), Misbehavior::Timeout));
+        assert_eq!(Some(BASE_BAN), reputation.report_at(now, peer(1), Misbehavior::InvalidBlock));
+
+        assert!(reputation.is_banned(&peer(1)));
+        assert!(!reputation.is_banned(&peer(2)));
+
+        let mut reputations = reputation.reputations_at(now);
+        reputations.sort_by_key(|reputation| reputation.address);
+        assert_eq!(
+            PeerReputation {
+                address: peer(1).ip(),
+                score: 0,
+                bans: 1,
+                banned_for: Some(BASE_BAN),
+            },
+            reputations[0]
+        );
+        assert_eq!(-5, reputations[1].score);
+    }
+
+    #[test]
+    fn bans_back_off_exponentially() {
+        let reputation = Reputation::default();
+        let mut now = Instant::now();
+        let mut expected = BASE_BAN;
+        for _ in 0..3 {
+            assert_eq!(Some(expected), reputation.report_at(now, peer(1), Misbehavior::ProtocolViolation));
+            now += expected;
+            expected *= 2;
+        }
+        assert_eq!(MAX_BAN, reputation.ban(peer(1), Some(MAX_BAN)));
+    }
+
+    #[test]
+    fn scores_recover_over_time() {
+        let reputation = Reputation::default();
+        let now = Instant::now();
+        reputation.report_at(now, peer(1), Misbehavior::InvalidBlock);
+        assert_eq!(-50, reputation.reputations_at(now)[0].score);
+        assert_eq!(-30, reputation.reputations_at(now + Duration::from_secs(120))[0].score);
+        assert_eq!(0, reputation.reputations_at(now + Duration::from_secs(3600))[0].score);
+    }
+
+    #[test]
+    fn records_are_kept_per_ip() {
+        let reputation = Reputation::default();
+        let now = Instant::now();
+        reputation.report_at(now, peer_at_port(1, 1000), Misbehavior::InvalidBlock);
+        assert_eq!(Some(BASE_BAN), reputation.report_at(now, peer_at_port(1, 2000), Misbehavior::InvalidBlock));
+        assert!(reputation.is_banned(&peer_at_port(1, 3000)));
+        assert_eq!(1, reputation.reputations_at(now).len());
+    }
+
+    #[test]
+    fn records_expire() {
+        let reputation = Reputation::default();
+        let now = Instant::now();
+        reputation.report_at(now, peer(1), Misbehavior::Timeout);
+        reputation.report_at(now, peer(2), Misbehavior::ProtocolViolation);
+
+        let later = now + RECORD_LIFETIME;
+        assert_eq!(1, reputation.reputations_at(later).len());
+        assert!(reputation.reputations_at(later + BASE_BAN).is_empty());
+    }
+}
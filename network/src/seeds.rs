@@ -0,0 +1,111 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::SocketAddr;
+use parking_lot::{Mutex, RwLock};
+use std::net::ToSocketAddrs;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long the addresses resolved from the DNS seeds are used before they are resolved again.
+const RESOLVE_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
+/// The addresses a node dials to join the network:
+/// the bootstrap addresses given by the operator and the nodes listed by the DNS seeds.
+pub struct Seeds {
+    bootstrap_addresses: Vec<SocketAddr>,
+    /// The `host:port` names whose A records are the addresses of the nodes.
+    dns_seeds: Vec<String>,
+    resolved: RwLock<Vec<SocketAddr>>,
+    /// When the last resolution started, if any.
+    resolved_at: Mutex<Option<Instant>>,
+}
+
+impl Seeds {
+    pub fn new(bootstrap_addresses: Vec<SocketAddr>, dns_seeds: Vec<String>) -> Self {
+        Self {
+            bootstrap_addresses,
+            dns_seeds,
+            resolved: Default::default(),
+            resolved_at: Default::default(),
+        }
+    }
+
+    pub fn addresses(&self) -> Vec<SocketAddr> {
+        let mut addresses = self.bootstrap_addresses.clone();
+        for address in self.resolved.read().iter() {
+            if !addresses.contains(address) {
+                addresses.push(*address);
+            }
+        }
+        addresses
+    }
+
+    /// Resolves the DNS seeds on another thread unless they were resolved recently.
+    pub fn refresh(self: &Arc<Self>) {
+        if self.dns_seeds.is_empty() {
+            return
+        }
+        {
+            let mut resolved_at = self.resolved_at.lock();
+            let now = Instant::now();
+            if resolved_at.map_or(false, |at| now.saturating_duration_since(at) < RESOLVE_INTERVAL) {
+                return
+            }
+            *resolved_at = Some(now);
+        }
+        let seeds = Arc::clone(self);
+        let spawned = thread::Builder::new().name("DNS seeds".to_string()).spawn(move || {
+            let resolved = seeds.dns_seeds.iter().flat_map(|seed| resolve(seed)).collect::<Vec<_>>();
+            cinfo!(NETWORK, "{} nodes are found from the DNS seeds", resolved.len());
+            *seeds.resolved.write() = resolved;
+        });
+        if let Err(err) = spawned {
+            cwarn!(NETWORK, "Cannot resolve the DNS seeds: {}", err);
+            *self.resolved_at.lock() = None;
+        }
+    }
+}
+
+fn resolve(seed: &str) -> Vec<SocketAddr> {
+    match seed.to_socket_addrs() {
+        // The network layer doesn't support IPv6 yet.
+        Ok(addresses) => addresses.filter(|address| address.is_ipv4()).map(SocketAddr::from).collect(),
+        Err(err) => {
+            cwarn!(NETWORK, "Cannot resolve the DNS seed {}: {}", seed, err);
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolved_addresses_follow_the_bootstrap_addresses() {
+        let seeds = Seeds::new(vec![SocketAddr::v4(10, 0, 0, 1, 3485)], Vec::new());
+        *seeds.resolved.write() = vec![SocketAddr::v4(10, 0, 0, 2, 3485), SocketAddr::v4(10, 0, 0, 1, 3485)];
+        assert_eq!(vec![SocketAddr::v4(10, 0, 0, 1, 3485), SocketAddr::v4(10, 0, 0, 2, 3485)], seeds.addresses());
+    }
+
+    #[test]
+    fn seeds_resolve_to_ipv4_addresses() {
+        assert_eq!(vec![SocketAddr::v4(127, 0, 0, 1, 3485)], resolve("127.0.0.1:3485"));
+        assert!(resolve("seed.invalid").is_empty());
+    }
+}
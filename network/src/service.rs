@@ -14,27 +14,38 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::address_book::AddressBook;
 use crate::client::Client;
 use crate::control::{Control, Error as ControlError};
 use crate::filters::{FilterEntry, FiltersControl};
+use crate::reputation::{PeerReputation, Reputation};
 use crate::routing_table::RoutingTable;
+use crate::seeds::Seeds;
+use crate::traffic::{PeerStats, Traffic};
 use crate::{p2p, Api, ManagingPeerdb, NetworkExtension, SocketAddr};
 use cidr::IpCidr;
 use cinfo_courier::InformerEventSender;
 use cio::{IoError, IoService};
-use ckey::{NetworkId, X25519Public as Public};
+use ckey::{Ed25519KeyPair as NodeKeyPair, NetworkId, X25519Public as Public};
 use crossbeam_channel::Sender;
 use ctimer::TimerLoop;
+use ctypes::NetworkIdentity;
 use std::collections::HashMap;
 use std::net::IpAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
 pub struct Service {
-    p2p: IoService<p2p::Message>,
+    p2p: Arc<IoService<p2p::Message>>,
     client: Arc<Client>,
     routing_table: Arc<RoutingTable>,
     p2p_handler: Arc<p2p::Handler>,
     filters_control: Arc<dyn FiltersControl>,
+    traffic: Arc<Traffic>,
+    reputation: Arc<Reputation>,
+    address_book: Arc<AddressBook>,
+    /// The chain whose extensions this service registers. `None` for the main chain.
+    namespace: Option<String>,
 }
 
 impl Service {
@@ -42,7 +53,9 @@ impl Service {
         network_id: NetworkId,
         timer_loop: TimerLoop,
         address: SocketAddr,
+        node_key_pair: NodeKeyPair,
         bootstrap_addresses: Vec<SocketAddr>,
+        dns_seeds: Vec<String>,
         min_peers: usize,
         max_peers: usize,
         filters_control: Arc<dyn FiltersControl>,
@@ -52,16 +65,22 @@ impl Service {
     ) -> Result<Arc<Self>, Error> {
         let p2p = IoService::start("P2P")?;
 
-        let client = Client::new(p2p.channel(), timer_loop);
+        let traffic = Arc::new(Traffic::default());
+        let reputation = Arc::new(Reputation::default());
+        let client = Client::new(p2p.channel(), timer_loop, Arc::clone(&traffic), Arc::clone(&reputation));
+        let address_book = Arc::new(AddressBook::default());
 
         let p2p_handler = Arc::new(p2p::Handler::try_new(
             p2p.channel(),
             network_id,
             address,
+            node_key_pair,
             Arc::clone(&client),
             Arc::clone(&routing_table),
             Arc::clone(&filters_control),
-            bootstrap_addresses,
+            Arc::clone(&address_book),
+            Arc::clone(&reputation),
+            Arc::new(Seeds::new(bootstrap_addresses, dns_seeds)),
             min_peers,
             max_peers,
             peer_db,
@@ -70,20 +89,41 @@ impl Service {
         p2p.register_handler(p2p_handler.clone())?;
 
         Ok(Arc::new(Self {
-            p2p,
+            p2p: Arc::new(p2p),
             client,
             routing_table,
             p2p_handler,
             filters_control,
+            traffic,
+            reputation,
+            address_book,
+            namespace: None,
         }))
     }
 
+    /// Returns the service of an app-chain, which registers the extensions as `<name>@<namespace>`.
+    /// It shares the peers of this service, and a peer talks to the extensions of the app-chain
+    /// only if it runs the app-chain under the same namespace.
+    pub fn in_namespace(&self, namespace: &str) -> Arc<Self> {
+        Arc::new(Self {
+            p2p: Arc::clone(&self.p2p),
+            client: Arc::clone(&self.client),
+            routing_table: Arc::clone(&self.routing_table),
+            p2p_handler: Arc::clone(&self.p2p_handler),
+            filters_control: Arc::clone(&self.filters_control),
+            traffic: Arc::clone(&self.traffic),
+            reputation: Arc::clone(&self.reputation),
+            address_book: Arc::clone(&self.address_book),
+            namespace: Some(namespace.to_string()),
+        })
+    }
+
     pub fn register_extension<T, E, F>(&self, factory: F) -> Sender<E>
     where
         T: 'static + Sized + NetworkExtension<E>,
         E: 'static + Sized + Send,
         F: 'static + FnOnce(Box<dyn Api>) -> T + Send, {
-        self.client.register_extension(factory)
+        self.client.register_extension_in(self.namespace.as_deref(), factory)
     }
 
     pub fn connect_to(&self, address: SocketAddr) -> Result<(), String> {
@@ -204,6 +244,34 @@ impl Control for Service {
     fn recent_network_usage(&self) -> Result<HashMap<String, usize>, ControlError> {
         Ok(self.p2p_handler.recent_network_usage())
     }
+
+    fn peer_stats(&self) -> Result<Vec<PeerStats>, ControlError> {
+        Ok(self.traffic.peer_stats())
+    }
+
+    fn peer_reputations(&self) -> Result<Vec<PeerReputation>, ControlError> {
+        Ok(self.reputation.reputations())
+    }
+
+    fn ban_peer(&self, addr: SocketAddr, duration: Option<Duration>) -> Result<Duration, ControlError> {
+        let duration = self.reputation.ban(addr, duration);
+        cinfo!(NETWORK, "{} is banned for {:?} by request", addr, duration);
+        if let Err(err) = self.p2p.send_message(p2p::Message::Ban(addr)) {
+            cerror!(NETWORK, "Error occurred while sending message Ban: {:?}", err);
+        }
+        Ok(duration)
+    }
+
+    fn update_validator_address_book(&self, identities: Vec<NetworkIdentity>) -> Result<(), ControlError> {
+        self.address_book.update(identities);
+        // Makes the validators candidates of the connections.
+        self.routing_table.touch_addresses(self.address_book.addresses());
+        Ok(())
+    }
+
+    fn validator_address_book(&self) -> Result<Vec<NetworkIdentity>, ControlError> {
+        Ok(self.address_book.identities())
+    }
 }
 
 #[derive(Debug)]
@@ -0,0 +1,310 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::SocketAddr;
+use parking_lot::Mutex;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// The windows over which the traffic of each peer is reported.
+pub const TRAFFIC_WINDOWS: [Duration; 3] = [Duration::from_secs(10), Duration::from_secs(60), Duration::from_secs(600)];
+
+/// The window used to score peers.
+const SCORE_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Usage {
+    pub bytes: usize,
+    pub messages: usize,
+}
+
+impl Usage {
+    fn add(&mut self, other: &Usage) {
+        self.bytes += other.bytes;
+        self.messages += other.messages;
+    }
+}
+
+/// The traffic exchanged with a peer in a protocol.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PeerTraffic {
+    pub sent: Usage,
+    pub received: Usage,
+}
+
+impl PeerTraffic {
+    fn add(&mut self, other: &PeerTraffic) {
+        self.sent.add(&other.sent);
+        self.received.add(&other.received);
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct PeerStats {
+    pub address: SocketAddr,
+    pub score: i64,
+    /// The traffic per protocol in each of `TRAFFIC_WINDOWS`.
+    pub windows: Vec<(Duration, HashMap<&'static str, PeerTraffic>)>,
+}
+
+/// Per-second buckets of the traffic, the oldest first.
+#[derive(Default)]
+struct Meter {
+    buckets: VecDeque<(Instant, PeerTraffic)>,
+}
+
+impl Meter {
+    fn record(&mut self, now: Instant, traffic: PeerTraffic) {
+        self.remove_outdated(now);
+        match self.buckets.back_mut() {
+            Some((started_at, bucket)) if now.duration_since(*started_at) < Duration::from_secs(1) => {
+                bucket.add(&traffic)
+            }
+            _ => self.buckets.push_back((now, traffic)),
+        }
+    }
+
+    fn remove_outdated(&mut self, now: Instant) {
+        let longest = TRAFFIC_WINDOWS[TRAFFIC_WINDOWS.len() - 1];
+        while let Some((started_at, _)) = self.buckets.front() {
+            if now.duration_since(*started_at) < longest {
+                break
+            }
+            self.buckets.pop_front();
+        }
+    }
+
+    fn sum(&self, now: Instant, window: Duration) -> PeerTraffic {
+        let mut total = PeerTraffic::default();
+        for (_, bucket) in
+            self.buckets.iter().rev().take_while(|(started_at, _)| now.duration_since(*started_at) < window)
+        {
+            total.add(bucket);
+        }
+        total
+    }
+}
+
+/// The outcomes of the recent requests to a peer, the oldest first.
+/// `true` is a useful response, and `false` is a failure such as a timeout or an invalid response.
+#[derive(Default)]
+struct Responses {
+    outcomes: VecDeque<(Instant, bool)>,
+}
+
+impl Responses {
+    fn record(&mut self, now: Instant, useful: bool) {
+        self.remove_outdated(now);
+        self.outcomes.push_back((now, useful));
+    }
+
+    fn remove_outdated(&mut self, now: Instant) {
+        while let Some((at, _)) = self.outcomes.front() {
+            if now.duration_since(*at) < SCORE_WINDOW {
+                break
+            }
+            self.outcomes.pop_front();
+        }
+    }
+
+    /// The number of useful responses per 100 requests answered or failed, from -100 to 100.
+    /// A peer which is never requested scores 0.
+    fn score(&self, now: Instant) -> i64 {
+        let (useful, failed) = self.outcomes.iter().filter(|(at, _)| now.duration_since(*at) < SCORE_WINDOW).fold(
+            (0i64, 0i64),
+            |(useful, failed), (_, is_useful)| {
+                if *is_useful {
+                    (useful + 1, failed)
+                } else {
+                    (useful, failed + 1)
+                }
+            },
+        );
+        if useful + failed == 0 {
+            return 0
+        }
+        (useful - failed) * 100 / (useful + failed)
+    }
+}
+
+/// Accounts the bytes and the messages exchanged with each peer per protocol,
+/// and scores the peers by the outcomes of the requests sent to them.
+#[derive(Default)]
+pub struct Traffic {
+    meters: Mutex<HashMap<SocketAddr, HashMap<&'static str, Meter>>>,
+    responses: Mutex<HashMap<SocketAddr, Responses>>,
+}
+
+impl Traffic {
+    pub fn record_sent(&self, peer: SocketAddr, protocol: &'static str, bytes: usize) {
+        self.record(peer, protocol, PeerTraffic {
+            sent: Usage {
+                bytes,
+                messages: 1,
+            },
+            ..Default::default()
+        })
+    }
+
+    pub fn record_received(&self, peer: SocketAddr, protocol: &'static str, bytes: usize) {
+        self.record(peer, protocol, PeerTraffic {
+            received: Usage {
+                bytes,
+                messages: 1,
+            },
+            ..Default::default()
+        })
+    }
+
+    fn record(&self, peer: SocketAddr, protocol: &'static str, traffic: PeerTraffic) {
+        let mut meters = self.meters.lock();
+        meters.entry(peer).or_default().entry(protocol).or_default().record(Instant::now(), traffic);
+    }
+
+    /// Records that the peer answered a request with something useful.
+    pub fn record_useful_response(&self, peer: SocketAddr) {
+        self.responses.lock().entry(peer).or_default().record(Instant::now(), true)
+    }
+
+    /// Records that a request to the peer timed out or was answered with an invalid response.
+    pub fn record_failure(&self, peer: SocketAddr) {
+        self.responses.lock().entry(peer).or_default().record(Instant::now(), false)
+    }
+
+    /// Scores a peer by its useful responses against its failures in the recent past.
+    /// Peers without recent requests score 0, and a peer failing more than it helps scores negative.
+    pub fn score(&self, peer: &SocketAddr) -> i64 {
+        self.responses.lock().get(peer).map_or(0, |responses| responses.score(Instant::now()))
+    }
+
+    /// Forgets the disconnected peer.
+    pub fn remove(&self, peer: &SocketAddr) {
+        self.meters.lock().remove(peer);
+        self.responses.lock().remove(peer);
+    }
+
+    pub fn peer_stats(&self) -> Vec<PeerStats> {
+        let mut meters = self.meters.lock();
+        let now = Instant::now();
+        for protocols in meters.values_mut() {
+            for meter in protocols.values_mut() {
+                meter.remove_outdated(now);
+            }
+            protocols.retain(|_, meter| !meter.buckets.is_empty());
+        }
+        meters.retain(|_, protocols| !protocols.is_empty());
+        let mut responses = self.responses.lock();
+        for outcomes in responses.values_mut() {
+            outcomes.remove_outdated(now);
+        }
+        responses.retain(|_, outcomes| !outcomes.outcomes.is_empty());
+
+        meters
+            .iter()
+            .map(|(address, protocols)| {
+                let windows = TRAFFIC_WINDOWS
+                    .iter()
+                    .map(|window| {
+                        let traffic = protocols
+                            .iter()
+                            .map(|(protocol, meter)| (*protocol, meter.sum(now, *window)))
+                            .filter(|(_, traffic)| *traffic != PeerTraffic::default())
+                            .collect();
+                        (*window, traffic)
+                    })
+                    .collect();
+                PeerStats {
+                    address: *address,
+                    score: responses.get(address).map_or(0, |responses| responses.score(now)),
+                    windows,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn peer(port: u16) -> SocketAddr {
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port)
+    }
+
+    #[test]
+    fn traffic_is_accounted_per_peer_and_protocol() {
+        let traffic = Traffic::default();
+        traffic.record_sent(peer(1), "headers", 10);
+        traffic.record_received(peer(1), "headers", 100);
+        traffic.record_received(peer(1), "headers", 50);
+        traffic.record_sent(peer(1), "transactions", 7);
+        traffic.record_sent(peer(2), "bodies", 3);
+
+        let mut stats = traffic.peer_stats();
+        stats.sort_by_key(|stats| stats.address.port());
+        assert_eq!(2, stats.len());
+
+        let (window, protocols) = &stats[0].windows[0];
+        assert_eq!(TRAFFIC_WINDOWS[0], *window);
+        assert_eq!(
+            PeerTraffic {
+                sent: Usage {
+                    bytes: 10,
+                    messages: 1,
+                },
+                received: Usage {
+                    bytes: 150,
+                    messages: 2,
+                },
+            },
+            protocols["headers"]
+        );
+        assert_eq!(7, protocols["transactions"].sent.bytes);
+        assert!(!stats[1].windows[0].1.contains_key("headers"));
+    }
+
+    #[test]
+    fn failing_peers_score_lower() {
+        let traffic = Traffic::default();
+        assert_eq!(0, traffic.score(&peer(1)));
+
+        // Chatty peers gain nothing from the messages alone.
+        traffic.record_received(peer(1), "bodies", 1000);
+        traffic.record_received(peer(1), "bodies", 1000);
+        assert_eq!(0, traffic.score(&peer(1)));
+
+        traffic.record_useful_response(peer(1));
+        traffic.record_useful_response(peer(1));
+        traffic.record_useful_response(peer(1));
+        traffic.record_failure(peer(1));
+        traffic.record_failure(peer(2));
+
+        assert_eq!(50, traffic.score(&peer(1)));
+        assert_eq!(-100, traffic.score(&peer(2)));
+    }
+
+    #[test]
+    fn removed_peers_are_forgotten() {
+        let traffic = Traffic::default();
+        traffic.record_sent(peer(1), "bodies", 10);
+        traffic.record_failure(peer(1));
+
+        traffic.remove(&peer(1));
+        assert_eq!(0, traffic.score(&peer(1)));
+        assert!(traffic.peer_stats().is_empty());
+    }
+}
@@ -37,6 +37,7 @@ pub use jsonrpc_http_server::Server as HttpServer;
 pub use jsonrpc_ipc_server::Server as IpcServer;
 pub use jsonrpc_ws_server::{Error as WsError, Server as WsServer};
 pub use rpc_server::start_http;
+pub use rpc_server::start_http_with_chains;
 pub use rpc_server::start_ipc;
 pub use rpc_server::start_ws;
 pub use rustc_serialize::hex;
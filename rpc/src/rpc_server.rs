@@ -15,12 +15,20 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 // TODO: panic handler
-use jsonrpc_http_server::{Host, Server as HttpServer, ServerBuilder as HttpServerBuilder};
+use jsonrpc_core::futures::{future, Future, Stream};
+use jsonrpc_http_server::{
+    hyper, Host, RequestMiddlewareAction, Server as HttpServer, ServerBuilder as HttpServerBuilder,
+};
 use jsonrpc_ipc_server::{Server as IpcServer, ServerBuilder as IpcServerBuilder};
 use jsonrpc_ws_server::{Error as WsError, Server as WsServer, ServerBuilder as WsServerBuilder};
+use std::collections::HashMap;
 use std::default::Default;
 use std::io;
 use std::net::SocketAddr;
+use std::sync::Arc;
+
+/// The threads of the HTTP server, so that a request waiting for a transaction doesn't hold up the others.
+const HTTP_SERVER_THREADS: usize = 4;
 
 /// Start http server asynchronously and returns result with `Server` handle on success or an error.
 pub fn start_http<M: jsonrpc_core::Metadata>(
@@ -31,6 +39,20 @@ pub fn start_http<M: jsonrpc_core::Metadata>(
 ) -> Result<HttpServer, io::Error>
 where
     M: Default, {
+    start_http_with_chains(addr, cors_domains, allowed_hosts, handler, HashMap::new())
+}
+
+/// Start http server serving `handler` at the root path and the handler of each app-chain at `/chains/<id>`.
+pub fn start_http_with_chains<M, S>(
+    addr: &SocketAddr,
+    cors_domains: Option<Vec<String>>,
+    allowed_hosts: Option<Vec<String>>,
+    handler: jsonrpc_core::MetaIoHandler<M, S>,
+    chains: HashMap<String, jsonrpc_core::MetaIoHandler<M, S>>,
+) -> Result<HttpServer, io::Error>
+where
+    M: jsonrpc_core::Metadata + Default,
+    S: jsonrpc_core::Middleware<M>, {
     let cors_domains = cors_domains.map(|domains| {
         domains
             .into_iter()
@@ -42,12 +64,67 @@ where
             .collect()
     });
 
+    let chains: HashMap<String, Arc<jsonrpc_core::MetaIoHandler<M, S>>> =
+        chains.into_iter().map(|(id, handler)| (id, Arc::new(handler))).collect();
     HttpServerBuilder::new(handler)
         .cors(cors_domains.into())
         .allowed_hosts(allowed_hosts.map(|hosts| hosts.into_iter().map(Host::from).collect()).into())
+        .request_middleware(move |request: hyper::Request<hyper::Body>| route_to_chain(&chains, request))
+        .threads(HTTP_SERVER_THREADS)
         .start_http(addr)
 }
 
+const CHAIN_PATH_PREFIX: &str = "/chains/";
+
+/// Answers the requests to `/chains/<id>` with the handler of the app-chain, leaving the others to the root handler.
+fn route_to_chain<M, S>(
+    chains: &HashMap<String, Arc<jsonrpc_core::MetaIoHandler<M, S>>>,
+    request: hyper::Request<hyper::Body>,
+) -> RequestMiddlewareAction
+where
+    M: jsonrpc_core::Metadata + Default,
+    S: jsonrpc_core::Middleware<M>, {
+    let id = match request.uri().path().strip_prefix(CHAIN_PATH_PREFIX) {
+        Some(id) => id.trim_end_matches('/').to_string(),
+        None => {
+            return RequestMiddlewareAction::Proceed {
+                should_continue_on_invalid_cors: false,
+                request,
+            }
+        }
+    };
+    let handler = match chains.get(&id) {
+        Some(handler) => Arc::clone(handler),
+        None => {
+            let response = hyper::Response::builder()
+                .status(hyper::StatusCode::NOT_FOUND)
+                .body(hyper::Body::from(format!("Unknown app-chain: {}\n", id)))
+                .expect("The response is well-formed");
+            return RequestMiddlewareAction::Respond {
+                should_validate_hosts: true,
+                response: Box::new(future::ok(response)),
+            }
+        }
+    };
+
+    let response = request.into_body().concat2().and_then(move |body| {
+        let body = String::from_utf8_lossy(&body).into_owned();
+        handler.handle_request(&body, M::default()).then(|result| {
+            // A notification has no response, so the body is left empty.
+            let body = result.ok().and_then(|response| response).unwrap_or_default();
+            let response = hyper::Response::builder()
+                .header(hyper::header::CONTENT_TYPE, "application/json; charset=utf-8")
+                .body(hyper::Body::from(body))
+                .expect("The response is well-formed");
+            Ok::<_, hyper::Error>(response)
+        })
+    });
+    RequestMiddlewareAction::Respond {
+        should_validate_hosts: true,
+        response: Box::new(response),
+    }
+}
+
 /// Start ipc server asynchronously and returns result with `Server` handle on success or an error.
 pub fn start_ipc<M: jsonrpc_core::Metadata>(
     addr: &str,
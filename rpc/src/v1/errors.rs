@@ -15,6 +15,7 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use ccore::AccountProviderError;
+use ccore::Categorized;
 use ccore::Error as CoreError;
 use ckey::Error as KeyError;
 use ckeystore::Error as KeystoreError;
@@ -63,13 +64,27 @@ mod codes {
     pub const NOT_UNLOCKED: i64 = -32045;
     pub const STATE_NOT_EXIST: i64 = -32048;
     pub const ACTION_DATA_HANDLER_NOT_FOUND: i64 = -32049;
+    pub const NO_TIMEOUTS: i64 = -32050;
+    pub const TRANSIENT_ERROR: i64 = -32051;
+    pub const UNAUTHORIZED: i64 = -32052;
+    pub const CANNOT_MINE: i64 = -32053;
     pub const UNKNOWN_ERROR: i64 = -32099;
 }
 
+/// The code for the errors which do not have their own codes.
+/// Clients may retry the request later when the code is `TRANSIENT_ERROR`.
+fn fallback_code(error: &CoreError, code: i64) -> ErrorCode {
+    if error.is_retryable() {
+        ErrorCode::ServerError(codes::TRANSIENT_ERROR)
+    } else {
+        ErrorCode::ServerError(code)
+    }
+}
+
 pub fn core<T: Into<CoreError>>(error: T) -> Error {
     let error = error.into();
     Error {
-        code: ErrorCode::ServerError(codes::CORE_ERROR),
+        code: fallback_code(&error, codes::CORE_ERROR),
         message: format!("{}", error),
         data: Some(Value::String(format!("{:?}", error))),
     }
@@ -90,7 +105,7 @@ pub fn conversion<T: Into<ConversionError>>(error: T) -> Error {
 pub fn transaction_core<T: Into<CoreError>>(error: T) -> Error {
     let error = error.into();
     let unknown_error = Error {
-        code: ErrorCode::ServerError(codes::UNKNOWN_ERROR),
+        code: fallback_code(&error, codes::UNKNOWN_ERROR),
         message: "Unknown error when sending transaction.".into(),
         data: Some(Value::String(format!("{:?}", error))),
     };
@@ -236,6 +251,30 @@ pub fn invalid_custom_action(err: String) -> Error {
     }
 }
 
+pub fn no_timeouts() -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::NO_TIMEOUTS),
+        message: "The consensus engine has no timeouts".into(),
+        data: None,
+    }
+}
+
+pub fn unauthorized() -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::UNAUTHORIZED),
+        message: "The admin token is wrong".into(),
+        data: None,
+    }
+}
+
+pub fn cannot_mine() -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::CANNOT_MINE),
+        message: "Blocks are mined on demand only by the solo engines".into(),
+        data: None,
+    }
+}
+
 pub fn io(error: std::io::Error) -> Error {
     Error {
         code: ErrorCode::InternalError,
@@ -243,3 +282,11 @@ pub fn io(error: std::io::Error) -> Error {
         data: None,
     }
 }
+
+pub fn invalid_params(message: String) -> Error {
+    Error {
+        code: ErrorCode::InvalidParams,
+        message,
+        data: None,
+    }
+}
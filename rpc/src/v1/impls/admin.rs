@@ -0,0 +1,110 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::super::errors;
+use super::super::traits::Admin;
+use super::super::types::{IntegrityReport, MaintenanceStatus, MemPoolItem};
+use ccore::{BlockChainClient, DatabaseClient, MinerService};
+use ctypes::{BlockNumber, TxHash};
+use jsonrpc_core::Result;
+use std::sync::Arc;
+
+pub struct AdminClient<C, M> {
+    client: Arc<C>,
+    miner: Arc<M>,
+    token: String,
+}
+
+impl<C, M> AdminClient<C, M>
+where
+    C: BlockChainClient,
+    M: MinerService,
+{
+    pub fn new(client: Arc<C>, miner: Arc<M>, token: String) -> Self {
+        Self {
+            client,
+            miner,
+            token,
+        }
+    }
+
+    /// Compares the tokens in a constant time, so the time taken doesn't tell how much of the token is right.
+    fn authorize(&self, token: &str) -> Result<()> {
+        let expected = self.token.as_bytes();
+        let given = token.as_bytes();
+        let difference =
+            expected.iter().zip(given.iter()).fold(expected.len() ^ given.len(), |acc, (a, b)| acc | (a ^ b) as usize);
+        if difference == 0 {
+            Ok(())
+        } else {
+            Err(errors::unauthorized())
+        }
+    }
+
+    fn maintenance_status(&self) -> MaintenanceStatus {
+        let since = self.miner.maintenance_since();
+        MaintenanceStatus {
+            enabled: since.is_some(),
+            since,
+            best_block_number: self.client.chain_info().best_block_number,
+            pending_transactions: self.miner.num_pending_transactions(),
+        }
+    }
+}
+
+impl<C, M> Admin for AdminClient<C, M>
+where
+    C: BlockChainClient + DatabaseClient + 'static,
+    M: MinerService + 'static,
+{
+    fn enter_maintenance(&self, token: String) -> Result<MaintenanceStatus> {
+        self.authorize(&token)?;
+        self.miner.set_maintenance(true);
+        Ok(self.maintenance_status())
+    }
+
+    fn exit_maintenance(&self, token: String) -> Result<MaintenanceStatus> {
+        self.authorize(&token)?;
+        self.miner.set_maintenance(false);
+        Ok(self.maintenance_status())
+    }
+
+    fn get_maintenance_status(&self, token: String) -> Result<MaintenanceStatus> {
+        self.authorize(&token)?;
+        Ok(self.maintenance_status())
+    }
+
+    fn verify_database(
+        &self,
+        token: String,
+        from: BlockNumber,
+        to: BlockNumber,
+        repair: bool,
+    ) -> Result<IntegrityReport> {
+        self.authorize(&token)?;
+        Ok(self.client.verify_database(from..to, repair).into())
+    }
+
+    fn load_mem_pool(&self, token: String, items: Vec<MemPoolItem>) -> Result<Vec<TxHash>> {
+        self.authorize(&token)?;
+        let transactions = items
+            .into_iter()
+            .map(MemPoolItem::try_into_transaction)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(errors::invalid_params)?;
+        Ok(self.client.load_mem_pool(transactions))
+    }
+}
@@ -16,12 +16,19 @@
 
 use super::super::errors;
 use super::super::traits::Chain;
-use super::super::types::{Block, BlockNumberAndHash, Transaction, ValidatorSet};
-use ccore::{BlockChainClient, EngineInfo, TermInfo};
+use super::super::types::{
+    ArchiveStats, BackfillStatus, Block, BlockNumberAndHash, InspectedTransaction, LightClientUpdate, ModuleDatumProof,
+    NodeHealth, Transaction, TransactionPreview, ValidatorSet,
+};
+use ccore::{encoded, BlockChainClient, EngineInfo, TermInfo};
+use cjson::bytes::Bytes;
 use cjson::scheme::Params;
 use ckey::{NetworkId, PlatformAddress};
-use ctypes::{BlockHash, BlockId, BlockNumber, TxHash};
+use coordinator::Transaction as CoreTransaction;
+use ctypes::{BlockHash, BlockId, BlockNumber, StorageId, TxHash};
 use jsonrpc_core::Result;
+use primitives::H256;
+use rlp::Rlp;
 use std::sync::Arc;
 
 pub struct ChainClient<C>
@@ -39,6 +46,11 @@ where
             client,
         }
     }
+
+    /// Falls back to the archive when the body was pruned from, or never synced to, the local database.
+    fn block(&self, id: &BlockId) -> Option<encoded::Block> {
+        self.client.block(id).or_else(|| self.client.archived_block(id))
+    }
 }
 
 impl<C> Chain for ChainClient<C>
@@ -72,19 +84,27 @@ where
 
     fn get_block_by_number(&self, block_number: u64) -> Result<Option<Block>> {
         let id = BlockId::Number(block_number);
-        Ok(self.client.block(&id).map(|block| Block::from_core(block.decode(), self.client.network_id())))
+        Ok(self.block(&id).map(|block| Block::from_core(block.decode(), self.client.network_id())))
     }
 
     fn get_block_by_hash(&self, block_hash: BlockHash) -> Result<Option<Block>> {
         let id = BlockId::Hash(block_hash);
-        Ok(self.client.block(&id).map(|block| {
+        Ok(self.block(&id).map(|block| {
             let block = block.decode();
             Block::from_core(block, self.client.network_id())
         }))
     }
 
+    fn get_block_by_timestamp(&self, timestamp: u64) -> Result<Option<Block>> {
+        Ok(self
+            .client
+            .block_number_by_timestamp(timestamp)
+            .and_then(|number| self.block(&BlockId::Number(number)))
+            .map(|block| Block::from_core(block.decode(), self.client.network_id())))
+    }
+
     fn get_block_transaction_count_by_hash(&self, block_hash: BlockHash) -> Result<Option<usize>> {
-        Ok(self.client.block(&BlockId::Hash(block_hash)).map(|block| block.transactions_count()))
+        Ok(self.block(&BlockId::Hash(block_hash)).map(|block| block.transactions_count()))
     }
 
     fn get_network_id(&self) -> Result<NetworkId> {
@@ -114,12 +134,82 @@ where
         Ok(self.client.metadata_seq(block_id))
     }
 
+    fn get_module_roots(&self, block_number: Option<u64>) -> Result<Option<Vec<H256>>> {
+        let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
+        Ok(self.client.module_roots(block_id))
+    }
+
+    fn get_module_datum_proof(
+        &self,
+        storage_id: StorageId,
+        key: Bytes,
+        block_number: Option<u64>,
+    ) -> Result<Option<ModuleDatumProof>> {
+        let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
+        Ok(self.client.module_datum_proof(block_id, storage_id, &key).map(From::from))
+    }
+
+    fn get_light_client_update(&self, block_number: u64) -> Result<Option<LightClientUpdate>> {
+        let block_id = BlockId::Number(block_number);
+        let header = match self.client.block_header(&block_id) {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+        let validator_set = match self.client.validator_set(block_id).map_err(errors::core)? {
+            Some(validator_set) => validator_set,
+            None => return Ok(None),
+        };
+        Ok(self.client.commit(block_id).map(|commit| LightClientUpdate {
+            header: header.into_inner().into(),
+            validator_set: ValidatorSet::from_core(validator_set),
+            commit: commit.into(),
+        }))
+    }
+
     fn get_possible_authors(&self, block_number: Option<u64>) -> Result<Option<Vec<PlatformAddress>>> {
         Ok(self.client.possible_authors(block_number).map_err(errors::core)?)
     }
 
     fn get_validator_set(&self, block_number: Option<u64>) -> Result<Option<ValidatorSet>> {
-        let validator_set_in_core = self.client.validator_set(block_number).map_err(errors::core)?;
+        let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
+        let validator_set_in_core = self.client.validator_set(block_id).map_err(errors::core)?;
         Ok(validator_set_in_core.map(ValidatorSet::from_core))
     }
+
+    fn get_validator_set_by_hash(&self, block_hash: BlockHash) -> Result<Option<ValidatorSet>> {
+        let validator_set_in_core = self.client.validator_set(BlockId::Hash(block_hash)).map_err(errors::core)?;
+        Ok(validator_set_in_core.map(ValidatorSet::from_core))
+    }
+
+    fn get_node_health(&self) -> Result<NodeHealth> {
+        Ok(self.client.node_health().into())
+    }
+
+    fn get_event_backfill_status(&self) -> Result<BackfillStatus> {
+        Ok(self.client.event_backfill_status().into())
+    }
+
+    fn get_archive_stats(&self) -> Result<Option<ArchiveStats>> {
+        Ok(self.client.archive_stats().map(From::from))
+    }
+
+    fn call(&self, raw: Bytes, block_number: Option<u64>) -> Result<TransactionPreview> {
+        let tx: CoreTransaction = Rlp::new(&raw.into_vec()).as_val().map_err(|e| errors::rlp(&e))?;
+        let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
+        let preview = self.client.preview_transaction(&tx, block_id).map_err(errors::core)?;
+        Ok(preview.ok_or_else(errors::state_not_exist)?.into())
+    }
+
+    fn inspect_transaction(&self, raw: Bytes) -> Result<InspectedTransaction> {
+        let tx: CoreTransaction = Rlp::new(&raw.into_vec()).as_val().map_err(|e| errors::rlp(&e))?;
+        Ok(InspectedTransaction::new(&tx, &|tx| self.client.inspect_transaction(tx)))
+    }
+
+    fn get_inspected_transaction(&self, transaction_hash: TxHash) -> Result<Option<InspectedTransaction>> {
+        let id = transaction_hash.into();
+        Ok(self
+            .client
+            .transaction(&id)
+            .map(|localized| InspectedTransaction::new(&localized.tx, &|tx| self.client.inspect_transaction(tx))))
+    }
 }
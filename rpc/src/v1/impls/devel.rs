@@ -16,8 +16,10 @@
 
 use super::super::errors;
 use super::super::traits::Devel;
+use super::super::types::{HeightStats, Timeouts};
 use ccore::{
-    DatabaseClient, EngineClient, EngineInfo, MinerService, MiningBlockChainClient, SnapshotClient, TermInfo, COL_STATE,
+    DatabaseClient, EngineClient, EngineInfo, EngineType, MinerService, MiningBlockChainClient, SnapshotClient,
+    TermInfo, COL_STATE,
 };
 use cjson::bytes::Bytes;
 use cnetwork::{unbounded_event_callback, EventSender, IntoSocketAddr};
@@ -31,6 +33,9 @@ use std::net::SocketAddr;
 use std::sync::Arc;
 use std::vec::Vec;
 
+/// The most blocks mined by a request. Each of them runs a second further ahead of the clock.
+const MAX_MINED_BLOCKS: usize = 100;
+
 pub struct DevelClient<C, M> {
     client: Arc<C>,
     db: Arc<dyn KeyValueDB>,
@@ -83,6 +88,25 @@ where
         Ok(())
     }
 
+    fn mine_blocks(&self, count: usize) -> Result<Vec<BlockHash>> {
+        if !matches!(self.miner.engine_type(), EngineType::Solo | EngineType::InstantSeal) {
+            return Err(errors::cannot_mine())
+        }
+        if count > MAX_MINED_BLOCKS {
+            return Err(errors::invalid_params(format!("At most {} blocks can be mined at once", MAX_MINED_BLOCKS)))
+        }
+        let mut mined = Vec::new();
+        for _ in 0..count {
+            // The blocks are imported at once instead of waiting for the clock to reach their timestamps.
+            if !self.miner.mine_block(&*self.client) {
+                // The miner couldn't make a block, e.g. the node is in the maintenance mode.
+                break
+            }
+            mined.push(self.client.best_block_header().hash());
+        }
+        Ok(mined)
+    }
+
     fn get_block_sync_peers(&self) -> Result<Vec<SocketAddr>> {
         if let Some(block_sync) = self.block_sync.as_ref() {
             let (sender, receiver) = unbounded_event_callback();
@@ -117,4 +141,16 @@ where
         self.client.notify_snapshot(BlockId::Hash(block_hash));
         Ok(())
     }
+
+    fn get_height_stats(&self) -> Result<Vec<HeightStats>> {
+        Ok(self.client.recent_height_stats().into_iter().map(HeightStats::from).collect())
+    }
+
+    fn get_timeouts(&self) -> Result<Timeouts> {
+        self.client.timeouts().map(Timeouts::from).ok_or_else(errors::no_timeouts)
+    }
+
+    fn update_timeouts(&self, timeouts: Timeouts) -> Result<Timeouts> {
+        self.client.update_timeouts(&timeouts.into()).map(Timeouts::from).ok_or_else(errors::no_timeouts)
+    }
 }
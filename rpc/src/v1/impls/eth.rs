@@ -0,0 +1,71 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::super::traits::Eth;
+use super::super::types::{EthBlock, EthBlockNumber, EthQuantity, EthReceipt, EthTransaction};
+use ccore::{BlockChainClient, EngineInfo};
+use ctypes::{BlockHash, BlockId, TxHash};
+use jsonrpc_core::Result;
+use std::sync::Arc;
+
+pub struct EthClient<C>
+where
+    C: BlockChainClient + EngineInfo, {
+    client: Arc<C>,
+}
+
+impl<C> EthClient<C>
+where
+    C: BlockChainClient + EngineInfo,
+{
+    pub fn new(client: Arc<C>) -> Self {
+        EthClient {
+            client,
+        }
+    }
+}
+
+impl<C> Eth for EthClient<C>
+where
+    C: BlockChainClient + EngineInfo + 'static,
+{
+    fn block_number(&self) -> Result<EthQuantity> {
+        Ok(EthQuantity(self.client.chain_info().best_block_number))
+    }
+
+    fn chain_id(&self) -> Result<EthQuantity> {
+        Ok(self.client.network_id().into())
+    }
+
+    fn get_block_by_number(&self, block_number: EthBlockNumber, full: bool) -> Result<Option<EthBlock>> {
+        Ok(self.client.block(&block_number.into()).map(|block| EthBlock::from_core(block.decode(), full)))
+    }
+
+    fn get_block_by_hash(&self, block_hash: BlockHash, full: bool) -> Result<Option<EthBlock>> {
+        Ok(self.client.block(&BlockId::Hash(block_hash)).map(|block| EthBlock::from_core(block.decode(), full)))
+    }
+
+    fn get_transaction_by_hash(&self, transaction_hash: TxHash) -> Result<Option<EthTransaction>> {
+        Ok(self.client.transaction(&transaction_hash.into()).map(From::from))
+    }
+
+    fn get_transaction_receipt(&self, transaction_hash: TxHash) -> Result<Option<EthReceipt>> {
+        Ok(self.client.transaction(&transaction_hash.into()).map(|tx| {
+            let sizes = self.client.transaction_sizes(&tx.tx);
+            EthReceipt::new(tx, sizes, self.client.events_by_tx_hash(&transaction_hash))
+        }))
+    }
+}
@@ -16,23 +16,73 @@
 
 use super::super::errors;
 use super::super::traits::Mempool;
-use super::super::types::PendingTransactions;
-use ccore::{BlockChainClient, EngineInfo};
+use super::super::types::{
+    IngressStats, LocalTransaction, MemPoolItem, OrphanStats, PendingTransactionPage, PendingTransactionQuery,
+    PendingTransactions, ScheduledTransaction, SentTransaction, TransactionPreview, TransactionStatus,
+};
+use ccore::{BlockChainClient, EngineInfo, TransactionId};
 use cjson::bytes::Bytes;
+use cnetwork::{unbounded_event_callback, EventSender};
 use coordinator::Transaction;
-use ctypes::TxHash;
+use csync::TransactionSyncEvent;
+use ctypes::{BlockId, BlockNumber, TxHash};
 use jsonrpc_core::Result;
 use rlp::Rlp;
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// The longest time a request can wait for a transaction to leave the mem pool.
+/// A waiting request holds a thread of the HTTP server, so it's kept short.
+const MAX_WAIT: Duration = Duration::from_secs(5);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// The most pending transactions listed in a page.
+const MAX_PAGE_SIZE: usize = 1000;
 
 pub struct MempoolClient<C> {
     client: Arc<C>,
+    transaction_sync: Option<EventSender<TransactionSyncEvent>>,
 }
 
 impl<C> MempoolClient<C> {
-    pub fn new(client: Arc<C>) -> Self {
+    pub fn new(client: Arc<C>, transaction_sync: Option<EventSender<TransactionSyncEvent>>) -> Self {
         MempoolClient {
             client,
+            transaction_sync,
+        }
+    }
+}
+
+impl<C> MempoolClient<C>
+where
+    C: BlockChainClient,
+{
+    fn transaction_status(&self, hash: &TxHash) -> TransactionStatus {
+        let included = || {
+            self.client.transaction(&TransactionId::Hash(*hash)).map(|tx| {
+                let sizes = self.client.transaction_sizes(&tx.tx);
+                TransactionStatus::included(tx, sizes, self.client.events_by_tx_hash(hash))
+            })
+        };
+        if let Some(status) = included() {
+            return status
+        }
+        if self.client.is_pending_transaction(hash) {
+            return TransactionStatus::Pending
+        }
+        // The transaction may have left the mem pool for a block imported in the meantime.
+        included().unwrap_or(TransactionStatus::Unknown)
+    }
+
+    fn wait_while_pending(&self, hash: &TxHash, wait: Option<u64>) -> TransactionStatus {
+        let deadline = Instant::now() + Duration::from_millis(wait.unwrap_or(0)).min(MAX_WAIT);
+        loop {
+            let status = self.transaction_status(hash);
+            if !status.is_pending() || Instant::now() >= deadline {
+                return status
+            }
+            sleep(POLL_INTERVAL);
         }
     }
 }
@@ -55,6 +105,33 @@ where
             .map(Into::into)
     }
 
+    fn send_module_transaction(&self, tx_type: String, body: Bytes, wait: Option<u64>) -> Result<SentTransaction> {
+        let tx = Transaction::new(tx_type, body.into_vec());
+        let hash = tx.hash();
+        self.client.queue_own_transaction(tx).map_err(errors::transaction_core)?;
+        Ok(SentTransaction {
+            hash,
+            status: self.wait_while_pending(&hash, wait),
+        })
+    }
+
+    fn get_transaction_status(&self, hash: TxHash, wait: Option<u64>) -> Result<TransactionStatus> {
+        Ok(self.wait_while_pending(&hash, wait))
+    }
+
+    fn send_scheduled_transaction(&self, raw: Bytes, activation_block: BlockNumber) -> Result<TxHash> {
+        let tx: Transaction = Rlp::new(&raw.into_vec()).as_val().map_err(|e| errors::rlp(&e))?;
+        let hash = tx.hash();
+        self.client.queue_scheduled_transaction(tx, activation_block).map_err(errors::transaction_core)?;
+        Ok(hash)
+    }
+
+    fn preview_transaction(&self, raw: Bytes) -> Result<TransactionPreview> {
+        let tx: Transaction = Rlp::new(&raw.into_vec()).as_val().map_err(|e| errors::rlp(&e))?;
+        let preview = self.client.preview_transaction(&tx, BlockId::Latest).map_err(errors::core)?;
+        Ok(preview.ok_or_else(errors::state_not_exist)?.into())
+    }
+
     fn delete_all_pending_transactions(&self) -> Result<()> {
         self.client.delete_all_pending_transactions();
         Ok(())
@@ -67,4 +144,67 @@ where
     fn get_pending_transactions_count(&self, from: Option<u64>, to: Option<u64>) -> Result<usize> {
         Ok(self.client.count_pending_transactions(from.unwrap_or(0)..to.unwrap_or(u64::MAX)))
     }
+
+    fn query_pending_transactions(&self, query: PendingTransactionQuery) -> Result<PendingTransactionPage> {
+        let query = query.try_into_pool_query(MAX_PAGE_SIZE).map_err(errors::invalid_params)?;
+        let page = self.client.query_pending_transactions(&query);
+        let transactions = page
+            .transactions
+            .into_iter()
+            .map(|item| {
+                let sizes = self.client.transaction_sizes(&item.tx);
+                MemPoolItem::new(item, sizes)
+            })
+            .collect();
+        Ok(PendingTransactionPage::new(transactions, page.next))
+    }
+
+    fn get_ingress_stats(&self) -> Result<Vec<IngressStats>> {
+        Ok(self.client.ingress_stats().into_iter().map(Into::into).collect())
+    }
+
+    fn get_orphan_stats(&self) -> Result<OrphanStats> {
+        Ok(self.client.orphan_stats().into())
+    }
+
+    fn get_scheduled_transactions(&self) -> Result<Vec<ScheduledTransaction>> {
+        Ok(self.client.scheduled_transactions().into_iter().map(Into::into).collect())
+    }
+
+    fn get_local_transactions(&self) -> Result<Vec<LocalTransaction>> {
+        let resends: HashMap<_, _> = if let Some(transaction_sync) = &self.transaction_sync {
+            let (sender, receiver) = unbounded_event_callback();
+            transaction_sync.send(TransactionSyncEvent::GetLocalResends(sender)).unwrap();
+            receiver.iter().map(|status| (status.hash, status)).collect()
+        } else {
+            HashMap::new()
+        };
+        Ok(self
+            .client
+            .local_pending_transactions()
+            .iter()
+            .map(|item| LocalTransaction::new(item, resends.get(&item.tx.hash())))
+            .collect())
+    }
+
+    fn get_replacement_fee(&self, hash: TxHash, fee: u64) -> Result<u64> {
+        if !self.client.local_pending_transactions().iter().any(|item| item.tx.hash() == hash) {
+            return Err(errors::invalid_params(format!("{} is not a pending local transaction", hash)))
+        }
+        self.client
+            .replacement_fee(fee)
+            .ok_or_else(|| errors::invalid_params(format!("No fee can replace the fee {}", fee)))
+    }
+
+    fn dump(&self) -> Result<Vec<MemPoolItem>> {
+        Ok(self
+            .client
+            .dump_mem_pool()
+            .into_iter()
+            .map(|item| {
+                let sizes = self.client.transaction_sizes(&item.tx);
+                MemPoolItem::new(item, sizes)
+            })
+            .collect())
+    }
 }
@@ -14,14 +14,20 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+mod admin;
 mod chain;
 mod devel;
+mod eth;
 mod mempool;
 mod net;
+mod node;
 mod snapshot;
 
+pub use self::admin::AdminClient;
 pub use self::chain::ChainClient;
 pub use self::devel::DevelClient;
+pub use self::eth::EthClient;
 pub use self::mempool::MempoolClient;
 pub use self::net::NetClient;
+pub use self::node::NodeClient;
 pub use self::snapshot::SnapshotClient;
@@ -16,7 +16,7 @@
 
 use super::super::errors;
 use super::super::traits::Net;
-use super::super::types::FilterStatus;
+use super::super::types::{FilterStatus, NetworkIdentity, PeerReputation, PeerStats};
 use cidr::IpCidr;
 use ckey::X25519Public as Public;
 use cnetwork::{NetworkControl, SocketAddr};
@@ -24,6 +24,7 @@ use jsonrpc_core::Result;
 use std::collections::HashMap;
 use std::net::{self, IpAddr};
 use std::sync::Arc;
+use std::time::Duration;
 
 pub struct NetClient {
     network_control: Arc<dyn NetworkControl>,
@@ -129,4 +130,27 @@ impl Net for NetClient {
     fn recent_network_usage(&self) -> Result<HashMap<String, usize>> {
         Ok(self.network_control.recent_network_usage().map_err(|e| errors::network_control(&e))?)
     }
+
+    fn peer_stats(&self) -> Result<Vec<PeerStats>> {
+        let stats = self.network_control.peer_stats().map_err(|e| errors::network_control(&e))?;
+        Ok(stats.into_iter().map(Into::into).collect())
+    }
+
+    fn peer_reputation(&self) -> Result<Vec<PeerReputation>> {
+        let reputations = self.network_control.peer_reputations().map_err(|e| errors::network_control(&e))?;
+        Ok(reputations.into_iter().map(Into::into).collect())
+    }
+
+    fn ban_peer(&self, address: IpAddr, port: u16, seconds: Option<u64>) -> Result<u64> {
+        let duration = self
+            .network_control
+            .ban_peer(SocketAddr::new(address, port), seconds.map(Duration::from_secs))
+            .map_err(|e| errors::network_control(&e))?;
+        Ok(duration.as_secs())
+    }
+
+    fn get_validator_address_book(&self) -> Result<Vec<NetworkIdentity>> {
+        let identities = self.network_control.validator_address_book().map_err(|e| errors::network_control(&e))?;
+        Ok(identities.into_iter().map(Into::into).collect())
+    }
 }
@@ -0,0 +1,159 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use super::super::traits::Node;
+use super::super::types::{HealthIssue, MemPoolBacklog, NodeStatus, StalledModuleCall, SyncStatus};
+use ccore::{BlockChainClient, MinerService};
+use cnetwork::{unbounded_event_callback, EventSender};
+use csync::BlockSyncEvent;
+use ctypes::BlockId;
+use jsonrpc_core::Result;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The node is syncing while its best block is more than this behind the highest best block of the peers.
+const MAX_BLOCKS_BEHIND: u64 = 2;
+const STALE_BLOCK_AGE: Duration = Duration::from_secs(60);
+const STALLED_MODULE_CALL: Duration = Duration::from_secs(10);
+const LOW_DISK_SPACE_BYTES: u64 = 5 << 30;
+const OUT_OF_DISK_SPACE_BYTES: u64 = 1 << 30;
+
+pub struct NodeClient<C, M> {
+    client: Arc<C>,
+    miner: Arc<M>,
+    block_sync: Option<EventSender<BlockSyncEvent>>,
+    db_path: String,
+}
+
+impl<C, M> NodeClient<C, M>
+where
+    C: BlockChainClient,
+    M: MinerService,
+{
+    pub fn new(
+        client: Arc<C>,
+        miner: Arc<M>,
+        block_sync: Option<EventSender<BlockSyncEvent>>,
+        db_path: String,
+    ) -> Self {
+        Self {
+            client,
+            miner,
+            block_sync,
+            db_path,
+        }
+    }
+
+    fn sync(&self) -> SyncStatus {
+        let chain_info = self.client.chain_info();
+        let peer_best_hashes = if let Some(block_sync) = &self.block_sync {
+            let (sender, receiver) = unbounded_event_callback();
+            block_sync.send(BlockSyncEvent::GetPeerBestBlockHashes(sender)).unwrap();
+            receiver.iter().map(|(_, hash)| hash).collect()
+        } else {
+            Vec::new()
+        };
+        let peer_count = peer_best_hashes.len();
+        let peer_best_numbers: Vec<_> =
+            peer_best_hashes.into_iter().filter_map(|hash| self.client.block_number(&BlockId::Hash(hash))).collect();
+        let highest_peer_block_number = peer_best_numbers.iter().max().cloned();
+        let blocks_behind =
+            highest_peer_block_number.map_or(0, |highest| highest.saturating_sub(chain_info.best_block_number));
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("The time is after the epoch").as_secs();
+
+        SyncStatus {
+            syncing: blocks_behind > MAX_BLOCKS_BEHIND,
+            best_block_number: chain_info.best_block_number,
+            best_block_hash: chain_info.best_block_hash,
+            last_block_age_seconds: now.saturating_sub(chain_info.best_block_timestamp),
+            highest_peer_block_number,
+            blocks_behind,
+            peer_count,
+            peers_with_unknown_best: peer_count - peer_best_numbers.len(),
+        }
+    }
+}
+
+impl<C, M> Node for NodeClient<C, M>
+where
+    C: BlockChainClient + 'static,
+    M: MinerService + 'static,
+{
+    fn health(&self) -> Result<NodeStatus> {
+        let sync = self.sync();
+        let node_health = self.client.node_health();
+        let stalled_module_calls: Vec<StalledModuleCall> = coordinator::pending_module_calls()
+            .into_iter()
+            .filter(|call| call.elapsed >= STALLED_MODULE_CALL)
+            .map(From::from)
+            .collect();
+        let disk_available_bytes = available_disk_space(&self.db_path);
+
+        let mut issues = Vec::new();
+        if sync.syncing {
+            issues.push(HealthIssue::Syncing);
+        }
+        if sync.last_block_age_seconds >= STALE_BLOCK_AGE.as_secs() {
+            issues.push(HealthIssue::StaleBestBlock);
+        }
+        if self.block_sync.is_some() && sync.peer_count == 0 {
+            issues.push(HealthIssue::NoPeers);
+        }
+        if node_health.mem_pool_near_limit {
+            issues.push(HealthIssue::MemPoolBacklog);
+        }
+        if !stalled_module_calls.is_empty() {
+            issues.push(HealthIssue::StalledModuleCall);
+        }
+        match disk_available_bytes {
+            Some(bytes) if bytes < OUT_OF_DISK_SPACE_BYTES => issues.push(HealthIssue::OutOfDiskSpace),
+            Some(bytes) if bytes < LOW_DISK_SPACE_BYTES => issues.push(HealthIssue::LowDiskSpace),
+            _ => {}
+        }
+        if node_health.all_sentries_lost {
+            issues.push(HealthIssue::AllSentriesLost);
+        } else if node_health.sentry_lost {
+            issues.push(HealthIssue::SentryLost);
+        }
+        if self.miner.maintenance_since().is_some() {
+            issues.push(HealthIssue::Maintenance);
+        }
+
+        let mem_pool = MemPoolBacklog::from(self.miner.mem_pool_usage());
+        Ok(NodeStatus::new(issues, sync, mem_pool, stalled_module_calls, disk_available_bytes))
+    }
+
+    fn sync_status(&self) -> Result<SyncStatus> {
+        Ok(self.sync())
+    }
+}
+
+/// The bytes available to an unprivileged user on the file system holding `path`.
+#[cfg(unix)]
+fn available_disk_space(path: &str) -> Option<u64> {
+    use std::ffi::CString;
+
+    let path = CString::new(path).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None
+    }
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+fn available_disk_space(_path: &str) -> Option<u64> {
+    None
+}
@@ -23,4 +23,4 @@ mod types;
 
 pub use self::impls::*;
 pub use self::traits::*;
-pub use self::types::Block;
+pub use self::types::{Block, BlockHeader, TransactionEvent};
@@ -0,0 +1,49 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::super::types::{IntegrityReport, MaintenanceStatus, MemPoolItem};
+use ctypes::{BlockNumber, TxHash};
+use jsonrpc_core::Result;
+
+/// The administrative APIs, which take the admin token of the node as their first parameter.
+#[rpc(server)]
+pub trait Admin {
+    /// Stops accepting transactions and proposing blocks, while the node keeps voting and serving reads.
+    #[rpc(name = "admin_enterMaintenance")]
+    fn enter_maintenance(&self, token: String) -> Result<MaintenanceStatus>;
+
+    /// Resumes accepting transactions and proposing blocks.
+    #[rpc(name = "admin_exitMaintenance")]
+    fn exit_maintenance(&self, token: String) -> Result<MaintenanceStatus>;
+
+    #[rpc(name = "admin_getMaintenanceStatus")]
+    fn get_maintenance_status(&self, token: String) -> Result<MaintenanceStatus>;
+
+    /// Checks the stored chain data of the blocks in `from..to`, repairing what can be rebuilt if `repair` is set.
+    #[rpc(name = "admin_verifyDatabase")]
+    fn verify_database(
+        &self,
+        token: String,
+        from: BlockNumber,
+        to: BlockNumber,
+        repair: bool,
+    ) -> Result<IntegrityReport>;
+
+    /// Loads the transactions dumped by `mempool_dump` into the mem pool, checking them as new transactions.
+    /// Returns the hashes of the loaded transactions.
+    #[rpc(name = "admin_loadMemPool")]
+    fn load_mem_pool(&self, token: String, items: Vec<MemPoolItem>) -> Result<Vec<TxHash>>;
+}
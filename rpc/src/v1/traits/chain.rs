@@ -14,11 +14,16 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::super::types::{Block, BlockNumberAndHash, Transaction, ValidatorSet};
+use super::super::types::{
+    ArchiveStats, BackfillStatus, Block, BlockNumberAndHash, InspectedTransaction, LightClientUpdate, ModuleDatumProof,
+    NodeHealth, Transaction, TransactionPreview, ValidatorSet,
+};
+use cjson::bytes::Bytes;
 use cjson::scheme::Params;
 use ckey::{NetworkId, PlatformAddress};
-use ctypes::{BlockHash, BlockNumber, TxHash};
+use ctypes::{BlockHash, BlockNumber, StorageId, TxHash};
 use jsonrpc_core::Result;
+use primitives::H256;
 
 #[rpc(server)]
 pub trait Chain {
@@ -50,6 +55,10 @@ pub trait Chain {
     #[rpc(name = "chain_getBlockByHash")]
     fn get_block_by_hash(&self, block_hash: BlockHash) -> Result<Option<Block>>;
 
+    /// Gets the last block whose timestamp is not after the given timestamp.
+    #[rpc(name = "chain_getBlockByTimestamp")]
+    fn get_block_by_timestamp(&self, timestamp: u64) -> Result<Option<Block>>;
+
     ///Gets the count of transactions in a block with given hash.
     #[rpc(name = "chain_getBlockTransactionCountByHash")]
     fn get_block_transaction_count_by_hash(&self, block_hash: BlockHash) -> Result<Option<usize>>;
@@ -70,11 +79,56 @@ pub trait Chain {
     #[rpc(name = "chain_getMetadataSeq")]
     fn get_metadata_seq(&self, block_number: Option<u64>) -> Result<Option<u64>>;
 
+    /// Return the roots of the module substorages at given block number
+    #[rpc(name = "chain_getModuleRoots")]
+    fn get_module_roots(&self, block_number: Option<u64>) -> Result<Option<Vec<H256>>>;
+
+    /// Return the proof of a module datum at given block number, for the light clients of this chain
+    #[rpc(name = "chain_getModuleDatumProof")]
+    fn get_module_datum_proof(
+        &self,
+        storage_id: StorageId,
+        key: Bytes,
+        block_number: Option<u64>,
+    ) -> Result<Option<ModuleDatumProof>>;
+
+    /// Return the header of given block number with the precommits finalizing it, for the light clients of this chain
+    #[rpc(name = "chain_getLightClientUpdate")]
+    fn get_light_client_update(&self, block_number: u64) -> Result<Option<LightClientUpdate>>;
+
     /// Return the valid block authors
     #[rpc(name = "chain_getPossibleAuthors")]
     fn get_possible_authors(&self, block_number: Option<u64>) -> Result<Option<Vec<PlatformAddress>>>;
 
-    /// Return the valid block authors
+    /// Return the validator set that votes on the block with given number
     #[rpc(name = "chain_getValidatorSet")]
     fn get_validator_set(&self, block_number: Option<u64>) -> Result<Option<ValidatorSet>>;
+
+    /// Return the validator set that votes on the block with given hash
+    #[rpc(name = "chain_getValidatorSetByHash")]
+    fn get_validator_set_by_hash(&self, block_hash: BlockHash) -> Result<Option<ValidatorSet>>;
+
+    /// Return the flags raised while blocks, the mem pool or candidate metadata are near their limits
+    #[rpc(name = "chain_getNodeHealth")]
+    fn get_node_health(&self) -> Result<NodeHealth>;
+
+    /// Return the progress of storing the events of the blocks imported before the events were stored
+    #[rpc(name = "chain_getEventBackfillStatus")]
+    fn get_event_backfill_status(&self) -> Result<BackfillStatus>;
+
+    /// Return how often the attached archive database was consulted for the history missing in the local database
+    #[rpc(name = "chain_getArchiveStats")]
+    fn get_archive_stats(&self) -> Result<Option<ArchiveStats>>;
+
+    /// Executes a transaction in a block following the given block number without committing it
+    #[rpc(name = "chain_call")]
+    fn call(&self, raw: Bytes, block_number: Option<u64>) -> Result<TransactionPreview>;
+
+    /// Decodes the payload of a transaction with the module owning its type
+    #[rpc(name = "chain_inspectTransaction")]
+    fn inspect_transaction(&self, raw: Bytes) -> Result<InspectedTransaction>;
+
+    /// Gets the transaction with given hash, with its payload decoded by the module owning its type
+    #[rpc(name = "chain_getInspectedTransaction")]
+    fn get_inspected_transaction(&self, transaction_hash: TxHash) -> Result<Option<InspectedTransaction>>;
 }
@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use super::super::types::{HeightStats, Timeouts};
 use cjson::bytes::Bytes;
 use ctypes::BlockHash;
 use jsonrpc_core::Result;
@@ -34,6 +35,9 @@ pub trait Devel {
     #[rpc(name = "devel_stopSealing")]
     fn stop_sealing(&self) -> Result<()>;
 
+    #[rpc(name = "devel_mineBlocks")]
+    fn mine_blocks(&self, count: usize) -> Result<Vec<BlockHash>>;
+
     #[rpc(name = "devel_getBlockSyncPeers")]
     fn get_block_sync_peers(&self) -> Result<Vec<SocketAddr>>;
 
@@ -45,4 +49,13 @@ pub trait Devel {
 
     #[rpc(name = "devel_snapshot")]
     fn snapshot(&self, hash: BlockHash) -> Result<()>;
+
+    #[rpc(name = "devel_getHeightStats")]
+    fn get_height_stats(&self) -> Result<Vec<HeightStats>>;
+
+    #[rpc(name = "devel_getTimeouts")]
+    fn get_timeouts(&self) -> Result<Timeouts>;
+
+    #[rpc(name = "devel_updateTimeouts")]
+    fn update_timeouts(&self, timeouts: Timeouts) -> Result<Timeouts>;
 }
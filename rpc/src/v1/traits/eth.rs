@@ -0,0 +1,47 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::super::types::{EthBlock, EthBlockNumber, EthQuantity, EthReceipt, EthTransaction};
+use ctypes::{BlockHash, TxHash};
+use jsonrpc_core::Result;
+
+/// A subset of Ethereum's JSON-RPC for the tools that only speak it.
+#[rpc(server)]
+pub trait Eth {
+    /// Gets the number of the best block.
+    #[rpc(name = "eth_blockNumber")]
+    fn block_number(&self) -> Result<EthQuantity>;
+
+    /// Gets the network id as an integer.
+    #[rpc(name = "eth_chainId")]
+    fn chain_id(&self) -> Result<EthQuantity>;
+
+    /// Gets the block with the given number, with the full transactions if `full` is true.
+    #[rpc(name = "eth_getBlockByNumber")]
+    fn get_block_by_number(&self, block_number: EthBlockNumber, full: bool) -> Result<Option<EthBlock>>;
+
+    /// Gets the block with the given hash, with the full transactions if `full` is true.
+    #[rpc(name = "eth_getBlockByHash")]
+    fn get_block_by_hash(&self, block_hash: BlockHash, full: bool) -> Result<Option<EthBlock>>;
+
+    /// Gets the included transaction with the given hash.
+    #[rpc(name = "eth_getTransactionByHash")]
+    fn get_transaction_by_hash(&self, transaction_hash: TxHash) -> Result<Option<EthTransaction>>;
+
+    /// Gets the events of the included transaction with the given hash.
+    #[rpc(name = "eth_getTransactionReceipt")]
+    fn get_transaction_receipt(&self, transaction_hash: TxHash) -> Result<Option<EthReceipt>>;
+}
@@ -14,9 +14,12 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::super::types::PendingTransactions;
+use super::super::types::{
+    IngressStats, LocalTransaction, MemPoolItem, OrphanStats, PendingTransactionPage, PendingTransactionQuery,
+    PendingTransactions, ScheduledTransaction, SentTransaction, TransactionPreview, TransactionStatus,
+};
 use cjson::bytes::Bytes;
-use ctypes::TxHash;
+use ctypes::{BlockNumber, TxHash};
 use jsonrpc_core::Result;
 
 #[rpc(server)]
@@ -25,6 +28,24 @@ pub trait Mempool {
     #[rpc(name = "mempool_sendSignedTransaction")]
     fn send_signed_transaction(&self, raw: Bytes) -> Result<TxHash>;
 
+    /// Sends a transaction of a module, given its type and its body, e.g. the CBOR encoded payload.
+    /// It waits at most `wait` milliseconds while the transaction is pending, and returns the status then.
+    #[rpc(name = "mempool_sendModuleTransaction")]
+    fn send_module_transaction(&self, tx_type: String, body: Bytes, wait: Option<u64>) -> Result<SentTransaction>;
+
+    /// Gets whether the transaction is pending, included in a block, or unknown.
+    /// It waits at most `wait` milliseconds while the transaction is pending.
+    #[rpc(name = "mempool_getTransactionStatus")]
+    fn get_transaction_status(&self, hash: TxHash, wait: Option<u64>) -> Result<TransactionStatus>;
+
+    /// Sends signed transaction that can't be included in a block before the activation block, returning its hash.
+    #[rpc(name = "mempool_sendScheduledTransaction")]
+    fn send_scheduled_transaction(&self, raw: Bytes, activation_block: BlockNumber) -> Result<TxHash>;
+
+    /// Previews the outcome of a transaction as if it were included in the next block, without sending it.
+    #[rpc(name = "mempool_previewTransaction")]
+    fn preview_transaction(&self, raw: Bytes) -> Result<TransactionPreview>;
+
     /// Deletes all pending transactions in the mem pool.
     #[rpc(name = "mempool_deleteAllPendingTransactions")]
     fn delete_all_pending_transactions(&self) -> Result<()>;
@@ -36,4 +57,33 @@ pub trait Mempool {
     /// Gets the count of transactions in the current mem pool.
     #[rpc(name = "mempool_getPendingTransactionsCount")]
     fn get_pending_transactions_count(&self, from: Option<u64>, to: Option<u64>) -> Result<usize>;
+
+    /// Gets a page of the pending transactions matching the query, sorted by their insertion, fees or sequences.
+    /// The cursor of the next page is returned while there are more.
+    #[rpc(name = "mempool_queryPendingTransactions")]
+    fn query_pending_transactions(&self, query: PendingTransactionQuery) -> Result<PendingTransactionPage>;
+
+    /// Gets how many external transactions of each rate-limited type were admitted to the mem pool and dropped.
+    #[rpc(name = "mempool_getIngressStats")]
+    fn get_ingress_stats(&self) -> Result<Vec<IngressStats>>;
+
+    /// Gets how many transactions invalid against the state were checked again, and how they ended up.
+    #[rpc(name = "mempool_getOrphanStats")]
+    fn get_orphan_stats(&self) -> Result<OrphanStats>;
+
+    /// Gets the transactions in the mem pool waiting for their activation blocks.
+    #[rpc(name = "mempool_getScheduledTransactions")]
+    fn get_scheduled_transactions(&self) -> Result<Vec<ScheduledTransaction>>;
+
+    /// Gets the transactions submitted to this node that are still in the mem pool, with their resend schedule.
+    #[rpc(name = "mempool_getLocalTransactions")]
+    fn get_local_transactions(&self) -> Result<Vec<LocalTransaction>>;
+
+    /// Gets the lowest fee with which a stuck local transaction paying `fee` can be replaced.
+    #[rpc(name = "mempool_getReplacementFee")]
+    fn get_replacement_fee(&self, hash: TxHash, fee: u64) -> Result<u64>;
+
+    /// Dumps all the transactions in the mem pool with their metadata.
+    #[rpc(name = "mempool_dump")]
+    fn dump(&self) -> Result<Vec<MemPoolItem>>;
 }
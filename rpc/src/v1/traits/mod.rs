@@ -14,14 +14,20 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+mod admin;
 mod chain;
 mod devel;
+mod eth;
 mod mempool;
 mod net;
+mod node;
 mod snapshot;
 
+pub use self::admin::Admin;
 pub use self::chain::Chain;
 pub use self::devel::Devel;
+pub use self::eth::Eth;
 pub use self::mempool::Mempool;
 pub use self::net::Net;
+pub use self::node::Node;
 pub use self::snapshot::Snapshot;
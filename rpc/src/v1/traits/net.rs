@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::super::types::FilterStatus;
+use super::super::types::{FilterStatus, NetworkIdentity, PeerReputation, PeerStats};
 use cidr::IpCidr;
 use ckey::X25519Public as Public;
 use jsonrpc_core::Result;
@@ -79,4 +79,18 @@ pub trait Net {
 
     #[rpc(name = "net_recentNetworkUsage")]
     fn recent_network_usage(&self) -> Result<HashMap<String, usize>>;
+
+    #[rpc(name = "net_peerStats")]
+    fn peer_stats(&self) -> Result<Vec<PeerStats>>;
+
+    #[rpc(name = "net_peerReputation")]
+    fn peer_reputation(&self) -> Result<Vec<PeerReputation>>;
+
+    /// Bans the IP address of the peer for the seconds, or for the next step of its backoff if they are omitted.
+    /// Returns the seconds the peer is banned for.
+    #[rpc(name = "net_banPeer")]
+    fn ban_peer(&self, addr: IpAddr, port: u16, seconds: Option<u64>) -> Result<u64>;
+
+    #[rpc(name = "net_getValidatorAddressBook")]
+    fn get_validator_address_book(&self) -> Result<Vec<NetworkIdentity>>;
 }
@@ -0,0 +1,28 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use super::super::types::{NodeStatus, SyncStatus};
+use jsonrpc_core::Result;
+
+#[rpc(server)]
+pub trait Node {
+    /// Gets whether the node is fit to serve, with the issues that make it less so.
+    #[rpc(name = "node_health")]
+    fn health(&self) -> Result<NodeStatus>;
+
+    /// Gets how far the best block is behind the best blocks of the peers.
+    #[rpc(name = "node_syncStatus")]
+    fn sync_status(&self) -> Result<SyncStatus>;
+}
@@ -0,0 +1,37 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::ArchiveStats as CoreArchiveStats;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveStats {
+    block_fallbacks: u64,
+    blocks_served: u64,
+    state_fallbacks: u64,
+    states_served: u64,
+}
+
+impl From<CoreArchiveStats> for ArchiveStats {
+    fn from(stats: CoreArchiveStats) -> Self {
+        Self {
+            block_fallbacks: stats.block_fallbacks,
+            blocks_served: stats.blocks_served,
+            state_fallbacks: stats.state_fallbacks,
+            states_served: stats.states_served,
+        }
+    }
+}
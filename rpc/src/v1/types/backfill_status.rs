@@ -0,0 +1,38 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::BackfillStatus as CoreBackfillStatus;
+use ctypes::BlockNumber;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackfillStatus {
+    next_block: BlockNumber,
+    backfilled_blocks: u64,
+    is_complete: bool,
+    error: Option<String>,
+}
+
+impl From<CoreBackfillStatus> for BackfillStatus {
+    fn from(status: CoreBackfillStatus) -> Self {
+        Self {
+            next_block: status.next_block,
+            backfilled_blocks: status.backfilled_blocks,
+            is_complete: status.is_complete,
+            error: status.error,
+        }
+    }
+}
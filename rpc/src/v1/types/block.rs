@@ -17,7 +17,7 @@
 use super::Transaction;
 use ccore::{Block as CoreBlock, LocalizedTransaction};
 use ckey::{NetworkId, PlatformAddress};
-use ctypes::{BlockHash, BlockNumber, TransactionIndex};
+use ctypes::{BlockHash, BlockNumber, Header, TransactionIndex};
 use primitives::H256;
 
 #[derive(Debug, Serialize)]
@@ -29,10 +29,12 @@ pub struct Block {
     author: PlatformAddress,
 
     extra_data: Vec<u8>,
+    external_anchor: Option<H256>,
 
     transactions_root: H256,
     state_root: H256,
     next_validator_set_hash: H256,
+    module_roots_hash: Option<H256>,
 
     seal: Vec<Vec<u8>>,
 
@@ -58,10 +60,12 @@ impl Block {
             author: PlatformAddress::new_v0(network_id, *block.header.author()),
 
             extra_data: block.header.extra_data().clone(),
+            external_anchor: block.header.external_anchor().copied(),
 
             transactions_root: *block.header.transactions_root(),
             state_root: *block.header.state_root(),
             next_validator_set_hash: *block.header.next_validator_set_hash(),
+            module_roots_hash: block.header.module_roots_hash().copied(),
 
             seal: block.header.seal().to_vec(),
 
@@ -71,6 +75,51 @@ impl Block {
     }
 }
 
+/// A block without its transactions.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockHeader {
+    parent_hash: BlockHash,
+    timestamp: u64,
+    number: u64,
+    author: PlatformAddress,
+
+    extra_data: Vec<u8>,
+    external_anchor: Option<H256>,
+
+    transactions_root: H256,
+    state_root: H256,
+    next_validator_set_hash: H256,
+    module_roots_hash: Option<H256>,
+
+    seal: Vec<Vec<u8>>,
+
+    hash: BlockHash,
+}
+
+impl BlockHeader {
+    pub fn from_core(header: &Header, network_id: NetworkId) -> Self {
+        BlockHeader {
+            parent_hash: *header.parent_hash(),
+            timestamp: header.timestamp(),
+            number: header.number(),
+            author: PlatformAddress::new_v0(network_id, *header.author()),
+
+            extra_data: header.extra_data().clone(),
+            external_anchor: header.external_anchor().copied(),
+
+            transactions_root: *header.transactions_root(),
+            state_root: *header.state_root(),
+            next_validator_set_hash: *header.next_validator_set_hash(),
+            module_roots_hash: header.module_roots_hash().copied(),
+
+            seal: header.seal().to_vec(),
+
+            hash: header.hash(),
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct BlockNumberAndHash {
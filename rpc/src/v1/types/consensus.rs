@@ -0,0 +1,88 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::{HeightStats as CoreHeightStats, TimeoutParams};
+use cjson::scheme::TendermintParams;
+use std::time::Duration;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeightStats {
+    height: u64,
+    finalized_view: Option<u64>,
+    views: Option<u64>,
+    propose_timeouts: u64,
+    prevote_timeouts: u64,
+    precommit_timeouts: u64,
+}
+
+impl From<CoreHeightStats> for HeightStats {
+    fn from(stats: CoreHeightStats) -> Self {
+        Self {
+            height: stats.height,
+            finalized_view: stats.finalized_view,
+            views: stats.views(),
+            propose_timeouts: stats.propose_timeouts,
+            prevote_timeouts: stats.prevote_timeouts,
+            precommit_timeouts: stats.precommit_timeouts,
+        }
+    }
+}
+
+/// Step timeouts in milliseconds, named as in the scheme's Tendermint params.
+/// A missing timeout is kept unchanged by an update.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Timeouts {
+    timeout_propose: Option<u64>,
+    timeout_propose_delta: Option<u64>,
+    timeout_prevote: Option<u64>,
+    timeout_prevote_delta: Option<u64>,
+    timeout_precommit: Option<u64>,
+    timeout_precommit_delta: Option<u64>,
+    timeout_commit: Option<u64>,
+}
+
+impl From<TimeoutParams> for Timeouts {
+    fn from(timeouts: TimeoutParams) -> Self {
+        let ms = |duration: Duration| Some(duration.as_millis() as u64);
+        Self {
+            timeout_propose: ms(timeouts.propose),
+            timeout_propose_delta: ms(timeouts.propose_delta),
+            timeout_prevote: ms(timeouts.prevote),
+            timeout_prevote_delta: ms(timeouts.prevote_delta),
+            timeout_precommit: ms(timeouts.precommit),
+            timeout_precommit_delta: ms(timeouts.precommit_delta),
+            timeout_commit: ms(timeouts.commit),
+        }
+    }
+}
+
+impl From<Timeouts> for TendermintParams {
+    fn from(timeouts: Timeouts) -> Self {
+        Self {
+            timeout_propose: timeouts.timeout_propose.map(Into::into),
+            timeout_propose_delta: timeouts.timeout_propose_delta.map(Into::into),
+            timeout_prevote: timeouts.timeout_prevote.map(Into::into),
+            timeout_prevote_delta: timeouts.timeout_prevote_delta.map(Into::into),
+            timeout_precommit: timeouts.timeout_precommit.map(Into::into),
+            timeout_precommit_delta: timeouts.timeout_precommit_delta.map(Into::into),
+            timeout_commit: timeouts.timeout_commit.map(Into::into),
+            allowed_past_timegap: None,
+            allowed_future_timegap: None,
+        }
+    }
+}
@@ -0,0 +1,234 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::{Block as CoreBlock, LocalizedTransaction};
+use ccrypto::blake256;
+use cjson::bytes::Bytes;
+use ckey::{Ed25519Public as Public, NetworkId};
+use coordinator::types::Event;
+use coordinator::TxSizes;
+use ctypes::{BlockHash, BlockId, BlockNumber, TransactionIndex, TxHash};
+use primitives::H256;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// An integer written in hex with the `0x` prefix, as Ethereum writes quantities.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EthQuantity(pub u64);
+
+impl Serialize for EthQuantity {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer, {
+        serializer.serialize_str(&format!("0x{:x}", self.0))
+    }
+}
+
+impl From<NetworkId> for EthQuantity {
+    /// The two characters of the network id, read as a big-endian integer.
+    fn from(network_id: NetworkId) -> Self {
+        EthQuantity(u64::from(u16::from_be_bytes([network_id[0], network_id[1]])))
+    }
+}
+
+/// A block number in hex, or one of the tags `earliest`, `latest` and `pending`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EthBlockNumber {
+    Earliest,
+    Latest,
+    /// There is no pending block in Foundry, so it is the same as `Latest`.
+    Pending,
+    Number(BlockNumber),
+}
+
+impl<'de> Deserialize<'de> for EthBlockNumber {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>, {
+        let s = String::deserialize(deserializer)?;
+        match s.as_str() {
+            "earliest" => Ok(EthBlockNumber::Earliest),
+            "latest" => Ok(EthBlockNumber::Latest),
+            "pending" => Ok(EthBlockNumber::Pending),
+            _ => {
+                let hex =
+                    s.strip_prefix("0x").ok_or_else(|| D::Error::custom(format!("Invalid block number {}", s)))?;
+                let number = u64::from_str_radix(hex, 16)
+                    .map_err(|_| D::Error::custom(format!("Invalid block number {}", s)))?;
+                Ok(EthBlockNumber::Number(number))
+            }
+        }
+    }
+}
+
+impl From<EthBlockNumber> for BlockId {
+    fn from(number: EthBlockNumber) -> Self {
+        match number {
+            EthBlockNumber::Earliest => BlockId::Earliest,
+            EthBlockNumber::Latest | EthBlockNumber::Pending => BlockId::Latest,
+            EthBlockNumber::Number(number) => BlockId::Number(number),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum EthBlockTransactions {
+    Hashes(Vec<TxHash>),
+    Full(Vec<EthTransaction>),
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EthBlock {
+    number: EthQuantity,
+    hash: BlockHash,
+    parent_hash: BlockHash,
+    timestamp: EthQuantity,
+    /// The public key of the author, since Foundry has no Ethereum addresses.
+    miner: Public,
+    state_root: H256,
+    transactions_root: H256,
+    extra_data: Bytes,
+    transactions: EthBlockTransactions,
+}
+
+impl EthBlock {
+    /// Has the full transactions if `full` is true, or only their hashes.
+    pub fn from_core(block: CoreBlock, full: bool) -> Self {
+        let header = &block.header;
+        let number = header.number();
+        let hash = header.hash();
+        let transactions = if full {
+            EthBlockTransactions::Full(
+                block
+                    .transactions
+                    .iter()
+                    .enumerate()
+                    .map(|(index, tx)| {
+                        EthTransaction::from(LocalizedTransaction {
+                            tx: tx.clone(),
+                            block_number: number,
+                            block_hash: hash,
+                            transaction_index: index as TransactionIndex,
+                        })
+                    })
+                    .collect(),
+            )
+        } else {
+            EthBlockTransactions::Hashes(block.transactions.iter().map(|tx| tx.hash()).collect())
+        };
+        Self {
+            number: EthQuantity(number),
+            hash,
+            parent_hash: *header.parent_hash(),
+            timestamp: EthQuantity(header.timestamp()),
+            miner: *header.author(),
+            state_root: *header.state_root(),
+            transactions_root: *header.transactions_root(),
+            extra_data: header.extra_data().clone().into(),
+            transactions,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EthTransaction {
+    hash: TxHash,
+    block_hash: BlockHash,
+    block_number: EthQuantity,
+    transaction_index: EthQuantity,
+    /// The type of the transaction, which names the module handling it.
+    tx_type: String,
+    /// The body of the transaction, which only the module handling it understands.
+    input: Bytes,
+}
+
+impl From<LocalizedTransaction> for EthTransaction {
+    fn from(tx: LocalizedTransaction) -> Self {
+        Self {
+            hash: tx.tx.hash(),
+            block_hash: tx.block_hash,
+            block_number: EthQuantity(tx.block_number),
+            transaction_index: EthQuantity(tx.transaction_index.into()),
+            tx_type: tx.tx.tx_type().to_string(),
+            input: tx.tx.body().clone().into(),
+        }
+    }
+}
+
+/// An event of a transaction written as an Ethereum log.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EthLog {
+    log_index: EthQuantity,
+    transaction_hash: TxHash,
+    transaction_index: EthQuantity,
+    block_hash: BlockHash,
+    block_number: EthQuantity,
+    /// The hash of the event key, to filter the logs by the key.
+    topics: Vec<H256>,
+    data: Bytes,
+    key: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EthReceipt {
+    transaction_hash: TxHash,
+    transaction_index: EthQuantity,
+    block_hash: BlockHash,
+    block_number: EthQuantity,
+    /// Always 1, since the transactions failed while being executed are not included in blocks.
+    status: EthQuantity,
+    /// The signatures and the envelope of the transaction, which fee policies may charge less for.
+    witness_size: EthQuantity,
+    payload_size: EthQuantity,
+    logs: Vec<EthLog>,
+}
+
+impl EthReceipt {
+    pub fn new(tx: LocalizedTransaction, sizes: TxSizes, events: Vec<Event>) -> Self {
+        let transaction_hash = tx.tx.hash();
+        let transaction_index = EthQuantity(tx.transaction_index.into());
+        let block_number = EthQuantity(tx.block_number);
+        let logs = events
+            .into_iter()
+            .enumerate()
+            .map(|(index, event)| EthLog {
+                log_index: EthQuantity(index as u64),
+                transaction_hash,
+                transaction_index,
+                block_hash: tx.block_hash,
+                block_number,
+                topics: vec![blake256(event.key.as_bytes())],
+                data: event.value.into(),
+                key: event.key,
+            })
+            .collect();
+        Self {
+            transaction_hash,
+            transaction_index,
+            block_hash: tx.block_hash,
+            block_number,
+            status: EthQuantity(1),
+            witness_size: EthQuantity(sizes.witness as u64),
+            payload_size: EthQuantity(sizes.payload as u64),
+            logs,
+        }
+    }
+}
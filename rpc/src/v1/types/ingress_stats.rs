@@ -0,0 +1,38 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::IngressStats as CoreIngressStats;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IngressStats {
+    tx_type: String,
+    /// The external transactions admitted per second.
+    limit: u32,
+    admitted: u64,
+    dropped: u64,
+}
+
+impl From<CoreIngressStats> for IngressStats {
+    fn from(stats: CoreIngressStats) -> Self {
+        Self {
+            tx_type: stats.tx_type,
+            limit: stats.limit,
+            admitted: stats.admitted,
+            dropped: stats.dropped,
+        }
+    }
+}
@@ -0,0 +1,86 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::ValidatorSet;
+use cjson::bytes::Bytes;
+use ckey::Signature;
+use ctypes::{Commit as CoreCommit, ModuleDatumProof as CoreModuleDatumProof, StorageId};
+use primitives::H256;
+
+/// What a light client of this chain needs to accept the header of a block.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LightClientUpdate {
+    /// The RLP-encoded header with its seal.
+    pub header: Bytes,
+    /// The validators voting on the block, whose hash is the `nextValidatorSetHash` of its parent.
+    pub validator_set: ValidatorSet,
+    pub commit: Commit,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Commit {
+    pub view: u64,
+    pub precommits: Vec<Precommit>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Precommit {
+    pub signer_index: usize,
+    pub signature: Signature,
+    pub timestamp: u64,
+}
+
+impl From<CoreCommit> for Commit {
+    fn from(commit: CoreCommit) -> Self {
+        Self {
+            view: commit.view,
+            precommits: commit
+                .precommits
+                .into_iter()
+                .map(|precommit| Precommit {
+                    signer_index: precommit.signer_index,
+                    signature: precommit.signature,
+                    timestamp: precommit.timestamp,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModuleDatumProof {
+    pub storage_id: StorageId,
+    pub key: Bytes,
+    pub datum: Option<Bytes>,
+    pub module_roots: Vec<H256>,
+    pub nodes: Vec<Bytes>,
+}
+
+impl From<CoreModuleDatumProof> for ModuleDatumProof {
+    fn from(proof: CoreModuleDatumProof) -> Self {
+        Self {
+            storage_id: proof.storage_id,
+            key: proof.key.into(),
+            datum: proof.datum.map(Bytes::new),
+            module_roots: proof.module_roots,
+            nodes: proof.nodes.into_iter().map(Bytes::new).collect(),
+        }
+    }
+}
@@ -14,14 +14,47 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+mod archive_stats;
+mod backfill_status;
 mod block;
+mod consensus;
+mod eth;
+mod ingress_stats;
+mod integrity;
+mod light_client;
+mod maintenance;
+mod network_identity;
+mod node_health;
+mod node_status;
+mod orphan_stats;
+mod peer_reputation;
+mod peer_stats;
 mod transaction;
 mod unsigned_transaction;
 mod work;
 
+pub use self::archive_stats::ArchiveStats;
+pub use self::backfill_status::BackfillStatus;
 pub use self::block::Block;
+pub use self::block::BlockHeader;
 pub use self::block::BlockNumberAndHash;
-pub use self::transaction::{PendingTransactions, Transaction};
+pub use self::consensus::{HeightStats, Timeouts};
+pub use self::eth::{EthBlock, EthBlockNumber, EthQuantity, EthReceipt, EthTransaction};
+pub use self::ingress_stats::IngressStats;
+pub use self::integrity::IntegrityReport;
+pub use self::light_client::{Commit, LightClientUpdate, ModuleDatumProof, Precommit};
+pub use self::maintenance::MaintenanceStatus;
+pub use self::network_identity::NetworkIdentity;
+pub use self::node_health::NodeHealth;
+pub use self::node_status::{HealthIssue, HealthStatus, MemPoolBacklog, NodeStatus, StalledModuleCall, SyncStatus};
+pub use self::orphan_stats::OrphanStats;
+pub use self::peer_reputation::PeerReputation;
+pub use self::peer_stats::PeerStats;
+pub use self::transaction::{
+    InspectedTransaction, LocalTransaction, MemPoolItem, PendingTransactionPage, PendingTransactionQuery,
+    PendingTransactions, ScheduledTransaction, SentTransaction, Transaction, TransactionEvent, TransactionPreview,
+    TransactionStatus,
+};
 pub use self::unsigned_transaction::UnsignedTransaction;
 pub use self::work::Work;
 
@@ -0,0 +1,41 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ckey::{Ed25519Public as Public, Signature};
+use ctypes::NetworkIdentity as CoreNetworkIdentity;
+use std::net::SocketAddr;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkIdentity {
+    validator: Public,
+    node_key: Public,
+    node_address: SocketAddr,
+    signature: Signature,
+    node_signature: Signature,
+}
+
+impl From<CoreNetworkIdentity> for NetworkIdentity {
+    fn from(identity: CoreNetworkIdentity) -> Self {
+        Self {
+            validator: identity.validator,
+            node_key: identity.node_key,
+            node_address: identity.node_address,
+            signature: identity.signature,
+            node_signature: identity.node_signature,
+        }
+    }
+}
@@ -0,0 +1,47 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::NodeHealth as CoreNodeHealth;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeHealth {
+    blocks_near_max_body_size: bool,
+    mem_pool_near_limit: bool,
+    candidate_metadata_near_limit: bool,
+    near_full_blocks: u64,
+    near_full_mem_pool_checks: u64,
+    near_limit_candidate_metadata_checks: u64,
+    sentry_lost: bool,
+    all_sentries_lost: bool,
+    sentry_losses: u64,
+}
+
+impl From<CoreNodeHealth> for NodeHealth {
+    fn from(health: CoreNodeHealth) -> Self {
+        Self {
+            blocks_near_max_body_size: health.blocks_near_max_body_size,
+            mem_pool_near_limit: health.mem_pool_near_limit,
+            candidate_metadata_near_limit: health.candidate_metadata_near_limit,
+            near_full_blocks: health.near_full_blocks,
+            near_full_mem_pool_checks: health.near_full_mem_pool_checks,
+            near_limit_candidate_metadata_checks: health.near_limit_candidate_metadata_checks,
+            sentry_lost: health.sentry_lost,
+            all_sentries_lost: health.all_sentries_lost,
+            sentry_losses: health.sentry_losses,
+        }
+    }
+}
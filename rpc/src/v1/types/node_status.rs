@@ -0,0 +1,165 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use ccore::MemPoolUsage;
+use coordinator::PendingModuleCall;
+use ctypes::{BlockHash, BlockNumber};
+
+/// How fit the node is to serve.
+/// The codes follow the exit codes of the Nagios plugins, so a load balancer can act on either.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HealthStatus {
+    Ok,
+    /// The node still serves, but an operator should look at it.
+    Degraded,
+    /// The node should be taken out of service.
+    Unhealthy,
+}
+
+impl HealthStatus {
+    pub fn code(self) -> u8 {
+        match self {
+            HealthStatus::Ok => 0,
+            HealthStatus::Degraded => 1,
+            HealthStatus::Unhealthy => 2,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HealthIssue {
+    /// The best block is too far behind the best blocks of the peers.
+    Syncing,
+    /// No block has been imported for a while.
+    StaleBestBlock,
+    NoPeers,
+    MemPoolBacklog,
+    /// A call into a module has not returned for a while, so its sandbox may be hung.
+    StalledModuleCall,
+    LowDiskSpace,
+    OutOfDiskSpace,
+    SentryLost,
+    AllSentriesLost,
+    /// The node accepts no transactions and proposes no blocks until an operator resumes it.
+    Maintenance,
+}
+
+impl HealthIssue {
+    pub fn severity(self) -> HealthStatus {
+        match self {
+            HealthIssue::StaleBestBlock
+            | HealthIssue::NoPeers
+            | HealthIssue::MemPoolBacklog
+            | HealthIssue::LowDiskSpace
+            | HealthIssue::SentryLost
+            | HealthIssue::Maintenance => HealthStatus::Degraded,
+            HealthIssue::Syncing
+            | HealthIssue::StalledModuleCall
+            | HealthIssue::OutOfDiskSpace
+            | HealthIssue::AllSentriesLost => HealthStatus::Unhealthy,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeStatus {
+    pub status: HealthStatus,
+    pub code: u8,
+    pub issues: Vec<HealthIssue>,
+    pub sync: SyncStatus,
+    pub mem_pool: MemPoolBacklog,
+    pub stalled_module_calls: Vec<StalledModuleCall>,
+    /// None if the free space of the disk cannot be read on this platform
+    pub disk_available_bytes: Option<u64>,
+}
+
+impl NodeStatus {
+    pub fn new(
+        issues: Vec<HealthIssue>,
+        sync: SyncStatus,
+        mem_pool: MemPoolBacklog,
+        stalled_module_calls: Vec<StalledModuleCall>,
+        disk_available_bytes: Option<u64>,
+    ) -> Self {
+        let status = issues.iter().map(|issue| issue.severity()).max().unwrap_or(HealthStatus::Ok);
+        Self {
+            status,
+            code: status.code(),
+            issues,
+            sync,
+            mem_pool,
+            stalled_module_calls,
+            disk_available_bytes,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStatus {
+    pub syncing: bool,
+    pub best_block_number: BlockNumber,
+    pub best_block_hash: BlockHash,
+    pub last_block_age_seconds: u64,
+    /// None if no peer has a best block known to this node
+    pub highest_peer_block_number: Option<BlockNumber>,
+    pub blocks_behind: u64,
+    pub peer_count: usize,
+    /// The peers whose best blocks are not imported yet, so their heights are unknown
+    pub peers_with_unknown_best: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemPoolBacklog {
+    count: usize,
+    count_limit: usize,
+    mem_usage: usize,
+    memory_limit: usize,
+}
+
+impl From<MemPoolUsage> for MemPoolBacklog {
+    fn from(usage: MemPoolUsage) -> Self {
+        Self {
+            count: usage.count,
+            count_limit: usage.count_limit,
+            mem_usage: usage.mem_usage,
+            memory_limit: usage.memory_limit,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StalledModuleCall {
+    service: &'static str,
+    method: &'static str,
+    key: String,
+    elapsed_ms: u64,
+}
+
+impl From<PendingModuleCall> for StalledModuleCall {
+    fn from(call: PendingModuleCall) -> Self {
+        Self {
+            service: call.service,
+            method: call.method,
+            key: call.key,
+            elapsed_ms: call.elapsed.as_millis() as u64,
+        }
+    }
+}
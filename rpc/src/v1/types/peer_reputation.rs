@@ -0,0 +1,39 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use cnetwork::PeerReputation as NetworkPeerReputation;
+use std::net::IpAddr;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerReputation {
+    address: IpAddr,
+    score: i64,
+    bans: u32,
+    /// The remaining seconds of the current ban.
+    banned_for: Option<u64>,
+}
+
+impl From<NetworkPeerReputation> for PeerReputation {
+    fn from(reputation: NetworkPeerReputation) -> Self {
+        Self {
+            address: reputation.address,
+            score: reputation.score,
+            bans: reputation.bans,
+            banned_for: reputation.banned_for.map(|duration| duration.as_secs()),
+        }
+    }
+}
@@ -0,0 +1,74 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use cnetwork::{PeerStats as NetworkPeerStats, PeerTraffic};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerStats {
+    address: SocketAddr,
+    score: i64,
+    windows: Vec<TrafficWindow>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrafficWindow {
+    seconds: u64,
+    protocols: HashMap<String, ProtocolTraffic>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProtocolTraffic {
+    sent_bytes: usize,
+    sent_messages: usize,
+    received_bytes: usize,
+    received_messages: usize,
+}
+
+impl From<PeerTraffic> for ProtocolTraffic {
+    fn from(traffic: PeerTraffic) -> Self {
+        Self {
+            sent_bytes: traffic.sent.bytes,
+            sent_messages: traffic.sent.messages,
+            received_bytes: traffic.received.bytes,
+            received_messages: traffic.received.messages,
+        }
+    }
+}
+
+impl From<NetworkPeerStats> for PeerStats {
+    fn from(stats: NetworkPeerStats) -> Self {
+        Self {
+            address: stats.address.into(),
+            score: stats.score,
+            windows: stats
+                .windows
+                .into_iter()
+                .map(|(window, protocols)| TrafficWindow {
+                    seconds: window.as_secs(),
+                    protocols: protocols
+                        .into_iter()
+                        .map(|(protocol, traffic)| (protocol.to_string(), traffic.into()))
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+}
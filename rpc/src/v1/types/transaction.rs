@@ -14,8 +14,19 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use ccore::{LocalizedTransaction, PendingTransactions as PendingVerifiedTransactions};
-use coordinator::Transaction as ValidatorTransaction;
+use ccore::{
+    LocalizedTransaction, PendingTransactions as PendingVerifiedTransactions, PoolCursor, PoolOrder, PoolQuery,
+    PoolSort,
+};
+use cjson::bytes::Bytes;
+use ckey::Ed25519Public as Public;
+use coordinator::types::{
+    Event, InspectedPayload as CoreInspectedPayload, TransactionOutcome, TransactionPreview as CoreTransactionPreview,
+};
+use coordinator::{Transaction as ValidatorTransaction, TransactionWithMetadata, TxOrigin, TxSizes};
+use csync::ResendStatus;
+use ctypes::{BlockHash, BlockNumber, TransactionIndex, TxHash};
+use std::ops::Range;
 
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -28,6 +39,450 @@ pub struct PendingTransactions {
     last_timestamp: Option<u64>,
 }
 
+/// A transaction with its payload decoded by the module inspecting its type.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InspectedTransaction {
+    hash: TxHash,
+    tx_type: String,
+    payload: InspectedPayload,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum InspectedPayload {
+    #[serde(rename_all = "camelCase")]
+    Decoded {
+        action: String,
+        fields: serde_json::Value,
+    },
+    /// The sub-transactions of a composite transaction, each inspected by the module of its type.
+    #[serde(rename_all = "camelCase")]
+    Composite {
+        sub_transactions: Vec<InspectedTransaction>,
+    },
+    /// No module inspects the type, so the body is left as it is.
+    #[serde(rename_all = "camelCase")]
+    Opaque {
+        body: Bytes,
+    },
+    #[serde(rename_all = "camelCase")]
+    Malformed {
+        reason: String,
+        body: Bytes,
+    },
+}
+
+impl InspectedTransaction {
+    pub fn new(
+        tx: &ValidatorTransaction,
+        inspect: &dyn Fn(&ValidatorTransaction) -> Option<Result<CoreInspectedPayload, String>>,
+    ) -> Self {
+        let malformed = |reason: String| InspectedPayload::Malformed {
+            reason,
+            body: tx.body().clone().into(),
+        };
+        let payload = if tx.is_composite() {
+            match tx.sub_transactions() {
+                Some(sub_transactions) => InspectedPayload::Composite {
+                    sub_transactions: sub_transactions.iter().map(|tx| Self::new(tx, inspect)).collect(),
+                },
+                None => malformed("Not a well-formed composite transaction".to_string()),
+            }
+        } else {
+            match inspect(tx) {
+                Some(Ok(CoreInspectedPayload {
+                    action,
+                    fields,
+                })) => match serde_json::from_str(&fields) {
+                    Ok(fields) => InspectedPayload::Decoded {
+                        action,
+                        fields,
+                    },
+                    Err(err) => malformed(format!("The inspector of {} returned invalid JSON: {}", tx.tx_type(), err)),
+                },
+                Some(Err(reason)) => malformed(reason),
+                None => InspectedPayload::Opaque {
+                    body: tx.body().clone().into(),
+                },
+            }
+        };
+        Self {
+            hash: tx.hash(),
+            tx_type: tx.tx_type().to_string(),
+            payload,
+        }
+    }
+}
+
+/// A transaction in the mem pool waiting for its activation block.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledTransaction {
+    hash: TxHash,
+    tx_type: String,
+    activation_block: BlockNumber,
+}
+
+impl From<(BlockNumber, ValidatorTransaction)> for ScheduledTransaction {
+    fn from((activation_block, tx): (BlockNumber, ValidatorTransaction)) -> Self {
+        Self {
+            hash: tx.hash(),
+            tx_type: tx.tx_type().to_string(),
+            activation_block,
+        }
+    }
+}
+
+/// A transaction submitted to this node, which is resent to the peers until it leaves the mem pool.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalTransaction {
+    hash: TxHash,
+    tx_type: String,
+    inserted_timestamp: u64,
+    resend_attempts: u32,
+    next_resend_in_ms: Option<u64>,
+}
+
+impl LocalTransaction {
+    pub fn new(item: &TransactionWithMetadata, status: Option<&ResendStatus>) -> Self {
+        Self {
+            hash: item.tx.hash(),
+            tx_type: item.tx.tx_type().to_string(),
+            inserted_timestamp: item.inserted_timestamp,
+            resend_attempts: status.map_or(0, |status| status.attempts),
+            next_resend_in_ms: status
+                .and_then(|status| status.next_resend_in)
+                .map(|next_resend_in| next_resend_in.as_millis() as u64),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionEvent {
+    key: String,
+    value: Bytes,
+}
+
+impl From<Event> for TransactionEvent {
+    fn from(event: Event) -> Self {
+        Self {
+            key: event.key,
+            value: event.value.into(),
+        }
+    }
+}
+
+/// The outcome of a sub-transaction of a composite transaction.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubOutcome {
+    gas_used: u64,
+    events: Vec<TransactionEvent>,
+}
+
+impl From<TransactionOutcome> for SubOutcome {
+    fn from(outcome: TransactionOutcome) -> Self {
+        Self {
+            gas_used: outcome.gas_used,
+            events: outcome.events.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// The expected result of a transaction, as if it were included in the next block.
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum TransactionPreview {
+    /// The owner module rejects the transaction, so it can't enter the mem pool.
+    #[serde(rename_all = "camelCase")]
+    Rejected {
+        error_code: u32,
+    },
+    /// The transaction fails while being executed, so it would be dropped from the block.
+    #[serde(rename_all = "camelCase")]
+    Failed {
+        gas_estimate: u64,
+    },
+    #[serde(rename_all = "camelCase")]
+    Executed {
+        gas_estimate: u64,
+        gas_used: u64,
+        events: Vec<TransactionEvent>,
+        /// Empty unless the transaction is a composite transaction.
+        sub_outcomes: Vec<SubOutcome>,
+    },
+}
+
+impl From<CoreTransactionPreview> for TransactionPreview {
+    fn from(preview: CoreTransactionPreview) -> Self {
+        match preview {
+            CoreTransactionPreview::Rejected(error_code) => TransactionPreview::Rejected {
+                error_code,
+            },
+            CoreTransactionPreview::Failed {
+                gas_estimate,
+            } => TransactionPreview::Failed {
+                gas_estimate,
+            },
+            CoreTransactionPreview::Executed {
+                gas_estimate,
+                outcome,
+            } => TransactionPreview::Executed {
+                gas_estimate,
+                gas_used: outcome.gas_used,
+                events: outcome.events.into_iter().map(Into::into).collect(),
+                sub_outcomes: outcome.sub_outcomes.into_iter().map(Into::into).collect(),
+            },
+        }
+    }
+}
+
+/// Where a transaction is in its lifecycle.
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum TransactionStatus {
+    /// The transaction is in the mem pool, waiting to be included in a block or for its activation block.
+    Pending,
+    #[serde(rename_all = "camelCase")]
+    Included {
+        block_number: BlockNumber,
+        block_hash: BlockHash,
+        transaction_index: TransactionIndex,
+        witness_size: usize,
+        payload_size: usize,
+        events: Vec<TransactionEvent>,
+    },
+    /// The transaction is neither in the mem pool nor in the chain.
+    /// It failed and was dropped from the mem pool, or it has never been sent to this node.
+    Unknown,
+}
+
+impl TransactionStatus {
+    pub fn included(tx: LocalizedTransaction, sizes: TxSizes, events: Vec<Event>) -> Self {
+        TransactionStatus::Included {
+            block_number: tx.block_number,
+            block_hash: tx.block_hash,
+            transaction_index: tx.transaction_index,
+            witness_size: sizes.witness,
+            payload_size: sizes.payload,
+            events: events.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    pub fn is_pending(&self) -> bool {
+        matches!(self, TransactionStatus::Pending)
+    }
+}
+
+/// A transaction sent to the mem pool, with its status after waiting for it to leave the mem pool.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SentTransaction {
+    pub hash: TxHash,
+    pub status: TransactionStatus,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MemPoolQueue {
+    Current,
+    Scheduled,
+}
+
+/// A transaction in the mem pool with the metadata, as dumped for debugging.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemPoolItem {
+    hash: TxHash,
+    tx_type: String,
+    body: Bytes,
+    origin: TxOrigin,
+    queue: MemPoolQueue,
+    inserted_block_number: BlockNumber,
+    inserted_timestamp: u64,
+    insertion_id: u64,
+    activation_block: Option<BlockNumber>,
+    /// Only informative. The sizes are ignored when the item is loaded.
+    #[serde(default)]
+    witness_size: usize,
+    #[serde(default)]
+    payload_size: usize,
+}
+
+impl MemPoolItem {
+    pub fn new(item: TransactionWithMetadata, sizes: TxSizes) -> Self {
+        Self {
+            hash: item.hash(),
+            tx_type: item.tx.tx_type().to_string(),
+            body: item.tx.body().clone().into(),
+            origin: item.origin,
+            queue: if item.activation_block.is_some() {
+                MemPoolQueue::Scheduled
+            } else {
+                MemPoolQueue::Current
+            },
+            inserted_block_number: item.inserted_block_number,
+            inserted_timestamp: item.inserted_timestamp,
+            insertion_id: item.insertion_id,
+            activation_block: item.activation_block,
+            witness_size: sizes.witness,
+            payload_size: sizes.payload,
+        }
+    }
+
+    /// Rebuilds the transaction, failing if it doesn't match the hash or the queue.
+    pub fn try_into_transaction(self) -> Result<TransactionWithMetadata, String> {
+        let tx = ValidatorTransaction::new(self.tx_type, self.body.into_vec());
+        if tx.hash() != self.hash {
+            return Err(format!("The transaction doesn't match the hash {}", self.hash))
+        }
+        let activation_block = match (self.queue, self.activation_block) {
+            (MemPoolQueue::Current, None) => None,
+            (MemPoolQueue::Scheduled, Some(activation_block)) => Some(activation_block),
+            _ => {
+                return Err(format!(
+                    "The transaction {} must have an activation block only if it's scheduled",
+                    self.hash
+                ))
+            }
+        };
+        Ok(TransactionWithMetadata::new(
+            tx,
+            self.origin,
+            self.inserted_block_number,
+            self.inserted_timestamp,
+            self.insertion_id,
+            activation_block,
+        ))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PendingTransactionSort {
+    Insertion,
+    Fee,
+    Seq,
+}
+
+impl Default for PendingTransactionSort {
+    fn default() -> Self {
+        PendingTransactionSort::Insertion
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PendingTransactionOrder {
+    Ascending,
+    Descending,
+}
+
+impl Default for PendingTransactionOrder {
+    fn default() -> Self {
+        PendingTransactionOrder::Ascending
+    }
+}
+
+/// The last transaction of a page, from which the next page continues.
+/// `key` is its fee or sequence if the page is sorted by it.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingTransactionCursor {
+    key: Option<u64>,
+    insertion_id: u64,
+    hash: TxHash,
+}
+
+/// The range from `from`, inclusive, to `to`, exclusive, if either bound is given.
+fn range(from: Option<u64>, to: Option<u64>) -> Option<Range<u64>> {
+    if from.is_none() && to.is_none() {
+        None
+    } else {
+        Some(from.unwrap_or(0)..to.unwrap_or(u64::MAX))
+    }
+}
+
+/// Selects a page of the pending transactions. The fields left out don't filter them.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PendingTransactionQuery {
+    tx_type: Option<String>,
+    origin: Option<TxOrigin>,
+    signer: Option<Public>,
+    /// The lowest timestamp of the block at which the transactions were inserted, inclusive.
+    inserted_from: Option<u64>,
+    /// The highest timestamp of the block at which the transactions were inserted, exclusive.
+    inserted_to: Option<u64>,
+    fee_from: Option<u64>,
+    fee_to: Option<u64>,
+    seq_from: Option<u64>,
+    seq_to: Option<u64>,
+    #[serde(default)]
+    sort: PendingTransactionSort,
+    #[serde(default)]
+    order: PendingTransactionOrder,
+    cursor: Option<PendingTransactionCursor>,
+    limit: Option<usize>,
+}
+
+impl PendingTransactionQuery {
+    /// Converts into the query of the mem pool, listing at most `max_limit` transactions.
+    pub fn try_into_pool_query(self, max_limit: usize) -> Result<PoolQuery, String> {
+        let limit = self.limit.unwrap_or(max_limit);
+        if limit == 0 || limit > max_limit {
+            return Err(format!("The limit must be between 1 and {}", max_limit))
+        }
+        Ok(PoolQuery {
+            tx_type: self.tx_type,
+            origin: self.origin,
+            signer: self.signer,
+            inserted: self.inserted_from.unwrap_or(0)..self.inserted_to.unwrap_or(u64::MAX),
+            fee: range(self.fee_from, self.fee_to),
+            seq: range(self.seq_from, self.seq_to),
+            sort: match self.sort {
+                PendingTransactionSort::Insertion => PoolSort::Insertion,
+                PendingTransactionSort::Fee => PoolSort::Fee,
+                PendingTransactionSort::Seq => PoolSort::Seq,
+            },
+            order: match self.order {
+                PendingTransactionOrder::Ascending => PoolOrder::Ascending,
+                PendingTransactionOrder::Descending => PoolOrder::Descending,
+            },
+            after: self.cursor.map(|cursor| PoolCursor {
+                key: cursor.key,
+                insertion_id: cursor.insertion_id,
+                hash: cursor.hash,
+            }),
+            limit,
+        })
+    }
+}
+
+/// A page of the pending transactions, with the cursor of the next page if there are more.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingTransactionPage {
+    transactions: Vec<MemPoolItem>,
+    next_cursor: Option<PendingTransactionCursor>,
+}
+
+impl PendingTransactionPage {
+    pub fn new(transactions: Vec<MemPoolItem>, next: Option<PoolCursor>) -> Self {
+        Self {
+            transactions,
+            next_cursor: next.map(|cursor| PendingTransactionCursor {
+                key: cursor.key,
+                insertion_id: cursor.insertion_id,
+                hash: cursor.hash,
+            }),
+        }
+    }
+}
+
 impl From<PendingVerifiedTransactions> for PendingTransactions {
     fn from(_tx: PendingVerifiedTransactions) -> Self {
         unimplemented!()
@@ -46,8 +46,9 @@ use cdb::{AsHashDB, DatabaseError};
 use coordinator::context::{StorageAccess, SubStorageAccess};
 use ctypes::errors::RuntimeError;
 use ctypes::util::unexpected::Mismatch;
-use ctypes::{CommonParams, ConsensusParams, StorageId};
+use ctypes::{CommonParams, ConsensusParams, ModuleDatumProof, StorageId};
 use kvdb::DBTransaction;
+use merkle_trie::proof::{CryptoProof, CryptoStructure};
 use merkle_trie::{Result as TrieResult, TrieError, TrieFactory};
 use parking_lot::{Mutex, RwLock};
 use primitives::{Bytes, H256};
@@ -125,6 +126,26 @@ impl TopStateView for TopLevelState {
         let trie = TrieFactory::readonly(db.as_hashdb(), &self.root)?;
         Ok(self.top_cache.action_data(key, &trie)?.map(Into::into))
     }
+
+    fn module_datum_proof(&self, storage_id: StorageId, key: &[u8]) -> TrieResult<Option<ModuleDatumProof>> {
+        let module_root = match self.module_root(storage_id)? {
+            Some(module_root) => module_root,
+            None => return Ok(None),
+        };
+        let module_roots = self.module_roots()?;
+        let datum = self.module_datum(storage_id, &key)?.map(|datum| datum.content());
+
+        let db = self.db.read();
+        let trie = TrieFactory::readonly(db.as_hashdb(), &module_root)?;
+        let (_, CryptoProof(nodes)) = trie.make_proof(ModuleDatumAddress::new(key, storage_id).as_ref())?;
+        Ok(Some(ModuleDatumProof {
+            storage_id,
+            key: key.to_vec(),
+            datum,
+            module_roots,
+            nodes,
+        }))
+    }
 }
 
 impl StorageAccess for TopLevelState {
@@ -452,6 +473,37 @@ mod test_module_states {
         }
     }
 
+    #[test]
+    fn module_datum_proof_is_verified_against_the_module_roots() {
+        let mut top_level_state = get_temp_state();
+        top_level_state.create_module().unwrap();
+        top_level_state.create_module().unwrap();
+        {
+            let state_with_id_1 = top_level_state.module_state_mut(1).unwrap();
+            module_level!(state_with_id_1, {
+                set: [(key: "alice" => datum_str: "Alice is a doctor")]
+            });
+        }
+        let (db, root) = top_level_state.commit_and_clone_db().unwrap();
+        let top_level_state = TopLevelState::from_existing(db, root).unwrap();
+        let module_roots_hash = ctypes::module_roots_hash(&top_level_state.module_roots().unwrap());
+
+        let proof = top_level_state.module_datum_proof(1, b"alice").unwrap().unwrap();
+        assert_eq!(Some(b"Alice is a doctor".to_vec()), proof.datum);
+        assert!(proof.verify(&module_roots_hash));
+
+        let absence = top_level_state.module_datum_proof(1, b"bob").unwrap().unwrap();
+        assert_eq!(None, absence.datum);
+        assert!(absence.verify(&module_roots_hash));
+
+        let forged = ModuleDatumProof {
+            datum: Some(b"Alice is a lawyer".to_vec()),
+            ..proof
+        };
+        assert!(!forged.verify(&module_roots_hash));
+        assert_eq!(None, top_level_state.module_datum_proof(2, b"alice").unwrap());
+    }
+
     #[test]
     fn without_commit() {
         let mut top_level_state = get_temp_state();
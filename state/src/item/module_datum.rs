@@ -124,6 +124,14 @@ mod tests {
         assert_eq!(&address[2..4], &storage_prefix);
     }
 
+    #[test]
+    fn light_clients_derive_the_same_address_and_value() {
+        let address = ModuleDatumAddress::new("foundry", 500);
+        assert_eq!(address.as_ref(), ctypes::module_datum_address(b"foundry", 500).as_bytes());
+        let datum = ModuleDatum::new(b"Foundry".to_vec());
+        assert_eq!(datum.rlp_bytes(), ctypes::encode_module_datum(b"Foundry"));
+    }
+
     #[test]
     fn different_storage_id_makes_different_address() {
         let address1 = ModuleDatumAddress::new("foundry", 1);
@@ -489,6 +489,10 @@ impl Candidates {
         self.0.len() == 0
     }
 
+    pub fn largest_metadata_size(&self) -> usize {
+        self.0.iter().map(|c| c.metadata.len()).max().unwrap_or(0)
+    }
+
     #[cfg(test)]
     pub fn get_index(&self, account: &Public) -> Option<usize> {
         self.0.iter().position(|c| c.pubkey == *account)
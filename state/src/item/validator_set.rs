@@ -71,6 +71,11 @@ impl NextValidatorSet {
     pub fn save_to_state(&self, state: &mut TopLevelState) -> StateResult<()> {
         self.0.save_to_state(state)
     }
+
+    /// Replaces the public keys of the validators, keeping their stakes.
+    pub fn translate(&mut self, translate: &dyn Fn(&Public) -> Public) {
+        self.0 = NextValidators::from_vector(translate_validators(&self.0, translate));
+    }
 }
 
 impl Deref for NextValidatorSet {
@@ -114,6 +119,32 @@ impl CurrentValidatorSet {
     pub fn update(&mut self, next_validator_set: NextValidatorSet) {
         self.0.update(next_validator_set.clone());
     }
+
+    /// Replaces the public keys of the validators, keeping their stakes.
+    pub fn translate(&mut self, translate: &dyn Fn(&Public) -> Public) {
+        let validators = translate_validators(&self.0, translate);
+        self.0.update(validators);
+    }
+}
+
+// Both validator sets are kept sorted by public key.
+fn translate_validators(validators: &[Validator], translate: &dyn Fn(&Public) -> Public) -> Vec<Validator> {
+    let mut translated: Vec<_> = validators
+        .iter()
+        .map(|validator| {
+            let mut translated = Validator::new(
+                validator.delegation(),
+                validator.deposit(),
+                translate(validator.pubkey()),
+                validator.nominated_at_block_number(),
+                validator.nominated_at_transaction_index(),
+            );
+            translated.set_weight(validator.weight());
+            translated
+        })
+        .collect();
+    translated.sort_unstable_by_key(|validator| *validator.pubkey());
+    translated
 }
 
 impl From<CurrentValidatorSet> for Vec<SimpleValidator> {
@@ -15,7 +15,7 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::{ActionData, Metadata, Module, ModuleDatum, StateDB, StateResult};
-use ctypes::{CommonParams, ConsensusParams, StorageId};
+use ctypes::{CommonParams, ConsensusParams, ModuleDatumProof, StorageId};
 use merkle_trie::Result as TrieResult;
 use primitives::{Bytes, H256};
 
@@ -33,6 +33,17 @@ pub trait TopStateView {
         Ok(self.module(storage_id)?.map(|module| *module.root()))
     }
 
+    /// Get the roots of all module substorages, in the order of their storage ids.
+    fn module_roots(&self) -> TrieResult<Vec<H256>> {
+        let number_of_modules = match self.metadata()? {
+            Some(metadata) => *metadata.number_of_modules(),
+            None => return Ok(Vec::new()),
+        };
+        (0..number_of_modules)
+            .map(|storage_id| Ok(self.module_root(storage_id)?.expect("Module root must exist")))
+            .collect()
+    }
+
     fn action_data(&self, key: &H256) -> TrieResult<Option<ActionData>>;
 
     fn module_datum(&self, storage_id: StorageId, key: &dyn AsRef<[u8]>) -> TrieResult<Option<ModuleDatum>> {
@@ -41,6 +52,10 @@ pub trait TopStateView {
             Some(state) => state.get_datum(key),
         }
     }
+
+    /// Get the proof of the datum of a module under the key, or of its absence.
+    /// `None` if the module doesn't exist.
+    fn module_datum_proof(&self, storage_id: StorageId, key: &[u8]) -> TrieResult<Option<ModuleDatumProof>>;
 }
 
 pub trait ModuleStateView {
@@ -16,21 +16,35 @@
 
 use super::super::message::RequestMessage;
 use ccore::Evidence;
+use cnetwork::NodeId;
 use coordinator::Transaction;
 use ctypes::BlockHash;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::mem::replace;
 
+const MAX_BODY_REQUEST_LENGTH: usize = 128;
+/// The fewest bodies asked of a peer, however often it failed.
+const MIN_BODY_REQUEST_LENGTH: usize = 8;
+/// How many targets from the first one are requested, which bounds the bodies held until the ones before them arrive.
+const MAX_DOWNLOAD_WINDOW: usize = 2048;
+/// The failures after which a peer is asked for the fewest bodies.
+const MAX_COUNTED_FAILURES: u32 = 4;
+
 #[derive(Debug, PartialEq)]
 enum State {
     Queued,
-    Downloading,
+    Downloading {
+        peer: NodeId,
+    },
     Downloaded {
+        peer: NodeId,
         evidences: Vec<Evidence>,
         transactions: Vec<Transaction>,
     },
-    Drained,
+    Drained {
+        peer: NodeId,
+    },
 }
 
 impl Default for State {
@@ -39,24 +53,51 @@ impl Default for State {
     }
 }
 
+impl State {
+    fn is_downloading_from(&self, peer: &NodeId) -> bool {
+        match self {
+            State::Downloading {
+                peer: downloading_from,
+            } => downloading_from == peer,
+            _ => false,
+        }
+    }
+}
+
+/// Downloads the bodies of the imported headers from many peers at once, and hands them over in the chain order.
+///
+/// A peer is asked for fewer bodies after it times out or serves a corrupted body, and for more again as it delivers.
+/// The bodies it failed to deliver are left to the other peers first.
 #[derive(Default)]
 pub struct BodyDownloader {
     targets: Vec<BlockHash>,
     states: HashMap<BlockHash, State>,
+    /// The recent failures of each peer, forgiven one by one as it delivers bodies.
+    failures: HashMap<NodeId, u32>,
+    /// The targets re-queued after the failure of a peer, skipped once by that peer.
+    avoided: HashMap<BlockHash, NodeId>,
 }
 
 impl BodyDownloader {
-    pub fn create_request(&mut self) -> Option<RequestMessage> {
-        const MAX_BODY_REQEUST_LENGTH: usize = 128;
+    pub fn create_request(&mut self, peer: &NodeId) -> Option<RequestMessage> {
+        let length = self.request_length(peer);
         let mut hashes = Vec::new();
-        for t in &self.targets {
+        for t in self.targets.iter().take(MAX_DOWNLOAD_WINDOW) {
             let state = self.states.entry(*t).or_default();
             if *state != State::Queued {
                 continue
             }
-            *state = State::Downloading;
+            if let Entry::Occupied(avoided) = self.avoided.entry(*t) {
+                if avoided.get() == peer {
+                    avoided.remove();
+                    continue
+                }
+            }
+            *state = State::Downloading {
+                peer: *peer,
+            };
             hashes.push(*t);
-            if hashes.len() >= MAX_BODY_REQEUST_LENGTH {
+            if hashes.len() >= length {
                 break
             }
         }
@@ -67,19 +108,37 @@ impl BodyDownloader {
         }
     }
 
-    pub fn import_bodies(&mut self, hashes: Vec<BlockHash>, bodies: Vec<(Vec<Evidence>, Vec<Transaction>)>) {
+    /// Halves the request for every recent failure of the peer.
+    fn request_length(&self, peer: &NodeId) -> usize {
+        let failures = self.failures.get(peer).copied().unwrap_or_default().min(MAX_COUNTED_FAILURES);
+        (MAX_BODY_REQUEST_LENGTH >> failures).max(MIN_BODY_REQUEST_LENGTH)
+    }
+
+    pub fn import_bodies(
+        &mut self,
+        peer: &NodeId,
+        hashes: Vec<BlockHash>,
+        bodies: Vec<(Vec<Evidence>, Vec<Transaction>)>,
+    ) {
         assert_eq!(hashes.len(), bodies.len());
         for (hash, (evidences, transactions)) in hashes.into_iter().zip(bodies) {
             if let Some(state) = self.states.get_mut(&hash) {
-                if state != &State::Downloading {
+                if !state.is_downloading_from(peer) {
                     continue
                 }
                 *state = State::Downloaded {
+                    peer: *peer,
                     evidences,
                     transactions,
                 }
             }
         }
+        if let Entry::Occupied(mut failures) = self.failures.entry(*peer) {
+            *failures.get_mut() -= 1;
+            if *failures.get() == 0 {
+                failures.remove();
+            }
+        }
     }
 
     pub fn get_target_hashes(&self) -> Vec<BlockHash> {
@@ -102,15 +161,31 @@ impl BodyDownloader {
         // XXX: It can be slow.
         self.states.retain(|hash, _| !targets.contains(hash));
         self.targets.retain(|hash| !targets.contains(hash));
+        self.avoided.retain(|hash, _| !targets.contains(hash));
         self.states.shrink_to_fit();
         self.targets.shrink_to_fit();
     }
 
-    pub fn reset_downloading(&mut self, hashes: &[BlockHash]) {
+    /// Queues the bodies the peer didn't deliver in time again, for the other peers.
+    pub fn reset_downloading(&mut self, peer: &NodeId, hashes: &[BlockHash]) {
         cdebug!(SYNC, "Remove downloading by timeout {:?}", hashes);
+        self.requeue(peer, hashes.iter().copied());
+        *self.failures.entry(*peer).or_default() += 1;
+    }
+
+    /// Queues the bodies the disconnected peer was downloading again, and forgets about the peer.
+    pub fn remove_peer(&mut self, peer: &NodeId) {
+        let downloading: Vec<_> =
+            self.states.iter().filter(|(_, state)| state.is_downloading_from(peer)).map(|(hash, _)| *hash).collect();
+        self.requeue(peer, downloading.into_iter());
+        self.failures.remove(peer);
+        self.avoided.retain(|_, avoided| avoided != peer);
+    }
+
+    fn requeue(&mut self, peer: &NodeId, hashes: impl Iterator<Item = BlockHash>) {
         for hash in hashes {
-            if let Some(state) = self.states.get_mut(hash) {
-                if *state == State::Downloading {
+            if let Some(state) = self.states.get_mut(&hash) {
+                if state.is_downloading_from(peer) {
                     *state = State::Queued;
                 }
             }
@@ -120,20 +195,23 @@ impl BodyDownloader {
     pub fn drain(&mut self) -> Vec<(BlockHash, Vec<Evidence>, Vec<Transaction>)> {
         let mut result = Vec::new();
         for hash in &self.targets {
-            let entry = self.states.entry(*hash);
-            let state = match entry {
-                Entry::Vacant(_) => unreachable!(),
-                Entry::Occupied(mut entry) => match entry.get_mut() {
-                    state @ State::Downloaded {
-                        ..
-                    } => replace(state, State::Drained),
-                    _ => break,
-                },
+            let peer = match self.states.get(hash) {
+                Some(State::Downloaded {
+                    peer,
+                    ..
+                }) => *peer,
+                Some(_) => break,
+                None => unreachable!(),
             };
+            let state = self.states.get_mut(hash).expect("The target has a state");
+            let state = replace(state, State::Drained {
+                peer,
+            });
             match state {
                 State::Downloaded {
                     evidences,
                     transactions,
+                    ..
                 } => {
                     result.push((*hash, evidences, transactions));
                 }
@@ -143,22 +221,108 @@ impl BodyDownloader {
         result
     }
 
-    pub fn re_request(&mut self, hash: BlockHash, remains: Vec<(BlockHash, Vec<Evidence>, Vec<Transaction>)>) {
-        #[inline]
-        fn insert(states: &mut HashMap<BlockHash, State>, hash: BlockHash, state: State) {
-            let old = states.insert(hash, state);
-            debug_assert_ne!(None, old);
-        }
-        // The implementation of extend method allocates an additional memory for new items.
-        // However, our implementation guarantees that new items are already in the map and it just
-        // update the states. So iterating over new items and calling the insert method is faster
-        // than using the extend method and uses less memory.
+    /// Queues the corrupted body again for another peer, and returns the peer which served it.
+    pub fn re_request(
+        &mut self,
+        hash: BlockHash,
+        remains: Vec<(BlockHash, Vec<Evidence>, Vec<Transaction>)>,
+    ) -> Option<NodeId> {
+        self.retry_later(remains);
+        let peer = match self.states.insert(hash, State::Queued) {
+            Some(State::Drained {
+                peer,
+            }) => peer,
+            _ => return None,
+        };
+        *self.failures.entry(peer).or_default() += 1;
+        self.avoided.insert(hash, peer);
+        Some(peer)
+    }
+
+    /// Puts the drained bodies back, so that they are drained again with the next bodies.
+    pub fn retry_later(&mut self, remains: Vec<(BlockHash, Vec<Evidence>, Vec<Transaction>)>) {
         for (hash, evidences, transactions) in remains {
-            insert(&mut self.states, hash, State::Downloaded {
+            let state = self.states.get_mut(&hash).expect("The drained bodies are still targets");
+            let peer = match state {
+                State::Drained {
+                    peer,
+                } => *peer,
+                _ => unreachable!("Only the drained bodies are put back"),
+            };
+            *state = State::Downloaded {
+                peer,
                 evidences,
                 transactions,
-            });
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use primitives::H256;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn peer(port: u16) -> NodeId {
+        NodeId::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), port)
+    }
+
+    fn requested(request: Option<RequestMessage>) -> Vec<BlockHash> {
+        match request {
+            Some(RequestMessage::Bodies(hashes)) => hashes,
+            _ => Vec::new(),
         }
-        insert(&mut self.states, hash, State::Queued);
+    }
+
+    fn downloader_with_targets(count: u64) -> (BodyDownloader, Vec<BlockHash>) {
+        let targets: Vec<BlockHash> = (0..count).map(|i| BlockHash::from(H256::from_slice(&[i as u8; 32]))).collect();
+        let mut downloader = BodyDownloader::default();
+        downloader.add_targets(targets.clone());
+        (downloader, targets)
+    }
+
+    #[test]
+    fn peers_download_different_bodies_in_parallel() {
+        let (mut downloader, targets) = downloader_with_targets(200);
+        let first = requested(downloader.create_request(&peer(1)));
+        let second = requested(downloader.create_request(&peer(2)));
+        assert_eq!(first, targets[..MAX_BODY_REQUEST_LENGTH].to_vec());
+        assert_eq!(second, targets[MAX_BODY_REQUEST_LENGTH..].to_vec());
+
+        // The later bodies wait for the earlier ones to be drained in order.
+        downloader.import_bodies(&peer(2), second.clone(), vec![Default::default(); second.len()]);
+        assert!(downloader.drain().is_empty());
+        downloader.import_bodies(&peer(1), first.clone(), vec![Default::default(); first.len()]);
+        assert_eq!(downloader.drain().len(), 200);
+    }
+
+    #[test]
+    fn failing_peer_is_asked_for_fewer_bodies() {
+        let (mut downloader, targets) = downloader_with_targets(200);
+        let hashes = requested(downloader.create_request(&peer(1)));
+        downloader.reset_downloading(&peer(1), &hashes);
+        assert_eq!(downloader.request_length(&peer(1)), MAX_BODY_REQUEST_LENGTH / 2);
+
+        // The bodies the peer failed to deliver are given to another peer.
+        assert_eq!(requested(downloader.create_request(&peer(2))), targets[..MAX_BODY_REQUEST_LENGTH].to_vec());
+
+        let hashes = requested(downloader.create_request(&peer(1)));
+        assert_eq!(hashes.len(), MAX_BODY_REQUEST_LENGTH / 2);
+        downloader.import_bodies(&peer(1), hashes.clone(), vec![Default::default(); hashes.len()]);
+        assert_eq!(downloader.request_length(&peer(1)), MAX_BODY_REQUEST_LENGTH);
+    }
+
+    #[test]
+    fn corrupted_body_is_requested_from_another_peer() {
+        let (mut downloader, targets) = downloader_with_targets(2);
+        let hashes = requested(downloader.create_request(&peer(1)));
+        downloader.import_bodies(&peer(1), hashes, vec![Default::default(); 2]);
+        let mut drained = downloader.drain();
+        let remains = drained.split_off(1);
+
+        assert_eq!(downloader.re_request(targets[0], remains), Some(peer(1)));
+        assert!(requested(downloader.create_request(&peer(1))).is_empty());
+        assert_eq!(requested(downloader.create_request(&peer(2))), vec![targets[0]]);
     }
 }
@@ -15,20 +15,19 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::downloader::{BodyDownloader, HeaderDownloader};
-use super::message::{Message, RequestMessage, ResponseMessage};
+use super::message::{Message, MessageID, RequestMessage, ResponseMessage};
 use crate::snapshot::snapshot_path;
 use ccore::encoded::Header as EncodedHeader;
 use ccore::{
-    Block, BlockChainClient, BlockChainTrait, BlockImportError, BlockStatus, ChainNotify, Client, EngineInfo, Evidence,
-    ImportBlock, ImportError, StateInfo,
+    Block, BlockChainClient, BlockChainTrait, BlockImportError, BlockStatus, BodyCompression, Categorized, ChainNotify,
+    Client, EngineInfo, ErrorCategory, Evidence, ImportBlock, ImportError, StateInfo,
 };
 use cdb::AsHashDB;
-use cnetwork::{Api, EventSender, IntoSocketAddr, NetworkExtension, NodeId};
+use cnetwork::{Api, EventSender, IntoSocketAddr, Misbehavior, NetworkExtension, NodeId};
 use codechain_crypto::BLAKE_NULL_RLP;
 use coordinator::Transaction;
 use cstate::{TopLevelState, TopStateView};
 use ctimer::TimerToken;
-use ctypes::header::Seal;
 use ctypes::{BlockHash, BlockId, BlockNumber, StorageId, SyncHeader};
 use kvdb::DBTransaction;
 use merkle_trie::snapshot::{ChunkDecompressor, Restore as SnapshotRestore};
@@ -36,9 +35,10 @@ use merkle_trie::{skewed_merkle_root, Trie, TrieFactory};
 use primitives::{H256, U256};
 use rand::prelude::SliceRandom;
 use rand::thread_rng;
-use rlp::{Encodable, Rlp};
+use rlp::{Encodable, Rlp, RlpStream};
+use std::cmp::Reverse;
 use std::collections::hash_map::Entry;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::fs;
 use std::iter::once;
 use std::mem::discriminant;
@@ -47,6 +47,9 @@ use std::sync::Arc;
 use std::time::Duration;
 use token_generator::TokenGenerator;
 
+/// The first version which understands the body responses compressed with lz4 or zstd.
+const BODY_COMPRESSION_VERSION: u64 = 1;
+
 const SYNC_TIMER_TOKEN: TimerToken = 0;
 const SYNC_EXPIRE_TOKEN_BEGIN: TimerToken = SYNC_TIMER_TOKEN + 1;
 const SYNC_EXPIRE_TOKEN_LIMIT: usize = 1000;
@@ -189,9 +192,13 @@ impl State {
 pub struct Extension {
     state: State,
     requests: HashMap<NodeId, Vec<(u64, RequestMessage)>>,
-    connected_nodes: HashSet<NodeId>,
+    /// The negotiated protocol versions of the connected nodes.
+    connected_nodes: HashMap<NodeId, u64>,
     header_downloaders: HashMap<NodeId, HeaderDownloader>,
     body_downloader: BodyDownloader,
+    /// Whether the block queue refused a downloaded block for being full.
+    /// No bodies are requested until the queue reports that blocks left it.
+    block_queue_full: bool,
     tokens: HashMap<NodeId, TimerToken>,
     tokens_info: HashMap<TimerToken, TokenInfo>,
     token_generator: TokenGenerator,
@@ -200,6 +207,7 @@ pub struct Extension {
     last_request: u64,
     seq: u64,
     snapshot_dir: Option<String>,
+    body_compression: Option<BodyCompression>,
 }
 
 impl Extension {
@@ -208,6 +216,7 @@ impl Extension {
         api: Box<dyn Api>,
         snapshot_target: Option<(H256, u64)>,
         snapshot_dir: Option<String>,
+        body_compression: Option<BodyCompression>,
     ) -> Extension {
         api.set_timer(SYNC_TIMER_TOKEN, Duration::from_millis(SYNC_TIMER_INTERVAL)).expect("Timer set succeeds");
 
@@ -242,6 +251,7 @@ impl Extension {
             connected_nodes: Default::default(),
             header_downloaders: Default::default(),
             body_downloader,
+            block_queue_full: false,
             tokens: Default::default(),
             tokens_info: Default::default(),
             token_generator: TokenGenerator::new(SYNC_EXPIRE_TOKEN_BEGIN, SYNC_EXPIRE_TOKEN_END),
@@ -250,6 +260,7 @@ impl Extension {
             last_request: Default::default(),
             seq: Default::default(),
             snapshot_dir,
+            body_compression,
         }
     }
 
@@ -314,7 +325,7 @@ impl Extension {
         }
 
         let chain_info = self.client.chain_info();
-        for id in self.connected_nodes.iter() {
+        for id in self.connected_nodes.keys() {
             self.api.send(
                 id,
                 Arc::new(
@@ -341,6 +352,10 @@ impl Extension {
     }
 
     fn send_body_request(&mut self, id: &NodeId) {
+        if self.block_queue_full {
+            cdebug!(SYNC, "Wait for the block queue to have room");
+            return
+        }
         if let Some(downloader) = self.header_downloaders.get(&id) {
             if self.client.block_status(&BlockId::Hash(downloader.best_hash())) == BlockStatus::InChain {
                 // Peer is lagging behind the local blockchain.
@@ -357,7 +372,7 @@ impl Extension {
                 return
             }
 
-            if let Some(request) = self.body_downloader.create_request() {
+            if let Some(request) = self.body_downloader.create_request(id) {
                 cdebug!(SYNC, "Request body to {} {:?}", id, request);
                 let request_id = self.last_request;
                 self.last_request += 1;
@@ -377,13 +392,21 @@ impl Extension {
         self.check_sync_variable();
     }
 
+    /// Returns the peers in the order to send requests to.
+    /// The peers with higher response scores come first, and the peers with the same score are shuffled.
+    fn prioritized_peers(&self) -> Vec<NodeId> {
+        let mut peer_ids: Vec<_> = self.header_downloaders.keys().cloned().collect();
+        peer_ids.shuffle(&mut thread_rng());
+        peer_ids.sort_by_key(|id| Reverse(self.api.peer_score(id)));
+        peer_ids
+    }
+
     fn send_chunk_request(&mut self, block: &BlockHash, root: &H256) {
         let have_chunk_request =
             self.requests.values().flatten().any(|r| matches!(r, (_, RequestMessage::StateChunk(..))));
 
         if !have_chunk_request {
-            let mut peer_ids: Vec<_> = self.header_downloaders.keys().cloned().collect();
-            peer_ids.shuffle(&mut thread_rng());
+            let peer_ids = self.prioritized_peers();
             if let Some(id) = peer_ids.first() {
                 if let Some(requests) = self.requests.get_mut(&id) {
                     let req = RequestMessage::StateChunk(*block, vec![*root]);
@@ -469,16 +492,20 @@ impl NetworkExtension<Event> for Extension {
     }
 
     fn versions() -> &'static [u64] {
-        const VERSIONS: &[u64] = &[0];
+        const VERSIONS: &[u64] = &[0, BODY_COMPRESSION_VERSION];
         &VERSIONS
     }
 
-    fn on_node_added(&mut self, id: &NodeId, _version: u64) {
-        cinfo!(SYNC, "New peer detected #{}", id);
+    fn protocol_of(message: &[u8]) -> &'static str {
+        Rlp::new(message).val_at(0).map(MessageID::protocol).unwrap_or_else(|_| Self::name())
+    }
+
+    fn on_node_added(&mut self, id: &NodeId, version: u64) {
+        cinfo!(SYNC, "New peer detected #{} (version {})", id, version);
         self.send_status(id);
 
-        let t = self.connected_nodes.insert(*id);
-        debug_assert!(t, "{} is already added to peer list", id);
+        let t = self.connected_nodes.insert(*id, version);
+        debug_assert!(t.is_none(), "{} is already added to peer list", id);
 
         let token = self.token_generator.gen().expect("Token generator is full");
         let token_info = TokenInfo {
@@ -496,16 +523,12 @@ impl NetworkExtension<Event> for Extension {
     }
 
     fn on_node_removed(&mut self, id: &NodeId) {
-        if self.connected_nodes.remove(id) {
+        if self.connected_nodes.remove(id).is_some() {
             cinfo!(SYNC, "Peer removed #{}", id);
 
             self.header_downloaders.remove(id);
-
-            for (_, request) in self.requests.remove(id).into_iter().flatten() {
-                if let RequestMessage::Bodies(hashes) = request {
-                    self.body_downloader.reset_downloading(&hashes);
-                }
-            }
+            self.requests.remove(id);
+            self.body_downloader.remove_peer(id);
 
             if let Some(token) = self.tokens.remove(id) {
                 self.api.clear_timer(token).expect("Timer cancel failed");
@@ -534,14 +557,14 @@ impl NetworkExtension<Event> for Extension {
             }
         } else {
             cinfo!(SYNC, "Invalid message from peer {}", id);
+            self.api.report(id, Misbehavior::ProtocolViolation);
         }
     }
 
     fn on_timeout(&mut self, token: TimerToken) {
         match token {
             SYNC_TIMER_TOKEN => {
-                let mut peer_ids: Vec<_> = self.header_downloaders.keys().cloned().collect();
-                peer_ids.shuffle(&mut thread_rng());
+                let peer_ids = self.prioritized_peers();
 
                 match self.state {
                     State::SnapshotHeader(_, num) => {
@@ -635,7 +658,7 @@ impl NetworkExtension<Event> for Extension {
                     if let Some((_, request)) = expired_request {
                         match request {
                             RequestMessage::Bodies(hashes) => {
-                                self.body_downloader.reset_downloading(&hashes);
+                                self.body_downloader.reset_downloading(&id, &hashes);
                             }
                             _ => unreachable!(),
                         }
@@ -643,6 +666,7 @@ impl NetworkExtension<Event> for Extension {
                 }
 
                 self.dismiss_request(&id, request_id);
+                self.api.report(&id, Misbehavior::Timeout);
                 self.check_sync_variable();
             }
             _ => unreachable!(),
@@ -678,6 +702,9 @@ impl NetworkExtension<Event> for Extension {
             } => {
                 self.new_blocks(imported, invalid);
             }
+            Event::BlocksCompleted => {
+                self.blocks_completed();
+            }
         }
     }
 }
@@ -694,6 +721,7 @@ pub enum Event {
         imported: Vec<BlockHash>,
         invalid: Vec<BlockHash>,
     },
+    BlocksCompleted,
 }
 
 impl Extension {
@@ -723,6 +751,21 @@ impl Extension {
 
         self.send_status_broadcast();
     }
+
+    /// Imports the blocks postponed for the full block queue, now that some blocks left it.
+    fn blocks_completed(&mut self) {
+        if !self.block_queue_full {
+            return
+        }
+        self.block_queue_full = false;
+        let completed = self.body_downloader.drain();
+        self.import_blocks(completed);
+
+        let peer_ids: Vec<_> = self.header_downloaders.keys().cloned().collect();
+        for id in peer_ids {
+            self.send_body_request(&id);
+        }
+    }
 }
 
 impl Extension {
@@ -730,6 +773,7 @@ impl Extension {
         // Validity check
         if genesis_hash != self.client.chain_info().genesis_hash {
             cinfo!(SYNC, "Genesis hash mismatch with peer {}", from);
+            self.api.report(from, Misbehavior::ProtocolViolation);
             return
         }
 
@@ -748,13 +792,17 @@ impl Extension {
     }
 
     fn on_peer_request(&self, from: &NodeId, id: u64, request: RequestMessage) {
-        if !self.connected_nodes.contains(from) {
-            cinfo!(SYNC, "Request from invalid peer #{} received", from);
-            return
-        }
+        let version = match self.connected_nodes.get(from) {
+            Some(version) => *version,
+            None => {
+                cinfo!(SYNC, "Request from invalid peer #{} received", from);
+                return
+            }
+        };
 
         if !self.is_valid_request(&request) {
             cinfo!(SYNC, "Invalid request received from peer #{}", from);
+            self.api.report(from, Misbehavior::ProtocolViolation);
             return
         }
 
@@ -768,7 +816,12 @@ impl Extension {
             }
             RequestMessage::Bodies(hashes) => {
                 ctrace!(SYNC, "Received body request from {}", from);
-                self.create_bodies_response(hashes)
+                let compression = if version >= BODY_COMPRESSION_VERSION {
+                    self.body_compression
+                } else {
+                    None
+                };
+                self.create_bodies_response(hashes, compression)
             }
             RequestMessage::StateChunk(block_hash, chunk_root) => {
                 self.create_state_chunk_response(block_hash, chunk_root)
@@ -802,7 +855,9 @@ impl Extension {
             .map(|block| {
                 let header = block.expect("take_while guarantees existance of item").header().decode();
                 let validator_set = if header.number() != 0 {
-                    self.client.validator_set(Some(header.number() - 1)).expect("We are querying existing block's data")
+                    self.client
+                        .validator_set(BlockId::Number(header.number() - 1))
+                        .expect("We are querying existing block's data")
                 } else {
                     None
                 };
@@ -825,7 +880,7 @@ impl Extension {
         ResponseMessage::Headers(headers)
     }
 
-    fn create_bodies_response(&self, hashes: Vec<BlockHash>) -> ResponseMessage {
+    fn create_bodies_response(&self, hashes: Vec<BlockHash>, compression: Option<BodyCompression>) -> ResponseMessage {
         let bodies = hashes
             .into_iter()
             .map(|hash| {
@@ -835,7 +890,7 @@ impl Extension {
                     .unwrap_or_default()
             })
             .collect();
-        ResponseMessage::Bodies(bodies)
+        ResponseMessage::Bodies(bodies, compression)
     }
 
     fn create_state_chunk_response(&self, hash: BlockHash, chunk_roots: Vec<H256>) -> ResponseMessage {
@@ -890,15 +945,24 @@ impl Extension {
             }
 
             if !self.is_valid_response(&request, &response) {
+                self.api.report(from, Misbehavior::InvalidBlock);
                 return
             }
+            let is_useful = match &response {
+                ResponseMessage::Headers(headers) => !headers.is_empty(),
+                ResponseMessage::Bodies(bodies, _) => !bodies.is_empty(),
+                ResponseMessage::StateChunk(chunks) => !chunks.is_empty(),
+            };
+            if is_useful {
+                self.api.record_useful_response(from);
+            }
 
             match response {
                 ResponseMessage::Headers(headers) => {
                     self.dismiss_request(from, id);
                     self.on_header_response(from, headers)
                 }
-                ResponseMessage::Bodies(bodies) => {
+                ResponseMessage::Bodies(bodies, _) => {
                     self.check_sync_variable();
                     let hashes = match request {
                         RequestMessage::Bodies(hashes) => hashes,
@@ -916,7 +980,7 @@ impl Extension {
                         }
                     }
                     self.dismiss_request(from, id);
-                    self.on_body_response(hashes, bodies);
+                    self.on_body_response(from, hashes, bodies);
                     self.check_sync_variable();
                 }
                 ResponseMessage::StateChunk(chunks) => {
@@ -983,7 +1047,7 @@ impl Extension {
 
                 headers.first().map(|header| header.number()) == Some(*start_number)
             }
-            (RequestMessage::Bodies(hashes), ResponseMessage::Bodies(bodies)) => {
+            (RequestMessage::Bodies(hashes), ResponseMessage::Bodies(bodies, _)) => {
                 if hashes.len() != bodies.len() {
                     cwarn!(
                         SYNC,
@@ -1062,9 +1126,12 @@ impl Extension {
                     match self.client.import_header(header) {
                         Err(BlockImportError::Import(ImportError::AlreadyInChain)) => exists.push(hash),
                         Err(BlockImportError::Import(ImportError::AlreadyQueued)) => queued.push(hash),
-                        // FIXME: handle import errors
                         Err(err) => {
                             cwarn!(SYNC, "Cannot import header({}): {:?}", hash, err);
+                            // A full queue or a failure of this node says nothing about the peer.
+                            if err.category() == ErrorCategory::Consensus {
+                                self.api.report(from, Misbehavior::InvalidBlock);
+                            }
                             break
                         }
                         _ => {}
@@ -1090,41 +1157,60 @@ impl Extension {
         let mut imported = Vec::new();
         let mut remains = Vec::new();
         let mut error_target = None;
+        let mut postponed = false;
         for (hash, evidences, transactions) in blocks {
-            if error_target.is_some() {
+            if error_target.is_some() || postponed {
                 remains.push((hash, evidences, transactions));
                 continue
             }
-            let header =
-                self.client.block_header(&BlockId::Hash(hash)).expect("Downloaded body's header must exist").decode();
+            let encoded_header =
+                self.client.block_header(&BlockId::Hash(hash)).expect("Downloaded body's header must exist");
+            // Only a few fields are read and the header is copied into the block as it is, so it isn't decoded.
+            let header = match encoded_header.validated_view() {
+                Ok(header) => header,
+                Err(err) => {
+                    cerror!(SYNC, "The header of the downloaded body({}) is corrupted: {:?}", hash, err);
+                    continue
+                }
+            };
             let calculated_evidences_root =
                 skewed_merkle_root(BLAKE_NULL_RLP, evidences.iter().map(Encodable::rlp_bytes));
-            if *header.evidences_root() != calculated_evidences_root {
+            if header.evidences_root() != calculated_evidences_root {
                 cwarn!(SYNC, "Received corrupted evidences for ${}({}", header.number(), hash);
                 error_target = Some(hash);
                 continue
             }
             let calculated_transactions_root =
                 skewed_merkle_root(BLAKE_NULL_RLP, transactions.iter().map(Encodable::rlp_bytes));
-            if *header.transactions_root() != calculated_transactions_root {
+            if header.transactions_root() != calculated_transactions_root {
                 cwarn!(SYNC, "Received corrupted transactions for ${}({}", header.number(), hash);
                 error_target = Some(hash);
                 continue
             }
 
-            let block = Block {
-                header,
-                evidences,
-                transactions,
-            };
-            cdebug!(SYNC, "Body download completed for #{}({})", block.header.number(), hash);
-            match self.client.import_block(block.rlp_bytes(&Seal::With)) {
+            cdebug!(SYNC, "Body download completed for #{}({})", header.number(), hash);
+            let mut block = RlpStream::new_list(3);
+            block.append_raw(header.rlp().as_raw(), 1);
+            block.append_list(&evidences);
+            block.append_list(&transactions);
+            match self.client.queue_block(block.out()) {
                 Err(BlockImportError::Import(ImportError::AlreadyInChain)) => {
                     cwarn!(SYNC, "Downloaded already existing block({})", hash)
                 }
+                Err(BlockImportError::Import(ImportError::QueueFull)) => {
+                    cdebug!(SYNC, "Postpone importing block({}) until the block queue has room", hash);
+                    self.block_queue_full = true;
+                    postponed = true;
+                    remains.push((hash, evidences, transactions));
+                }
                 Err(BlockImportError::Import(ImportError::AlreadyQueued)) => {
                     cwarn!(SYNC, "Downloaded already queued in the verification queue({})", hash)
                 }
+                Err(err) if err.is_retryable() => {
+                    cdebug!(SYNC, "Postpone importing block({}): {:?}", hash, err);
+                    postponed = true;
+                    remains.push((hash, evidences, transactions));
+                }
                 Err(err) => {
                     // FIXME: handle import errors
                     cwarn!(SYNC, "Cannot import block({}): {:?}", hash, err);
@@ -1136,12 +1222,21 @@ impl Extension {
             }
         }
         if let Some(hash) = error_target {
-            self.body_downloader.re_request(hash, remains);
+            if let Some(peer) = self.body_downloader.re_request(hash, remains) {
+                self.api.report(&peer, Misbehavior::InvalidBlock);
+            }
+        } else if postponed {
+            self.body_downloader.retry_later(remains);
         }
         self.body_downloader.remove_targets(&imported);
     }
 
-    fn on_body_response(&mut self, hashes: Vec<BlockHash>, bodies: Vec<(Vec<Evidence>, Vec<Transaction>)>) {
+    fn on_body_response(
+        &mut self,
+        from: &NodeId,
+        hashes: Vec<BlockHash>,
+        bodies: Vec<(Vec<Evidence>, Vec<Transaction>)>,
+    ) {
         ctrace!(SYNC, "Received body response with length({}) {:?}", hashes.len(), hashes);
 
         match &self.state {
@@ -1173,14 +1268,11 @@ impl Extension {
                 }
             }
             State::Full => {
-                self.body_downloader.import_bodies(hashes, bodies);
+                self.body_downloader.import_bodies(from, hashes, bodies);
                 let completed = self.body_downloader.drain();
                 self.import_blocks(completed);
 
-                let mut peer_ids: Vec<_> = self.header_downloaders.keys().cloned().collect();
-                peer_ids.shuffle(&mut thread_rng());
-
-                for id in peer_ids {
+                for id in self.prioritized_peers() {
                     self.send_body_request(&id);
                 }
             }
@@ -1213,6 +1305,7 @@ impl Extension {
                 Ok(chunk) => chunk,
                 Err(e) => {
                     cwarn!(SYNC, "Decode failed for chunk response from peer {}: {}", from, e);
+                    self.api.report(from, Misbehavior::ProtocolViolation);
                     continue
                 }
             };
@@ -1220,6 +1313,7 @@ impl Extension {
                 Ok(chunk) => chunk,
                 Err(e) => {
                     cwarn!(SYNC, "Invalid chunk response from peer {}: {}", from, e);
+                    self.api.report(from, Misbehavior::ProtocolViolation);
                     continue
                 }
             };
@@ -1279,4 +1373,8 @@ impl ChainNotify for BlockSyncSender {
             })
             .unwrap();
     }
+
+    fn blocks_completed(&self, _imported: Vec<BlockHash>, _invalid: Vec<BlockHash>) {
+        self.0.send(Event::BlocksCompleted).unwrap();
+    }
 }
@@ -36,6 +36,18 @@ pub enum MessageID {
     StateChunk = 0x0b,
 }
 
+impl MessageID {
+    /// The protocol which the message is accounted to in the traffic statistics.
+    pub fn protocol(self) -> &'static str {
+        match self {
+            MessageID::Status => "status",
+            MessageID::GetHeaders | MessageID::Headers => "headers",
+            MessageID::GetBodies | MessageID::Bodies => "bodies",
+            MessageID::GetStateChunk | MessageID::StateChunk => "state-chunks",
+        }
+    }
+}
+
 impl Encodable for MessageID {
     fn rlp_append(&self, s: &mut RlpStream) {
         s.append_single_value(&(*self as u8));
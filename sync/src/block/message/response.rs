@@ -15,15 +15,20 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::MessageID;
-use ccore::Evidence;
+use ccore::{BodyCompression, Evidence};
 use coordinator::Transaction;
 use ctypes::SyncHeader;
 use rlp::{DecoderError, Encodable, Rlp, RlpStream};
 
+/// Bodies larger than this are rejected when decompressed.
+const MAX_UNCOMPRESSED_BODIES_SIZE: usize = 128 * 1024 * 1024;
+
 #[derive(Debug)]
 pub enum ResponseMessage {
     Headers(Vec<SyncHeader>),
-    Bodies(Vec<(Vec<Evidence>, Vec<Transaction>)>),
+    /// The bodies are compressed with snappy if no compression is given.
+    /// The others are understood only by the peers of version 1 or later.
+    Bodies(Vec<(Vec<Evidence>, Vec<Transaction>)>, Option<BodyCompression>),
     StateChunk(Vec<Vec<u8>>),
 }
 
@@ -33,9 +38,7 @@ impl Encodable for ResponseMessage {
             ResponseMessage::Headers(headers) => {
                 s.append_list(headers);
             }
-            ResponseMessage::Bodies(bodies) => {
-                s.begin_list(1);
-
+            ResponseMessage::Bodies(bodies, compression) => {
                 let uncompressed = {
                     let mut inner_list = RlpStream::new_list(bodies.len());
                     bodies.iter().for_each(|(evidences, transactions)| {
@@ -46,13 +49,22 @@ impl Encodable for ResponseMessage {
                     inner_list.out()
                 };
 
-                let compressed = {
-                    // TODO: Cache the Encoder object
-                    let mut snappy_encoder = snap::Encoder::new();
-                    snappy_encoder.compress_vec(&uncompressed).expect("Compression always succeed")
-                };
-
-                s.append(&compressed);
+                match compression {
+                    Some(compression) => {
+                        s.begin_list(2);
+                        s.append(&compression.tag());
+                        s.append(&compression.compress(&uncompressed));
+                    }
+                    None => {
+                        // TODO: Cache the Encoder object
+                        let mut snappy_encoder = snap::Encoder::new();
+                        let compressed =
+                            snappy_encoder.compress_vec(&uncompressed).expect("Compression always succeed");
+
+                        s.begin_list(1);
+                        s.append(&compressed);
+                    }
+                }
             }
             ResponseMessage::StateChunk(chunks) => {
                 s.append_list::<Vec<u8>, Vec<u8>>(chunks);
@@ -78,22 +90,41 @@ impl ResponseMessage {
         let message = match id {
             MessageID::Headers => ResponseMessage::Headers(rlp.as_list()?),
             MessageID::Bodies => {
-                let item_count = rlp.item_count()?;
-                if item_count != 1 {
-                    return Err(DecoderError::RlpIncorrectListLen {
-                        got: item_count,
-                        expected: 1,
-                    })
-                }
-
-                let compressed: Vec<u8> = rlp.val_at(0)?;
-                let uncompressed = {
-                    // TODO: Cache the Decoder object
-                    let mut snappy_decoder = snap::Decoder::new();
-                    snappy_decoder.decompress_vec(&compressed).map_err(|err| {
-                        cwarn!(SYNC, "Decompression failed while decoding a body response: {}", err);
-                        DecoderError::Custom("Invalid compression format")
-                    })?
+                let (uncompressed, compression) = match rlp.item_count()? {
+                    1 => {
+                        let compressed: Vec<u8> = rlp.val_at(0)?;
+                        let uncompressed_len = snap::decompress_len(&compressed).map_err(|err| {
+                            cwarn!(SYNC, "Decompression failed while decoding a body response: {}", err);
+                            DecoderError::Custom("Invalid compression format")
+                        })?;
+                        if uncompressed_len > MAX_UNCOMPRESSED_BODIES_SIZE {
+                            return Err(DecoderError::Custom("Too large bodies"))
+                        }
+                        // TODO: Cache the Decoder object
+                        let mut snappy_decoder = snap::Decoder::new();
+                        let uncompressed = snappy_decoder.decompress_vec(&compressed).map_err(|err| {
+                            cwarn!(SYNC, "Decompression failed while decoding a body response: {}", err);
+                            DecoderError::Custom("Invalid compression format")
+                        })?;
+                        (uncompressed, None)
+                    }
+                    2 => {
+                        let compression = BodyCompression::from_tag(rlp.val_at(0)?)
+                            .ok_or(DecoderError::Custom("Unknown body compression"))?;
+                        let compressed: Vec<u8> = rlp.val_at(1)?;
+                        let uncompressed =
+                            compression.decompress(&compressed, MAX_UNCOMPRESSED_BODIES_SIZE).map_err(|err| {
+                                cwarn!(SYNC, "Decompression failed while decoding a body response: {}", err);
+                                DecoderError::Custom("Invalid compression format")
+                            })?;
+                        (uncompressed, Some(compression))
+                    }
+                    item_count => {
+                        return Err(DecoderError::RlpIncorrectListLen {
+                            got: item_count,
+                            expected: 2,
+                        })
+                    }
                 };
 
                 let uncompressed_rlp = Rlp::new(&uncompressed);
@@ -104,7 +135,7 @@ impl ResponseMessage {
                     let transactions = item.list_at(1)?;
                     bodies.push((evidences, transactions));
                 }
-                ResponseMessage::Bodies(bodies)
+                ResponseMessage::Bodies(bodies, compression)
             }
             MessageID::StateChunk => ResponseMessage::StateChunk(rlp.as_list()?),
             _ => return Err(DecoderError::Custom("Unknown message id detected")),
@@ -144,15 +175,36 @@ mod tests {
 
     #[test]
     fn bodies_message_rlp() {
-        let message = ResponseMessage::Bodies(vec![(vec![], vec![])]);
+        let message = ResponseMessage::Bodies(vec![(vec![], vec![])], None);
         assert_eq_by_debug(&message, &decode_bytes(message.message_id(), message.rlp_bytes().as_ref()));
 
         let tx = Transaction::new("sample".to_string(), vec![1, 2, 3, 4, 5]);
 
-        let message = ResponseMessage::Bodies(vec![(vec![], vec![tx])]);
+        let message = ResponseMessage::Bodies(vec![(vec![], vec![tx])], None);
         assert_eq_by_debug(&message, &decode_bytes(message.message_id(), message.rlp_bytes().as_ref()));
     }
 
+    #[test]
+    fn compressed_bodies_message_rlp() {
+        let tx = Transaction::new("sample".to_string(), vec![7; 1024]);
+        for compression in &[BodyCompression::Lz4, BodyCompression::Zstd] {
+            let message =
+                ResponseMessage::Bodies(vec![(vec![], vec![]), (vec![], vec![tx.clone()])], Some(*compression));
+            let bytes = message.rlp_bytes();
+            assert!(bytes.len() < 1024);
+            assert_eq_by_debug(&message, &decode_bytes(message.message_id(), bytes.as_ref()));
+        }
+    }
+
+    #[test]
+    fn bodies_with_unknown_compression_are_rejected() {
+        let mut s = RlpStream::new_list(2);
+        s.append(&0xffu8);
+        s.append(&vec![0u8; 8]);
+        let bytes = s.out();
+        assert!(ResponseMessage::decode(MessageID::Bodies, &Rlp::new(&bytes)).is_err());
+    }
+
     #[test]
     fn state_chunk_message_rlp() {
         let message = ResponseMessage::StateChunk(vec![]);
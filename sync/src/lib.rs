@@ -30,7 +30,7 @@ pub mod snapshot;
 mod transaction;
 
 pub use crate::block::{BlockSyncEvent, BlockSyncExtension, BlockSyncSender};
-pub use crate::transaction::TransactionSyncExtension;
+pub use crate::transaction::{ResendStatus, TransactionSyncEvent, TransactionSyncExtension};
 
 #[cfg(test)]
 extern crate codechain_key as ckey;
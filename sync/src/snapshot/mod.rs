@@ -15,11 +15,10 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use ccore::snapshot_notify::{NotifyReceiverSource, ReceiverCanceller};
-use ccore::{BlockChainClient, BlockChainTrait, Client};
-use cdb::{AsHashDB, HashDB};
-use cstate::{StateDB, TopLevelState, TopStateView};
+use ccore::{export_state, BlockChainClient, BlockChainTrait, Client};
+use cstate::StateDB;
 use ctypes::{BlockHash, BlockId};
-use merkle_trie::snapshot::{ChunkCompressor, Error as SnapshotError, Snapshot};
+use merkle_trie::snapshot::Error as SnapshotError;
 use primitives::H256;
 use std::fs;
 use std::path::PathBuf;
@@ -95,32 +94,7 @@ impl Service {
     }
 }
 fn snapshot(db: &StateDB, block_hash: BlockHash, root: H256, dir: &str) -> Result<(), SnapshotError> {
-    snapshot_trie(db.as_hashdb(), block_hash, root, dir)?;
-
-    let top_state = TopLevelState::from_existing(db.clone(&root), root)?;
-    let module_roots = {
-        let metadata = top_state.metadata()?.expect("Metadata must exist for snapshot block");
-        let module_num = *metadata.number_of_modules();
-        (0..module_num).map(|n| top_state.module_root(n))
-    };
-    for sr in module_roots {
-        snapshot_trie(db.as_hashdb(), block_hash, sr?.expect("Module root must exist"), dir)?;
-    }
-    Ok(())
-}
-
-fn snapshot_trie(db: &dyn HashDB, block_hash: BlockHash, root: H256, root_dir: &str) -> Result<(), SnapshotError> {
-    let snapshot_dir = snapshot_dir(root_dir, &block_hash);
-    fs::create_dir_all(snapshot_dir)?;
-
-    for chunk in Snapshot::from_hashdb(db, root) {
-        let chunk_path = snapshot_path(root_dir, &block_hash, &chunk.root);
-        let chunk_file = fs::File::create(chunk_path)?;
-        let compressor = ChunkCompressor::new(chunk_file);
-        compressor.compress_chunk(&chunk)?;
-    }
-
-    Ok(())
+    export_state(db, root, &snapshot_dir(dir, &block_hash))
 }
 
 fn cleanup_expired(client: &Client, root_dir: &str, expiration: u64) -> Result<(), SnapshotError> {
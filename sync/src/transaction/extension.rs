@@ -15,45 +15,29 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::message::Message;
+use super::propagation::Propagation;
+use super::rebroadcast::{Rebroadcast, ResendStatus};
 use ccore::BlockChainClient;
-use cnetwork::{Api, NetworkExtension, NodeId};
-use coordinator::Transaction;
+use cnetwork::{Api, EventSender, Misbehavior, NetworkExtension, NodeId};
 use ctimer::TimerToken;
-use ctypes::TxHash;
-use never_type::Never;
 use rlp::{Encodable, Rlp};
-use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 const BROADCAST_TIMER_TOKEN: TimerToken = 0;
 const BROADCAST_TIMER_INTERVAL: u64 = 1000;
-const MAX_HISTORY_SIZE: usize = 100_000;
 
-#[derive(Default)]
-struct KnownTxs {
-    history_set: HashSet<TxHash>,
-    history_queue: VecDeque<TxHash>,
-}
-
-impl KnownTxs {
-    fn push(&mut self, hash: TxHash) {
-        debug_assert!(!self.history_set.contains(&hash));
-        self.history_set.insert(hash);
-        self.history_queue.push_back(hash);
-        if self.history_queue.len() > MAX_HISTORY_SIZE {
-            self.history_queue.pop_front();
-        }
-    }
-
-    fn contains(&mut self, hash: &TxHash) -> bool {
-        self.history_set.contains(hash)
-    }
+pub enum Event {
+    /// Lists the local transactions in the mem pool with their resend schedule.
+    GetLocalResends(EventSender<ResendStatus>),
 }
 
 pub struct Extension {
-    known_txs: KnownTxs,
-    peers: HashMap<NodeId, KnownTxs>,
+    propagation: Propagation,
+    rebroadcast: Rebroadcast,
+    /// Whether the local transactions recovered from the backup were scheduled,
+    /// which waits until the node is synced.
+    resumed: bool,
     client: Arc<dyn BlockChainClient>,
     api: Box<dyn Api>,
 }
@@ -63,15 +47,16 @@ impl Extension {
         api.set_timer(BROADCAST_TIMER_TOKEN, Duration::from_millis(BROADCAST_TIMER_INTERVAL))
             .expect("Timer set succeeds");
         Extension {
-            known_txs: Default::default(),
-            peers: Default::default(),
+            propagation: Default::default(),
+            rebroadcast: Default::default(),
+            resumed: false,
             client,
             api,
         }
     }
 }
 
-impl NetworkExtension<Never> for Extension {
+impl NetworkExtension<Event> for Extension {
     fn name() -> &'static str {
         "transaction-propagation"
     }
@@ -84,83 +69,116 @@ impl NetworkExtension<Never> for Extension {
         &VERSIONS
     }
 
+    fn protocol_of(_message: &[u8]) -> &'static str {
+        "transactions"
+    }
+
     fn on_node_added(&mut self, token: &NodeId, _version: u64) {
-        self.peers.insert(*token, KnownTxs::default());
+        self.propagation.add_peer(*token, Instant::now());
     }
     fn on_node_removed(&mut self, token: &NodeId) {
-        self.peers.remove(token);
+        self.propagation.remove_peer(token);
     }
 
     fn on_message(&mut self, token: &NodeId, data: &[u8]) {
         if let Ok(received_message) = Rlp::new(data).as_val() {
             match received_message {
                 Message::Transactions(transactions) => {
-                    let transactions: Vec<_> = {
-                        transactions
-                            .into_iter()
-                            .filter(|tx| {
-                                let hash = tx.hash();
-                                if self.known_txs.contains(&hash) {
-                                    false
-                                } else {
-                                    self.known_txs.push(hash);
-                                    true
-                                }
-                            })
-                            .collect()
-                    };
+                    if !self.propagation.has_peer(token) {
+                        cwarn!(SYNC_TX, "Message from {} but it's already removed", token);
+                    }
+                    let received = transactions.len();
+                    let transactions = self.propagation.receive(token, transactions);
+                    cinfo!(
+                        SYNC_TX,
+                        "Receive {} transactions from {}, {} of them seen before",
+                        received,
+                        token,
+                        received - transactions.len()
+                    );
+                    ctrace!(SYNC_TX, "Receive {:?}", transactions.iter().map(|tx| tx.hash()).collect::<Vec<_>>());
 
                     self.client.queue_transactions(
                         transactions.iter().map(|unverified| unverified.rlp_bytes().to_vec()).collect(),
                     );
-                    if let Some(peer) = self.peers.get_mut(token) {
-                        let transactions: Vec<_> = transactions
-                            .iter()
-                            .map(Transaction::hash)
-                            .filter(|tx_hash| !peer.contains(tx_hash))
-                            .collect();
-                        for unverified in transactions.iter() {
-                            peer.push(*unverified);
-                        }
-                        cinfo!(SYNC_TX, "Receive {} transactions from {}", transactions.len(), token);
-                        ctrace!(SYNC_TX, "Receive {:?}", transactions);
-                    } else {
-                        cwarn!(SYNC_TX, "Message from {} but it's already removed", token);
-                    }
                 }
             }
         } else {
             cwarn!(SYNC_TX, "Invalid message from peer {}", token);
+            self.api.report(token, Misbehavior::InvalidTransaction);
         }
     }
 
     fn on_timeout(&mut self, timer: TimerToken) {
         match timer {
-            BROADCAST_TIMER_TOKEN => self.random_broadcast(),
+            BROADCAST_TIMER_TOKEN => self.broadcast(),
             _ => unreachable!(),
         }
     }
+
+    fn on_event(&mut self, event: Event) {
+        match event {
+            Event::GetLocalResends(channel) => {
+                let local = self.client.local_pending_transactions();
+                let chain_timestamp = self.client.best_block_header().timestamp();
+                for status in self.rebroadcast.status(&local, Instant::now(), chain_timestamp) {
+                    channel.send(status).unwrap();
+                }
+            }
+        }
+    }
 }
 
 impl Extension {
-    fn random_broadcast(&mut self) {
-        let transactions = self.client.pending_transactions(0..u64::MAX).transactions;
+    fn broadcast(&mut self) {
+        let now = Instant::now();
+        self.rebroadcast_local(now);
+
+        // Only the transactions some peer hasn't been sent are ordered, rather than the whole pool.
+        let unsent = self.propagation.unsent(self.client.propagatable_transaction_hashes());
+        if unsent.is_empty() {
+            ctrace!(SYNC_TX, "No transactions to propagate");
+            return
+        }
+        let transactions = self.client.prioritized_pending_transactions(&unsent);
         if transactions.is_empty() {
             ctrace!(SYNC_TX, "No transactions to propagate");
             return
         }
-        for (token, peer) in &mut self.peers {
-            let unsent: Vec<_> = transactions.iter().filter(|tx| !peer.contains(&tx.hash())).cloned().collect();
-            if unsent.is_empty() {
-                continue
-            }
-            let unsent_hashes = unsent.iter().map(Transaction::hash).collect::<Vec<_>>();
-            for h in unsent_hashes.iter() {
-                peer.push(*h);
-            }
+        for (token, unsent) in self.propagation.broadcast(&transactions, now) {
             cinfo!(SYNC_TX, "Send {} transactions to {}", unsent.len(), token);
-            ctrace!(SYNC_TX, "Send {:?}", unsent_hashes);
-            self.api.send(token, Arc::new(Message::Transactions(unsent).rlp_bytes()));
+            ctrace!(SYNC_TX, "Send {:?}", unsent.iter().map(|tx| tx.hash()).collect::<Vec<_>>());
+            self.api.send(&token, Arc::new(Message::Transactions(unsent).rlp_bytes()));
         }
     }
+
+    /// Resends the local transactions that haven't been included for a while, in case a peer missed them.
+    /// Nothing is resent while the node is syncing, because the transactions may be in the blocks not imported yet.
+    fn rebroadcast_local(&mut self, now: Instant) {
+        if !self.is_synced() {
+            return
+        }
+        let local = self.client.local_pending_transactions();
+        if !self.resumed {
+            self.resumed = true;
+            cinfo!(SYNC_TX, "Synced, resend {} local transactions recovered from the mem pool backup", local.len());
+            self.rebroadcast.resume(&local, now);
+        }
+        let chain_timestamp = self.client.best_block_header().timestamp();
+        let due = self.rebroadcast.due(&local, now, chain_timestamp);
+        if due.is_empty() {
+            return
+        }
+        for (token, resent) in self.propagation.resend(&due, now) {
+            cinfo!(SYNC_TX, "Resend {} local transactions to {}", resent.len(), token);
+            ctrace!(SYNC_TX, "Resend {:?}", resent.iter().map(|tx| tx.hash()).collect::<Vec<_>>());
+            self.api.send(&token, Arc::new(Message::Transactions(resent).rlp_bytes()));
+        }
+    }
+
+    /// The blocks of all the headers synced so far are imported.
+    fn is_synced(&self) -> bool {
+        self.client.queue_info().is_empty()
+            && self.client.best_block_header().number() >= self.client.best_header().number()
+    }
 }
@@ -16,5 +16,8 @@
 
 mod extension;
 mod message;
+mod propagation;
+mod rebroadcast;
 
-pub use self::extension::Extension as TransactionSyncExtension;
+pub use self::extension::{Event as TransactionSyncEvent, Extension as TransactionSyncExtension};
+pub use self::rebroadcast::ResendStatus;
@@ -0,0 +1,287 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use cnetwork::NodeId;
+use coordinator::Transaction;
+use ctypes::TxHash;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::Instant;
+
+/// The number of the transactions this node remembers to have seen.
+const MAX_HISTORY_SIZE: usize = 100_000;
+/// The number of the transactions this node remembers a peer to know.
+const MAX_PEER_HISTORY_SIZE: usize = 20_000;
+/// The bytes of transactions a peer is sent per second on average.
+const PEER_BANDWIDTH: usize = 256 * 1024;
+/// The bytes of transactions a peer can be sent at once after a quiet while.
+const PEER_BURST: usize = 4 * PEER_BANDWIDTH;
+
+/// A rolling window of transaction hashes, which forgets the oldest one when it's full.
+struct KnownTxs {
+    capacity: usize,
+    history_set: HashSet<TxHash>,
+    history_queue: VecDeque<TxHash>,
+}
+
+impl KnownTxs {
+    fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity,
+            history_set: Default::default(),
+            history_queue: Default::default(),
+        }
+    }
+
+    /// Returns false if the hash is already in the window.
+    fn insert(&mut self, hash: TxHash) -> bool {
+        if !self.history_set.insert(hash) {
+            return false
+        }
+        self.history_queue.push_back(hash);
+        if self.history_queue.len() > self.capacity {
+            if let Some(oldest) = self.history_queue.pop_front() {
+                self.history_set.remove(&oldest);
+            }
+        }
+        true
+    }
+
+    fn contains(&self, hash: &TxHash) -> bool {
+        self.history_set.contains(hash)
+    }
+}
+
+/// A token bucket of the bytes that can be sent to a peer.
+struct Bandwidth {
+    available: usize,
+    refilled_at: Instant,
+}
+
+impl Bandwidth {
+    fn new(now: Instant) -> Self {
+        Self {
+            available: PEER_BURST,
+            refilled_at: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.refilled_at).as_millis() as usize;
+        let refill = elapsed.saturating_mul(PEER_BANDWIDTH) / 1000;
+        if refill > 0 {
+            self.available = self.available.saturating_add(refill).min(PEER_BURST);
+            self.refilled_at = now;
+        }
+    }
+
+    /// A transaction larger than the burst is allowed only when the bucket is full, so that it's sent eventually.
+    fn try_consume(&mut self, bytes: usize) -> bool {
+        if bytes > self.available && self.available < PEER_BURST {
+            return false
+        }
+        self.available = self.available.saturating_sub(bytes);
+        true
+    }
+}
+
+struct Peer {
+    known_txs: KnownTxs,
+    bandwidth: Bandwidth,
+}
+
+/// Decides which transactions are relayed to which peers.
+///
+/// A transaction is relayed only once to a peer, and never to the peer it came from.
+/// Each peer is sent at most `PEER_BANDWIDTH` bytes per second, the preferred transactions first,
+/// and the transactions left out are tried again in the next round.
+pub struct Propagation {
+    known_txs: KnownTxs,
+    peers: HashMap<NodeId, Peer>,
+}
+
+impl Default for Propagation {
+    fn default() -> Self {
+        Self {
+            known_txs: KnownTxs::with_capacity(MAX_HISTORY_SIZE),
+            peers: Default::default(),
+        }
+    }
+}
+
+impl Propagation {
+    pub fn add_peer(&mut self, peer: NodeId, now: Instant) {
+        self.peers.insert(peer, Peer {
+            known_txs: KnownTxs::with_capacity(MAX_PEER_HISTORY_SIZE),
+            bandwidth: Bandwidth::new(now),
+        });
+    }
+
+    pub fn remove_peer(&mut self, peer: &NodeId) {
+        self.peers.remove(peer);
+    }
+
+    pub fn has_peer(&self, peer: &NodeId) -> bool {
+        self.peers.contains_key(peer)
+    }
+
+    /// Drops the transactions seen before and remembers that the sender knows all of them.
+    pub fn receive(&mut self, from: &NodeId, transactions: Vec<Transaction>) -> Vec<Transaction> {
+        if let Some(peer) = self.peers.get_mut(from) {
+            for tx in &transactions {
+                peer.known_txs.insert(tx.hash());
+            }
+        }
+        let known_txs = &mut self.known_txs;
+        transactions.into_iter().filter(|tx| known_txs.insert(tx.hash())).collect()
+    }
+
+    /// Keeps the transactions that some peer doesn't know.
+    pub fn unsent(&self, mut hashes: Vec<TxHash>) -> Vec<TxHash> {
+        hashes.retain(|hash| self.peers.values().any(|peer| !peer.known_txs.contains(hash)));
+        hashes
+    }
+
+    /// Picks the transactions each peer doesn't know from the given ones, which are ordered from the preferred one.
+    /// When a peer runs out of its bandwidth, the rest are left for the next round.
+    pub fn broadcast(&mut self, transactions: &[Transaction], now: Instant) -> Vec<(NodeId, Vec<Transaction>)> {
+        let mut messages = Vec::new();
+        for (node_id, peer) in &mut self.peers {
+            peer.bandwidth.refill(now);
+            let mut unsent = Vec::new();
+            for tx in transactions {
+                let hash = tx.hash();
+                if peer.known_txs.contains(&hash) {
+                    continue
+                }
+                if !peer.bandwidth.try_consume(tx.size()) {
+                    ctrace!(SYNC_TX, "The transactions to {} are throttled from {}", node_id, hash);
+                    break
+                }
+                peer.known_txs.insert(hash);
+                unsent.push(tx.clone());
+            }
+            if !unsent.is_empty() {
+                messages.push((*node_id, unsent));
+            }
+        }
+        messages
+    }
+
+    /// Picks the given transactions for every peer again, even the ones a peer is supposed to know already.
+    /// Unlike `broadcast`, the transactions left out by the bandwidth are not tried again in the next round.
+    pub fn resend(&mut self, transactions: &[Transaction], now: Instant) -> Vec<(NodeId, Vec<Transaction>)> {
+        let mut messages = Vec::new();
+        for (node_id, peer) in &mut self.peers {
+            peer.bandwidth.refill(now);
+            let mut resent = Vec::new();
+            for tx in transactions {
+                if !peer.bandwidth.try_consume(tx.size()) {
+                    ctrace!(SYNC_TX, "The transactions resent to {} are throttled from {}", node_id, tx.hash());
+                    break
+                }
+                peer.known_txs.insert(tx.hash());
+                resent.push(tx.clone());
+            }
+            if !resent.is_empty() {
+                messages.push((*node_id, resent));
+            }
+        }
+        messages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::time::Duration;
+
+    fn node(port: u16) -> NodeId {
+        NodeId::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port)
+    }
+
+    fn tx(seed: u8, size: usize) -> Transaction {
+        let mut body = vec![0; size];
+        body[0] = seed;
+        Transaction::new("sample".to_string(), body)
+    }
+
+    #[test]
+    fn known_txs_forget_the_oldest() {
+        let mut known_txs = KnownTxs::with_capacity(2);
+        let hashes: Vec<_> = (0..3).map(|seed| tx(seed, 1).hash()).collect();
+        for hash in &hashes {
+            assert!(known_txs.insert(*hash));
+        }
+        assert!(!known_txs.contains(&hashes[0]));
+        assert!(known_txs.contains(&hashes[1]));
+        assert!(!known_txs.insert(hashes[2]));
+    }
+
+    #[test]
+    fn transactions_seen_before_are_dropped() {
+        let now = Instant::now();
+        let mut propagation = Propagation::default();
+        propagation.add_peer(node(1), now);
+        propagation.add_peer(node(2), now);
+
+        assert_eq!(propagation.receive(&node(1), vec![tx(0, 1), tx(1, 1)]).len(), 2);
+        assert_eq!(propagation.receive(&node(2), vec![tx(1, 1), tx(2, 1)]), vec![tx(2, 1)]);
+    }
+
+    #[test]
+    fn transactions_are_not_sent_back() {
+        let now = Instant::now();
+        let mut propagation = Propagation::default();
+        propagation.add_peer(node(1), now);
+        propagation.add_peer(node(2), now);
+        let received = propagation.receive(&node(1), vec![tx(0, 1)]);
+        let hashes = vec![tx(0, 1).hash()];
+        assert_eq!(propagation.unsent(hashes.clone()), hashes);
+
+        assert_eq!(propagation.broadcast(&received, now), vec![(node(2), vec![tx(0, 1)])]);
+        assert_eq!(propagation.broadcast(&received, now), Vec::new());
+        assert!(propagation.unsent(hashes).is_empty());
+    }
+
+    #[test]
+    fn throttled_peer_is_sent_the_preferred_transactions_first() {
+        let now = Instant::now();
+        let mut propagation = Propagation::default();
+        propagation.add_peer(node(1), now);
+        let size = PEER_BURST / 4;
+        let transactions: Vec<_> = (0..4).map(|seed| tx(seed, size)).collect();
+
+        let sent = propagation.broadcast(&transactions, now);
+        assert_eq!(sent, vec![(node(1), transactions[..3].to_vec())]);
+        assert_eq!(propagation.broadcast(&transactions, now), Vec::new());
+
+        let sent = propagation.broadcast(&transactions, now + Duration::from_secs(2));
+        assert_eq!(sent, vec![(node(1), transactions[3..].to_vec())]);
+    }
+
+    #[test]
+    fn resent_transactions_reach_peers_knowing_them() {
+        let now = Instant::now();
+        let mut propagation = Propagation::default();
+        propagation.add_peer(node(1), now);
+        let transactions = vec![tx(0, 1)];
+
+        assert_eq!(propagation.broadcast(&transactions, now), vec![(node(1), transactions.clone())]);
+        assert_eq!(propagation.broadcast(&transactions, now), Vec::new());
+        assert_eq!(propagation.resend(&transactions, now), vec![(node(1), transactions)]);
+    }
+}
@@ -0,0 +1,176 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use coordinator::{Transaction, TransactionWithMetadata};
+use ctypes::TxHash;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// The delay before a local transaction is sent again for the first time.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(10);
+/// The longest delay between two resends of a local transaction.
+const MAX_BACKOFF: Duration = Duration::from_secs(10 * 60);
+/// The seconds of chain time after which a local transaction is no longer resent.
+const LIFETIME: u64 = 60 * 60;
+
+struct Retry {
+    attempts: u32,
+    next_at: Instant,
+}
+
+/// Where a local transaction is in the resend schedule.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResendStatus {
+    pub hash: TxHash,
+    /// The times it has been resent since the node started
+    pub attempts: u32,
+    /// None if it is no longer resent, or not scheduled yet because the node is syncing
+    pub next_resend_in: Option<Duration>,
+}
+
+/// Schedules resending the transactions submitted to this node until they leave the mem pool.
+///
+/// The delay doubles after each resend up to `MAX_BACKOFF`. A transaction stops being resent once it has
+/// stayed `LIFETIME` seconds of chain time in the mem pool, which is measured from the metadata kept by the
+/// mem pool, so that the lifetime carries over node restarts.
+#[derive(Default)]
+pub struct Rebroadcast {
+    retries: HashMap<TxHash, Retry>,
+}
+
+impl Rebroadcast {
+    /// Picks the local transactions due to be resent, forgetting the ones no longer pending.
+    pub fn due(&mut self, local: &[TransactionWithMetadata], now: Instant, chain_timestamp: u64) -> Vec<Transaction> {
+        let mut retries = HashMap::with_capacity(local.len());
+        let mut due = Vec::new();
+        for item in local {
+            if chain_timestamp.saturating_sub(item.inserted_timestamp) > LIFETIME {
+                continue
+            }
+            let hash = item.tx.hash();
+            let mut retry = self.retries.remove(&hash).unwrap_or_else(|| Retry {
+                attempts: 0,
+                next_at: now + INITIAL_BACKOFF,
+            });
+            if retry.next_at <= now {
+                retry.attempts += 1;
+                retry.next_at = now + backoff(retry.attempts);
+                due.push(item.tx.clone());
+            }
+            retries.insert(hash, retry);
+        }
+        self.retries = retries;
+        due
+    }
+
+    /// Schedules the local transactions recovered from the mem pool backup to be resent right away,
+    /// because the peers may have dropped them while this node was down.
+    pub fn resume(&mut self, recovered: &[TransactionWithMetadata], now: Instant) {
+        for item in recovered {
+            self.retries.insert(item.tx.hash(), Retry {
+                attempts: 0,
+                next_at: now,
+            });
+        }
+    }
+
+    pub fn status(&self, local: &[TransactionWithMetadata], now: Instant, chain_timestamp: u64) -> Vec<ResendStatus> {
+        local
+            .iter()
+            .map(|item| {
+                let hash = item.tx.hash();
+                let retry = self.retries.get(&hash);
+                let expired = chain_timestamp.saturating_sub(item.inserted_timestamp) > LIFETIME;
+                ResendStatus {
+                    hash,
+                    attempts: retry.map_or(0, |retry| retry.attempts),
+                    next_resend_in: retry
+                        .filter(|_| !expired)
+                        .map(|retry| retry.next_at.checked_duration_since(now).unwrap_or_default()),
+                }
+            })
+            .collect()
+    }
+}
+
+fn backoff(attempts: u32) -> Duration {
+    INITIAL_BACKOFF.checked_mul(1 << attempts.min(16)).map_or(MAX_BACKOFF, |backoff| backoff.min(MAX_BACKOFF))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use coordinator::TxOrigin;
+
+    fn local(seed: u8, inserted_timestamp: u64) -> TransactionWithMetadata {
+        let tx = Transaction::new("sample".to_string(), vec![seed]);
+        TransactionWithMetadata::new(tx, TxOrigin::Local, 0, inserted_timestamp, seed as u64, None)
+    }
+
+    #[test]
+    fn resend_intervals_double_up_to_the_cap() {
+        let start = Instant::now();
+        let mut rebroadcast = Rebroadcast::default();
+        let local = vec![local(0, 0)];
+
+        assert_eq!(rebroadcast.due(&local, start, 0), Vec::new());
+        let mut at = start + INITIAL_BACKOFF;
+        let mut expected = INITIAL_BACKOFF;
+        for _ in 0..10 {
+            assert_eq!(rebroadcast.due(&local, at - Duration::from_millis(1), 0), Vec::new());
+            assert_eq!(rebroadcast.due(&local, at, 0), vec![local[0].tx.clone()]);
+            expected = (expected * 2).min(MAX_BACKOFF);
+            at += expected;
+        }
+        assert_eq!(expected, MAX_BACKOFF);
+    }
+
+    #[test]
+    fn expired_transactions_are_not_resent() {
+        let start = Instant::now();
+        let mut rebroadcast = Rebroadcast::default();
+        let local = vec![local(0, 100), local(1, 200)];
+        rebroadcast.due(&local, start, 100);
+
+        let due = rebroadcast.due(&local, start + INITIAL_BACKOFF, 150 + LIFETIME);
+        assert_eq!(due, vec![local[1].tx.clone()]);
+    }
+
+    #[test]
+    fn recovered_transactions_are_resent_right_away() {
+        let start = Instant::now();
+        let mut rebroadcast = Rebroadcast::default();
+        let local = vec![local(0, 0)];
+        rebroadcast.resume(&local, start);
+
+        assert_eq!(rebroadcast.due(&local, start, 0), vec![local[0].tx.clone()]);
+        let status = rebroadcast.status(&local, start, 0);
+        assert_eq!(status[0].attempts, 1);
+        assert_eq!(status[0].next_resend_in, Some(backoff(1)));
+    }
+
+    #[test]
+    fn transactions_left_the_pool_are_forgotten() {
+        let start = Instant::now();
+        let mut rebroadcast = Rebroadcast::default();
+        let local = vec![local(0, 0)];
+        rebroadcast.due(&local, start, 0);
+
+        rebroadcast.due(&[], start, 0);
+        assert!(rebroadcast.retries.is_empty());
+        assert_eq!(rebroadcast.due(&local, start + INITIAL_BACKOFF, 0), Vec::new());
+    }
+}
@@ -67,9 +67,14 @@ function encodableMessage(data: MessageData): RLP.Input {
     const encodableBlockHash =
         blockHash === null ? [] : [blockHash.toEncodeObject()];
     const encodableVoteOn = [encodableVoteStep, encodableBlockHash];
+    // A vote signs the time it is made at as well.
+    const encodableTimestamp = U64.ensure(
+        Math.floor(Date.now() / 1000)
+    ).toEncodeObject();
 
-    const rlpVoteOn = RLP.encode(encodableVoteOn);
-    const messageForEd25519 = blake256(rlpVoteOn);
+    const messageForEd25519 = blake256(
+        RLP.encode([encodableVoteOn, encodableTimestamp])
+    );
     const ed25519Signature = signEd25519(messageForEd25519, privKey);
     // pad because signEd25519 function does not guarantee the length of r and s to be 64.
     const encodableEd25519Signature = new H512(
@@ -79,7 +84,8 @@ function encodableMessage(data: MessageData): RLP.Input {
     return [
         encodableVoteOn,
         encodableEd25519Signature,
-        U64.ensure(signerIdx).toEncodeObject()
+        U64.ensure(signerIdx).toEncodeObject(),
+        encodableTimestamp
     ];
 }
 
@@ -34,6 +34,16 @@ export class Header {
         const timestamp = new U256(parseInt(decodedmsg[7].toString("hex"), 16));
         const lastCommittedValidators: string[] = decodedmsg[8];
         const extraData = decodedmsg[9];
+        // The extension is the only list before the seal.
+        const hasExtension =
+            decodedmsg.getLength() > 10 && Array.isArray(decodedmsg[10]);
+        const externalAnchor = hasExtension
+            ? decodedmsg[10][0]
+            : Buffer.alloc(0);
+        const moduleRootsHash =
+            hasExtension && decodedmsg[10].length > 1
+                ? new H256(decodedmsg[10][1].toString("hex"))
+                : null;
 
         // Be careful of the order! Three roots have same types, so mistake on the order will not be catched by typechecker.
         const header = new Header(
@@ -50,7 +60,10 @@ export class Header {
             []
         );
 
-        for (let i = 8; i < decodedmsg.getLength(); i++) {
+        header.setExternalAnchor(externalAnchor);
+        header.setModuleRootsHash(moduleRootsHash);
+
+        for (let i = hasExtension ? 11 : 10; i < decodedmsg.getLength(); i++) {
             header.seal.push(decodedmsg[i]);
         }
 
@@ -83,6 +96,8 @@ export class Header {
     private author: H256;
     private lastCommittedValidators: string[];
     private extraData: Buffer;
+    private externalAnchor: Buffer = Buffer.alloc(0);
+    private moduleRootsHash: H256 | null = null;
     private evidencesRoot: H256;
     private transactionsRoot: H256;
     private stateRoot: H256;
@@ -141,6 +156,10 @@ export class Header {
         this.extraData = extraData;
     }
 
+    public setExternalAnchor(anchor: Buffer) {
+        this.externalAnchor = anchor;
+    }
+
     public setEvidencesRoot(root: H256) {
         this.evidencesRoot = root;
     }
@@ -157,6 +176,10 @@ export class Header {
         this.nextValidatorSetHash = root;
     }
 
+    public setModuleRootsHash(root: H256 | null) {
+        this.moduleRootsHash = root;
+    }
+
     public setSeal(seal: any[]) {
         this.seal = seal;
     }
@@ -185,7 +208,9 @@ export class Header {
             this.timestamp.toEncodeObject(),
             this.lastCommittedValidators,
             this.extraData
-        ].concat(this.seal);
+        ]
+            .concat(this.extensionEncodeObject())
+            .concat(this.seal);
     }
 
     public rlpBytes(): Buffer {
@@ -195,4 +220,13 @@ export class Header {
     public hashing(): H256 {
         return new H256(blake256(this.rlpBytes()));
     }
+
+    private extensionEncodeObject(): Array<any> {
+        if (this.moduleRootsHash !== null) {
+            return [
+                [this.externalAnchor, this.moduleRootsHash.toEncodeObject()]
+            ];
+        }
+        return this.externalAnchor.length > 0 ? [[this.externalAnchor]] : [];
+    }
 }
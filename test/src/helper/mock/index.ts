@@ -533,18 +533,22 @@ export class Mock {
             "consensusmessage",
             (message: ConsensusMessage) => {
                 const digest = (
-                    on: ConsensusMessage["messages"][number]["on"]
+                    on: ConsensusMessage["messages"][number]["on"],
+                    timestamp: number
                 ) =>
                     blake256(
                         RLP.encode([
                             [
-                                new U64(on.step.height).toEncodeObject(),
-                                new U64(on.step.view).toEncodeObject(),
-                                new U64(on.step.step).toEncodeObject()
+                                [
+                                    new U64(on.step.height).toEncodeObject(),
+                                    new U64(on.step.view).toEncodeObject(),
+                                    new U64(on.step.step).toEncodeObject()
+                                ],
+                                on.blockHash == null
+                                    ? []
+                                    : [on.blockHash.toEncodeObject()]
                             ],
-                            on.blockHash == null
-                                ? []
-                                : [on.blockHash.toEncodeObject()]
+                            new U64(timestamp).toEncodeObject()
                         ])
                     );
 
@@ -554,7 +558,11 @@ export class Mock {
                 // handy and efficient.
                 const original = message.messages.find(m => {
                     return (
-                        verifyEd25519(digest(m.on), m.signature, pub) &&
+                        verifyEd25519(
+                            digest(m.on, m.timestamp),
+                            m.signature,
+                            pub
+                        ) &&
                         m.on.step.step === step
                     );
                 });
@@ -563,7 +571,7 @@ export class Mock {
                         step: original.on.step,
                         blockHash: H256.zero()
                     };
-                    const newDigest = digest(newOn);
+                    const newDigest = digest(newOn, original.timestamp);
                     const signature = signEd25519(newDigest, priv);
                     this.sendTendermintMessage(
                         new TendermintMessage({
@@ -572,7 +580,8 @@ export class Mock {
                                 {
                                     on: newOn,
                                     signature,
-                                    signerIndex: original.signerIndex
+                                    signerIndex: original.signerIndex,
+                                    timestamp: original.timestamp
                                 }
                             ]
                         })
@@ -603,23 +612,31 @@ export class Mock {
     public startDoubleProposal(priv: string) {
         const pub = getPublicFromPrivate(priv);
         TendermintEmitter.on("proposalblock", (message: ProposalBlock) => {
-            const digest = (on: ConsensusMessage["messages"][number]["on"]) =>
+            // The proposer signs the proposal at the timestamp of its header.
+            const digest = (
+                on: ConsensusMessage["messages"][number]["on"],
+                timestamp: number
+            ) =>
                 blake256(
                     RLP.encode([
                         [
-                            new U64(on.step.height).toEncodeObject(),
-                            new U64(on.step.view).toEncodeObject(),
-                            new U64(on.step.step).toEncodeObject()
+                            [
+                                new U64(on.step.height).toEncodeObject(),
+                                new U64(on.step.view).toEncodeObject(),
+                                new U64(on.step.step).toEncodeObject()
+                            ],
+                            on.blockHash == null
+                                ? []
+                                : [on.blockHash.toEncodeObject()]
                         ],
-                        on.blockHash == null
-                            ? []
-                            : [on.blockHash.toEncodeObject()]
+                        new U64(timestamp).toEncodeObject()
                     ])
                 );
 
             const signature: Ed25519Signature = message.signature;
 
             const block: any = RLP.decode(message.message);
+            const oldTimestamp = readUIntRLP(block[0][7]);
             const oldOn: Parameters<typeof digest>[0] = {
                 step: {
                     height: readUIntRLP(block[0][6]),
@@ -628,16 +645,20 @@ export class Mock {
                 },
                 blockHash: new H256(blake256(RLP.encode(block[0])))
             };
-            if (verifyEd25519(digest(oldOn), signature, pub)) {
+            if (verifyEd25519(digest(oldOn, oldTimestamp), signature, pub)) {
+                const newTimestamp = oldTimestamp + 1;
                 const newHeader = [
                     ...block[0].slice(0, 7),
-                    new U64(readUIntRLP(block[0][7]) + 1).toEncodeObject(), // timestamp
+                    new U64(newTimestamp).toEncodeObject(),
                     ...block[0].slice(8)
                 ];
-                const newDigest = digest({
-                    ...oldOn,
-                    blockHash: new H256(blake256(RLP.encode(newHeader)))
-                });
+                const newDigest = digest(
+                    {
+                        ...oldOn,
+                        blockHash: new H256(blake256(RLP.encode(newHeader)))
+                    },
+                    newTimestamp
+                );
                 const newSignature = signEd25519(newDigest, priv);
 
                 this.sendTendermintMessage(
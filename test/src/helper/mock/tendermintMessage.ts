@@ -53,6 +53,7 @@ export interface ConsensusMessage {
         };
         signature: string;
         signerIndex: number;
+        timestamp: number;
     }>;
 }
 
@@ -114,7 +115,8 @@ export class TendermintMessage {
                                 )
                             },
                             signature: inner[1].toString("hex"),
-                            signerIndex: readUIntRLP(inner[2])
+                            signerIndex: readUIntRLP(inner[2]),
+                            timestamp: readUIntRLP(inner[3])
                         };
                     })
                 };
@@ -201,7 +203,8 @@ export class TendermintMessage {
                                     : [m.on.blockHash.toEncodeObject()]
                             ],
                             Buffer.from(m.signature, "hex"),
-                            new U64(m.signerIndex).toEncodeObject()
+                            new U64(m.signerIndex).toEncodeObject(),
+                            new U64(m.timestamp).toEncodeObject()
                         ])
                     )
                 ];
@@ -29,10 +29,12 @@ export interface ConsensusMessage {
     };
     signature: H512;
     signerIndex: U64;
+    timestamp: U64;
 }
 
+// A message without a timestamp keeps the three fields it had before the timestamped votes.
 export function messageToEncodeObject(message: ConsensusMessage) {
-    return [
+    const encoded: any[] = [
         [
             [
                 message.on.step.height.toEncodeObject(),
@@ -46,12 +48,16 @@ export function messageToEncodeObject(message: ConsensusMessage) {
         message.signature.toEncodeObject(),
         message.signerIndex.toEncodeObject()
     ];
+    if (!message.timestamp.isEqualTo(0)) {
+        encoded.push(message.timestamp.toEncodeObject());
+    }
+    return encoded;
 }
 
 export function decodeMessage(list: any[]): ConsensusMessage {
-    if (list.length !== 3) {
+    if (list.length !== 3 && list.length !== 4) {
         throw new Error(
-            "The raw value of ConsensusMessage should be a list of length 3"
+            "The raw value of ConsensusMessage should be a list of length 3 or 4"
         );
     }
     if (!Array.isArray(list[0]) || list[0].length !== 2) {
@@ -89,6 +95,7 @@ export function decodeMessage(list: any[]): ConsensusMessage {
 
     const signature = decodeH512(list[1]);
     const signerIndex = decodeU64(list[2]);
+    const timestamp = list.length === 4 ? decodeU64(list[3]) : new U64(0);
 
     return {
         on: {
@@ -96,6 +103,7 @@ export function decodeMessage(list: any[]): ConsensusMessage {
             blockHash
         },
         signature,
-        signerIndex
+        signerIndex,
+        timestamp
     };
 }
@@ -0,0 +1,37 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Deterministic tests of many nodes in one process.
+//!
+//! `SimNetwork` runs the real network extensions of the nodes over a simulated network, whose messages and timers are
+//! events on a virtual clock. The latency and the loss of each link can be set, and the network can be partitioned.
+//! `TestNode` is a node with a real client that syncs blocks and transactions over it, and authors blocks if it's given
+//! a key pair.
+
+extern crate codechain_core as ccore;
+extern crate codechain_key as ckey;
+extern crate codechain_network as cnetwork;
+extern crate codechain_sync as csync;
+extern crate codechain_timer as ctimer;
+extern crate codechain_types as ctypes;
+
+mod network;
+mod node;
+mod scheduler;
+
+pub use crate::network::{LinkConditions, Report, SimNetwork};
+pub use crate::node::TestNode;
+pub use crate::scheduler::Scheduler;
@@ -0,0 +1,839 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::scheduler::Scheduler;
+use cnetwork::{Api, EventReceiver, EventSender, Misbehavior, NetworkExtension, NetworkExtensionResult, NodeId};
+use ctimer::{TimerScheduleError, TimerToken};
+use parking_lot::Mutex;
+use primitives::Bytes;
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The port every simulated node pretends to listen on. The nodes are told apart by their addresses.
+const PORT: u16 = 3485;
+
+/// How the messages from a node to another travel.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LinkConditions {
+    pub min_latency: Duration,
+    /// The latency of a message is picked uniformly between the two.
+    pub max_latency: Duration,
+    /// The probability that a message is lost.
+    pub loss: f64,
+}
+
+impl Default for LinkConditions {
+    fn default() -> Self {
+        Self {
+            min_latency: Duration::from_millis(10),
+            max_latency: Duration::from_millis(10),
+            loss: 0.0,
+        }
+    }
+}
+
+impl LinkConditions {
+    fn assert_valid(&self) {
+        assert!(self.min_latency <= self.max_latency, "The latency range is empty: {:?}", self);
+        assert!((0.0..=1.0).contains(&self.loss), "The loss is not a probability: {:?}", self);
+    }
+}
+
+/// A misbehavior an extension reported through its `Api`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Report {
+    pub reporter: usize,
+    pub reported: NodeId,
+    pub misbehavior: Misbehavior,
+}
+
+enum Event {
+    NodeAdded {
+        node: usize,
+        peer: usize,
+        extension: &'static str,
+    },
+    NodeRemoved {
+        node: usize,
+        peer: usize,
+        extension: &'static str,
+    },
+    Message {
+        from: usize,
+        to: usize,
+        extension: &'static str,
+        data: Arc<Bytes>,
+    },
+    Timeout {
+        node: usize,
+        extension: &'static str,
+        token: TimerToken,
+        generation: u64,
+    },
+}
+
+/// What the extensions asked for while they were handling an event.
+enum Outgoing {
+    Message {
+        from: usize,
+        to: NodeId,
+        extension: &'static str,
+        data: Arc<Bytes>,
+    },
+    Timer {
+        node: usize,
+        extension: &'static str,
+        token: TimerToken,
+        generation: u64,
+        after: Duration,
+    },
+}
+
+struct Timer {
+    /// Tells a cleared timer from the one set again with the same token.
+    generation: u64,
+    repeat: Option<Duration>,
+}
+
+#[derive(Default)]
+struct Shared {
+    outbox: Vec<Outgoing>,
+    timers: HashMap<(usize, &'static str, TimerToken), Timer>,
+    next_generation: u64,
+    reports: Vec<Report>,
+}
+
+struct SimApi {
+    node: usize,
+    extension: &'static str,
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl SimApi {
+    fn schedule(&self, token: TimerToken, after: Duration, repeat: Option<Duration>) -> NetworkExtensionResult<()> {
+        let mut shared = self.shared.lock();
+        let key = (self.node, self.extension, token);
+        if shared.timers.contains_key(&key) {
+            return Err(TimerScheduleError::TokenAlreadyScheduled.into())
+        }
+        let generation = shared.next_generation;
+        shared.next_generation += 1;
+        shared.timers.insert(key, Timer {
+            generation,
+            repeat,
+        });
+        shared.outbox.push(Outgoing::Timer {
+            node: self.node,
+            extension: self.extension,
+            token,
+            generation,
+            after,
+        });
+        Ok(())
+    }
+}
+
+impl Api for SimApi {
+    fn send(&self, node: &NodeId, message: Arc<Bytes>) {
+        self.shared.lock().outbox.push(Outgoing::Message {
+            from: self.node,
+            to: *node,
+            extension: self.extension,
+            data: message,
+        });
+    }
+
+    fn set_timer(&self, token: TimerToken, d: Duration) -> NetworkExtensionResult<()> {
+        self.schedule(token, d, Some(d))
+    }
+
+    fn set_timer_once(&self, token: TimerToken, d: Duration) -> NetworkExtensionResult<()> {
+        self.schedule(token, d, None)
+    }
+
+    fn clear_timer(&self, token: TimerToken) -> NetworkExtensionResult<()> {
+        self.shared.lock().timers.remove(&(self.node, self.extension, token));
+        Ok(())
+    }
+
+    fn peer_score(&self, _node: &NodeId) -> i64 {
+        0
+    }
+
+    fn record_useful_response(&self, _node: &NodeId) {}
+
+    fn report(&self, node: &NodeId, misbehavior: Misbehavior) {
+        self.shared.lock().reports.push(Report {
+            reporter: self.node,
+            reported: *node,
+            misbehavior,
+        });
+    }
+}
+
+/// An extension of any type, with the channel of its events.
+trait Endpoint {
+    fn name(&self) -> &'static str;
+    fn on_node_added(&mut self, node: &NodeId);
+    fn on_node_removed(&mut self, node: &NodeId);
+    fn on_message(&mut self, node: &NodeId, message: &[u8]);
+    fn on_timeout(&mut self, token: TimerToken);
+    /// Handles the queued events. It returns false if there were none.
+    fn handle_events(&mut self) -> bool;
+}
+
+struct ExtensionEndpoint<T, E> {
+    extension: T,
+    events: EventReceiver<E>,
+}
+
+impl<T, E> Endpoint for ExtensionEndpoint<T, E>
+where
+    T: NetworkExtension<E>,
+    E: Send,
+{
+    fn name(&self) -> &'static str {
+        T::name()
+    }
+
+    fn on_node_added(&mut self, node: &NodeId) {
+        let version = *T::versions().last().expect("An extension has at least one version");
+        self.extension.on_node_added(node, version);
+    }
+
+    fn on_node_removed(&mut self, node: &NodeId) {
+        self.extension.on_node_removed(node);
+    }
+
+    fn on_message(&mut self, node: &NodeId, message: &[u8]) {
+        self.extension.on_message(node, message);
+    }
+
+    fn on_timeout(&mut self, token: TimerToken) {
+        self.extension.on_timeout(token);
+    }
+
+    fn handle_events(&mut self) -> bool {
+        let mut handled = false;
+        while let Ok(event) = self.events.try_recv() {
+            self.extension.on_event(event);
+            handled = true;
+        }
+        handled
+    }
+}
+
+/// Network extensions of many nodes in one process, connected by a simulated network.
+///
+/// Nothing goes through sockets or timer threads: the messages and the timers are events on a virtual clock, and they
+/// are handled one by one in the calling thread. With the same seed and the same inputs, every run delivers the same
+/// messages in the same order at the same virtual times, so a failing scenario can be replayed.
+/// Every pair of nodes is connected unless they are partitioned, and a link delivers its messages in order like the
+/// session it stands for.
+pub struct SimNetwork {
+    scheduler: Scheduler<Event>,
+    nodes: Vec<Vec<Box<dyn Endpoint>>>,
+    indices: HashMap<NodeId, usize>,
+    shared: Arc<Mutex<Shared>>,
+    default_link: LinkConditions,
+    links: HashMap<(usize, usize), LinkConditions>,
+    /// When the last message on each link arrives, which the next one can't overtake.
+    last_arrivals: HashMap<(usize, usize), Duration>,
+    /// The pairs of nodes that can't talk to each other, the smaller index first.
+    separated: HashSet<(usize, usize)>,
+    settle_hooks: Vec<Box<dyn Fn()>>,
+    delivered: usize,
+    dropped: usize,
+}
+
+impl SimNetwork {
+    pub fn new(size: usize, seed: u64) -> Self {
+        assert!(size <= 1 << 16, "Too many nodes: {}", size);
+        Self {
+            scheduler: Scheduler::new(seed),
+            nodes: (0..size).map(|_| Vec::new()).collect(),
+            indices: (0..size).map(|index| (Self::node_id(index), index)).collect(),
+            shared: Default::default(),
+            default_link: Default::default(),
+            links: Default::default(),
+            last_arrivals: Default::default(),
+            separated: Default::default(),
+            settle_hooks: Vec::new(),
+            delivered: 0,
+            dropped: 0,
+        }
+    }
+
+    /// The id the other nodes know the node by.
+    pub fn node_id(index: usize) -> NodeId {
+        NodeId::new(IpAddr::V4(Ipv4Addr::new(10, 0, (index >> 8) as u8, index as u8)), PORT)
+    }
+
+    pub fn index_of(&self, id: &NodeId) -> Option<usize> {
+        self.indices.get(id).copied()
+    }
+
+    pub fn size(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// The time elapsed on the virtual clock.
+    pub fn now(&self) -> Duration {
+        self.scheduler.now()
+    }
+
+    /// The number of messages handed to the extensions.
+    pub fn delivered(&self) -> usize {
+        self.delivered
+    }
+
+    /// The number of messages lost on the links, between partitioned nodes or on the way to a stopped node.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// The misbehaviors the extensions reported so far.
+    pub fn reports(&self) -> Vec<Report> {
+        self.shared.lock().reports.clone()
+    }
+
+    /// Sets the conditions of the links that don't have their own.
+    pub fn set_default_link(&mut self, conditions: LinkConditions) {
+        conditions.assert_valid();
+        self.default_link = conditions;
+    }
+
+    /// Sets the conditions of the link from `from` to `to`. The link back is not changed.
+    pub fn set_link(&mut self, from: usize, to: usize, conditions: LinkConditions) {
+        conditions.assert_valid();
+        self.links.insert((from, to), conditions);
+    }
+
+    /// Creates the extension of the node with the `Api` of the simulated network.
+    /// The events sent through the returned channel are handled before the next event of the network.
+    pub fn register_extension<T, E, F>(&mut self, node: usize, factory: F) -> EventSender<E>
+    where
+        T: 'static + NetworkExtension<E>,
+        E: 'static + Send,
+        F: FnOnce(Box<dyn Api>) -> T, {
+        let name = T::name();
+        assert!(!self.has_extension(node, name), "The node #{} already has {}", node, name);
+        let (sender, events) = crossbeam_channel::unbounded();
+        let api = SimApi {
+            node,
+            extension: name,
+            shared: Arc::clone(&self.shared),
+        };
+        let extension = factory(Box::new(api));
+        self.nodes[node].push(Box::new(ExtensionEndpoint {
+            extension,
+            events,
+        }));
+
+        for peer in 0..self.size() {
+            if self.is_connected(node, peer) && self.has_extension(peer, name) {
+                self.add_node(node, peer, name);
+                self.add_node(peer, node, name);
+            }
+        }
+        self.flush_outbox();
+        sender
+    }
+
+    /// Removes the extensions of the node, as if it were shut down.
+    /// The other nodes see it removed, and its timers and the messages on the way to it are dropped.
+    /// The node can register its extensions again, and then the other nodes see it added.
+    pub fn remove_extensions(&mut self, node: usize) {
+        let mut removed = Vec::new();
+        for peer in 0..self.size() {
+            if self.is_connected(node, peer) {
+                removed.extend(self.common_extensions(node, peer).into_iter().map(|name| (peer, name)));
+            }
+        }
+        for (peer, name) in removed {
+            self.remove_node(peer, node, name);
+        }
+        self.nodes[node].clear();
+        self.shared.lock().timers.retain(|(timer_node, ..), _| *timer_node != node);
+    }
+
+    /// Adds a hook that runs before and after every event.
+    /// A node whose client works in its own threads uses it to finish that work before the clock moves on.
+    pub fn add_settle_hook<F>(&mut self, hook: F)
+    where
+        F: 'static + Fn(), {
+        self.settle_hooks.push(Box::new(hook));
+    }
+
+    pub fn is_connected(&self, a: usize, b: usize) -> bool {
+        a != b && !self.separated.contains(&pair(a, b))
+    }
+
+    /// Splits the network so that the nodes only talk to the nodes in the same group.
+    /// A node that is not in any group keeps talking to everyone.
+    /// The extensions see the nodes in the other groups removed, and the messages on the way to them are lost.
+    pub fn partition(&mut self, groups: &[&[usize]]) {
+        for (i, group) in groups.iter().enumerate() {
+            for other in &groups[i + 1..] {
+                for a in group.iter() {
+                    for b in other.iter() {
+                        if self.separated.insert(pair(*a, *b)) {
+                            for name in self.common_extensions(*a, *b) {
+                                self.remove_node(*a, *b, name);
+                                self.remove_node(*b, *a, name);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Removes all partitions. The extensions see the nodes that were kept apart added again.
+    pub fn heal(&mut self) {
+        let mut separated: Vec<_> = self.separated.drain().collect();
+        // The set has no order, so the events are scheduled in the order of the pairs to keep the runs the same.
+        separated.sort();
+        for (a, b) in separated {
+            for name in self.common_extensions(a, b) {
+                self.add_node(a, b, name);
+                self.add_node(b, a, name);
+            }
+        }
+    }
+
+    /// Handles the next event. It returns false if there is none.
+    pub fn step(&mut self) -> bool {
+        self.step_until(None)
+    }
+
+    /// Handles the events of the next `duration` and moves the clock to its end.
+    pub fn run_for(&mut self, duration: Duration) {
+        let until = self.now() + duration;
+        while self.step_until(Some(until)) {}
+        self.scheduler.advance_to(until);
+    }
+
+    /// Handles the events until the condition holds. It returns false if the condition doesn't hold within `limit`.
+    pub fn run_until<F>(&mut self, limit: Duration, mut condition: F) -> bool
+    where
+        F: FnMut(&Self) -> bool, {
+        let deadline = self.now() + limit;
+        self.settle();
+        loop {
+            if condition(self) {
+                return true
+            }
+            if !self.step_until(Some(deadline)) {
+                self.scheduler.advance_to(deadline);
+                return condition(self)
+            }
+        }
+    }
+
+    fn step_until(&mut self, until: Option<Duration>) -> bool {
+        self.settle();
+        let event = match until {
+            Some(until) => self.scheduler.pop_until(until),
+            None => self.scheduler.pop(),
+        };
+        match event {
+            Some(event) => {
+                self.dispatch(event);
+                self.settle();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn dispatch(&mut self, event: Event) {
+        match event {
+            Event::NodeAdded {
+                node,
+                peer,
+                extension,
+            } => {
+                if let Some(endpoint) = self.endpoint(node, extension) {
+                    endpoint.on_node_added(&Self::node_id(peer));
+                }
+            }
+            Event::NodeRemoved {
+                node,
+                peer,
+                extension,
+            } => {
+                if let Some(endpoint) = self.endpoint(node, extension) {
+                    endpoint.on_node_removed(&Self::node_id(peer));
+                }
+            }
+            Event::Message {
+                from,
+                to,
+                extension,
+                data,
+            } => {
+                if !self.is_connected(from, to) {
+                    self.dropped += 1;
+                    return
+                }
+                match self.endpoint(to, extension) {
+                    Some(endpoint) => {
+                        endpoint.on_message(&Self::node_id(from), &data);
+                        self.delivered += 1;
+                    }
+                    None => self.dropped += 1,
+                }
+            }
+            Event::Timeout {
+                node,
+                extension,
+                token,
+                generation,
+            } => {
+                let fires = {
+                    let mut shared = self.shared.lock();
+                    let key = (node, extension, token);
+                    let live = shared.timers.get(&key).filter(|timer| timer.generation == generation);
+                    match live.map(|timer| timer.repeat) {
+                        Some(Some(repeat)) => {
+                            self.scheduler.schedule_after(repeat, Event::Timeout {
+                                node,
+                                extension,
+                                token,
+                                generation,
+                            });
+                            true
+                        }
+                        Some(None) => {
+                            shared.timers.remove(&key);
+                            true
+                        }
+                        None => false,
+                    }
+                };
+                if fires {
+                    if let Some(endpoint) = self.endpoint(node, extension) {
+                        endpoint.on_timeout(token);
+                    }
+                }
+            }
+        }
+        self.flush_outbox();
+    }
+
+    /// Runs the hooks and handles the events of the extensions until nothing is left.
+    fn settle(&mut self) {
+        loop {
+            for hook in &self.settle_hooks {
+                hook();
+            }
+            let mut handled = false;
+            for endpoints in &mut self.nodes {
+                for endpoint in endpoints.iter_mut() {
+                    handled |= endpoint.handle_events();
+                }
+            }
+            self.flush_outbox();
+            if !handled {
+                break
+            }
+        }
+    }
+
+    fn flush_outbox(&mut self) {
+        let outbox = std::mem::take(&mut self.shared.lock().outbox);
+        for outgoing in outbox {
+            match outgoing {
+                Outgoing::Message {
+                    from,
+                    to,
+                    extension,
+                    data,
+                } => {
+                    let to = match self.index_of(&to) {
+                        Some(to) if self.is_connected(from, to) => to,
+                        _ => {
+                            self.dropped += 1;
+                            continue
+                        }
+                    };
+                    let link = self.links.get(&(from, to)).copied().unwrap_or(self.default_link);
+                    if link.loss > 0.0 && self.scheduler.rng().gen_bool(link.loss) {
+                        self.dropped += 1;
+                        continue
+                    }
+                    let jitter = (link.max_latency - link.min_latency).as_micros() as u64;
+                    let latency =
+                        link.min_latency + Duration::from_micros(self.scheduler.rng().gen_range(0, jitter + 1));
+                    let last_arrival = self.last_arrivals.entry((from, to)).or_default();
+                    let arrival = (self.scheduler.now() + latency).max(*last_arrival);
+                    *last_arrival = arrival;
+                    self.scheduler.schedule_at(arrival, Event::Message {
+                        from,
+                        to,
+                        extension,
+                        data,
+                    });
+                }
+                Outgoing::Timer {
+                    node,
+                    extension,
+                    token,
+                    generation,
+                    after,
+                } => self.scheduler.schedule_after(after, Event::Timeout {
+                    node,
+                    extension,
+                    token,
+                    generation,
+                }),
+            }
+        }
+    }
+
+    fn add_node(&mut self, node: usize, peer: usize, extension: &'static str) {
+        self.scheduler.schedule_after(Duration::from_secs(0), Event::NodeAdded {
+            node,
+            peer,
+            extension,
+        });
+    }
+
+    fn remove_node(&mut self, node: usize, peer: usize, extension: &'static str) {
+        self.scheduler.schedule_after(Duration::from_secs(0), Event::NodeRemoved {
+            node,
+            peer,
+            extension,
+        });
+    }
+
+    fn endpoint(&mut self, node: usize, name: &str) -> Option<&mut dyn Endpoint> {
+        self.nodes[node].iter_mut().find(|endpoint| endpoint.name() == name).map(|endpoint| &mut **endpoint)
+    }
+
+    fn has_extension(&self, node: usize, name: &str) -> bool {
+        self.nodes[node].iter().any(|endpoint| endpoint.name() == name)
+    }
+
+    fn common_extensions(&self, a: usize, b: usize) -> Vec<&'static str> {
+        self.nodes[a].iter().map(|endpoint| endpoint.name()).filter(|name| self.has_extension(b, name)).collect()
+    }
+}
+
+fn pair(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    const TICK: TimerToken = 0;
+    const TICK_INTERVAL: Duration = Duration::from_millis(100);
+
+    #[derive(Default)]
+    struct Seen {
+        values: Vec<u64>,
+        ticks: usize,
+    }
+
+    enum GossipEvent {
+        Publish(u64),
+        StopTicking,
+    }
+
+    /// Floods the values to the peers, and gives all of them to a peer that joins.
+    struct Gossip {
+        api: Box<dyn Api>,
+        peers: Vec<NodeId>,
+        seen: Arc<Mutex<Seen>>,
+    }
+
+    impl Gossip {
+        fn new(api: Box<dyn Api>, seen: Arc<Mutex<Seen>>) -> Self {
+            api.set_timer(TICK, TICK_INTERVAL).unwrap();
+            Self {
+                api,
+                peers: Vec::new(),
+                seen,
+            }
+        }
+
+        fn learn(&mut self, value: u64) {
+            let mut seen = self.seen.lock();
+            if seen.values.contains(&value) {
+                return
+            }
+            seen.values.push(value);
+            for peer in &self.peers {
+                self.api.send(peer, Arc::new(value.to_le_bytes().to_vec()));
+            }
+        }
+    }
+
+    impl NetworkExtension<GossipEvent> for Gossip {
+        fn name() -> &'static str {
+            "gossip"
+        }
+
+        fn need_encryption() -> bool {
+            false
+        }
+
+        fn versions() -> &'static [u64] {
+            &[0]
+        }
+
+        fn on_node_added(&mut self, node: &NodeId, _version: u64) {
+            self.peers.push(*node);
+            for value in &self.seen.lock().values {
+                self.api.send(node, Arc::new(value.to_le_bytes().to_vec()));
+            }
+        }
+
+        fn on_node_removed(&mut self, node: &NodeId) {
+            self.peers.retain(|peer| peer != node);
+        }
+
+        fn on_message(&mut self, _node: &NodeId, message: &[u8]) {
+            self.learn(u64::from_le_bytes(message.try_into().unwrap()));
+        }
+
+        fn on_timeout(&mut self, token: TimerToken) {
+            assert_eq!(token, TICK);
+            self.seen.lock().ticks += 1;
+        }
+
+        fn on_event(&mut self, event: GossipEvent) {
+            match event {
+                GossipEvent::Publish(value) => self.learn(value),
+                GossipEvent::StopTicking => self.api.clear_timer(TICK).unwrap(),
+            }
+        }
+    }
+
+    fn gossip_network(size: usize, seed: u64) -> (SimNetwork, Vec<EventSender<GossipEvent>>, Vec<Arc<Mutex<Seen>>>) {
+        let mut network = SimNetwork::new(size, seed);
+        let seen: Vec<Arc<Mutex<Seen>>> = (0..size).map(|_| Default::default()).collect();
+        let senders = (0..size)
+            .map(|node| {
+                let seen = Arc::clone(&seen[node]);
+                network.register_extension(node, move |api| Gossip::new(api, seen))
+            })
+            .collect();
+        (network, senders, seen)
+    }
+
+    #[test]
+    fn a_message_arrives_after_the_latency() {
+        let (mut network, senders, seen) = gossip_network(2, 0);
+        network.set_link(0, 1, LinkConditions {
+            min_latency: Duration::from_millis(100),
+            max_latency: Duration::from_millis(100),
+            loss: 0.0,
+        });
+        // Lets the nodes know each other.
+        network.run_for(Duration::from_secs(0));
+        senders[0].send(GossipEvent::Publish(7)).unwrap();
+
+        assert!(network.run_until(Duration::from_secs(1), |_| seen[1].lock().values == vec![7]));
+        assert_eq!(network.now(), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn partitioned_nodes_meet_again_after_healing() {
+        let (mut network, senders, seen) = gossip_network(3, 0);
+        network.run_for(Duration::from_secs(0));
+        network.partition(&[&[0, 1], &[2]]);
+        senders[0].send(GossipEvent::Publish(7)).unwrap();
+        network.run_for(Duration::from_secs(1));
+        assert_eq!(seen[1].lock().values, vec![7]);
+        assert!(seen[2].lock().values.is_empty());
+
+        network.heal();
+        assert!(network.run_until(Duration::from_secs(1), |_| seen[2].lock().values == vec![7]));
+    }
+
+    #[test]
+    fn a_message_on_the_way_is_lost_by_a_partition() {
+        let (mut network, senders, seen) = gossip_network(2, 0);
+        network.run_for(Duration::from_secs(0));
+        senders[0].send(GossipEvent::Publish(7)).unwrap();
+        // The message takes 10ms, so it's still on the way.
+        network.run_for(Duration::from_millis(5));
+        network.partition(&[&[0], &[1]]);
+        network.run_for(Duration::from_secs(1));
+
+        assert!(seen[1].lock().values.is_empty());
+        assert_eq!(network.dropped(), 1);
+    }
+
+    #[test]
+    fn a_node_registered_again_gets_what_it_missed() {
+        let (mut network, senders, seen) = gossip_network(2, 0);
+        network.run_for(Duration::from_secs(0));
+        network.remove_extensions(1);
+        senders[0].send(GossipEvent::Publish(7)).unwrap();
+        network.run_for(Duration::from_secs(1));
+        let ticks = seen[1].lock().ticks;
+        network.run_for(Duration::from_secs(1));
+        assert!(seen[1].lock().values.is_empty());
+        assert_eq!(seen[1].lock().ticks, ticks);
+
+        let restarted: Arc<Mutex<Seen>> = Default::default();
+        {
+            let restarted = Arc::clone(&restarted);
+            network.register_extension(1, move |api| Gossip::new(api, restarted));
+        }
+        assert!(network.run_until(Duration::from_secs(1), |_| restarted.lock().values == vec![7]));
+    }
+
+    #[test]
+    fn a_repeating_timer_fires_until_cleared() {
+        let (mut network, senders, seen) = gossip_network(1, 0);
+        network.run_for(Duration::from_secs(1));
+        assert_eq!(seen[0].lock().ticks, 10);
+
+        senders[0].send(GossipEvent::StopTicking).unwrap();
+        network.run_for(Duration::from_secs(1));
+        assert_eq!(seen[0].lock().ticks, 10);
+    }
+
+    #[test]
+    fn the_same_seed_replays_the_same_run() {
+        fn run(seed: u64) -> (Vec<Vec<u64>>, usize, usize) {
+            let (mut network, senders, seen) = gossip_network(5, seed);
+            network.set_default_link(LinkConditions {
+                min_latency: Duration::from_millis(1),
+                max_latency: Duration::from_millis(50),
+                loss: 0.2,
+            });
+            for (node, sender) in senders.iter().enumerate() {
+                sender.send(GossipEvent::Publish(node as u64)).unwrap();
+            }
+            network.run_for(Duration::from_secs(1));
+            let seen = seen.iter().map(|seen| seen.lock().values.clone()).collect();
+            (seen, network.delivered(), network.dropped())
+        }
+
+        assert_eq!(run(42), run(42));
+    }
+}
@@ -0,0 +1,178 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::network::SimNetwork;
+use ccore::{
+    AccountProvider, BlockChainClient, BlockChainTrait, ChainNotify, Client, ClientConfig, ClientService, Miner,
+    MinerOptions, MinerService, Scheme, NUM_COLUMNS,
+};
+use ckey::{Ed25519KeyPair, KeyPairTrait};
+use coordinator::engine::{BlockExecutor, GraphQlHandlerProvider, Initializer, NetworkIdentityProvider, TxFilter};
+use coordinator::test_coordinator::TestCoordinator;
+use csync::{BlockSyncExtension, BlockSyncSender, TransactionSyncExtension};
+use ctimer::TimerLoop;
+use ctypes::{BlockHash, BlockId, BlockNumber};
+use kvdb::KeyValueDB;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+use std::thread::sleep;
+use std::time::Duration;
+
+/// A node with a real client and miner, which syncs blocks and transactions over a `SimNetwork`.
+///
+/// The client verifies blocks in its own threads, so the node adds a settle hook that waits for the blocks the client
+/// was given and imports them before the network goes on.
+pub struct TestNode {
+    index: usize,
+    client: Arc<Client>,
+    _service: ClientService,
+    _sync: Arc<BlockSyncSender>,
+    _timer_loop: TimerLoop,
+    /// Cleared when the node shuts down, so its settle hook stops waiting on the queues of the client.
+    running: Arc<AtomicBool>,
+}
+
+impl TestNode {
+    /// Starts the node of the given index with the solo scheme, so the nodes share the genesis block.
+    pub fn solo(network: &mut SimNetwork, index: usize) -> Result<Self, String> {
+        Self::start(
+            network,
+            index,
+            &Scheme::new_test_solo(),
+            Self::new_db(),
+            Arc::new(TestCoordinator::default()),
+            None,
+        )
+    }
+
+    /// An empty in-memory database for a node.
+    pub fn new_db() -> Arc<dyn KeyValueDB> {
+        Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap_or(0)))
+    }
+
+    /// Starts the node of the given index on the database, which authors blocks with the key pair if it's given.
+    /// A node started on the database of a stopped node continues from the chain it had.
+    pub fn start<C>(
+        network: &mut SimNetwork,
+        index: usize,
+        scheme: &Scheme,
+        db: Arc<dyn KeyValueDB>,
+        coordinator: Arc<C>,
+        author: Option<&Ed25519KeyPair>,
+    ) -> Result<Self, String>
+    where
+        C: 'static + Initializer + BlockExecutor + TxFilter + GraphQlHandlerProvider + NetworkIdentityProvider, {
+        let miner = Miner::new(MinerOptions::default(), scheme, Arc::clone(&db), Arc::clone(&coordinator));
+        if let Some(author) = author {
+            let ap = AccountProvider::transient_provider();
+            let password = String::new().into();
+            ap.insert_account(author.private().clone(), &password).map_err(|err| err.to_string())?;
+            ap.unlock_account_permanently(*author.public(), password).map_err(|err| err.to_string())?;
+            miner.set_author(ap, *author.public()).map_err(|err| err.to_string())?;
+        }
+
+        let timer_loop = TimerLoop::new(1);
+        let reseal_timer = timer_loop.new_timer_with_name("Client reseal timer");
+        let service = ClientService::start(
+            &ClientConfig::default(),
+            scheme,
+            db,
+            Arc::clone(&miner),
+            coordinator,
+            reseal_timer.clone(),
+        )
+        .map_err(|err| format!("Client service error: {}", err))?;
+        reseal_timer.set_handler(Arc::downgrade(&service.client()));
+        miner.recover_from_db();
+        let client = service.client();
+
+        let sync = {
+            let client = Arc::clone(&client);
+            Arc::new(BlockSyncSender::from(
+                network.register_extension(index, move |api| BlockSyncExtension::new(client, api, None, None, None)),
+            ))
+        };
+        client.add_notify(Arc::downgrade(&sync) as Weak<dyn ChainNotify>);
+        {
+            let client = Arc::clone(&client);
+            network.register_extension(index, move |api| TransactionSyncExtension::new(client, api));
+        }
+        let running = Arc::new(AtomicBool::new(true));
+        {
+            let client = Arc::downgrade(&client);
+            let running = Arc::clone(&running);
+            network.add_settle_hook(move || {
+                if let Some(client) = client.upgrade() {
+                    if running.load(Ordering::SeqCst) {
+                        flush_queues(&client);
+                    }
+                }
+            });
+        }
+
+        Ok(Self {
+            index,
+            client,
+            _service: service,
+            _sync: sync,
+            _timer_loop: timer_loop,
+            running,
+        })
+    }
+
+    /// Shuts the node down and removes it from the network. Its database is kept by whoever gave it.
+    pub fn stop(self, network: &mut SimNetwork) {
+        network.remove_extensions(self.index);
+    }
+
+    pub fn client(&self) -> &Arc<Client> {
+        &self.client
+    }
+
+    /// Seals the given number of blocks on top of the best block.
+    /// The timestamps of the blocks must increase, so it takes about a second for each block.
+    /// A validator only seals the blocks of its turns.
+    pub fn mine(&self, count: usize) {
+        for _ in 0..count {
+            self.client.update_sealing(BlockId::Latest, true);
+        }
+    }
+
+    pub fn best_block_number(&self) -> BlockNumber {
+        self.client.block_number(&BlockId::Latest).expect("The best block always exists")
+    }
+
+    pub fn best_block_hash(&self) -> BlockHash {
+        self.client.best_block_header().hash()
+    }
+}
+
+impl Drop for TestNode {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        self.client.shutdown();
+    }
+}
+
+/// Waits until the queued headers and blocks are verified, and imports them in the calling thread.
+/// It lets the network go on only after the blocks it gave to the client are in the chain.
+fn flush_queues(client: &Client) {
+    while client.header_queue_info().incomplete_queue_size() != 0 || client.queue_info().incomplete_queue_size() != 0 {
+        sleep(Duration::from_millis(1));
+    }
+    client.import_verified_headers();
+    client.import_verified_blocks();
+}
@@ -0,0 +1,184 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::time::Duration;
+
+/// A queue of events on a virtual clock.
+///
+/// The events are popped in the order of their times, and the events of the same time in the order they were scheduled,
+/// so a run depends only on the seed and the inputs. Popping an event moves the clock to its time at once.
+pub struct Scheduler<T> {
+    now: Duration,
+    next_seq: u64,
+    queue: BinaryHeap<Entry<T>>,
+    rng: XorShiftRng,
+}
+
+struct Entry<T> {
+    at: Duration,
+    seq: u64,
+    event: T,
+}
+
+impl<T> PartialEq for Entry<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.at == other.at && self.seq == other.seq
+    }
+}
+
+impl<T> Eq for Entry<T> {}
+
+impl<T> PartialOrd for Entry<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Entry<T> {
+    // BinaryHeap is a max-heap, so the earliest entry is the greatest.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.at.cmp(&self.at).then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl<T> Scheduler<T> {
+    pub fn new(seed: u64) -> Self {
+        let mut bytes = [0u8; 16];
+        bytes[..8].copy_from_slice(&seed.to_le_bytes());
+        // The xorshift generator needs a seed that is not all zeros.
+        bytes[8..].copy_from_slice(&(!seed).to_le_bytes());
+        Self {
+            now: Duration::from_secs(0),
+            next_seq: 0,
+            queue: BinaryHeap::new(),
+            rng: XorShiftRng::from_seed(bytes),
+        }
+    }
+
+    /// The time elapsed on the virtual clock since the scheduler was created.
+    pub fn now(&self) -> Duration {
+        self.now
+    }
+
+    /// The random number generator for the decisions that must be replayed with the same seed.
+    pub fn rng(&mut self) -> &mut XorShiftRng {
+        &mut self.rng
+    }
+
+    pub fn schedule_after(&mut self, after: Duration, event: T) {
+        let at = self.now + after;
+        self.schedule_at(at, event);
+    }
+
+    /// Schedules the event at the given time. An event in the past is scheduled now.
+    pub fn schedule_at(&mut self, at: Duration, event: T) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.queue.push(Entry {
+            at: at.max(self.now),
+            seq,
+            event,
+        });
+    }
+
+    /// The time of the next event, if any.
+    pub fn next_time(&self) -> Option<Duration> {
+        self.queue.peek().map(|entry| entry.at)
+    }
+
+    /// Pops the next event and moves the clock to its time.
+    pub fn pop(&mut self) -> Option<T> {
+        let entry = self.queue.pop()?;
+        self.now = entry.at;
+        Some(entry.event)
+    }
+
+    /// Pops the next event if it's due by `until`.
+    pub fn pop_until(&mut self, until: Duration) -> Option<T> {
+        match self.next_time() {
+            Some(at) if at <= until => self.pop(),
+            _ => None,
+        }
+    }
+
+    /// Moves the clock forward without popping anything. It never goes back.
+    pub fn advance_to(&mut self, at: Duration) {
+        self.now = self.now.max(at);
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn events_are_popped_in_time_order() {
+        let mut scheduler = Scheduler::new(0);
+        scheduler.schedule_after(Duration::from_millis(30), "c");
+        scheduler.schedule_after(Duration::from_millis(10), "a");
+        scheduler.schedule_after(Duration::from_millis(20), "b");
+
+        assert_eq!(scheduler.pop(), Some("a"));
+        assert_eq!(scheduler.now(), Duration::from_millis(10));
+        assert_eq!(scheduler.pop(), Some("b"));
+        assert_eq!(scheduler.pop(), Some("c"));
+        assert_eq!(scheduler.now(), Duration::from_millis(30));
+        assert_eq!(scheduler.pop(), None);
+    }
+
+    #[test]
+    fn events_of_the_same_time_keep_their_order() {
+        let mut scheduler = Scheduler::new(0);
+        for event in 0..100 {
+            scheduler.schedule_after(Duration::from_millis(5), event);
+        }
+        let popped: Vec<_> = std::iter::from_fn(|| scheduler.pop()).collect();
+        assert_eq!(popped, (0..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn events_after_the_limit_stay() {
+        let mut scheduler = Scheduler::new(0);
+        scheduler.schedule_after(Duration::from_millis(10), 1);
+        scheduler.schedule_after(Duration::from_millis(20), 2);
+
+        assert_eq!(scheduler.pop_until(Duration::from_millis(15)), Some(1));
+        assert_eq!(scheduler.pop_until(Duration::from_millis(15)), None);
+        assert_eq!(scheduler.len(), 1);
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_numbers() {
+        let mut a = Scheduler::<()>::new(42);
+        let mut b = Scheduler::<()>::new(42);
+        let from_a: Vec<u32> = (0..10).map(|_| a.rng().gen()).collect();
+        let from_b: Vec<u32> = (0..10).map(|_| b.rng().gen()).collect();
+        assert_eq!(from_a, from_b);
+    }
+}
@@ -0,0 +1,51 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+extern crate codechain_test_kit as test_kit;
+
+use std::time::Duration;
+use test_kit::{SimNetwork, TestNode};
+
+const LIMIT: Duration = Duration::from_secs(60);
+
+fn start(size: usize) -> (SimNetwork, Vec<TestNode>) {
+    let mut network = SimNetwork::new(size, 0);
+    let nodes = (0..size).map(|index| TestNode::solo(&mut network, index).unwrap()).collect();
+    (network, nodes)
+}
+
+#[test]
+fn a_node_catches_up_with_its_peer() {
+    let (mut network, nodes) = start(2);
+    nodes[0].mine(3);
+
+    assert!(network.run_until(LIMIT, |_| nodes[1].best_block_hash() == nodes[0].best_block_hash()));
+    assert_eq!(nodes[1].best_block_number(), 3);
+}
+
+#[test]
+fn a_partitioned_node_catches_up_after_healing() {
+    let (mut network, nodes) = start(3);
+    network.partition(&[&[0, 1], &[2]]);
+    nodes[0].mine(2);
+
+    assert!(network.run_until(LIMIT, |_| nodes[1].best_block_number() == 2));
+    network.run_for(LIMIT);
+    assert_eq!(nodes[2].best_block_number(), 0);
+
+    network.heal();
+    assert!(network.run_until(LIMIT, |_| nodes[2].best_block_hash() == nodes[0].best_block_hash()));
+}
@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::state_machine::GetAccount;
+use super::state_machine::{GetAccount, GetBlockNumber};
 use super::types::*;
 use super::{ServiceHandler, StateMachine};
 use crate::common::*;
@@ -37,6 +37,18 @@ impl GraphQlRoot {
             })
             .ok()
     }
+
+    /// The part of the balance not locked by the vesting schedule at the executing block.
+    async fn spendable_balance(&self, public: GqlPublic) -> Option<u64> {
+        let account = self
+            .state_machine
+            .execute_access(GetAccount {
+                public: &public.0,
+                default: false,
+            })
+            .ok()?;
+        Some(account.spendable_at(self.state_machine.execute_access(GetBlockNumber)))
+    }
 }
 
 #[async_graphql::Object]
@@ -44,6 +56,33 @@ impl Account {
     async fn seq(&self) -> TxSeq {
         self.seq
     }
+
+    async fn balance(&self) -> u64 {
+        self.balance
+    }
+
+    async fn vesting(&self) -> Option<VestingSchedule> {
+        self.vesting
+    }
+}
+
+#[async_graphql::Object]
+impl VestingSchedule {
+    async fn amount(&self) -> u64 {
+        self.amount
+    }
+
+    async fn start(&self) -> u64 {
+        self.start
+    }
+
+    async fn cliff(&self) -> u64 {
+        self.cliff
+    }
+
+    async fn end(&self) -> u64 {
+        self.end
+    }
 }
 
 pub struct GraphQlRequestHandler {
@@ -25,29 +25,36 @@ use super::common::StateManager;
 use coordinator::module::{SessionId, Stateful};
 pub use module::Module;
 use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use state_machine::GetConfig;
 use std::sync::Arc;
 
 /// A configuration that defines the behavior of the state machine.
+/// The governance can replace it through the `update-config` service.
+#[derive(Clone, Debug, Serialize, Deserialize)]
 struct Config {
     allow_hello: bool,
 }
 
 struct ServiceHandler {
-    config: Config,
+    /// The config used until the governance updates it.
+    init_config: Config,
 
     state_manager: Arc<RwLock<StateManager>>,
 }
 
 impl ServiceHandler {
-    fn new(config: Config) -> Self {
+    fn new(init_config: Config) -> Self {
         Self {
-            config,
+            init_config,
             state_manager: Arc::new(RwLock::new(StateManager::default())),
         }
     }
 
-    fn config(&self) -> &Config {
-        &self.config
+    fn config(&self, session: SessionId) -> Config {
+        self.create_state_machine(session).execute_access(GetConfig {
+            init_config: &self.init_config,
+        })
     }
 
     fn create_state_machine(&self, session: SessionId) -> StateMachine {
@@ -62,4 +69,4 @@ impl ServiceHandler {
 impl remote_trait_object::Service for ServiceHandler {}
 
 pub use types::Error;
-pub use types::TxHello;
+pub use types::{Account, GenesisAllocation, MultisigAccount, TxHello, TxMultisig, TxPay, VestingSchedule};
@@ -50,6 +50,10 @@ impl UserModule for Module {
                 assert_empty_arg(ctor_arg).unwrap();
                 Skeleton::new(Arc::clone(&self.service_handler) as Arc<dyn super::services::AccountManager>)
             }
+            "init-genesis" => {
+                assert_empty_arg(ctor_arg).unwrap();
+                Skeleton::new(Arc::clone(&self.service_handler) as Arc<dyn InitGenesis>)
+            }
             "stateful" => {
                 assert_empty_arg(ctor_arg).unwrap();
                 Skeleton::new(self.service_handler.get_stateful())
@@ -58,6 +62,10 @@ impl UserModule for Module {
                 assert_empty_arg(ctor_arg).unwrap();
                 Skeleton::new(Box::new(super::types::GetAccountAndSeq) as Box<dyn crate::sorting::GetAccountAndSeq>)
             }
+            "update-config" => {
+                assert_empty_arg(ctor_arg).unwrap();
+                Skeleton::new(Arc::clone(&self.service_handler) as Arc<dyn UpdateConfig>)
+            }
             "handle-graphql-request" => {
                 assert_empty_arg(ctor_arg).unwrap();
                 Skeleton::new(Box::new(GraphQlRequestHandler::new(Arc::clone(&self.service_handler)))
@@ -17,11 +17,13 @@
 use super::state_machine::*;
 use super::types::*;
 use super::ServiceHandler;
+use crate::common::witness_size;
 pub use ckey::{Ed25519Private as Private, Ed25519Public as Public};
 use coordinator::module::*;
 use coordinator::types::*;
 use coordinator::{Header, Transaction};
 use remote_trait_object::{service, Service};
+use std::collections::HashMap;
 
 #[service]
 pub trait AccountManager: Service {
@@ -31,6 +33,11 @@ pub trait AccountManager: Service {
     // Mutable accesses
     fn create_account(&self, session: SessionId, public: &Public) -> Result<(), Error>;
     fn increase_sequence(&self, session: SessionId, public: &Public, default: bool) -> Result<(), Error>;
+    fn add_balance(&self, session: SessionId, public: &Public, quantity: u64) -> Result<(), Error>;
+    /// Subtracts only from the part of the balance unlocked at the executing block, e.g. to pay a fee.
+    fn sub_balance(&self, session: SessionId, public: &Public, quantity: u64) -> Result<(), Error>;
+
+    fn get_multisig_account(&self, session: SessionId, id: &Public) -> Result<MultisigAccount, Error>;
 }
 
 impl AccountManager for ServiceHandler {
@@ -54,13 +61,74 @@ impl AccountManager for ServiceHandler {
         state_machine.execute_transition(IncreaseSequence {
             public,
             default,
-            config: self.config(),
+            config: &self.config(session),
+        })
+    }
+
+    fn add_balance(&self, session: SessionId, public: &Public, quantity: u64) -> Result<(), Error> {
+        let state_machine = self.create_state_machine(session);
+        state_machine.execute_transition(AddBalance {
+            public,
+            quantity,
+        })
+    }
+
+    fn sub_balance(&self, session: SessionId, public: &Public, quantity: u64) -> Result<(), Error> {
+        let state_machine = self.create_state_machine(session);
+        state_machine.execute_transition(SubBalance {
+            public,
+            quantity,
+        })
+    }
+
+    fn get_multisig_account(&self, session: SessionId, id: &Public) -> Result<MultisigAccount, Error> {
+        let state_machine = self.create_state_machine(session);
+        state_machine.execute_access(GetMultisigAccount {
+            id,
         })
     }
 }
 
+impl UpdateConfig for ServiceHandler {
+    fn update_config(&self, session: SessionId, config: &[u8]) -> Result<(), String> {
+        let config = serde_cbor::from_slice(config).map_err(|err| format!("Invalid config: {}", err))?;
+        let state_machine = self.create_state_machine(session);
+        state_machine.execute_transition(SetConfig {
+            config,
+        });
+        Ok(())
+    }
+}
+
+impl InitGenesis for ServiceHandler {
+    fn init_genesis(&self, session: SessionId, config: &[u8]) {
+        let allocations: HashMap<Public, GenesisAllocation> = serde_cbor::from_slice(&config).unwrap();
+        let state_machine = self.create_state_machine(session);
+        for (public, allocation) in allocations {
+            state_machine
+                .execute_transition(AddBalance {
+                    public: &public,
+                    quantity: allocation.balance,
+                })
+                .unwrap();
+            if let Some(vesting) = allocation.vesting {
+                state_machine
+                    .execute_transition(SetVesting {
+                        public: &public,
+                        vesting,
+                    })
+                    .unwrap();
+            }
+        }
+    }
+}
+
 impl TxOwner for ServiceHandler {
-    fn block_opened(&self, _: SessionId, _: &Header) -> Result<(), HeaderError> {
+    fn block_opened(&self, session: SessionId, header: &Header) -> Result<(), HeaderError> {
+        let state_machine = self.create_state_machine(session);
+        state_machine.execute_transition(SetBlockNumber {
+            block_number: header.number(),
+        });
         Ok(())
     }
 
@@ -68,7 +136,7 @@ impl TxOwner for ServiceHandler {
         let state_machine = self.create_state_machine(session);
         if let Err(error) = state_machine.execute_transition(ExecuteTransaction {
             tx: transaction,
-            config: self.config(),
+            config: &self.config(session),
         }) {
             match error {
                 ExecuteError::InvalidMetadata => Err(()),
@@ -77,6 +145,8 @@ impl TxOwner for ServiceHandler {
                 ExecuteError::AccountError(_) => Err(()),
                 ExecuteError::InvalidSequence => Err(()),
                 ExecuteError::NotAllowedHello => Err(()),
+                ExecuteError::InvalidMultisig => Err(()),
+                ExecuteError::NotEnoughSignatures => Err(()),
             }
         } else {
             Ok(Default::default())
@@ -85,12 +155,39 @@ impl TxOwner for ServiceHandler {
 
     fn check_transaction(&self, transaction: &Transaction) -> Result<(), coordinator::types::ErrorCode> {
         let todo_fixthis: coordinator::types::ErrorCode = 3;
-        assert_eq!(transaction.tx_type(), "account");
-        let tx: OwnTransaction = serde_cbor::from_slice(&transaction.body()).map_err(|_| todo_fixthis)?;
-        tx.verify().map_err(|_| todo_fixthis)?;
+        match transaction.tx_type() {
+            "account" => {
+                let tx: OwnTransaction = serde_cbor::from_slice(&transaction.body()).map_err(|_| todo_fixthis)?;
+                tx.verify().map_err(|_| todo_fixthis)?;
+            }
+            "multisig" => {
+                let tx: MultisigTransaction = serde_cbor::from_slice(&transaction.body()).map_err(|_| todo_fixthis)?;
+                tx.verify().map_err(|_| todo_fixthis)?;
+            }
+            "pay" => {
+                let tx: PayTransaction = serde_cbor::from_slice(&transaction.body()).map_err(|_| todo_fixthis)?;
+                tx.verify().map_err(|_| todo_fixthis)?;
+            }
+            tx_type => panic!("Unexpected transaction type: {}", tx_type),
+        }
         Ok(())
     }
 
+    fn estimate_gas(&self, transaction: &Transaction) -> u64 {
+        transaction.size() as u64
+    }
+
+    fn witness_size(&self, transaction: &Transaction) -> usize {
+        let body = transaction.body();
+        let signed = match transaction.tx_type() {
+            "account" => serde_cbor::from_slice::<OwnTransaction>(body).ok().map(|tx| witness_size(body, &tx.tx)),
+            "multisig" => serde_cbor::from_slice::<MultisigTransaction>(body).ok().map(|tx| witness_size(body, &tx.tx)),
+            "pay" => serde_cbor::from_slice::<PayTransaction>(body).ok().map(|tx| witness_size(body, &tx.tx)),
+            _ => None,
+        };
+        signed.unwrap_or(0)
+    }
+
     fn block_closed(&self, _session: SessionId) -> Result<Vec<Event>, CloseBlockError> {
         Ok(Vec::new())
     }
@@ -17,9 +17,63 @@
 use super::types::*;
 use super::Config;
 use crate::common::state_machine::{StateAccess, StateTransition};
+use crate::common::{StateCache, TxSeq};
 pub use ckey::{Ed25519Private as Private, Ed25519Public as Public};
-use coordinator::context::SubStorageAccess;
 use coordinator::Transaction;
+use std::collections::BTreeSet;
+
+const CONFIG_KEY: &[u8] = b"Config";
+const BLOCK_NUMBER_KEY: &[u8] = b"BlockNumber";
+
+pub struct GetConfig<'a> {
+    pub(super) init_config: &'a Config,
+}
+
+impl<'a> StateAccess for GetConfig<'a> {
+    type Outcome = Config;
+
+    fn execute(self, state: &StateCache) -> Config {
+        match state.get_decoded(CONFIG_KEY) {
+            Some(config) => config.expect("The config is written by SetConfig"),
+            None => self.init_config.clone(),
+        }
+    }
+}
+
+pub struct SetConfig {
+    pub(super) config: Config,
+}
+
+impl StateTransition for SetConfig {
+    type Outcome = ();
+
+    fn execute(self, state: &mut StateCache) {
+        state.set_encoded(CONFIG_KEY, &self.config);
+    }
+}
+
+/// The number of the block being executed, by which the vesting schedules release the balances.
+pub struct GetBlockNumber;
+
+impl StateAccess for GetBlockNumber {
+    type Outcome = u64;
+
+    fn execute(self, state: &StateCache) -> u64 {
+        state.get_decoded(BLOCK_NUMBER_KEY).map_or(0, |number| number.expect("The number is written by SetBlockNumber"))
+    }
+}
+
+pub struct SetBlockNumber {
+    pub block_number: u64,
+}
+
+impl StateTransition for SetBlockNumber {
+    type Outcome = ();
+
+    fn execute(self, state: &mut StateCache) {
+        state.set_encoded(BLOCK_NUMBER_KEY, &self.block_number);
+    }
+}
 
 pub struct GetAccount<'a> {
     pub public: &'a Public,
@@ -29,18 +83,17 @@ pub struct GetAccount<'a> {
 impl<'a> StateAccess for GetAccount<'a> {
     type Outcome = Result<Account, Error>;
 
-    fn execute(self, state: &dyn SubStorageAccess) -> Result<Account, Error> {
-        let bytes = match state.get(self.public.as_ref()) {
-            Some(bytes) => bytes,
+    fn execute(self, state: &StateCache) -> Result<Account, Error> {
+        match state.get_decoded(self.public.as_ref()) {
+            Some(account) => account.map_err(|_| Error::InvalidKey),
             None => {
                 if self.default {
-                    return Ok(Default::default())
+                    Ok(Default::default())
                 } else {
-                    return Err(Error::NoSuchAccount)
+                    Err(Error::NoSuchAccount)
                 }
             }
-        };
-        Ok(serde_cbor::from_slice(&bytes).map_err(|_| Error::InvalidKey)?)
+        }
     }
 }
 
@@ -51,14 +104,12 @@ pub struct CreateAccount<'a> {
 impl<'a> StateTransition for CreateAccount<'a> {
     type Outcome = Result<(), Error>;
 
-    fn execute(self, state: &mut dyn SubStorageAccess) -> Result<(), Error> {
-        let account = Account {
-            seq: 0,
-        };
+    fn execute(self, state: &mut StateCache) -> Result<(), Error> {
+        let account = Account::default();
         if state.has(self.public.as_ref()) {
             return Err(Error::AccountExists)
         }
-        state.set(self.public.as_ref(), serde_cbor::to_vec(&account).unwrap());
+        state.set_encoded(self.public.as_ref(), &account);
         Ok(())
     }
 }
@@ -74,9 +125,9 @@ pub struct IncreaseSequence<'a> {
 impl<'a> StateTransition for IncreaseSequence<'a> {
     type Outcome = Result<(), Error>;
 
-    fn execute(self, state: &mut dyn SubStorageAccess) -> Result<(), Error> {
-        let option_bytes = state.get(self.public.as_ref());
-        if option_bytes.is_none() {
+    fn execute(self, state: &mut StateCache) -> Result<(), Error> {
+        let option_account = state.get_decoded::<Account>(self.public.as_ref());
+        if option_account.is_none() {
             if self.default {
                 CreateAccount {
                     public: self.public,
@@ -89,14 +140,110 @@ impl<'a> StateTransition for IncreaseSequence<'a> {
                 return Err(Error::NoSuchAccount)
             }
         }
-        let bytes = option_bytes.unwrap();
-        let mut account: Account = serde_cbor::from_slice(&bytes).map_err(|_| Error::InvalidKey)?;
+        let mut account = option_account.unwrap().map_err(|_| Error::InvalidKey)?;
         account.seq += 1;
-        state.set(self.public.as_ref(), serde_cbor::to_vec(&account).unwrap());
+        state.set_encoded(self.public.as_ref(), &account);
+        Ok(())
+    }
+}
+
+pub struct AddBalance<'a> {
+    pub public: &'a Public,
+    pub quantity: u64,
+}
+
+impl<'a> StateTransition for AddBalance<'a> {
+    type Outcome = Result<(), Error>;
+
+    fn execute(self, state: &mut StateCache) -> Result<(), Error> {
+        let mut account = GetAccount {
+            public: self.public,
+            default: true,
+        }
+        .execute(state)?;
+        account.balance += self.quantity;
+        state.set_encoded(self.public.as_ref(), &account);
+        Ok(())
+    }
+}
+
+/// Only the part of the balance unlocked at the executing block can be subtracted.
+pub struct SubBalance<'a> {
+    pub public: &'a Public,
+    pub quantity: u64,
+}
+
+impl<'a> StateTransition for SubBalance<'a> {
+    type Outcome = Result<(), Error>;
+
+    fn execute(self, state: &mut StateCache) -> Result<(), Error> {
+        let mut account = GetAccount {
+            public: self.public,
+            default: false,
+        }
+        .execute(state)?;
+        if account.balance < self.quantity {
+            return Err(Error::InsufficientBalance)
+        }
+        if account.spendable_at(GetBlockNumber.execute(state)) < self.quantity {
+            return Err(Error::LockedBalance)
+        }
+        account.balance -= self.quantity;
+        state.set_encoded(self.public.as_ref(), &account);
         Ok(())
     }
 }
 
+/// Locks a part of the balance under the schedule, replacing a schedule that released everything.
+pub struct SetVesting<'a> {
+    pub public: &'a Public,
+    pub vesting: VestingSchedule,
+}
+
+impl<'a> SetVesting<'a> {
+    fn check(&self, state: &StateCache) -> Result<Account, Error> {
+        if !self.vesting.is_valid() {
+            return Err(Error::InvalidVestingSchedule)
+        }
+        let account = GetAccount {
+            public: self.public,
+            default: true,
+        }
+        .execute(state)?;
+        let block_number = GetBlockNumber.execute(state);
+        if account.vesting.map_or(false, |current| current.locked_at(block_number) > 0) {
+            return Err(Error::VestingInProgress)
+        }
+        Ok(account)
+    }
+}
+
+impl<'a> StateTransition for SetVesting<'a> {
+    type Outcome = Result<(), Error>;
+
+    fn execute(self, state: &mut StateCache) -> Result<(), Error> {
+        let mut account = self.check(state)?;
+        account.vesting = Some(self.vesting);
+        state.set_encoded(self.public.as_ref(), &account);
+        Ok(())
+    }
+}
+
+pub struct GetMultisigAccount<'a> {
+    pub id: &'a Public,
+}
+
+impl<'a> StateAccess for GetMultisigAccount<'a> {
+    type Outcome = Result<MultisigAccount, Error>;
+
+    fn execute(self, state: &StateCache) -> Result<MultisigAccount, Error> {
+        state
+            .get_decoded(get_state_key_multisig(self.id).as_bytes())
+            .ok_or(Error::NoSuchMultisigAccount)?
+            .map_err(|_| Error::InvalidKey)
+    }
+}
+
 pub enum ExecuteError {
     InvalidMetadata,
     InvalidSign,
@@ -104,6 +251,8 @@ pub enum ExecuteError {
     InvalidSequence,
     AccountError(Error),
     NotAllowedHello,
+    InvalidMultisig,
+    NotEnoughSignatures,
 }
 
 pub struct ExecuteTransaction<'a> {
@@ -114,9 +263,24 @@ pub struct ExecuteTransaction<'a> {
 impl<'a> StateTransition for ExecuteTransaction<'a> {
     type Outcome = Result<(), ExecuteError>;
 
-    fn execute(self, state: &mut dyn SubStorageAccess) -> Result<(), ExecuteError> {
-        if self.tx.tx_type() != "account" {
-            return Err(ExecuteError::InvalidMetadata)
+    fn execute(self, state: &mut StateCache) -> Result<(), ExecuteError> {
+        match self.tx.tx_type() {
+            "account" => {}
+            "multisig" => {
+                return ExecuteMultisigTransaction {
+                    tx: self.tx,
+                    config: self.config,
+                }
+                .execute(state)
+            }
+            "pay" => {
+                return ExecutePayTransaction {
+                    tx: self.tx,
+                    config: self.config,
+                }
+                .execute(state)
+            }
+            _ => return Err(ExecuteError::InvalidMetadata),
         }
 
         let tx: OwnTransaction = serde_cbor::from_slice(&self.tx.body()).map_err(|_| ExecuteError::InvalidFormat)?;
@@ -145,3 +309,196 @@ impl<'a> StateTransition for ExecuteTransaction<'a> {
         Ok(())
     }
 }
+
+struct ExecuteMultisigTransaction<'a> {
+    tx: &'a Transaction,
+    config: &'a Config,
+}
+
+impl<'a> StateTransition for ExecuteMultisigTransaction<'a> {
+    type Outcome = Result<(), ExecuteError>;
+
+    fn execute(self, state: &mut StateCache) -> Result<(), ExecuteError> {
+        let tx: MultisigTransaction =
+            serde_cbor::from_slice(&self.tx.body()).map_err(|_| ExecuteError::InvalidFormat)?;
+        let signers = tx.verify().map_err(|_| ExecuteError::InvalidSign)?;
+        let id = tx.account();
+
+        let multisig = tx.tx.action.dispatch(
+            &MultisigActionExecutor {
+                id: &id,
+                signers: &signers,
+                seq: tx.tx.seq,
+                config: self.config,
+            },
+            &mut *state,
+        )?;
+
+        if !multisig.is_satisfied_by(&signers) {
+            return Err(ExecuteError::NotEnoughSignatures)
+        }
+        if (GetAccount {
+            public: &id,
+            default: false,
+        }
+        .execute(state)
+        .map_err(ExecuteError::AccountError)?
+        .seq)
+            != tx.tx.seq
+        {
+            return Err(ExecuteError::InvalidSequence)
+        }
+        IncreaseSequence {
+            public: &id,
+            default: false,
+            config: self.config,
+        }
+        .execute(state)
+        .unwrap();
+        Ok(())
+    }
+}
+
+/// Executes a multisig action and returns the multisig account it acts on.
+struct MultisigActionExecutor<'a> {
+    id: &'a Public,
+    signers: &'a BTreeSet<Public>,
+    seq: TxSeq,
+    config: &'a Config,
+}
+
+impl<'a> TxMultisigHandler for MultisigActionExecutor<'a> {
+    type Context = &'a mut StateCache;
+    type Output = Result<MultisigAccount, ExecuteError>;
+
+    fn create(&self, state: Self::Context, publics: BTreeSet<Public>, threshold: u8) -> Self::Output {
+        let multisig = MultisigAccount {
+            publics,
+            threshold,
+        };
+        if !multisig.is_valid() {
+            return Err(ExecuteError::InvalidMultisig)
+        }
+        if !multisig.publics.is_subset(self.signers) {
+            return Err(ExecuteError::NotEnoughSignatures)
+        }
+        if self.seq != 0 {
+            return Err(ExecuteError::InvalidSequence)
+        }
+        CreateAccount {
+            public: self.id,
+        }
+        .execute(state)
+        .map_err(ExecuteError::AccountError)?;
+        state.set_encoded(get_state_key_multisig(self.id).as_bytes(), &multisig);
+        Ok(multisig)
+    }
+
+    fn hello(&self, state: Self::Context, _account: Public) -> Self::Output {
+        if !self.config.allow_hello {
+            return Err(ExecuteError::NotAllowedHello)
+        }
+        GetMultisigAccount {
+            id: self.id,
+        }
+        .execute(state)
+        .map_err(ExecuteError::AccountError)
+    }
+}
+
+struct ExecutePayTransaction<'a> {
+    tx: &'a Transaction,
+    config: &'a Config,
+}
+
+impl<'a> StateTransition for ExecutePayTransaction<'a> {
+    type Outcome = Result<(), ExecuteError>;
+
+    fn execute(self, state: &mut StateCache) -> Result<(), ExecuteError> {
+        let tx: PayTransaction = serde_cbor::from_slice(&self.tx.body()).map_err(|_| ExecuteError::InvalidFormat)?;
+        tx.verify().map_err(|_| ExecuteError::InvalidSign)?;
+        if (GetAccount {
+            public: &tx.signer_public,
+            default: false,
+        }
+        .execute(state)
+        .map_err(ExecuteError::AccountError)?
+        .seq)
+            != tx.tx.seq
+        {
+            return Err(ExecuteError::InvalidSequence)
+        }
+        tx.tx
+            .action
+            .dispatch(
+                &PayActionExecutor {
+                    sender: &tx.signer_public,
+                },
+                &mut *state,
+            )
+            .map_err(ExecuteError::AccountError)?;
+        IncreaseSequence {
+            public: &tx.signer_public,
+            default: false,
+            config: self.config,
+        }
+        .execute(state)
+        .unwrap();
+        Ok(())
+    }
+}
+
+/// Moves the quantity from the sender to the receiver, checking everything before changing the state.
+struct PayActionExecutor<'a> {
+    sender: &'a Public,
+}
+
+impl<'a> TxPayHandler for PayActionExecutor<'a> {
+    type Context = &'a mut StateCache;
+    type Output = Result<(), Error>;
+
+    fn pay(&self, state: Self::Context, receiver: Public, quantity: u64) -> Self::Output {
+        SubBalance {
+            public: self.sender,
+            quantity,
+        }
+        .execute(state)?;
+        AddBalance {
+            public: &receiver,
+            quantity,
+        }
+        .execute(state)
+    }
+
+    fn pay_vested(
+        &self,
+        state: Self::Context,
+        receiver: Public,
+        quantity: u64,
+        start: u64,
+        cliff: u64,
+        end: u64,
+    ) -> Self::Output {
+        let set_vesting = SetVesting {
+            public: &receiver,
+            vesting: VestingSchedule {
+                amount: quantity,
+                start,
+                cliff,
+                end,
+            },
+        };
+        set_vesting.check(state)?;
+        SubBalance {
+            public: self.sender,
+            quantity,
+        }
+        .execute(state)?;
+        set_vesting.execute(state)?;
+        AddBalance {
+            public: &receiver,
+            quantity,
+        }
+        .execute(state)
+    }
+}
@@ -15,14 +15,72 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::common::*;
+use ccrypto::blake256;
 pub use ckey::{Ed25519Private as Private, Ed25519Public as Public};
 use coordinator::Transaction;
+use primitives::H256;
 use remote_trait_object::Service;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct Account {
     pub seq: TxSeq,
+    #[serde(default)]
+    pub balance: u64,
+    /// Left out of the encoding when absent, so the accounts without a lockup keep their encodings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vesting: Option<VestingSchedule>,
+}
+
+impl Account {
+    /// The part of the balance that can be transferred or paid as fees at the block.
+    pub fn spendable_at(&self, block_number: u64) -> u64 {
+        let locked = self.vesting.map_or(0, |vesting| vesting.locked_at(block_number));
+        self.balance.saturating_sub(locked)
+    }
+}
+
+/// A lockup of a part of the balance. Nothing is released before the cliff,
+/// and then the amount is released linearly from the start to the end.
+/// A schedule whose cliff is its end releases everything at once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VestingSchedule {
+    /// The amount locked at the start.
+    pub amount: u64,
+    /// Block numbers.
+    pub start: u64,
+    pub cliff: u64,
+    pub end: u64,
+}
+
+impl VestingSchedule {
+    pub fn is_valid(&self) -> bool {
+        self.start <= self.cliff && self.cliff <= self.end
+    }
+
+    pub fn unlocked_at(&self, block_number: u64) -> u64 {
+        if block_number < self.cliff {
+            0
+        } else if block_number >= self.end {
+            self.amount
+        } else {
+            let elapsed = u128::from(block_number - self.start);
+            (u128::from(self.amount) * elapsed / u128::from(self.end - self.start)) as u64
+        }
+    }
+
+    pub fn locked_at(&self, block_number: u64) -> u64 {
+        self.amount - self.unlocked_at(block_number)
+    }
+}
+
+/// The balance an account starts with at the genesis, locked under the schedule if there is one.
+/// The genesis config maps the public key of each account to one of these.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GenesisAllocation {
+    pub balance: u64,
+    pub vesting: Option<VestingSchedule>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -30,6 +88,14 @@ pub enum Error {
     NoSuchAccount,
     AccountExists,
     InvalidKey,
+    NoSuchMultisigAccount,
+    InsufficientBalance,
+    /// The balance is enough, but a part of it is still locked.
+    LockedBalance,
+    /// The cliff of the schedule is not between its start and end.
+    InvalidVestingSchedule,
+    /// The account already has a schedule locking a part of its balance.
+    VestingInProgress,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -37,12 +103,113 @@ pub struct TxHello;
 impl Action for TxHello {}
 pub type OwnTransaction = crate::common::SignedTransaction<TxHello>;
 
+/// An M-of-N account. Its sequence is kept in an `Account` under its id, like other accounts.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MultisigAccount {
+    pub publics: BTreeSet<Public>,
+    pub threshold: u8,
+}
+
+impl MultisigAccount {
+    /// The id of a multisig account is derived from its keys and threshold,
+    /// and is used in place of a public key to identify the account.
+    pub fn id(&self) -> Public {
+        let hash = blake256(&{
+            let mut v = serde_cbor::to_vec(&(&self.publics, self.threshold)).unwrap();
+            v.extend_from_slice(b"Account-Module-Multisig-Id");
+            v
+        } as &[u8]);
+        Public::from_slice(hash.as_ref()).expect("H256 has length 32")
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.threshold > 0 && self.threshold as usize <= self.publics.len()
+    }
+
+    pub fn is_satisfied_by(&self, signers: &BTreeSet<Public>) -> bool {
+        signers.intersection(&self.publics).count() >= self.threshold as usize
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ActionEnum)]
+pub enum TxMultisig {
+    /// Creates an M-of-N account. Every key in `publics` must sign the transaction.
+    Create {
+        publics: BTreeSet<Public>,
+        threshold: u8,
+    },
+    /// Says hello on behalf of the multisig `account`. At least `threshold` of its keys must sign.
+    Hello {
+        account: Public,
+    },
+}
+impl Action for TxMultisig {}
+pub type MultisigTransaction = crate::common::MultiSignedTransaction<TxMultisig>;
+
+#[derive(Serialize, Deserialize, Clone, Debug, ActionEnum)]
+pub enum TxPay {
+    Pay {
+        receiver: Public,
+        quantity: u64,
+    },
+    /// Pays the quantity locked under a vesting schedule, e.g. for a token sale.
+    /// The receiver must not have a schedule still locking its balance.
+    PayVested {
+        receiver: Public,
+        quantity: u64,
+        start: u64,
+        cliff: u64,
+        end: u64,
+    },
+}
+impl Action for TxPay {}
+pub type PayTransaction = crate::common::SignedTransaction<TxPay>;
+
+impl MultisigTransaction {
+    /// Returns the id of the multisig account whose sequence this transaction uses.
+    pub fn account(&self) -> Public {
+        match &self.tx.action {
+            TxMultisig::Create {
+                publics,
+                threshold,
+            } => MultisigAccount {
+                publics: publics.clone(),
+                threshold: *threshold,
+            }
+            .id(),
+            TxMultisig::Hello {
+                account,
+            } => *account,
+        }
+    }
+}
+
 pub struct GetAccountAndSeq;
 impl Service for GetAccountAndSeq {}
 impl crate::sorting::GetAccountAndSeq for GetAccountAndSeq {
     fn get_account_and_seq(&self, tx: &Transaction) -> Result<(Public, TxSeq), ()> {
-        assert_eq!(tx.tx_type(), "account");
-        let tx: OwnTransaction = serde_cbor::from_slice(&tx.body()).map_err(|_| ())?;
-        Ok((tx.signer_public, tx.tx.seq))
+        match tx.tx_type() {
+            "account" => {
+                let tx: OwnTransaction = serde_cbor::from_slice(&tx.body()).map_err(|_| ())?;
+                Ok((tx.signer_public, tx.tx.seq))
+            }
+            "multisig" => {
+                let tx: MultisigTransaction = serde_cbor::from_slice(&tx.body()).map_err(|_| ())?;
+                Ok((tx.account(), tx.tx.seq))
+            }
+            "pay" => {
+                let tx: PayTransaction = serde_cbor::from_slice(&tx.body()).map_err(|_| ())?;
+                Ok((tx.signer_public, tx.tx.seq))
+            }
+            tx_type => panic!("Unexpected transaction type: {}", tx_type),
+        }
     }
 }
+
+pub fn get_state_key_multisig(id: &Public) -> H256 {
+    blake256(&{
+        let mut v = serde_cbor::to_vec(&id).unwrap();
+        v.extend_from_slice(b"Account-Module-Multisig");
+        v
+    } as &[u8])
+}
@@ -14,15 +14,20 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+mod state_cache;
 pub mod state_machine;
 mod state_manager;
 
-use async_graphql::{InputValueError, InputValueResult, Scalar, ScalarType, Value as GqlValue};
 use ccrypto::blake256;
 use ckey::{verify, Ed25519Public as Public, Signature};
+use coordinator::types::InspectedPayload;
+pub use module_macros::{foundry_module, ActionEnum, HexScalar};
 use primitives::H256;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+pub use state_cache::{CacheStats, StateCache};
 pub use state_manager::StateManager;
+use std::collections::BTreeSet;
 use std::fmt;
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
@@ -72,6 +77,28 @@ impl<T: Action> SignedTransaction<T> {
     }
 }
 
+/// A transaction signed by several keys, used by multi-signature accounts.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MultiSignedTransaction<T: Action> {
+    pub signatures: Vec<(Public, Signature)>,
+    pub tx: UserTransaction<T>,
+}
+
+impl<T: Action> MultiSignedTransaction<T> {
+    /// Verifies every signature and returns the signers.
+    /// It fails if any signature is invalid or if a key signed more than once.
+    pub fn verify(&self) -> Result<BTreeSet<Public>, ()> {
+        let message = self.tx.hash();
+        let mut signers = BTreeSet::new();
+        for (public, signature) in self.signatures.iter() {
+            if !verify(signature, message.as_bytes(), public) || !signers.insert(*public) {
+                return Err(())
+            }
+        }
+        Ok(signers)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct UserTransaction<T: Action> {
     pub seq: TxSeq,
@@ -86,43 +113,50 @@ impl<T: Action> UserTransaction<T> {
     }
 }
 
-pub struct GqlPublic(pub Public);
+/// Counts the bytes of the encoded transaction that are not the signed `UserTransaction`,
+/// i.e. the signatures, the keys and the encoding around them.
+pub fn witness_size<T: Action>(body: &[u8], tx: &UserTransaction<T>) -> usize {
+    serde_cbor::to_vec(tx).map_or(0, |tx| body.len().saturating_sub(tx.len()))
+}
 
-#[Scalar]
-impl ScalarType for GqlPublic {
-    fn parse(value: GqlValue) -> InputValueResult<Self> {
-        if let GqlValue::String(s) = value {
-            Ok(GqlPublic(
-                Public::from_slice(
-                    &hex::decode(&s).map_err(|_| InputValueError::Custom("Invalid public key".to_owned()))?,
-                )
-                .ok_or_else(|| InputValueError::Custom("Invalid public key".to_owned()))?,
-            ))
-        } else {
-            Err(InputValueError::Custom("Invalid public key".to_owned()))
-        }
-    }
+#[derive(Serialize)]
+struct InspectedFields<'a, T: Action> {
+    signer: &'a Public,
+    seq: TxSeq,
+    action: &'a T,
+}
 
-    fn to_value(&self) -> GqlValue {
-        GqlValue::String(hex::encode(self.0.as_ref()))
-    }
+/// Decodes a signed transaction for the `TxInspector` of its type.
+/// The fields are its signer, its sequence and its action.
+pub fn inspect_signed_transaction<T: Action + DeserializeOwned>(
+    body: &[u8],
+    action_name: impl FnOnce(&T) -> &'static str,
+) -> Result<InspectedPayload, String> {
+    let tx: SignedTransaction<T> = serde_cbor::from_slice(body).map_err(|err| err.to_string())?;
+    let fields = InspectedFields {
+        signer: &tx.signer_public,
+        seq: tx.tx.seq,
+        action: &tx.tx.action,
+    };
+    Ok(InspectedPayload {
+        action: action_name(&tx.tx.action).to_owned(),
+        fields: async_graphql::serde_json::to_string(&fields).map_err(|err| err.to_string())?,
+    })
 }
 
-pub struct GqlH256(pub H256);
+#[derive(HexScalar)]
+#[hex_scalar(from = "Public::from_slice", error = "Invalid public key")]
+pub struct GqlPublic(pub Public);
 
-impl ScalarType for GqlH256 {
-    fn parse(value: GqlValue) -> InputValueResult<Self> {
-        if let GqlValue::String(s) = value {
-            Ok(GqlH256(H256::from_slice(
-                &hex::decode(&s).map_err(|_| InputValueError::Custom("Invalid public key".to_owned()))?,
-            )))
-        } else {
-            Err(InputValueError::Custom("Invalid public key".to_owned()))
-        }
-    }
+#[derive(HexScalar)]
+#[hex_scalar(from = "h256_from_slice", error = "Invalid hash")]
+pub struct GqlH256(pub H256);
 
-    fn to_value(&self) -> GqlValue {
-        GqlValue::String(hex::encode(self.0.as_ref()))
+fn h256_from_slice(bytes: &[u8]) -> Option<H256> {
+    if bytes.len() == 32 {
+        Some(H256::from_slice(bytes))
+    } else {
+        None
     }
 }
 
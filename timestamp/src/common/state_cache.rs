@@ -0,0 +1,259 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use coordinator::context::SubStorageAccess;
+use parking_lot::Mutex;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A value of the storage seen in the session.
+struct Entry {
+    /// `None` if the key doesn't exist.
+    bytes: Option<Vec<u8>>,
+    /// The value decoded from `bytes`, if someone has decoded it.
+    decoded: Option<Arc<dyn Any + Send + Sync>>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// The number of reads answered without calling the storage.
+    pub hits: u64,
+    /// The number of calls made to the storage.
+    pub storage_calls: u64,
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: HashMap<Vec<u8>, Entry>,
+    /// Whether the session has written since the entries were cleared.
+    dirty: bool,
+    stats: CacheStats,
+}
+
+/// The storage of a session, remembering the values read and written through it.
+///
+/// Reading a key that was read or written before in the session doesn't call the storage service,
+/// and reading it with `get_decoded` doesn't decode the CBOR again. Writes go through to the storage right away.
+///
+/// The coordinator may revert the storage to a checkpoint between the calls into the module, without the module knowing.
+/// So `invalidate` must be called whenever the module is entered, and it drops the remembered values once the session has written.
+pub struct StateCache {
+    storage: Box<dyn SubStorageAccess>,
+    inner: Mutex<Inner>,
+}
+
+impl StateCache {
+    pub fn new(storage: Box<dyn SubStorageAccess>) -> Self {
+        Self {
+            storage,
+            inner: Default::default(),
+        }
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let mut inner = self.inner.lock();
+        self.read(&mut inner, key)
+    }
+
+    pub fn has(&self, key: &[u8]) -> bool {
+        let mut inner = self.inner.lock();
+        if let Some(entry) = inner.entries.get(key) {
+            let exists = entry.bytes.is_some();
+            inner.stats.hits += 1;
+            return exists
+        }
+        inner.stats.storage_calls += 1;
+        self.storage.has(key)
+    }
+
+    pub fn set(&mut self, key: &[u8], value: Vec<u8>) {
+        self.storage.set(key, value.clone());
+        self.remember(key, Entry {
+            bytes: Some(value),
+            decoded: None,
+        });
+    }
+
+    pub fn remove(&mut self, key: &[u8]) {
+        self.storage.remove(key);
+        self.remember(key, Entry {
+            bytes: None,
+            decoded: None,
+        });
+    }
+
+    /// Reads the value of the key and decodes it, reusing the value decoded before in the session.
+    /// Returns `None` if the key doesn't exist.
+    pub fn get_decoded<T>(&self, key: &[u8]) -> Option<Result<T, serde_cbor::Error>>
+    where
+        T: DeserializeOwned + Clone + Send + Sync + 'static, {
+        let mut inner = self.inner.lock();
+        let inner = &mut *inner;
+        let decoded = inner.entries.get(key).and_then(|entry| entry.decoded.as_ref());
+        if let Some(value) = decoded.and_then(|decoded| decoded.downcast_ref::<T>()) {
+            let value = value.clone();
+            inner.stats.hits += 1;
+            return Some(Ok(value))
+        }
+
+        let bytes = self.read(inner, key)?;
+        let value: T = match serde_cbor::from_slice(&bytes) {
+            Ok(value) => value,
+            Err(err) => return Some(Err(err)),
+        };
+        if let Some(entry) = inner.entries.get_mut(key) {
+            entry.decoded = Some(Arc::new(value.clone()));
+        }
+        Some(Ok(value))
+    }
+
+    /// Encodes and writes the value, keeping it for the following `get_decoded` in the session.
+    pub fn set_encoded<T>(&mut self, key: &[u8], value: &T)
+    where
+        T: Serialize + Clone + Send + Sync + 'static, {
+        let bytes = serde_cbor::to_vec(value).unwrap();
+        self.storage.set(key, bytes.clone());
+        self.remember(key, Entry {
+            bytes: Some(bytes),
+            decoded: Some(Arc::new(value.clone())),
+        });
+    }
+
+    /// Forgets the remembered values if the session has written since they were last forgotten,
+    /// since the writes may have been reverted.
+    pub fn invalidate(&self) {
+        let mut inner = self.inner.lock();
+        if inner.dirty {
+            inner.entries.clear();
+            inner.dirty = false;
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.inner.lock().stats
+    }
+
+    fn read(&self, inner: &mut Inner, key: &[u8]) -> Option<Vec<u8>> {
+        if let Some(entry) = inner.entries.get(key) {
+            inner.stats.hits += 1;
+            return entry.bytes.clone()
+        }
+        inner.stats.storage_calls += 1;
+        let bytes = self.storage.get(key);
+        inner.entries.insert(key.to_vec(), Entry {
+            bytes: bytes.clone(),
+            decoded: None,
+        });
+        bytes
+    }
+
+    fn remember(&mut self, key: &[u8], entry: Entry) {
+        let inner = self.inner.get_mut();
+        inner.stats.storage_calls += 1;
+        inner.entries.insert(key.to_vec(), entry);
+        inner.dirty = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use remote_trait_object::Service;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct CountingStorage {
+        map: HashMap<Vec<u8>, Vec<u8>>,
+        reads: Arc<AtomicUsize>,
+    }
+
+    impl Service for CountingStorage {}
+
+    impl SubStorageAccess for CountingStorage {
+        fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            self.map.get(key).cloned()
+        }
+
+        fn set(&mut self, key: &[u8], value: Vec<u8>) {
+            self.map.insert(key.to_vec(), value);
+        }
+
+        fn has(&self, key: &[u8]) -> bool {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            self.map.contains_key(key)
+        }
+
+        fn remove(&mut self, key: &[u8]) {
+            self.map.remove(key);
+        }
+    }
+
+    fn cache_with(entries: &[(&[u8], u64)]) -> (StateCache, Arc<AtomicUsize>) {
+        let mut storage = CountingStorage::default();
+        for (key, value) in entries {
+            storage.map.insert(key.to_vec(), serde_cbor::to_vec(value).unwrap());
+        }
+        let reads = Arc::clone(&storage.reads);
+        (StateCache::new(Box::new(storage)), reads)
+    }
+
+    #[test]
+    fn repeated_reads_call_the_storage_once() {
+        let (cache, reads) = cache_with(&[(b"a", 7)]);
+        for _ in 0..3 {
+            assert_eq!(cache.get_decoded::<u64>(b"a").unwrap().unwrap(), 7);
+            assert!(cache.has(b"a"));
+            assert!(cache.get_decoded::<u64>(b"b").is_none());
+            assert!(!cache.has(b"b"));
+        }
+        assert_eq!(reads.load(Ordering::SeqCst), 2);
+        assert_eq!(cache.stats().storage_calls, 2);
+    }
+
+    #[test]
+    fn writes_go_through_and_are_read_back_without_storage_calls() {
+        let (mut cache, reads) = cache_with(&[]);
+        cache.set_encoded(b"a", &3u64);
+        assert_eq!(cache.get_decoded::<u64>(b"a").unwrap().unwrap(), 3);
+        cache.remove(b"a");
+        assert!(!cache.has(b"a"));
+        assert_eq!(reads.load(Ordering::SeqCst), 0);
+        assert_eq!(cache.storage.get(b"a"), None);
+
+        cache.set(b"a", serde_cbor::to_vec(&5u64).unwrap());
+        assert_eq!(cache.storage.get(b"a"), Some(serde_cbor::to_vec(&5u64).unwrap()));
+        assert_eq!(cache.get_decoded::<u64>(b"a").unwrap().unwrap(), 5);
+    }
+
+    #[test]
+    fn invalidate_forgets_values_only_after_a_write() {
+        let (mut cache, reads) = cache_with(&[(b"a", 7)]);
+        cache.get(b"a");
+        cache.invalidate();
+        cache.get(b"a");
+        assert_eq!(reads.load(Ordering::SeqCst), 1);
+
+        cache.set_encoded(b"b", &1u64);
+        cache.invalidate();
+        cache.get(b"a");
+        cache.get(b"b");
+        assert_eq!(reads.load(Ordering::SeqCst), 3);
+    }
+}
@@ -14,38 +14,42 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use coordinator::context::SubStorageAccess;
+use super::StateCache;
 use parking_lot::RwLock;
 use std::sync::Arc;
 
 pub trait StateAccess {
     type Outcome;
-    fn execute(self, state: &dyn SubStorageAccess) -> Self::Outcome;
+    fn execute(self, state: &StateCache) -> Self::Outcome;
 }
 
 pub trait StateTransition {
     type Outcome;
-    fn execute(self, state: &mut dyn SubStorageAccess) -> Self::Outcome;
+    fn execute(self, state: &mut StateCache) -> Self::Outcome;
 }
 
 /// A struct that defines the way of accessing the state and the way of making state transition.
 pub struct StateMachine {
     /// The state that this machine will act upon.
-    state: Arc<RwLock<dyn SubStorageAccess>>,
+    state: Arc<RwLock<StateCache>>,
 }
 
 impl StateMachine {
-    pub fn new(state: Arc<RwLock<dyn SubStorageAccess>>) -> Self {
+    pub fn new(state: Arc<RwLock<StateCache>>) -> Self {
         Self {
             state,
         }
     }
 
     pub fn execute_access<S: StateAccess>(&self, x: S) -> S::Outcome {
-        x.execute(&*(self.state.read()))
+        let state = self.state.read();
+        state.invalidate();
+        x.execute(&*state)
     }
 
     pub fn execute_transition<S: StateTransition>(&self, x: S) -> S::Outcome {
-        x.execute(&mut *(self.state.write()))
+        let mut state = self.state.write();
+        state.invalidate();
+        x.execute(&mut *state)
     }
 }
@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use super::StateCache;
 use coordinator::context::SubStorageAccess;
 use coordinator::module::{SessionId, Stateful};
 use parking_lot::RwLock;
@@ -23,7 +24,7 @@ use std::sync::Arc;
 
 #[derive(Default)]
 pub struct StateManager {
-    states: HashMap<SessionId, Arc<RwLock<dyn SubStorageAccess>>>,
+    states: HashMap<SessionId, Arc<RwLock<StateCache>>>,
 }
 
 impl Service for StateManager {}
@@ -31,7 +32,9 @@ impl Service for StateManager {}
 impl Stateful for StateManager {
     fn new_session(&mut self, session: SessionId, storage: ServiceRef<dyn SubStorageAccess>) {
         assert!(
-            self.states.insert(session, storage.unwrap_import().into_proxy()).is_none(),
+            self.states
+                .insert(session, Arc::new(RwLock::new(StateCache::new(storage.unwrap_import().into_proxy()))))
+                .is_none(),
             "invalid set_storage() requested from coordinator. This is a bug"
         )
     }
@@ -42,7 +45,7 @@ impl Stateful for StateManager {
 }
 
 impl StateManager {
-    pub fn get(&self, session: SessionId) -> Arc<RwLock<dyn SubStorageAccess>> {
+    pub fn get(&self, session: SessionId) -> Arc<RwLock<StateCache>> {
         Arc::clone(&self.states.get(&session).unwrap())
     }
 }
@@ -0,0 +1,144 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::state_machine::*;
+use super::types::*;
+use super::{ServiceHandler, StateMachine};
+use crate::common::*;
+use coordinator::module::*;
+use remote_trait_object::Service;
+use std::sync::Arc;
+
+/// The most orders returned for each side of a book.
+const MAX_DEPTH: u64 = 100;
+
+struct GraphQlRoot {
+    state_machine: StateMachine,
+}
+
+#[async_graphql::Object]
+impl GraphQlRoot {
+    /// An open order. Null once it is filled or cancelled.
+    async fn order(&self, id: u64) -> Option<Order> {
+        self.state_machine
+            .execute_access(GetOrder {
+                id,
+            })
+            .ok()
+    }
+
+    /// The best `depth` orders of each side of the market.
+    async fn order_book(&self, base: GqlH256, quote: GqlH256, depth: u64) -> Option<GqlOrderBook> {
+        let book = self
+            .state_machine
+            .execute_access(GetOrderBook {
+                market: &Market {
+                    base: base.0,
+                    quote: quote.0,
+                },
+            })
+            .ok()?;
+        let depth = depth.min(MAX_DEPTH) as usize;
+        let orders = |side: &[(u64, u64)]| -> Option<Vec<Order>> {
+            side.iter()
+                .take(depth)
+                .map(|(_, id)| {
+                    self.state_machine
+                        .execute_access(GetOrder {
+                            id: *id,
+                        })
+                        .ok()
+                })
+                .collect()
+        };
+        Some(GqlOrderBook {
+            bids: orders(&book.bids)?,
+            asks: orders(&book.asks)?,
+        })
+    }
+}
+
+struct GqlOrderBook {
+    bids: Vec<Order>,
+    asks: Vec<Order>,
+}
+
+#[async_graphql::Object]
+impl GqlOrderBook {
+    /// The buy orders, the highest price first.
+    async fn bids(&self) -> &Vec<Order> {
+        &self.bids
+    }
+
+    /// The sell orders, the lowest price first.
+    async fn asks(&self) -> &Vec<Order> {
+        &self.asks
+    }
+}
+
+#[async_graphql::Object]
+impl Order {
+    async fn id(&self) -> u64 {
+        self.id
+    }
+
+    async fn owner(&self) -> String {
+        hex::encode(self.owner.as_ref())
+    }
+
+    async fn buy(&self) -> bool {
+        self.side == Side::Buy
+    }
+
+    async fn price(&self) -> u64 {
+        self.price
+    }
+
+    async fn amount(&self) -> u64 {
+        self.amount
+    }
+}
+
+pub struct GraphQlRequestHandler {
+    service_handler: Arc<ServiceHandler>,
+
+    /// A runtime to process the asynchronous result of the query
+    tokio_runtime: tokio::runtime::Runtime,
+}
+
+impl GraphQlRequestHandler {
+    pub(super) fn new(service_handler: Arc<ServiceHandler>) -> Self {
+        Self {
+            service_handler,
+            tokio_runtime: tokio::runtime::Runtime::new().unwrap(),
+        }
+    }
+}
+
+impl Service for GraphQlRequestHandler {}
+
+impl HandleGraphQlRequest for GraphQlRequestHandler {
+    fn execute(&self, session: SessionId, query: &str, variables: &str) -> String {
+        handle_gql_query(
+            self.tokio_runtime.handle(),
+            GraphQlRoot {
+                state_machine: self.service_handler.create_state_machine(session),
+            },
+            query,
+            variables,
+        )
+    }
+}
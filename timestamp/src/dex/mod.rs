@@ -0,0 +1,65 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! An order book exchange of the tokens of the token module, kept as an example of modules composing services.
+//!
+//! The module imports `token-manager` to hold the tokens of open orders in escrow and to hand them over,
+//! and `account-manager` for the sequences. Its transactions are ordered by the sorting module,
+//! the `TxSorter` of the app, through the `get-account-and-seq` service it exports.
+//! Orders are matched only when a block is closed, so the trades don't depend on
+//! the order of the transactions in the block other than through the order ids.
+
+mod graphql;
+mod module;
+mod services;
+mod state_machine;
+mod types;
+
+use super::common::state_machine::StateMachine;
+use super::common::StateManager;
+use coordinator::module::{SessionId, Stateful};
+pub use module::Module;
+use parking_lot::RwLock;
+use remote_trait_object::raw_exchange::import_null_proxy;
+use std::sync::Arc;
+
+struct ServiceHandler {
+    state_manager: Arc<RwLock<StateManager>>,
+    account_manager: RwLock<Box<dyn crate::account::services::AccountManager>>,
+    token_manager: RwLock<Box<dyn crate::token::services::TokenManager>>,
+}
+
+impl ServiceHandler {
+    fn new() -> Self {
+        Self {
+            state_manager: Arc::new(RwLock::new(StateManager::default())),
+            account_manager: RwLock::new(import_null_proxy()),
+            token_manager: RwLock::new(import_null_proxy()),
+        }
+    }
+
+    fn create_state_machine(&self, session: SessionId) -> StateMachine {
+        StateMachine::new(self.state_manager.read().get(session))
+    }
+
+    fn get_stateful(&self) -> Arc<RwLock<dyn Stateful>> {
+        Arc::clone(&self.state_manager) as Arc<RwLock<dyn Stateful>>
+    }
+}
+
+impl remote_trait_object::Service for ServiceHandler {}
+
+pub use types::{Error, Market, Order, Side, Trade, TxDex, TRADE_EVENT};
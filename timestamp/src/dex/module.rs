@@ -0,0 +1,35 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::graphql::GraphQlRequestHandler;
+use super::types::{GetAccountAndSeq, InspectTransaction};
+use super::ServiceHandler;
+use crate::common::*;
+use coordinator::module::*;
+use std::sync::Arc;
+
+#[foundry_module(handler = ServiceHandler)]
+#[export_service("stateful", handler.get_stateful())]
+#[export_service("tx-owner", dyn TxOwner)]
+#[export_service("get-account-and-seq", Box::new(GetAccountAndSeq) as Box<dyn crate::sorting::GetAccountAndSeq>)]
+#[export_service("tx-inspector", Box::new(InspectTransaction) as Box<dyn TxInspector>)]
+#[export_service(
+    "handle-graphql-request",
+    Box::new(GraphQlRequestHandler::new(Arc::clone(handler))) as Box<dyn HandleGraphQlRequest>
+)]
+#[import_service("account-manager", account_manager)]
+#[import_service("token-manager", token_manager)]
+pub struct Module;
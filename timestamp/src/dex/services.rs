@@ -0,0 +1,198 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::state_machine::*;
+use super::types::*;
+use super::ServiceHandler;
+use crate::common::witness_size;
+use coordinator::module::*;
+use coordinator::types::*;
+use coordinator::{Header, Transaction};
+use primitives::H256;
+
+enum ExecuteError {
+    InvalidMetadata,
+    InvalidSign,
+    InvalidFormat,
+    AccountModuleError(crate::account::Error),
+    TokenModuleError(crate::token::Error),
+    DexError(Error),
+    InvalidSequence,
+    /// The signer doesn't have the tokens to put in escrow for the order.
+    InsufficientTokens,
+}
+
+impl ServiceHandler {
+    fn excute_tx(&self, session: SessionId, transaction: &Transaction) -> Result<(), ExecuteError> {
+        if transaction.tx_type() != "dex" {
+            return Err(ExecuteError::InvalidMetadata)
+        }
+
+        let tx: OwnTransaction =
+            serde_cbor::from_slice(&transaction.body()).map_err(|_| ExecuteError::InvalidFormat)?;
+        tx.verify().map_err(|_| ExecuteError::InvalidSign)?;
+        if self
+            .account_manager
+            .read()
+            .get_account(session, &tx.signer_public, true)
+            .map_err(ExecuteError::AccountModuleError)?
+            .seq
+            != tx.tx.seq
+        {
+            return Err(ExecuteError::InvalidSequence)
+        }
+
+        let state_machine = self.create_state_machine(session);
+        match &tx.tx.action {
+            TxDex::PlaceOrder {
+                market,
+                side,
+                price,
+                amount,
+            } => {
+                // The tokens are counted first so that nothing fails after the order is recorded.
+                let (issuer, count) =
+                    escrow(market, *side, *price, *amount).ok_or(ExecuteError::DexError(Error::InvalidOrder))?;
+                let owned = self
+                    .token_manager
+                    .read()
+                    .get_account(session, &tx.signer_public, true)
+                    .map_err(ExecuteError::TokenModuleError)?
+                    .tokens
+                    .iter()
+                    .filter(|token| token.issuer == issuer)
+                    .count();
+                if (owned as u64) < count {
+                    return Err(ExecuteError::InsufficientTokens)
+                }
+                state_machine
+                    .execute_transition(PlaceOrder {
+                        owner: &tx.signer_public,
+                        market,
+                        side: *side,
+                        price: *price,
+                        amount: *amount,
+                    })
+                    .map_err(ExecuteError::DexError)?;
+                let token_manager = self.token_manager.read();
+                for _ in 0..count {
+                    token_manager.take_token(session, &issuer, &tx.signer_public).unwrap();
+                }
+            }
+            TxDex::CancelOrder {
+                id,
+            } => {
+                let order = state_machine
+                    .execute_transition(CancelOrder {
+                        owner: &tx.signer_public,
+                        id: *id,
+                    })
+                    .map_err(ExecuteError::DexError)?;
+                let (issuer, count) = order.escrow().unwrap();
+                self.release(session, &issuer, &tx.signer_public, count).map_err(ExecuteError::TokenModuleError)?;
+            }
+        }
+        self.account_manager.read().increase_sequence(session, &tx.signer_public, true).unwrap();
+        Ok(())
+    }
+
+    /// Gives `count` tokens of the issuer held in escrow to the receiver.
+    fn release(
+        &self,
+        session: SessionId,
+        issuer: &H256,
+        receiver: &Public,
+        count: u64,
+    ) -> Result<(), crate::token::Error> {
+        let token_manager = self.token_manager.read();
+        for _ in 0..count {
+            token_manager.issue_token(session, issuer, receiver)?;
+        }
+        Ok(())
+    }
+
+    fn settle(&self, session: SessionId, trade: &Trade) -> Result<(), crate::token::Error> {
+        self.release(session, &trade.market.base, &trade.buyer, trade.amount)?;
+        self.release(session, &trade.market.quote, &trade.seller, trade.price * trade.amount)?;
+        self.release(session, &trade.market.quote, &trade.buyer, trade.refund)
+    }
+}
+
+impl TxOwner for ServiceHandler {
+    fn block_opened(&self, _session: SessionId, _: &Header) -> Result<(), HeaderError> {
+        Ok(())
+    }
+
+    fn execute_transaction(&self, session: SessionId, transaction: &Transaction) -> Result<TransactionOutcome, ()> {
+        if let Err(error) = self.excute_tx(session, transaction) {
+            match error {
+                ExecuteError::InvalidMetadata => Err(()),
+                ExecuteError::InvalidSign => Err(()),
+                ExecuteError::InvalidFormat => Err(()),
+                ExecuteError::AccountModuleError(_) => Err(()),
+                ExecuteError::TokenModuleError(_) => Err(()),
+                ExecuteError::DexError(_) => Err(()),
+                ExecuteError::InvalidSequence => Err(()),
+                ExecuteError::InsufficientTokens => Err(()),
+            }
+        } else {
+            Ok(Default::default())
+        }
+    }
+
+    fn check_transaction(&self, transaction: &Transaction) -> Result<(), coordinator::types::ErrorCode> {
+        let todo_fixthis: coordinator::types::ErrorCode = 3;
+        if transaction.tx_type() != "dex" {
+            return Err(todo_fixthis)
+        }
+        let tx: OwnTransaction = serde_cbor::from_slice(&transaction.body()).map_err(|_| todo_fixthis)?;
+        tx.verify().map_err(|_| todo_fixthis)?;
+        Ok(())
+    }
+
+    fn estimate_gas(&self, transaction: &Transaction) -> u64 {
+        transaction.size() as u64
+    }
+
+    fn witness_size(&self, transaction: &Transaction) -> usize {
+        match serde_cbor::from_slice::<OwnTransaction>(&transaction.body()) {
+            Ok(tx) => witness_size(&transaction.body(), &tx.tx),
+            Err(_) => 0,
+        }
+    }
+
+    /// Matches the orders of the markets with orders placed in the block, and reports the trades as events.
+    fn block_closed(&self, session: SessionId) -> Result<Vec<Event>, CloseBlockError> {
+        let state_machine = self.create_state_machine(session);
+        let markets = state_machine.execute_transition(TakeTouchedMarkets).map_err(|err| format!("{:?}", err))?;
+        let mut events = Vec::new();
+        for market in markets.iter() {
+            let trades = state_machine
+                .execute_transition(MatchOrders {
+                    market,
+                })
+                .map_err(|err| format!("{:?}", err))?;
+            for trade in trades {
+                self.settle(session, &trade).map_err(|err| format!("{:?}", err))?;
+                events.push(Event {
+                    key: TRADE_EVENT.to_owned(),
+                    value: serde_cbor::to_vec(&trade).unwrap(),
+                });
+            }
+        }
+        Ok(events)
+    }
+}
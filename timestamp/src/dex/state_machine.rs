@@ -0,0 +1,220 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::types::*;
+use crate::common::state_machine::{StateAccess, StateTransition};
+use crate::common::StateCache;
+
+pub struct GetOrder {
+    pub id: u64,
+}
+
+impl StateAccess for GetOrder {
+    type Outcome = Result<Order, Error>;
+
+    fn execute(self, state: &StateCache) -> Result<Order, Error> {
+        state
+            .get_decoded(get_state_key_order(self.id).as_bytes())
+            .ok_or(Error::NoSuchOrder)?
+            .map_err(|_| Error::InvalidKey)
+    }
+}
+
+pub struct GetOrderBook<'a> {
+    pub market: &'a Market,
+}
+
+impl<'a> StateAccess for GetOrderBook<'a> {
+    type Outcome = Result<OrderBook, Error>;
+
+    fn execute(self, state: &StateCache) -> Result<OrderBook, Error> {
+        state
+            .get_decoded(get_state_key_order_book(self.market).as_bytes())
+            .transpose()
+            .map(Option::unwrap_or_default)
+            .map_err(|_| Error::InvalidKey)
+    }
+}
+
+fn set_order(state: &mut StateCache, order: &Order) {
+    state.set_encoded(get_state_key_order(order.id).as_bytes(), order);
+}
+
+fn set_order_book(state: &mut StateCache, market: &Market, book: &OrderBook) {
+    let key = get_state_key_order_book(market);
+    if book.is_empty() {
+        state.remove(key.as_bytes());
+    } else {
+        state.set_encoded(key.as_bytes(), book);
+    }
+}
+
+/// Records a new order in the book of its market, and returns it.
+/// The caller takes the tokens of `Order::escrow` from the owner.
+pub struct PlaceOrder<'a> {
+    pub owner: &'a Public,
+    pub market: &'a Market,
+    pub side: Side,
+    pub price: u64,
+    pub amount: u64,
+}
+
+impl<'a> StateTransition for PlaceOrder<'a> {
+    type Outcome = Result<Order, Error>;
+
+    fn execute(self, state: &mut StateCache) -> Result<Order, Error> {
+        let id_key = get_state_key_next_order_id();
+        let id: u64 = state.get_decoded(id_key.as_bytes()).transpose().map_err(|_| Error::InvalidKey)?.unwrap_or(0);
+        let order = Order {
+            id,
+            market: *self.market,
+            owner: *self.owner,
+            side: self.side,
+            price: self.price,
+            amount: self.amount,
+        };
+        if self.market.base == self.market.quote || self.price == 0 || self.amount == 0 || order.escrow().is_none() {
+            return Err(Error::InvalidOrder)
+        }
+
+        let mut book = GetOrderBook {
+            market: self.market,
+        }
+        .execute(state)?;
+        book.insert(self.side, self.price, id);
+        set_order_book(state, self.market, &book);
+        set_order(state, &order);
+        state.set_encoded(id_key.as_bytes(), &(id + 1));
+
+        let touched_key = get_state_key_touched_markets();
+        let mut touched: TouchedMarkets =
+            state.get_decoded(touched_key.as_bytes()).transpose().map_err(|_| Error::InvalidKey)?.unwrap_or_default();
+        if touched.insert(*self.market) {
+            state.set_encoded(touched_key.as_bytes(), &touched);
+        }
+        Ok(order)
+    }
+}
+
+/// Removes an order of the owner, and returns what was left of it.
+/// The caller returns the tokens of `Order::escrow` to the owner.
+pub struct CancelOrder<'a> {
+    pub owner: &'a Public,
+    pub id: u64,
+}
+
+impl<'a> StateTransition for CancelOrder<'a> {
+    type Outcome = Result<Order, Error>;
+
+    fn execute(self, state: &mut StateCache) -> Result<Order, Error> {
+        let order = GetOrder {
+            id: self.id,
+        }
+        .execute(state)?;
+        if order.owner != *self.owner {
+            return Err(Error::NotOwner)
+        }
+        let mut book = GetOrderBook {
+            market: &order.market,
+        }
+        .execute(state)?;
+        assert!(book.remove(order.side, order.id), "An open order must be in the book");
+        set_order_book(state, &order.market, &book);
+        state.remove(get_state_key_order(order.id).as_bytes());
+        Ok(order)
+    }
+}
+
+/// Takes out the markets with orders placed in the current block.
+pub struct TakeTouchedMarkets;
+
+impl StateTransition for TakeTouchedMarkets {
+    type Outcome = Result<TouchedMarkets, Error>;
+
+    fn execute(self, state: &mut StateCache) -> Result<TouchedMarkets, Error> {
+        let key = get_state_key_touched_markets();
+        let touched = match state.get_decoded(key.as_bytes()) {
+            Some(touched) => touched.map_err(|_| Error::InvalidKey)?,
+            None => return Ok(Default::default()),
+        };
+        state.remove(key.as_bytes());
+        Ok(touched)
+    }
+}
+
+/// Matches the best buy order with the best sell order while their prices cross, and returns the trades.
+///
+/// A trade is made at the price of the order placed earlier, which was waiting in the book.
+/// The result depends only on the book, so every node makes the same trades.
+/// The caller hands over the traded tokens and the refunds from the escrow.
+pub struct MatchOrders<'a> {
+    pub market: &'a Market,
+}
+
+impl<'a> StateTransition for MatchOrders<'a> {
+    type Outcome = Result<Vec<Trade>, Error>;
+
+    fn execute(self, state: &mut StateCache) -> Result<Vec<Trade>, Error> {
+        let mut book = GetOrderBook {
+            market: self.market,
+        }
+        .execute(state)?;
+        let mut trades = Vec::new();
+        while let (Some(&(bid_price, bid_id)), Some(&(ask_price, ask_id))) = (book.bids.first(), book.asks.first()) {
+            if bid_price < ask_price {
+                break;
+            }
+            let mut bid = GetOrder {
+                id: bid_id,
+            }
+            .execute(state)?;
+            let mut ask = GetOrder {
+                id: ask_id,
+            }
+            .execute(state)?;
+            let price = if bid_id < ask_id {
+                bid_price
+            } else {
+                ask_price
+            };
+            let amount = bid.amount.min(ask.amount);
+            trades.push(Trade {
+                market: *self.market,
+                buy_order: bid_id,
+                sell_order: ask_id,
+                buyer: bid.owner,
+                seller: ask.owner,
+                price,
+                amount,
+                // The escrow of the bid covers `bid_price * bid.amount`, so these can't overflow.
+                refund: (bid_price - price) * amount,
+            });
+
+            bid.amount -= amount;
+            ask.amount -= amount;
+            for order in [bid, ask].iter() {
+                if order.amount == 0 {
+                    book.remove(order.side, order.id);
+                    state.remove(get_state_key_order(order.id).as_bytes());
+                } else {
+                    set_order(state, order);
+                }
+            }
+        }
+        set_order_book(state, self.market, &book);
+        Ok(trades)
+    }
+}
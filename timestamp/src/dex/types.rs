@@ -0,0 +1,217 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::common::*;
+use ccrypto::blake256;
+pub use ckey::Ed25519Public as Public;
+use coordinator::module::TxInspector;
+use coordinator::types::InspectedPayload;
+use coordinator::Transaction;
+use primitives::H256;
+use remote_trait_object::Service;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// The key of the block events reporting the trades made on closing the block.
+/// The value is a CBOR-encoded `Trade`.
+pub const TRADE_EVENT: &str = "dex-trade";
+
+/// A market trading the tokens of the `base` issuer for the tokens of the `quote` issuer.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Market {
+    pub base: H256,
+    pub quote: H256,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    /// Buys base tokens, paying quote tokens.
+    Buy,
+    /// Sells base tokens for quote tokens.
+    Sell,
+}
+
+/// A limit order, whose tokens are held in escrow while it is open.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Order {
+    pub id: u64,
+    pub market: Market,
+    pub owner: Public,
+    pub side: Side,
+    /// The quote tokens paid for a base token.
+    pub price: u64,
+    /// The base tokens left to trade.
+    pub amount: u64,
+}
+
+impl Order {
+    /// The tokens held in escrow for the rest of the order, as `(issuer, count)`.
+    pub fn escrow(&self) -> Option<(H256, u64)> {
+        escrow(&self.market, self.side, self.price, self.amount)
+    }
+}
+
+/// The tokens an order takes into escrow, as `(issuer, count)`.
+/// `None` if the count overflows.
+pub fn escrow(market: &Market, side: Side, price: u64, amount: u64) -> Option<(H256, u64)> {
+    match side {
+        Side::Buy => Some((market.quote, price.checked_mul(amount)?)),
+        Side::Sell => Some((market.base, amount)),
+    }
+}
+
+/// The open orders of a market as `(price, id)`, the best first.
+/// The orders at a same price are in the order they were placed.
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct OrderBook {
+    /// The buy orders, the highest price first.
+    pub bids: Vec<(u64, u64)>,
+    /// The sell orders, the lowest price first.
+    pub asks: Vec<(u64, u64)>,
+}
+
+impl OrderBook {
+    pub fn insert(&mut self, side: Side, price: u64, id: u64) {
+        let orders = match side {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        };
+        let index = orders
+            .iter()
+            .position(|(p, _)| match side {
+                Side::Buy => *p < price,
+                Side::Sell => *p > price,
+            })
+            .unwrap_or_else(|| orders.len());
+        orders.insert(index, (price, id));
+    }
+
+    /// Returns whether the order was in the book.
+    pub fn remove(&mut self, side: Side, id: u64) -> bool {
+        let orders = match side {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        };
+        match orders.iter().position(|(_, x)| *x == id) {
+            Some(index) => {
+                orders.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bids.is_empty() && self.asks.is_empty()
+    }
+}
+
+/// A match between a buy order and a sell order, at the price of the one placed earlier.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Trade {
+    pub market: Market,
+    pub buy_order: u64,
+    pub sell_order: u64,
+    pub buyer: Public,
+    pub seller: Public,
+    pub price: u64,
+    pub amount: u64,
+    /// The quote tokens returned to the buyer, who escrowed them at a higher price.
+    pub refund: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum Error {
+    NoSuchOrder,
+    /// The signer didn't place the order.
+    NotOwner,
+    /// The order trades a token for itself, has no amount or price, or escrows more tokens than can be counted.
+    InvalidOrder,
+    InvalidKey,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum TxDex {
+    /// Places a limit order, taking the tokens it may pay into escrow.
+    /// It is matched with the other orders of the market when the block is closed.
+    PlaceOrder {
+        market: Market,
+        side: Side,
+        price: u64,
+        amount: u64,
+    },
+    /// Cancels the rest of an order, returning its escrowed tokens.
+    CancelOrder {
+        id: u64,
+    },
+}
+impl Action for TxDex {}
+pub type OwnTransaction = SignedTransaction<TxDex>;
+
+pub struct GetAccountAndSeq;
+impl Service for GetAccountAndSeq {}
+impl crate::sorting::GetAccountAndSeq for GetAccountAndSeq {
+    fn get_account_and_seq(&self, tx: &Transaction) -> Result<(Public, TxSeq), ()> {
+        assert_eq!(tx.tx_type(), "dex");
+        let tx: OwnTransaction = serde_cbor::from_slice(&tx.body()).map_err(|_| ())?;
+        Ok((tx.signer_public, tx.tx.seq))
+    }
+}
+
+pub struct InspectTransaction;
+impl Service for InspectTransaction {}
+impl TxInspector for InspectTransaction {
+    fn inspect_transaction(&self, tx: &Transaction) -> Result<InspectedPayload, String> {
+        match tx.tx_type() {
+            "dex" => inspect_signed_transaction(tx.body(), |action: &TxDex| match action {
+                TxDex::PlaceOrder {
+                    ..
+                } => "place_order",
+                TxDex::CancelOrder {
+                    ..
+                } => "cancel_order",
+            }),
+            tx_type => Err(format!("Unexpected tx_type: {}", tx_type)),
+        }
+    }
+}
+
+/// The markets with orders placed in the current block, to be matched on closing it.
+pub type TouchedMarkets = BTreeSet<Market>;
+
+fn state_key<T: Serialize>(key: &T, postfix: &[u8]) -> H256 {
+    blake256(&{
+        let mut v = serde_cbor::to_vec(key).unwrap();
+        v.extend_from_slice(postfix);
+        v
+    } as &[u8])
+}
+
+pub fn get_state_key_order(id: u64) -> H256 {
+    state_key(&id, b"Dex-Module-Order")
+}
+
+pub fn get_state_key_order_book(market: &Market) -> H256 {
+    state_key(market, b"Dex-Module-Order-Book")
+}
+
+pub fn get_state_key_next_order_id() -> H256 {
+    blake256(b"Dex-Module-Next-Order-Id")
+}
+
+pub fn get_state_key_touched_markets() -> H256 {
+    blake256(b"Dex-Module-Touched-Markets")
+}
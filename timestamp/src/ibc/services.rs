@@ -0,0 +1,271 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::state_machine::*;
+use super::types::*;
+use super::ServiceHandler;
+use crate::common::witness_size;
+use coordinator::module::*;
+use coordinator::types::*;
+use coordinator::{Header, Transaction};
+use ctypes::{Commit, CompactValidatorSet, ModuleDatumProof};
+use remote_trait_object::{service, Service};
+use std::collections::HashMap;
+
+#[service]
+pub trait PacketSender: Service {
+    /// Commits a packet to be delivered to the `port` module of the counterparty, and returns its sequence.
+    fn send_packet(&self, session: SessionId, counterparty: &str, port: &str, payload: &[u8]) -> Result<u64, Error>;
+}
+
+#[service]
+pub trait HandlePacket: Service {
+    /// Handles a packet that `counterparty` sent to this module.
+    ///
+    /// On an error the receiving transaction fails,
+    /// and the later packets from the counterparty wait until this one is handled.
+    fn handle_packet(&self, session: SessionId, counterparty: &str, payload: &[u8]) -> Result<(), String>;
+}
+
+enum ExecuteError {
+    InvalidMetadata,
+    InvalidSign,
+    InvalidFormat,
+    AccountModuleError(crate::account::Error),
+    IbcError(Error),
+    InvalidSequence,
+    InvalidHeader,
+    NotNextHeader,
+    WrongValidatorSet,
+    InvalidCommit,
+    UnknownHeight,
+    InvalidProof,
+    OutOfOrderPacket,
+    NoHandler,
+    HandlerFailed(String),
+}
+
+impl ServiceHandler {
+    fn excute_tx(&self, session: SessionId, transaction: &Transaction) -> Result<(), ExecuteError> {
+        if transaction.tx_type() != "ibc" {
+            return Err(ExecuteError::InvalidMetadata)
+        }
+
+        let tx: OwnTransaction =
+            serde_cbor::from_slice(&transaction.body()).map_err(|_| ExecuteError::InvalidFormat)?;
+        tx.verify().map_err(|_| ExecuteError::InvalidSign)?;
+        if self
+            .account_manager
+            .read()
+            .get_account(session, &tx.signer_public, true)
+            .map_err(ExecuteError::AccountModuleError)?
+            .seq
+            != tx.tx.seq
+        {
+            return Err(ExecuteError::InvalidSequence)
+        }
+
+        match &tx.tx.action {
+            TxIbc::UpdateClient {
+                counterparty,
+                header,
+                validator_set,
+                commit,
+            } => self.update_client(session, counterparty, header, validator_set, commit)?,
+            TxIbc::ReceivePacket {
+                counterparty,
+                height,
+                packet,
+                proof,
+            } => self.receive_packet(session, counterparty, *height, packet, proof)?,
+        }
+        self.account_manager.read().increase_sequence(session, &tx.signer_public, true).unwrap();
+        Ok(())
+    }
+
+    fn update_client(
+        &self,
+        session: SessionId,
+        counterparty: &str,
+        header: &[u8],
+        validator_set: &CompactValidatorSet,
+        commit: &Commit,
+    ) -> Result<(), ExecuteError> {
+        let state_machine = self.create_state_machine(session);
+        let client_state = state_machine
+            .execute_access(GetClientState {
+                counterparty,
+            })
+            .map_err(ExecuteError::IbcError)?;
+
+        let header: ctypes::Header = rlp::decode(header).map_err(|_| ExecuteError::InvalidHeader)?;
+        // Headers are accepted one by one, so the validator set of each is the one its parent committed to.
+        if header.number() != client_state.height + 1 || *header.parent_hash() != client_state.hash {
+            return Err(ExecuteError::NotNextHeader)
+        }
+        if validator_set.hash() != client_state.next_validator_set_hash {
+            return Err(ExecuteError::WrongValidatorSet)
+        }
+        commit.verify(&header, validator_set).map_err(|_| ExecuteError::InvalidCommit)?;
+
+        state_machine.execute_transition(AcceptHeader {
+            counterparty,
+            client_state: &ClientState {
+                height: header.number(),
+                hash: header.hash(),
+                next_validator_set_hash: *header.next_validator_set_hash(),
+                ..client_state
+            },
+            module_roots_hash: header.module_roots_hash(),
+        });
+        Ok(())
+    }
+
+    fn receive_packet(
+        &self,
+        session: SessionId,
+        counterparty: &str,
+        height: u64,
+        packet: &Packet,
+        proof: &ModuleDatumProof,
+    ) -> Result<(), ExecuteError> {
+        let state_machine = self.create_state_machine(session);
+        let client_state = state_machine
+            .execute_access(GetClientState {
+                counterparty,
+            })
+            .map_err(ExecuteError::IbcError)?;
+        let module_roots_hash = state_machine
+            .execute_access(GetModuleRootsHash {
+                counterparty,
+                height,
+            })
+            .map_err(ExecuteError::IbcError)?
+            .ok_or(ExecuteError::UnknownHeight)?;
+
+        let key = get_state_key_packet(&client_state.id_on_counterparty, packet.sequence);
+        if proof.storage_id != client_state.storage_id
+            || proof.key.as_slice() != key.as_bytes()
+            || proof.datum != Some(serde_cbor::to_vec(packet).unwrap())
+            || !proof.verify(&module_roots_hash)
+        {
+            return Err(ExecuteError::InvalidProof)
+        }
+        if !state_machine
+            .execute_transition(TakeReceiveSequence {
+                counterparty,
+                sequence: packet.sequence,
+            })
+            .map_err(ExecuteError::IbcError)?
+        {
+            return Err(ExecuteError::OutOfOrderPacket)
+        }
+
+        let handlers = self.packet_handlers.read();
+        let handler = handlers.get(&packet.port).ok_or(ExecuteError::NoHandler)?;
+        handler.handle_packet(session, counterparty, &packet.payload).map_err(ExecuteError::HandlerFailed)
+    }
+}
+
+impl PacketSender for ServiceHandler {
+    fn send_packet(&self, session: SessionId, counterparty: &str, port: &str, payload: &[u8]) -> Result<u64, Error> {
+        let state_machine = self.create_state_machine(session);
+        state_machine.execute_access(GetClientState {
+            counterparty,
+        })?;
+        state_machine.execute_transition(CommitPacket {
+            counterparty,
+            port,
+            payload,
+        })
+    }
+}
+
+impl InitGenesis for ServiceHandler {
+    fn init_genesis(&self, session: SessionId, config: &[u8]) {
+        let counterparties: HashMap<String, CounterpartyConfig> = serde_cbor::from_slice(&config).unwrap();
+        let state_machine = self.create_state_machine(session);
+        for (counterparty, config) in counterparties {
+            let header: ctypes::Header = rlp::decode(&hex::decode(&config.trusted_header).unwrap()).unwrap();
+            state_machine.execute_transition(AcceptHeader {
+                counterparty: &counterparty,
+                client_state: &ClientState {
+                    storage_id: config.storage_id,
+                    id_on_counterparty: config.id_on_counterparty,
+                    height: header.number(),
+                    hash: header.hash(),
+                    next_validator_set_hash: *header.next_validator_set_hash(),
+                },
+                module_roots_hash: header.module_roots_hash(),
+            });
+        }
+    }
+}
+
+impl TxOwner for ServiceHandler {
+    fn block_opened(&self, _session: SessionId, _: &Header) -> Result<(), HeaderError> {
+        Ok(())
+    }
+
+    fn execute_transaction(&self, session: SessionId, transaction: &Transaction) -> Result<TransactionOutcome, ()> {
+        if let Err(error) = self.excute_tx(session, transaction) {
+            match error {
+                ExecuteError::InvalidMetadata => Err(()),
+                ExecuteError::InvalidSign => Err(()),
+                ExecuteError::InvalidFormat => Err(()),
+                ExecuteError::AccountModuleError(_) => Err(()),
+                ExecuteError::IbcError(_) => Err(()),
+                ExecuteError::InvalidSequence => Err(()),
+                ExecuteError::InvalidHeader => Err(()),
+                ExecuteError::NotNextHeader => Err(()),
+                ExecuteError::WrongValidatorSet => Err(()),
+                ExecuteError::InvalidCommit => Err(()),
+                ExecuteError::UnknownHeight => Err(()),
+                ExecuteError::InvalidProof => Err(()),
+                ExecuteError::OutOfOrderPacket => Err(()),
+                ExecuteError::NoHandler => Err(()),
+                ExecuteError::HandlerFailed(_) => Err(()),
+            }
+        } else {
+            Ok(Default::default())
+        }
+    }
+
+    fn check_transaction(&self, transaction: &Transaction) -> Result<(), coordinator::types::ErrorCode> {
+        let todo_fixthis: coordinator::types::ErrorCode = 3;
+        if transaction.tx_type() != "ibc" {
+            return Err(todo_fixthis)
+        }
+        let tx: OwnTransaction = serde_cbor::from_slice(&transaction.body()).map_err(|_| todo_fixthis)?;
+        tx.verify().map_err(|_| todo_fixthis)?;
+        Ok(())
+    }
+
+    fn estimate_gas(&self, transaction: &Transaction) -> u64 {
+        transaction.size() as u64
+    }
+
+    fn witness_size(&self, transaction: &Transaction) -> usize {
+        match serde_cbor::from_slice::<OwnTransaction>(&transaction.body()) {
+            Ok(tx) => witness_size(&transaction.body(), &tx.tx),
+            Err(_) => 0,
+        }
+    }
+
+    fn block_closed(&self, _session: SessionId) -> Result<Vec<Event>, CloseBlockError> {
+        Ok(Vec::new())
+    }
+}
@@ -0,0 +1,121 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::types::*;
+use crate::common::state_machine::{StateAccess, StateTransition};
+use crate::common::StateCache;
+use primitives::H256;
+
+pub struct GetClientState<'a> {
+    pub counterparty: &'a str,
+}
+
+impl<'a> StateAccess for GetClientState<'a> {
+    type Outcome = Result<ClientState, Error>;
+
+    fn execute(self, state: &StateCache) -> Result<ClientState, Error> {
+        state
+            .get_decoded(get_state_key_client(self.counterparty).as_bytes())
+            .ok_or(Error::UnknownCounterparty)?
+            .map_err(|_| Error::InvalidKey)
+    }
+}
+
+pub struct GetModuleRootsHash<'a> {
+    pub counterparty: &'a str,
+    pub height: u64,
+}
+
+impl<'a> StateAccess for GetModuleRootsHash<'a> {
+    type Outcome = Result<Option<H256>, Error>;
+
+    fn execute(self, state: &StateCache) -> Result<Option<H256>, Error> {
+        state
+            .get_decoded(get_state_key_module_roots_hash(self.counterparty, self.height).as_bytes())
+            .transpose()
+            .map_err(|_| Error::InvalidKey)
+    }
+}
+
+/// Records a header of the counterparty as the latest one the light client trusts.
+/// The packets at its height can be proven only if it commits to the module roots.
+pub struct AcceptHeader<'a> {
+    pub counterparty: &'a str,
+    pub client_state: &'a ClientState,
+    pub module_roots_hash: Option<&'a H256>,
+}
+
+impl<'a> StateTransition for AcceptHeader<'a> {
+    type Outcome = ();
+
+    fn execute(self, state: &mut StateCache) {
+        state.set_encoded(get_state_key_client(self.counterparty).as_bytes(), self.client_state);
+        if let Some(module_roots_hash) = self.module_roots_hash {
+            state.set_encoded(
+                get_state_key_module_roots_hash(self.counterparty, self.client_state.height).as_bytes(),
+                module_roots_hash,
+            );
+        }
+    }
+}
+
+fn get_sequence(state: &StateCache, key: &H256) -> Result<u64, Error> {
+    state.get_decoded(key.as_bytes()).transpose().map(Option::unwrap_or_default).map_err(|_| Error::InvalidKey)
+}
+
+/// Stores a packet for the relayers to prove to the counterparty, and returns its sequence.
+pub struct CommitPacket<'a> {
+    pub counterparty: &'a str,
+    pub port: &'a str,
+    pub payload: &'a [u8],
+}
+
+impl<'a> StateTransition for CommitPacket<'a> {
+    type Outcome = Result<u64, Error>;
+
+    fn execute(self, state: &mut StateCache) -> Result<u64, Error> {
+        let key = get_state_key_send_sequence(self.counterparty);
+        let sequence = get_sequence(state, &key)?;
+        let packet = Packet {
+            sequence,
+            port: self.port.to_owned(),
+            payload: self.payload.to_vec(),
+        };
+        state.set_encoded(get_state_key_packet(self.counterparty, sequence).as_bytes(), &packet);
+        state.set_encoded(key.as_bytes(), &(sequence + 1));
+        Ok(sequence)
+    }
+}
+
+/// Marks the packet with the sequence as received if it is the next one from the counterparty.
+/// Returns whether it was.
+pub struct TakeReceiveSequence<'a> {
+    pub counterparty: &'a str,
+    pub sequence: u64,
+}
+
+impl<'a> StateTransition for TakeReceiveSequence<'a> {
+    type Outcome = Result<bool, Error>;
+
+    fn execute(self, state: &mut StateCache) -> Result<bool, Error> {
+        let key = get_state_key_receive_sequence(self.counterparty);
+        if get_sequence(state, &key)? != self.sequence {
+            return Ok(false)
+        }
+        state.set_encoded(key.as_bytes(), &(self.sequence + 1));
+        Ok(true)
+    }
+}
@@ -0,0 +1,124 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::common::*;
+use ccrypto::blake256;
+pub use ckey::{Ed25519Private as Private, Ed25519Public as Public};
+use coordinator::Transaction;
+use ctypes::{BlockHash, Commit, CompactValidatorSet, ModuleDatumProof, StorageId};
+use primitives::{Bytes, H256};
+use remote_trait_object::Service;
+use serde::{Deserialize, Serialize};
+
+/// How this chain follows a counterparty.
+/// The genesis config maps the id of each counterparty to one of these.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct CounterpartyConfig {
+    /// The hex of the RLP-encoded header the light client trusts without a proof.
+    pub trusted_header: String,
+    /// The storage id of the IBC module on the counterparty.
+    pub storage_id: StorageId,
+    /// The id by which the counterparty knows this chain.
+    pub id_on_counterparty: String,
+}
+
+/// The light client of a counterparty.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ClientState {
+    pub storage_id: StorageId,
+    pub id_on_counterparty: String,
+    /// The number of the latest header accepted.
+    pub height: u64,
+    pub hash: BlockHash,
+    /// The validators who must sign the next header.
+    pub next_validator_set_hash: H256,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Packet {
+    /// Packets from a chain are handled in the order of their sequences.
+    pub sequence: u64,
+    /// The module on the destination chain that handles the packet.
+    pub port: String,
+    pub payload: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum TxIbc {
+    /// Moves the light client of the counterparty to the next header.
+    UpdateClient {
+        counterparty: String,
+        header: Bytes,
+        validator_set: CompactValidatorSet,
+        commit: Commit,
+    },
+    /// Delivers a packet the counterparty committed in the state of an accepted header.
+    ReceivePacket {
+        counterparty: String,
+        height: u64,
+        packet: Packet,
+        proof: ModuleDatumProof,
+    },
+}
+impl Action for TxIbc {}
+pub type OwnTransaction = SignedTransaction<TxIbc>;
+
+pub struct GetAccountAndSeq;
+impl Service for GetAccountAndSeq {}
+impl crate::sorting::GetAccountAndSeq for GetAccountAndSeq {
+    fn get_account_and_seq(&self, tx: &Transaction) -> Result<(Public, TxSeq), ()> {
+        assert_eq!(tx.tx_type(), "ibc");
+        let tx: OwnTransaction = serde_cbor::from_slice(&tx.body()).map_err(|_| ())?;
+        Ok((tx.signer_public, tx.tx.seq))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum Error {
+    UnknownCounterparty,
+    InvalidKey,
+}
+
+fn state_key<T: Serialize>(key: &T, postfix: &[u8]) -> H256 {
+    blake256(&{
+        let mut v = serde_cbor::to_vec(key).unwrap();
+        v.extend_from_slice(postfix);
+        v
+    } as &[u8])
+}
+
+pub fn get_state_key_client(counterparty: &str) -> H256 {
+    state_key(&counterparty, b"Ibc-Module-Client")
+}
+
+pub fn get_state_key_module_roots_hash(counterparty: &str, height: u64) -> H256 {
+    state_key(&(counterparty, height), b"Ibc-Module-Module-Roots-Hash")
+}
+
+pub fn get_state_key_send_sequence(counterparty: &str) -> H256 {
+    state_key(&counterparty, b"Ibc-Module-Send-Sequence")
+}
+
+pub fn get_state_key_receive_sequence(counterparty: &str) -> H256 {
+    state_key(&counterparty, b"Ibc-Module-Receive-Sequence")
+}
+
+/// The key of a packet sent to `counterparty`.
+/// The receiving chain derives it with its own `id_on_counterparty` to check the proof.
+pub fn get_state_key_packet(counterparty: &str, sequence: u64) -> H256 {
+    state_key(&(counterparty, sequence), b"Ibc-Module-Packet")
+}
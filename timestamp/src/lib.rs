@@ -16,6 +16,9 @@
 
 pub mod account;
 pub mod common;
+pub mod dex;
+pub mod ibc;
+pub mod nft;
 pub mod sorting;
 pub mod staking;
 pub mod stamp;
@@ -0,0 +1,157 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::state_machine::*;
+use super::types::*;
+use super::{ServiceHandler, StateMachine};
+use crate::common::*;
+use coordinator::module::*;
+use remote_trait_object::Service;
+use std::sync::Arc;
+
+/// The most items of a list returned at once.
+const MAX_LIMIT: u64 = 1000;
+
+struct GraphQlRoot {
+    state_machine: StateMachine,
+}
+
+#[async_graphql::Object]
+impl GraphQlRoot {
+    async fn asset(&self, id: GqlH256) -> Option<Asset> {
+        self.state_machine
+            .execute_access(GetAsset {
+                id: &id.0,
+            })
+            .ok()
+    }
+
+    /// The ids of the assets of `owner`, at most `limit` from the `offset`th.
+    /// The positions of the assets change as the owner transfers them.
+    async fn assets(&self, owner: GqlPublic, offset: u64, limit: u64) -> Vec<String> {
+        self.state_machine
+            .execute_access(GetOwnedAssets {
+                owner: &owner.0,
+                range: offset..offset.saturating_add(limit.min(MAX_LIMIT)),
+            })
+            .map(|ids| ids.iter().map(|id| hex::encode(id.as_ref())).collect())
+            .unwrap_or_default()
+    }
+
+    async fn asset_count(&self, owner: GqlPublic) -> u64 {
+        self.state_machine
+            .execute_access(CountOwnedAssets {
+                owner: &owner.0,
+            })
+            .unwrap_or_default()
+    }
+
+    /// The provenance of an asset: its transfer records from the minting, at most `limit` from the `offset`th.
+    async fn history(&self, asset: GqlH256, offset: u64, limit: u64) -> Option<Vec<TransferRecord>> {
+        self.state_machine
+            .execute_access(GetHistory {
+                asset: &asset.0,
+                range: offset..offset.saturating_add(limit.min(MAX_LIMIT)),
+            })
+            .ok()
+    }
+
+    /// The transfer records of the assets sent or received by `public`, at most `limit` from the `offset`th.
+    async fn account_history(&self, public: GqlPublic, offset: u64, limit: u64) -> Vec<TransferRecord> {
+        self.state_machine
+            .execute_access(GetAccountHistory {
+                public: &public.0,
+                range: offset..offset.saturating_add(limit.min(MAX_LIMIT)),
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[async_graphql::Object]
+impl Asset {
+    async fn id(&self) -> String {
+        hex::encode(self.id.as_ref())
+    }
+
+    async fn creator(&self) -> String {
+        hex::encode(self.creator.as_ref())
+    }
+
+    /// Null if the asset was burnt.
+    async fn owner(&self) -> Option<String> {
+        self.owner.as_ref().map(|owner| hex::encode(owner.as_ref()))
+    }
+
+    async fn metadata(&self) -> String {
+        hex::encode(self.metadata.as_ref())
+    }
+
+    async fn transfers(&self) -> u64 {
+        self.history_len
+    }
+}
+
+#[async_graphql::Object]
+impl TransferRecord {
+    async fn asset(&self) -> String {
+        hex::encode(self.asset.as_ref())
+    }
+
+    /// Null for the minting.
+    async fn from(&self) -> Option<String> {
+        self.from.as_ref().map(|from| hex::encode(from.as_ref()))
+    }
+
+    /// Null for the burning.
+    async fn to(&self) -> Option<String> {
+        self.to.as_ref().map(|to| hex::encode(to.as_ref()))
+    }
+
+    async fn tx_hash(&self) -> String {
+        hex::encode(self.tx_hash.as_ref())
+    }
+}
+
+pub struct GraphQlRequestHandler {
+    service_handler: Arc<ServiceHandler>,
+
+    /// A runtime to process the asynchronous result of the query
+    tokio_runtime: tokio::runtime::Runtime,
+}
+
+impl GraphQlRequestHandler {
+    pub(super) fn new(service_handler: Arc<ServiceHandler>) -> Self {
+        Self {
+            service_handler,
+            tokio_runtime: tokio::runtime::Runtime::new().unwrap(),
+        }
+    }
+}
+
+impl Service for GraphQlRequestHandler {}
+
+impl HandleGraphQlRequest for GraphQlRequestHandler {
+    fn execute(&self, session: SessionId, query: &str, variables: &str) -> String {
+        handle_gql_query(
+            self.tokio_runtime.handle(),
+            GraphQlRoot {
+                state_machine: self.service_handler.create_state_machine(session),
+            },
+            query,
+            variables,
+        )
+    }
+}
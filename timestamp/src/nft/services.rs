@@ -0,0 +1,133 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::state_machine::*;
+use super::types::*;
+use super::ServiceHandler;
+use crate::common::witness_size;
+use coordinator::module::*;
+use coordinator::types::*;
+use coordinator::{Header, Transaction};
+
+enum ExecuteError {
+    InvalidMetadata,
+    InvalidSign,
+    InvalidFormat,
+    AccountModuleError(crate::account::Error),
+    NftError(Error),
+    InvalidSequence,
+}
+
+impl ServiceHandler {
+    fn excute_tx(&self, session: SessionId, transaction: &Transaction) -> Result<(), ExecuteError> {
+        if transaction.tx_type() != "nft" {
+            return Err(ExecuteError::InvalidMetadata)
+        }
+
+        let tx: OwnTransaction =
+            serde_cbor::from_slice(&transaction.body()).map_err(|_| ExecuteError::InvalidFormat)?;
+        tx.verify().map_err(|_| ExecuteError::InvalidSign)?;
+        if self
+            .account_manager
+            .read()
+            .get_account(session, &tx.signer_public, true)
+            .map_err(ExecuteError::AccountModuleError)?
+            .seq
+            != tx.tx.seq
+        {
+            return Err(ExecuteError::InvalidSequence)
+        }
+
+        let state_machine = self.create_state_machine(session);
+        let tx_hash = tx.tx.hash();
+        match &tx.tx.action {
+            TxNft::Mint {
+                metadata,
+            } => state_machine
+                .execute_transition(Mint {
+                    creator: &tx.signer_public,
+                    metadata,
+                    tx_hash: &tx_hash,
+                })
+                .map(|_| ()),
+            TxNft::Transfer {
+                asset,
+                receiver,
+            } => state_machine.execute_transition(Transfer {
+                sender: &tx.signer_public,
+                asset,
+                receiver,
+                tx_hash: &tx_hash,
+            }),
+            TxNft::Burn {
+                asset,
+            } => state_machine.execute_transition(Burn {
+                owner: &tx.signer_public,
+                asset,
+                tx_hash: &tx_hash,
+            }),
+        }
+        .map_err(ExecuteError::NftError)?;
+        self.account_manager.read().increase_sequence(session, &tx.signer_public, true).unwrap();
+        Ok(())
+    }
+}
+
+impl TxOwner for ServiceHandler {
+    fn block_opened(&self, _session: SessionId, _: &Header) -> Result<(), HeaderError> {
+        Ok(())
+    }
+
+    fn execute_transaction(&self, session: SessionId, transaction: &Transaction) -> Result<TransactionOutcome, ()> {
+        if let Err(error) = self.excute_tx(session, transaction) {
+            match error {
+                ExecuteError::InvalidMetadata => Err(()),
+                ExecuteError::InvalidSign => Err(()),
+                ExecuteError::InvalidFormat => Err(()),
+                ExecuteError::AccountModuleError(_) => Err(()),
+                ExecuteError::NftError(_) => Err(()),
+                ExecuteError::InvalidSequence => Err(()),
+            }
+        } else {
+            Ok(Default::default())
+        }
+    }
+
+    fn check_transaction(&self, transaction: &Transaction) -> Result<(), coordinator::types::ErrorCode> {
+        let todo_fixthis: coordinator::types::ErrorCode = 3;
+        if transaction.tx_type() != "nft" {
+            return Err(todo_fixthis)
+        }
+        let tx: OwnTransaction = serde_cbor::from_slice(&transaction.body()).map_err(|_| todo_fixthis)?;
+        tx.verify().map_err(|_| todo_fixthis)?;
+        Ok(())
+    }
+
+    fn estimate_gas(&self, transaction: &Transaction) -> u64 {
+        transaction.size() as u64
+    }
+
+    fn witness_size(&self, transaction: &Transaction) -> usize {
+        match serde_cbor::from_slice::<OwnTransaction>(&transaction.body()) {
+            Ok(tx) => witness_size(&transaction.body(), &tx.tx),
+            Err(_) => 0,
+        }
+    }
+
+    fn block_closed(&self, _session: SessionId) -> Result<Vec<Event>, CloseBlockError> {
+        Ok(Vec::new())
+    }
+}
@@ -0,0 +1,279 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::types::*;
+use crate::common::state_machine::{StateAccess, StateTransition};
+use crate::common::StateCache;
+pub use ckey::Ed25519Public as Public;
+use primitives::H256;
+use std::ops::Range;
+
+fn get_count(state: &StateCache, key: &H256) -> Result<u64, Error> {
+    state.get_decoded(key.as_bytes()).transpose().map(Option::unwrap_or_default).map_err(|_| Error::InvalidKey)
+}
+
+fn get_item<T>(state: &StateCache, key: &H256) -> Result<T, Error>
+where
+    T: serde::de::DeserializeOwned + Clone + Send + Sync + 'static, {
+    state.get_decoded(key.as_bytes()).ok_or(Error::InvalidKey)?.map_err(|_| Error::InvalidKey)
+}
+
+/// Reads the items of a list kept under a prefix, in the `range` of positions clipped to its length.
+fn read_list<T>(
+    state: &StateCache,
+    count_key: &H256,
+    item_key: impl Fn(u64) -> H256,
+    range: Range<u64>,
+) -> Result<Vec<T>, Error>
+where
+    T: serde::de::DeserializeOwned + Clone + Send + Sync + 'static, {
+    let count = get_count(state, count_key)?;
+    (range.start..range.end.min(count)).map(|index| get_item(state, &item_key(index))).collect()
+}
+
+pub struct GetAsset<'a> {
+    pub id: &'a H256,
+}
+
+impl<'a> StateAccess for GetAsset<'a> {
+    type Outcome = Result<Asset, Error>;
+
+    fn execute(self, state: &StateCache) -> Result<Asset, Error> {
+        state
+            .get_decoded(get_state_key_asset(self.id).as_bytes())
+            .ok_or(Error::NoSuchAsset)?
+            .map_err(|_| Error::InvalidKey)
+    }
+}
+
+pub struct GetOwnedAssets<'a> {
+    pub owner: &'a Public,
+    pub range: Range<u64>,
+}
+
+impl<'a> StateAccess for GetOwnedAssets<'a> {
+    type Outcome = Result<Vec<H256>, Error>;
+
+    fn execute(self, state: &StateCache) -> Result<Vec<H256>, Error> {
+        let owner = self.owner;
+        read_list(state, &get_state_key_owned_count(owner), |index| get_state_key_owned(owner, index), self.range)
+    }
+}
+
+pub struct CountOwnedAssets<'a> {
+    pub owner: &'a Public,
+}
+
+impl<'a> StateAccess for CountOwnedAssets<'a> {
+    type Outcome = Result<u64, Error>;
+
+    fn execute(self, state: &StateCache) -> Result<u64, Error> {
+        get_count(state, &get_state_key_owned_count(self.owner))
+    }
+}
+
+/// The transfer records of an asset, from the minting.
+pub struct GetHistory<'a> {
+    pub asset: &'a H256,
+    pub range: Range<u64>,
+}
+
+impl<'a> StateAccess for GetHistory<'a> {
+    type Outcome = Result<Vec<TransferRecord>, Error>;
+
+    fn execute(self, state: &StateCache) -> Result<Vec<TransferRecord>, Error> {
+        let asset = GetAsset {
+            id: self.asset,
+        }
+        .execute(state)?;
+        (self.range.start..self.range.end.min(asset.history_len))
+            .map(|index| get_item(state, &get_state_key_history(self.asset, index)))
+            .collect()
+    }
+}
+
+/// The transfer records of the assets sent or received by an account, oldest first.
+pub struct GetAccountHistory<'a> {
+    pub public: &'a Public,
+    pub range: Range<u64>,
+}
+
+impl<'a> StateAccess for GetAccountHistory<'a> {
+    type Outcome = Result<Vec<TransferRecord>, Error>;
+
+    fn execute(self, state: &StateCache) -> Result<Vec<TransferRecord>, Error> {
+        let public = self.public;
+        let references: Vec<(H256, u64)> = read_list(
+            state,
+            &get_state_key_account_history_count(public),
+            |index| get_state_key_account_history(public, index),
+            self.range,
+        )?;
+        references.iter().map(|(asset, index)| get_item(state, &get_state_key_history(asset, *index))).collect()
+    }
+}
+
+fn set_asset(state: &mut StateCache, asset: &Asset) {
+    state.set_encoded(get_state_key_asset(&asset.id).as_bytes(), asset);
+}
+
+/// Appends the asset to the list of its owner, and returns its position.
+fn add_owned(state: &mut StateCache, owner: &Public, id: &H256) -> Result<u64, Error> {
+    let count_key = get_state_key_owned_count(owner);
+    let count = get_count(state, &count_key)?;
+    state.set_encoded(get_state_key_owned(owner, count).as_bytes(), id);
+    state.set_encoded(count_key.as_bytes(), &(count + 1));
+    Ok(count)
+}
+
+/// Removes the asset at `index` from the list of `owner`, moving the last asset of the list into its place.
+fn remove_owned(state: &mut StateCache, owner: &Public, index: u64) -> Result<(), Error> {
+    let count_key = get_state_key_owned_count(owner);
+    let last = get_count(state, &count_key)?.checked_sub(1).ok_or(Error::InvalidKey)?;
+    if index != last {
+        let moved: H256 = get_item(state, &get_state_key_owned(owner, last))?;
+        let mut moved_asset = GetAsset {
+            id: &moved,
+        }
+        .execute(state)?;
+        moved_asset.owner_index = index;
+        set_asset(state, &moved_asset);
+        state.set_encoded(get_state_key_owned(owner, index).as_bytes(), &moved);
+    }
+    state.remove(get_state_key_owned(owner, last).as_bytes());
+    state.set_encoded(count_key.as_bytes(), &last);
+    Ok(())
+}
+
+fn add_account_history(state: &mut StateCache, public: &Public, reference: (H256, u64)) -> Result<(), Error> {
+    let count_key = get_state_key_account_history_count(public);
+    let count = get_count(state, &count_key)?;
+    state.set_encoded(get_state_key_account_history(public, count).as_bytes(), &reference);
+    state.set_encoded(count_key.as_bytes(), &(count + 1));
+    Ok(())
+}
+
+/// Appends the record to the history of the asset and to the histories of the accounts in it.
+/// The caller saves the asset afterwards.
+fn record_transfer(state: &mut StateCache, asset: &mut Asset, record: TransferRecord) -> Result<(), Error> {
+    let index = asset.history_len;
+    state.set_encoded(get_state_key_history(&asset.id, index).as_bytes(), &record);
+    asset.history_len += 1;
+    if let Some(from) = &record.from {
+        add_account_history(state, from, (asset.id, index))?;
+    }
+    match (&record.from, &record.to) {
+        (Some(from), Some(to)) if from == to => {}
+        (_, Some(to)) => add_account_history(state, to, (asset.id, index))?,
+        (_, None) => {}
+    }
+    Ok(())
+}
+
+/// Takes an asset owned by `owner` out of the owner's list.
+fn take_owned(state: &mut StateCache, owner: &Public, id: &H256) -> Result<Asset, Error> {
+    let asset = GetAsset {
+        id,
+    }
+    .execute(state)?;
+    if asset.owner.as_ref() != Some(owner) {
+        return Err(Error::NotOwner)
+    }
+    remove_owned(state, owner, asset.owner_index)?;
+    Ok(asset)
+}
+
+pub struct Mint<'a> {
+    pub creator: &'a Public,
+    pub metadata: &'a H256,
+    pub tx_hash: &'a H256,
+}
+
+impl<'a> StateTransition for Mint<'a> {
+    type Outcome = Result<H256, Error>;
+
+    fn execute(self, state: &mut StateCache) -> Result<H256, Error> {
+        let id = asset_id(self.creator, self.tx_hash);
+        if state.has(get_state_key_asset(&id).as_bytes()) {
+            return Err(Error::AssetExists)
+        }
+        let mut asset = Asset {
+            id,
+            creator: *self.creator,
+            owner: Some(*self.creator),
+            metadata: *self.metadata,
+            owner_index: add_owned(state, self.creator, &id)?,
+            history_len: 0,
+        };
+        record_transfer(state, &mut asset, TransferRecord {
+            asset: id,
+            from: None,
+            to: Some(*self.creator),
+            tx_hash: *self.tx_hash,
+        })?;
+        set_asset(state, &asset);
+        Ok(id)
+    }
+}
+
+pub struct Transfer<'a> {
+    pub sender: &'a Public,
+    pub asset: &'a H256,
+    pub receiver: &'a Public,
+    pub tx_hash: &'a H256,
+}
+
+impl<'a> StateTransition for Transfer<'a> {
+    type Outcome = Result<(), Error>;
+
+    fn execute(self, state: &mut StateCache) -> Result<(), Error> {
+        let mut asset = take_owned(state, self.sender, self.asset)?;
+        asset.owner = Some(*self.receiver);
+        asset.owner_index = add_owned(state, self.receiver, self.asset)?;
+        record_transfer(state, &mut asset, TransferRecord {
+            asset: *self.asset,
+            from: Some(*self.sender),
+            to: Some(*self.receiver),
+            tx_hash: *self.tx_hash,
+        })?;
+        set_asset(state, &asset);
+        Ok(())
+    }
+}
+
+pub struct Burn<'a> {
+    pub owner: &'a Public,
+    pub asset: &'a H256,
+    pub tx_hash: &'a H256,
+}
+
+impl<'a> StateTransition for Burn<'a> {
+    type Outcome = Result<(), Error>;
+
+    fn execute(self, state: &mut StateCache) -> Result<(), Error> {
+        let mut asset = take_owned(state, self.owner, self.asset)?;
+        asset.owner = None;
+        asset.owner_index = 0;
+        record_transfer(state, &mut asset, TransferRecord {
+            asset: *self.asset,
+            from: Some(*self.owner),
+            to: None,
+            tx_hash: *self.tx_hash,
+        })?;
+        set_asset(state, &asset);
+        Ok(())
+    }
+}
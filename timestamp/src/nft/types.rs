@@ -0,0 +1,150 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::common::*;
+use ccrypto::blake256;
+pub use ckey::Ed25519Public as Public;
+use coordinator::module::TxInspector;
+use coordinator::types::InspectedPayload;
+use coordinator::Transaction;
+use primitives::H256;
+use remote_trait_object::Service;
+use serde::{Deserialize, Serialize};
+
+/// A non-fungible asset. The record of a burnt asset stays with no owner, so that its history can still be read.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Asset {
+    pub id: H256,
+    pub creator: Public,
+    pub owner: Option<Public>,
+    /// The hash of the metadata, which is kept off the chain.
+    pub metadata: H256,
+    /// The position of the asset in the list of the assets of its owner.
+    pub owner_index: u64,
+    /// The number of the transfer records of the asset.
+    pub history_len: u64,
+}
+
+/// A change of the owner of an asset.
+/// `from` is `None` for the minting, and `to` is `None` for the burning.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TransferRecord {
+    pub asset: H256,
+    pub from: Option<Public>,
+    pub to: Option<Public>,
+    /// The hash of the transaction that made the transfer.
+    pub tx_hash: H256,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum Error {
+    NoSuchAsset,
+    /// The signer doesn't own the asset, or it was burnt.
+    NotOwner,
+    /// An asset with the same id was minted before.
+    AssetExists,
+    InvalidKey,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum TxNft {
+    /// Mints an asset owned by the signer.
+    /// Its id is derived from the signer and the hash of the transaction.
+    Mint {
+        metadata: H256,
+    },
+    Transfer {
+        asset: H256,
+        receiver: Public,
+    },
+    Burn {
+        asset: H256,
+    },
+}
+impl Action for TxNft {}
+pub type OwnTransaction = SignedTransaction<TxNft>;
+
+pub struct GetAccountAndSeq;
+impl Service for GetAccountAndSeq {}
+impl crate::sorting::GetAccountAndSeq for GetAccountAndSeq {
+    fn get_account_and_seq(&self, tx: &Transaction) -> Result<(Public, TxSeq), ()> {
+        assert_eq!(tx.tx_type(), "nft");
+        let tx: OwnTransaction = serde_cbor::from_slice(&tx.body()).map_err(|_| ())?;
+        Ok((tx.signer_public, tx.tx.seq))
+    }
+}
+
+pub struct InspectTransaction;
+impl Service for InspectTransaction {}
+impl TxInspector for InspectTransaction {
+    fn inspect_transaction(&self, tx: &Transaction) -> Result<InspectedPayload, String> {
+        match tx.tx_type() {
+            "nft" => inspect_signed_transaction(tx.body(), |action: &TxNft| match action {
+                TxNft::Mint {
+                    ..
+                } => "mint_nft",
+                TxNft::Transfer {
+                    ..
+                } => "transfer_nft",
+                TxNft::Burn {
+                    ..
+                } => "burn_nft",
+            }),
+            tx_type => Err(format!("Unexpected tx_type: {}", tx_type)),
+        }
+    }
+}
+
+/// The id of the asset minted by `creator` with the transaction of `tx_hash`.
+pub fn asset_id(creator: &Public, tx_hash: &H256) -> H256 {
+    state_key(&(creator, tx_hash), b"Nft-Module-Asset-Id")
+}
+
+fn state_key<T: Serialize>(key: &T, postfix: &[u8]) -> H256 {
+    blake256(&{
+        let mut v = serde_cbor::to_vec(key).unwrap();
+        v.extend_from_slice(postfix);
+        v
+    } as &[u8])
+}
+
+pub fn get_state_key_asset(id: &H256) -> H256 {
+    state_key(id, b"Nft-Module-Asset")
+}
+
+// The indices below are lists kept under a prefix: the length under the prefix alone,
+// and the items under the prefix with their positions.
+
+pub fn get_state_key_owned_count(owner: &Public) -> H256 {
+    state_key(owner, b"Nft-Module-Owned-Count")
+}
+
+pub fn get_state_key_owned(owner: &Public, index: u64) -> H256 {
+    state_key(&(owner, index), b"Nft-Module-Owned")
+}
+
+pub fn get_state_key_history(asset: &H256, index: u64) -> H256 {
+    state_key(&(asset, index), b"Nft-Module-History")
+}
+
+pub fn get_state_key_account_history_count(public: &Public) -> H256 {
+    state_key(public, b"Nft-Module-Account-History-Count")
+}
+
+/// The key of a reference to a transfer record, `(asset, index in its history)`, of the transfers from or to `public`.
+pub fn get_state_key_account_history(public: &Public, index: u64) -> H256 {
+    state_key(&(public, index), b"Nft-Module-Account-History")
+}
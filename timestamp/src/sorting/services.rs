@@ -19,18 +19,27 @@ use super::ServiceHandler;
 use crate::common::*;
 pub use ckey::{Ed25519Private as Private, Ed25519Public as Public};
 use coordinator::module::*;
-use coordinator::TransactionWithMetadata;
+use coordinator::{Transaction, TransactionWithMetadata};
 use std::collections::HashMap;
 
 impl ServiceHandler {
-    fn account_and_seq_from_tx(&self, tx: &TransactionWithMetadata) -> Option<(Public, TxSeq)> {
+    fn account_and_seq_from_tx(&self, tx: &Transaction) -> Option<(Public, TxSeq)> {
+        // A composite transaction is ordered by its first sub-transaction.
+        let first_sub_transaction;
+        let tx = if tx.is_composite() {
+            first_sub_transaction = tx.sub_transactions()?.swap_remove(0);
+            &first_sub_transaction
+        } else {
+            tx
+        };
+
         let guard = self.get_account_and_seqs.read();
-        let get_account_and_seq: &dyn GetAccountAndSeq = match guard.get(tx.tx.tx_type()) {
+        let get_account_and_seq: &dyn GetAccountAndSeq = match guard.get(tx.tx_type()) {
             Some(get_account_and_seq) => get_account_and_seq.as_ref(),
             None => return None,
         };
 
-        match get_account_and_seq.get_account_and_seq(&tx.tx) {
+        match get_account_and_seq.get_account_and_seq(tx) {
             Ok((public, seq)) => Some((public, seq)),
             _ => None,
         }
@@ -45,7 +54,7 @@ impl TxSorter for ServiceHandler {
         let mut invalid: Vec<usize> = Vec::new();
 
         for (i, tx) in txs.iter().enumerate() {
-            if let Some((public, seq)) = self.account_and_seq_from_tx(tx) {
+            if let Some((public, seq)) = self.account_and_seq_from_tx(&tx.tx) {
                 if let Some(valid) = accounts.get_mut(&public) {
                     valid.push((seq, i));
                 } else {
@@ -79,6 +88,19 @@ impl TxSorter for ServiceHandler {
         SortedTxs {
             sorted,
             invalid,
+            priority_tx_types: Vec::new(),
+        }
+    }
+
+    // The transactions of this application charge no fee.
+    fn sorting_metadata(&self, tx: &Transaction) -> TxSortingMetadata {
+        match self.account_and_seq_from_tx(tx) {
+            Some((signer, seq)) => TxSortingMetadata {
+                signer: Some(signer),
+                fee: None,
+                seq: Some(seq),
+            },
+            None => TxSortingMetadata::default(),
         }
     }
 }
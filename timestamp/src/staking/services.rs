@@ -65,7 +65,7 @@ impl InitChain for ServiceHandler {
 impl UpdateChain for ServiceHandler {
     fn update_chain(&self, session: SessionId) -> (Option<CompactValidatorSet>, Option<ConsensusParams>) {
         let validator_set = self.track_validator_set(session);
-        let consensus_params = ConsensusParams::default_for_test();
-        (Some(validator_set), Some(consensus_params))
+        // This module has no params-change transaction, so keep the params set at init_chain().
+        (Some(validator_set), None)
     }
 }
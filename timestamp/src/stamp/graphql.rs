@@ -0,0 +1,137 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::merkle::{InclusionProof, ProofStep};
+use super::state_machine::GetStamp;
+use super::types::*;
+use super::{ServiceHandler, StateMachine};
+use crate::common::*;
+pub use ckey::{Ed25519Private as Private, Ed25519Public as Public};
+use coordinator::module::*;
+use remote_trait_object::Service;
+use std::sync::Arc;
+
+struct GraphQlRoot {
+    state_machine: StateMachine,
+}
+
+#[async_graphql::Object]
+impl GraphQlRoot {
+    async fn stamp(&self, hash: GqlH256) -> Option<Stamp> {
+        self.state_machine
+            .execute_access(GetStamp {
+                hash: &hash.0,
+            })
+            .ok()
+    }
+
+    /// Returns the proof that `document` is included in the stamped batch of `documents`.
+    async fn inclusion_proof(&self, documents: Vec<GqlH256>, document: GqlH256) -> Option<StampProof> {
+        let documents: Vec<_> = documents.into_iter().map(|x| x.0).collect();
+        let index = documents.iter().position(|x| *x == document.0)?;
+        let proof = InclusionProof::new(&documents, index)?;
+        let root = proof.root();
+        let stamp = self
+            .state_machine
+            .execute_access(GetStamp {
+                hash: &root,
+            })
+            .ok()?;
+        if stamp.batch_size != Some(documents.len() as u64) {
+            return None
+        }
+        Some(StampProof {
+            stamp,
+            root,
+            proof,
+        })
+    }
+}
+
+#[async_graphql::Object]
+impl Stamp {
+    async fn stamper(&self) -> String {
+        hex::encode(self.stamper.as_ref())
+    }
+
+    async fn batch_size(&self) -> Option<u64> {
+        self.batch_size
+    }
+}
+
+struct StampProof {
+    stamp: Stamp,
+    root: primitives::H256,
+    proof: InclusionProof,
+}
+
+#[async_graphql::Object]
+impl StampProof {
+    async fn stamp(&self) -> &Stamp {
+        &self.stamp
+    }
+
+    async fn root(&self) -> String {
+        hex::encode(self.root.as_ref())
+    }
+
+    async fn document(&self) -> String {
+        hex::encode(self.proof.document.as_ref())
+    }
+
+    /// Siblings from the leaf up to the root, each prefixed with "L:" or "R:" for its side.
+    async fn path(&self) -> Vec<String> {
+        self.proof
+            .path
+            .iter()
+            .map(|step| match step {
+                ProofStep::Left(sibling) => format!("L:{}", hex::encode(sibling.as_ref())),
+                ProofStep::Right(sibling) => format!("R:{}", hex::encode(sibling.as_ref())),
+            })
+            .collect()
+    }
+}
+
+pub struct GraphQlRequestHandler {
+    service_handler: Arc<ServiceHandler>,
+
+    /// A runtime to process the asynchronous result of the query
+    tokio_runtime: tokio::runtime::Runtime,
+}
+
+impl GraphQlRequestHandler {
+    pub(super) fn new(service_handler: Arc<ServiceHandler>) -> Self {
+        Self {
+            service_handler,
+            tokio_runtime: tokio::runtime::Runtime::new().unwrap(),
+        }
+    }
+}
+
+impl Service for GraphQlRequestHandler {}
+
+impl HandleGraphQlRequest for GraphQlRequestHandler {
+    fn execute(&self, session: SessionId, query: &str, variables: &str) -> String {
+        handle_gql_query(
+            self.tokio_runtime.handle(),
+            GraphQlRoot {
+                state_machine: self.service_handler.create_state_machine(session),
+            },
+            query,
+            variables,
+        )
+    }
+}
@@ -0,0 +1,150 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! A binary Merkle tree over document hashes, used to stamp many documents with a single `TxStamp`.
+//!
+//! Leaves and inner nodes are hashed with distinct prefixes so that an inner node can never be
+//! presented as a document. A node without a sibling is carried up to the next level unchanged.
+
+use ccrypto::blake256;
+use primitives::H256;
+use serde::{Deserialize, Serialize};
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+fn hash_leaf(document: &H256) -> H256 {
+    let mut v = Vec::with_capacity(33);
+    v.push(LEAF_PREFIX);
+    v.extend_from_slice(document.as_bytes());
+    blake256(v)
+}
+
+fn hash_node(left: &H256, right: &H256) -> H256 {
+    let mut v = Vec::with_capacity(65);
+    v.push(NODE_PREFIX);
+    v.extend_from_slice(left.as_bytes());
+    v.extend_from_slice(right.as_bytes());
+    blake256(v)
+}
+
+fn next_level(level: &[H256]) -> Vec<H256> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => hash_node(left, right),
+            [single] => *single,
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+/// Returns the Merkle root of the given documents, or `None` if there is no document.
+pub fn merkle_root(documents: &[H256]) -> Option<H256> {
+    if documents.is_empty() {
+        return None
+    }
+    let mut level: Vec<H256> = documents.iter().map(hash_leaf).collect();
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    Some(level[0])
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProofStep {
+    /// The sibling is on the left of the current node.
+    Left(H256),
+    /// The sibling is on the right of the current node.
+    Right(H256),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct InclusionProof {
+    pub document: H256,
+    /// Siblings from the leaf level up to the root.
+    pub path: Vec<ProofStep>,
+}
+
+impl InclusionProof {
+    /// Builds the proof for `documents[index]`.
+    pub fn new(documents: &[H256], index: usize) -> Option<Self> {
+        let document = *documents.get(index)?;
+        let mut path = Vec::new();
+        let mut level: Vec<H256> = documents.iter().map(hash_leaf).collect();
+        let mut index = index;
+        while level.len() > 1 {
+            if index % 2 == 1 {
+                path.push(ProofStep::Left(level[index - 1]));
+            } else if let Some(sibling) = level.get(index + 1) {
+                path.push(ProofStep::Right(*sibling));
+            }
+            level = next_level(&level);
+            index /= 2;
+        }
+        Some(Self {
+            document,
+            path,
+        })
+    }
+
+    pub fn root(&self) -> H256 {
+        self.path.iter().fold(hash_leaf(&self.document), |node, step| match step {
+            ProofStep::Left(sibling) => hash_node(sibling, &node),
+            ProofStep::Right(sibling) => hash_node(&node, sibling),
+        })
+    }
+
+    pub fn verify(&self, root: &H256) -> bool {
+        self.root() == *root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn documents(n: usize) -> Vec<H256> {
+        (0..n).map(|i| blake256(format!("document{}", i))).collect()
+    }
+
+    #[test]
+    fn empty_batch_has_no_root() {
+        assert_eq!(merkle_root(&[]), None);
+    }
+
+    #[test]
+    fn every_document_is_included() {
+        for n in 1..=17 {
+            let documents = documents(n);
+            let root = merkle_root(&documents).unwrap();
+            for i in 0..n {
+                let proof = InclusionProof::new(&documents, i).unwrap();
+                assert!(proof.verify(&root), "document {} of {}", i, n);
+            }
+            assert_eq!(InclusionProof::new(&documents, n), None);
+        }
+    }
+
+    #[test]
+    fn proof_of_other_document_fails() {
+        let documents = documents(5);
+        let root = merkle_root(&documents).unwrap();
+        let mut proof = InclusionProof::new(&documents, 2).unwrap();
+        proof.document = blake256("not stamped");
+        assert!(!proof.verify(&root));
+    }
+}
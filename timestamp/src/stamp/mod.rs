@@ -14,14 +14,21 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+mod graphql;
+pub mod merkle;
 mod module;
 pub mod services;
+mod state_machine;
 mod types;
 
+use super::common::state_machine::StateMachine;
+use super::common::StateManager;
+use coordinator::module::{SessionId, Stateful};
 pub use module::Module;
 use parking_lot::RwLock;
 use primitives::H256;
 use remote_trait_object::raw_exchange::import_null_proxy;
+use std::sync::Arc;
 
 struct Config {
     token_issuer: H256,
@@ -29,6 +36,7 @@ struct Config {
 
 struct ServiceHandler {
     config: Config,
+    state_manager: Arc<RwLock<StateManager>>,
     account_manager: RwLock<Box<dyn crate::account::services::AccountManager>>,
     token_manager: RwLock<Box<dyn crate::token::services::TokenManager>>,
 }
@@ -37,6 +45,7 @@ impl ServiceHandler {
     fn new(config: Config) -> Self {
         Self {
             config,
+            state_manager: Arc::new(RwLock::new(StateManager::default())),
             account_manager: RwLock::new(import_null_proxy()),
             token_manager: RwLock::new(import_null_proxy()),
         }
@@ -49,8 +58,16 @@ impl ServiceHandler {
     fn token_manager(&self) -> &RwLock<Box<dyn crate::token::services::TokenManager>> {
         &self.token_manager
     }
+
+    fn create_state_machine(&self, session: SessionId) -> StateMachine {
+        StateMachine::new(self.state_manager.read().get(session))
+    }
+
+    fn get_stateful(&self) -> Arc<RwLock<dyn Stateful>> {
+        Arc::clone(&self.state_manager) as Arc<RwLock<dyn Stateful>>
+    }
 }
 
 impl remote_trait_object::Service for ServiceHandler {}
 
-pub use types::TxStamp;
+pub use types::{Error, Stamp, TxStamp};
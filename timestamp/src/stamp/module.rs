@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use super::graphql::GraphQlRequestHandler;
 use super::Config;
 use super::ServiceHandler;
 use crate::common::*;
@@ -40,6 +41,10 @@ impl UserModule for Module {
 
     fn prepare_service_to_export(&mut self, ctor_name: &str, ctor_arg: &[u8]) -> Skeleton {
         match ctor_name {
+            "stateful" => {
+                assert_empty_arg(ctor_arg).unwrap();
+                Skeleton::new(self.service_handler.get_stateful())
+            }
             "tx-owner" => {
                 assert_empty_arg(ctor_arg).unwrap();
                 Skeleton::new(Arc::clone(&self.service_handler) as Arc<dyn TxOwner>)
@@ -48,10 +53,23 @@ impl UserModule for Module {
                 assert_empty_arg(ctor_arg).unwrap();
                 Skeleton::new(Arc::clone(&self.service_handler) as Arc<dyn InitGenesis>)
             }
+            "anchor-block" => {
+                assert_empty_arg(ctor_arg).unwrap();
+                Skeleton::new(Arc::clone(&self.service_handler) as Arc<dyn AnchorBlock>)
+            }
             "get-account-and-seq" => {
                 assert_empty_arg(ctor_arg).unwrap();
                 Skeleton::new(Box::new(super::types::GetAccountAndSeq) as Box<dyn crate::sorting::GetAccountAndSeq>)
             }
+            "tx-inspector" => {
+                assert_empty_arg(ctor_arg).unwrap();
+                Skeleton::new(Box::new(super::types::InspectTransaction) as Box<dyn TxInspector>)
+            }
+            "handle-graphql-request" => {
+                assert_empty_arg(ctor_arg).unwrap();
+                Skeleton::new(Box::new(GraphQlRequestHandler::new(Arc::clone(&self.service_handler)))
+                    as Box<dyn HandleGraphQlRequest>)
+            }
             _ => panic!("Unsupported ctor_name in prepare_service_to_export() : {}", ctor_name),
         }
     }
@@ -14,12 +14,16 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use super::merkle::merkle_root;
+use super::state_machine::*;
 use super::types::*;
 use super::ServiceHandler;
+use crate::common::witness_size;
 pub use ckey::{Ed25519Private as Private, Ed25519Public as Public};
 use coordinator::module::*;
 use coordinator::types::*;
 use coordinator::{Header, Transaction};
+use primitives::H256;
 use std::collections::HashMap;
 
 enum ExecuteError {
@@ -30,9 +34,9 @@ enum ExecuteError {
     TokenModuleError(crate::token::Error),
     InvalidSequence,
     NotEligibleStamper,
+    InvalidBatchSize,
 }
 
-/// As this module is stateless, we implement execute_tx() right on the ServiceHandler.
 impl ServiceHandler {
     fn excute_tx(&self, session: SessionId, transaction: &Transaction) -> Result<(), ExecuteError> {
         if transaction.tx_type() != "stamp" {
@@ -42,6 +46,9 @@ impl ServiceHandler {
         let tx: OwnTransaction =
             serde_cbor::from_slice(&transaction.body()).map_err(|_| ExecuteError::InvalidFormat)?;
         tx.verify().map_err(|_| ExecuteError::InvalidSign)?;
+        if tx.tx.action.batch_size == Some(0) {
+            return Err(ExecuteError::InvalidBatchSize)
+        }
         if self
             .account_manager
             .read()
@@ -60,6 +67,13 @@ impl ServiceHandler {
             .map_err(ExecuteError::TokenModuleError)?;
         if account.tokens.iter().any(|x| x.issuer == self.config.token_issuer) {
             self.account_manager.read().increase_sequence(session, &tx.signer_public, true).unwrap();
+            self.create_state_machine(session).execute_transition(RecordStamp {
+                hash: &tx.tx.action.hash,
+                stamp: &Stamp {
+                    stamper: tx.signer_public,
+                    batch_size: tx.tx.action.batch_size,
+                },
+            });
             Ok(())
         } else {
             Err(ExecuteError::NotEligibleStamper)
@@ -94,6 +108,7 @@ impl TxOwner for ServiceHandler {
                 ExecuteError::TokenModuleError(_) => Err(()),
                 ExecuteError::InvalidSequence => Err(()),
                 ExecuteError::NotEligibleStamper => Err(()),
+                ExecuteError::InvalidBatchSize => Err(()),
             }
         } else {
             Ok(Default::default())
@@ -105,10 +120,32 @@ impl TxOwner for ServiceHandler {
         assert_eq!(transaction.tx_type(), "stamp");
         let tx: OwnTransaction = serde_cbor::from_slice(&transaction.body()).map_err(|_| todo_fixthis)?;
         tx.verify().map_err(|_| todo_fixthis)?;
+        if tx.tx.action.batch_size == Some(0) {
+            return Err(todo_fixthis)
+        }
         Ok(())
     }
 
+    fn estimate_gas(&self, transaction: &Transaction) -> u64 {
+        transaction.size() as u64
+    }
+
+    fn witness_size(&self, transaction: &Transaction) -> usize {
+        match serde_cbor::from_slice::<OwnTransaction>(&transaction.body()) {
+            Ok(tx) => witness_size(&transaction.body(), &tx.tx),
+            Err(_) => 0,
+        }
+    }
+
     fn block_closed(&self, _session: SessionId) -> Result<Vec<Event>, CloseBlockError> {
         Ok(Vec::new())
     }
 }
+
+impl AnchorBlock for ServiceHandler {
+    /// Anchors the Merkle root of the hashes stamped in the block.
+    fn external_anchor(&self, session: SessionId) -> Option<H256> {
+        let block_stamps = self.create_state_machine(session).execute_transition(TakeBlockStamps);
+        merkle_root(&block_stamps)
+    }
+}
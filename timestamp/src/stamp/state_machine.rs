@@ -0,0 +1,75 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::types::*;
+use crate::common::state_machine::{StateAccess, StateTransition};
+use crate::common::StateCache;
+pub use ckey::{Ed25519Private as Private, Ed25519Public as Public};
+use primitives::H256;
+
+pub struct GetStamp<'a> {
+    pub hash: &'a H256,
+}
+
+impl<'a> StateAccess for GetStamp<'a> {
+    type Outcome = Result<Stamp, Error>;
+
+    fn execute(self, state: &StateCache) -> Result<Stamp, Error> {
+        state.get_decoded(get_state_key(self.hash).as_bytes()).ok_or(Error::NoSuchStamp)?.map_err(|_| Error::InvalidKey)
+    }
+}
+
+/// Records the stamp unless the same hash was stamped before, so the earliest stamp is kept.
+pub struct RecordStamp<'a> {
+    pub hash: &'a H256,
+    pub stamp: &'a Stamp,
+}
+
+impl<'a> StateTransition for RecordStamp<'a> {
+    type Outcome = ();
+
+    fn execute(self, state: &mut StateCache) {
+        let key = get_state_key(self.hash);
+        if state.has(key.as_bytes()) {
+            return
+        }
+        state.set_encoded(key.as_bytes(), self.stamp);
+
+        let block_stamps_key = get_block_stamps_key();
+        let mut block_stamps: Vec<H256> =
+            state.get_decoded(block_stamps_key.as_bytes()).map(Result::unwrap).unwrap_or_default();
+        block_stamps.push(*self.hash);
+        state.set_encoded(block_stamps_key.as_bytes(), &block_stamps);
+    }
+}
+
+/// Takes out the hashes stamped in the current block.
+/// They are removed from the state, so nothing is left after closing the block.
+pub struct TakeBlockStamps;
+
+impl StateTransition for TakeBlockStamps {
+    type Outcome = Vec<H256>;
+
+    fn execute(self, state: &mut StateCache) -> Vec<H256> {
+        let key = get_block_stamps_key();
+        let block_stamps = match state.get_decoded(key.as_bytes()) {
+            Some(block_stamps) => block_stamps.unwrap(),
+            None => return Vec::new(),
+        };
+        state.remove(key.as_bytes());
+        block_stamps
+    }
+}
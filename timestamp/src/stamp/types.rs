@@ -15,7 +15,10 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::common::*;
+use ccrypto::blake256;
 pub use ckey::{Ed25519Private as Private, Ed25519Public as Public};
+use coordinator::module::TxInspector;
+use coordinator::types::InspectedPayload;
 use coordinator::Transaction;
 use primitives::H256;
 use remote_trait_object::Service;
@@ -23,7 +26,11 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct TxStamp {
+    /// The hash of a single document, or the Merkle root of a batch of documents.
     pub hash: H256,
+    /// The number of documents under `hash` if it is a Merkle root of a batch.
+    #[serde(default)]
+    pub batch_size: Option<u64>,
 }
 
 impl Action for TxStamp {}
@@ -41,3 +48,46 @@ impl crate::sorting::GetAccountAndSeq for GetAccountAndSeq {
         Ok((tx.signer_public, tx.tx.seq))
     }
 }
+
+pub struct InspectTransaction;
+
+impl Service for InspectTransaction {}
+
+impl TxInspector for InspectTransaction {
+    fn inspect_transaction(&self, tx: &Transaction) -> Result<InspectedPayload, String> {
+        assert_eq!(tx.tx_type(), "stamp");
+        inspect_signed_transaction(tx.body(), |stamp: &TxStamp| {
+            if stamp.batch_size.is_some() {
+                "stamp_batch"
+            } else {
+                "stamp"
+            }
+        })
+    }
+}
+
+/// A stamp recorded in the state.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Stamp {
+    pub stamper: Public,
+    pub batch_size: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum Error {
+    NoSuchStamp,
+    InvalidKey,
+}
+
+/// The key of the hashes stamped in the current block, which are anchored on closing it.
+pub fn get_block_stamps_key() -> H256 {
+    blake256(b"Stamp-Module-Block-Stamps")
+}
+
+pub fn get_state_key(hash: &H256) -> H256 {
+    blake256(&{
+        let mut v = serde_cbor::to_vec(&hash).unwrap();
+        v.extend_from_slice(b"Stamp-Module-Stamp");
+        v
+    } as &[u8])
+}
@@ -31,6 +31,7 @@ struct ServiceHandler {
     state_manager: Arc<RwLock<StateManager>>,
 
     account_manager: RwLock<Box<dyn crate::account::services::AccountManager>>,
+    packet_sender: RwLock<Box<dyn crate::ibc::services::PacketSender>>,
 }
 
 impl ServiceHandler {
@@ -38,6 +39,7 @@ impl ServiceHandler {
         Self {
             state_manager: Arc::new(RwLock::new(StateManager::default())),
             account_manager: RwLock::new(remote_trait_object::raw_exchange::import_null_proxy()),
+            packet_sender: RwLock::new(remote_trait_object::raw_exchange::import_null_proxy()),
         }
     }
 
@@ -56,5 +58,5 @@ impl ServiceHandler {
 
 impl remote_trait_object::Service for ServiceHandler {}
 
-pub use types::ActionTransferToken;
 pub use types::Error;
+pub use types::{ActionSendToken, ActionTransferToken, TokenPacket};
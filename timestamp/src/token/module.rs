@@ -53,6 +53,14 @@ impl UserModule for Module {
                 assert_empty_arg(ctor_arg).unwrap();
                 Skeleton::new(Box::new(super::types::GetAccountAndSeq) as Box<dyn crate::sorting::GetAccountAndSeq>)
             }
+            "tx-inspector" => {
+                assert_empty_arg(ctor_arg).unwrap();
+                Skeleton::new(Box::new(super::types::InspectTransaction) as Box<dyn TxInspector>)
+            }
+            "handle-packet" => {
+                assert_empty_arg(ctor_arg).unwrap();
+                Skeleton::new(Arc::clone(&self.service_handler) as Arc<dyn crate::ibc::services::HandlePacket>)
+            }
             "handle-graphql-request" => {
                 assert_empty_arg(ctor_arg).unwrap();
                 Skeleton::new(Box::new(GraphQlRequestHandler::new(Arc::clone(&self.service_handler)))
@@ -67,6 +75,9 @@ impl UserModule for Module {
             "account-manager" => {
                 *(self.service_handler.account_manager().write()) = import_service_from_handle(rto_context, handle);
             }
+            "packet-sender" => {
+                *(self.service_handler.packet_sender.write()) = import_service_from_handle(rto_context, handle);
+            }
             _ => panic!("Unsupported name in import_service() : {}", name),
         }
     }
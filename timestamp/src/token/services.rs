@@ -17,6 +17,8 @@
 use super::state_machine::*;
 use super::types::*;
 use super::ServiceHandler;
+use crate::common::witness_size;
+use crate::ibc::services::HandlePacket;
 pub use ckey::{Ed25519Private as Private, Ed25519Public as Public};
 use coordinator::module::*;
 use coordinator::types::*;
@@ -33,6 +35,7 @@ pub trait TokenManager: Service {
 
     // Mutable accesses
     fn issue_token(&self, session: SessionId, issuer: &H256, receiver: &Public) -> Result<(), Error>;
+    fn take_token(&self, session: SessionId, issuer: &H256, owner: &Public) -> Result<(), Error>;
 }
 
 impl TokenManager for ServiceHandler {
@@ -58,6 +61,62 @@ impl TokenManager for ServiceHandler {
             receiver,
         })
     }
+
+    fn take_token(&self, session: SessionId, issuer: &H256, owner: &Public) -> Result<(), Error> {
+        let state_machine = self.create_state_machine(session);
+        state_machine.execute_transition(TakeToken {
+            issuer,
+            owner,
+        })
+    }
+}
+
+impl ServiceHandler {
+    fn send_token(&self, session: SessionId, transaction: &Transaction) -> Result<(), ExecuteError> {
+        let tx: SendTransaction =
+            serde_cbor::from_slice(&transaction.body()).map_err(|_| ExecuteError::InvalidFormat)?;
+        tx.verify().map_err(|_| ExecuteError::InvalidSign)?;
+        if self
+            .account_manager
+            .read()
+            .get_account(session, &tx.signer_public, true)
+            .map_err(ExecuteError::AccountModuleError)?
+            .seq
+            != tx.tx.seq
+        {
+            return Err(ExecuteError::InvalidSequence)
+        }
+
+        let ActionSendToken {
+            counterparty,
+            receiver,
+            issuer,
+        } = &tx.tx.action;
+        let packet = self.create_state_machine(session).execute_transition(SendToken {
+            sender: &tx.signer_public,
+            counterparty,
+            receiver,
+            issuer,
+        })?;
+        self.packet_sender
+            .read()
+            .send_packet(session, counterparty, "token", &serde_cbor::to_vec(&packet).unwrap())
+            .map_err(ExecuteError::IbcModuleError)?;
+        self.account_manager.read().increase_sequence(session, &tx.signer_public, true).unwrap();
+        Ok(())
+    }
+}
+
+impl HandlePacket for ServiceHandler {
+    fn handle_packet(&self, session: SessionId, counterparty: &str, payload: &[u8]) -> Result<(), String> {
+        let packet: TokenPacket = serde_cbor::from_slice(payload).map_err(|err| err.to_string())?;
+        self.create_state_machine(session)
+            .execute_transition(ReceiveToken {
+                counterparty,
+                packet: &packet,
+            })
+            .map_err(|err| format!("{:?}", err))
+    }
 }
 
 impl TxOwner for ServiceHandler {
@@ -74,11 +133,16 @@ impl TxOwner for ServiceHandler {
             self.account_manager.read().increase_sequence(session, public, true).unwrap();
         };
 
-        if let Err(error) = state_machine.execute_transition(ExecuteTransaction {
-            tx: transaction,
-            get_sequence: &get_sequence,
-            increase_sequence: &increase_sequence,
-        }) {
+        let result = if transaction.tx_type() == "token-out" {
+            self.send_token(session, transaction)
+        } else {
+            state_machine.execute_transition(ExecuteTransaction {
+                tx: transaction,
+                get_sequence: &get_sequence,
+                increase_sequence: &increase_sequence,
+            })
+        };
+        if let Err(error) = result {
             match error {
                 ExecuteError::InvalidMetadata => Err(()),
                 ExecuteError::InvalidSign => Err(()),
@@ -88,6 +152,9 @@ impl TxOwner for ServiceHandler {
                 ExecuteError::NoSuchAccount => Err(()),
                 ExecuteError::InvalidKey => Err(()),
                 ExecuteError::NoToken => Err(()),
+                ExecuteError::ForeignVoucher => Err(()),
+                ExecuteError::NoEscrow => Err(()),
+                ExecuteError::IbcModuleError(_) => Err(()),
             }
         } else {
             Ok(Default::default())
@@ -96,10 +163,35 @@ impl TxOwner for ServiceHandler {
 
     fn check_transaction(&self, transaction: &Transaction) -> Result<(), coordinator::types::ErrorCode> {
         let todo_fixthis: coordinator::types::ErrorCode = 3;
-        assert_eq!(transaction.tx_type(), "stamp");
-        let tx: OwnTransaction = serde_cbor::from_slice(&transaction.body()).map_err(|_| todo_fixthis)?;
-        tx.verify().map_err(|_| todo_fixthis)?;
-        Ok(())
+        match transaction.tx_type() {
+            "token" => {
+                let tx: OwnTransaction = serde_cbor::from_slice(&transaction.body()).map_err(|_| todo_fixthis)?;
+                tx.verify().map_err(|_| todo_fixthis)
+            }
+            "token-out" => {
+                let tx: SendTransaction = serde_cbor::from_slice(&transaction.body()).map_err(|_| todo_fixthis)?;
+                tx.verify().map_err(|_| todo_fixthis)
+            }
+            _ => Err(todo_fixthis),
+        }
+    }
+
+    fn estimate_gas(&self, transaction: &Transaction) -> u64 {
+        transaction.size() as u64
+    }
+
+    fn witness_size(&self, transaction: &Transaction) -> usize {
+        if transaction.tx_type() == "token-out" {
+            match serde_cbor::from_slice::<SendTransaction>(&transaction.body()) {
+                Ok(tx) => witness_size(&transaction.body(), &tx.tx),
+                Err(_) => 0,
+            }
+        } else {
+            match serde_cbor::from_slice::<OwnTransaction>(&transaction.body()) {
+                Ok(tx) => witness_size(&transaction.body(), &tx.tx),
+                Err(_) => 0,
+            }
+        }
     }
 
     fn block_closed(&self, _session: SessionId) -> Result<Vec<Event>, CloseBlockError> {
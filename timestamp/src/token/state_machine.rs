@@ -16,8 +16,8 @@
 
 use super::types::*;
 use crate::common::state_machine::{StateAccess, StateTransition};
+use crate::common::StateCache;
 pub use ckey::{Ed25519Private as Private, Ed25519Public as Public};
-use coordinator::context::SubStorageAccess;
 use coordinator::Transaction;
 use primitives::H256;
 use std::collections::BTreeSet;
@@ -34,27 +34,26 @@ pub struct GetAccount<'a> {
 impl<'a> StateAccess for GetAccount<'a> {
     type Outcome = Result<Account, Error>;
 
-    fn execute(self, state: &dyn SubStorageAccess) -> Result<Account, Error> {
-        let bytes = match state.get(get_state_key(self.public).as_bytes()) {
-            Some(bytes) => bytes,
+    fn execute(self, state: &StateCache) -> Result<Account, Error> {
+        match state.get_decoded(get_state_key(self.public).as_bytes()) {
+            Some(account) => account.map_err(|_| Error::InvalidKey),
             None => {
                 if self.default {
-                    return Ok(Default::default())
+                    Ok(Default::default())
                 } else {
-                    return Err(Error::NoSuchAccount)
+                    Err(Error::NoSuchAccount)
                 }
             }
-        };
-        Ok(serde_cbor::from_slice(&bytes).map_err(|_| Error::InvalidKey)?)
+        }
     }
 }
 
-fn set_account(state: &mut dyn SubStorageAccess, key: &Public, account: &Account) {
-    state.set(get_state_key(key).as_bytes(), serde_cbor::to_vec(account).unwrap());
+fn set_account(state: &mut StateCache, key: &Public, account: &Account) {
+    state.set_encoded(get_state_key(key).as_bytes(), account);
 }
 
-fn set_owning_accounts_with_issuer(state: &mut dyn SubStorageAccess, issuer: &H256, set: BTreeSet<Public>) {
-    state.set(get_state_key_account_set(issuer).as_bytes(), serde_cbor::to_vec(&set).unwrap());
+fn set_owning_accounts_with_issuer(state: &mut StateCache, issuer: &H256, set: BTreeSet<Public>) {
+    state.set_encoded(get_state_key_account_set(issuer).as_bytes(), &set);
 }
 
 pub struct IssueToken<'a> {
@@ -65,7 +64,7 @@ pub struct IssueToken<'a> {
 impl<'a> StateTransition for IssueToken<'a> {
     type Outcome = Result<(), Error>;
 
-    fn execute(self, state: &mut dyn SubStorageAccess) -> Result<(), Error> {
+    fn execute(self, state: &mut StateCache) -> Result<(), Error> {
         let mut account = GetAccount {
             public: self.receiver,
             default: true,
@@ -86,6 +85,37 @@ impl<'a> StateTransition for IssueToken<'a> {
     }
 }
 
+/// Removes a token of the issuer from the owner's account, for a module holding it in escrow.
+/// The module gives it back with `IssueToken`.
+pub struct TakeToken<'a> {
+    pub issuer: &'a H256,
+    pub owner: &'a Public,
+}
+
+impl<'a> StateTransition for TakeToken<'a> {
+    type Outcome = Result<(), Error>;
+
+    fn execute(self, state: &mut StateCache) -> Result<(), Error> {
+        let mut account = GetAccount {
+            public: self.owner,
+            default: false,
+        }
+        .execute(state)?;
+        let index = account.tokens.iter().position(|token| token.issuer == *self.issuer).ok_or(Error::NoToken)?;
+        account.tokens.remove(index);
+        if account.tokens.iter().all(|token| token.issuer != *self.issuer) {
+            let mut set = GetOwningAccountsWithIssuer {
+                issuer: self.issuer,
+            }
+            .execute(state)?;
+            set.remove(self.owner);
+            set_owning_accounts_with_issuer(state, self.issuer, set);
+        }
+        set_account(state, self.owner, &account);
+        Ok(())
+    }
+}
+
 pub struct GetOwningAccountsWithIssuer<'a> {
     pub issuer: &'a H256,
 }
@@ -93,9 +123,9 @@ pub struct GetOwningAccountsWithIssuer<'a> {
 impl<'a> StateAccess for GetOwningAccountsWithIssuer<'a> {
     type Outcome = Result<BTreeSet<Public>, Error>;
 
-    fn execute(self, state: &dyn SubStorageAccess) -> Result<BTreeSet<Public>, Error> {
-        Ok(if let Some(x) = state.get(get_state_key_account_set(self.issuer).as_bytes()) {
-            serde_cbor::from_slice(&x).map_err(|_| Error::InvalidKey)?
+    fn execute(self, state: &StateCache) -> Result<BTreeSet<Public>, Error> {
+        Ok(if let Some(set) = state.get_decoded(get_state_key_account_set(self.issuer).as_bytes()) {
+            set.map_err(|_| Error::InvalidKey)?
         } else {
             BTreeSet::new()
         })
@@ -112,6 +142,9 @@ pub(super) enum ExecuteError {
     NoSuchAccount,
     InvalidKey,
     NoToken,
+    ForeignVoucher,
+    NoEscrow,
+    IbcModuleError(crate::ibc::Error),
 }
 
 impl From<Error> for ExecuteError {
@@ -119,6 +152,9 @@ impl From<Error> for ExecuteError {
         match e {
             Error::InvalidKey => ExecuteError::InvalidKey,
             Error::NoSuchAccount => ExecuteError::NoSuchAccount,
+            Error::ForeignVoucher => ExecuteError::ForeignVoucher,
+            Error::NoEscrow => ExecuteError::NoEscrow,
+            Error::NoToken => ExecuteError::NoToken,
         }
     }
 }
@@ -132,7 +168,7 @@ pub(super) struct ExecuteTransaction<'a, 'b> {
 impl<'a, 'b> StateTransition for ExecuteTransaction<'a, 'b> {
     type Outcome = Result<(), ExecuteError>;
 
-    fn execute(self, state: &mut dyn SubStorageAccess) -> Result<(), ExecuteError> {
+    fn execute(self, state: &mut StateCache) -> Result<(), ExecuteError> {
         if self.tx.tx_type() != "token" {
             return Err(ExecuteError::InvalidMetadata)
         }
@@ -147,10 +183,10 @@ impl<'a, 'b> StateTransition for ExecuteTransaction<'a, 'b> {
             issuer,
         } = tx.tx.action;
 
-        let mut sender_account: Account = serde_cbor::from_slice(
-            &state.get(get_state_key(&tx.signer_public).as_bytes()).ok_or(ExecuteError::NoSuchAccount)?,
-        )
-        .map_err(|_| ExecuteError::InvalidKey)?;
+        let mut sender_account: Account = state
+            .get_decoded(get_state_key(&tx.signer_public).as_bytes())
+            .ok_or(ExecuteError::NoSuchAccount)?
+            .map_err(|_| ExecuteError::InvalidKey)?;
 
         let mut found = None;
         for (i, token) in sender_account.tokens.iter().enumerate() {
@@ -188,3 +224,107 @@ impl<'a, 'b> StateTransition for ExecuteTransaction<'a, 'b> {
         Ok(())
     }
 }
+
+fn get_escrow(state: &StateCache, counterparty: &str, issuer: &H256) -> Result<u64, Error> {
+    state
+        .get_decoded(get_state_key_escrow(counterparty, issuer).as_bytes())
+        .transpose()
+        .map(Option::unwrap_or_default)
+        .map_err(|_| Error::InvalidKey)
+}
+
+fn get_voucher_origin(state: &StateCache, voucher_issuer: &H256) -> Result<Option<VoucherOrigin>, Error> {
+    state
+        .get_decoded(get_state_key_voucher_origin(voucher_issuer).as_bytes())
+        .transpose()
+        .map_err(|_| Error::InvalidKey)
+}
+
+/// Takes a token out of the sender's account for `counterparty`, and returns the packet that sends it.
+pub(super) struct SendToken<'a> {
+    pub sender: &'a Public,
+    pub counterparty: &'a str,
+    pub receiver: &'a Public,
+    pub issuer: &'a H256,
+}
+
+impl<'a> StateTransition for SendToken<'a> {
+    type Outcome = Result<TokenPacket, ExecuteError>;
+
+    fn execute(self, state: &mut StateCache) -> Result<TokenPacket, ExecuteError> {
+        let mut account = GetAccount {
+            public: self.sender,
+            default: false,
+        }
+        .execute(state)?;
+        let index =
+            account.tokens.iter().position(|token| token.issuer == *self.issuer).ok_or(ExecuteError::NoToken)?;
+        let mut set = GetOwningAccountsWithIssuer {
+            issuer: self.issuer,
+        }
+        .execute(state)?;
+
+        let packet = match get_voucher_origin(state, self.issuer)? {
+            Some(origin) => {
+                if origin.counterparty != self.counterparty {
+                    return Err(ExecuteError::ForeignVoucher)
+                }
+                TokenPacket {
+                    receiver: *self.receiver,
+                    issuer: origin.issuer,
+                    returning: true,
+                }
+            }
+            None => {
+                let escrow = get_escrow(state, self.counterparty, self.issuer)?;
+                state.set_encoded(get_state_key_escrow(self.counterparty, self.issuer).as_bytes(), &(escrow + 1));
+                TokenPacket {
+                    receiver: *self.receiver,
+                    issuer: *self.issuer,
+                    returning: false,
+                }
+            }
+        };
+
+        account.tokens.remove(index);
+        if account.tokens.iter().all(|token| token.issuer != *self.issuer) {
+            assert!(set.remove(self.sender));
+        }
+        set_owning_accounts_with_issuer(state, self.issuer, set);
+        set_account(state, self.sender, &account);
+        Ok(packet)
+    }
+}
+
+/// Gives the receiver of a packet from `counterparty` either the escrowed token or a voucher for it.
+pub(super) struct ReceiveToken<'a> {
+    pub counterparty: &'a str,
+    pub packet: &'a TokenPacket,
+}
+
+impl<'a> StateTransition for ReceiveToken<'a> {
+    type Outcome = Result<(), Error>;
+
+    fn execute(self, state: &mut StateCache) -> Result<(), Error> {
+        let issuer = if self.packet.returning {
+            let escrow = get_escrow(state, self.counterparty, &self.packet.issuer)?;
+            if escrow == 0 {
+                return Err(Error::NoEscrow)
+            }
+            state.set_encoded(get_state_key_escrow(self.counterparty, &self.packet.issuer).as_bytes(), &(escrow - 1));
+            self.packet.issuer
+        } else {
+            let issuer = voucher_issuer(self.counterparty, &self.packet.issuer);
+            state.set_encoded(get_state_key_voucher_origin(&issuer).as_bytes(), &VoucherOrigin {
+                counterparty: self.counterparty.to_owned(),
+                issuer: self.packet.issuer,
+            });
+            issuer
+        };
+        IssueToken {
+            issuer: &issuer,
+            receiver: &self.packet.receiver,
+        }
+        .execute(state)
+    }
+}
@@ -17,12 +17,14 @@
 use crate::common::*;
 use ccrypto::blake256;
 pub use ckey::{Ed25519Private as Private, Ed25519Public as Public};
+use coordinator::module::TxInspector;
+use coordinator::types::InspectedPayload;
 use coordinator::Transaction;
 use primitives::H256;
 use remote_trait_object::Service;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Token {
     /// The issuer is recorded in the Token.
     /// Since Token module is general, it can be used from various other modules.
@@ -33,7 +35,7 @@ pub struct Token {
     pub issuer: H256,
 }
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
 pub struct Account {
     pub tokens: Vec<Token>,
 }
@@ -42,6 +44,12 @@ pub struct Account {
 pub enum Error {
     NoSuchAccount,
     InvalidKey,
+    /// The voucher was minted for a token from another chain.
+    ForeignVoucher,
+    /// The counterparty has no escrowed token of the issuer to return.
+    NoEscrow,
+    /// The account has no token of the issuer.
+    NoToken,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -55,13 +63,62 @@ pub struct ActionTransferToken {
 impl Action for ActionTransferToken {}
 pub type OwnTransaction = SignedTransaction<ActionTransferToken>;
 
+/// Sends a token to an account on another chain through the IBC module.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ActionSendToken {
+    pub counterparty: String,
+    pub receiver: Public,
+    pub issuer: H256,
+}
+impl Action for ActionSendToken {}
+pub type SendTransaction = SignedTransaction<ActionSendToken>;
+
+/// The payload of the packets between the token modules of two chains.
+///
+/// A token leaving the chain it was issued on is escrowed there, and the counterparty mints a voucher for it.
+/// The voucher going back is burnt, and the escrowed token is released.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TokenPacket {
+    pub receiver: Public,
+    /// The issuer on the chain the token was issued on.
+    pub issuer: H256,
+    pub returning: bool,
+}
+
+/// Where the token a voucher stands for was issued.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct VoucherOrigin {
+    pub counterparty: String,
+    pub issuer: H256,
+}
+
 pub struct GetAccountAndSeq;
 impl Service for GetAccountAndSeq {}
 impl crate::sorting::GetAccountAndSeq for GetAccountAndSeq {
     fn get_account_and_seq(&self, tx: &Transaction) -> Result<(Public, TxSeq), ()> {
-        assert_eq!(tx.tx_type(), "token");
-        let tx: OwnTransaction = serde_cbor::from_slice(&tx.body()).map_err(|_| ())?;
-        Ok((tx.signer_public, tx.tx.seq))
+        match tx.tx_type() {
+            "token" => {
+                let tx: OwnTransaction = serde_cbor::from_slice(&tx.body()).map_err(|_| ())?;
+                Ok((tx.signer_public, tx.tx.seq))
+            }
+            "token-out" => {
+                let tx: SendTransaction = serde_cbor::from_slice(&tx.body()).map_err(|_| ())?;
+                Ok((tx.signer_public, tx.tx.seq))
+            }
+            tx_type => panic!("Unexpected tx_type: {}", tx_type),
+        }
+    }
+}
+
+pub struct InspectTransaction;
+impl Service for InspectTransaction {}
+impl TxInspector for InspectTransaction {
+    fn inspect_transaction(&self, tx: &Transaction) -> Result<InspectedPayload, String> {
+        match tx.tx_type() {
+            "token" => inspect_signed_transaction(tx.body(), |_: &ActionTransferToken| "transfer_token"),
+            "token-out" => inspect_signed_transaction(tx.body(), |_: &ActionSendToken| "send_token"),
+            tx_type => Err(format!("Unexpected tx_type: {}", tx_type)),
+        }
     }
 }
 
@@ -80,3 +137,28 @@ pub fn get_state_key_account_set(issuer: &H256) -> H256 {
         v
     } as &[u8])
 }
+
+/// The issuer of the vouchers for the tokens of `issuer` from `counterparty`.
+pub fn voucher_issuer(counterparty: &str, issuer: &H256) -> H256 {
+    blake256(&{
+        let mut v = serde_cbor::to_vec(&(counterparty, issuer)).unwrap();
+        v.extend_from_slice(b"Token-Module-Voucher");
+        v
+    } as &[u8])
+}
+
+pub fn get_state_key_voucher_origin(voucher_issuer: &H256) -> H256 {
+    blake256(&{
+        let mut v = serde_cbor::to_vec(&voucher_issuer).unwrap();
+        v.extend_from_slice(b"Token-Module-Voucher-Origin");
+        v
+    } as &[u8])
+}
+
+pub fn get_state_key_escrow(counterparty: &str, issuer: &H256) -> H256 {
+    blake256(&{
+        let mut v = serde_cbor::to_vec(&(counterparty, issuer)).unwrap();
+        v.extend_from_slice(b"Token-Module-Escrow");
+        v
+    } as &[u8])
+}
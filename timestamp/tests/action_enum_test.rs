@@ -0,0 +1,91 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+extern crate codechain_timestamp as timestamp;
+
+use serde::{Deserialize, Serialize};
+use timestamp::common::ActionEnum;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, ActionEnum)]
+#[action(graphql)]
+pub enum SampleAction {
+    #[action(fee = 10)]
+    TransferToken {
+        receiver: String,
+        quantity: i32,
+    },
+    Memo(String),
+    Noop,
+}
+
+struct Describer;
+
+impl SampleActionHandler for Describer {
+    type Context = &'static str;
+    type Output = String;
+
+    fn transfer_token(&self, sender: &'static str, receiver: String, quantity: i32) -> String {
+        format!("{} sends {} to {}", sender, quantity, receiver)
+    }
+
+    fn memo(&self, sender: &'static str, memo: String) -> String {
+        format!("{} writes {}", sender, memo)
+    }
+
+    fn noop(&self, sender: &'static str) -> String {
+        format!("{} does nothing", sender)
+    }
+}
+
+fn transfer() -> SampleAction {
+    SampleAction::TransferToken {
+        receiver: "bob".to_owned(),
+        quantity: 3,
+    }
+}
+
+#[test]
+fn action_names_and_fees() {
+    assert_eq!(transfer().action_name(), "transfer_token");
+    assert_eq!(SampleAction::Memo("hi".to_owned()).action_name(), "memo");
+    assert_eq!(SampleAction::Noop.action_name(), "noop");
+
+    assert_eq!(transfer().fee(), 10);
+    assert_eq!(SampleAction::Noop.fee(), 0);
+}
+
+#[test]
+fn dispatch_calls_the_handler_of_the_variant() {
+    assert_eq!(transfer().dispatch(&Describer, "alice"), "alice sends 3 to bob");
+    assert_eq!(SampleAction::Memo("hi".to_owned()).dispatch(&Describer, "alice"), "alice writes hi");
+    assert_eq!(SampleAction::Noop.dispatch(&Describer, "alice"), "alice does nothing");
+}
+
+#[test]
+fn decode_cbor_encoded_action() {
+    let encoded = serde_cbor::to_vec(&transfer()).unwrap();
+    assert_eq!(SampleAction::decode(&encoded).unwrap(), transfer());
+    assert!(SampleAction::decode(&encoded[1..]).is_err());
+}
+
+#[test]
+fn graphql_input_converts_into_action() {
+    let input = SampleActionTransferTokenInput {
+        receiver: "bob".to_owned(),
+        quantity: 3,
+    };
+    assert_eq!(SampleAction::from(input), transfer());
+}
@@ -15,14 +15,15 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use ccrypto::blake256;
-use ckey::{Ed25519Private as Private, Ed25519Public as Public};
-use coordinator::context::SubStorageAccess;
+use ckey::{Ed25519KeyPair, Ed25519Private as Private, Ed25519Public as Public, KeyPairTrait};
+use coordinator::context::{StorageAccess, SubStorageAccess};
 use coordinator::module::*;
 use coordinator::Coordinator;
 use coordinator::Transaction;
 use primitives::H256;
 use remote_trait_object::ServiceRef;
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use timestamp::common::*;
 
 pub fn tx_hello(public: &Public, private: &Private, seq: u64) -> Transaction {
@@ -41,9 +42,58 @@ pub fn tx_hello(public: &Public, private: &Private, seq: u64) -> Transaction {
     Transaction::new("account".to_owned(), serde_cbor::to_vec(&tx).unwrap())
 }
 
+pub fn tx_multisig(signers: &[Ed25519KeyPair], seq: u64, action: timestamp::account::TxMultisig) -> Transaction {
+    let tx = UserTransaction {
+        seq,
+        network_id: Default::default(),
+        action,
+    };
+    let tx_hash = tx.hash();
+    let tx = MultiSignedTransaction {
+        signatures: signers.iter().map(|x| (*x.public(), ckey::sign(tx_hash.as_bytes(), x.private()))).collect(),
+        tx,
+    };
+    Transaction::new("multisig".to_owned(), serde_cbor::to_vec(&tx).unwrap())
+}
+
+pub fn tx_pay(public: &Public, private: &Private, seq: u64, action: timestamp::account::TxPay) -> Transaction {
+    let tx = UserTransaction {
+        seq,
+        network_id: Default::default(),
+        action,
+    };
+    let tx_hash = tx.hash();
+    let tx = SignedTransaction {
+        signature: ckey::sign(tx_hash.as_bytes(), private),
+        signer_public: *public,
+        tx,
+    };
+    Transaction::new("pay".to_owned(), serde_cbor::to_vec(&tx).unwrap())
+}
+
 pub fn tx_stamp(public: &Public, private: &Private, seq: u64, contents: &str) -> Transaction {
     let tx = timestamp::stamp::TxStamp {
         hash: blake256(contents),
+        batch_size: None,
+    };
+    let tx = UserTransaction {
+        seq,
+        network_id: Default::default(),
+        action: tx,
+    };
+    let tx_hash = tx.hash();
+    let tx = SignedTransaction {
+        signature: ckey::sign(tx_hash.as_bytes(), private),
+        signer_public: *public,
+        tx,
+    };
+    Transaction::new("stamp".to_owned(), serde_cbor::to_vec(&tx).unwrap())
+}
+
+pub fn tx_stamp_batch(public: &Public, private: &Private, seq: u64, documents: &[H256]) -> Transaction {
+    let tx = timestamp::stamp::TxStamp {
+        hash: timestamp::stamp::merkle::merkle_root(documents).unwrap(),
+        batch_size: Some(documents.len() as u64),
     };
     let tx = UserTransaction {
         seq,
@@ -78,6 +128,78 @@ pub fn tx_token_transfer(public: &Public, private: &Private, seq: u64, receiver:
     Transaction::new("token".to_owned(), serde_cbor::to_vec(&tx).unwrap())
 }
 
+pub fn tx_token_send(
+    public: &Public,
+    private: &Private,
+    seq: u64,
+    counterparty: &str,
+    receiver: Public,
+    issuer: H256,
+) -> Transaction {
+    let tx = timestamp::token::ActionSendToken {
+        counterparty: counterparty.to_owned(),
+        receiver,
+        issuer,
+    };
+    let tx = UserTransaction {
+        seq,
+        network_id: Default::default(),
+        action: tx,
+    };
+    let tx_hash = tx.hash();
+    let tx = SignedTransaction {
+        signature: ckey::sign(tx_hash.as_bytes(), private),
+        signer_public: *public,
+        tx,
+    };
+    Transaction::new("token-out".to_owned(), serde_cbor::to_vec(&tx).unwrap())
+}
+
+pub fn tx_nft(public: &Public, private: &Private, seq: u64, action: timestamp::nft::TxNft) -> Transaction {
+    let tx = UserTransaction {
+        seq,
+        network_id: Default::default(),
+        action,
+    };
+    let tx_hash = tx.hash();
+    let tx = SignedTransaction {
+        signature: ckey::sign(tx_hash.as_bytes(), private),
+        signer_public: *public,
+        tx,
+    };
+    Transaction::new("nft".to_owned(), serde_cbor::to_vec(&tx).unwrap())
+}
+
+pub fn tx_dex(public: &Public, private: &Private, seq: u64, action: timestamp::dex::TxDex) -> Transaction {
+    let tx = UserTransaction {
+        seq,
+        network_id: Default::default(),
+        action,
+    };
+    let tx_hash = tx.hash();
+    let tx = SignedTransaction {
+        signature: ckey::sign(tx_hash.as_bytes(), private),
+        signer_public: *public,
+        tx,
+    };
+    Transaction::new("dex".to_owned(), serde_cbor::to_vec(&tx).unwrap())
+}
+
+pub fn tx_ibc(public: &Public, private: &Private, seq: u64, action: timestamp::ibc::TxIbc) -> Transaction {
+    let tx = UserTransaction {
+        seq,
+        network_id: Default::default(),
+        action,
+    };
+    let tx_hash = tx.hash();
+    let tx = SignedTransaction {
+        signature: ckey::sign(tx_hash.as_bytes(), private),
+        signer_public: *public,
+        tx,
+    };
+    Transaction::new("ibc".to_owned(), serde_cbor::to_vec(&tx).unwrap())
+}
+
 #[derive(Default)]
 pub struct TestStorage {
     map: HashMap<Vec<u8>, Vec<u8>>,
@@ -103,6 +225,63 @@ impl SubStorageAccess for TestStorage {
     }
 }
 
+type SubStorages = HashMap<u16, HashMap<Vec<u8>, Vec<u8>>>;
+
+/// A `StorageAccess` which supports checkpoints by copying the whole storage.
+#[derive(Default)]
+pub struct TestCheckpointStorage {
+    sub_storages: Arc<Mutex<SubStorages>>,
+    checkpoints: Vec<SubStorages>,
+}
+
+struct TestSubStorage {
+    storage_id: u16,
+    sub_storages: Arc<Mutex<SubStorages>>,
+}
+
+impl remote_trait_object::Service for TestSubStorage {}
+
+impl SubStorageAccess for TestSubStorage {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.sub_storages.lock().unwrap().get(&self.storage_id)?.get(key).cloned()
+    }
+
+    fn set(&mut self, key: &[u8], value: Vec<u8>) {
+        self.sub_storages.lock().unwrap().entry(self.storage_id).or_default().insert(key.to_vec(), value);
+    }
+
+    fn has(&self, key: &[u8]) -> bool {
+        self.get(key).is_some()
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        if let Some(sub_storage) = self.sub_storages.lock().unwrap().get_mut(&self.storage_id) {
+            sub_storage.remove(key);
+        }
+    }
+}
+
+impl StorageAccess for TestCheckpointStorage {
+    fn sub_storage(&mut self, storage_id: u16) -> Box<dyn SubStorageAccess> {
+        Box::new(TestSubStorage {
+            storage_id,
+            sub_storages: Arc::clone(&self.sub_storages),
+        })
+    }
+
+    fn create_checkpoint(&mut self) {
+        self.checkpoints.push(self.sub_storages.lock().unwrap().clone());
+    }
+
+    fn revert_to_the_checkpoint(&mut self) {
+        *self.sub_storages.lock().unwrap() = self.checkpoints.pop().expect("No checkpoint to revert to");
+    }
+
+    fn discard_checkpoint(&mut self) {
+        self.checkpoints.pop().expect("No checkpoint to discard");
+    }
+}
+
 pub fn set_empty_session(id: SessionId, c: &Coordinator) {
     for (_, s) in c.services().stateful.lock().iter_mut() {
         s.new_session(id, ServiceRef::create_export(Box::new(TestStorage::default()) as Box<dyn SubStorageAccess>))
@@ -117,8 +296,11 @@ pub struct Services<'a> {
     pub handle_crimes: &'a dyn HandleCrimes,
     pub init_chain: &'a dyn InitChain,
     pub update_chain: &'a dyn UpdateChain,
+    pub anchor_block: &'a dyn AnchorBlock,
     pub tx_sorter: &'a dyn TxSorter,
     pub handle_graphqls: HashMap<&'a str, &'a dyn HandleGraphQlRequest>,
+    pub handle_event: HashMap<&'a str, &'a dyn HandleEvent>,
+    pub update_config: HashMap<&'a str, &'a dyn UpdateConfig>,
 }
 
 impl<'a> Services<'a> {
@@ -131,8 +313,11 @@ impl<'a> Services<'a> {
             handle_crimes: s.handle_crimes.as_ref(),
             init_chain: s.init_chain.as_ref(),
             update_chain: s.update_chain.as_ref(),
+            anchor_block: s.anchor_block.as_ref(),
             tx_sorter: s.tx_sorter.as_ref(),
             handle_graphqls: s.handle_graphqls.iter().map(|(s, x)| (s.as_str(), x.as_ref())).collect(),
+            handle_event: s.handle_event.iter().map(|(s, x)| (s.as_str(), x.as_ref())).collect(),
+            update_config: s.update_config.iter().map(|(s, x)| (s.as_str(), x.as_ref())).collect(),
         }
     }
 }
@@ -0,0 +1,54 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+extern crate codechain_timestamp as timestamp;
+
+use async_graphql::{InputValueError, ScalarType, Value};
+use timestamp::common::HexScalar;
+
+#[derive(Debug, HexScalar)]
+#[hex_scalar(from = "four_bytes")]
+pub struct GqlWord(pub [u8; 4]);
+
+fn four_bytes(bytes: &[u8]) -> Option<[u8; 4]> {
+    if bytes.len() == 4 {
+        let mut word = [0; 4];
+        word.copy_from_slice(bytes);
+        Some(word)
+    } else {
+        None
+    }
+}
+
+#[test]
+fn hex_string_is_parsed_into_the_inner_value() {
+    let word = GqlWord::parse(Value::String("0102abcd".to_owned())).unwrap();
+    assert_eq!(word.0, [0x01, 0x02, 0xab, 0xcd]);
+    match word.to_value() {
+        Value::String(s) => assert_eq!(s, "0102abcd"),
+        _ => panic!("A hex scalar must be written as a string"),
+    }
+}
+
+#[test]
+fn invalid_input_is_rejected_with_the_default_error() {
+    for value in vec![Value::String("0102".to_owned()), Value::String("not hex".to_owned()), Value::Int(3)] {
+        match GqlWord::parse(value) {
+            Err(InputValueError::Custom(message)) => assert_eq!(message, "Invalid GqlWord"),
+            _ => panic!("The input must be rejected with the default error"),
+        }
+    }
+}
@@ -23,10 +23,12 @@ mod common;
 use ccrypto::blake256;
 use ckey::{Ed25519KeyPair, Generator, KeyPairTrait, Random};
 use common::*;
+use coordinator::engine::{BlockExecutor, TxFilter};
 use coordinator::module::SessionId;
-use coordinator::{AppDesc, Coordinator};
+use coordinator::{AppDesc, Coordinator, Header, Transaction};
+use primitives::H256;
 use rand::prelude::*;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::sync::Arc;
 
 mod timestamp_setup {
@@ -82,6 +84,39 @@ mod timestamp_setup {
         }
     }
 
+    #[distributed_slice(MODULE_INITS)]
+    fn ibc() {
+        static VISIT: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(true);
+        if VISIT.compare_and_swap(true, false, std::sync::atomic::Ordering::SeqCst) {
+            add_function_pool(
+                "a060000000012345678901234567890123456789012345678901234567890123".to_owned(),
+                Arc::new(start::<<SingleProcess as ExecutionScheme>::Ipc, timestamp::ibc::Module>),
+            );
+        }
+    }
+
+    #[distributed_slice(MODULE_INITS)]
+    fn nft() {
+        static VISIT: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(true);
+        if VISIT.compare_and_swap(true, false, std::sync::atomic::Ordering::SeqCst) {
+            add_function_pool(
+                "a070000000012345678901234567890123456789012345678901234567890123".to_owned(),
+                Arc::new(start::<<SingleProcess as ExecutionScheme>::Ipc, timestamp::nft::Module>),
+            );
+        }
+    }
+
+    #[distributed_slice(MODULE_INITS)]
+    fn dex() {
+        static VISIT: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(true);
+        if VISIT.compare_and_swap(true, false, std::sync::atomic::Ordering::SeqCst) {
+            add_function_pool(
+                "a080000000012345678901234567890123456789012345678901234567890123".to_owned(),
+                Arc::new(start::<<SingleProcess as ExecutionScheme>::Ipc, timestamp::dex::Module>),
+            );
+        }
+    }
+
     #[distributed_slice(MODULE_INITS)]
     fn sorting() {
         static VISIT: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(true);
@@ -118,10 +153,11 @@ fn app_desc() -> AppDesc {
 fn weave() {
     let c = Coordinator::from_app_desc(&app_desc()).unwrap();
 
-    assert_eq!(c.services().stateful.lock().len(), 2);
-    assert_eq!(c.services().init_genesis.len(), 2);
-    assert_eq!(c.services().tx_owner.len(), 3);
-    assert_eq!(c.services().handle_graphqls.len(), 2);
+    assert_eq!(c.services().stateful.lock().len(), 6);
+    assert_eq!(c.services().init_genesis.len(), 4);
+    assert_eq!(c.services().tx_owner.len(), 9);
+    assert_eq!(c.services().tx_inspector.len(), 5);
+    assert_eq!(c.services().handle_graphqls.len(), 5);
 }
 
 #[test]
@@ -133,10 +169,11 @@ fn weave_conccurent() {
             joins.push(std::thread::spawn(|| {
                 let c = Coordinator::from_app_desc(&app_desc()).unwrap();
 
-                assert_eq!(c.services().stateful.lock().len(), 2);
-                assert_eq!(c.services().init_genesis.len(), 2);
-                assert_eq!(c.services().tx_owner.len(), 3);
-                assert_eq!(c.services().handle_graphqls.len(), 2);
+                assert_eq!(c.services().stateful.lock().len(), 6);
+                assert_eq!(c.services().init_genesis.len(), 4);
+                assert_eq!(c.services().tx_owner.len(), 9);
+                assert_eq!(c.services().tx_inspector.len(), 5);
+                assert_eq!(c.services().handle_graphqls.len(), 5);
             }))
         }
         for j in joins {
@@ -271,3 +308,647 @@ fn query() {
     );
     assert_eq!(r#"{"data":{"account":{"seq":21}}}"#, result);
 }
+
+#[test]
+fn batch_stamp_and_inclusion_proof() {
+    let coordinator = Coordinator::from_app_desc(&app_desc()).unwrap();
+    set_empty_session(0, &coordinator);
+    let services = Services::new(&coordinator);
+
+    let user: Ed25519KeyPair = Random.generate().unwrap();
+    let mut stampers = HashMap::new();
+    stampers.insert(user.public(), 1usize);
+    services.init_genesis.get("module-stamp").unwrap().init_genesis(0, &serde_cbor::to_vec(&stampers).unwrap());
+
+    let documents: Vec<_> = (0..5).map(|i| blake256(format!("document{}", i))).collect();
+    let tx = tx_stamp_batch(user.public(), user.private(), 0, &documents);
+    services.tx_owner.get("stamp").unwrap().execute_transaction(0, &tx).unwrap();
+
+    let documents_str =
+        documents.iter().map(|x| format!("\"{}\"", hex::encode(x.as_ref()))).collect::<Vec<_>>().join(",");
+    let query = |document: &str| {
+        services.handle_graphqls.get("module-stamp").unwrap().execute(
+            0,
+            &format!(
+                "{{ inclusionProof(documents: [{}], document: \"{}\") {{ root stamp {{ batchSize }} }} }}",
+                documents_str, document
+            ),
+            "{}",
+        )
+    };
+
+    let root = timestamp::stamp::merkle::merkle_root(&documents).unwrap();
+    assert_eq!(
+        format!(
+            r#"{{"data":{{"inclusionProof":{{"root":"{}","stamp":{{"batchSize":5}}}}}}}}"#,
+            hex::encode(root.as_ref())
+        ),
+        query(&hex::encode(documents[3].as_ref()))
+    );
+    assert_eq!(r#"{"data":{"inclusionProof":null}}"#, query(&hex::encode(blake256("not stamped").as_ref())));
+}
+
+#[test]
+fn multisig() {
+    let coordinator = Coordinator::from_app_desc(&app_desc()).unwrap();
+    set_empty_session(0, &coordinator);
+    let services = Services::new(&coordinator);
+    let account_tx_owner = services.tx_owner.get("multisig").unwrap();
+
+    let users: Vec<Ed25519KeyPair> = (0..3).map(|_| Random.generate().unwrap()).collect();
+    let publics: BTreeSet<_> = users.iter().map(|x| *x.public()).collect();
+    let multisig = timestamp::account::MultisigAccount {
+        publics: publics.clone(),
+        threshold: 2,
+    };
+    let create = timestamp::account::TxMultisig::Create {
+        publics,
+        threshold: 2,
+    };
+    let hello = || timestamp::account::TxMultisig::Hello {
+        account: multisig.id(),
+    };
+
+    // Every key must sign the creation
+    assert!(account_tx_owner.execute_transaction(0, &tx_multisig(&users[..2], 0, create.clone())).is_err());
+    account_tx_owner.execute_transaction(0, &tx_multisig(&users, 0, create.clone())).unwrap();
+    assert!(account_tx_owner.execute_transaction(0, &tx_multisig(&users, 1, create)).is_err());
+
+    assert!(account_tx_owner.execute_transaction(0, &tx_multisig(&users[..1], 1, hello())).is_err());
+    account_tx_owner.execute_transaction(0, &tx_multisig(&users[1..], 1, hello())).unwrap();
+    assert!(account_tx_owner.execute_transaction(0, &tx_multisig(&users[1..], 1, hello())).is_err());
+    account_tx_owner.execute_transaction(0, &tx_multisig(&users, 2, hello())).unwrap();
+
+    let result = services.handle_graphqls.get("module-account").unwrap().execute(
+        0,
+        &format!("{{ account(public: \"{}\") {{ seq }} }}", hex::encode(multisig.id().as_ref())),
+        "{}",
+    );
+    assert_eq!(r#"{"data":{"account":{"seq":3}}}"#, result);
+}
+
+#[derive(serde::Serialize)]
+struct AccountConfig {
+    allow_hello: bool,
+}
+
+#[test]
+fn account_config_is_updated_by_the_governance() {
+    let coordinator = Coordinator::from_app_desc(&app_desc()).unwrap();
+    assert!(coordinator.services().config_schemas.contains_key("module-account"));
+    set_empty_session(0, &coordinator);
+    let services = Services::new(&coordinator);
+    let account_tx_owner = services.tx_owner.get("account").unwrap();
+    let update_config = services.update_config.get("module-account").unwrap();
+    let user: Ed25519KeyPair = Random.generate().unwrap();
+
+    account_tx_owner.execute_transaction(0, &tx_hello(user.public(), user.private(), 0)).unwrap();
+
+    let config = serde_cbor::to_vec(&AccountConfig {
+        allow_hello: false,
+    })
+    .unwrap();
+    update_config.update_config(0, &config).unwrap();
+    assert!(account_tx_owner.execute_transaction(0, &tx_hello(user.public(), user.private(), 1)).is_err());
+
+    // A malformed config is rejected, and the module keeps its config
+    assert!(update_config.update_config(0, &[0xff]).is_err());
+    assert!(account_tx_owner.execute_transaction(0, &tx_hello(user.public(), user.private(), 1)).is_err());
+
+    let config = serde_cbor::to_vec(&AccountConfig {
+        allow_hello: true,
+    })
+    .unwrap();
+    update_config.update_config(0, &config).unwrap();
+    account_tx_owner.execute_transaction(0, &tx_hello(user.public(), user.private(), 1)).unwrap();
+}
+
+#[test]
+fn vested_balance_is_unlocked_by_the_block_number() {
+    use timestamp::account::{GenesisAllocation, TxPay, VestingSchedule};
+
+    let coordinator = Coordinator::from_app_desc(&app_desc()).unwrap();
+    set_empty_session(0, &coordinator);
+    let services = Services::new(&coordinator);
+    let pay = services.tx_owner.get("pay").unwrap();
+
+    let buyer: Ed25519KeyPair = Random.generate().unwrap();
+    let other: Ed25519KeyPair = Random.generate().unwrap();
+    let mut allocations = HashMap::new();
+    allocations.insert(*buyer.public(), GenesisAllocation {
+        balance: 1000,
+        vesting: Some(VestingSchedule {
+            amount: 800,
+            start: 0,
+            cliff: 10,
+            end: 40,
+        }),
+    });
+    services.init_genesis.get("module-account").unwrap().init_genesis(0, &serde_cbor::to_vec(&allocations).unwrap());
+
+    let open_block = |number: u64| {
+        let header = Header::new(Default::default(), 0, number, *buyer.public(), Vec::new(), Vec::new());
+        pay.block_opened(0, &header).unwrap();
+    };
+    let spendable = || {
+        services.handle_graphqls.get("module-account").unwrap().execute(
+            0,
+            &format!("{{ spendableBalance(public: \"{}\") }}", hex::encode(buyer.public().as_ref())),
+            "{}",
+        )
+    };
+    let pay_other = |seq: u64, quantity: u64| {
+        tx_pay(buyer.public(), buyer.private(), seq, TxPay::Pay {
+            receiver: *other.public(),
+            quantity,
+        })
+    };
+
+    // Nothing is released before the cliff
+    open_block(9);
+    assert_eq!(r#"{"data":{"spendableBalance":200}}"#, spendable());
+    assert!(pay.execute_transaction(0, &pay_other(0, 201)).is_err());
+    pay.execute_transaction(0, &pay_other(0, 200)).unwrap();
+
+    // A half of the lockup is released at the middle of the schedule
+    open_block(20);
+    assert_eq!(r#"{"data":{"spendableBalance":400}}"#, spendable());
+    assert!(pay.execute_transaction(0, &pay_other(1, 401)).is_err());
+    pay.execute_transaction(0, &pay_other(1, 400)).unwrap();
+
+    open_block(40);
+    assert_eq!(r#"{"data":{"spendableBalance":400}}"#, spendable());
+    pay.execute_transaction(0, &pay_other(2, 400)).unwrap();
+
+    let result = services.handle_graphqls.get("module-account").unwrap().execute(
+        0,
+        &format!(
+            "{{ account(public: \"{}\") {{ seq balance vesting {{ amount cliff }} }} }}",
+            hex::encode(buyer.public().as_ref())
+        ),
+        "{}",
+    );
+    assert_eq!(r#"{"data":{"account":{"seq":3,"balance":0,"vesting":{"amount":800,"cliff":10}}}}"#, result);
+}
+
+#[test]
+fn vested_payment_locks_the_balance_of_the_receiver() {
+    use timestamp::account::{GenesisAllocation, TxPay};
+
+    let coordinator = Coordinator::from_app_desc(&app_desc()).unwrap();
+    set_empty_session(0, &coordinator);
+    let services = Services::new(&coordinator);
+    let pay = services.tx_owner.get("pay").unwrap();
+
+    let seller: Ed25519KeyPair = Random.generate().unwrap();
+    let buyer: Ed25519KeyPair = Random.generate().unwrap();
+    let mut allocations = HashMap::new();
+    allocations.insert(*seller.public(), GenesisAllocation {
+        balance: 1000,
+        vesting: None,
+    });
+    services.init_genesis.get("module-account").unwrap().init_genesis(0, &serde_cbor::to_vec(&allocations).unwrap());
+    let vested = |seq: u64, cliff: u64, end: u64| {
+        tx_pay(seller.public(), seller.private(), seq, TxPay::PayVested {
+            receiver: *buyer.public(),
+            quantity: 100,
+            start: 0,
+            cliff,
+            end,
+        })
+    };
+
+    // The cliff must be between the start and the end
+    assert!(pay.execute_transaction(0, &vested(0, 20, 10)).is_err());
+    // All at once at the block 10
+    pay.execute_transaction(0, &vested(0, 10, 10)).unwrap();
+    // The receiver has a schedule still locking its balance
+    assert!(pay.execute_transaction(0, &vested(1, 10, 10)).is_err());
+
+    // The buyer can't spend the locked balance
+    let pay_back = |seq: u64| {
+        tx_pay(buyer.public(), buyer.private(), seq, TxPay::Pay {
+            receiver: *seller.public(),
+            quantity: 1,
+        })
+    };
+    assert!(pay.execute_transaction(0, &pay_back(0)).is_err());
+
+    let header = Header::new(Default::default(), 0, 10, *seller.public(), Vec::new(), Vec::new());
+    pay.block_opened(0, &header).unwrap();
+    pay.execute_transaction(0, &pay_back(0)).unwrap();
+    // The released schedule is replaced by a new one
+    pay.execute_transaction(0, &vested(1, 20, 30)).unwrap();
+}
+
+#[test]
+fn composite_transaction_is_atomic() {
+    let coordinator = Coordinator::from_app_desc(&app_desc()).unwrap();
+    let mut storage = TestCheckpointStorage::default();
+
+    let user1: Ed25519KeyPair = Random.generate().unwrap();
+    let user2: Ed25519KeyPair = Random.generate().unwrap();
+    let header = Header::new(Default::default(), 0, 1, *user1.public(), Vec::new(), Vec::new());
+    let session = coordinator.open_block(&mut storage, &header, &Default::default(), header.timestamp(), &[]).unwrap();
+
+    let services = Services::new(&coordinator);
+    let mut stampers = HashMap::new();
+    stampers.insert(user1.public(), 1usize);
+    services.init_genesis.get("module-stamp").unwrap().init_genesis(session, &serde_cbor::to_vec(&stampers).unwrap());
+    let stamp_issuer = blake256("stamp");
+
+    // Stamp and hand the token over to user2 at once
+    let composite = Transaction::new_composite(&[
+        tx_stamp(user1.public(), user1.private(), 0, "Hello"),
+        tx_token_transfer(user1.public(), user1.private(), 1, *user2.public(), stamp_issuer),
+    ]);
+    assert_eq!(coordinator.execute_transactions(session, &mut storage, &[composite]).unwrap().len(), 1);
+
+    // The transfer fails because user2 has no token of the issuer, so the stamp is reverted as well
+    let composite = Transaction::new_composite(&[
+        tx_stamp(user2.public(), user2.private(), 0, "World"),
+        tx_token_transfer(user2.public(), user2.private(), 1, *user1.public(), blake256("no such issuer")),
+    ]);
+    assert!(coordinator.execute_transactions(session, &mut storage, &[composite]).unwrap().is_empty());
+
+    let stamp = tx_stamp(user2.public(), user2.private(), 0, "World");
+    assert_eq!(coordinator.execute_transactions(session, &mut storage, &[stamp]).unwrap().len(), 1);
+}
+
+#[test]
+fn stamps_in_block_are_anchored() {
+    let coordinator = Coordinator::from_app_desc(&app_desc()).unwrap();
+    let mut storage = TestCheckpointStorage::default();
+
+    let user: Ed25519KeyPair = Random.generate().unwrap();
+    let header = Header::new(Default::default(), 0, 1, *user.public(), Vec::new(), Vec::new());
+    let session = coordinator.open_block(&mut storage, &header, &Default::default(), header.timestamp(), &[]).unwrap();
+
+    let services = Services::new(&coordinator);
+    let mut stampers = HashMap::new();
+    stampers.insert(user.public(), 1usize);
+    services.init_genesis.get("module-stamp").unwrap().init_genesis(session, &serde_cbor::to_vec(&stampers).unwrap());
+
+    // The stamp in the failed composite transaction is reverted, so it is not anchored
+    let failed = Transaction::new_composite(&[
+        tx_stamp(user.public(), user.private(), 1, "Reverted"),
+        tx_token_transfer(user.public(), user.private(), 2, *user.public(), blake256("no such issuer")),
+    ]);
+    let txs = [
+        tx_stamp(user.public(), user.private(), 0, "Hello"),
+        failed,
+        tx_stamp(user.public(), user.private(), 1, "World"),
+    ];
+    assert_eq!(coordinator.execute_transactions(session, &mut storage, &txs).unwrap().len(), 2);
+
+    let anchor = timestamp::stamp::merkle::merkle_root(&[blake256("Hello"), blake256("World")]);
+    assert_eq!(anchor, services.anchor_block.external_anchor(session));
+    // The stamps are taken out, so the next block starts with nothing to anchor
+    assert_eq!(None, services.anchor_block.external_anchor(session));
+}
+
+#[test]
+fn parallel_execution_matches_serial_execution() {
+    let users: Vec<Ed25519KeyPair> = (0..4).map(|_| Random.generate().unwrap()).collect();
+    // Interleaved sequences of each user, with a transaction of a wrong sequence in the middle
+    let mut txs: Vec<_> =
+        (0..3).flat_map(|seq| users.iter().map(move |user| tx_hello(user.public(), user.private(), seq))).collect();
+    txs.insert(5, tx_hello(users[0].public(), users[0].private(), 0));
+
+    let run = |coordinator: Coordinator| {
+        let mut storage = TestCheckpointStorage::default();
+        let header = Header::new(Default::default(), 0, 1, *users[0].public(), Vec::new(), Vec::new());
+        let session =
+            coordinator.open_block(&mut storage, &header, &Default::default(), header.timestamp(), &[]).unwrap();
+        let executed = coordinator.execute_transactions(session, &mut storage, &txs).unwrap().len();
+
+        let services = Services::new(&coordinator);
+        let seqs: Vec<_> = users
+            .iter()
+            .map(|user| {
+                services.handle_graphqls.get("module-account").unwrap().execute(
+                    session,
+                    &format!("{{ account(public: \"{}\") {{ seq }} }}", hex::encode(user.public().as_ref())),
+                    "{}",
+                )
+            })
+            .collect();
+        (executed, seqs)
+    };
+
+    let serial = run(Coordinator::from_app_desc(&app_desc()).unwrap());
+    let coordinator = Coordinator::from_app_desc(&app_desc()).unwrap().with_execution_threads(4);
+    assert_eq!(coordinator.execution_threads(), 4);
+    let parallel = run(coordinator);
+    assert_eq!(serial.0, 12);
+    assert_eq!(serial, parallel);
+}
+
+#[test]
+fn execution_stays_serial_unless_every_module_opts_in() {
+    let mut app_desc = app_desc();
+    app_desc.modules.values_mut().next().unwrap().parallel_execution = false;
+    let coordinator = Coordinator::from_app_desc(&app_desc).unwrap().with_execution_threads(4);
+    assert_eq!(coordinator.execution_threads(), 1);
+}
+
+fn signed_header(
+    parent: &ctypes::Header,
+    validators: &[Ed25519KeyPair],
+    module_roots_hash: H256,
+) -> (ctypes::Header, ctypes::Commit) {
+    let mut header = ctypes::Header::new();
+    header.set_parent_hash(parent.hash());
+    header.set_number(parent.number() + 1);
+    header.set_next_validator_set_hash(*parent.next_validator_set_hash());
+    header.set_module_roots_hash(Some(module_roots_hash));
+    let timestamp = 100 + header.number();
+    let message = ctypes::precommit_hash(header.number(), 0, &header.hash(), timestamp);
+    let commit = ctypes::Commit {
+        view: 0,
+        precommits: validators
+            .iter()
+            .enumerate()
+            .map(|(signer_index, validator)| ctypes::Precommit {
+                signer_index,
+                signature: ckey::sign(message.as_bytes(), validator.private()),
+                timestamp,
+            })
+            .collect(),
+    };
+    (header, commit)
+}
+
+fn ibc_genesis_config(trusted_header: &ctypes::Header) -> Vec<u8> {
+    let mut counterparties = HashMap::new();
+    counterparties.insert("chain-b".to_owned(), timestamp::ibc::CounterpartyConfig {
+        trusted_header: hex::encode(rlp::encode(trusted_header)),
+        storage_id: 1,
+        id_on_counterparty: "chain-a".to_owned(),
+    });
+    serde_cbor::to_vec(&counterparties).unwrap()
+}
+
+#[test]
+fn ibc_client_accepts_only_committed_headers_in_sequence() {
+    let coordinator = Coordinator::from_app_desc(&app_desc()).unwrap();
+    set_empty_session(0, &coordinator);
+    let services = Services::new(&coordinator);
+    let ibc = services.tx_owner.get("ibc").unwrap();
+
+    let validators: Vec<Ed25519KeyPair> = (0..3).map(|_| Random.generate().unwrap()).collect();
+    let validator_set = ctypes::CompactValidatorSet::new(
+        validators
+            .iter()
+            .map(|validator| ctypes::CompactValidatorEntry {
+                public_key: *validator.public(),
+                delegation: 1,
+            })
+            .collect(),
+    );
+    let mut genesis = ctypes::Header::new();
+    genesis.set_next_validator_set_hash(validator_set.hash());
+    services.init_genesis.get("module-ibc").unwrap().init_genesis(0, &ibc_genesis_config(&genesis));
+
+    let relayer: Ed25519KeyPair = Random.generate().unwrap();
+    let update = |seq: u64, header: &ctypes::Header, commit: ctypes::Commit| {
+        tx_ibc(relayer.public(), relayer.private(), seq, timestamp::ibc::TxIbc::UpdateClient {
+            counterparty: "chain-b".to_owned(),
+            header: rlp::encode(header),
+            validator_set: validator_set.clone(),
+            commit,
+        })
+    };
+
+    let (header1, commit1) = signed_header(&genesis, &validators, blake256("roots1"));
+    // Two of three validators are not more than two thirds
+    let mut partial_commit = commit1.clone();
+    partial_commit.precommits.pop();
+    assert!(ibc.execute_transaction(0, &update(0, &header1, partial_commit)).is_err());
+    ibc.execute_transaction(0, &update(0, &header1, commit1.clone())).unwrap();
+    assert!(ibc.execute_transaction(0, &update(1, &header1, commit1)).is_err());
+
+    let (header2, commit2) = signed_header(&header1, &validators, blake256("roots2"));
+    let (header3, commit3) = signed_header(&header2, &validators, blake256("roots3"));
+    assert!(ibc.execute_transaction(0, &update(1, &header3, commit3.clone())).is_err());
+    ibc.execute_transaction(0, &update(1, &header2, commit2)).unwrap();
+    ibc.execute_transaction(0, &update(2, &header3, commit3)).unwrap();
+
+    let outsiders: Vec<Ed25519KeyPair> = (0..3).map(|_| Random.generate().unwrap()).collect();
+    let (header4, forged) = signed_header(&header3, &outsiders, blake256("roots4"));
+    assert!(ibc.execute_transaction(0, &update(3, &header4, forged)).is_err());
+}
+
+#[test]
+fn token_sent_out_is_escrowed_only_for_known_counterparties() {
+    let coordinator = Coordinator::from_app_desc(&app_desc()).unwrap();
+    let mut storage = TestCheckpointStorage::default();
+
+    let user1: Ed25519KeyPair = Random.generate().unwrap();
+    let user2: Ed25519KeyPair = Random.generate().unwrap();
+    let header = Header::new(Default::default(), 0, 1, *user1.public(), Vec::new(), Vec::new());
+    let session = coordinator.open_block(&mut storage, &header, &Default::default(), header.timestamp(), &[]).unwrap();
+
+    let services = Services::new(&coordinator);
+    let mut stampers = HashMap::new();
+    stampers.insert(user1.public(), 1usize);
+    services.init_genesis.get("module-stamp").unwrap().init_genesis(session, &serde_cbor::to_vec(&stampers).unwrap());
+    services.init_genesis.get("module-ibc").unwrap().init_genesis(session, &ibc_genesis_config(&ctypes::Header::new()));
+    let stamp_issuer = blake256("stamp");
+
+    // The packet can't be committed for an unknown chain, so the token stays
+    let tx = tx_token_send(user1.public(), user1.private(), 0, "chain-c", *user2.public(), stamp_issuer);
+    assert!(coordinator.execute_transactions(session, &mut storage, &[tx]).unwrap().is_empty());
+
+    let tx = tx_token_send(user1.public(), user1.private(), 0, "chain-b", *user2.public(), stamp_issuer);
+    assert_eq!(coordinator.execute_transactions(session, &mut storage, &[tx]).unwrap().len(), 1);
+
+    // The token has left the chain
+    let tx = tx_stamp(user1.public(), user1.private(), 1, "Hello");
+    assert!(coordinator.execute_transactions(session, &mut storage, &[tx]).unwrap().is_empty());
+    let tx = tx_token_send(user1.public(), user1.private(), 1, "chain-b", *user2.public(), stamp_issuer);
+    assert!(coordinator.execute_transactions(session, &mut storage, &[tx]).unwrap().is_empty());
+}
+
+#[test]
+fn transactions_are_inspected_by_the_modules_owning_their_types() {
+    use async_graphql::serde_json::{self, Value};
+
+    let coordinator = Coordinator::from_app_desc(&app_desc()).unwrap();
+    let user1: Ed25519KeyPair = Random.generate().unwrap();
+    let user2: Ed25519KeyPair = Random.generate().unwrap();
+
+    let tx = tx_token_transfer(user1.public(), user1.private(), 3, *user2.public(), blake256("stamp"));
+    let payload = coordinator.inspect_transaction(&tx).unwrap().unwrap();
+    assert_eq!(payload.action, "transfer_token");
+    let fields: Value = serde_json::from_str(&payload.fields).unwrap();
+    assert_eq!(fields["seq"], 3);
+    assert_eq!(fields["signer"], serde_json::to_value(user1.public()).unwrap());
+    assert_eq!(fields["action"]["receiver"], serde_json::to_value(user2.public()).unwrap());
+
+    let tx = tx_stamp(user1.public(), user1.private(), 0, "document");
+    assert_eq!(coordinator.inspect_transaction(&tx).unwrap().unwrap().action, "stamp");
+
+    // The account module doesn't inspect its transactions, and a malformed body can't be decoded
+    assert!(coordinator.inspect_transaction(&tx_hello(user1.public(), user1.private(), 0)).is_none());
+    assert!(coordinator.inspect_transaction(&Transaction::new("token".to_owned(), vec![1, 2, 3])).unwrap().is_err());
+}
+
+#[test]
+fn nft_ownership_and_provenance_are_indexed() {
+    use async_graphql::serde_json::{self, Value};
+    use timestamp::nft::TxNft;
+
+    let coordinator = Coordinator::from_app_desc(&app_desc()).unwrap();
+    set_empty_session(0, &coordinator);
+    let services = Services::new(&coordinator);
+    let nft = services.tx_owner.get("nft").unwrap();
+    let query = |query: String| -> Value {
+        serde_json::from_str(&services.handle_graphqls.get("module-nft").unwrap().execute(0, &query, "{}")).unwrap()
+    };
+
+    let user1: Ed25519KeyPair = Random.generate().unwrap();
+    let user2: Ed25519KeyPair = Random.generate().unwrap();
+    let public1 = hex::encode(user1.public().as_ref());
+    let public2 = hex::encode(user2.public().as_ref());
+
+    for (seq, metadata) in ["first", "second", "third"].iter().enumerate() {
+        let tx = tx_nft(user1.public(), user1.private(), seq as u64, TxNft::Mint {
+            metadata: blake256(metadata),
+        });
+        nft.execute_transaction(0, &tx).unwrap();
+    }
+    let assets = query(format!("{{ assets(owner: \"{}\", offset: 0, limit: 10) }}", public1));
+    let ids: Vec<H256> = assets["data"]["assets"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|id| H256::from_slice(&hex::decode(id.as_str().unwrap()).unwrap()))
+        .collect();
+    assert_eq!(ids.len(), 3);
+
+    let tx = tx_nft(user1.public(), user1.private(), 3, TxNft::Transfer {
+        asset: ids[0],
+        receiver: *user2.public(),
+    });
+    nft.execute_transaction(0, &tx).unwrap();
+    // Only the owner can move an asset
+    let tx = tx_nft(user1.public(), user1.private(), 4, TxNft::Burn {
+        asset: ids[0],
+    });
+    assert!(nft.execute_transaction(0, &tx).is_err());
+    let tx = tx_nft(user1.public(), user1.private(), 4, TxNft::Burn {
+        asset: ids[1],
+    });
+    nft.execute_transaction(0, &tx).unwrap();
+
+    let result = query(format!(
+        "{{ a: assetCount(owner: \"{}\") b: assetCount(owner: \"{}\") assets(owner: \"{}\", offset: 0, limit: 10) }}",
+        public1, public2, public1
+    ));
+    assert_eq!(result["data"]["a"], 1);
+    assert_eq!(result["data"]["b"], 1);
+    assert_eq!(result["data"]["assets"][0], hex::encode(ids[2].as_ref()));
+
+    let id = hex::encode(ids[0].as_ref());
+    let result = query(format!(
+        "{{ asset(id: \"{}\") {{ owner transfers }} history(asset: \"{}\", offset: 0, limit: 10) {{ from to }} }}",
+        id, id
+    ));
+    assert_eq!(result["data"]["asset"]["owner"], public2.as_str());
+    assert_eq!(result["data"]["asset"]["transfers"], 2);
+    assert_eq!(result["data"]["history"][0]["from"], Value::Null);
+    assert_eq!(result["data"]["history"][1]["from"], public1.as_str());
+    assert_eq!(result["data"]["history"][1]["to"], public2.as_str());
+
+    // The burnt asset has no owner, but its history is kept
+    let result = query(format!(
+        "{{ asset(id: \"{}\") {{ owner }} accountHistory(public: \"{}\", offset: 0, limit: 10) {{ asset to }} }}",
+        hex::encode(ids[1].as_ref()),
+        public1
+    ));
+    assert_eq!(result["data"]["asset"]["owner"], Value::Null);
+    let history = result["data"]["accountHistory"].as_array().unwrap();
+    assert_eq!(history.len(), 5);
+    assert_eq!(history[4]["asset"], hex::encode(ids[1].as_ref()));
+    assert_eq!(history[4]["to"], Value::Null);
+}
+
+#[test]
+fn dex_orders_are_matched_on_closing_the_block() {
+    use async_graphql::serde_json::{self, Value};
+    use timestamp::dex::{Market, Side, Trade, TxDex};
+
+    let coordinator = Coordinator::from_app_desc(&app_desc()).unwrap();
+    set_empty_session(0, &coordinator);
+    let services = Services::new(&coordinator);
+    let dex = services.tx_owner.get("dex").unwrap();
+
+    let seller: Ed25519KeyPair = Random.generate().unwrap();
+    let buyer: Ed25519KeyPair = Random.generate().unwrap();
+    let validator_issuer = blake256("validator");
+    let stamp_issuer = blake256("stamp");
+    services
+        .init_genesis
+        .get("module-staking")
+        .unwrap()
+        .init_genesis(0, &serde_cbor::to_vec(&vec![hex::encode(seller.public().as_ref())]).unwrap());
+    let mut stampers = HashMap::new();
+    stampers.insert(buyer.public(), 5usize);
+    services.init_genesis.get("module-stamp").unwrap().init_genesis(0, &serde_cbor::to_vec(&stampers).unwrap());
+
+    let count_tokens = |public: &ckey::Ed25519Public, issuer: &H256| -> usize {
+        let result = services.handle_graphqls.get("module-token").unwrap().execute(
+            0,
+            &format!("{{ account(public: \"{}\") {{ tokens {{ issuer }} }} }}", hex::encode(public.as_ref())),
+            "{}",
+        );
+        let result: Value = serde_json::from_str(&result).unwrap();
+        let issuer = hex::encode(issuer.as_ref());
+        result["data"]["account"]["tokens"]
+            .as_array()
+            .map_or(0, |tokens| tokens.iter().filter(|token| token["issuer"] == issuer.as_str()).count())
+    };
+
+    let market = Market {
+        base: validator_issuer,
+        quote: stamp_issuer,
+    };
+    let place = |key: &Ed25519KeyPair, side: Side, price: u64, amount: u64| {
+        tx_dex(key.public(), key.private(), 0, TxDex::PlaceOrder {
+            market,
+            side,
+            price,
+            amount,
+        })
+    };
+    // The seller has only one token to sell
+    assert!(dex.execute_transaction(0, &place(&seller, Side::Sell, 2, 2)).is_err());
+    dex.execute_transaction(0, &place(&seller, Side::Sell, 2, 1)).unwrap();
+    dex.execute_transaction(0, &place(&buyer, Side::Buy, 3, 1)).unwrap();
+    // The tokens are held in escrow until the block is closed
+    assert_eq!(count_tokens(seller.public(), &validator_issuer), 0);
+    assert_eq!(count_tokens(buyer.public(), &stamp_issuer), 2);
+
+    let events = dex.block_closed(0).unwrap();
+    assert_eq!(events.len(), 1);
+    let trade: Trade = serde_cbor::from_slice(&events[0].value).unwrap();
+    // The trade is made at the price of the earlier order, and the buyer gets back the difference
+    assert_eq!((trade.buyer, trade.seller), (*buyer.public(), *seller.public()));
+    assert_eq!((trade.price, trade.amount, trade.refund), (2, 1, 1));
+    assert_eq!(count_tokens(buyer.public(), &validator_issuer), 1);
+    assert_eq!(count_tokens(buyer.public(), &stamp_issuer), 3);
+    assert_eq!(count_tokens(seller.public(), &stamp_issuer), 2);
+
+    let result = services.handle_graphqls.get("module-dex").unwrap().execute(
+        0,
+        &format!(
+            "{{ orderBook(base: \"{}\", quote: \"{}\", depth: 10) {{ bids {{ id }} asks {{ id }} }} }}",
+            hex::encode(validator_issuer.as_ref()),
+            hex::encode(stamp_issuer.as_ref())
+        ),
+        "{}",
+    );
+    assert_eq!(r#"{"data":{"orderBook":{"bids":[],"asks":[]}}}"#, result);
+    assert!(dex.block_closed(0).unwrap().is_empty());
+}
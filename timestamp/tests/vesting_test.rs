@@ -0,0 +1,92 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+extern crate codechain_timestamp as timestamp;
+
+use timestamp::account::{Account, VestingSchedule};
+
+fn schedule(amount: u64, start: u64, cliff: u64, end: u64) -> VestingSchedule {
+    VestingSchedule {
+        amount,
+        start,
+        cliff,
+        end,
+    }
+}
+
+#[test]
+fn nothing_is_unlocked_before_the_cliff() {
+    let vesting = schedule(1000, 10, 20, 110);
+    assert_eq!(vesting.unlocked_at(0), 0);
+    assert_eq!(vesting.unlocked_at(19), 0);
+    assert_eq!(vesting.locked_at(19), 1000);
+}
+
+#[test]
+fn amount_is_unlocked_linearly_from_the_start() {
+    let vesting = schedule(1000, 10, 20, 110);
+    // The part accrued from the start is released at the cliff
+    assert_eq!(vesting.unlocked_at(20), 100);
+    assert_eq!(vesting.unlocked_at(60), 500);
+    assert_eq!(vesting.unlocked_at(109), 990);
+    assert_eq!(vesting.unlocked_at(110), 1000);
+    assert_eq!(vesting.unlocked_at(u64::MAX), 1000);
+    assert_eq!(vesting.locked_at(60), 500);
+}
+
+#[test]
+fn unlocked_amount_is_rounded_down() {
+    let vesting = schedule(10, 0, 0, 3);
+    assert_eq!(vesting.unlocked_at(1), 3);
+    assert_eq!(vesting.unlocked_at(2), 6);
+    assert_eq!(vesting.locked_at(2), 4);
+}
+
+#[test]
+fn large_amount_does_not_overflow() {
+    let vesting = schedule(u64::MAX, 0, 0, 4);
+    assert_eq!(vesting.unlocked_at(2), u64::MAX / 2);
+}
+
+#[test]
+fn cliff_at_the_end_releases_everything_at_once() {
+    let vesting = schedule(1000, 0, 50, 50);
+    assert!(vesting.is_valid());
+    assert_eq!(vesting.unlocked_at(49), 0);
+    assert_eq!(vesting.unlocked_at(50), 1000);
+    assert!(!schedule(1000, 10, 5, 50).is_valid());
+    assert!(!schedule(1000, 0, 60, 50).is_valid());
+}
+
+#[test]
+fn only_the_unlocked_part_of_the_balance_is_spendable() {
+    let account = Account {
+        seq: 0,
+        balance: 1500,
+        vesting: Some(schedule(1000, 0, 0, 100)),
+    };
+    assert_eq!(account.spendable_at(0), 500);
+    assert_eq!(account.spendable_at(30), 800);
+    assert_eq!(account.spendable_at(100), 1500);
+
+    // The locked part may be larger than what is left after spending the unlocked part
+    let account = Account {
+        balance: 200,
+        ..account
+    };
+    assert_eq!(account.spendable_at(0), 0);
+    assert_eq!(account.spendable_at(90), 100);
+}
@@ -0,0 +1,198 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::BlockNumber;
+use cjson::scheme::Activations as JsonActivations;
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+
+const INACTIVE: BlockNumber = BlockNumber::max_value();
+
+/// The block numbers from which the consensus rules added after a chain started apply.
+///
+/// The params of a chain written before the rules existed decode with every rule inactive, so its blocks keep verifying
+/// until a params change schedules the rules. A new chain applies the rules its scheme doesn't schedule from the genesis.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Activations {
+    /// The first height whose votes sign the time they are made at.
+    timestamped_votes: BlockNumber,
+    /// The first block whose header may carry the external anchor set by a module.
+    external_anchor: BlockNumber,
+    /// The first block whose header commits to the roots of the module substorages.
+    module_roots: BlockNumber,
+    /// The first block whose body size counts only its transactions, which fill it up to the max body size.
+    exact_body_size: BlockNumber,
+    /// The first block invalid when any of its transactions fails the checks of its owner module without the state.
+    transaction_checks: BlockNumber,
+}
+
+impl Default for Activations {
+    fn default() -> Self {
+        Self::inactive()
+    }
+}
+
+impl Activations {
+    /// No rule applies to any block.
+    pub fn inactive() -> Self {
+        Self {
+            timestamped_votes: INACTIVE,
+            external_anchor: INACTIVE,
+            module_roots: INACTIVE,
+            exact_body_size: INACTIVE,
+            transaction_checks: INACTIVE,
+        }
+    }
+
+    /// Every rule applies from the genesis.
+    pub fn from_genesis() -> Self {
+        Self {
+            timestamped_votes: 0,
+            external_anchor: 0,
+            module_roots: 0,
+            exact_body_size: 0,
+            transaction_checks: 0,
+        }
+    }
+
+    /// Whether the votes at the height sign the time they are made at.
+    pub fn timestamped_votes(&self, height: BlockNumber) -> bool {
+        height >= self.timestamped_votes
+    }
+
+    /// Whether the header of the block may carry the external anchor.
+    pub fn external_anchor(&self, number: BlockNumber) -> bool {
+        number >= self.external_anchor
+    }
+
+    /// Whether the header of the block commits to the roots of the module substorages.
+    pub fn module_roots(&self, number: BlockNumber) -> bool {
+        number >= self.module_roots
+    }
+
+    /// Whether the body size of the block counts only its transactions, which may fill it up to the max body size.
+    pub fn exact_body_size(&self, number: BlockNumber) -> bool {
+        number >= self.exact_body_size
+    }
+
+    /// Whether the block is invalid when a transaction of it fails the checks of its owner module without the state.
+    pub fn transaction_checks(&self, number: BlockNumber) -> bool {
+        number >= self.transaction_checks
+    }
+
+    /// Returns the activations with the one of the given name moved to the given block number.
+    pub fn with_change(mut self, name: &str, number: BlockNumber) -> Result<Self, String> {
+        match name {
+            "timestamped_votes_activation" => self.timestamped_votes = number,
+            "external_anchor_activation" => self.external_anchor = number,
+            "module_roots_activation" => self.module_roots = number,
+            "exact_body_size_activation" => self.exact_body_size = number,
+            "transaction_checks_activation" => self.transaction_checks = number,
+            _ => return Err(format!("There is no activation named {}", name)),
+        }
+        Ok(self)
+    }
+}
+
+impl Encodable for Activations {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(5)
+            .append(&self.timestamped_votes)
+            .append(&self.external_anchor)
+            .append(&self.module_roots)
+            .append(&self.exact_body_size)
+            .append(&self.transaction_checks);
+    }
+}
+
+// The rules added later are appended, so the activations written before them decode with the new rules inactive.
+impl Decodable for Activations {
+    fn decode(rlp: &Rlp<'_>) -> Result<Self, DecoderError> {
+        let size = rlp.item_count()?;
+        if size > 5 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                expected: 5,
+                got: size,
+            })
+        }
+        let mut activations = Self::inactive();
+        if size > 0 {
+            activations.timestamped_votes = rlp.val_at(0)?;
+        }
+        if size > 1 {
+            activations.external_anchor = rlp.val_at(1)?;
+        }
+        if size > 2 {
+            activations.module_roots = rlp.val_at(2)?;
+        }
+        if size > 3 {
+            activations.exact_body_size = rlp.val_at(3)?;
+        }
+        if size > 4 {
+            activations.transaction_checks = rlp.val_at(4)?;
+        }
+        Ok(activations)
+    }
+}
+
+impl From<JsonActivations> for Activations {
+    fn from(a: JsonActivations) -> Self {
+        let genesis = Self::from_genesis();
+        Self {
+            timestamped_votes: a.timestamped_votes.map_or(genesis.timestamped_votes, Into::into),
+            external_anchor: a.external_anchor.map_or(genesis.external_anchor, Into::into),
+            module_roots: a.module_roots.map_or(genesis.module_roots, Into::into),
+            exact_body_size: a.exact_body_size.map_or(genesis.exact_body_size, Into::into),
+            transaction_checks: a.transaction_checks.map_or(genesis.transaction_checks, Into::into),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rlp::rlp_encode_and_decode_test;
+
+    #[test]
+    fn encode_and_decode() {
+        rlp_encode_and_decode_test!(Activations::inactive());
+        rlp_encode_and_decode_test!(Activations::from_genesis());
+    }
+
+    #[test]
+    fn missing_activations_are_inactive() {
+        let activations: Activations = rlp::decode(&rlp::EMPTY_LIST_RLP).unwrap();
+        assert_eq!(Activations::inactive(), activations);
+        assert!(!activations.timestamped_votes(BlockNumber::max_value() - 1));
+
+        let mut s = RlpStream::new_list(1);
+        s.append(&100u64);
+        let activations: Activations = rlp::decode(&s.out()).unwrap();
+        assert!(activations.timestamped_votes(100));
+        assert!(!activations.external_anchor(BlockNumber::max_value() - 1));
+        assert!(!activations.module_roots(BlockNumber::max_value() - 1));
+        assert!(!activations.exact_body_size(BlockNumber::max_value() - 1));
+        assert!(!activations.transaction_checks(BlockNumber::max_value() - 1));
+    }
+
+    #[test]
+    fn with_change_moves_the_activation() {
+        let activations = Activations::inactive().with_change("timestamped_votes_activation", 100).unwrap();
+        assert!(!activations.timestamped_votes(99));
+        assert!(activations.timestamped_votes(100));
+        assert!(Activations::inactive().with_change("unknown", 100).is_err());
+    }
+}
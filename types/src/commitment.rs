@@ -0,0 +1,292 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! What a light client of another Foundry chain needs to follow its headers and to check its module states.
+//!
+//! A header is final once more than two thirds of the delegation of its validators precommitted it.
+//! Those precommits are carried in the seal of the next block, and `Commit` takes them out of the
+//! engine-specific seal so they can be checked against the validator set alone.
+//! `ModuleDatumProof` then proves a datum of a module substorage against the `module_roots_hash` of a final header.
+
+use crate::{BlockHash, CompactValidatorSet, Header, StorageId};
+use ccrypto::{blake256, Blake, BLAKE_NULL_RLP};
+use ckey::{verify_all, Signature};
+use merkle_trie::proof::{verify, CryptoProof, CryptoProofUnit};
+use merkle_trie::skewed_merkle_root;
+use primitives::{Bytes, H256};
+use rlp::{Encodable, RlpStream};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// The step number of a precommit in the Tendermint votes.
+const PRECOMMIT_STEP: u8 = 2;
+/// The prefix of the module data in the state trie.
+const MODULE_DATUM_PREFIX: u8 = b'S';
+
+/// The hash a validator signs for a Tendermint vote, given the RLP of what it votes on.
+/// It covers the time the validator voted at as well. The votes made before the timestamped votes are activated
+/// have 0 as their timestamps, and sign only what they vote on as they did before.
+pub fn vote_hash(vote_on: &[u8], timestamp: u64) -> H256 {
+    if timestamp == 0 {
+        return blake256(vote_on)
+    }
+    let mut s = RlpStream::new_list(2);
+    s.append_raw(vote_on, 1);
+    s.append(&timestamp);
+    blake256(s.out())
+}
+
+/// The hash a validator signs to precommit a block: the `vote_hash` of its Tendermint vote on `block_hash`.
+pub fn precommit_hash(height: u64, view: u64, block_hash: &BlockHash, timestamp: u64) -> H256 {
+    let mut s = RlpStream::new_list(2);
+    s.begin_list(3).append(&height).append(&view).append(&PRECOMMIT_STEP);
+    s.append(&Some(*block_hash));
+    vote_hash(&s.out(), timestamp)
+}
+
+/// The commitment to the module substorage roots put in the header.
+/// A light client can check the root of a single module against it
+/// given only the other module roots, without the whole top-level state.
+pub fn module_roots_hash(module_roots: &[H256]) -> H256 {
+    skewed_merkle_root(BLAKE_NULL_RLP, module_roots.iter().map(Encodable::rlp_bytes))
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct Precommit {
+    /// The index of the signer in the validator set voting on the block.
+    pub signer_index: usize,
+    pub signature: Signature,
+    /// The time in seconds the signer precommitted at, which is signed together.
+    pub timestamp: u64,
+}
+
+/// The precommits that finalized a block, taken from the seal of its child.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Commit {
+    /// The view the block was finalized at.
+    pub view: u64,
+    pub precommits: Vec<Precommit>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum CommitError {
+    UnknownSigner(usize),
+    DuplicatedSigner(usize),
+    InvalidSignature(usize),
+    /// Not more than two thirds of the delegation precommitted the block.
+    NotEnoughDelegation {
+        signed: u64,
+        total: u64,
+    },
+}
+
+impl Commit {
+    /// Checks that more than two thirds of the delegation in `validator_set` precommitted `header`.
+    /// `validator_set` must be the one whose hash is the `next_validator_set_hash` of the parent of `header`.
+    pub fn verify(&self, header: &Header, validator_set: &CompactValidatorSet) -> Result<(), CommitError> {
+        let block_hash = header.hash();
+        let mut signers = HashSet::with_capacity(self.precommits.len());
+        let mut signed_delegation = 0u64;
+        let mut signed = Vec::with_capacity(self.precommits.len());
+        for precommit in &self.precommits {
+            let validator =
+                validator_set.get(precommit.signer_index).ok_or(CommitError::UnknownSigner(precommit.signer_index))?;
+            if !signers.insert(precommit.signer_index) {
+                return Err(CommitError::DuplicatedSigner(precommit.signer_index))
+            }
+            let message = precommit_hash(header.number(), self.view, &block_hash, precommit.timestamp);
+            signed.push((precommit.signature, message, validator.public_key));
+            signed_delegation += validator.delegation;
+        }
+        if let Some(index) = verify_all(&signed).iter().position(|is_valid| !is_valid) {
+            return Err(CommitError::InvalidSignature(self.precommits[index].signer_index))
+        }
+
+        let total_delegation: u64 = validator_set.iter().map(|validator| validator.delegation).sum();
+        if signed_delegation * 3 > total_delegation * 2 {
+            Ok(())
+        } else {
+            Err(CommitError::NotEnoughDelegation {
+                signed: signed_delegation,
+                total: total_delegation,
+            })
+        }
+    }
+}
+
+/// Proves the datum a module has under a key, or that it has none, in the state of a block.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct ModuleDatumProof {
+    pub storage_id: StorageId,
+    /// The key the module used, not the one in the trie.
+    pub key: Bytes,
+    /// `None` proves the absence of the key.
+    pub datum: Option<Bytes>,
+    /// The roots of all module substorages, in the order of their storage ids.
+    pub module_roots: Vec<H256>,
+    /// The trie nodes on the path to the key in the substorage of the module.
+    pub nodes: Vec<Bytes>,
+}
+
+impl ModuleDatumProof {
+    /// Checks the proof against the `module_roots_hash` of the header of the block.
+    pub fn verify(&self, module_roots_hash: &H256) -> bool {
+        if module_roots_hash != &self::module_roots_hash(&self.module_roots) {
+            return false
+        }
+        let root = match self.module_roots.get(self.storage_id as usize) {
+            Some(root) => *root,
+            None => return false,
+        };
+        let unit = CryptoProofUnit {
+            root,
+            key: module_datum_address(&self.key, self.storage_id).to_vec(),
+            value: self.datum.as_ref().map(|datum| encode_module_datum(datum)),
+        };
+        verify(&CryptoProof(self.nodes.clone()), &unit)
+    }
+}
+
+/// The key of a module datum in the substorage trie.
+pub fn module_datum_address(key: &[u8], storage_id: StorageId) -> H256 {
+    let mut address: H256 = Blake::blake(key);
+    address[0..2].copy_from_slice(&[MODULE_DATUM_PREFIX, 0]);
+    address[2..4].copy_from_slice(&storage_id.to_be_bytes());
+    address
+}
+
+/// The value of a module datum in the substorage trie.
+pub fn encode_module_datum(datum: &[u8]) -> Bytes {
+    let mut s = RlpStream::new_list(2);
+    s.append(&MODULE_DATUM_PREFIX);
+    s.append(&datum.to_vec());
+    s.out()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CompactValidatorEntry;
+    use ckey::{sign, Ed25519KeyPair, Generator, KeyPairTrait, Random};
+
+    fn validators(count: usize) -> (Vec<Ed25519KeyPair>, CompactValidatorSet) {
+        let key_pairs: Vec<Ed25519KeyPair> = (0..count).map(|_| Random.generate().unwrap()).collect();
+        let validator_set = CompactValidatorSet::new(
+            key_pairs
+                .iter()
+                .map(|key_pair| CompactValidatorEntry {
+                    public_key: *key_pair.public(),
+                    delegation: 1,
+                })
+                .collect(),
+        );
+        (key_pairs, validator_set)
+    }
+
+    fn commit(header: &Header, view: u64, signers: &[(usize, &Ed25519KeyPair)]) -> Commit {
+        commit_at(header, view, signers, |signer_index| 1_600_000_000 + signer_index as u64)
+    }
+
+    fn commit_at<F>(header: &Header, view: u64, signers: &[(usize, &Ed25519KeyPair)], timestamp: F) -> Commit
+    where
+        F: Fn(usize) -> u64, {
+        Commit {
+            view,
+            precommits: signers
+                .iter()
+                .map(|(signer_index, key_pair)| {
+                    let timestamp = timestamp(*signer_index);
+                    let message = precommit_hash(header.number(), view, &header.hash(), timestamp);
+                    Precommit {
+                        signer_index: *signer_index,
+                        signature: sign(message.as_ref(), key_pair.private()),
+                        timestamp,
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    fn header() -> Header {
+        let mut header = Header::default();
+        header.set_number(10);
+        header
+    }
+
+    #[test]
+    fn commit_of_more_than_two_thirds_is_verified() {
+        let (key_pairs, validator_set) = validators(4);
+        let header = header();
+        let commit = commit(&header, 1, &[(0, &key_pairs[0]), (2, &key_pairs[2]), (3, &key_pairs[3])]);
+        assert_eq!(Ok(()), commit.verify(&header, &validator_set));
+    }
+
+    #[test]
+    fn commit_made_before_the_timestamped_votes_is_verified() {
+        let (key_pairs, validator_set) = validators(4);
+        let header = header();
+        let commit = commit_at(&header, 1, &[(0, &key_pairs[0]), (2, &key_pairs[2]), (3, &key_pairs[3])], |_| 0);
+        assert_eq!(Ok(()), commit.verify(&header, &validator_set));
+    }
+
+    #[test]
+    fn commit_of_two_thirds_is_not_enough() {
+        let (key_pairs, validator_set) = validators(3);
+        let header = header();
+        let commit = commit(&header, 1, &[(0, &key_pairs[0]), (1, &key_pairs[1])]);
+        assert_eq!(
+            Err(CommitError::NotEnoughDelegation {
+                signed: 2,
+                total: 3
+            }),
+            commit.verify(&header, &validator_set)
+        );
+    }
+
+    #[test]
+    fn commit_on_another_view_is_rejected() {
+        let (key_pairs, validator_set) = validators(1);
+        let header = header();
+        let mut commit = commit(&header, 1, &[(0, &key_pairs[0])]);
+        commit.view = 2;
+        assert_eq!(Err(CommitError::InvalidSignature(0)), commit.verify(&header, &validator_set));
+    }
+
+    #[test]
+    fn duplicated_signer_is_counted_once() {
+        let (key_pairs, validator_set) = validators(4);
+        let header = header();
+        let commit = commit(&header, 1, &[(0, &key_pairs[0]), (0, &key_pairs[0]), (1, &key_pairs[1])]);
+        assert_eq!(Err(CommitError::DuplicatedSigner(0)), commit.verify(&header, &validator_set));
+    }
+
+    #[test]
+    fn proof_with_other_module_roots_is_rejected() {
+        let proof = ModuleDatumProof {
+            storage_id: 0,
+            key: b"key".to_vec(),
+            datum: None,
+            module_roots: vec![BLAKE_NULL_RLP],
+            nodes: Vec::new(),
+        };
+        assert!(!proof.verify(&module_roots_hash(&[BLAKE_NULL_RLP, BLAKE_NULL_RLP])));
+        assert!(!ModuleDatumProof {
+            storage_id: 1,
+            ..proof
+        }
+        .verify(&module_roots_hash(&[BLAKE_NULL_RLP])));
+    }
+}
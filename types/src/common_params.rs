@@ -181,6 +181,8 @@ impl From<CommonParams> for Params {
             delegation_threshold: p.delegation_threshold().into(),
             min_deposit: p.min_deposit().into(),
             max_candidate_metadata_size: p.max_candidate_metadata_size().into(),
+            seeded_tx_ordering: None,
+            activations: None,
             era: None,
         };
         let era = p.era();
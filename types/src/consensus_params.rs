@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::{Activations, TxOrdering};
 use cjson::scheme::Params;
 use ckey::NetworkId;
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
@@ -32,6 +33,12 @@ pub struct ConsensusParams {
     snapshot_period: u64,
 
     term_seconds: u64,
+    /// The rule deciding the order of the transactions in a block.
+    #[serde(default)]
+    tx_ordering: TxOrdering,
+    /// The block numbers from which the rules added after the chain started apply.
+    #[serde(default)]
+    activations: Activations,
 }
 
 impl ConsensusParams {
@@ -50,6 +57,48 @@ impl ConsensusParams {
     pub fn term_seconds(&self) -> u64 {
         self.term_seconds
     }
+    pub fn tx_ordering(&self) -> TxOrdering {
+        self.tx_ordering
+    }
+    pub fn activations(&self) -> &Activations {
+        &self.activations
+    }
+
+    /// Returns the params with a field changed by its name, for the fields that can change once the chain runs.
+    pub fn with_change(mut self, name: &str, value: u64) -> Result<Self, String> {
+        match name {
+            "max_extra_data_size" => self.max_extra_data_size = value,
+            "max_body_size" => self.max_body_size = value,
+            "snapshot_period" => self.snapshot_period = value,
+            "term_seconds" => self.term_seconds = value,
+            _ if name.ends_with("_activation") => self.activations = self.activations.with_change(name, value)?,
+            _ => return Err(format!("The consensus param {} can't be changed", name)),
+        }
+        Ok(self)
+    }
+
+    pub fn verify(&self) -> Result<(), String> {
+        if self.max_body_size == 0 {
+            return Err("You should set the maximum body size".to_string())
+        }
+        if self.snapshot_period == 0 {
+            return Err("You should set the snapshot period".to_string())
+        }
+        Ok(())
+    }
+
+    pub fn verify_change(&self, current_params: &Self) -> Result<(), String> {
+        self.verify()?;
+        let current_network_id = current_params.network_id();
+        let transaction_network_id = self.network_id();
+        if current_network_id != transaction_network_id {
+            return Err(format!(
+                "The current network id is {} but the transaction tries to change the network id to {}",
+                current_network_id, transaction_network_id
+            ))
+        }
+        Ok(())
+    }
 
     pub fn default_for_test() -> Self {
         Self {
@@ -58,27 +107,46 @@ impl ConsensusParams {
             max_body_size: 100_000,
             snapshot_period: 1000,
             term_seconds: 1000,
+            tx_ordering: TxOrdering::Proposer,
+            activations: Activations::inactive(),
         }
     }
 }
 
+// The ordering and the activations are appended only when they're not the defaults,
+// so that the states written before they existed keep their roots.
 impl Encodable for ConsensusParams {
     fn rlp_append(&self, s: &mut RlpStream) {
-        s.begin_list(5)
+        let has_activations = self.activations != Activations::default();
+        let has_tx_ordering = has_activations || self.tx_ordering != TxOrdering::default();
+        let len = if has_activations {
+            7
+        } else if has_tx_ordering {
+            6
+        } else {
+            5
+        };
+        s.begin_list(len)
             .append(&self.max_extra_data_size)
             .append(&self.network_id)
             .append(&self.max_body_size)
             .append(&self.snapshot_period)
             .append(&self.term_seconds);
+        if has_tx_ordering {
+            s.append(&self.tx_ordering);
+        }
+        if has_activations {
+            s.append(&self.activations);
+        }
     }
 }
 
 impl Decodable for ConsensusParams {
     fn decode(rlp: &Rlp<'_>) -> Result<Self, DecoderError> {
         let size = rlp.item_count()?;
-        if size != 5 {
+        if size < 5 || size > 7 {
             return Err(DecoderError::RlpIncorrectListLen {
-                expected: 5,
+                expected: 7,
                 got: size,
             })
         }
@@ -88,6 +156,16 @@ impl Decodable for ConsensusParams {
         let max_body_size = rlp.val_at(2)?;
         let snapshot_period = rlp.val_at(3)?;
         let term_seconds = rlp.val_at(4)?;
+        let tx_ordering = if size >= 6 {
+            rlp.val_at(5)?
+        } else {
+            TxOrdering::default()
+        };
+        let activations = if size == 7 {
+            rlp.val_at(6)?
+        } else {
+            Activations::default()
+        };
 
         Ok(Self {
             max_extra_data_size,
@@ -95,6 +173,8 @@ impl Decodable for ConsensusParams {
             max_body_size,
             snapshot_period,
             term_seconds,
+            tx_ordering,
+            activations,
         })
     }
 }
@@ -107,6 +187,12 @@ impl From<Params> for ConsensusParams {
             max_body_size: p.max_body_size.into(),
             snapshot_period: p.snapshot_period.into(),
             term_seconds: p.term_seconds.into(),
+            tx_ordering: if p.seeded_tx_ordering.unwrap_or(false) {
+                TxOrdering::Seeded
+            } else {
+                TxOrdering::Proposer
+            },
+            activations: p.activations.map_or_else(Activations::from_genesis, From::from),
         }
     }
 }
@@ -128,4 +214,60 @@ mod tests {
         params.max_body_size = 123;
         rlp_encode_and_decode_test!(params);
     }
+
+    #[test]
+    fn rlp_with_seeded_tx_ordering() {
+        let mut params = ConsensusParams::default_for_test();
+        params.tx_ordering = TxOrdering::Seeded;
+        rlp_encode_and_decode_test!(params);
+    }
+
+    #[test]
+    fn rlp_with_activations() {
+        let mut params = ConsensusParams::default_for_test();
+        params.activations = Activations::from_genesis();
+        assert_eq!(7, Rlp::new(&rlp::encode(&params)).item_count().unwrap());
+        rlp_encode_and_decode_test!(params);
+    }
+
+    #[test]
+    fn with_change_schedules_an_activation() {
+        let params = ConsensusParams::default_for_test().with_change("timestamped_votes_activation", 100).unwrap();
+        assert!(!params.activations().timestamped_votes(99));
+        assert!(params.activations().timestamped_votes(100));
+    }
+
+    #[test]
+    fn default_tx_ordering_keeps_the_old_encoding() {
+        let params = ConsensusParams::default_for_test();
+        assert_eq!(5, Rlp::new(&rlp::encode(&params)).item_count().unwrap());
+    }
+
+    #[test]
+    fn verify_change_rejects_network_id_change() {
+        let current = ConsensusParams::default_for_test();
+        let mut params = current;
+        params.max_body_size = 200_000;
+        assert_eq!(Ok(()), params.verify_change(&current));
+
+        params.network_id = NetworkId::from_str("tc").unwrap();
+        assert!(params.verify_change(&current).is_err());
+    }
+
+    #[test]
+    fn verify_change_rejects_zero_body_size() {
+        let current = ConsensusParams::default_for_test();
+        let mut params = current;
+        params.max_body_size = 0;
+        assert!(params.verify_change(&current).is_err());
+    }
+
+    #[test]
+    fn with_change_keeps_the_network_id() {
+        let current = ConsensusParams::default_for_test();
+        let params = current.with_change("max_body_size", 200_000).unwrap();
+        assert_eq!(200_000, params.max_body_size());
+        assert_eq!(current.network_id(), params.network_id());
+        assert!(current.with_change("network_id", 0).is_err());
+    }
 }
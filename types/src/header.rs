@@ -48,6 +48,9 @@ pub struct Header {
 
     /// Block extra data.
     extra_data: Bytes,
+    /// External anchor (e.g. a hash to be notarized on another chain) set by a module on closing the block.
+    /// It's encoded in the header extension, so a header without it encodes the same as the headers made before it.
+    external_anchor: Option<H256>,
 
     /// Evidences root
     evidenecs_root: H256,
@@ -57,6 +60,9 @@ pub struct Header {
     state_root: H256,
     /// Next validator set hash.
     next_validator_set_hash: H256,
+    /// Hash of the roots of the module substorages, in the order of their storage ids.
+    /// It's encoded in the header extension, and it's not set in the blocks made before it's activated.
+    module_roots_hash: Option<H256>,
 
     /// Vector of post-RLP-encoded fields.
     seal: Vec<Bytes>,
@@ -77,11 +83,13 @@ impl Default for Header {
             author: Default::default(),
             last_committed_validators: Default::default(),
             extra_data: vec![],
+            external_anchor: None,
 
             evidenecs_root: BLAKE_NULL_RLP,
             transactions_root: BLAKE_NULL_RLP,
             state_root: BLAKE_NULL_RLP,
             next_validator_set_hash: BLAKE_NULL_RLP,
+            module_roots_hash: None,
 
             seal: vec![],
             hash: RefCell::new(None),
@@ -90,7 +98,15 @@ impl Default for Header {
     }
 }
 
-const SIZE_WITHOUT_SEAL: usize = 10;
+/// The number of the fields every header has before the extension and the seal.
+pub const FIXED_FIELDS: usize = 10;
+
+/// The index of the header extension.
+///
+/// The extension is an RLP list of the fields added to the header after the chains started, each of them appended
+/// and optional, and it's omitted while the header has none of them. So the headers made before a field is activated
+/// keep their encoding and hash. It's told from the seal by being a list: the first seal field of every engine is not.
+pub const EXTENSION_INDEX: usize = FIXED_FIELDS;
 
 impl Header {
     /// Create a new, default-valued, header.
@@ -129,6 +145,11 @@ impl Header {
         &mut self.extra_data
     }
 
+    /// Get the external anchor field of the header.
+    pub fn external_anchor(&self) -> Option<&H256> {
+        self.external_anchor.as_ref()
+    }
+
     /// Get the state root field of the header.
     pub fn state_root(&self) -> &H256 {
         &self.state_root
@@ -149,6 +170,11 @@ impl Header {
         &self.next_validator_set_hash
     }
 
+    /// Get the module roots hash field of the header.
+    pub fn module_roots_hash(&self) -> Option<&H256> {
+        self.module_roots_hash.as_ref()
+    }
+
     /// Get whether the block has transactions.
     pub fn is_empty(&self) -> bool {
         self.transactions_root() == &BLAKE_NULL_RLP
@@ -207,6 +233,14 @@ impl Header {
         }
     }
 
+    /// Set the external anchor field of the header.
+    pub fn set_external_anchor(&mut self, a: Option<H256>) {
+        if a != self.external_anchor {
+            self.external_anchor = a;
+            self.note_dirty();
+        }
+    }
+
     /// Set the state root field of the header.
     pub fn set_state_root(&mut self, a: H256) {
         self.state_root = a;
@@ -227,6 +261,13 @@ impl Header {
         self.next_validator_set_hash = a;
         self.note_dirty()
     }
+    /// Set the module roots hash field of the header.
+    pub fn set_module_roots_hash(&mut self, a: Option<H256>) {
+        if a != self.module_roots_hash {
+            self.module_roots_hash = a;
+            self.note_dirty();
+        }
+    }
     /// Set the seal field of the header.
     pub fn set_seal(&mut self, a: Vec<Bytes>) {
         self.seal = a;
@@ -259,10 +300,16 @@ impl Header {
         }
     }
 
+    /// Whether the header has a field of the extension.
+    fn has_extension(&self) -> bool {
+        self.external_anchor.is_some() || self.module_roots_hash.is_some()
+    }
+
     /// Place this header into an RLP stream `s`, optionally `with_seal`.
     pub fn stream_rlp(&self, s: &mut RlpStream, with_seal: &Seal) {
         s.begin_list(
-            SIZE_WITHOUT_SEAL
+            FIXED_FIELDS
+                + self.has_extension() as usize
                 + match with_seal {
                     Seal::With => self.seal.len(),
                     _ => 0,
@@ -278,6 +325,13 @@ impl Header {
         s.append(&self.timestamp);
         s.append_list(&self.last_committed_validators);
         s.append(&self.extra_data);
+        if self.has_extension() {
+            s.begin_list(1 + self.module_roots_hash.is_some() as usize);
+            s.append(&encode_external_anchor(self.external_anchor.as_ref()));
+            if let Some(module_roots_hash) = &self.module_roots_hash {
+                s.append(module_roots_hash);
+            }
+        }
         if let Seal::With = with_seal {
             for b in &self.seal {
                 s.append_raw(b, 1);
@@ -328,12 +382,22 @@ impl Decodable for Header {
             timestamp: cmp::min(r.val_at::<U256>(7)?, u64::max_value().into()).as_u64(),
             last_committed_validators: r.list_at(8)?,
             extra_data: r.val_at(9)?,
+            external_anchor: None,
+            module_roots_hash: None,
             seal: vec![],
             hash: RefCell::new(Some(blake256(r.as_raw()))),
             bare_hash: RefCell::new(None),
         };
 
-        for i in SIZE_WITHOUT_SEAL..r.item_count()? {
+        let seal_index = if has_extension(r)? {
+            let extension = decode_extension(&r.at(EXTENSION_INDEX)?)?;
+            blockheader.external_anchor = extension.external_anchor;
+            blockheader.module_roots_hash = extension.module_roots_hash;
+            EXTENSION_INDEX + 1
+        } else {
+            FIXED_FIELDS
+        };
+        for i in seal_index..r.item_count()? {
             blockheader.seal.push(r.at(i)?.as_raw().to_vec())
         }
 
@@ -341,6 +405,61 @@ impl Decodable for Header {
     }
 }
 
+/// Whether the header rlp has the extension.
+pub fn has_extension(r: &Rlp<'_>) -> Result<bool, DecoderError> {
+    Ok(r.item_count()? > EXTENSION_INDEX && r.at(EXTENSION_INDEX)?.is_list())
+}
+
+/// The fields of the header extension.
+#[derive(Default)]
+pub struct Extension {
+    pub external_anchor: Option<H256>,
+    pub module_roots_hash: Option<H256>,
+}
+
+/// Decodes the fields of the extension. The external anchor is always encoded, and the module roots hash follows it
+/// when it's set.
+///
+/// An extension without any field is rejected, since the header would be encoded without it.
+pub fn decode_extension(r: &Rlp<'_>) -> Result<Extension, DecoderError> {
+    let item_count = r.item_count()?;
+    if item_count < 1 || item_count > 2 {
+        return Err(DecoderError::RlpIncorrectListLen {
+            expected: 2,
+            got: item_count,
+        })
+    }
+    let external_anchor = decode_external_anchor(r.at(0)?.data()?)?;
+    let module_roots_hash = if item_count > 1 {
+        Some(r.val_at(1)?)
+    } else {
+        None
+    };
+    if external_anchor.is_none() && module_roots_hash.is_none() {
+        return Err(DecoderError::Custom("The header extension has no field"))
+    }
+    Ok(Extension {
+        external_anchor,
+        module_roots_hash,
+    })
+}
+
+/// The external anchor is encoded as an empty string when it's not set.
+fn encode_external_anchor(anchor: Option<&H256>) -> Bytes {
+    anchor.map(|anchor| anchor.as_bytes().to_vec()).unwrap_or_default()
+}
+
+pub fn decode_external_anchor(bytes: &[u8]) -> Result<Option<H256>, DecoderError> {
+    match bytes.len() {
+        0 => Ok(None),
+        32 => Ok(Some(H256::from_slice(bytes))),
+        got => Err(DecoderError::RlpInvalidLength {
+            expected: 32,
+            got,
+        }),
+    }
+}
+
 impl Encodable for Header {
     fn rlp_append(&self, s: &mut RlpStream) {
         self.stream_rlp(s, &Seal::With);
@@ -358,4 +477,60 @@ mod tests {
         let decoded: Header = rlp::decode(&encoded).unwrap();
         assert_eq!(empty.hash(), decoded.hash());
     }
+
+    #[test]
+    fn external_anchor_is_encoded_in_the_extension() {
+        let mut header = Header::default();
+        header.set_external_anchor(Some(blake256("anchor")));
+        header.set_seal(vec![rlp::encode(&1u64), rlp::encode(&2u64)]);
+        let encoded = rlp::encode(&header);
+        assert_eq!(FIXED_FIELDS + 1 + 2, Rlp::new(&encoded).item_count().unwrap());
+        let decoded: Header = rlp::decode(&encoded).unwrap();
+        assert_eq!(Some(&blake256("anchor")), decoded.external_anchor());
+        assert_eq!(header.seal(), decoded.seal());
+        assert_eq!(header.hash(), decoded.hash());
+    }
+
+    #[test]
+    fn header_without_anchor_and_module_roots_has_no_extension() {
+        let mut header = Header::default();
+        header.set_seal(vec![rlp::encode(&1u64), rlp::encode(&2u64)]);
+        let encoded = rlp::encode(&header);
+        assert_eq!(FIXED_FIELDS + 2, Rlp::new(&encoded).item_count().unwrap());
+        let decoded: Header = rlp::decode(&encoded).unwrap();
+        assert_eq!(None, decoded.external_anchor());
+        assert_eq!(header.seal(), decoded.seal());
+        assert_eq!(header.hash(), decoded.hash());
+    }
+
+    #[test]
+    fn empty_extension_is_rejected() {
+        let header = Header::default();
+        let mut s = RlpStream::new_list(FIXED_FIELDS + 1);
+        for item in Rlp::new(&header.rlp(&Seal::Without)).iter() {
+            s.append_raw(item.as_raw(), 1);
+        }
+        s.begin_list(1).append(&Vec::<u8>::new());
+        assert!(rlp::decode::<Header>(&s.out()).is_err());
+    }
+
+    #[test]
+    fn module_roots_hash_is_encoded_in_the_extension() {
+        let mut header = Header::default();
+        header.set_module_roots_hash(Some(blake256("module roots")));
+        header.set_seal(vec![rlp::encode(&1u64)]);
+        let encoded = rlp::encode(&header);
+        assert_eq!(2, Rlp::new(&encoded).at(EXTENSION_INDEX).unwrap().item_count().unwrap());
+        let decoded: Header = rlp::decode(&encoded).unwrap();
+        assert_eq!(None, decoded.external_anchor());
+        assert_eq!(Some(&blake256("module roots")), decoded.module_roots_hash());
+        assert_eq!(header.seal(), decoded.seal());
+        assert_eq!(header.bare_hash(), decoded.bare_hash());
+
+        header.set_external_anchor(Some(blake256("anchor")));
+        let decoded: Header = rlp::decode(&rlp::encode(&header)).unwrap();
+        assert_eq!(Some(&blake256("anchor")), decoded.external_anchor());
+        assert_eq!(Some(&blake256("module roots")), decoded.module_roots_hash());
+        assert_eq!(header.hash(), decoded.hash());
+    }
 }
@@ -19,17 +19,23 @@ extern crate serde_derive;
 #[macro_use]
 extern crate rlp_derive;
 
+mod activations;
 mod block_hash;
 mod block_id;
+mod commitment;
 mod common_params;
 mod consensus_params;
 mod deposit;
+mod network_identity;
 mod sync_header;
 mod tx_hash;
+mod tx_ordering;
+mod validator_key_change;
 mod validator_set;
 
 pub mod errors;
 pub mod header;
+pub mod schema;
 pub mod transaction;
 pub mod util;
 
@@ -43,13 +49,22 @@ pub struct TransactionLocation {
     pub transaction_index: TransactionIndex,
 }
 
+pub use activations::Activations;
 pub use block_hash::BlockHash;
 pub use block_id::BlockId;
+pub use commitment::{
+    encode_module_datum, module_datum_address, module_roots_hash, precommit_hash, vote_hash, Commit, CommitError,
+    ModuleDatumProof, Precommit,
+};
 pub use common_params::CommonParams;
 pub use consensus_params::ConsensusParams;
 pub use deposit::Deposit;
 pub use header::Header;
+pub use network_identity::NetworkIdentity;
+pub use schema::schema;
 pub use sync_header::SyncHeader;
 pub use tx_hash::TxHash;
+pub use tx_ordering::TxOrdering;
+pub use validator_key_change::ValidatorKeyChange;
 pub use validator_set::CompactValidatorEntry;
 pub use validator_set::CompactValidatorSet;
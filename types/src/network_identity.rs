@@ -0,0 +1,109 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccrypto::blake256;
+use ckey::{sign, verify, Ed25519Private as Private, Ed25519Public as Public, Signature};
+use primitives::H256;
+use rlp::RlpStream;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+
+/// Binds the network address and the node key of a validator's node to the validator's consensus key.
+/// It is signed with both keys: the consensus key claims the node, and the node key consents to the claim.
+/// So a validator can't claim a node it doesn't run, and the node proves the node key in the p2p handshake.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct NetworkIdentity {
+    pub validator: Public,
+    pub node_key: Public,
+    pub node_address: SocketAddr,
+    pub signature: Signature,
+    pub node_signature: Signature,
+}
+
+impl NetworkIdentity {
+    pub fn new_signed(
+        validator: Public,
+        validator_private: &Private,
+        node_key: Public,
+        node_private: &Private,
+        node_address: SocketAddr,
+    ) -> Self {
+        let message = Self::message(&validator, &node_key, &node_address);
+        Self {
+            validator,
+            node_key,
+            node_address,
+            signature: sign(message.as_ref(), validator_private),
+            node_signature: sign(message.as_ref(), node_private),
+        }
+    }
+
+    /// The message that both the validator and the node sign.
+    pub fn message(validator: &Public, node_key: &Public, node_address: &SocketAddr) -> H256 {
+        let mut s = RlpStream::new_list(3);
+        s.append(validator);
+        s.append(node_key);
+        s.append(&node_address.to_string());
+        blake256(s.out())
+    }
+
+    pub fn verify(&self) -> bool {
+        let message = Self::message(&self.validator, &self.node_key, &self.node_address);
+        verify(&self.signature, message.as_ref(), &self.validator)
+            && verify(&self.node_signature, message.as_ref(), &self.node_key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckey::{Ed25519KeyPair, Generator, KeyPairTrait, Random};
+
+    fn identity(validator: &Ed25519KeyPair, node: &Ed25519KeyPair) -> NetworkIdentity {
+        let address = "127.0.0.1:3485".parse().unwrap();
+        NetworkIdentity::new_signed(*validator.public(), validator.private(), *node.public(), node.private(), address)
+    }
+
+    #[test]
+    fn signed_identity_is_verified() {
+        let validator: Ed25519KeyPair = Random.generate().unwrap();
+        let node: Ed25519KeyPair = Random.generate().unwrap();
+        assert!(identity(&validator, &node).verify());
+    }
+
+    #[test]
+    fn identity_with_another_address_is_not_verified() {
+        let validator: Ed25519KeyPair = Random.generate().unwrap();
+        let node: Ed25519KeyPair = Random.generate().unwrap();
+        let mut identity = identity(&validator, &node);
+        identity.node_address = "127.0.0.1:3486".parse().unwrap();
+        assert!(!identity.verify());
+    }
+
+    #[test]
+    fn identity_claiming_another_node_key_is_not_verified() {
+        let validator: Ed25519KeyPair = Random.generate().unwrap();
+        let node: Ed25519KeyPair = Random.generate().unwrap();
+        let others_node: Ed25519KeyPair = Random.generate().unwrap();
+        let mut identity = identity(&validator, &node);
+        identity.node_key = *others_node.public();
+        identity.signature = sign(
+            NetworkIdentity::message(&identity.validator, &identity.node_key, &identity.node_address).as_ref(),
+            validator.private(),
+        );
+        assert!(!identity.verify());
+    }
+}
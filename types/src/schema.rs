@@ -0,0 +1,292 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Machine-readable descriptions of the RLP encodings of the chain data,
+//! for the generators of the SDK bindings and for diffing the encodings across releases.
+//!
+//! The version of a schema is bumped whenever its encoding changes. The tests of each encoder check
+//! its output against the schema with `TypeSchema::check`, and compare every field with a golden encoding,
+//! so a field that moves or changes its type without a new version fails them.
+
+use ckey::{Ed25519Public as Public, NetworkId};
+use primitives::{Bytes, H256};
+use rlp::Rlp;
+
+/// A field of an RLP list.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldSchema {
+    pub name: &'static str,
+    /// The index of the field in the RLP list.
+    pub position: usize,
+    /// The type of the field: a primitive like `u64`, `H256` or `Bytes`, or `List<T>` of one.
+    #[serde(rename = "type")]
+    pub ty: &'static str,
+}
+
+/// The encoding of a type as an RLP list.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypeSchema {
+    pub name: &'static str,
+    pub version: u32,
+    pub fields: Vec<FieldSchema>,
+    /// The items that may follow the fields, described in words.
+    pub trailing: Option<&'static str>,
+}
+
+impl TypeSchema {
+    /// Checks that the bytes are an RLP list of the fields, each decoding as its type.
+    /// The trailing items are only counted.
+    pub fn check(&self, bytes: &[u8]) -> Result<(), String> {
+        let rlp = Rlp::new(bytes);
+        let item_count = rlp.item_count().map_err(|err| format!("{} is not a list: {}", self.name, err))?;
+        if item_count < self.fields.len() || (self.trailing.is_none() && item_count > self.fields.len()) {
+            return Err(format!("{} has {} items, but {} fields", self.name, item_count, self.fields.len()))
+        }
+        for field in &self.fields {
+            let item = rlp.at(field.position).map_err(|err| format!("{}.{}: {}", self.name, field.name, err))?;
+            check_type(&item, field.ty).map_err(|err| format!("{}.{}: {}", self.name, field.name, err))?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks that the item decodes as the type named in a schema.
+fn check_type(item: &Rlp<'_>, ty: &str) -> Result<(), String> {
+    let result = match ty {
+        "u64" => item.as_val::<u64>().map(|_| ()),
+        "H256" => item.as_val::<H256>().map(|_| ()),
+        "Ed25519Public" => item.as_val::<Public>().map(|_| ()),
+        "NetworkId" => item.as_val::<NetworkId>().map(|_| ()),
+        "Bytes" => item.as_val::<Bytes>().map(|_| ()),
+        "String" => item.as_val::<String>().map(|_| ()),
+        _ => {
+            let element = ty
+                .strip_prefix("List<")
+                .and_then(|ty| ty.strip_suffix('>'))
+                .ok_or_else(|| format!("unknown type {}", ty))?;
+            if !item.is_list() {
+                return Err(format!("{} is not a list", ty))
+            }
+            return item.iter().try_for_each(|item| check_type(&item, element))
+        }
+    };
+    result.map_err(|err| format!("not a {}: {}", ty, err))
+}
+
+fn fields(fields: &[(&'static str, &'static str)]) -> Vec<FieldSchema> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(position, &(name, ty))| FieldSchema {
+            name,
+            position,
+            ty,
+        })
+        .collect()
+}
+
+pub fn header_schema() -> TypeSchema {
+    TypeSchema {
+        name: "Header",
+        version: 2,
+        fields: fields(&[
+            ("parentHash", "H256"),
+            ("author", "Ed25519Public"),
+            ("stateRoot", "H256"),
+            ("evidencesRoot", "H256"),
+            ("transactionsRoot", "H256"),
+            ("nextValidatorSetHash", "H256"),
+            ("number", "u64"),
+            ("timestamp", "u64"),
+            ("lastCommittedValidators", "List<Ed25519Public>"),
+            ("extraData", "Bytes"),
+        ]),
+        trailing: Some(
+            "The extension, a list of [externalAnchor: Bytes, moduleRootsHash: H256] without the hash when it's not \
+             set, present only when either is set, then the seal of the consensus engine, an RLP item each, \
+             only in the sealed form",
+        ),
+    }
+}
+
+pub fn common_params_schema() -> TypeSchema {
+    TypeSchema {
+        name: "CommonParams",
+        version: 1,
+        fields: fields(&[
+            ("maxExtraDataSize", "u64"),
+            ("networkId", "NetworkId"),
+            ("maxBodySize", "u64"),
+            ("snapshotPeriod", "u64"),
+            ("termSeconds", "u64"),
+            ("nominationExpiration", "u64"),
+            ("custodyPeriod", "u64"),
+            ("releasePeriod", "u64"),
+            ("maxNumOfValidators", "u64"),
+            ("minNumOfValidators", "u64"),
+            ("delegationThreshold", "u64"),
+            ("minDeposit", "u64"),
+            ("maxCandidateMetadataSize", "u64"),
+            ("era", "u64"),
+        ]),
+        trailing: None,
+    }
+}
+
+/// The envelope of a transaction, encoded by the coordinator. The body is opaque to the host.
+pub fn transaction_schema() -> TypeSchema {
+    TypeSchema {
+        name: "Transaction",
+        version: 1,
+        fields: fields(&[("txType", "String"), ("body", "Bytes")]),
+        trailing: None,
+    }
+}
+
+/// An event emitted by a transaction, encoded by the coordinator.
+/// The receipt of a transaction is the list of its events.
+pub fn event_schema() -> TypeSchema {
+    TypeSchema {
+        name: "Event",
+        version: 1,
+        fields: fields(&[("key", "String"), ("value", "Bytes")]),
+        trailing: None,
+    }
+}
+
+/// The schemas of all the types described.
+pub fn schema() -> Vec<TypeSchema> {
+    vec![header_schema(), common_params_schema(), transaction_schema(), event_schema()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::{Header, Seal};
+    use crate::CommonParams;
+    use ccrypto::blake256;
+    use cjson::scheme::Params;
+
+    /// Compares the item at the position of each field with its golden encoding.
+    fn assert_golden(schema: &TypeSchema, bytes: &[u8], golden: &[(&str, Vec<u8>)]) {
+        schema.check(bytes).unwrap();
+        let rlp = Rlp::new(bytes);
+        assert_eq!(schema.fields.len(), golden.len(), "{}", schema.name);
+        for field in &schema.fields {
+            let (_, expected) = golden
+                .iter()
+                .find(|(name, _)| *name == field.name)
+                .unwrap_or_else(|| panic!("No golden encoding of {}.{}", schema.name, field.name));
+            assert_eq!(&expected[..], rlp.at(field.position).unwrap().as_raw(), "{}.{}", schema.name, field.name);
+        }
+    }
+
+    #[test]
+    fn header_schema_matches_the_encoding() {
+        let author = Public::random();
+        let mut header = Header::default();
+        header.set_parent_hash(blake256("parent").into());
+        header.set_author(author);
+        header.set_state_root(blake256("state"));
+        header.set_evidences_root(blake256("evidences"));
+        header.set_transactions_root(blake256("transactions"));
+        header.set_next_validator_set_hash(blake256("validators"));
+        header.set_number(7);
+        header.set_timestamp(8);
+        header.set_extra_data(b"extra".to_vec());
+        header.set_external_anchor(Some(blake256("anchor")));
+        header.set_module_roots_hash(Some(blake256("module roots")));
+
+        let golden = [
+            ("parentHash", rlp::encode(&blake256("parent"))),
+            ("author", rlp::encode(&author)),
+            ("stateRoot", rlp::encode(&blake256("state"))),
+            ("evidencesRoot", rlp::encode(&blake256("evidences"))),
+            ("transactionsRoot", rlp::encode(&blake256("transactions"))),
+            ("nextValidatorSetHash", rlp::encode(&blake256("validators"))),
+            ("number", rlp::encode(&7u64)),
+            ("timestamp", rlp::encode(&8u64)),
+            ("lastCommittedValidators", rlp::encode_list::<Public, Public>(&[])),
+            ("extraData", rlp::encode(&b"extra".to_vec())),
+        ];
+        let bytes = header.rlp(&Seal::Without);
+        assert_golden(&header_schema(), &bytes, &golden);
+        let mut extension = rlp::RlpStream::new_list(2);
+        extension.append(&blake256("anchor").as_bytes().to_vec()).append(&blake256("module roots"));
+        assert_eq!(&extension.out()[..], Rlp::new(&bytes).at(golden.len()).unwrap().as_raw());
+
+        header.set_seal(vec![rlp::encode(&1u64)]);
+        header_schema().check(&header.rlp(&Seal::With)).unwrap();
+    }
+
+    #[test]
+    fn common_params_schema_matches_the_encoding() {
+        let params: Params = serde_json::from_str(
+            r#"{
+            "maxExtraDataSize": 21,
+            "networkID" : "tc",
+            "maxBodySize" : 22,
+            "snapshotPeriod": 23,
+            "termSeconds": 24,
+            "nominationExpiration": 25,
+            "custodyPeriod": 26,
+            "releasePeriod": 27,
+            "maxNumOfValidators": 28,
+            "minNumOfValidators": 29,
+            "delegationThreshold": 30,
+            "minDeposit": 31,
+            "maxCandidateMetadataSize": 32,
+            "era": 33
+        }"#,
+        )
+        .unwrap();
+        let bytes = rlp::encode(&CommonParams::from(params));
+
+        let schema = common_params_schema();
+        let mut golden: Vec<_> = schema
+            .fields
+            .iter()
+            .filter(|field| field.ty == "u64")
+            .zip(21u64..)
+            .map(|(field, value)| (field.name, rlp::encode(&value)))
+            .collect();
+        golden.push(("networkId", rlp::encode(&NetworkId::from("tc"))));
+        assert_golden(&schema, &bytes, &golden);
+    }
+
+    #[test]
+    fn check_rejects_a_field_of_another_type() {
+        let header = Header::default();
+        let mut bytes = header.rlp(&Seal::Without);
+        assert_eq!(Ok(()), header_schema().check(&bytes));
+
+        // The timestamp becomes a hash.
+        let rlp = Rlp::new(&bytes);
+        let mut s = rlp::RlpStream::new_list(rlp.item_count().unwrap());
+        for (index, item) in rlp.iter().enumerate() {
+            if index == 7 {
+                s.append(&H256::zero());
+            } else {
+                s.append_raw(item.as_raw(), 1);
+            }
+        }
+        bytes = s.out();
+        assert!(header_schema().check(&bytes).is_err());
+        assert!(common_params_schema().check(&bytes).is_err());
+    }
+}
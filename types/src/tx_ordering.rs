@@ -0,0 +1,96 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::TxHash;
+use ccrypto::blake256;
+use primitives::{Bytes, H256};
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+
+/// The rule deciding the order of the transactions in a block.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum TxOrdering {
+    /// The proposer orders the transactions.
+    Proposer,
+    /// The transactions are sorted by keys mixing their hashes with the seal of the parent block,
+    /// so the order is fixed by the transactions rather than written by the proposer.
+    ///
+    /// It doesn't prevent front-running. The seed is known before the transactions are chosen,
+    /// so a sender can grind the hash of its transaction until its key comes before another's.
+    /// The proposer of the parent can also move the seed by choosing which precommits go into the seal.
+    Seeded,
+}
+
+impl Default for TxOrdering {
+    fn default() -> Self {
+        TxOrdering::Proposer
+    }
+}
+
+impl TxOrdering {
+    /// The seed of the order of the transactions in the child of the block sealed with `parent_seal`.
+    pub fn seed(parent_seal: &[Bytes]) -> H256 {
+        let mut s = RlpStream::new_list(parent_seal.len());
+        for field in parent_seal {
+            s.append_raw(field, 1);
+        }
+        blake256(s.out())
+    }
+
+    /// The key of a transaction under the seeded ordering. The transaction with the smallest key comes first.
+    pub fn seeded_key(seed: &H256, tx_hash: &TxHash) -> H256 {
+        let mut input = seed.to_vec();
+        input.extend_from_slice(tx_hash.as_ref());
+        blake256(input)
+    }
+}
+
+impl Encodable for TxOrdering {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        let tag: u8 = match self {
+            TxOrdering::Proposer => 0,
+            TxOrdering::Seeded => 1,
+        };
+        s.append(&tag);
+    }
+}
+
+impl Decodable for TxOrdering {
+    fn decode(rlp: &Rlp<'_>) -> Result<Self, DecoderError> {
+        match rlp.as_val::<u8>()? {
+            0 => Ok(TxOrdering::Proposer),
+            1 => Ok(TxOrdering::Seeded),
+            _ => Err(DecoderError::Custom("Unknown transaction ordering")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rlp::rlp_encode_and_decode_test;
+
+    #[test]
+    fn encode_and_decode() {
+        rlp_encode_and_decode_test!(TxOrdering::Proposer);
+        rlp_encode_and_decode_test!(TxOrdering::Seeded);
+    }
+
+    #[test]
+    fn seed_depends_on_the_seal() {
+        assert_ne!(TxOrdering::seed(&[vec![0x80], vec![0x01]]), TxOrdering::seed(&[vec![0x80], vec![0x02]]));
+    }
+}
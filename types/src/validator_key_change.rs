@@ -0,0 +1,87 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccrypto::blake256;
+use ckey::{sign, verify, Ed25519Private as Private, Ed25519Public as Public, NetworkId, Signature};
+use primitives::H256;
+use rlp::RlpStream;
+use serde::{Deserialize, Serialize};
+
+/// Requests that a validator's consensus key be replaced by `next`.
+/// It is signed with the next key, so a validator cannot hand its seat to a key nobody holds.
+/// The network id is signed as well, so the change can't be replayed on another chain.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct ValidatorKeyChange {
+    pub network_id: NetworkId,
+    pub current: Public,
+    pub next: Public,
+    pub signature: Signature,
+}
+
+impl ValidatorKeyChange {
+    pub fn new_signed(network_id: NetworkId, current: Public, next: Public, next_private: &Private) -> Self {
+        let signature = sign(message(&network_id, &current, &next).as_ref(), next_private);
+        Self {
+            network_id,
+            current,
+            next,
+            signature,
+        }
+    }
+
+    pub fn verify(&self) -> bool {
+        verify(&self.signature, message(&self.network_id, &self.current, &self.next).as_ref(), &self.next)
+    }
+}
+
+fn message(network_id: &NetworkId, current: &Public, next: &Public) -> H256 {
+    let mut s = RlpStream::new_list(3);
+    s.append(network_id);
+    s.append(current);
+    s.append(next);
+    blake256(s.out())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckey::{Ed25519KeyPair, Generator, KeyPairTrait, Random};
+
+    #[test]
+    fn key_change_signed_by_the_next_key_is_verified() {
+        let current: Ed25519KeyPair = Random.generate().unwrap();
+        let next: Ed25519KeyPair = Random.generate().unwrap();
+        let change = ValidatorKeyChange::new_signed("tc".into(), *current.public(), *next.public(), next.private());
+        assert!(change.verify());
+    }
+
+    #[test]
+    fn key_change_signed_by_the_current_key_is_not_verified() {
+        let current: Ed25519KeyPair = Random.generate().unwrap();
+        let next: Ed25519KeyPair = Random.generate().unwrap();
+        let change = ValidatorKeyChange::new_signed("tc".into(), *current.public(), *next.public(), current.private());
+        assert!(!change.verify());
+    }
+
+    #[test]
+    fn key_change_for_another_network_is_not_verified() {
+        let current: Ed25519KeyPair = Random.generate().unwrap();
+        let next: Ed25519KeyPair = Random.generate().unwrap();
+        let mut change = ValidatorKeyChange::new_signed("tc".into(), *current.public(), *next.public(), next.private());
+        change.network_id = "cc".into();
+        assert!(!change.verify());
+    }
+}